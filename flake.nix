@@ -37,6 +37,7 @@
             JN_PLUGIN="-Mjn-plugin=libs/zig/jn-plugin/src/root.zig"
             JN_ADDRESS="-Mjn-address=libs/zig/jn-address/src/root.zig"
             JN_PROFILE="-Mjn-profile=libs/zig/jn-profile/src/root.zig"
+            JN_HTTP="--dep jn-core -Mjn-http=libs/zig/jn-http/src/root.zig"
 
             mkdir -p dist/bin
 
@@ -54,12 +55,13 @@
               echo "Building $tool..."
               pushd tools/zig/$tool
               zig build-exe -O ReleaseFast \
-                --dep jn-core --dep jn-cli --dep jn-address --dep jn-profile \
+                --dep jn-core --dep jn-cli --dep jn-address --dep jn-profile --dep jn-http \
                 -Mroot=./main.zig \
                 -Mjn-core=../../../libs/zig/jn-core/src/root.zig \
                 -Mjn-cli=../../../libs/zig/jn-cli/src/root.zig \
                 -Mjn-address=../../../libs/zig/jn-address/src/root.zig \
                 -Mjn-profile=../../../libs/zig/jn-profile/src/root.zig \
+                --dep jn-core -Mjn-http=../../../libs/zig/jn-http/src/root.zig \
                 -femit-bin=../../../dist/bin/$tool
               popd
             done
@@ -67,11 +69,14 @@
             # Build ZQ
             echo "Building zq..."
             pushd zq
-            zig build-exe src/main.zig -O ReleaseFast -femit-bin=../dist/bin/zq
+            zig build-exe -O ReleaseFast \
+              --dep jn-core -Mroot=src/main.zig \
+              -Mjn-core=../libs/zig/jn-core/src/root.zig \
+              -femit-bin=../dist/bin/zq
             popd
 
             # Build plugins
-            for plugin in csv json jsonl gz yaml toml; do
+            for plugin in csv json jsonl gz bz2 xz zst yaml toml; do
               echo "Building $plugin..."
               pushd plugins/zig/$plugin
               zig build-exe -O ReleaseFast \
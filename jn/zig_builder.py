@@ -90,12 +90,20 @@ def build_zq() -> str:
     if not source_file.exists():
         raise FileNotFoundError(source_file)
 
+    jn_core = repo_root / "libs" / "zig" / "jn-core" / "src"
+    module_flags = [
+        "--dep",
+        "jn-core",
+        "-Mroot=main.zig",
+        f"-Mjn-core={jn_core / 'root.zig'}",
+    ]
+
     output_path = _build_zig_binary(
         output_stem="zq",
         cwd=source_file.parent,
-        source_file=source_file,
-        extra_args=[],
-        hash_inputs=[source_file],
+        source_file=None,
+        extra_args=module_flags,
+        hash_inputs=[*_zig_files_under(source_file.parent), *_zig_files_under(jn_core)],
     )
     return str(output_path)
 
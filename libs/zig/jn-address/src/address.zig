@@ -77,6 +77,13 @@ pub const Compression = enum {
             .zstd => ".zst",
         };
     }
+
+    /// Name of the plugin that reads and writes this compression
+    /// (the extension without its dot)
+    pub fn plugin(self: Compression) ?[]const u8 {
+        const ext = self.extension() orelse return null;
+        return ext[1..];
+    }
 };
 
 /// Parsed address structure
@@ -511,6 +518,8 @@ test "compression types" {
     try std.testing.expectEqual(Compression.xz, Compression.fromExtension(".xz"));
     try std.testing.expectEqual(Compression.zstd, Compression.fromExtension(".zst"));
     try std.testing.expectEqual(Compression.none, Compression.fromExtension(".txt"));
+    try std.testing.expectEqualStrings("zst", Compression.zstd.plugin().?);
+    try std.testing.expect(Compression.none.plugin() == null);
 }
 
 test "effective format prefers override" {
@@ -0,0 +1,650 @@
+//! Dry-run reports for `--explain`.
+//!
+//! jn-cat and jn-put assemble their work as process pipelines such as
+//! `curl … | gz --mode=raw | csv --mode=read`. With `--explain` (or
+//! `JN_EXPLAIN=1`) they record what they resolved along the way and print it
+//! instead of running anything:
+//!
+//! - the parsed address
+//! - the profile file and its merged config
+//! - the plugins discovery chose, with where they were found
+//! - every HTTP request the tool sends itself: method, URL, headers, body
+//! - every pipeline: each stage's argv, environment and stdin/stdout
+//!
+//! `--explain=json` (or `JN_EXPLAIN=json`) prints the report as one JSON object.
+//!
+//! Secrets never reach the report. Config values under names like `token`,
+//! `password` or `Authorization` are replaced with `[REDACTED]`, and so is
+//! every later appearance of those values, e.g. in a request header.
+
+const std = @import("std");
+const errors = @import("errors.zig");
+const pipeline = @import("pipeline.zig");
+
+pub const REDACTED = "[REDACTED]";
+
+pub const Format = enum { text, json };
+
+/// The report format asked for by `--explain[=text|json]` or `JN_EXPLAIN`,
+/// null when the tool should run normally. `args` is a jn-cli ArgParser.
+pub fn requested(args: anytype, tool: []const u8) ?Format {
+    if (args.get("explain", null)) |value| {
+        return parseFormat(value) orelse {
+            std.debug.print("{s}: --explain expects text or json, got '{s}'\n", .{ tool, value });
+            errors.ExitCode.usage_error.exit();
+        };
+    }
+    const env = std.posix.getenv("JN_EXPLAIN") orelse return null;
+    if (env.len == 0 or std.mem.eql(u8, env, "0")) return null;
+    // JN_EXPLAIN=1 (or any other value) means text
+    return parseFormat(env) orelse .text;
+}
+
+fn parseFormat(value: []const u8) ?Format {
+    if (value.len == 0 or std.mem.eql(u8, value, "text")) return .text;
+    if (std.mem.eql(u8, value, "json")) return .json;
+    return null;
+}
+
+/// A plugin chosen by discovery.
+pub const Plugin = struct {
+    name: []const u8,
+    /// "zig" or "python"
+    kind: []const u8,
+    path: []const u8,
+    /// Which discovery location matched, e.g. "$JN_HOME/bin"
+    source: []const u8,
+    /// Position of that location in the search order (1 is searched first)
+    priority: u32,
+};
+
+/// A process the tool would spawn, as one stage of a pipeline.
+pub const Process = struct {
+    argv: []const []const u8,
+    /// Variables set on top of the tool's environment, as NAME=value
+    env: []const []const u8,
+    /// "inherit", "null", "pipe" or "file:PATH"
+    stdin: []const u8,
+    /// "inherit", "pipe", "capture" or "file:PATH"
+    stdout: []const u8,
+};
+
+/// An HTTP request the tool sends itself.
+pub const Request = struct {
+    method: []const u8,
+    url: []const u8,
+    /// As "Name: value"
+    headers: []const []const u8,
+    body: ?[]const u8,
+};
+
+/// What a tool resolved, collected until `finish` prints it. Everything is
+/// copied into the report's arena, so callers may free their values.
+pub const Report = struct {
+    arena_state: std.heap.ArenaAllocator,
+    tool: []const u8,
+    format: Format,
+    address: ?std.json.Value = null,
+    profile_path: ?[]const u8 = null,
+    profile_config: ?std.json.Value = null,
+    plugins: std.ArrayListUnmanaged(Plugin) = .empty,
+    requests: std.ArrayListUnmanaged(Request) = .empty,
+    pipelines: std.ArrayListUnmanaged([]const Process) = .empty,
+    notes: std.ArrayListUnmanaged([]const u8) = .empty,
+    /// Values to scrub from everything printed
+    secrets: std.ArrayListUnmanaged([]const u8) = .empty,
+
+    pub fn init(allocator: std.mem.Allocator, tool: []const u8, format: Format) Report {
+        return .{ .arena_state = .init(allocator), .tool = tool, .format = format };
+    }
+
+    pub fn deinit(self: *Report) void {
+        self.arena_state.deinit();
+    }
+
+    fn arena(self: *Report) std.mem.Allocator {
+        return self.arena_state.allocator();
+    }
+
+    fn dupe(self: *Report, text: []const u8) []const u8 {
+        return self.arena().dupe(u8, text) catch oom(self.tool);
+    }
+
+    /// Record a parsed address: a struct of string, optional string and enum
+    /// fields such as `jn_address.Address`.
+    pub fn setAddress(self: *Report, address: anytype) void {
+        var fields = std.json.ObjectMap.init(self.arena());
+        inline for (std.meta.fields(@TypeOf(address))) |field| {
+            const value = @field(address, field.name);
+            const json_value: std.json.Value = switch (@typeInfo(field.type)) {
+                .@"enum" => .{ .string = @tagName(value) },
+                .optional => if (value) |v| .{ .string = self.dupe(v) } else .null,
+                else => .{ .string = self.dupe(value) },
+            };
+            fields.put(field.name, json_value) catch oom(self.tool);
+        }
+        if (@hasField(@TypeOf(address), "query_string")) {
+            if (address.query_string) |query| self.addQuerySecrets(query);
+        }
+        self.address = .{ .object = fields };
+    }
+
+    /// Record the profile file an address resolved to.
+    pub fn setProfile(self: *Report, path: []const u8) void {
+        self.profile_path = self.dupe(path);
+    }
+
+    /// Record the profile's merged config, with secret values redacted.
+    pub fn setConfig(self: *Report, config: std.json.Value) void {
+        self.profile_config = redact(self.arena(), config, false, &self.secrets) catch oom(self.tool);
+    }
+
+    /// Record a plugin chosen by discovery (once per name and path).
+    pub fn addPlugin(self: *Report, plugin: Plugin) void {
+        for (self.plugins.items) |existing| {
+            if (std.mem.eql(u8, existing.name, plugin.name) and std.mem.eql(u8, existing.path, plugin.path)) return;
+        }
+        self.plugins.append(self.arena(), .{
+            .name = self.dupe(plugin.name),
+            .kind = self.dupe(plugin.kind),
+            .path = self.dupe(plugin.path),
+            .source = self.dupe(plugin.source),
+            .priority = plugin.priority,
+        }) catch oom(self.tool);
+    }
+
+    /// Record a pipeline the tool would run.
+    pub fn addPipeline(self: *Report, p: *const pipeline.Pipeline) void {
+        const stages = p.stages.items;
+        const processes = self.arena().alloc(Process, stages.len) catch oom(self.tool);
+        for (stages, 0..) |stage, i| {
+            const argv = self.arena().alloc([]const u8, stage.argv.items.len) catch oom(self.tool);
+            for (stage.argv.items, 0..) |arg, j| argv[j] = self.dupe(arg);
+            const env = self.arena().alloc([]const u8, stage.env.items.len) catch oom(self.tool);
+            for (stage.env.items, 0..) |variable, j| env[j] = self.print("{s}={s}", .{ variable.name, variable.value });
+
+            processes[i] = .{
+                .argv = argv,
+                .env = env,
+                .stdin = if (i > 0) "pipe" else switch (p.stdin) {
+                    .inherit => "inherit",
+                    .null_device => "null",
+                    .file => |path| self.print("file:{s}", .{path}),
+                    .pipe => "pipe",
+                },
+                .stdout = if (i + 1 < stages.len) "pipe" else switch (p.stdout) {
+                    .inherit => "inherit",
+                    .capture => "capture",
+                    .pipe => "pipe",
+                    .file => |path| self.print("file:{s}", .{path}),
+                },
+            };
+        }
+        self.pipelines.append(self.arena(), processes) catch oom(self.tool);
+    }
+
+    /// Record an HTTP request. `headers` is a slice of structs with `name`
+    /// and `value` fields, such as `std.http.Header`; credentials among them
+    /// are redacted.
+    pub fn addRequest(self: *Report, method: []const u8, url: []const u8, headers: anytype, body: ?[]const u8) void {
+        const lines = self.arena().alloc([]const u8, headers.len) catch oom(self.tool);
+        for (headers, 0..) |header, i| {
+            lines[i] = self.print("{s}: {s}", .{ header.name, header.value });
+            self.addHeaderSecret(lines[i]);
+        }
+        if (std.mem.indexOfScalar(u8, url, '?')) |q| self.addQuerySecrets(url[q + 1 ..]);
+        self.requests.append(self.arena(), .{
+            .method = self.dupe(method),
+            .url = self.dupe(url),
+            .headers = lines,
+            .body = if (body) |text| self.dupe(text) else null,
+        }) catch oom(self.tool);
+    }
+
+    fn print(self: *Report, comptime fmt: []const u8, args: anytype) []const u8 {
+        return std.fmt.allocPrint(self.arena(), fmt, args) catch oom(self.tool);
+    }
+
+    pub fn addNote(self: *Report, note: []const u8) void {
+        self.notes.append(self.arena(), self.dupe(note)) catch oom(self.tool);
+    }
+
+    /// addNote with a formatted note.
+    pub fn addNoteFmt(self: *Report, comptime fmt: []const u8, args: anytype) void {
+        self.notes.append(self.arena(), self.print(fmt, args)) catch oom(self.tool);
+    }
+
+    /// Treat `value` as a secret wherever it appears in the report.
+    pub fn addSecret(self: *Report, value: []const u8) void {
+        if (value.len == 0) return;
+        self.secrets.append(self.arena(), self.dupe(value)) catch oom(self.tool);
+    }
+
+    /// Treat the value of an HTTP header given as "Name: value" as a secret
+    /// when the header carries credentials.
+    pub fn addHeaderSecret(self: *Report, header: []const u8) void {
+        const colon = std.mem.indexOfScalar(u8, header, ':') orelse return;
+        const value = std.mem.trim(u8, header[colon + 1 ..], " ");
+        if (isSecretName(header[0..colon]) or isSecretValue(value)) self.addSecret(value);
+    }
+
+    /// Treat the values of secret-named query parameters as secrets.
+    pub fn addQuerySecrets(self: *Report, query: []const u8) void {
+        var params = std.mem.splitScalar(u8, query, '&');
+        while (params.next()) |param| {
+            const eq = std.mem.indexOfScalar(u8, param, '=') orelse continue;
+            if (isSecretName(param[0..eq])) self.addSecret(param[eq + 1 ..]);
+        }
+    }
+
+    /// `text` with every known secret replaced.
+    fn scrub(self: *Report, text: []const u8) []const u8 {
+        var result = text;
+        for (self.secrets.items) |secret| {
+            if (std.mem.indexOf(u8, result, secret) == null) continue;
+            result = std.mem.replaceOwned(u8, self.arena(), result, secret, REDACTED) catch oom(self.tool);
+        }
+        return result;
+    }
+
+    fn scrubValue(self: *Report, value: std.json.Value) std.json.Value {
+        switch (value) {
+            .object => |object| {
+                var copy = std.json.ObjectMap.init(self.arena());
+                var it = object.iterator();
+                while (it.next()) |entry| {
+                    copy.put(entry.key_ptr.*, self.scrubValue(entry.value_ptr.*)) catch oom(self.tool);
+                }
+                return .{ .object = copy };
+            },
+            .array => |array| {
+                var copy = std.json.Array.init(self.arena());
+                for (array.items) |item| copy.append(self.scrubValue(item)) catch oom(self.tool);
+                return .{ .array = copy };
+            },
+            .string => |text| return .{ .string = self.scrub(text) },
+            else => return value,
+        }
+    }
+
+    fn scrubAll(self: *Report, items: []const []const u8) []const []const u8 {
+        const copy = self.arena().alloc([]const u8, items.len) catch oom(self.tool);
+        for (items, 0..) |item, i| copy[i] = self.scrub(item);
+        return copy;
+    }
+
+    /// Print the report to stdout and exit 0.
+    pub fn finish(self: *Report) noreturn {
+        var buf: [4096]u8 = undefined;
+        var stdout_wrapper = std.fs.File.stdout().writerStreaming(&buf);
+        const stdout = &stdout_wrapper.interface;
+        self.write(stdout) catch {};
+        stdout.flush() catch {};
+        std.process.exit(0);
+    }
+
+    /// Write the report in its format.
+    pub fn write(self: *Report, writer: *std.Io.Writer) !void {
+        const address = if (self.address) |value| self.scrubValue(value) else null;
+        const config = if (self.profile_config) |value| self.scrubValue(value) else null;
+        const pipelines = try self.arena().alloc([]const Process, self.pipelines.items.len);
+        for (self.pipelines.items, 0..) |processes, i| {
+            const copy = try self.arena().alloc(Process, processes.len);
+            for (processes, 0..) |process, j| {
+                copy[j] = .{
+                    .argv = self.scrubAll(process.argv),
+                    .env = self.scrubAll(process.env),
+                    .stdin = self.scrub(process.stdin),
+                    .stdout = self.scrub(process.stdout),
+                };
+            }
+            pipelines[i] = copy;
+        }
+        const requests = try self.arena().alloc(Request, self.requests.items.len);
+        for (self.requests.items, 0..) |request, i| {
+            requests[i] = .{
+                .method = request.method,
+                .url = self.scrub(request.url),
+                .headers = self.scrubAll(request.headers),
+                .body = if (request.body) |body| self.scrub(body) else null,
+            };
+        }
+
+        switch (self.format) {
+            .json => {
+                const Profile = struct { path: []const u8, config: ?std.json.Value };
+                try std.json.Stringify.value(.{
+                    .tool = self.tool,
+                    .address = address,
+                    .profile = if (self.profile_path) |path| Profile{ .path = self.scrub(path), .config = config } else null,
+                    .plugins = self.plugins.items,
+                    .requests = requests,
+                    .pipelines = pipelines,
+                    .notes = self.notes.items,
+                }, .{}, writer);
+                try writer.writeByte('\n');
+            },
+            .text => try self.writeText(writer, address, config, requests, pipelines),
+        }
+    }
+
+    fn writeText(self: *Report, w: *std.Io.Writer, address: ?std.json.Value, config: ?std.json.Value, requests: []const Request, pipelines: []const []const Process) !void {
+        if (address) |value| {
+            const raw = value.object.get("raw");
+            try w.print("Address:    {s}\n", .{if (raw) |r| r.string else ""});
+            var it = value.object.iterator();
+            while (it.next()) |entry| {
+                if (std.mem.eql(u8, entry.key_ptr.*, "raw")) continue;
+                if (entry.value_ptr.* != .string) continue;
+                try w.print("  {s}: {s}\n", .{ entry.key_ptr.*, entry.value_ptr.string });
+            }
+        }
+
+        if (self.profile_path) |path| {
+            try w.print("Profile:    {s}\n", .{self.scrub(path)});
+            if (config) |value| {
+                var pretty: std.Io.Writer.Allocating = .init(self.arena());
+                try std.json.Stringify.value(value, .{ .whitespace = .indent_2 }, &pretty.writer);
+                var lines = std.mem.splitScalar(u8, pretty.written(), '\n');
+                while (lines.next()) |line| try w.print("  {s}\n", .{line});
+            }
+        }
+
+        if (self.plugins.items.len > 0) {
+            try w.writeAll("Plugins:\n");
+            for (self.plugins.items) |plugin| {
+                try w.print("  {s} ({s}): {s}\n", .{ plugin.name, plugin.kind, plugin.path });
+                try w.print("    found in {s}, priority {d}\n", .{ plugin.source, plugin.priority });
+            }
+        }
+
+        if (requests.len > 0) {
+            try w.writeAll("Requests:\n");
+            for (requests) |request| {
+                try w.print("  {s} {s}\n", .{ request.method, request.url });
+                for (request.headers) |header| try w.print("    {s}\n", .{header});
+                if (request.body) |body| try w.print("    Body: {s}\n", .{body});
+            }
+        }
+
+        // Each pipeline in shell notation, one stage per line
+        try w.writeAll("Pipelines:\n");
+        if (pipelines.len == 0) try w.writeAll("  (none)\n");
+        for (pipelines) |processes| {
+            for (processes, 0..) |process, i| {
+                try w.writeAll(if (i == 0) "  " else "    | ");
+                // Fed by the tool itself, e.g. with a response body
+                if (i == 0 and std.mem.eql(u8, process.stdin, "pipe")) try w.print("{s} | ", .{self.tool});
+                for (process.env) |variable| {
+                    try writeShellWord(w, variable);
+                    try w.writeByte(' ');
+                }
+                for (process.argv, 0..) |arg, j| {
+                    if (j > 0) try w.writeByte(' ');
+                    try writeShellWord(w, arg);
+                }
+                if (std.mem.eql(u8, process.stdin, "null")) {
+                    try w.writeAll(" < /dev/null");
+                } else if (std.mem.startsWith(u8, process.stdin, "file:")) {
+                    try w.writeAll(" < ");
+                    try writeShellWord(w, process.stdin["file:".len..]);
+                }
+                if (std.mem.startsWith(u8, process.stdout, "file:")) {
+                    try w.writeAll(" > ");
+                    try writeShellWord(w, process.stdout["file:".len..]);
+                } else if (i + 1 == processes.len and std.mem.eql(u8, process.stdout, "pipe")) {
+                    try w.print(" | {s}", .{self.tool});
+                }
+                try w.writeByte('\n');
+            }
+        }
+
+        for (self.notes.items) |note| try w.print("Note:       {s}\n", .{self.scrub(note)});
+    }
+};
+
+fn oom(tool: []const u8) noreturn {
+    errors.exitWithError("{s}: out of memory building the --explain report", .{tool});
+}
+
+/// Shortest redacted value that is also scrubbed from the rest of the report.
+/// Shorter ones (`"auth": "basic"`, a 4-digit PIN) would replace unrelated
+/// text; they are still redacted where they sit in the config.
+const min_scrub_len = 6;
+
+/// Deep copy of `value`, allocated with `allocator` (an arena, typically),
+/// with the values of secret-named keys and credential-looking strings
+/// replaced by `[REDACTED]`. `secret` marks a value that sits under a
+/// secret name. The redacted values, as text, and secret query parameters in
+/// URLs are appended to `secrets` when it is given, if at least
+/// `min_scrub_len` bytes long.
+pub fn redact(allocator: std.mem.Allocator, value: std.json.Value, secret: bool, secrets: ?*std.ArrayListUnmanaged([]const u8)) error{OutOfMemory}!std.json.Value {
+    switch (value) {
+        .object => |object| {
+            var copy = std.json.ObjectMap.init(allocator);
+            var it = object.iterator();
+            while (it.next()) |entry| {
+                const redacted = try redact(allocator, entry.value_ptr.*, isSecretName(entry.key_ptr.*), secrets);
+                try copy.put(try allocator.dupe(u8, entry.key_ptr.*), redacted);
+            }
+            return .{ .object = copy };
+        },
+        .array => |array| {
+            var copy = std.json.Array.init(allocator);
+            for (array.items) |item| try copy.append(try redact(allocator, item, secret, secrets));
+            return .{ .array = copy };
+        },
+        .string, .number_string => |text| {
+            const copy = try allocator.dupe(u8, text);
+            if (secret or (value == .string and isSecretValue(text))) {
+                try addScrubbed(allocator, secrets, copy);
+                return .{ .string = REDACTED };
+            }
+            if (std.mem.indexOfScalar(u8, copy, '?')) |q| {
+                var params = std.mem.splitScalar(u8, copy[q + 1 ..], '&');
+                while (params.next()) |param| {
+                    const eq = std.mem.indexOfScalar(u8, param, '=') orelse continue;
+                    if (isSecretName(param[0..eq])) try addScrubbed(allocator, secrets, param[eq + 1 ..]);
+                }
+            }
+            return if (value == .string) .{ .string = copy } else .{ .number_string = copy };
+        },
+        .integer, .float => {
+            if (!secret) return value;
+            // A numeric key shows up in URLs and arguments as its digits
+            if (secrets != null) {
+                const text = switch (value) {
+                    .integer => |i| try std.fmt.allocPrint(allocator, "{d}", .{i}),
+                    else => try std.fmt.allocPrint(allocator, "{d}", .{value.float}),
+                };
+                try addScrubbed(allocator, secrets, text);
+            }
+            return .{ .string = REDACTED };
+        },
+        .bool, .null => return value,
+    }
+}
+
+fn addScrubbed(allocator: std.mem.Allocator, secrets: ?*std.ArrayListUnmanaged([]const u8), text: []const u8) error{OutOfMemory}!void {
+    const list = secrets orelse return;
+    if (text.len >= min_scrub_len) try list.append(allocator, text);
+}
+
+/// Whether a config key, header or query parameter name carries a credential.
+/// Names of settings about credentials, such as `token_url` and
+/// `client_auth`, don't.
+pub fn isSecretName(name: []const u8) bool {
+    var buf: [64]u8 = undefined;
+    var len: usize = 0;
+    for (name) |c| {
+        // Compare without case or separators: api_key, Api-Key and APIKEY match
+        if (c == '_' or c == '-') continue;
+        if (len == buf.len) break;
+        buf[len] = std.ascii.toLower(c);
+        len += 1;
+    }
+    const normalized = buf[0..len];
+    if (std.mem.endsWith(u8, normalized, "url") or std.mem.endsWith(u8, normalized, "uri")) return false;
+    if (std.mem.eql(u8, normalized, "clientauth")) return false;
+    const markers = [_][]const u8{ "token", "secret", "passw", "auth", "cookie", "apikey", "accesskey", "privatekey", "credential", "session", "signature" };
+    for (markers) |marker| {
+        if (std.mem.indexOf(u8, normalized, marker) != null) return true;
+    }
+    return false;
+}
+
+/// Whether a value looks like a credential whatever its name, such as an
+/// Authorization header value stored under some other key.
+pub fn isSecretValue(value: []const u8) bool {
+    const schemes = [_][]const u8{ "Bearer ", "Basic ", "Token " };
+    for (schemes) |scheme| {
+        if (std.ascii.startsWithIgnoreCase(value, scheme)) return true;
+    }
+    return false;
+}
+
+/// Write `word` as one shell word, single-quoted when it needs to be.
+fn writeShellWord(w: *std.Io.Writer, word: []const u8) !void {
+    if (isPlainWord(word)) return w.writeAll(word);
+
+    try w.writeByte('\'');
+    for (word) |c| {
+        if (c == '\'') {
+            try w.writeAll("'\\''");
+        } else {
+            try w.writeByte(c);
+        }
+    }
+    try w.writeByte('\'');
+}
+
+fn isPlainWord(word: []const u8) bool {
+    if (word.len == 0) return false;
+    for (word) |c| {
+        if (!std.ascii.isAlphanumeric(c) and std.mem.indexOfScalar(u8, "-_./=:,+@%", c) == null) return false;
+    }
+    return true;
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+test "isSecretName matches credential names" {
+    try std.testing.expect(isSecretName("Authorization"));
+    try std.testing.expect(isSecretName("api_key"));
+    try std.testing.expect(isSecretName("X-API-Key"));
+    try std.testing.expect(isSecretName("client_secret"));
+    try std.testing.expect(isSecretName("password"));
+    try std.testing.expect(isSecretName("refresh_token"));
+    try std.testing.expect(!isSecretName("base_url"));
+    try std.testing.expect(!isSecretName("token_url"));
+    try std.testing.expect(!isSecretName("client_auth"));
+    try std.testing.expect(!isSecretName("path"));
+    try std.testing.expect(!isSecretName("Accept"));
+}
+
+test "redact hides credentials but not the settings around them" {
+    var arena_state = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena_state.deinit();
+    const arena = arena_state.allocator();
+    const config = try std.json.parseFromSliceLeaky(std.json.Value, arena,
+        \\{"auth":{"type":"oauth2","token_url":"https://auth.test/token","client_auth":"basic","client_secret":"s3cr3t"},"path":"/x?api_key=k3y123"}
+    , .{});
+
+    var secrets: std.ArrayListUnmanaged([]const u8) = .empty;
+    const redacted = try redact(arena, config, false, &secrets);
+    const json = try std.json.Stringify.valueAlloc(arena, redacted, .{});
+    try std.testing.expectEqualStrings(
+        \\{"auth":{"type":"oauth2","token_url":"https://auth.test/token","client_auth":"basic","client_secret":"[REDACTED]"},"path":"/x?api_key=k3y123"}
+    , json);
+    try std.testing.expectEqual(@as(usize, 2), secrets.items.len);
+    try std.testing.expectEqualStrings("s3cr3t", secrets.items[0]);
+    try std.testing.expectEqualStrings("k3y123", secrets.items[1]);
+}
+
+test "redact scrubs numeric secrets but not short ones" {
+    var arena_state = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena_state.deinit();
+    const arena = arena_state.allocator();
+    const config = try std.json.parseFromSliceLeaky(std.json.Value, arena,
+        \\{"api_key":12345678,"auth":"basic","password":"pw","path":"/x?token=t0k"}
+    , .{});
+
+    var secrets: std.ArrayListUnmanaged([]const u8) = .empty;
+    const redacted = try redact(arena, config, false, &secrets);
+    const json = try std.json.Stringify.valueAlloc(arena, redacted, .{});
+    try std.testing.expectEqualStrings(
+        \\{"api_key":"[REDACTED]","auth":"[REDACTED]","password":"[REDACTED]","path":"/x?token=t0k"}
+    , json);
+    // "basic", "pw" and "t0k" would match too much unrelated text
+    try std.testing.expectEqual(@as(usize, 1), secrets.items.len);
+    try std.testing.expectEqualStrings("12345678", secrets.items[0]);
+}
+
+test "report redacts profile secrets everywhere" {
+    const parsed = try std.json.parseFromSlice(std.json.Value, std.testing.allocator,
+        \\{"base_url":"https://api.example.com","headers":{"Authorization":"Bearer abc123","Accept":"application/json"}}
+    , .{});
+    defer parsed.deinit();
+
+    var report = Report.init(std.testing.allocator, "jn-cat", .json);
+    defer report.deinit();
+    report.setProfile("/p/http/api/users.json");
+    report.setConfig(parsed.value);
+    var p = pipeline.Pipeline.init(std.testing.allocator);
+    defer p.deinit();
+    p.stdin = .null_device;
+    _ = try p.add(&.{ "curl", "-sS", "-H", "Authorization: Bearer abc123", "https://api.example.com" });
+    _ = try p.add(&.{ "/bin/json", "--mode=read" });
+    report.addPipeline(&p);
+    report.addPlugin(.{ .name = "json", .kind = "zig", .path = "/bin/json", .source = "$JN_HOME/bin", .priority = 1 });
+
+    var out: std.Io.Writer.Allocating = .init(std.testing.allocator);
+    defer out.deinit();
+    try report.write(&out.writer);
+    const text = out.written();
+    try std.testing.expect(std.mem.indexOf(u8, text, "abc123") == null);
+    try std.testing.expect(std.mem.indexOf(u8, text, "\"Authorization\":\"[REDACTED]\"") != null);
+    try std.testing.expect(std.mem.indexOf(u8, text, "Authorization: [REDACTED]") != null);
+    try std.testing.expect(std.mem.indexOf(u8, text, "\"Accept\":\"application/json\"") != null);
+    try std.testing.expect(std.mem.indexOf(u8, text, "\"stdin\":\"null\",\"stdout\":\"pipe\"") != null);
+}
+
+test "report redacts request credentials" {
+    const Header = struct { name: []const u8, value: []const u8 };
+    var report = Report.init(std.testing.allocator, "jn-cat", .text);
+    defer report.deinit();
+    report.addRequest("GET", "https://api.example.com/users?token=t0k", &[_]Header{
+        .{ .name = "Authorization", .value = "Bearer abc123" },
+        .{ .name = "Accept", .value = "application/json" },
+    }, null);
+    var p = pipeline.Pipeline.init(std.testing.allocator);
+    defer p.deinit();
+    p.stdin = .pipe;
+    _ = try p.add(&.{ "/bin/csv", "--mode=read" });
+    report.addPipeline(&p);
+
+    var out: std.Io.Writer.Allocating = .init(std.testing.allocator);
+    defer out.deinit();
+    try report.write(&out.writer);
+    const text = out.written();
+    try std.testing.expect(std.mem.indexOf(u8, text, "abc123") == null);
+    try std.testing.expect(std.mem.indexOf(u8, text, "t0k") == null);
+    try std.testing.expect(std.mem.indexOf(u8, text, "  GET https://api.example.com/users?token=[REDACTED]\n") != null);
+    try std.testing.expect(std.mem.indexOf(u8, text, "    Authorization: [REDACTED]\n") != null);
+    try std.testing.expect(std.mem.indexOf(u8, text, "  jn-cat | /bin/csv --mode=read\n") != null);
+}
+
+test "report scrubs secret query parameters from the address" {
+    const Address = struct { raw: []const u8, query_string: ?[]const u8, kind: enum { url } };
+    var report = Report.init(std.testing.allocator, "jn-cat", .text);
+    defer report.deinit();
+    report.setAddress(Address{ .raw = "https://x/data?api_key=s3cr3t&page=2", .query_string = "api_key=s3cr3t&page=2", .kind = .url });
+
+    var out: std.Io.Writer.Allocating = .init(std.testing.allocator);
+    defer out.deinit();
+    try report.write(&out.writer);
+    try std.testing.expect(std.mem.indexOf(u8, out.written(), "s3cr3t") == null);
+    try std.testing.expect(std.mem.indexOf(u8, out.written(), "page=2") != null);
+}
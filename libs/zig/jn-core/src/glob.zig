@@ -0,0 +1,256 @@
+//! Glob expansion for file addresses, without a shell.
+//!
+//! Patterns use `*`, `?` and `[...]` (`[!...]` negates) within one path
+//! component, as in sh. As in sh, a wildcard doesn't match a leading dot
+//! unless the component starts with one.
+//!
+//! `**` searches recursively: `logs/**/*.jsonl` lists every file under `logs`
+//! whose name matches `*.jsonl`, like `find logs -type f -name '*.jsonl'`.
+//!
+//! Only files are listed, sorted by path.
+
+const std = @import("std");
+
+/// Whether `text` contains glob wildcards.
+pub fn hasWildcard(text: []const u8) bool {
+    return std.mem.indexOfAny(u8, text, "*?[") != null;
+}
+
+/// Whether `name` matches the one-component `pattern`.
+pub fn match(pattern: []const u8, name: []const u8) bool {
+    var p: usize = 0;
+    var n: usize = 0;
+    // Where to resume after the last `*` when a later part fails to match
+    var star: ?usize = null;
+    var star_n: usize = 0;
+
+    while (n < name.len) {
+        if (p < pattern.len) {
+            switch (pattern[p]) {
+                '*' => {
+                    star = p;
+                    star_n = n;
+                    p += 1;
+                    continue;
+                },
+                '?' => {
+                    p += 1;
+                    n += 1;
+                    continue;
+                },
+                '[' => {
+                    if (bracket(pattern[p..], name[n])) |class| {
+                        if (class.matched) {
+                            p += class.len;
+                            n += 1;
+                            continue;
+                        }
+                    } else if (name[n] == '[') {
+                        p += 1;
+                        n += 1;
+                        continue;
+                    }
+                },
+                '\\' => {
+                    if (p + 1 < pattern.len and pattern[p + 1] == name[n]) {
+                        p += 2;
+                        n += 1;
+                        continue;
+                    }
+                },
+                else => |c| {
+                    if (c == name[n]) {
+                        p += 1;
+                        n += 1;
+                        continue;
+                    }
+                },
+            }
+        }
+        const resume_at = star orelse return false;
+        p = resume_at + 1;
+        star_n += 1;
+        n = star_n;
+    }
+    while (p < pattern.len and pattern[p] == '*') p += 1;
+    return p == pattern.len;
+}
+
+const Bracket = struct { len: usize, matched: bool };
+
+/// The `[...]` class at the start of `pattern`: its length and whether `c`
+/// is in it. Null when the bracket is never closed (it is then a literal).
+fn bracket(pattern: []const u8, c: u8) ?Bracket {
+    var i: usize = 1;
+    const negated = i < pattern.len and (pattern[i] == '!' or pattern[i] == '^');
+    if (negated) i += 1;
+    var matched = false;
+    const first = i;
+    while (i < pattern.len) {
+        // A `]` first in the class is a member, not the end
+        if (pattern[i] == ']' and i > first) return .{ .len = i + 1, .matched = matched != negated };
+        if (i + 2 < pattern.len and pattern[i + 1] == '-' and pattern[i + 2] != ']') {
+            if (pattern[i] <= c and c <= pattern[i + 2]) matched = true;
+            i += 3;
+        } else {
+            if (pattern[i] == c) matched = true;
+            i += 1;
+        }
+    }
+    return null;
+}
+
+/// Files matching `pattern`, sorted. Free the result with `freeMatches`.
+/// Directories that can't be read contribute no matches.
+pub fn expand(allocator: std.mem.Allocator, pattern: []const u8) ![][]const u8 {
+    var matches: std.ArrayListUnmanaged([]const u8) = .empty;
+    errdefer {
+        for (matches.items) |path| allocator.free(path);
+        matches.deinit(allocator);
+    }
+
+    if (std.mem.indexOf(u8, pattern, "**") != null) {
+        try expandRecursive(allocator, &matches, pattern);
+    } else {
+        var components: std.ArrayListUnmanaged([]const u8) = .empty;
+        defer components.deinit(allocator);
+        var it = std.mem.tokenizeScalar(u8, pattern, '/');
+        while (it.next()) |component| try components.append(allocator, component);
+
+        const root = if (std.mem.startsWith(u8, pattern, "/")) "/" else "";
+        if (components.items.len > 0) try expandFrom(allocator, &matches, root, components.items);
+    }
+
+    std.mem.sort([]const u8, matches.items, {}, lessThan);
+    return matches.toOwnedSlice(allocator);
+}
+
+pub fn freeMatches(allocator: std.mem.Allocator, matches: []const []const u8) void {
+    for (matches) |path| allocator.free(path);
+    allocator.free(matches);
+}
+
+fn lessThan(_: void, a: []const u8, b: []const u8) bool {
+    return std.mem.lessThan(u8, a, b);
+}
+
+/// Expand `components` below `base` ("" for the current directory).
+fn expandFrom(allocator: std.mem.Allocator, matches: *std.ArrayListUnmanaged([]const u8), base: []const u8, components: []const []const u8) !void {
+    const component = components[0];
+    const rest = components[1..];
+
+    if (!hasWildcard(component)) {
+        try visit(allocator, matches, try join(allocator, base, component), rest);
+        return;
+    }
+
+    var dir = std.fs.cwd().openDir(if (base.len == 0) "." else base, .{ .iterate = true }) catch return;
+    defer dir.close();
+    var entries = dir.iterate();
+    while (entries.next() catch null) |entry| {
+        if (entry.name[0] == '.' and component[0] != '.') continue;
+        if (!match(component, entry.name)) continue;
+        try visit(allocator, matches, try join(allocator, base, entry.name), rest);
+    }
+}
+
+/// Keep `path` if it is a file and the last component, or descend into it.
+/// Takes ownership of `path`.
+fn visit(allocator: std.mem.Allocator, matches: *std.ArrayListUnmanaged([]const u8), path: []const u8, rest: []const []const u8) !void {
+    if (rest.len == 0) {
+        const stat = std.fs.cwd().statFile(path) catch {
+            allocator.free(path);
+            return;
+        };
+        if (stat.kind != .file) {
+            allocator.free(path);
+            return;
+        }
+        matches.append(allocator, path) catch |err| {
+            allocator.free(path);
+            return err;
+        };
+        return;
+    }
+    defer allocator.free(path);
+    try expandFrom(allocator, matches, path, rest);
+}
+
+/// `dir/**/name-pattern`: every file below the part before `**` whose name
+/// matches the last component.
+fn expandRecursive(allocator: std.mem.Allocator, matches: *std.ArrayListUnmanaged([]const u8), pattern: []const u8) !void {
+    const last_slash = std.mem.lastIndexOfScalar(u8, pattern, '/') orelse 0;
+    const base_dir = if (last_slash > 0) pattern[0..last_slash] else ".";
+    var name_pattern = pattern[if (last_slash > 0) last_slash + 1 else 0..];
+    while (std.mem.startsWith(u8, name_pattern, "**/")) name_pattern = name_pattern[3..];
+    if (std.mem.startsWith(u8, name_pattern, "**")) name_pattern = name_pattern[2..];
+
+    var search_dir = base_dir;
+    if (std.mem.indexOf(u8, base_dir, "**")) |double_star| {
+        search_dir = if (double_star > 0) base_dir[0 .. double_star - 1] else ".";
+    }
+
+    var dir = std.fs.cwd().openDir(search_dir, .{ .iterate = true }) catch return;
+    defer dir.close();
+    var walker = try dir.walk(allocator);
+    defer walker.deinit();
+    while (walker.next() catch null) |entry| {
+        if (entry.kind != .file) continue;
+        if (!match(name_pattern, entry.basename)) continue;
+        const path = try join(allocator, search_dir, entry.path);
+        matches.append(allocator, path) catch |err| {
+            allocator.free(path);
+            return err;
+        };
+    }
+}
+
+fn join(allocator: std.mem.Allocator, base: []const u8, name: []const u8) ![]const u8 {
+    if (base.len == 0) return allocator.dupe(u8, name);
+    if (std.mem.endsWith(u8, base, "/")) return std.fmt.allocPrint(allocator, "{s}{s}", .{ base, name });
+    return std.fmt.allocPrint(allocator, "{s}/{s}", .{ base, name });
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+test "match handles wildcards and classes" {
+    try std.testing.expect(match("*.csv", "data.csv"));
+    try std.testing.expect(!match("*.csv", "data.csv.gz"));
+    try std.testing.expect(match("data-??.jsonl", "data-01.jsonl"));
+    try std.testing.expect(match("log[0-9].txt", "log7.txt"));
+    try std.testing.expect(!match("log[!0-9].txt", "log7.txt"));
+    try std.testing.expect(match("a*b*c", "axxbyyc"));
+    try std.testing.expect(!match("a*b*c", "axxbyy"));
+    try std.testing.expect(match("[x", "[x"));
+    try std.testing.expect(match("*", ""));
+}
+
+test "expand lists matching files in order" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    try tmp.dir.makePath("data/2024");
+    for ([_][]const u8{ "data/b.csv", "data/a.csv", "data/.hidden.csv", "data/notes.txt", "data/2024/c.csv" }) |path| {
+        const file = try tmp.dir.createFile(path, .{});
+        file.close();
+    }
+    const root = try tmp.dir.realpathAlloc(std.testing.allocator, ".");
+    defer std.testing.allocator.free(root);
+
+    const flat_pattern = try std.fmt.allocPrint(std.testing.allocator, "{s}/data/*.csv", .{root});
+    defer std.testing.allocator.free(flat_pattern);
+    const flat = try expand(std.testing.allocator, flat_pattern);
+    defer freeMatches(std.testing.allocator, flat);
+    try std.testing.expectEqual(@as(usize, 2), flat.len);
+    try std.testing.expect(std.mem.endsWith(u8, flat[0], "/data/a.csv"));
+    try std.testing.expect(std.mem.endsWith(u8, flat[1], "/data/b.csv"));
+
+    const deep_pattern = try std.fmt.allocPrint(std.testing.allocator, "{s}/data/**/*.csv", .{root});
+    defer std.testing.allocator.free(deep_pattern);
+    const deep = try expand(std.testing.allocator, deep_pattern);
+    defer freeMatches(std.testing.allocator, deep);
+    try std.testing.expectEqual(@as(usize, 4), deep.len);
+    try std.testing.expect(std.mem.endsWith(u8, deep[0], "/data/.hidden.csv"));
+    try std.testing.expect(std.mem.endsWith(u8, deep[1], "/data/2024/c.csv"));
+}
@@ -0,0 +1,443 @@
+//! Multi-process pipelines without a shell.
+//!
+//! jn-cat and jn-put chain plugins (`curl | gz --mode=raw | csv --mode=read`).
+//! A `Pipeline` spawns each stage directly from its argv and connects
+//! neighbours with pipes, so paths, URLs and header values never pass through
+//! `/bin/sh` and need no quoting:
+//!
+//! ```zig
+//! var pipeline = Pipeline.init(allocator);
+//! defer pipeline.deinit();
+//! pipeline.stdin = .{ .file = "data.csv.gz" };
+//! _ = try pipeline.add(&.{ gz_path, "--mode=raw" });
+//! _ = try pipeline.add(&.{ csv_path, "--mode=read" });
+//! const result = try pipeline.run();
+//! pipeline.exitOnFailure(result, "jn-cat");
+//! ```
+//!
+//! `spawn` starts the stages without waiting, for tools that feed the first
+//! stage themselves (`.stdin = .pipe`, e.g. an HTTP response body) or read
+//! the last one as it writes (`.stdout = .pipe`); `wait` then reaps them.
+//!
+//! Each stage gets the default SIGPIPE disposition (JN's Zig tools ignore it,
+//! and exec would pass that on), so when a reader exits early the stages
+//! feeding it stop the way they would in a shell pipeline. A stage killed by
+//! SIGPIPE has terminated early, not failed; see spec/08-streaming-backpressure.md.
+//! Otherwise the first stage that fails decides the pipeline's exit status.
+
+const std = @import("std");
+const posix = std.posix;
+
+/// Where the first stage reads from.
+pub const Input = union(enum) {
+    /// The tool's own stdin
+    inherit,
+    /// /dev/null, for sources that read nothing
+    null_device,
+    /// A file, opened for reading
+    file: []const u8,
+    /// Written by the tool through `Running.stdin`
+    pipe,
+};
+
+/// Where the last stage writes to.
+pub const Output = union(enum) {
+    /// The tool's own stdout
+    inherit,
+    /// A file, created or truncated
+    file: []const u8,
+    /// Read by the tool into `Result.output`
+    capture,
+    /// Read by the tool through `Running.stdout` as the stages write it
+    pipe,
+};
+
+pub const EnvVar = struct {
+    name: []const u8,
+    value: []const u8,
+};
+
+/// One process of a pipeline. Arguments are copied into the pipeline.
+pub const Stage = struct {
+    allocator: std.mem.Allocator,
+    argv: std.ArrayListUnmanaged([]const u8) = .empty,
+    /// Variables set on top of the tool's environment
+    env: std.ArrayListUnmanaged(EnvVar) = .empty,
+    /// Name used in messages; the basename of argv[0] when null
+    label: ?[]const u8 = null,
+
+    pub fn arg(self: *Stage, value: []const u8) !void {
+        try self.argv.append(self.allocator, try self.allocator.dupe(u8, value));
+    }
+
+    /// Append an argument built from a format string, e.g. `--delimiter={s}`.
+    pub fn argPrint(self: *Stage, comptime fmt: []const u8, args: anytype) !void {
+        try self.argv.append(self.allocator, try std.fmt.allocPrint(self.allocator, fmt, args));
+    }
+
+    pub fn setEnv(self: *Stage, name: []const u8, value: []const u8) !void {
+        try self.env.append(self.allocator, .{
+            .name = try self.allocator.dupe(u8, name),
+            .value = try self.allocator.dupe(u8, value),
+        });
+    }
+
+    pub fn name(self: *const Stage) []const u8 {
+        return self.label orelse std.fs.path.basename(self.argv.items[0]);
+    }
+};
+
+/// How a stage ended.
+pub const Term = union(enum) {
+    exited: u8,
+    signal: u32,
+
+    /// Shell-style status: the exit code, or 128 + the signal number.
+    pub fn code(self: Term) u8 {
+        return switch (self) {
+            .exited => |status| status,
+            .signal => |sig| 128 +| @as(u8, @intCast(@min(sig, 127))),
+        };
+    }
+
+    /// Stopped by a reader going away, which ends a stage early but cleanly.
+    pub fn isBrokenPipe(self: Term) bool {
+        return self == .signal and self.signal == posix.SIG.PIPE;
+    }
+
+    pub fn succeeded(self: Term) bool {
+        return (self == .exited and self.exited == 0) or self.isBrokenPipe();
+    }
+};
+
+pub const Result = struct {
+    /// How each stage ended, in pipeline order
+    terms: []const Term,
+    /// What the last stage wrote, when stdout is `.capture` (owned by the
+    /// pipeline)
+    output: []const u8 = "",
+
+    /// Index of the first stage that failed, or null when every stage
+    /// succeeded or only stopped on SIGPIPE.
+    pub fn failed(self: Result) ?usize {
+        for (self.terms, 0..) |term, i| {
+            if (!term.succeeded()) return i;
+        }
+        return null;
+    }
+};
+
+pub const Pipeline = struct {
+    arena_state: std.heap.ArenaAllocator,
+    stages: std.ArrayListUnmanaged(*Stage) = .empty,
+    stdin: Input = .inherit,
+    stdout: Output = .inherit,
+
+    pub fn init(allocator: std.mem.Allocator) Pipeline {
+        return .{ .arena_state = .init(allocator) };
+    }
+
+    pub fn deinit(self: *Pipeline) void {
+        self.arena_state.deinit();
+    }
+
+    fn arena(self: *Pipeline) std.mem.Allocator {
+        return self.arena_state.allocator();
+    }
+
+    /// Append a stage running `argv`; more arguments may be added to the
+    /// returned stage before `run`.
+    pub fn add(self: *Pipeline, argv: []const []const u8) !*Stage {
+        const stage = try self.arena().create(Stage);
+        stage.* = .{ .allocator = self.arena() };
+        for (argv) |value| try stage.arg(value);
+        try self.stages.append(self.arena(), stage);
+        return stage;
+    }
+
+    /// Spawn every stage, connected by pipes, and wait for all of them.
+    pub fn run(self: *Pipeline) !Result {
+        var running = try self.spawn();
+        return running.wait();
+    }
+
+    /// Spawn every stage, connected by pipes, without waiting. With `.pipe`
+    /// stdin or stdout the tool feeds or reads the pipeline through the
+    /// returned `Running` before calling `wait`.
+    pub fn spawn(self: *Pipeline) !Running {
+        const count = self.stages.items.len;
+        std.debug.assert(count > 0);
+        const allocator = self.arena();
+
+        // Everything exec needs is built before forking
+        const env_map = try std.process.getEnvMap(allocator);
+        const base_envp = try std.process.createNullDelimitedEnvMap(allocator, &env_map);
+        const argvs = try allocator.alloc([*:null]const ?[*:0]const u8, count);
+        const envps = try allocator.alloc([*:null]const ?[*:0]const u8, count);
+        for (self.stages.items, 0..) |stage, i| {
+            const argv = try allocator.allocSentinel(?[*:0]const u8, stage.argv.items.len, null);
+            for (stage.argv.items, 0..) |value, j| argv[j] = (try allocator.dupeZ(u8, value)).ptr;
+            argvs[i] = argv.ptr;
+
+            envps[i] = base_envp.ptr;
+            if (stage.env.items.len > 0) {
+                var stage_env = try std.process.getEnvMap(allocator);
+                for (stage.env.items) |variable| try stage_env.put(variable.name, variable.value);
+                envps[i] = (try std.process.createNullDelimitedEnvMap(allocator, &stage_env)).ptr;
+            }
+        }
+
+        // The tool's ends of its pipes are CLOEXEC, so no stage holds them
+        const stdin_pipe: ?[2]posix.fd_t = if (self.stdin == .pipe) try posix.pipe2(.{ .CLOEXEC = true }) else null;
+        errdefer if (stdin_pipe) |pipe| posix.close(pipe[1]);
+        const first_input: posix.fd_t = switch (self.stdin) {
+            .inherit => posix.STDIN_FILENO,
+            .null_device => try posix.open("/dev/null", .{ .ACCMODE = .RDONLY, .CLOEXEC = true }, 0),
+            .file => |path| try posix.open(path, .{ .ACCMODE = .RDONLY, .CLOEXEC = true }, 0),
+            .pipe => stdin_pipe.?[0],
+        };
+        defer if (first_input != posix.STDIN_FILENO) posix.close(first_input);
+        const stdout_pipe: ?[2]posix.fd_t = switch (self.stdout) {
+            .capture, .pipe => try posix.pipe2(.{ .CLOEXEC = true }),
+            .inherit, .file => null,
+        };
+        errdefer if (stdout_pipe) |pipe| posix.close(pipe[0]);
+        const last_output: posix.fd_t = switch (self.stdout) {
+            .inherit => posix.STDOUT_FILENO,
+            .file => |path| try posix.open(path, .{ .ACCMODE = .WRONLY, .CREAT = true, .TRUNC = true, .CLOEXEC = true }, 0o644),
+            .capture, .pipe => stdout_pipe.?[1],
+        };
+        defer if (last_output != posix.STDOUT_FILENO) posix.close(last_output);
+
+        const pids = try allocator.alloc(posix.pid_t, count);
+        var spawned: usize = 0;
+        errdefer {
+            for (pids[0..spawned]) |pid| {
+                posix.kill(pid, posix.SIG.TERM) catch {};
+                _ = posix.waitpid(pid, 0);
+            }
+        }
+
+        var input = first_input;
+        errdefer if (input != first_input) posix.close(input);
+        for (self.stages.items, 0..) |stage, i| {
+            const last = i + 1 == count;
+            var pipe: [2]posix.fd_t = undefined;
+            if (!last) pipe = try posix.pipe2(.{ .CLOEXEC = true });
+            errdefer if (!last) {
+                posix.close(pipe[0]);
+                posix.close(pipe[1]);
+            };
+            const output = if (last) last_output else pipe[1];
+
+            const pid = try posix.fork();
+            if (pid == 0) execStage(stage, argvs[i], envps[i], input, output);
+            pids[i] = pid;
+            spawned += 1;
+
+            // The parent keeps no pipe ends, so each stage sees EOF or
+            // SIGPIPE as soon as its neighbour exits
+            if (input != first_input) posix.close(input);
+            if (!last) {
+                posix.close(pipe[1]);
+                input = pipe[0];
+            }
+        }
+
+        return .{
+            .pipeline = self,
+            .pids = pids,
+            .stdin = if (stdin_pipe) |pipe| .{ .handle = pipe[1] } else null,
+            .stdout = if (stdout_pipe) |pipe| .{ .handle = pipe[0] } else null,
+        };
+    }
+
+    /// Exit with the status of the first stage that failed, naming it on
+    /// stderr. Returns when the pipeline succeeded.
+    pub fn exitOnFailure(self: *const Pipeline, result: Result, tool: []const u8) void {
+        const index = result.failed() orelse return;
+        const stage = self.stages.items[index];
+        switch (result.terms[index]) {
+            .exited => |status| std.debug.print("{s}: stage {d} of {d} ({s}) exited with status {d}\n", .{ tool, index + 1, self.stages.items.len, stage.name(), status }),
+            .signal => |sig| std.debug.print("{s}: stage {d} of {d} ({s}) was killed by signal {d}\n", .{ tool, index + 1, self.stages.items.len, stage.name(), sig }),
+        }
+        std.process.exit(result.terms[index].code());
+    }
+};
+
+/// A spawned pipeline, until `wait` reaps its stages.
+pub const Running = struct {
+    pipeline: *Pipeline,
+    pids: []const posix.pid_t,
+    /// The first stage's stdin, when the pipeline's stdin is `.pipe`
+    stdin: ?std.fs.File = null,
+    /// The last stage's stdout, when the pipeline's stdout is `.pipe` or
+    /// `.capture`
+    stdout: ?std.fs.File = null,
+
+    /// Close `stdin`, so the first stage sees EOF.
+    pub fn closeStdin(self: *Running) void {
+        if (self.stdin) |file| file.close();
+        self.stdin = null;
+    }
+
+    /// Close the tool's pipe ends and wait for every stage.
+    pub fn wait(self: *Running) !Result {
+        self.closeStdin();
+        const allocator = self.pipeline.arena();
+
+        // Read captured output before waiting, or a full pipe would block
+        // the last stage forever
+        var output: []const u8 = "";
+        if (self.stdout) |file| {
+            defer file.close();
+            self.stdout = null;
+            if (self.pipeline.stdout == .capture) output = try file.readToEndAlloc(allocator, std.math.maxInt(usize));
+        }
+
+        const terms = try allocator.alloc(Term, self.pids.len);
+        for (self.pids, 0..) |pid, i| terms[i] = termFromStatus(posix.waitpid(pid, 0).status);
+        return .{ .terms = terms, .output = output };
+    }
+};
+
+/// In the forked child: wire up stdin/stdout and exec. Never returns.
+fn execStage(stage: *const Stage, argv: [*:null]const ?[*:0]const u8, envp: [*:null]const ?[*:0]const u8, input: posix.fd_t, output: posix.fd_t) noreturn {
+    // Stages die on SIGPIPE as they would under a shell
+    const default_action: posix.Sigaction = .{
+        .handler = .{ .handler = posix.SIG.DFL },
+        .mask = posix.sigemptyset(),
+        .flags = 0,
+    };
+    posix.sigaction(posix.SIG.PIPE, &default_action, null);
+
+    // dup2 leaves the new descriptors open across exec; the originals are CLOEXEC
+    if (input != posix.STDIN_FILENO) posix.dup2(input, posix.STDIN_FILENO) catch posix.exit(127);
+    if (output != posix.STDOUT_FILENO) posix.dup2(output, posix.STDOUT_FILENO) catch posix.exit(127);
+
+    // Straight to fd 2: std.debug.print takes a lock some other thread of
+    // the parent may have held when it forked
+    const err = posix.execvpeZ(argv[0].?, argv, envp);
+    var buf: [512]u8 = undefined;
+    var message = std.Io.Writer.fixed(&buf);
+    message.print("cannot run '{s}': {s}", .{ stage.argv.items[0], @errorName(err) }) catch {};
+    _ = posix.write(posix.STDERR_FILENO, message.buffered()) catch {};
+    _ = posix.write(posix.STDERR_FILENO, "\n") catch {};
+    posix.exit(127);
+}
+
+fn termFromStatus(status: u32) Term {
+    if (posix.W.IFEXITED(status)) return .{ .exited = posix.W.EXITSTATUS(status) };
+    if (posix.W.IFSIGNALED(status)) return .{ .signal = posix.W.TERMSIG(status) };
+    return .{ .exited = 1 };
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+test "pipeline connects stages and reports success" {
+    var pipeline = Pipeline.init(std.testing.allocator);
+    defer pipeline.deinit();
+    pipeline.stdin = .null_device;
+    _ = try pipeline.add(&.{ "printf", "a\\nb\\n" });
+    _ = try pipeline.add(&.{ "grep", "-q", "b" });
+
+    const result = try pipeline.run();
+    try std.testing.expectEqual(@as(?usize, null), result.failed());
+}
+
+test "pipeline reports the first failing stage" {
+    var pipeline = Pipeline.init(std.testing.allocator);
+    defer pipeline.deinit();
+    pipeline.stdin = .null_device;
+    _ = try pipeline.add(&.{"true"});
+    const failing = try pipeline.add(&.{ "sh", "-c" });
+    try failing.arg("cat >/dev/null; exit 3");
+    _ = try pipeline.add(&.{"cat"});
+
+    const result = try pipeline.run();
+    try std.testing.expectEqual(@as(?usize, 1), result.failed());
+    try std.testing.expectEqual(@as(u8, 3), result.terms[1].code());
+    try std.testing.expectEqualStrings("sh", pipeline.stages.items[1].name());
+}
+
+test "a stage that can't be run exits 127" {
+    var pipeline = Pipeline.init(std.testing.allocator);
+    defer pipeline.deinit();
+    pipeline.stdin = .null_device;
+    pipeline.stdout = .capture;
+    _ = try pipeline.add(&.{"/nonexistent/jn-no-such-tool"});
+
+    const result = try pipeline.run();
+    try std.testing.expectEqual(@as(?usize, 0), result.failed());
+    try std.testing.expectEqual(@as(u8, 127), result.terms[0].code());
+}
+
+test "SIGPIPE is an early stop, not a failure" {
+    var pipeline = Pipeline.init(std.testing.allocator);
+    defer pipeline.deinit();
+    pipeline.stdin = .null_device;
+    _ = try pipeline.add(&.{"yes"});
+    _ = try pipeline.add(&.{ "head", "-c", "1" });
+    pipeline.stdout = .{ .file = "/dev/null" };
+
+    const result = try pipeline.run();
+    try std.testing.expect(result.terms[0].isBrokenPipe());
+    try std.testing.expectEqual(@as(?usize, null), result.failed());
+}
+
+test "captured output" {
+    var pipeline = Pipeline.init(std.testing.allocator);
+    defer pipeline.deinit();
+    pipeline.stdin = .null_device;
+    pipeline.stdout = .capture;
+    _ = try pipeline.add(&.{ "printf", "a\\nb\\n" });
+    _ = try pipeline.add(&.{ "tr", "a-z", "A-Z" });
+
+    const result = try pipeline.run();
+    try std.testing.expectEqual(@as(?usize, null), result.failed());
+    try std.testing.expectEqualStrings("A\nB\n", result.output);
+}
+
+test "tool feeds and reads a spawned pipeline" {
+    var pipeline = Pipeline.init(std.testing.allocator);
+    defer pipeline.deinit();
+    pipeline.stdin = .pipe;
+    pipeline.stdout = .pipe;
+    _ = try pipeline.add(&.{ "tr", "a-z", "A-Z" });
+
+    var running = try pipeline.spawn();
+    try running.stdin.?.writeAll("abc\n");
+    running.closeStdin();
+    var buf: [16]u8 = undefined;
+    const n = try running.stdout.?.readAll(&buf);
+    try std.testing.expectEqualStrings("ABC\n", buf[0..n]);
+
+    const result = try running.wait();
+    try std.testing.expectEqual(@as(?usize, null), result.failed());
+}
+
+test "stage environment and output file" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    const dir = try tmp.dir.realpathAlloc(std.testing.allocator, ".");
+    defer std.testing.allocator.free(dir);
+    const out_path = try std.fs.path.join(std.testing.allocator, &.{ dir, "out.txt" });
+    defer std.testing.allocator.free(out_path);
+
+    var pipeline = Pipeline.init(std.testing.allocator);
+    defer pipeline.deinit();
+    pipeline.stdin = .null_device;
+    pipeline.stdout = .{ .file = out_path };
+    const stage = try pipeline.add(&.{ "sh", "-c", "printf %s \"$JN_TEST_VALUE\"" });
+    try stage.setEnv("JN_TEST_VALUE", "it's here");
+
+    const result = try pipeline.run();
+    try std.testing.expectEqual(@as(?usize, null), result.failed());
+    const file = try tmp.dir.openFile("out.txt", .{});
+    defer file.close();
+    const written = try file.readToEndAlloc(std.testing.allocator, 1024);
+    defer std.testing.allocator.free(written);
+    try std.testing.expectEqualStrings("it's here", written);
+}
@@ -36,6 +36,10 @@ const builtin = @import("builtin");
 /// Default buffer size for stdin (64KB)
 pub const DEFAULT_BUFFER_SIZE = 64 * 1024;
 
+/// Window buffer size for `std.compress.zstd.Decompress`: zstd's default
+/// window (levels up to 19) plus the block being decoded into it
+pub const ZSTD_WINDOW_SIZE = std.compress.zstd.default_window_len + std.compress.zstd.block_size_max;
+
 /// Possible errors when reading lines
 pub const ReadError = error{
     /// Input line exceeds buffer capacity
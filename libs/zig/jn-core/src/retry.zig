@@ -0,0 +1,180 @@
+//! Retries and rate limits for requests to remote services.
+//!
+//! `Policy` spaces retries with exponential backoff plus jitter and defers
+//! to a server's `Retry-After`; `RateLimiter` is a token bucket shared by
+//! every request a tool makes (all pages of a paginated API, say).
+
+const std = @import("std");
+
+/// How many times, and how far apart, to retry a failed request
+pub const Policy = struct {
+    /// Attempts after the first one
+    retries: u32 = 0,
+    /// Delay before the first retry; doubles with each retry
+    base_delay_ms: u64 = 500,
+    max_delay_ms: u64 = 30_000,
+
+    /// Milliseconds to wait before retry number `attempt` (1 for the first
+    /// retry). A server's Retry-After wins; otherwise the doubled delay is
+    /// jittered between half and all of itself so clients spread out.
+    pub fn delay(self: Policy, attempt: u32, retry_after_ms: ?u64, random: std.Random) u64 {
+        if (retry_after_ms) |ms| return ms;
+        const shift: u6 = @intCast(@min(attempt -| 1, 32));
+        const ceiling = @min(self.max_delay_ms, self.base_delay_ms << shift);
+        return ceiling / 2 + random.uintAtMost(u64, ceiling - ceiling / 2);
+    }
+};
+
+/// Whether an HTTP status is worth retrying: timeouts, rate limits and
+/// temporary server errors
+pub fn isRetryableStatus(status: u16) bool {
+    return switch (status) {
+        408, 429, 500, 502, 503, 504 => true,
+        else => false,
+    };
+}
+
+/// Milliseconds to wait according to a `Retry-After` header, given as
+/// seconds or as an HTTP date (compared with `now`, in Unix seconds).
+pub fn parseRetryAfter(value: []const u8, now: i64) ?u64 {
+    const text = std.mem.trim(u8, value, " \t");
+    if (std.fmt.parseInt(u64, text, 10)) |seconds| {
+        return std.math.mul(u64, seconds, std.time.ms_per_s) catch null;
+    } else |_| {}
+    const date = parseHttpDate(text) orelse return null;
+    return @intCast(@max(date - now, 0) * std.time.ms_per_s);
+}
+
+const months = [_][]const u8{ "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec" };
+
+/// Unix seconds of an IMF-fixdate ("Sun, 06 Nov 1994 08:49:37 GMT")
+fn parseHttpDate(text: []const u8) ?i64 {
+    var fields = std.mem.tokenizeScalar(u8, text, ' ');
+    _ = fields.next() orelse return null; // day name
+    const day = std.fmt.parseInt(u32, fields.next() orelse return null, 10) catch return null;
+    const month_name = fields.next() orelse return null;
+    const year = std.fmt.parseInt(i64, fields.next() orelse return null, 10) catch return null;
+    const time = fields.next() orelse return null;
+    const zone = fields.next() orelse return null;
+    if (!std.mem.eql(u8, zone, "GMT") or time.len != 8 or time[2] != ':' or time[5] != ':') return null;
+
+    const month: u32 = for (months, 1..) |name, i| {
+        if (std.mem.eql(u8, name, month_name)) break @intCast(i);
+    } else return null;
+    if (day < 1 or day > 31) return null;
+    const hours = std.fmt.parseInt(i64, time[0..2], 10) catch return null;
+    const minutes = std.fmt.parseInt(i64, time[3..5], 10) catch return null;
+    const seconds = std.fmt.parseInt(i64, time[6..8], 10) catch return null;
+
+    return daysFromCivil(year, month, day) * std.time.s_per_day + hours * 3600 + minutes * 60 + seconds;
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn daysFromCivil(year: i64, month: u32, day: u32) i64 {
+    const y = if (month <= 2) year - 1 else year;
+    const era = @divFloor(y, 400);
+    const year_of_era = y - era * 400;
+    const month_from_march: i64 = (month + 9) % 12;
+    const day_of_year = @divFloor(153 * month_from_march + 2, 5) + day - 1;
+    const day_of_era = year_of_era * 365 + @divFloor(year_of_era, 4) - @divFloor(year_of_era, 100) + day_of_year;
+    return era * 146097 + day_of_era - 719468;
+}
+
+/// Token bucket allowing `per_second` requests per second on average and
+/// bursts of `burst` requests.
+pub const RateLimiter = struct {
+    per_second: f64,
+    burst: f64 = 1,
+    /// Negative while callers wait for tokens they've already taken
+    tokens: f64 = 1,
+    last_ns: ?i128 = null,
+
+    /// Parse a rate such as "10/sec", "100/min", "1/hour" or "5" (per second)
+    pub fn parse(text: []const u8) !RateLimiter {
+        const slash = std.mem.indexOfScalar(u8, text, '/');
+        const count = std.fmt.parseFloat(f64, std.mem.trim(u8, text[0 .. slash orelse text.len], " ")) catch return error.InvalidRate;
+        if (!(count > 0) or std.math.isInf(count)) return error.InvalidRate;
+
+        const unit = if (slash) |i| std.mem.trim(u8, text[i + 1 ..], " ") else "s";
+        const seconds: f64 = find: for (units) |entry| {
+            for (entry.names) |name| {
+                if (std.ascii.eqlIgnoreCase(unit, name)) break :find entry.seconds;
+            }
+        } else return error.InvalidRate;
+        return .{ .per_second = count / seconds };
+    }
+
+    const units = [_]struct { names: []const []const u8, seconds: f64 }{
+        .{ .names = &.{ "s", "sec", "secs", "second", "seconds" }, .seconds = 1 },
+        .{ .names = &.{ "m", "min", "mins", "minute", "minutes" }, .seconds = 60 },
+        .{ .names = &.{ "h", "hr", "hour", "hours" }, .seconds = 3600 },
+    };
+
+    /// Take a token, sleeping until one is available
+    pub fn acquire(self: *RateLimiter) void {
+        const wait_ns = self.take(std.time.nanoTimestamp());
+        if (wait_ns > 0) std.Thread.sleep(wait_ns);
+    }
+
+    /// Take a token at `now_ns` and return the nanoseconds until it is due
+    pub fn take(self: *RateLimiter, now_ns: i128) u64 {
+        if (self.last_ns) |last| {
+            const elapsed: f64 = @floatFromInt(now_ns - last);
+            self.tokens = @min(self.burst, self.tokens + elapsed / std.time.ns_per_s * self.per_second);
+        }
+        self.last_ns = now_ns;
+        self.tokens -= 1;
+        if (self.tokens >= 0) return 0;
+        return @intFromFloat(-self.tokens / self.per_second * std.time.ns_per_s);
+    }
+};
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+test "backoff doubles with jitter and respects Retry-After" {
+    var prng = std.Random.DefaultPrng.init(42);
+    const policy: Policy = .{ .retries = 5, .base_delay_ms = 100, .max_delay_ms = 1000 };
+    for (1..6) |attempt| {
+        const ceiling = @min(1000, @as(u64, 100) << @intCast(attempt - 1));
+        const ms = policy.delay(@intCast(attempt), null, prng.random());
+        try std.testing.expect(ms >= ceiling / 2 and ms <= ceiling);
+    }
+    try std.testing.expectEqual(@as(u64, 7000), policy.delay(1, 7000, prng.random()));
+}
+
+test "retryable statuses" {
+    try std.testing.expect(isRetryableStatus(429));
+    try std.testing.expect(isRetryableStatus(503));
+    try std.testing.expect(!isRetryableStatus(404));
+    try std.testing.expect(!isRetryableStatus(200));
+}
+
+test "parse Retry-After seconds and dates" {
+    try std.testing.expectEqual(@as(?u64, 120_000), parseRetryAfter(" 120 ", 0));
+    // Sun, 06 Nov 1994 08:49:37 GMT is 784111777
+    try std.testing.expectEqual(@as(?u64, 30_000), parseRetryAfter("Sun, 06 Nov 1994 08:49:37 GMT", 784111747));
+    try std.testing.expectEqual(@as(?u64, 0), parseRetryAfter("Sun, 06 Nov 1994 08:49:37 GMT", 784111800));
+    try std.testing.expectEqual(@as(?u64, null), parseRetryAfter("soon", 0));
+}
+
+test "parse rates" {
+    try std.testing.expectEqual(@as(f64, 10), (try RateLimiter.parse("10/sec")).per_second);
+    try std.testing.expectEqual(@as(f64, 2), (try RateLimiter.parse("120/min")).per_second);
+    try std.testing.expectEqual(@as(f64, 5), (try RateLimiter.parse("5")).per_second);
+    try std.testing.expectError(error.InvalidRate, RateLimiter.parse("0/sec"));
+    try std.testing.expectError(error.InvalidRate, RateLimiter.parse("10/fortnight"));
+}
+
+test "token bucket spaces requests" {
+    var limiter = try RateLimiter.parse("4/sec");
+    const quarter = std.time.ns_per_s / 4;
+    try std.testing.expectEqual(@as(u64, 0), limiter.take(0));
+    // Two more requests at once wait a quarter and a half second
+    try std.testing.expectEqual(@as(u64, quarter), limiter.take(0));
+    try std.testing.expectEqual(@as(u64, 2 * quarter), limiter.take(0));
+    // After a long pause only one token has built up
+    try std.testing.expectEqual(@as(u64, 0), limiter.take(10 * std.time.ns_per_s));
+    try std.testing.expectEqual(@as(u64, quarter), limiter.take(10 * std.time.ns_per_s));
+}
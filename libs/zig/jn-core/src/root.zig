@@ -9,6 +9,11 @@ pub const writer = @import("writer.zig");
 pub const json = @import("json.zig");
 pub const errors = @import("errors.zig");
 pub const shell = @import("shell.zig");
+pub const sidecar = @import("sidecar.zig");
+pub const explain = @import("explain.zig");
+pub const pipeline = @import("pipeline.zig");
+pub const glob = @import("glob.zig");
+pub const retry = @import("retry.zig");
 
 // Re-export main functions for convenience
 pub const readLine = reader.readLine;
@@ -28,9 +33,20 @@ pub const writeJsonString = json.writeJsonString;
 pub const writeJsonValue = json.writeJsonValue;
 pub const writeJsonLine = json.writeJsonLine;
 
+// Error sidecar (--errors=PATH, --max-errors=N)
+pub const ErrorSink = sidecar.ErrorSink;
+
+// Process pipelines without a shell
+pub const Pipeline = pipeline.Pipeline;
+
+// Retries and rate limits for remote requests
+pub const RetryPolicy = retry.Policy;
+pub const RateLimiter = retry.RateLimiter;
+
 // Buffer size constants
 pub const STDIN_BUFFER_SIZE = reader.DEFAULT_BUFFER_SIZE;
 pub const STDOUT_BUFFER_SIZE = writer.DEFAULT_BUFFER_SIZE;
+pub const ZSTD_WINDOW_SIZE = reader.ZSTD_WINDOW_SIZE;
 
 // Shell utilities
 pub const escapeForShellSingleQuote = shell.escapeForShellSingleQuote;
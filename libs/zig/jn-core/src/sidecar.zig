@@ -0,0 +1,215 @@
+//! Error sidecar for JN plugins and tools.
+//!
+//! With `--errors=PATH`, records that cannot be read or processed are appended
+//! to PATH as NDJSON instead of being dropped or ending the pipeline:
+//!
+//! ```json
+//! {"_error":"unterminated quoted field","_line":42,"_raw":"a,\"b","_source":"data.csv","_stage":"csv"}
+//! ```
+//!
+//! `_line` is the 1-based input line (null when the record has none), `_raw`
+//! the unparsed text, `_source` what was being read (`--errors-source=NAME`,
+//! which jn-cat passes to format plugins; stdin otherwise) and `_stage` the
+//! plugin or tool that gave up on it.
+//!
+//! Every stage of a pipeline may share one file: each record is a single
+//! append, so lines from different processes never interleave.
+//!
+//! `--max-errors=N` is the budget. Once a stage has routed more than N records
+//! it exits 1, failing the pipeline. The budget applies without `--errors` too,
+//! counting the records the stage would otherwise skip.
+
+const std = @import("std");
+const errors = @import("errors.zig");
+const json = @import("json.zig");
+
+/// Where failed records go, and how many are allowed.
+pub const ErrorSink = struct {
+    allocator: std.mem.Allocator,
+    /// Opened for appending; null without --errors
+    file: ?std.fs.File = null,
+    /// Plugin or tool name, for `_stage` and messages
+    stage: []const u8,
+    /// Default `_source`
+    source: []const u8 = "<stdin>",
+    /// Exit once `count` goes past this
+    max_errors: ?u64 = null,
+    count: u64 = 0,
+    /// Flushed before the budget ends the run, so records already written
+    /// reach the next stage
+    output: ?*std.Io.Writer = null,
+
+    /// The sink for `--errors=PATH`, `--max-errors=N` and `--errors-source=NAME`.
+    /// `args` is a jn-cli ArgParser. A file that can't be opened, or a budget
+    /// that isn't a number, is a usage error.
+    pub fn fromArgs(allocator: std.mem.Allocator, args: anytype, stage: []const u8) ErrorSink {
+        var sink: ErrorSink = .{ .allocator = allocator, .stage = stage };
+        if (args.get("errors-source", null)) |source| sink.source = source;
+        if (args.get("max-errors", null)) |text| {
+            sink.max_errors = std.fmt.parseInt(u64, text, 10) catch {
+                std.debug.print("{s}: --max-errors expects a number, got '{s}'\n", .{ stage, text });
+                errors.ExitCode.usage_error.exit();
+            };
+        }
+        if (args.get("errors", null)) |path| {
+            sink.file = openAppend(path) catch |err| {
+                std.debug.print("{s}: cannot open error file '{s}': {s}\n", .{ stage, path, @errorName(err) });
+                errors.ExitCode.usage_error.exit();
+            };
+        }
+        return sink;
+    }
+
+    /// Whether failed records are written somewhere. Callers keep their
+    /// previous behavior (skip, warn) when they are not.
+    pub fn enabled(self: *const ErrorSink) bool {
+        return self.file != null;
+    }
+
+    /// Whether failed records are tracked at all: written or budgeted.
+    pub fn counting(self: *const ErrorSink) bool {
+        return self.file != null or self.max_errors != null;
+    }
+
+    /// Route one failed record from `source` (null for the sink's default),
+    /// then let the caller carry on. Exits 1 once the budget is spent.
+    pub fn reportFrom(self: *ErrorSink, source: ?[]const u8, message: []const u8, line: ?u64, raw: []const u8) void {
+        if (self.file == null) return self.commit("");
+        const record = formatRecord(self.allocator, message, line, raw, source orelse self.source, self.stage) catch {
+            errors.exitWithError("{s}: out of memory writing the error file", .{self.stage});
+        };
+        defer self.allocator.free(record);
+        self.commit(record);
+    }
+
+    /// reportFrom the sink's default source.
+    pub fn report(self: *ErrorSink, message: []const u8, line: ?u64, raw: []const u8) void {
+        self.reportFrom(null, message, line, raw);
+    }
+
+    /// Write one record from the sink's default source to `writer`, for
+    /// stages that collect records (on worker threads, say) and `commit`
+    /// them later.
+    pub fn format(self: *const ErrorSink, writer: *std.Io.Writer, message: []const u8, line: ?u64, raw: []const u8) !void {
+        try writeRecord(writer, message, line, raw, self.source, self.stage);
+    }
+
+    /// Count one failed record and append `record` (from `format`, or empty
+    /// without a file). Exits 1 once the budget is spent.
+    pub fn commit(self: *ErrorSink, record: []const u8) void {
+        self.count += 1;
+        if (self.file) |file| {
+            // One write per record keeps lines whole when stages share the file
+            file.writeAll(record) catch |err| {
+                errors.exitWithError("{s}: cannot write the error file: {s}", .{ self.stage, @errorName(err) });
+            };
+        }
+        if (self.max_errors) |max| {
+            if (self.count > max) {
+                if (self.output) |writer| writer.flush() catch {};
+                errors.exitWithError("{s}: too many errors ({d}, --max-errors={d})", .{ self.stage, self.count, max });
+            }
+        }
+    }
+
+    pub fn close(self: *ErrorSink) void {
+        if (self.file) |file| file.close();
+        self.file = null;
+    }
+};
+
+/// Open `path` for appending, creating it if needed.
+pub fn openAppend(path: []const u8) !std.fs.File {
+    const fd = try std.posix.open(path, .{ .ACCMODE = .WRONLY, .CREAT = true, .APPEND = true, .CLOEXEC = true }, 0o644);
+    return .{ .handle = fd };
+}
+
+/// Whether `text` is one well-formed JSON value, for stages that pass lines
+/// through without parsing them.
+pub fn isJson(allocator: std.mem.Allocator, text: []const u8) bool {
+    return std.json.validate(allocator, text) catch false;
+}
+
+/// Line `line` (1-based) of `text`, for the `_raw` of a document-level error.
+pub fn lineAt(text: []const u8, line: u64) []const u8 {
+    var lines = std.mem.splitScalar(u8, text, '\n');
+    var n: u64 = 1;
+    while (lines.next()) |current| : (n += 1) {
+        if (n == line) return current;
+    }
+    return "";
+}
+
+/// One error record, newline-terminated. Caller owns the result.
+pub fn formatRecord(allocator: std.mem.Allocator, message: []const u8, line: ?u64, raw: []const u8, source: []const u8, stage: []const u8) ![]u8 {
+    var out: std.Io.Writer.Allocating = .init(allocator);
+    errdefer out.deinit();
+    try writeRecord(&out.writer, message, line, raw, source, stage);
+    return out.toOwnedSlice();
+}
+
+/// One error record, newline-terminated, written to `w`.
+pub fn writeRecord(w: *std.Io.Writer, message: []const u8, line: ?u64, raw: []const u8, source: []const u8, stage: []const u8) !void {
+    try w.writeAll("{\"_error\":");
+    try json.writeJsonString(w, message);
+    if (line) |n| {
+        try w.print(",\"_line\":{d}", .{n});
+    } else {
+        try w.writeAll(",\"_line\":null");
+    }
+    try w.writeAll(",\"_raw\":");
+    try json.writeJsonString(w, raw);
+    try w.writeAll(",\"_source\":");
+    try json.writeJsonString(w, source);
+    try w.writeAll(",\"_stage\":");
+    try json.writeJsonString(w, stage);
+    try w.writeAll("}\n");
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+test "formatRecord writes the sidecar fields in order" {
+    const record = try formatRecord(std.testing.allocator, "invalid JSON", 42, "{\"a\":", "data.jsonl", "jn-join");
+    defer std.testing.allocator.free(record);
+    try std.testing.expectEqualStrings(
+        "{\"_error\":\"invalid JSON\",\"_line\":42,\"_raw\":\"{\\\"a\\\":\",\"_source\":\"data.jsonl\",\"_stage\":\"jn-join\"}\n",
+        record,
+    );
+
+    const no_line = try formatRecord(std.testing.allocator, "parse failed", null, "", "<stdin>", "yaml");
+    defer std.testing.allocator.free(no_line);
+    try std.testing.expect(std.mem.indexOf(u8, no_line, "\"_line\":null") != null);
+}
+
+test "lineAt finds a line of a document" {
+    try std.testing.expectEqualStrings("b: [", lineAt("a: 1\nb: [\nc: 2\n", 2));
+    try std.testing.expectEqualStrings("", lineAt("a: 1\n", 7));
+}
+
+test "isJson accepts one value per line" {
+    try std.testing.expect(isJson(std.testing.allocator, "{\"a\":1}"));
+    try std.testing.expect(isJson(std.testing.allocator, "[1, 2]"));
+    try std.testing.expect(!isJson(std.testing.allocator, "{\"a\":"));
+    try std.testing.expect(!isJson(std.testing.allocator, "not json"));
+}
+
+test "ErrorSink.format matches formatRecord" {
+    const sink: ErrorSink = .{ .allocator = std.testing.allocator, .stage = "zq" };
+    var out: std.Io.Writer.Allocating = .init(std.testing.allocator);
+    defer out.deinit();
+    try sink.format(&out.writer, "Cannot index number with \"a\"", 3, "1");
+    const record = try formatRecord(std.testing.allocator, "Cannot index number with \"a\"", 3, "1", "<stdin>", "zq");
+    defer std.testing.allocator.free(record);
+    try std.testing.expectEqualStrings(record, out.written());
+}
+
+test "ErrorSink counts records without a file" {
+    var sink: ErrorSink = .{ .allocator = std.testing.allocator, .stage = "test", .max_errors = 2 };
+    try std.testing.expect(!sink.enabled());
+    try std.testing.expect(sink.counting());
+    sink.report("bad", 1, "x");
+    sink.report("bad", 2, "y");
+    try std.testing.expectEqual(@as(u64, 2), sink.count);
+}
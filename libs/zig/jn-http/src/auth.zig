@@ -0,0 +1,286 @@
+//! The `auth` block of HTTP profiles: credentials added to every request.
+//!
+//! | type        | Sends                                                    |
+//! |-------------|----------------------------------------------------------|
+//! | `basic`     | `Authorization: Basic` from `username` and `password`    |
+//! | `bearer`    | `Authorization: Bearer` with `token`                     |
+//! | `oauth2`    | `Authorization: Bearer` with a token from `token_url`    |
+//! | `aws_sigv4` | An AWS Signature Version 4 for `service` in `region`     |
+//!
+//! OAuth2 tokens are fetched on the first request and renewed when they
+//! expire; a 401 renews the token and sends the request once more (see
+//! oauth2.zig). SigV4 credentials and region the block leaves out come
+//! from `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`, `AWS_SESSION_TOKEN`
+//! and `AWS_REGION` (or `AWS_DEFAULT_REGION`).
+
+const std = @import("std");
+const client_mod = @import("client.zig");
+pub const oauth2 = @import("oauth2.zig");
+pub const sigv4 = @import("sigv4.zig");
+
+const Client = client_mod.Client;
+const Header = client_mod.Header;
+const Request = client_mod.Request;
+
+pub const Config = union(enum) {
+    basic: struct { username: []const u8, password: []const u8 },
+    bearer: []const u8,
+    oauth2: oauth2.Config,
+    aws_sigv4: sigv4.Config,
+
+    /// Read an `auth` block. Strings borrow from `value`; empty strings
+    /// (an unset `${VAR}`) count as missing.
+    pub fn fromJson(value: std.json.Value) !Config {
+        if (value != .object) return error.InvalidAuth;
+        const object = value.object;
+        const type_name = try getString(object, "type") orelse return error.InvalidAuth;
+        const kind = std.meta.stringToEnum(std.meta.Tag(Config), type_name) orelse return error.UnknownAuthType;
+        switch (kind) {
+            .basic => return .{ .basic = .{
+                .username = try required(object, "username"),
+                .password = try getString(object, "password") orelse "",
+            } },
+            .bearer => return .{ .bearer = try required(object, "token") },
+            .oauth2 => {
+                var config: oauth2.Config = .{ .token_url = try required(object, "token_url") };
+                if (try getString(object, "grant")) |name| {
+                    config.grant = std.meta.stringToEnum(oauth2.Grant, name) orelse return error.InvalidAuth;
+                }
+                if (try getString(object, "client_auth")) |name| {
+                    config.client_auth = std.meta.stringToEnum(oauth2.ClientAuth, name) orelse return error.InvalidAuth;
+                }
+                inline for (.{ "client_id", "client_secret", "scope", "audience", "refresh_token", "device_url" }) |name| {
+                    @field(config, name) = try getString(object, name);
+                }
+                switch (config.grant) {
+                    .client_credentials => if (config.client_id == null) return error.MissingAuthField,
+                    .refresh_token => if (config.refresh_token == null) return error.MissingAuthField,
+                    .device_code => if (config.client_id == null or config.device_url == null) return error.MissingAuthField,
+                }
+                return .{ .oauth2 = config };
+            },
+            .aws_sigv4 => return .{ .aws_sigv4 = .{
+                .service = try required(object, "service"),
+                .region = try getString(object, "region") orelse env("AWS_REGION") orelse env("AWS_DEFAULT_REGION") orelse return error.MissingAuthField,
+                .access_key_id = try getString(object, "access_key_id") orelse env("AWS_ACCESS_KEY_ID") orelse return error.MissingAuthField,
+                .secret_access_key = try getString(object, "secret_access_key") orelse env("AWS_SECRET_ACCESS_KEY") orelse return error.MissingAuthField,
+                .session_token = try getString(object, "session_token") orelse env("AWS_SESSION_TOKEN"),
+            } },
+        }
+    }
+};
+
+/// Adds a profile's credentials to requests. Several threads may share one.
+pub const Authenticator = struct {
+    allocator: std.mem.Allocator,
+    config: Config,
+    /// Where OAuth2 tokens are cached between runs; null keeps them in memory
+    cache_dir: ?[]const u8 = null,
+    /// Prefixes the device-code prompt and token errors
+    tool: []const u8 = "jn",
+
+    mutex: std.Thread.Mutex = .{},
+    token: ?oauth2.Token = null,
+    /// Owns `token`
+    token_arena: ?std.heap.ArenaAllocator = null,
+    cache_checked: bool = false,
+    cache_path: ?[]u8 = null,
+
+    pub fn init(allocator: std.mem.Allocator, config: Config) Authenticator {
+        return .{ .allocator = allocator, .config = config };
+    }
+
+    pub fn deinit(self: *Authenticator) void {
+        if (self.token_arena) |*arena| arena.deinit();
+        if (self.cache_path) |path| self.allocator.free(path);
+    }
+
+    /// `request.headers` followed by the credentials for `request`,
+    /// allocated with `arena`. May fetch an OAuth2 token with `client`.
+    pub fn headers(self: *Authenticator, arena: std.mem.Allocator, client: *Client, request: Request) ![]const Header {
+        var list: std.ArrayListUnmanaged(Header) = .empty;
+        try list.appendSlice(arena, request.headers);
+        switch (self.config) {
+            .basic => |basic| try list.append(arena, .{
+                .name = "Authorization",
+                .value = try basicCredentials(arena, basic.username, basic.password),
+            }),
+            .bearer => |token| try list.append(arena, .{
+                .name = "Authorization",
+                .value = try std.fmt.allocPrint(arena, "Bearer {s}", .{token}),
+            }),
+            .oauth2 => try list.append(arena, .{
+                .name = "Authorization",
+                .value = try std.fmt.allocPrint(arena, "Bearer {s}", .{try self.accessToken(arena, client)}),
+            }),
+            .aws_sigv4 => |config| {
+                // The client only sends a body with POST, PUT and PATCH
+                const body = if (request.method.requestHasBody()) request.body orelse "" else "";
+                try list.appendSlice(arena, try sigv4.sign(arena, config, @tagName(request.method), request.url, body, std.time.timestamp()));
+            },
+        }
+        return list.items;
+    }
+
+    /// Called when a request sent with `sent` got a 401. Forgets the OAuth2
+    /// token it carried, so the next request gets a new one, and returns
+    /// whether sending it again may help.
+    pub fn rejected(self: *Authenticator, sent: []const Header) bool {
+        if (self.config != .oauth2) return false;
+        self.mutex.lock();
+        defer self.mutex.unlock();
+        const token = self.token orelse return true;
+        for (sent) |h| {
+            if (std.ascii.eqlIgnoreCase(h.name, "authorization") and std.mem.endsWith(u8, h.value, token.access_token)) {
+                self.token.?.expires_at = 0;
+            }
+        }
+        return true;
+    }
+
+    /// The current OAuth2 access token, renewed when it is about to expire
+    fn accessToken(self: *Authenticator, arena: std.mem.Allocator, client: *Client) ![]const u8 {
+        self.mutex.lock();
+        defer self.mutex.unlock();
+        if (!self.cache_checked) {
+            self.cache_checked = true;
+            try self.loadCached();
+        }
+        if (self.token == null or !self.token.?.fresh(std.time.timestamp())) try self.renew(client);
+        return arena.dupe(u8, self.token.?.access_token);
+    }
+
+    fn loadCached(self: *Authenticator) !void {
+        const dir = self.cache_dir orelse return;
+        self.cache_path = try oauth2.cachePath(self.allocator, dir, self.config.oauth2);
+        var arena_state = std.heap.ArenaAllocator.init(self.allocator);
+        const token = oauth2.load(arena_state.allocator(), self.cache_path.?) orelse {
+            arena_state.deinit();
+            return;
+        };
+        self.setToken(arena_state, token);
+    }
+
+    /// Get a token with the refresh token when there is one, else with the
+    /// configured grant, and cache it
+    fn renew(self: *Authenticator, client: *Client) !void {
+        const config = self.config.oauth2;
+        var arena_state = std.heap.ArenaAllocator.init(self.allocator);
+        errdefer arena_state.deinit();
+        const arena = arena_state.allocator();
+
+        const refresh_token = if (self.token) |current| current.refresh_token else null;
+        const token = if (refresh_token) |rt|
+            oauth2.refresh(arena, client, config, self.tool, rt) catch try oauth2.grant(arena, client, config, self.tool)
+        else
+            try oauth2.grant(arena, client, config, self.tool);
+
+        if (self.cache_path) |path| {
+            oauth2.save(arena, path, token) catch |err| {
+                std.debug.print("{s}: warning: cannot cache the access token in {s}: {s}\n", .{ self.tool, path, @errorName(err) });
+            };
+        }
+        self.setToken(arena_state, token);
+    }
+
+    fn setToken(self: *Authenticator, arena_state: std.heap.ArenaAllocator, token: oauth2.Token) void {
+        if (self.token_arena) |*arena| arena.deinit();
+        self.token_arena = arena_state;
+        self.token = token;
+    }
+};
+
+/// `Basic` credentials for an Authorization header
+pub fn basicCredentials(allocator: std.mem.Allocator, username: []const u8, password: []const u8) ![]u8 {
+    const plain = try std.fmt.allocPrint(allocator, "{s}:{s}", .{ username, password });
+    defer allocator.free(plain);
+    const encoder = std.base64.standard.Encoder;
+    const out = try allocator.alloc(u8, "Basic ".len + encoder.calcSize(plain.len));
+    @memcpy(out[0.."Basic ".len], "Basic ");
+    _ = encoder.encode(out["Basic ".len..], plain);
+    return out;
+}
+
+fn getString(object: std.json.ObjectMap, key: []const u8) !?[]const u8 {
+    const value = object.get(key) orelse return null;
+    return switch (value) {
+        .null => null,
+        .string => |text| if (text.len > 0) text else null,
+        else => error.InvalidAuth,
+    };
+}
+
+fn required(object: std.json.ObjectMap, key: []const u8) ![]const u8 {
+    return try getString(object, key) orelse error.MissingAuthField;
+}
+
+fn env(name: []const u8) ?[]const u8 {
+    const value = std.posix.getenv(name) orelse return null;
+    return if (value.len > 0) value else null;
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+fn parseConfig(arena: std.mem.Allocator, text: []const u8) !Config {
+    return Config.fromJson(try std.json.parseFromSliceLeaky(std.json.Value, arena, text, .{}));
+}
+
+test "auth blocks" {
+    var arena_state = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena_state.deinit();
+    const arena = arena_state.allocator();
+
+    const basic = try parseConfig(arena, "{\"type\":\"basic\",\"username\":\"ada\",\"password\":\"pw\"}");
+    try std.testing.expectEqualStrings("ada", basic.basic.username);
+
+    const oauth = try parseConfig(arena,
+        \\{"type":"oauth2","token_url":"https://auth.test/token","client_id":"jn","client_secret":"s","scope":"read","client_auth":"basic"}
+    );
+    try std.testing.expectEqual(oauth2.Grant.client_credentials, oauth.oauth2.grant);
+    try std.testing.expectEqual(oauth2.ClientAuth.basic, oauth.oauth2.client_auth);
+    try std.testing.expectEqualStrings("read", oauth.oauth2.scope.?);
+
+    const sigv4_config = try parseConfig(arena,
+        \\{"type":"aws_sigv4","service":"execute-api","region":"eu-west-1","access_key_id":"AK","secret_access_key":"SK"}
+    );
+    try std.testing.expectEqualStrings("eu-west-1", sigv4_config.aws_sigv4.region);
+
+    try std.testing.expectError(error.UnknownAuthType, parseConfig(arena, "{\"type\":\"digest\"}"));
+    try std.testing.expectError(error.MissingAuthField, parseConfig(arena, "{\"type\":\"bearer\",\"token\":\"\"}"));
+    try std.testing.expectError(error.MissingAuthField, parseConfig(arena, "{\"type\":\"oauth2\",\"grant\":\"device_code\",\"token_url\":\"https://a/t\",\"client_id\":\"jn\"}"));
+    try std.testing.expectError(error.InvalidAuth, parseConfig(arena, "{\"type\":\"oauth2\",\"token_url\":\"https://a/t\",\"grant\":\"password\"}"));
+    try std.testing.expectError(error.InvalidAuth, parseConfig(arena, "\"basic\""));
+}
+
+test "basic and bearer headers" {
+    var arena_state = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena_state.deinit();
+    const arena = arena_state.allocator();
+    var client = Client.init(std.testing.allocator);
+    defer client.deinit();
+    const request: Request = .{ .url = "https://api.test/", .headers = &.{.{ .name = "Accept", .value = "application/json" }} };
+
+    var basic = Authenticator.init(std.testing.allocator, .{ .basic = .{ .username = "Aladdin", .password = "open sesame" } });
+    defer basic.deinit();
+    const headers = try basic.headers(arena, &client, request);
+    try std.testing.expectEqual(@as(usize, 2), headers.len);
+    try std.testing.expectEqualStrings("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==", headers[1].value);
+    try std.testing.expect(!basic.rejected(headers));
+
+    var bearer = Authenticator.init(std.testing.allocator, .{ .bearer = "t0ken" });
+    defer bearer.deinit();
+    try std.testing.expectEqualStrings("Bearer t0ken", (try bearer.headers(arena, &client, request))[1].value);
+}
+
+test "a rejected OAuth2 token is renewed" {
+    var auth = Authenticator.init(std.testing.allocator, .{ .oauth2 = .{ .token_url = "https://auth.test/token" } });
+    defer auth.deinit();
+    auth.setToken(.init(std.testing.allocator), .{ .access_token = "old", .expires_at = std.math.maxInt(i32) });
+
+    try std.testing.expect(auth.rejected(&.{.{ .name = "Authorization", .value = "Bearer other" }}));
+    try std.testing.expect(auth.token.?.fresh(std.time.timestamp()));
+    try std.testing.expect(auth.rejected(&.{.{ .name = "Authorization", .value = "Bearer old" }}));
+    try std.testing.expect(!auth.token.?.fresh(std.time.timestamp()));
+}
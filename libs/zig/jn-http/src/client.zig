@@ -0,0 +1,359 @@
+//! Requests on `std.http.Client`: methods, bodies, headers, redirects,
+//! gzip/deflate/zstd `Content-Encoding`, proxies from the environment and
+//! bodies streamed through a reader.
+//!
+//! ```zig
+//! var client = Client.init(allocator);
+//! defer client.deinit();
+//! const response = try client.send(.{ .url = "https://api.test/users", .timeout = 30 });
+//! defer response.deinit();
+//! if (response.status >= 400) return error.HttpError;
+//! _ = try (try response.reader()).streamRemaining(writer);
+//! ```
+
+const std = @import("std");
+const jn_core = @import("jn-core");
+const urls = @import("url.zig");
+const envelope = @import("envelope.zig");
+const auth = @import("auth.zig");
+
+pub const Header = std.http.Header;
+pub const Method = std.http.Method;
+
+pub const Request = struct {
+    method: Method = .GET,
+    url: []const u8,
+    /// Sent after `User-Agent: jn`, which a User-Agent header replaces
+    headers: []const Header = &.{},
+    /// Sent with POST, PUT and PATCH (empty when null)
+    body: ?[]const u8 = null,
+    /// Without this a 3xx response is returned as it is
+    follow_redirects: bool = true,
+    max_redirects: u16 = 10,
+    /// Seconds the server may stay silent before a read fails (bounded by
+    /// `max_timeout`)
+    timeout: ?f64 = null,
+    /// Adds credentials to the request (see auth.zig)
+    auth: ?*auth.Authenticator = null,
+};
+
+pub const Client = struct {
+    allocator: std.mem.Allocator,
+    http: std.http.Client,
+    /// Proxy settings from the environment, read before the first request
+    proxy_arena: std.heap.ArenaAllocator,
+    proxies_loaded: bool = false,
+    proxy_mutex: std.Thread.Mutex = .{},
+
+    pub fn init(allocator: std.mem.Allocator) Client {
+        return .{
+            .allocator = allocator,
+            .http = .{ .allocator = allocator },
+            .proxy_arena = .init(allocator),
+        };
+    }
+
+    pub fn deinit(self: *Client) void {
+        self.http.deinit();
+        self.proxy_arena.deinit();
+    }
+
+    /// Send `request` and read the head of the final response, following
+    /// redirects. The caller reads the body from `Response.reader` and
+    /// calls `Response.deinit`.
+    ///
+    /// Proxies come from `http_proxy`, `https_proxy` and `all_proxy` (or
+    /// their uppercase forms). Several threads may send at once.
+    ///
+    /// With `request.auth`, a 401 to an OAuth2 token renews the token and
+    /// sends the request once more.
+    pub fn send(self: *Client, request: Request) !*Response {
+        try self.loadProxies();
+        var renewed = false;
+        while (true) {
+            // Heap allocated: std's response and body reader point into it
+            const response = try self.allocator.create(Response);
+            response.* = .{ .allocator = self.allocator, .arena_state = .init(self.allocator) };
+            errdefer response.deinit();
+            var sent = request;
+            if (request.auth) |authenticator| {
+                sent.headers = try authenticator.headers(response.arena_state.allocator(), self, request);
+            }
+            try response.open(&self.http, sent);
+
+            if (response.status == 401 and !renewed) {
+                if (request.auth) |authenticator| {
+                    if (authenticator.rejected(sent.headers)) {
+                        response.deinit();
+                        renewed = true;
+                        continue;
+                    }
+                }
+            }
+            return response;
+        }
+    }
+
+    fn loadProxies(self: *Client) !void {
+        self.proxy_mutex.lock();
+        defer self.proxy_mutex.unlock();
+        if (self.proxies_loaded) return;
+        try self.http.initDefaultProxies(self.proxy_arena.allocator());
+        self.proxies_loaded = true;
+    }
+};
+
+pub const Response = struct {
+    allocator: std.mem.Allocator,
+    arena_state: std.heap.ArenaAllocator,
+    status: u16 = 0,
+    /// Where the final response came from, after redirects
+    url: []const u8 = "",
+    /// In the order received, owned by the response
+    headers: []const Header = &.{},
+
+    request: ?std.http.Client.Request = null,
+    raw: std.http.Client.Response = undefined,
+    body_reader: ?*std.Io.Reader = null,
+    transfer_buffer: [64]u8 = undefined,
+    decompress: std.http.Decompress = undefined,
+
+    pub fn deinit(self: *Response) void {
+        if (self.request) |*request| request.deinit();
+        self.arena_state.deinit();
+        self.allocator.destroy(self);
+    }
+
+    /// The value of the first header called `name` (case-insensitive)
+    pub fn header(self: *const Response, name: []const u8) ?[]const u8 {
+        for (self.headers) |h| {
+            if (std.ascii.eqlIgnoreCase(h.name, name)) return h.value;
+        }
+        return null;
+    }
+
+    /// The body, decoded according to its Content-Encoding
+    pub fn reader(self: *Response) !*std.Io.Reader {
+        if (self.body_reader) |body| return body;
+        const arena = self.arena_state.allocator();
+        const decompress_buffer: []u8 = switch (self.raw.head.content_encoding) {
+            .identity => self.transfer_buffer[0..0],
+            .deflate, .gzip => try arena.alloc(u8, std.compress.flate.max_window_len),
+            .zstd => try arena.alloc(u8, jn_core.ZSTD_WINDOW_SIZE),
+            .compress => return error.UnsupportedContentEncoding,
+        };
+        self.body_reader = self.raw.readerDecompressing(&self.transfer_buffer, &self.decompress, decompress_buffer);
+        return self.body_reader.?;
+    }
+
+    /// Read the rest of the body into memory owned by `allocator`
+    pub fn readAll(self: *Response, allocator: std.mem.Allocator) ![]u8 {
+        const body = try self.reader();
+        return body.allocRemaining(allocator, .unlimited);
+    }
+
+    /// The response's `_http` envelope as JSON, owned by `allocator`
+    pub fn meta(self: *const Response, allocator: std.mem.Allocator) ![]u8 {
+        return envelope.meta(allocator, self.status, self.url, self.headers);
+    }
+
+    fn open(self: *Response, client: *std.http.Client, request: Request) !void {
+        const arena = self.arena_state.allocator();
+        var method = request.method;
+        var body = request.body;
+        var url = try arena.dupe(u8, request.url);
+        // Credentials only go to the origin they were written for
+        var same_origin = true;
+        var redirects: u16 = 0;
+
+        while (true) {
+            const uri = std.Uri.parse(url) catch return error.InvalidUrl;
+            var standard: std.http.Client.Request.Headers = .{ .user_agent = .{ .override = "jn" } };
+            const extra = try splitHeaders(arena, request.headers, &standard, same_origin);
+
+            self.request = try client.request(method, uri, .{
+                .redirect_behavior = .unhandled,
+                .headers = standard,
+                .extra_headers = extra,
+            });
+            const req = &self.request.?;
+            if (request.timeout) |seconds| try setTimeout(req, seconds);
+
+            const started = std.time.nanoTimestamp();
+            sendBody(req, method, body) catch |err| return timedOut(err, request.timeout, started);
+            // Redirects are followed here, so std needs no redirect buffer
+            var redirect_buffer: [0]u8 = undefined;
+            self.raw = req.receiveHead(&redirect_buffer) catch |err| return timedOut(err, request.timeout, started);
+
+            const status: u16 = @intFromEnum(self.raw.head.status);
+            const location = self.raw.head.location;
+            if (request.follow_redirects and isRedirect(status) and location != null) {
+                if (redirects == request.max_redirects) return error.TooManyHttpRedirects;
+                redirects += 1;
+                const next = try urls.resolve(arena, url, location.?);
+                same_origin = same_origin and urls.sameOrigin(url, next);
+                // 303, and 301/302 after a POST, continue as a GET
+                if (status == 303 or (status != 307 and status != 308 and method == .POST)) {
+                    if (method != .HEAD) method = .GET;
+                    body = null;
+                }
+                url = next;
+                req.deinit();
+                self.request = null;
+                continue;
+            }
+
+            self.status = status;
+            self.url = url;
+            // Copied now: the head is overwritten once the body is read
+            var headers: std.ArrayListUnmanaged(Header) = .empty;
+            var it = self.raw.head.iterateHeaders();
+            while (it.next()) |h| {
+                try headers.append(arena, .{ .name = try arena.dupe(u8, h.name), .value = try arena.dupe(u8, h.value) });
+            }
+            self.headers = headers.items;
+            return;
+        }
+    }
+};
+
+/// Whether a failed request is worth retrying: the connection failed,
+/// dropped or timed out, rather than the request being invalid.
+pub fn isTransientError(err: anyerror) bool {
+    return switch (err) {
+        error.ConnectionRefused,
+        error.ConnectionResetByPeer,
+        error.ConnectionTimedOut,
+        error.NetworkUnreachable,
+        error.HostUnreachable,
+        error.TemporaryNameServerFailure,
+        error.NameServerFailure,
+        error.TlsInitializationFailed,
+        error.HttpConnectionClosing,
+        error.ReadFailed,
+        error.WriteFailed,
+        error.Timeout,
+        => true,
+        else => false,
+    };
+}
+
+fn isRedirect(status: u16) bool {
+    return switch (status) {
+        301, 302, 303, 307, 308 => true,
+        else => false,
+    };
+}
+
+/// Move the headers std.http.Client writes itself into `standard` and
+/// return the rest. Authorization and cookies are dropped after a redirect
+/// to another origin.
+fn splitHeaders(arena: std.mem.Allocator, headers: []const Header, standard: *std.http.Client.Request.Headers, same_origin: bool) ![]const Header {
+    var extra: std.ArrayListUnmanaged(Header) = .empty;
+    for (headers) |h| {
+        const value: std.http.Client.Request.Headers.Value = .{ .override = h.value };
+        if (std.ascii.eqlIgnoreCase(h.name, "authorization")) {
+            standard.authorization = if (same_origin) value else .omit;
+        } else if (std.ascii.eqlIgnoreCase(h.name, "cookie")) {
+            if (same_origin) try extra.append(arena, h);
+        } else if (std.ascii.eqlIgnoreCase(h.name, "user-agent")) {
+            standard.user_agent = value;
+        } else if (std.ascii.eqlIgnoreCase(h.name, "content-type")) {
+            standard.content_type = value;
+        } else if (std.ascii.eqlIgnoreCase(h.name, "accept-encoding")) {
+            standard.accept_encoding = value;
+        } else if (std.ascii.eqlIgnoreCase(h.name, "host")) {
+            standard.host = value;
+        } else if (std.ascii.eqlIgnoreCase(h.name, "connection")) {
+            standard.connection = value;
+        } else {
+            try extra.append(arena, h);
+        }
+    }
+    return extra.items;
+}
+
+fn sendBody(req: *std.http.Client.Request, method: Method, body: ?[]const u8) !void {
+    if (!method.requestHasBody()) return req.sendBodiless();
+    const bytes = body orelse "";
+    req.transfer_encoding = .{ .content_length = bytes.len };
+    var buffer: [1024]u8 = undefined;
+    var writer = try req.sendBody(&buffer);
+    try writer.writer.writeAll(bytes);
+    try writer.end();
+}
+
+/// Longest timeout a request can have: one day
+pub const max_timeout: f64 = 24 * 60 * 60;
+
+/// `seconds` as a socket timeout in microseconds, between 1ms and
+/// `max_timeout` (the longest for anything not finite)
+fn timeoutMicros(seconds: f64) i64 {
+    const bounded = if (std.math.isFinite(seconds)) std.math.clamp(seconds, 0.001, max_timeout) else max_timeout;
+    return @intFromFloat(bounded * std.time.us_per_s);
+}
+
+/// Make reads and writes on the request's connection fail after `seconds`
+/// without progress.
+fn setTimeout(req: *std.http.Client.Request, seconds: f64) !void {
+    const connection = req.connection orelse return;
+    const micros = timeoutMicros(seconds);
+    const timeout: std.posix.timeval = .{
+        .sec = @intCast(@divTrunc(micros, std.time.us_per_s)),
+        .usec = @intCast(@rem(micros, std.time.us_per_s)),
+    };
+    const socket = connection.stream_reader.getStream().handle;
+    try std.posix.setsockopt(socket, std.posix.SOL.SOCKET, std.posix.SO.RCVTIMEO, std.mem.asBytes(&timeout));
+    try std.posix.setsockopt(socket, std.posix.SOL.SOCKET, std.posix.SO.SNDTIMEO, std.mem.asBytes(&timeout));
+}
+
+/// `error.Timeout` when a request failed after its timeout ran out, else `err`
+fn timedOut(err: anyerror, timeout: ?f64, started: i128) anyerror {
+    const seconds = timeout orelse return err;
+    const elapsed: f64 = @floatFromInt(std.time.nanoTimestamp() - started);
+    return if (elapsed >= seconds * std.time.ns_per_s) error.Timeout else err;
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+test "standard headers are moved out of the extra headers" {
+    var arena_state = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena_state.deinit();
+    const headers = [_]Header{
+        .{ .name = "Authorization", .value = "Bearer t" },
+        .{ .name = "Content-Type", .value = "application/json" },
+        .{ .name = "Cookie", .value = "session=1" },
+        .{ .name = "X-Api-Version", .value = "2" },
+    };
+
+    var standard: std.http.Client.Request.Headers = .{};
+    const extra = try splitHeaders(arena_state.allocator(), &headers, &standard, true);
+    try std.testing.expectEqualStrings("Bearer t", standard.authorization.override);
+    try std.testing.expectEqualStrings("application/json", standard.content_type.override);
+    try std.testing.expectEqual(@as(usize, 2), extra.len);
+
+    var elsewhere: std.http.Client.Request.Headers = .{};
+    const forwarded = try splitHeaders(arena_state.allocator(), &headers, &elsewhere, false);
+    try std.testing.expect(elsewhere.authorization == .omit);
+    try std.testing.expectEqual(@as(usize, 1), forwarded.len);
+    try std.testing.expectEqualStrings("X-Api-Version", forwarded[0].name);
+}
+
+test "socket timeouts are bounded" {
+    try std.testing.expectEqual(@as(i64, 2_500_000), timeoutMicros(2.5));
+    try std.testing.expectEqual(@as(i64, 1000), timeoutMicros(0));
+    try std.testing.expectEqual(@as(i64, 1000), timeoutMicros(-3));
+    const longest: i64 = @intFromFloat(max_timeout * std.time.us_per_s);
+    try std.testing.expectEqual(longest, timeoutMicros(1e300));
+    try std.testing.expectEqual(longest, timeoutMicros(std.math.inf(f64)));
+    try std.testing.expectEqual(longest, timeoutMicros(std.math.nan(f64)));
+}
+
+test "transient errors" {
+    try std.testing.expect(isTransientError(error.ConnectionRefused));
+    try std.testing.expect(isTransientError(error.Timeout));
+    try std.testing.expect(!isTransientError(error.InvalidUrl));
+    try std.testing.expect(!isTransientError(error.UnknownHostName));
+}
@@ -0,0 +1,70 @@
+//! The opt-in `_http` envelope: response metadata added to every record
+//! read from a response.
+//!
+//! ```json
+//! {"_http":{"status":200,"url":"https://api.test/users","headers":{"content-type":"application/json"}},"id":1}
+//! ```
+
+const std = @import("std");
+
+/// The `_http` value for a response as JSON. Header names are lowercased
+/// and repeated headers joined with ", ".
+pub fn meta(allocator: std.mem.Allocator, status: u16, url: []const u8, headers: []const std.http.Header) ![]u8 {
+    var arena_state = std.heap.ArenaAllocator.init(allocator);
+    defer arena_state.deinit();
+    const arena = arena_state.allocator();
+
+    var fields = std.json.ObjectMap.init(arena);
+    for (headers) |header| {
+        const entry = try fields.getOrPut(try std.ascii.allocLowerString(arena, header.name));
+        entry.value_ptr.* = .{ .string = if (entry.found_existing)
+            try std.fmt.allocPrint(arena, "{s}, {s}", .{ entry.value_ptr.string, header.value })
+        else
+            header.value };
+    }
+    return std.json.Stringify.valueAlloc(allocator, .{
+        .status = status,
+        .url = url,
+        .headers = std.json.Value{ .object = fields },
+    }, .{});
+}
+
+/// Write one NDJSON record with `"_http": meta_json` as its first field.
+/// Records that aren't objects are written unchanged.
+pub fn write(writer: *std.Io.Writer, meta_json: []const u8, record: []const u8) std.Io.Writer.Error!void {
+    const trimmed = std.mem.trim(u8, record, " \t");
+    if (trimmed.len < 2 or trimmed[0] != '{') return writer.print("{s}\n", .{record});
+    const rest = std.mem.trimLeft(u8, trimmed[1..], " \t");
+    const separator = if (rest[0] == '}') "" else ",";
+    try writer.print("{{\"_http\":{s}{s}{s}\n", .{ meta_json, separator, rest });
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+test "meta lowercases and joins headers" {
+    const json = try meta(std.testing.allocator, 200, "https://api.test/u", &.{
+        .{ .name = "Content-Type", .value = "application/json" },
+        .{ .name = "Set-Cookie", .value = "a=1" },
+        .{ .name = "set-cookie", .value = "b=2" },
+    });
+    defer std.testing.allocator.free(json);
+    try std.testing.expectEqualStrings(
+        \\{"status":200,"url":"https://api.test/u","headers":{"content-type":"application/json","set-cookie":"a=1, b=2"}}
+    , json);
+}
+
+test "write adds _http to objects only" {
+    var out: std.Io.Writer.Allocating = .init(std.testing.allocator);
+    defer out.deinit();
+    try write(&out.writer, "{\"status\":200}", "{\"id\":1}");
+    try write(&out.writer, "{\"status\":200}", "{}");
+    try write(&out.writer, "{\"status\":200}", "[1,2]");
+    try std.testing.expectEqualStrings(
+        \\{"_http":{"status":200},"id":1}
+        \\{"_http":{"status":200}}
+        \\[1,2]
+        \\
+    , out.written());
+}
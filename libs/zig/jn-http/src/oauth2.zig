@@ -0,0 +1,374 @@
+//! OAuth2 access tokens: the client-credentials, refresh-token and
+//! device-code grants, and a token cache on disk.
+//!
+//! ```json
+//! "auth": {
+//!   "type": "oauth2",
+//!   "grant": "client_credentials",
+//!   "token_url": "https://auth.example.com/oauth/token",
+//!   "client_id": "${CLIENT_ID}",
+//!   "client_secret": "${CLIENT_SECRET}",
+//!   "scope": "read:users"
+//! }
+//! ```
+//!
+//! The device-code grant prints a URL and a code for the user to approve in
+//! a browser, then polls `token_url` until they do.
+//!
+//! Tokens are cached one file per grant, token URL, client and scope (mode
+//! 0600), along with the refresh token when the server sends one, so later
+//! runs skip the grant until the token expires and then try the refresh
+//! token first.
+
+const std = @import("std");
+const client_mod = @import("client.zig");
+const auth = @import("auth.zig");
+
+const Client = client_mod.Client;
+const Header = client_mod.Header;
+
+pub const Grant = enum { client_credentials, refresh_token, device_code };
+
+/// How the client proves itself to the token endpoint
+pub const ClientAuth = enum {
+    /// `client_id` and `client_secret` form fields
+    body,
+    /// HTTP basic (`client_secret_basic`)
+    basic,
+};
+
+pub const Config = struct {
+    grant: Grant = .client_credentials,
+    token_url: []const u8,
+    client_id: ?[]const u8 = null,
+    client_secret: ?[]const u8 = null,
+    /// Space-separated scopes
+    scope: ?[]const u8 = null,
+    audience: ?[]const u8 = null,
+    /// Where the refresh-token grant starts
+    refresh_token: ?[]const u8 = null,
+    /// The device-code grant's device authorization endpoint
+    device_url: ?[]const u8 = null,
+    client_auth: ClientAuth = .body,
+};
+
+pub const Token = struct {
+    access_token: []const u8,
+    refresh_token: ?[]const u8 = null,
+    /// Unix seconds; null when the server didn't say
+    expires_at: ?i64 = null,
+
+    /// Seconds before `expires_at` at which a token is renewed
+    pub const margin = 60;
+
+    pub fn fresh(self: Token, now: i64) bool {
+        const expires_at = self.expires_at orelse return true;
+        return now + margin < expires_at;
+    }
+};
+
+const Field = [2][]const u8;
+
+/// Get a new token with the configured grant. `tool` prefixes the messages
+/// printed for the device-code prompt and failed token requests.
+pub fn grant(allocator: std.mem.Allocator, client: *Client, config: Config, tool: []const u8) !Token {
+    switch (config.grant) {
+        .client_credentials => {
+            var fields: std.ArrayListUnmanaged(Field) = .empty;
+            try fields.append(allocator, .{ "grant_type", "client_credentials" });
+            if (config.scope) |scope| try fields.append(allocator, .{ "scope", scope });
+            if (config.audience) |audience| try fields.append(allocator, .{ "audience", audience });
+            return requestToken(allocator, client, config, tool, fields.items);
+        },
+        .refresh_token => return refresh(allocator, client, config, tool, config.refresh_token orelse return error.MissingRefreshToken),
+        .device_code => return deviceCode(allocator, client, config, tool),
+    }
+}
+
+/// Exchange `refresh_token` for a new token. Servers that don't rotate
+/// refresh tokens leave them out, so the old one is kept.
+pub fn refresh(allocator: std.mem.Allocator, client: *Client, config: Config, tool: []const u8, refresh_token: []const u8) !Token {
+    var token = try requestToken(allocator, client, config, tool, &.{
+        .{ "grant_type", "refresh_token" },
+        .{ "refresh_token", refresh_token },
+    });
+    if (token.refresh_token == null) token.refresh_token = try allocator.dupe(u8, refresh_token);
+    return token;
+}
+
+fn requestToken(allocator: std.mem.Allocator, client: *Client, config: Config, tool: []const u8, fields: []const Field) !Token {
+    const reply = try post(allocator, client, config, config.token_url, fields);
+    if (reply.status >= 400) return failed(tool, config.token_url, reply);
+    return parseToken(allocator, reply.body, std.time.timestamp());
+}
+
+/// RFC 8628: ask for a device code, show it to the user and poll until they
+/// approve it
+fn deviceCode(allocator: std.mem.Allocator, client: *Client, config: Config, tool: []const u8) !Token {
+    const device_url = config.device_url orelse return error.MissingDeviceUrl;
+    var fields: std.ArrayListUnmanaged(Field) = .empty;
+    if (config.scope) |scope| try fields.append(allocator, .{ "scope", scope });
+    const reply = try post(allocator, client, config, device_url, fields.items);
+    if (reply.status >= 400 or reply.body != .object) return failed(tool, device_url, reply);
+
+    const info = reply.body.object;
+    const device_code = getString(info, "device_code") orelse return error.InvalidTokenResponse;
+    const user_code = getString(info, "user_code") orelse return error.InvalidTokenResponse;
+    // Google calls it verification_url
+    const verification = getString(info, "verification_uri_complete") orelse
+        getString(info, "verification_uri") orelse
+        getString(info, "verification_url") orelse return error.InvalidTokenResponse;
+    var interval = getSeconds(info, "interval") orelse 5;
+    const deadline = std.time.timestamp() + (getSeconds(info, "expires_in") orelse 900);
+    std.debug.print("{s}: to authorize, open {s} and enter the code {s}\n", .{ tool, verification, user_code });
+
+    while (true) {
+        std.Thread.sleep(@as(u64, @intCast(interval)) * std.time.ns_per_s);
+        const poll = try post(allocator, client, config, config.token_url, &.{
+            .{ "grant_type", "urn:ietf:params:oauth:grant-type:device_code" },
+            .{ "device_code", device_code },
+        });
+        if (poll.status < 400) return parseToken(allocator, poll.body, std.time.timestamp());
+
+        const code = if (poll.body == .object) getString(poll.body.object, "error") orelse "" else "";
+        if (std.mem.eql(u8, code, "slow_down")) {
+            interval += 5;
+        } else if (!std.mem.eql(u8, code, "authorization_pending")) {
+            return failed(tool, config.token_url, poll);
+        }
+        if (std.time.timestamp() >= deadline) {
+            std.debug.print("{s}: the device code expired before it was approved\n", .{tool});
+            return error.DeviceCodeExpired;
+        }
+    }
+}
+
+const Reply = struct { status: u16, body: std.json.Value };
+
+/// POST `fields` as a form with the client's credentials and parse the JSON
+/// reply (null when it isn't JSON)
+fn post(allocator: std.mem.Allocator, client: *Client, config: Config, url: []const u8, fields: []const Field) !Reply {
+    var form: std.Io.Writer.Allocating = .init(allocator);
+    for (fields) |field| try writeField(&form.writer, field);
+    var headers: std.ArrayListUnmanaged(Header) = .empty;
+    try headers.append(allocator, .{ .name = "Content-Type", .value = "application/x-www-form-urlencoded" });
+    try headers.append(allocator, .{ .name = "Accept", .value = "application/json" });
+    if (config.client_id) |id| {
+        switch (config.client_auth) {
+            .body => {
+                try writeField(&form.writer, .{ "client_id", id });
+                if (config.client_secret) |secret| try writeField(&form.writer, .{ "client_secret", secret });
+            },
+            .basic => try headers.append(allocator, .{
+                .name = "Authorization",
+                .value = try auth.basicCredentials(allocator, id, config.client_secret orelse ""),
+            }),
+        }
+    }
+
+    const response = try client.send(.{ .method = .POST, .url = url, .headers = headers.items, .body = form.written() });
+    defer response.deinit();
+    const text = try response.readAll(allocator);
+    return .{
+        .status = response.status,
+        .body = std.json.parseFromSliceLeaky(std.json.Value, allocator, text, .{}) catch .null,
+    };
+}
+
+fn writeField(w: *std.Io.Writer, field: Field) !void {
+    if (w.end > 0) try w.writeByte('&');
+    try writeFormEncoded(w, field[0]);
+    try w.writeByte('=');
+    try writeFormEncoded(w, field[1]);
+}
+
+fn writeFormEncoded(w: *std.Io.Writer, text: []const u8) !void {
+    for (text) |c| {
+        if (std.ascii.isAlphanumeric(c) or std.mem.indexOfScalar(u8, "-._~", c) != null) {
+            try w.writeByte(c);
+        } else if (c == ' ') {
+            try w.writeByte('+');
+        } else {
+            try w.print("%{X:0>2}", .{c});
+        }
+    }
+}
+
+/// Print why a token request failed, from the OAuth2 `error` and
+/// `error_description` when the server sent them
+fn failed(tool: []const u8, url: []const u8, reply: Reply) error{TokenRequestFailed} {
+    const object = if (reply.body == .object) reply.body.object else null;
+    const code = if (object) |o| getString(o, "error") orelse "" else "";
+    const description = if (object) |o| getString(o, "error_description") orelse "" else "";
+    std.debug.print("{s}: token request to {s} failed: HTTP {d} {s}{s}{s}\n", .{
+        tool,
+        url,
+        reply.status,
+        code,
+        if (description.len > 0) ": " else "",
+        description,
+    });
+    return error.TokenRequestFailed;
+}
+
+/// The token in a token endpoint's reply, received at `now`
+pub fn parseToken(allocator: std.mem.Allocator, body: std.json.Value, now: i64) !Token {
+    if (body != .object) return error.InvalidTokenResponse;
+    const access_token = getString(body.object, "access_token") orelse return error.InvalidTokenResponse;
+    const refresh_token = getString(body.object, "refresh_token");
+    const expires_in = getSeconds(body.object, "expires_in");
+    return .{
+        .access_token = try allocator.dupe(u8, access_token),
+        .refresh_token = if (refresh_token) |token| try allocator.dupe(u8, token) else null,
+        .expires_at = if (expires_in) |seconds| now + seconds else null,
+    };
+}
+
+fn getString(object: std.json.ObjectMap, key: []const u8) ?[]const u8 {
+    const value = object.get(key) orelse return null;
+    return if (value == .string and value.string.len > 0) value.string else null;
+}
+
+/// Longest duration accepted from a server; anything beyond it (or negative,
+/// or not a finite number) counts as absent so later arithmetic can't overflow
+const max_seconds: i64 = std.math.maxInt(u32);
+
+/// A number of seconds, as a number or a string (some servers quote it)
+fn getSeconds(object: std.json.ObjectMap, key: []const u8) ?i64 {
+    const value = object.get(key) orelse return null;
+    const seconds: i64 = switch (value) {
+        .integer => |n| n,
+        .float => |f| if (std.math.isFinite(f) and f >= 0 and f <= @as(f64, @floatFromInt(max_seconds))) @intFromFloat(f) else return null,
+        .string, .number_string => |text| std.fmt.parseInt(i64, text, 10) catch return null,
+        else => return null,
+    };
+    return if (seconds >= 0 and seconds <= max_seconds) seconds else null;
+}
+
+// ============================================================================
+// Token cache
+// ============================================================================
+
+/// The cache file in `dir` for tokens of `config`: one per grant, token URL,
+/// client, scope, audience and starting refresh token.
+pub fn cachePath(allocator: std.mem.Allocator, dir: []const u8, config: Config) ![]u8 {
+    var hasher = std.crypto.hash.sha2.Sha256.init(.{});
+    const parts = [_]?[]const u8{ @tagName(config.grant), config.token_url, config.client_id, config.scope, config.audience, config.refresh_token };
+    for (parts) |part| {
+        hasher.update(part orelse "");
+        hasher.update("\x00");
+    }
+    const digest = hasher.finalResult();
+    const name = std.fmt.bytesToHex(digest[0..16], .lower);
+    return std.fmt.allocPrint(allocator, "{s}/{s}.json", .{ dir, &name });
+}
+
+/// The token cached at `path`, or null when there is none
+pub fn load(allocator: std.mem.Allocator, path: []const u8) ?Token {
+    const text = std.fs.cwd().readFileAlloc(allocator, path, 1 << 16) catch return null;
+    const value = std.json.parseFromSliceLeaky(std.json.Value, allocator, text, .{}) catch return null;
+    if (value != .object) return null;
+    const expires_at = value.object.get("expires_at");
+    return .{
+        .access_token = getString(value.object, "access_token") orelse return null,
+        .refresh_token = getString(value.object, "refresh_token"),
+        .expires_at = if (expires_at != null and expires_at.? == .integer) expires_at.?.integer else null,
+    };
+}
+
+/// Write `token` to `path` (mode 0600), replacing the file in one step
+pub fn save(allocator: std.mem.Allocator, path: []const u8, token: Token) !void {
+    if (std.fs.path.dirname(path)) |dir| try std.fs.cwd().makePath(dir);
+    const json = try std.json.Stringify.valueAlloc(allocator, token, .{ .emit_null_optional_fields = false });
+    defer allocator.free(json);
+
+    const tmp_path = try std.fmt.allocPrint(allocator, "{s}.{x}.tmp", .{ path, std.crypto.random.int(u32) });
+    defer allocator.free(tmp_path);
+    {
+        const file = try std.fs.cwd().createFile(tmp_path, .{ .mode = 0o600 });
+        defer file.close();
+        try file.writeAll(json);
+    }
+    std.fs.cwd().rename(tmp_path, path) catch |err| {
+        std.fs.cwd().deleteFile(tmp_path) catch {};
+        return err;
+    };
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+test "token expiry" {
+    var arena_state = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena_state.deinit();
+    const arena = arena_state.allocator();
+    const body = try std.json.parseFromSliceLeaky(std.json.Value, arena,
+        \\{"access_token":"abc","token_type":"Bearer","expires_in":"3600","refresh_token":"r1"}
+    , .{});
+
+    const token = try parseToken(arena, body, 1000);
+    try std.testing.expectEqualStrings("abc", token.access_token);
+    try std.testing.expectEqualStrings("r1", token.refresh_token.?);
+    try std.testing.expectEqual(@as(?i64, 4600), token.expires_at);
+    try std.testing.expect(token.fresh(4000));
+    try std.testing.expect(!token.fresh(4550));
+    try std.testing.expect((Token{ .access_token = "x" }).fresh(std.math.maxInt(i32)));
+
+    for ([_][]const u8{ "1e300", "-5", "99999999999999999999", "\"-1\"" }) |bad| {
+        const text = try std.fmt.allocPrint(arena, "{{\"access_token\":\"abc\",\"expires_in\":{s}}}", .{bad});
+        const odd = try std.json.parseFromSliceLeaky(std.json.Value, arena, text, .{});
+        try std.testing.expectEqual(@as(?i64, null), (try parseToken(arena, odd, 1000)).expires_at);
+    }
+    const fractional = try std.json.parseFromSliceLeaky(std.json.Value, arena, "{\"access_token\":\"abc\",\"expires_in\":59.5}", .{});
+    try std.testing.expectEqual(@as(?i64, 1059), (try parseToken(arena, fractional, 1000)).expires_at);
+
+    const empty = try std.json.parseFromSliceLeaky(std.json.Value, arena, "{\"error\":\"invalid_client\"}", .{});
+    try std.testing.expectError(error.InvalidTokenResponse, parseToken(arena, empty, 0));
+}
+
+test "cache path depends on client and scope" {
+    const allocator = std.testing.allocator;
+    const config: Config = .{ .token_url = "https://auth.test/token", .client_id = "jn", .scope = "read" };
+    const a = try cachePath(allocator, "/cache", config);
+    defer allocator.free(a);
+    const b = try cachePath(allocator, "/cache", config);
+    defer allocator.free(b);
+    var other = config;
+    other.scope = "write";
+    const c = try cachePath(allocator, "/cache", other);
+    defer allocator.free(c);
+
+    try std.testing.expectEqualStrings(a, b);
+    try std.testing.expect(!std.mem.eql(u8, a, c));
+    try std.testing.expect(std.mem.startsWith(u8, a, "/cache/") and std.mem.endsWith(u8, a, ".json"));
+}
+
+test "cached tokens round-trip" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var arena_state = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena_state.deinit();
+    const arena = arena_state.allocator();
+
+    const dir = try tmp.dir.realpathAlloc(arena, ".");
+    const path = try std.fmt.allocPrint(arena, "{s}/auth/token.json", .{dir});
+    try std.testing.expect(load(arena, path) == null);
+
+    try save(arena, path, .{ .access_token = "abc", .refresh_token = "r1", .expires_at = 4600 });
+    const token = load(arena, path).?;
+    try std.testing.expectEqualStrings("abc", token.access_token);
+    try std.testing.expectEqualStrings("r1", token.refresh_token.?);
+    try std.testing.expectEqual(@as(?i64, 4600), token.expires_at);
+
+    const stat = try tmp.dir.statFile("auth/token.json");
+    try std.testing.expectEqual(@as(std.fs.File.Mode, 0o600), stat.mode & 0o777);
+}
+
+test "form encoding" {
+    var out: std.Io.Writer.Allocating = .init(std.testing.allocator);
+    defer out.deinit();
+    try writeField(&out.writer, .{ "grant_type", "client_credentials" });
+    try writeField(&out.writer, .{ "scope", "read write:all" });
+    try std.testing.expectEqualStrings("grant_type=client_credentials&scope=read+write%3Aall", out.written());
+}
@@ -0,0 +1,43 @@
+//! JN HTTP Library
+//!
+//! HTTP requests for JN tools on `std.http.Client`, so reading a REST API
+//! needs no curl. Responses expose their status and headers, and bodies
+//! stream through a reader straight into a format plugin.
+//!
+//! Features:
+//! - Any method, with a request body and headers
+//! - Redirects, dropping credentials when they leave the origin
+//! - gzip, deflate and zstd `Content-Encoding`
+//! - Proxies from `http_proxy`, `https_proxy` and `all_proxy`
+//! - A read timeout per request
+//! - Basic, bearer, OAuth2 and AWS SigV4 credentials (see auth.zig)
+//! - The opt-in `_http` envelope: status, URL and headers on every record
+//!
+//! Retries and rate limits are left to the caller (see jn-core's `retry`),
+//! which can inspect each response before deciding.
+
+pub const client = @import("client.zig");
+pub const url = @import("url.zig");
+pub const envelope = @import("envelope.zig");
+pub const auth = @import("auth.zig");
+pub const oauth2 = @import("oauth2.zig");
+pub const sigv4 = @import("sigv4.zig");
+
+// Re-export main types
+pub const Client = client.Client;
+pub const Request = client.Request;
+pub const Response = client.Response;
+pub const Header = client.Header;
+pub const Method = client.Method;
+pub const max_timeout = client.max_timeout;
+pub const Authenticator = auth.Authenticator;
+pub const AuthConfig = auth.Config;
+
+// Re-export main functions
+pub const isTransientError = client.isTransientError;
+pub const resolveUrl = url.resolve;
+pub const sameOrigin = url.sameOrigin;
+
+test {
+    @import("std").testing.refAllDecls(@This());
+}
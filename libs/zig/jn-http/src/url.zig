@@ -0,0 +1,68 @@
+//! URL helpers for following links and redirects.
+
+const std = @import("std");
+
+/// Resolve a link against the URL it came from: absolute URLs as they are,
+/// `//host/path` against the scheme, `/path` against the origin, `?query`
+/// against the path and anything else against the path's directory. The
+/// result is always allocated.
+pub fn resolve(allocator: std.mem.Allocator, base: []const u8, target: []const u8) ![]u8 {
+    if (std.mem.indexOf(u8, target, "://") != null) return allocator.dupe(u8, target);
+    const scheme_end = (std.mem.indexOf(u8, base, "://") orelse return allocator.dupe(u8, target)) + 3;
+    if (std.mem.startsWith(u8, target, "//")) {
+        return std.fmt.allocPrint(allocator, "{s}{s}", .{ base[0 .. scheme_end - 2], target[2..] });
+    }
+    if (std.mem.startsWith(u8, target, "?")) {
+        const path_end = std.mem.indexOfAny(u8, base, "?#") orelse base.len;
+        return std.fmt.allocPrint(allocator, "{s}{s}", .{ base[0..path_end], target });
+    }
+    const origin_end = originEnd(base, scheme_end);
+    if (std.mem.startsWith(u8, target, "/")) {
+        return std.fmt.allocPrint(allocator, "{s}{s}", .{ base[0..origin_end], target });
+    }
+    // Relative to the current path's directory
+    const path_end = std.mem.indexOfAnyPos(u8, base, origin_end, "?#") orelse base.len;
+    const dir_end = if (std.mem.lastIndexOfScalar(u8, base[origin_end..path_end], '/')) |slash| origin_end + slash + 1 else origin_end;
+    const separator = if (dir_end == origin_end) "/" else "";
+    return std.fmt.allocPrint(allocator, "{s}{s}{s}", .{ base[0..dir_end], separator, target });
+}
+
+/// Whether two URLs share scheme, host and port (compared as written).
+pub fn sameOrigin(a: []const u8, b: []const u8) bool {
+    return std.ascii.eqlIgnoreCase(origin(a), origin(b));
+}
+
+fn origin(url: []const u8) []const u8 {
+    const scheme_end = (std.mem.indexOf(u8, url, "://") orelse return url) + 3;
+    return url[0..originEnd(url, scheme_end)];
+}
+
+fn originEnd(url: []const u8, scheme_end: usize) usize {
+    return std.mem.indexOfAnyPos(u8, url, scheme_end, "/?#") orelse url.len;
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+fn expectResolved(base: []const u8, target: []const u8, expected: []const u8) !void {
+    const resolved = try resolve(std.testing.allocator, base, target);
+    defer std.testing.allocator.free(resolved);
+    try std.testing.expectEqualStrings(expected, resolved);
+}
+
+test "resolve links and redirects" {
+    const base = "https://api.test/v1/list?page=1";
+    try expectResolved(base, "https://other.test/x", "https://other.test/x");
+    try expectResolved(base, "//cdn.test/file.csv", "https://cdn.test/file.csv");
+    try expectResolved(base, "/v2/list", "https://api.test/v2/list");
+    try expectResolved(base, "?page=2", "https://api.test/v1/list?page=2");
+    try expectResolved(base, "items", "https://api.test/v1/items");
+    try expectResolved("https://api.test", "items", "https://api.test/items");
+}
+
+test "same origin" {
+    try std.testing.expect(sameOrigin("https://api.test/a?x=1", "HTTPS://API.test/b"));
+    try std.testing.expect(!sameOrigin("https://api.test/a", "https://api.test:8443/a"));
+    try std.testing.expect(!sameOrigin("https://api.test/a", "http://api.test/a"));
+}
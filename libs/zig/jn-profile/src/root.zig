@@ -12,6 +12,7 @@
 //! Features:
 //! - Hierarchical merge via `_meta.json` files
 //! - Environment variable substitution (`${VAR}`, `${VAR:-default}`)
+//! - Record templates (`{field}`) for per-record requests
 //! - Deep merge of nested objects
 //!
 //! Example:
@@ -31,6 +32,7 @@
 
 pub const profile = @import("profile.zig");
 pub const envsubst = @import("envsubst.zig");
+pub const template = @import("template.zig");
 
 // Re-export main types
 pub const Source = profile.Source;
@@ -53,6 +55,10 @@ pub const pathExists = profile.pathExists;
 pub const substitute = envsubst.substitute;
 pub const substituteJsonValue = envsubst.substituteJsonValue;
 
+// Record templates
+pub const renderTemplate = template.render;
+pub const renderJsonTemplate = template.renderJsonValue;
+
 test {
     @import("std").testing.refAllDecls(@This());
 }
@@ -0,0 +1,204 @@
+//! Record templates for JN profiles.
+//!
+//! `{field}` is replaced with a field of an NDJSON record, so one profile can
+//! describe a request per record:
+//!
+//!   "/users/{id}"            -> "/users/42"
+//!   "/orgs/{org.name}/repos" -> "/orgs/botassembly/repos" (dotted paths)
+//!
+//! Only `{` followed by a name (letters, digits, `_` and `.`) and `}` is a
+//! placeholder, so JSON text such as `{"id": {id}}` keeps its own braces.
+//! Unlike `${VAR}` (see envsubst.zig), placeholders are filled per record
+//! when the request is made, not when the profile is loaded.
+
+const std = @import("std");
+
+/// How field values are written into the text
+pub const Escape = enum {
+    /// As they are (request bodies)
+    none,
+    /// Percent-encoded, so a value stays one path segment or query value
+    url,
+};
+
+/// `MissingField` when a placeholder names a field the record lacks
+pub const Error = error{ OutOfMemory, WriteFailed, MissingField };
+
+/// Fill the `{field}` placeholders in `text` from `record`.
+///
+/// Strings are written as they are, numbers and booleans as JSON, objects
+/// and arrays as compact JSON. A field that is missing or null fails with
+/// `error.MissingField` and, when `missing` is given, sets it to the
+/// placeholder's name. The caller owns the returned memory.
+pub fn render(allocator: std.mem.Allocator, text: []const u8, record: std.json.Value, escape: Escape, missing: ?*[]const u8) Error![]u8 {
+    var out: std.Io.Writer.Allocating = .init(allocator);
+    errdefer out.deinit();
+    const w = &out.writer;
+
+    var i: usize = 0;
+    while (i < text.len) {
+        const name = placeholderAt(text, i) orelse {
+            try w.writeByte(text[i]);
+            i += 1;
+            continue;
+        };
+        const value = field(record, name) orelse return missingField(missing, name);
+        if (value == .null) return missingField(missing, name);
+        switch (escape) {
+            .none => try writeValue(w, value),
+            .url => {
+                var raw: std.Io.Writer.Allocating = .init(allocator);
+                defer raw.deinit();
+                try writeValue(&raw.writer, value);
+                try writePercentEncoded(w, raw.written());
+            },
+        }
+        i += name.len + 2;
+    }
+    return out.toOwnedSlice();
+}
+
+/// Fill the placeholders in every string of a JSON template. A string that
+/// is one placeholder (`"{tags}"`) takes the field's value as it is, so
+/// numbers, arrays and null keep their type; other strings are rendered
+/// as text.
+///
+/// The result is allocated with `allocator` (an arena, typically) and may
+/// share memory with `record`.
+pub fn renderJsonValue(allocator: std.mem.Allocator, template: std.json.Value, record: std.json.Value, missing: ?*[]const u8) Error!std.json.Value {
+    switch (template) {
+        .string => |text| {
+            if (placeholderAt(text, 0)) |name| {
+                if (name.len + 2 == text.len) return field(record, name) orelse return missingField(missing, name);
+            }
+            return .{ .string = try render(allocator, text, record, .none, missing) };
+        },
+        .array => |array| {
+            var result = std.json.Array.init(allocator);
+            try result.ensureTotalCapacity(array.items.len);
+            for (array.items) |item| result.appendAssumeCapacity(try renderJsonValue(allocator, item, record, missing));
+            return .{ .array = result };
+        },
+        .object => |object| {
+            var result = std.json.ObjectMap.init(allocator);
+            var it = object.iterator();
+            while (it.next()) |entry| {
+                try result.put(entry.key_ptr.*, try renderJsonValue(allocator, entry.value_ptr.*, record, missing));
+            }
+            return .{ .object = result };
+        },
+        else => return template,
+    }
+}
+
+/// Whether `text` has any `{field}` placeholder
+pub fn hasPlaceholders(text: []const u8) bool {
+    for (0..text.len) |i| {
+        if (placeholderAt(text, i) != null) return true;
+    }
+    return false;
+}
+
+/// The name of the placeholder starting at `text[i]`, if there is one
+fn placeholderAt(text: []const u8, i: usize) ?[]const u8 {
+    if (text[i] != '{') return null;
+    var end = i + 1;
+    while (end < text.len and isNameChar(text[end])) : (end += 1) {}
+    if (end == i + 1 or end == text.len or text[end] != '}') return null;
+    return text[i + 1 .. end];
+}
+
+fn isNameChar(c: u8) bool {
+    return std.ascii.isAlphanumeric(c) or c == '_' or c == '.';
+}
+
+fn missingField(missing: ?*[]const u8, name: []const u8) error{MissingField} {
+    if (missing) |out| out.* = name;
+    return error.MissingField;
+}
+
+/// The value at a dotted path ("id", "user.address.city", "tags.0")
+fn field(record: std.json.Value, path: []const u8) ?std.json.Value {
+    var current = record;
+    var keys = std.mem.tokenizeScalar(u8, path, '.');
+    while (keys.next()) |key| {
+        current = switch (current) {
+            .object => |object| object.get(key) orelse return null,
+            .array => |array| blk: {
+                const index = std.fmt.parseInt(usize, key, 10) catch return null;
+                if (index >= array.items.len) return null;
+                break :blk array.items[index];
+            },
+            else => return null,
+        };
+    }
+    return current;
+}
+
+fn writeValue(w: *std.Io.Writer, value: std.json.Value) !void {
+    switch (value) {
+        .string, .number_string => |text| try w.writeAll(text),
+        else => try std.json.Stringify.value(value, .{}, w),
+    }
+}
+
+fn writePercentEncoded(w: *std.Io.Writer, text: []const u8) !void {
+    for (text) |c| {
+        if (std.ascii.isAlphanumeric(c) or std.mem.indexOfScalar(u8, "-._~", c) != null) {
+            try w.writeByte(c);
+        } else {
+            try w.print("%{X:0>2}", .{c});
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+fn parseRecord(text: []const u8) !std.json.Parsed(std.json.Value) {
+    return std.json.parseFromSlice(std.json.Value, std.testing.allocator, text, .{});
+}
+
+test "render fields into a URL" {
+    const record = try parseRecord("{\"id\":42,\"user\":{\"name\":\"Ada Lovelace\"},\"tag\":\"a/b\"}");
+    defer record.deinit();
+
+    const url = try render(std.testing.allocator, "/users/{id}?name={user.name}&tag={tag}", record.value, .url, null);
+    defer std.testing.allocator.free(url);
+    try std.testing.expectEqualStrings("/users/42?name=Ada%20Lovelace&tag=a%2Fb", url);
+}
+
+test "render leaves JSON braces alone" {
+    const record = try parseRecord("{\"id\":7,\"name\":\"x\"}");
+    defer record.deinit();
+
+    const body = try render(std.testing.allocator, "{\"id\": {id}, \"name\": \"{name}\", \"x\": {}}", record.value, .none, null);
+    defer std.testing.allocator.free(body);
+    try std.testing.expectEqualStrings("{\"id\": 7, \"name\": \"x\", \"x\": {}}", body);
+    try std.testing.expect(hasPlaceholders("/users/{id}"));
+    try std.testing.expect(!hasPlaceholders("{\"a\": {}}"));
+}
+
+test "render reports the missing field" {
+    const record = try parseRecord("{\"id\":null}");
+    defer record.deinit();
+
+    var missing: []const u8 = "";
+    try std.testing.expectError(error.MissingField, render(std.testing.allocator, "/a/{name}", record.value, .url, &missing));
+    try std.testing.expectEqualStrings("name", missing);
+    try std.testing.expectError(error.MissingField, render(std.testing.allocator, "/a/{id}", record.value, .url, &missing));
+    try std.testing.expectEqualStrings("id", missing);
+}
+
+test "renderJsonValue keeps the type of whole-string placeholders" {
+    var arena_state = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena_state.deinit();
+    const arena = arena_state.allocator();
+    const record = try std.json.parseFromSliceLeaky(std.json.Value, arena, "{\"id\":42,\"tags\":[\"a\"],\"name\":\"Ada\"}", .{});
+    const template = try std.json.parseFromSliceLeaky(std.json.Value, arena, "{\"id\":\"{id}\",\"tags\":\"{tags}\",\"greeting\":\"hi {name}\",\"n\":1}", .{});
+
+    const body = try renderJsonValue(arena, template, record, null);
+    const json = try std.json.Stringify.valueAlloc(arena, body, .{});
+    try std.testing.expectEqualStrings("{\"id\":42,\"tags\":[\"a\"],\"greeting\":\"hi Ada\",\"n\":1}", json);
+}
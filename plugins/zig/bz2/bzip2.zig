@@ -0,0 +1,784 @@
+//! bzip2 decompression and compression for Zig 0.15
+//!
+//! The standard library has no bzip2, so this file implements the format as
+//! bzip2 1.0 writes it:
+//!
+//!   run-length pass (RLE1) -> Burrows-Wheeler transform -> move-to-front
+//!   -> zero-run coding (RUNA/RUNB) -> Huffman coding
+//!
+//! `decompress` reads any bzip2 file, including several streams concatenated
+//! (as pbzip2 writes). `compress` writes standard streams that `bzip2 -d`
+//! reads; it is simpler than bzip2 (one Huffman table per block, plain
+//! prefix-doubling sort), so output is somewhat larger and slower to produce.
+//!
+//! Usage:
+//! ```zig
+//! var input = std.Io.Reader.fixed(data);
+//! var output: std.Io.Writer.Allocating = .init(allocator);
+//! try bzip2.compress(allocator, &input, &output.writer, 9);
+//! ```
+
+const std = @import("std");
+
+pub const Error = error{
+    /// Not a bzip2 stream, or a damaged one
+    InvalidData,
+    /// A block or stream CRC doesn't match the data
+    ChecksumMismatch,
+    /// "Randomised" blocks, written only by bzip2 0.9.0 and older
+    RandomisedBlock,
+};
+
+const block_magic: u48 = 0x314159_265359;
+const end_magic: u48 = 0x177245_385090;
+
+/// Symbols are coded in groups of 50, each group choosing a Huffman table
+const group_size = 50;
+const max_groups = 6;
+const max_alpha_size = 258;
+const max_selectors = 2 + (900_000 / group_size);
+/// Longest code length the format can describe
+const max_code_len = 20;
+/// Longest code length the encoder produces (as bzip2 does)
+const max_encode_len = 17;
+
+const run_a = 0;
+const run_b = 1;
+
+// ============================================================================
+// CRC (CRC-32/BZIP2: polynomial 0x04c11db7, most significant bit first)
+// ============================================================================
+
+const crc_table = blk: {
+    @setEvalBranchQuota(10_000);
+    var table: [256]u32 = undefined;
+    for (&table, 0..) |*entry, i| {
+        var c: u32 = @as(u32, i) << 24;
+        for (0..8) |_| {
+            c = if (c & 0x8000_0000 != 0) (c << 1) ^ 0x04c1_1db7 else c << 1;
+        }
+        entry.* = c;
+    }
+    break :blk table;
+};
+
+fn crcUpdate(crc: u32, byte: u8) u32 {
+    return (crc << 8) ^ crc_table[(crc >> 24) ^ byte];
+}
+
+// ============================================================================
+// Decompression
+// ============================================================================
+
+/// Most significant bit first reader over a byte stream
+const BitReader = struct {
+    reader: *std.Io.Reader,
+    bits: u64 = 0,
+    count: u6 = 0,
+
+    /// Read `n` bits (at most 32)
+    fn read(self: *BitReader, n: u6) !u32 {
+        while (self.count < n) {
+            self.bits = (self.bits << 8) | try self.reader.takeByte();
+            self.count += 8;
+        }
+        self.count -= n;
+        const value: u32 = @intCast((self.bits >> self.count) & ((@as(u64, 1) << n) - 1));
+        self.bits &= (@as(u64, 1) << self.count) - 1;
+        return value;
+    }
+
+    fn alignToByte(self: *BitReader) void {
+        self.count -= self.count % 8;
+        self.bits &= (@as(u64, 1) << self.count) - 1;
+    }
+
+    /// Whether the input is exhausted (only valid on a byte boundary)
+    fn atEnd(self: *BitReader) !bool {
+        if (self.count > 0) return false;
+        _ = self.reader.peekByte() catch |err| switch (err) {
+            error.EndOfStream => return true,
+            else => return err,
+        };
+        return false;
+    }
+};
+
+/// Canonical Huffman decoding table
+const Huffman = struct {
+    /// Per code length: how many codes, the first code, and where the
+    /// symbols of that length start in `symbols`
+    count: [max_code_len + 1]u16 = @splat(0),
+    first: [max_code_len + 1]u32 = @splat(0),
+    offset: [max_code_len + 1]u16 = @splat(0),
+    /// Symbols ordered by code length, then by value
+    symbols: [max_alpha_size]u16 = undefined,
+
+    fn init(lengths: []const u8) Huffman {
+        var h: Huffman = .{};
+        for (lengths) |length| h.count[length] += 1;
+
+        var code: u32 = 0;
+        var offset: u16 = 0;
+        for (1..max_code_len + 1) |len| {
+            h.first[len] = code;
+            h.offset[len] = offset;
+            code = (code + h.count[len]) << 1;
+            offset += h.count[len];
+        }
+
+        var next = h.offset;
+        for (lengths, 0..) |length, symbol| {
+            h.symbols[next[length]] = @intCast(symbol);
+            next[length] += 1;
+        }
+        return h;
+    }
+
+    fn decode(self: *const Huffman, bits: *BitReader) !u16 {
+        var code: u32 = 0;
+        for (1..max_code_len + 1) |len| {
+            code = (code << 1) | try bits.read(1);
+            const index = code -% self.first[len];
+            if (index < self.count[len]) return self.symbols[self.offset[len] + index];
+        }
+        return error.InvalidData;
+    }
+};
+
+/// Decompress every bzip2 stream in `reader` to `writer`.
+pub fn decompress(allocator: std.mem.Allocator, reader: *std.Io.Reader, writer: *std.Io.Writer) !void {
+    var bits: BitReader = .{ .reader = reader };
+    var first = true;
+
+    while (first or !try bits.atEnd()) : (first = false) {
+        // Stream header: "BZh" and the block size in units of 100k
+        if ((try bits.read(8)) != 'B' or (try bits.read(8)) != 'Z' or (try bits.read(8)) != 'h') {
+            return error.InvalidData;
+        }
+        const level = try bits.read(8);
+        if (level < '1' or level > '9') return error.InvalidData;
+
+        const tt = try allocator.alloc(u32, 100_000 * (level - '0'));
+        defer allocator.free(tt);
+
+        var combined: u32 = 0;
+        while (true) {
+            const magic = (@as(u48, try bits.read(24)) << 24) | try bits.read(24);
+            if (magic == end_magic) {
+                if ((try bits.read(32)) != combined) return error.ChecksumMismatch;
+                bits.alignToByte();
+                break;
+            }
+            if (magic != block_magic) return error.InvalidData;
+
+            const crc = try decodeBlock(&bits, tt, writer);
+            combined = std.math.rotl(u32, combined, 1) ^ crc;
+        }
+    }
+}
+
+/// Decode one block into `writer` and return its (verified) CRC.
+/// `tt` holds the block while the BWT is inverted.
+fn decodeBlock(bits: *BitReader, tt: []u32, writer: *std.Io.Writer) !u32 {
+    const stored_crc = try bits.read(32);
+    if ((try bits.read(1)) != 0) return error.RandomisedBlock;
+    const orig_ptr = try bits.read(24);
+
+    // Byte values used in the block: a 16-bit map of 16-value ranges, then
+    // a 16-bit map for each range present
+    var seq_to_byte: [256]u8 = undefined;
+    var in_use: u16 = 0;
+    const ranges = try bits.read(16);
+    for (0..16) |i| {
+        if (ranges & (@as(u32, 0x8000) >> @intCast(i)) == 0) continue;
+        const used = try bits.read(16);
+        for (0..16) |j| {
+            if (used & (@as(u32, 0x8000) >> @intCast(j)) == 0) continue;
+            seq_to_byte[in_use] = @intCast(i * 16 + j);
+            in_use += 1;
+        }
+    }
+    if (in_use == 0) return error.InvalidData;
+    const alpha_size = in_use + 2;
+    const end_of_block = in_use + 1;
+
+    // Which table codes each group of 50 symbols, move-to-front coded
+    const num_groups = try bits.read(3);
+    if (num_groups < 2 or num_groups > max_groups) return error.InvalidData;
+    const num_selectors = try bits.read(15);
+    if (num_selectors == 0) return error.InvalidData;
+
+    var selectors: [max_selectors]u8 = undefined;
+    var group_order = [_]u8{ 0, 1, 2, 3, 4, 5 };
+    for (0..num_selectors) |i| {
+        var j: usize = 0;
+        while ((try bits.read(1)) != 0) {
+            j += 1;
+            if (j >= num_groups) return error.InvalidData;
+        }
+        const group = group_order[j];
+        std.mem.copyBackwards(u8, group_order[1 .. j + 1], group_order[0..j]);
+        group_order[0] = group;
+        // bzip2 1.0.8 ignores selectors past the maximum
+        if (i < max_selectors) selectors[i] = group;
+    }
+    const used_selectors = @min(num_selectors, max_selectors);
+
+    // Code lengths: a 5-bit start, then delta coded per symbol
+    var tables: [max_groups]Huffman = undefined;
+    for (tables[0..num_groups]) |*table| {
+        var lengths: [max_alpha_size]u8 = undefined;
+        var current = try bits.read(5);
+        for (lengths[0..alpha_size]) |*length| {
+            while (true) {
+                if (current < 1 or current > max_code_len) return error.InvalidData;
+                if ((try bits.read(1)) == 0) break;
+                if ((try bits.read(1)) == 0) current += 1 else current -= 1;
+            }
+            length.* = @intCast(current);
+        }
+        table.* = Huffman.init(lengths[0..alpha_size]);
+    }
+
+    // Symbols: undo zero-run coding and move-to-front into `tt`
+    var mtf: [256]u8 = undefined;
+    for (&mtf, 0..) |*slot, i| slot.* = @intCast(i);
+    var counts: [256]u32 = @splat(0);
+    var n: u32 = 0;
+
+    var run: u32 = 0;
+    var run_weight: u32 = 0;
+    var selector: usize = 0;
+    var group_left: u32 = 0;
+    var table: *const Huffman = undefined;
+
+    while (true) {
+        if (group_left == 0) {
+            if (selector >= used_selectors) return error.InvalidData;
+            table = &tables[selectors[selector]];
+            selector += 1;
+            group_left = group_size;
+        }
+        group_left -= 1;
+
+        const symbol = try table.decode(bits);
+        if (symbol == run_a or symbol == run_b) {
+            // Runs of the front byte, written in bijective base 2
+            if (run_weight == 0) {
+                run = 0;
+                run_weight = 1;
+            }
+            run += if (symbol == run_a) run_weight else 2 * run_weight;
+            if (run > tt.len) return error.InvalidData;
+            run_weight <<= 1;
+            continue;
+        }
+
+        if (run_weight != 0) {
+            if (n + run > tt.len) return error.InvalidData;
+            const byte = seq_to_byte[mtf[0]];
+            @memset(tt[n .. n + run], byte);
+            counts[byte] += run;
+            n += run;
+            run_weight = 0;
+        }
+        if (symbol == end_of_block) break;
+
+        // Symbol s moves entry s-1 to the front
+        const index = symbol - 1;
+        const value = mtf[index];
+        std.mem.copyBackwards(u8, mtf[1 .. index + 1], mtf[0..index]);
+        mtf[0] = value;
+
+        if (n >= tt.len) return error.InvalidData;
+        const byte = seq_to_byte[value];
+        tt[n] = byte;
+        counts[byte] += 1;
+        n += 1;
+    }
+    if (orig_ptr >= n) return error.InvalidData;
+
+    // Inverse BWT: keep each byte in the low 8 bits and link it to the
+    // position that follows it in the upper 24
+    var next: [256]u32 = undefined;
+    var total: u32 = 0;
+    for (counts, 0..) |count, byte| {
+        next[byte] = total;
+        total += count;
+    }
+    for (0..n) |i| {
+        const byte = tt[i] & 0xff;
+        tt[next[byte]] |= @as(u32, @intCast(i)) << 8;
+        next[byte] += 1;
+    }
+
+    // Walk the chain, undoing the initial run-length pass: four equal bytes
+    // are followed by a count of further copies
+    var crc: u32 = 0xffff_ffff;
+    var position = tt[orig_ptr] >> 8;
+    var last: u8 = 0;
+    var repeat: u8 = 0;
+    for (0..n) |_| {
+        const entry = tt[position];
+        const byte: u8 = @truncate(entry);
+        position = entry >> 8;
+
+        if (repeat == 4) {
+            for (0..byte) |_| {
+                crc = crcUpdate(crc, last);
+                try writer.writeByte(last);
+            }
+            repeat = 0;
+            continue;
+        }
+        repeat = if (repeat > 0 and byte == last) repeat + 1 else 1;
+        last = byte;
+        crc = crcUpdate(crc, byte);
+        try writer.writeByte(byte);
+    }
+
+    if (~crc != stored_crc) return error.ChecksumMismatch;
+    return stored_crc;
+}
+
+// ============================================================================
+// Compression
+// ============================================================================
+
+/// Most significant bit first writer over a byte stream
+const BitWriter = struct {
+    writer: *std.Io.Writer,
+    bits: u64 = 0,
+    count: u6 = 0,
+
+    /// Write the low `n` bits (at most 32) of `value`
+    fn write(self: *BitWriter, n: u6, value: u32) !void {
+        self.bits = (self.bits << n) | value;
+        self.count += n;
+        while (self.count >= 8) {
+            self.count -= 8;
+            try self.writer.writeByte(@truncate(self.bits >> self.count));
+        }
+        self.bits &= (@as(u64, 1) << self.count) - 1;
+    }
+
+    fn flush(self: *BitWriter) !void {
+        if (self.count == 0) return;
+        try self.writer.writeByte(@truncate(self.bits << (8 - self.count)));
+        self.bits = 0;
+        self.count = 0;
+    }
+};
+
+/// Compress everything in `reader` to `writer` as one bzip2 stream.
+/// `level` (1-9) sets the block size in units of 100k.
+pub fn compress(allocator: std.mem.Allocator, reader: *std.Io.Reader, writer: *std.Io.Writer, level: u4) !void {
+    var encoder = try Encoder.init(allocator, writer, level);
+    defer encoder.deinit();
+
+    var buf: [64 * 1024]u8 = undefined;
+    while (true) {
+        const n = try reader.readSliceShort(&buf);
+        try encoder.write(buf[0..n]);
+        if (n < buf.len) break;
+    }
+    try encoder.finish();
+}
+
+const Encoder = struct {
+    allocator: std.mem.Allocator,
+    bits: BitWriter,
+    /// Bytes a block may hold after the run-length pass (bzip2 keeps a
+    /// margin of 19 below the nominal size)
+    block_limit: u32,
+
+    block: []u8,
+    len: u32 = 0,
+    crc: u32 = 0xffff_ffff,
+    combined: u32 = 0,
+
+    /// Pending run of `run_byte` for the run-length pass
+    run_byte: u8 = 0,
+    run_length: u32 = 0,
+
+    // Work space for the BWT and the symbols of one block
+    sa: []u32,
+    rank: []u32,
+    tmp: []u32,
+    last: []u8,
+    symbols: []u16,
+    num_symbols: usize = 0,
+    freq: [max_alpha_size]u32 = undefined,
+
+    fn init(allocator: std.mem.Allocator, writer: *std.Io.Writer, level: u4) !Encoder {
+        std.debug.assert(level >= 1 and level <= 9);
+        const limit = 100_000 * @as(u32, level) - 19;
+
+        const block = try allocator.alloc(u8, limit);
+        errdefer allocator.free(block);
+        const sa = try allocator.alloc(u32, limit);
+        errdefer allocator.free(sa);
+        const rank = try allocator.alloc(u32, limit);
+        errdefer allocator.free(rank);
+        const tmp = try allocator.alloc(u32, limit);
+        errdefer allocator.free(tmp);
+        const last = try allocator.alloc(u8, limit);
+        errdefer allocator.free(last);
+        const symbols = try allocator.alloc(u16, limit + 1);
+        errdefer allocator.free(symbols);
+
+        var self: Encoder = .{
+            .allocator = allocator,
+            .bits = .{ .writer = writer },
+            .block_limit = limit,
+            .block = block,
+            .sa = sa,
+            .rank = rank,
+            .tmp = tmp,
+            .last = last,
+            .symbols = symbols,
+        };
+        try self.bits.write(24, 0x425a68); // "BZh"
+        try self.bits.write(8, '0' + @as(u32, level));
+        return self;
+    }
+
+    fn deinit(self: *Encoder) void {
+        self.allocator.free(self.block);
+        self.allocator.free(self.sa);
+        self.allocator.free(self.rank);
+        self.allocator.free(self.tmp);
+        self.allocator.free(self.last);
+        self.allocator.free(self.symbols);
+    }
+
+    fn write(self: *Encoder, bytes: []const u8) !void {
+        for (bytes) |byte| {
+            if (self.run_length > 0 and (byte != self.run_byte or self.run_length == 255)) {
+                try self.addRun();
+            }
+            self.run_byte = byte;
+            self.run_length += 1;
+        }
+    }
+
+    fn finish(self: *Encoder) !void {
+        try self.addRun();
+        try self.endBlock();
+        try self.bits.write(24, @truncate(end_magic >> 24));
+        try self.bits.write(24, @truncate(end_magic));
+        try self.bits.write(32, self.combined);
+        try self.bits.flush();
+    }
+
+    /// Run-length pass: runs of 4 to 255 become 4 bytes and a count
+    fn addRun(self: *Encoder) !void {
+        if (self.run_length == 0) return;
+        if (self.len + 5 > self.block_limit) try self.endBlock();
+
+        for (0..self.run_length) |_| self.crc = crcUpdate(self.crc, self.run_byte);
+        const copies = @min(self.run_length, 4);
+        @memset(self.block[self.len..][0..copies], self.run_byte);
+        self.len += copies;
+        if (self.run_length >= 4) {
+            self.block[self.len] = @intCast(self.run_length - 4);
+            self.len += 1;
+        }
+        self.run_length = 0;
+    }
+
+    fn endBlock(self: *Encoder) !void {
+        if (self.len == 0) return;
+        const crc = ~self.crc;
+        self.combined = std.math.rotl(u32, self.combined, 1) ^ crc;
+        try self.writeBlock(crc);
+        self.len = 0;
+        self.crc = 0xffff_ffff;
+    }
+
+    const RotationOrder = struct {
+        rank: []const u32,
+        k: u32,
+        n: u32,
+
+        fn key(self: RotationOrder, i: u32) u64 {
+            const second = self.rank[(i + self.k) % self.n];
+            return (@as(u64, self.rank[i]) << 32) | second;
+        }
+
+        fn lessThan(self: RotationOrder, a: u32, b: u32) bool {
+            return self.key(a) < self.key(b);
+        }
+    };
+
+    /// Sort the rotations of the block into `sa` by prefix doubling and
+    /// return the position of the original string.
+    fn sortRotations(self: *Encoder) u32 {
+        const n = self.len;
+        const sa = self.sa[0..n];
+        var rank = self.rank[0..n];
+        var tmp = self.tmp[0..n];
+        for (sa, 0..) |*slot, i| slot.* = @intCast(i);
+        for (rank, self.block[0..n]) |*slot, byte| slot.* = byte;
+
+        var k: u32 = 1;
+        while (true) : (k *= 2) {
+            const order: RotationOrder = .{ .rank = rank, .k = k, .n = n };
+            std.sort.pdq(u32, sa, order, RotationOrder.lessThan);
+
+            tmp[sa[0]] = 0;
+            for (1..n) |i| {
+                tmp[sa[i]] = tmp[sa[i - 1]] + @intFromBool(order.lessThan(sa[i - 1], sa[i]));
+            }
+            std.mem.swap([]u32, &rank, &tmp);
+            if (rank[sa[n - 1]] == n - 1 or k >= n) break;
+        }
+        return @intCast(std.mem.indexOfScalar(u32, sa, 0).?);
+    }
+
+    fn pushSymbol(self: *Encoder, symbol: u16) void {
+        self.symbols[self.num_symbols] = symbol;
+        self.num_symbols += 1;
+        self.freq[symbol] += 1;
+    }
+
+    /// A run of `run` zeros in bijective base 2: RUNA is 1, RUNB is 2
+    fn pushZeroRun(self: *Encoder, run: u32) void {
+        if (run == 0) return;
+        var z = run - 1;
+        while (true) {
+            self.pushSymbol(if (z & 1 == 0) run_a else run_b);
+            if (z < 2) break;
+            z = (z - 2) / 2;
+        }
+    }
+
+    fn writeBlock(self: *Encoder, crc: u32) !void {
+        const n = self.len;
+        const block = self.block[0..n];
+        const orig_ptr = self.sortRotations();
+        for (self.sa[0..n], self.last[0..n]) |start, *byte| {
+            byte.* = block[if (start == 0) n - 1 else start - 1];
+        }
+
+        const bits = &self.bits;
+        try bits.write(24, @truncate(block_magic >> 24));
+        try bits.write(24, @truncate(block_magic));
+        try bits.write(32, crc);
+        try bits.write(1, 0); // not randomised
+        try bits.write(24, orig_ptr);
+
+        // Byte values in use
+        var in_use: [256]bool = @splat(false);
+        for (block) |byte| in_use[byte] = true;
+        var ranges: u32 = 0;
+        for (0..16) |i| {
+            if (std.mem.indexOfScalar(bool, in_use[i * 16 ..][0..16], true) != null) {
+                ranges |= @as(u32, 0x8000) >> @intCast(i);
+            }
+        }
+        try bits.write(16, ranges);
+        for (0..16) |i| {
+            if (ranges & (@as(u32, 0x8000) >> @intCast(i)) == 0) continue;
+            var used: u32 = 0;
+            for (0..16) |j| {
+                if (in_use[i * 16 + j]) used |= @as(u32, 0x8000) >> @intCast(j);
+            }
+            try bits.write(16, used);
+        }
+
+        var byte_to_seq: [256]u8 = undefined;
+        var num_in_use: u16 = 0;
+        for (in_use, 0..) |used, byte| {
+            if (!used) continue;
+            byte_to_seq[byte] = @intCast(num_in_use);
+            num_in_use += 1;
+        }
+        const alpha_size = num_in_use + 2;
+        const end_of_block = num_in_use + 1;
+
+        // Move-to-front, with runs of the front byte zero-run coded
+        var mtf: [256]u8 = undefined;
+        for (&mtf, 0..) |*slot, i| slot.* = @intCast(i);
+        self.num_symbols = 0;
+        @memset(&self.freq, 0);
+        var zero_run: u32 = 0;
+        for (self.last[0..n]) |byte| {
+            const value = byte_to_seq[byte];
+            const index = std.mem.indexOfScalar(u8, &mtf, value).?;
+            if (index == 0) {
+                zero_run += 1;
+                continue;
+            }
+            self.pushZeroRun(zero_run);
+            zero_run = 0;
+            std.mem.copyBackwards(u8, mtf[1 .. index + 1], mtf[0..index]);
+            mtf[0] = value;
+            self.pushSymbol(@intCast(index + 1));
+        }
+        self.pushZeroRun(zero_run);
+        self.pushSymbol(end_of_block);
+
+        var lengths: [max_alpha_size]u8 = undefined;
+        buildCodeLengths(self.freq[0..alpha_size], lengths[0..alpha_size]);
+
+        var codes: [max_alpha_size]u32 = undefined;
+        var code: u32 = 0;
+        for (1..max_encode_len + 1) |len| {
+            for (lengths[0..alpha_size], 0..) |length, symbol| {
+                if (length != len) continue;
+                codes[symbol] = code;
+                code += 1;
+            }
+            code <<= 1;
+        }
+
+        // Two identical tables (the format needs at least two), every group
+        // selecting the first
+        const num_selectors: u32 = @intCast((self.num_symbols + group_size - 1) / group_size);
+        try bits.write(3, 2);
+        try bits.write(15, num_selectors);
+        for (0..num_selectors) |_| try bits.write(1, 0);
+        for (0..2) |_| {
+            var current: u8 = lengths[0];
+            try bits.write(5, current);
+            for (lengths[0..alpha_size]) |length| {
+                while (current < length) : (current += 1) try bits.write(2, 0b10);
+                while (current > length) : (current -= 1) try bits.write(2, 0b11);
+                try bits.write(1, 0);
+            }
+        }
+
+        for (self.symbols[0..self.num_symbols]) |symbol| {
+            try bits.write(@intCast(lengths[symbol]), codes[symbol]);
+        }
+    }
+};
+
+/// Huffman code lengths for `freq`, limited to `max_encode_len` by
+/// flattening the weights until the tree is shallow enough (as bzip2 does).
+fn buildCodeLengths(freq: []const u32, lengths: []u8) void {
+    const n = freq.len;
+    var weight: [2 * max_alpha_size]u32 = undefined;
+    var parent: [2 * max_alpha_size]u16 = undefined;
+    var active: [2 * max_alpha_size]bool = undefined;
+    for (freq, weight[0..n]) |f, *w| w.* = @max(f, 1);
+
+    while (true) {
+        @memset(active[0..n], true);
+        var nodes = n;
+        while (nodes < 2 * n - 1) : (nodes += 1) {
+            const a = takeLightest(weight[0..nodes], active[0..nodes]);
+            const b = takeLightest(weight[0..nodes], active[0..nodes]);
+            weight[nodes] = weight[a] + weight[b];
+            parent[a] = @intCast(nodes);
+            parent[b] = @intCast(nodes);
+            active[nodes] = true;
+        }
+
+        const root = nodes - 1;
+        var too_long = false;
+        for (lengths, 0..) |*length, leaf| {
+            var depth: usize = 0;
+            var node = leaf;
+            while (node != root) : (depth += 1) node = parent[node];
+            if (depth > max_encode_len) too_long = true;
+            length.* = @intCast(@min(depth, 255));
+        }
+        if (!too_long) return;
+
+        for (weight[0..n]) |*w| w.* = 1 + w.* / 2;
+    }
+}
+
+/// Index of the lightest active node (the lowest index among equals),
+/// which is then deactivated
+fn takeLightest(weight: []const u32, active: []bool) usize {
+    var best: ?usize = null;
+    for (active, 0..) |on, i| {
+        if (!on) continue;
+        if (best == null or weight[i] < weight[best.?]) best = i;
+    }
+    active[best.?] = false;
+    return best.?;
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+fn roundTrip(data: []const u8, level: u4) !void {
+    const allocator = std.testing.allocator;
+
+    var input = std.Io.Reader.fixed(data);
+    var compressed: std.Io.Writer.Allocating = .init(allocator);
+    defer compressed.deinit();
+    try compress(allocator, &input, &compressed.writer, level);
+
+    var packed_input = std.Io.Reader.fixed(compressed.written());
+    var output: std.Io.Writer.Allocating = .init(allocator);
+    defer output.deinit();
+    try decompress(allocator, &packed_input, &output.writer);
+
+    try std.testing.expectEqualSlices(u8, data, output.written());
+}
+
+test "decompress bzip2 output" {
+    // bz2.compress(b'{"a":1}\n{"a":2}\n')
+    const stream = [_]u8{
+        0x42, 0x5a, 0x68, 0x39, 0x31, 0x41, 0x59, 0x26, 0x53, 0x59, 0x22, 0x9d, 0xe2, 0xe9,
+        0x00, 0x00, 0x06, 0x59, 0x80, 0x00, 0x10, 0x10, 0x00, 0x30, 0x10, 0x20, 0x00, 0x00,
+        0x0a, 0x20, 0x00, 0x31, 0x0c, 0x08, 0x12, 0x80, 0x7a, 0x89, 0xc2, 0x26, 0x86, 0x8b,
+        0xe2, 0xee, 0x48, 0xa7, 0x0a, 0x12, 0x04, 0x53, 0xbc, 0x5d, 0x20,
+    };
+    var input = std.Io.Reader.fixed(&stream);
+    var output: std.Io.Writer.Allocating = .init(std.testing.allocator);
+    defer output.deinit();
+    try decompress(std.testing.allocator, &input, &output.writer);
+    try std.testing.expectEqualStrings("{\"a\":1}\n{\"a\":2}\n", output.written());
+}
+
+test "round trip text, runs and empty input" {
+    try roundTrip("", 9);
+    try roundTrip("a", 9);
+    try roundTrip("{\"name\":\"Alice\",\"age\":30}\n{\"name\":\"Bob\",\"age\":25}\n", 9);
+
+    var runs: [3000]u8 = undefined;
+    @memset(runs[0..1000], 'x');
+    @memset(runs[1000..1004], 'y');
+    for (runs[1004..], 0..) |*byte, i| byte.* = @truncate(i * 7);
+    try roundTrip(&runs, 9);
+}
+
+test "round trip spans several blocks" {
+    const allocator = std.testing.allocator;
+    const data = try allocator.alloc(u8, 250_000);
+    defer allocator.free(data);
+    var prng = std.Random.DefaultPrng.init(42);
+    prng.random().bytes(data);
+    try roundTrip(data, 1);
+}
+
+test "concatenated streams decompress as one" {
+    const allocator = std.testing.allocator;
+    var compressed: std.Io.Writer.Allocating = .init(allocator);
+    defer compressed.deinit();
+    for ([_][]const u8{ "first\n", "second\n" }) |part| {
+        var input = std.Io.Reader.fixed(part);
+        try compress(allocator, &input, &compressed.writer, 9);
+    }
+
+    var packed_input = std.Io.Reader.fixed(compressed.written());
+    var output: std.Io.Writer.Allocating = .init(allocator);
+    defer output.deinit();
+    try decompress(allocator, &packed_input, &output.writer);
+    try std.testing.expectEqualStrings("first\nsecond\n", output.written());
+}
+
+test "damaged data is rejected" {
+    var input = std.Io.Reader.fixed("BZh9not a block");
+    var output: std.Io.Writer.Allocating = .init(std.testing.allocator);
+    defer output.deinit();
+    try std.testing.expectError(error.InvalidData, decompress(std.testing.allocator, &input, &output.writer));
+}
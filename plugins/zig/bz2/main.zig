@@ -0,0 +1,89 @@
+const std = @import("std");
+const jn_core = @import("jn-core");
+const jn_cli = @import("jn-cli");
+const jn_plugin = @import("jn-plugin");
+const bzip2 = @import("bzip2.zig");
+
+const plugin_meta = jn_plugin.PluginMeta{
+    .name = "bz2",
+    .version = "0.1.0",
+    .matches = &.{".*\\.bz2$"},
+    .role = .compression,
+    .modes = &.{ .raw, .write },
+    .supports_raw = true,
+};
+
+pub fn main() !void {
+    var gpa = std.heap.GeneralPurposeAllocator(.{}){};
+    defer _ = gpa.deinit();
+    const allocator = gpa.allocator();
+
+    const args = jn_cli.parseArgs();
+    const mode = args.get("mode", "raw") orelse "raw";
+
+    if (args.has("jn-meta")) {
+        try jn_plugin.outputManifestToStdout(plugin_meta);
+        return;
+    }
+
+    if (std.mem.eql(u8, mode, "raw")) {
+        try decompressMode(allocator);
+    } else if (std.mem.eql(u8, mode, "write")) {
+        try compressMode(allocator);
+    } else {
+        jn_core.exitWithError("bz2: unknown mode '{s}' (supported: raw, write)", .{mode});
+    }
+}
+
+fn decompressMode(allocator: std.mem.Allocator) !void {
+    var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
+    var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
+
+    var stdout_buf: [jn_core.STDOUT_BUFFER_SIZE]u8 = undefined;
+    var stdout_wrapper = std.fs.File.stdout().writerStreaming(&stdout_buf);
+    const writer = &stdout_wrapper.interface;
+
+    bzip2.decompress(allocator, &stdin_wrapper.interface, writer) catch |err| switch (err) {
+        error.WriteFailed => jn_core.handleWriteError(stdout_wrapper.err orelse err),
+        else => jn_core.exitWithError("bz2: decompression error: {}", .{err}),
+    };
+
+    jn_core.flushWriter(writer);
+}
+
+fn compressMode(allocator: std.mem.Allocator) !void {
+    var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
+    var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
+
+    var stdout_buf: [jn_core.STDOUT_BUFFER_SIZE]u8 = undefined;
+    var stdout_wrapper = std.fs.File.stdout().writerStreaming(&stdout_buf);
+    const writer = &stdout_wrapper.interface;
+
+    bzip2.compress(allocator, &stdin_wrapper.interface, writer, 9) catch |err| switch (err) {
+        error.WriteFailed => jn_core.handleWriteError(stdout_wrapper.err orelse err),
+        else => jn_core.exitWithError("bz2: compression error: {}", .{err}),
+    };
+
+    jn_core.flushWriter(writer);
+}
+
+// Tests
+test "manifest contains plugin name" {
+    var buf: [256]u8 = undefined;
+    var fbs = std.io.fixedBufferStream(&buf);
+    try jn_plugin.outputManifest(fbs.writer(), plugin_meta);
+    const output = fbs.getWritten();
+    try std.testing.expect(std.mem.indexOf(u8, output, "\"bz2\"") != null);
+}
+
+test "manifest contains compression role" {
+    var buf: [256]u8 = undefined;
+    var fbs = std.io.fixedBufferStream(&buf);
+    try jn_plugin.outputManifest(fbs.writer(), plugin_meta);
+    const output = fbs.getWritten();
+    try std.testing.expect(std.mem.indexOf(u8, output, "\"compression\"") != null);
+}
+
+test "manifest supports raw mode" {
+    try std.testing.expect(plugin_meta.supports_raw);
+}
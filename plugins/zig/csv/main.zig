@@ -40,13 +40,16 @@ pub fn main() !void {
         return;
     }
 
+    var errors = jn_core.ErrorSink.fromArgs(allocator, args, "csv");
+    defer errors.close();
+
     if (std.mem.eql(u8, mode, "read")) {
-        try readMode(allocator, config);
+        try readMode(allocator, config, &errors);
         return;
     }
 
     if (std.mem.eql(u8, mode, "write")) {
-        try writeMode(allocator, config);
+        try writeMode(allocator, config, &errors);
         return;
     }
 
@@ -220,7 +223,7 @@ fn detectDelimiter(sample_lines: []const []const u8) DetectionResult {
     return .{ .delimiter = best_delim, .has_evidence = found };
 }
 
-fn readMode(allocator: std.mem.Allocator, config: Config) !void {
+fn readMode(allocator: std.mem.Allocator, config: Config, errors: *jn_core.ErrorSink) !void {
     var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
     var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
     const reader = &stdin_wrapper.interface;
@@ -310,9 +313,18 @@ fn readMode(allocator: std.mem.Allocator, config: Config) !void {
             starts: *[MAX_CSV_FIELDS]usize,
             ends: *[MAX_CSV_FIELDS]usize,
             warn_state: *TruncationWarningState,
+            errs: *jn_core.ErrorSink,
+            line_no: u64,
         ) void {
             if (line.len == 0) return;
 
+            if (errs.enabled()) {
+                if (rowProblem(line, delim)) |problem| {
+                    errs.report(problem, line_no, line);
+                    return;
+                }
+            }
+
             const fc = parseCSVRowFast(line, delim, starts, ends, warn_state);
 
             w.writeByte('{') catch |err| jn_core.handleWriteError(err);
@@ -356,19 +368,44 @@ fn readMode(allocator: std.mem.Allocator, config: Config) !void {
     }.f;
 
     // Process buffered sample lines first
-    for (sample_lines.items[data_start_idx..]) |line| {
-        processLine(allocator, writer, line, delimiter, &headers, config.no_header, &field_starts, &field_ends, &truncation_warn);
+    for (sample_lines.items[data_start_idx..], data_start_idx + 1..) |line, line_no| {
+        processLine(allocator, writer, line, delimiter, &headers, config.no_header, &field_starts, &field_ends, &truncation_warn, errors, line_no);
     }
 
-    // Continue reading from stdin
+    // Continue reading from stdin (the header was line 1 if it wasn't sampled)
+    var line_no: u64 = if (sample_lines.items.len > 0) sample_lines.items.len else if (config.no_header) 0 else 1;
     while (jn_core.readLine(reader)) |line| {
+        line_no += 1;
         const clean_line = jn_core.stripCR(line);
-        processLine(allocator, writer, clean_line, delimiter, &headers, config.no_header, &field_starts, &field_ends, &truncation_warn);
+        processLine(allocator, writer, clean_line, delimiter, &headers, config.no_header, &field_starts, &field_ends, &truncation_warn, errors, line_no);
     }
 
     jn_core.flushWriter(writer);
 }
 
+/// Why a row can't be read as a record, if it can't. Rows are single lines,
+/// so a quote left open is a broken record rather than a multi-line field.
+fn rowProblem(line: []const u8, delimiter: u8) ?[]const u8 {
+    var in_quotes = false;
+    var fields: usize = 1;
+    var i: usize = 0;
+    while (i < line.len) : (i += 1) {
+        const c = line[i];
+        if (c == '"') {
+            if (in_quotes and i + 1 < line.len and line[i + 1] == '"') {
+                i += 1;
+                continue;
+            }
+            in_quotes = !in_quotes;
+        } else if (c == delimiter and !in_quotes) {
+            fields += 1;
+        }
+    }
+    if (in_quotes) return "unterminated quoted field";
+    if (fields > MAX_CSV_FIELDS) return std.fmt.comptimePrint("row has more than {d} fields", .{MAX_CSV_FIELDS});
+    return null;
+}
+
 fn parseCSVRowFast(line: []const u8, delimiter: u8, starts: *[MAX_CSV_FIELDS]usize, ends: *[MAX_CSV_FIELDS]usize, warn_state: ?*TruncationWarningState) usize {
     var field_count: usize = 0;
     var i: usize = 0;
@@ -520,7 +557,7 @@ fn unquoteField(allocator: std.mem.Allocator, field: []const u8, needs_free: *bo
     return owned;
 }
 
-fn writeMode(allocator: std.mem.Allocator, config: Config) !void {
+fn writeMode(allocator: std.mem.Allocator, config: Config, errors: *jn_core.ErrorSink) !void {
     var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
     var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
     const reader = &stdin_wrapper.interface;
@@ -544,23 +581,30 @@ fn writeMode(allocator: std.mem.Allocator, config: Config) !void {
     var headers_seen = std.StringHashMap(void).init(allocator);
     defer headers_seen.deinit();
 
+    // Lines that aren't JSON objects can't be rows: they are skipped, and
+    // routed to the error sidecar if there is one
+    var line_no: u64 = 0;
     while (jn_core.readLine(reader)) |line| {
+        line_no += 1;
         if (line.len == 0) continue;
 
         if (jn_core.parseJsonLine(allocator, line)) |parsed| {
             defer parsed.deinit();
-            if (parsed.value == .object) {
-                var iter = parsed.value.object.iterator();
-                while (iter.next()) |entry| {
-                    const key = entry.key_ptr.*;
-                    if (!headers_seen.contains(key)) {
-                        const duped = try allocator.dupe(u8, key);
-                        try headers_list.append(allocator, duped);
-                        try headers_seen.put(duped, {});
-                    }
+            if (parsed.value != .object) {
+                errors.report("record is not an object", line_no, line);
+                continue;
+            }
+            var iter = parsed.value.object.iterator();
+            while (iter.next()) |entry| {
+                const key = entry.key_ptr.*;
+                if (!headers_seen.contains(key)) {
+                    const duped = try allocator.dupe(u8, key);
+                    try headers_list.append(allocator, duped);
+                    try headers_seen.put(duped, {});
                 }
             }
         } else {
+            errors.report("invalid JSON", line_no, line);
             continue;
         }
 
@@ -585,8 +629,6 @@ fn writeMode(allocator: std.mem.Allocator, config: Config) !void {
         const parsed = jn_core.parseJsonLine(allocator, line) orelse continue;
         defer parsed.deinit();
 
-        if (parsed.value != .object) continue;
-
         for (headers_list.items, 0..) |header, i| {
             if (i > 0) writer.writeByte(config.delimiter) catch |err| jn_core.handleWriteError(err);
 
@@ -805,3 +847,9 @@ test "parseDelimiter empty returns comma" {
     // Empty delimiter should fall back to comma (with warning printed to stderr)
     try std.testing.expectEqual(@as(u8, ','), parseDelimiter(""));
 }
+
+test "rowProblem flags unterminated quotes" {
+    try std.testing.expect(rowProblem("a,\"b,c", ',') != null);
+    try std.testing.expect(rowProblem("a,\"b \"\"q\"\"\",c", ',') == null);
+    try std.testing.expect(rowProblem("plain,row", ',') == null);
+}
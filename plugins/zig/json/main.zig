@@ -35,14 +35,17 @@ pub fn main() !void {
         return;
     }
 
+    var errors = jn_core.ErrorSink.fromArgs(allocator, args, "json");
+    defer errors.close();
+
     if (std.mem.eql(u8, mode, "read")) {
-        try readMode(allocator);
+        try readMode(allocator, &errors);
         return;
     }
 
     if (std.mem.eql(u8, mode, "write")) {
         const config = parseWriteConfig(args);
-        try writeMode(config, allocator);
+        try writeMode(config, allocator, &errors);
         return;
     }
 
@@ -76,7 +79,7 @@ fn parseIndent(value: []const u8) u8 {
         jn_core.exitWithError("json: invalid indent '{s}'", .{value});
 }
 
-fn readMode(allocator: std.mem.Allocator) !void {
+fn readMode(allocator: std.mem.Allocator, errors: *jn_core.ErrorSink) !void {
     var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
     var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
     const reader = &stdin_wrapper.interface;
@@ -91,7 +94,7 @@ fn readMode(allocator: std.mem.Allocator) !void {
     while (jn_core.readLine(reader)) |line| {
         // Check size limit to prevent OOM on maliciously large input
         if (input.items.len + line.len > DEFAULT_MAX_INPUT_SIZE) {
-            jn_core.exitWithError("json: input exceeds maximum size of {d}MB", .{DEFAULT_MAX_INPUT_SIZE / (1024 * 1024)});
+            jn_core.exitWithError("json: input exceeds maximum size of {d}MB\nHint: split a large array with: zq -nc --stream 'fromstream(1|truncate_stream(inputs))'", .{DEFAULT_MAX_INPUT_SIZE / (1024 * 1024)});
         }
         try input.appendSlice(allocator, line);
         try input.append(allocator, '\n');
@@ -102,8 +105,23 @@ fn readMode(allocator: std.mem.Allocator) !void {
         return;
     }
 
-    const parsed = std.json.parseFromSlice(std.json.Value, allocator, input.items, .{}) catch |err| {
-        jn_core.exitWithError("json: parse error: {}", .{err});
+    var scanner = std.json.Scanner.initCompleteInput(allocator, input.items);
+    defer scanner.deinit();
+    var diagnostics: std.json.Diagnostics = .{};
+    scanner.enableDiagnostics(&diagnostics);
+
+    const parsed = std.json.parseFromTokenSource(std.json.Value, allocator, &scanner, .{}) catch |err| {
+        if (!errors.enabled()) jn_core.exitWithError("json: parse error: {}", .{err});
+        // A document either parses or not: route the line where it broke
+        const line = diagnostics.getLine();
+        var message_buf: [128]u8 = undefined;
+        const message = std.fmt.bufPrint(&message_buf, "parse error at column {d}: {s}", .{
+            diagnostics.getColumn(),
+            @errorName(err),
+        }) catch "parse error";
+        errors.report(message, line, jn_core.sidecar.lineAt(input.items, line));
+        jn_core.flushWriter(writer);
+        return;
     };
     defer parsed.deinit();
 
@@ -123,7 +141,7 @@ fn readMode(allocator: std.mem.Allocator) !void {
     jn_core.flushWriter(writer);
 }
 
-fn writeMode(config: WriteConfig, allocator: std.mem.Allocator) !void {
+fn writeMode(config: WriteConfig, allocator: std.mem.Allocator, errors: *jn_core.ErrorSink) !void {
     var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
     var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
     const reader = &stdin_wrapper.interface;
@@ -133,31 +151,45 @@ fn writeMode(config: WriteConfig, allocator: std.mem.Allocator) !void {
     const writer = &stdout_wrapper.interface;
 
     switch (config.format) {
-        .ndjson => try streamNdjson(reader, writer),
-        .array => try writeArray(reader, writer, config.indent),
+        .ndjson => try streamNdjson(reader, writer, allocator, errors),
+        .array => try writeArray(reader, writer, config.indent, allocator, errors),
         .object => try writeSingleObject(reader, writer, allocator),
     }
 
     jn_core.flushWriter(writer);
 }
 
-fn streamNdjson(reader: anytype, writer: anytype) !void {
+/// Whether a line should be written. Lines are passed through unparsed, so
+/// they are only checked when an error sidecar wants the bad ones.
+fn acceptLine(allocator: std.mem.Allocator, errors: *jn_core.ErrorSink, line: []const u8, line_no: u64) bool {
+    if (!errors.enabled() or jn_core.sidecar.isJson(allocator, line)) return true;
+    errors.report("invalid JSON", line_no, line);
+    return false;
+}
+
+fn streamNdjson(reader: anytype, writer: anytype, allocator: std.mem.Allocator, errors: *jn_core.ErrorSink) !void {
+    var line_no: u64 = 0;
     while (jn_core.readLine(reader)) |line| {
+        line_no += 1;
         if (line.len == 0) continue;
+        if (!acceptLine(allocator, errors, line, line_no)) continue;
         writer.writeAll(line) catch |err| jn_core.handleWriteError(err);
         writer.writeByte('\n') catch |err| jn_core.handleWriteError(err);
     }
 }
 
-fn writeArray(reader: anytype, writer: anytype, indent: ?u8) !void {
+fn writeArray(reader: anytype, writer: anytype, indent: ?u8, allocator: std.mem.Allocator, errors: *jn_core.ErrorSink) !void {
     const pretty = indent != null;
     const indent_spaces = indent orelse 0;
 
     writer.writeByte('[') catch |err| jn_core.handleWriteError(err);
 
     var first = true;
+    var line_no: u64 = 0;
     while (jn_core.readLine(reader)) |line| {
+        line_no += 1;
         if (line.len == 0) continue;
+        if (!acceptLine(allocator, errors, line, line_no)) continue;
 
         if (!first) {
             writer.writeByte(',') catch |err| jn_core.handleWriteError(err);
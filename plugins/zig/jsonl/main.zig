@@ -12,6 +12,10 @@ const plugin_meta = jn_plugin.PluginMeta{
 };
 
 pub fn main() !void {
+    var gpa = std.heap.GeneralPurposeAllocator(.{}){};
+    defer _ = gpa.deinit();
+    const allocator = gpa.allocator();
+
     const args = jn_cli.parseArgs();
     const mode = args.get("mode", "read") orelse "read";
 
@@ -20,15 +24,20 @@ pub fn main() !void {
         return;
     }
 
+    var errors = jn_core.ErrorSink.fromArgs(allocator, args, "jsonl");
+    defer errors.close();
+
     if (std.mem.eql(u8, mode, "read") or std.mem.eql(u8, mode, "write")) {
-        try streamNdjson();
+        try streamNdjson(allocator, &errors);
         return;
     }
 
     jn_core.exitWithError("jsonl: unknown mode '{s}'", .{mode});
 }
 
-fn streamNdjson() !void {
+/// Lines are passed through as they are; with an error sidecar, each one is
+/// validated and malformed lines are routed there instead.
+fn streamNdjson(allocator: std.mem.Allocator, errors: *jn_core.ErrorSink) !void {
     var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
     var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
     const reader = &stdin_wrapper.interface;
@@ -37,8 +46,14 @@ fn streamNdjson() !void {
     var stdout_wrapper = std.fs.File.stdout().writerStreaming(&stdout_buf);
     const writer = &stdout_wrapper.interface;
 
+    var line_no: u64 = 0;
     while (jn_core.readLine(reader)) |line| {
+        line_no += 1;
         if (line.len == 0) continue;
+        if (errors.enabled() and !jn_core.sidecar.isJson(allocator, line)) {
+            errors.report("invalid JSON", line_no, line);
+            continue;
+        }
         writer.writeAll(line) catch |err| jn_core.handleWriteError(err);
         writer.writeByte('\n') catch |err| jn_core.handleWriteError(err);
     }
@@ -64,7 +64,44 @@ pub fn main() !void {
     const uri = std.Uri.parse(url_arg) catch
         jn_core.exitWithError("opendal: invalid URL '{s}'", .{url_arg});
 
-    streamUrl(allocator, uri, args);
+    const retries = std.fmt.parseInt(u32, args.get("retries", "0") orelse "0", 10) catch
+        jn_core.exitWithError("opendal: invalid --retries value", .{});
+    if (args.get("timeout", null)) |text| {
+        const seconds = std.fmt.parseFloat(f64, text) catch 0;
+        if (!(seconds > 0)) jn_core.exitWithError("opendal: invalid --timeout value '{s}'", .{text});
+        startWatchdog(@intFromFloat(seconds * std.time.ms_per_s));
+    }
+
+    streamUrl(allocator, uri, args, .{ .retries = retries });
+}
+
+// ## Timeouts
+//
+// The C API has no timeouts and its calls block, so a watchdog thread ends
+// the process when no data has arrived for --timeout seconds.
+
+/// Milliseconds timestamp of the last sign of progress
+var last_progress = std.atomic.Value(i64).init(0);
+
+fn progress() void {
+    last_progress.store(std.time.milliTimestamp(), .monotonic);
+}
+
+fn startWatchdog(timeout_ms: i64) void {
+    progress();
+    const thread = std.Thread.spawn(.{}, watchdog, .{timeout_ms}) catch
+        jn_core.exitWithError("opendal: cannot start timeout watchdog", .{});
+    thread.detach();
+}
+
+fn watchdog(timeout_ms: i64) void {
+    const check_ms: u64 = @intCast(std.math.clamp(@divFloor(timeout_ms, 4), 10, 1000));
+    while (true) {
+        std.Thread.sleep(check_ms * std.time.ns_per_ms);
+        if (std.time.milliTimestamp() - last_progress.load(.monotonic) > timeout_ms) {
+            jn_core.exitWithError("opendal: timed out after {d}ms without data", .{timeout_ms});
+        }
+    }
 }
 
 fn findPositionalUrl() ?[]const u8 {
@@ -78,7 +115,7 @@ fn findPositionalUrl() ?[]const u8 {
     return null;
 }
 
-fn streamUrl(allocator: std.mem.Allocator, uri: std.Uri, args: jn_cli.ArgParser) void {
+fn streamUrl(allocator: std.mem.Allocator, uri: std.Uri, args: jn_cli.ArgParser, policy: jn_core.RetryPolicy) void {
     const scheme = uri.scheme;
     const service: [:0]const u8 = if (std.mem.eql(u8, scheme, "http") or std.mem.eql(u8, scheme, "https"))
         "http"
@@ -150,22 +187,55 @@ fn streamUrl(allocator: std.mem.Allocator, uri: std.Uri, args: jn_cli.ArgParser)
     }
     defer c.opendal_operator_free(op_res.op);
 
-    const reader_res = c.opendal_operator_reader(op_res.op, object_path.ptr);
+    // Retry transient failures, resuming after the bytes already written
+    var written: u64 = 0;
+    var attempt: u32 = 0;
+    while (streamObject(op_res.op, object_path, &written)) |failure| : (attempt += 1) {
+        const transient = failure.err.code == c.OPENDAL_UNEXPECTED or failure.err.code == c.OPENDAL_RATE_LIMITED;
+        if (!transient or attempt == policy.retries) reportErrorAndExit(failure.prefix, failure.err);
+
+        const delay_ms = policy.delay(attempt + 1, null, std.crypto.random);
+        const msg = failure.err.message;
+        std.debug.print("{s}: code={d} msg={s}; retry {d} of {d} in {d}ms\n", .{ failure.prefix, failure.err.code, msg.data[0..msg.len], attempt + 1, policy.retries, delay_ms });
+        c.opendal_error_free(failure.err);
+        std.Thread.sleep(delay_ms * std.time.ns_per_ms);
+        progress();
+    }
+}
+
+const Failure = struct {
+    prefix: []const u8,
+    err: *c.struct_opendal_error,
+};
+
+/// Copy the object to stdout, skipping the first `written` bytes (sent by an
+/// earlier attempt) and counting the rest.
+fn streamObject(op: [*c]const c.struct_opendal_operator, object_path: [:0]const u8, written: *u64) ?Failure {
+    const reader_res = c.opendal_operator_reader(op, object_path.ptr);
     if (reader_res.@"error" != null) {
-        reportErrorAndExit("opendal: reader error", reader_res.@"error");
+        return .{ .prefix = "opendal: reader error", .err = reader_res.@"error" };
     }
     defer c.opendal_reader_free(reader_res.reader);
 
     var buf: [jn_core.STDOUT_BUFFER_SIZE]u8 = undefined;
     const stdout = std.fs.File.stdout();
+    var offset: u64 = 0;
 
     while (true) {
         const chunk = c.opendal_reader_read(reader_res.reader, &buf, buf.len);
         if (chunk.@"error" != null) {
-            reportErrorAndExit("opendal: read error", chunk.@"error");
+            return .{ .prefix = "opendal: read error", .err = chunk.@"error" };
         }
-        if (chunk.size == 0) break;
-        _ = stdout.write(buf[0..chunk.size]) catch |err| jn_core.handleWriteError(err);
+        if (chunk.size == 0) return null;
+        progress();
+
+        var data = buf[0..chunk.size];
+        const skip: usize = @intCast(@min(written.* -| offset, data.len));
+        offset += data.len;
+        data = data[skip..];
+        if (data.len == 0) continue;
+        stdout.writeAll(data) catch |err| jn_core.handleWriteError(err);
+        written.* += data.len;
     }
 }
 
@@ -27,13 +27,16 @@ pub fn main() !void {
         return;
     }
 
+    var errors = jn_core.ErrorSink.fromArgs(allocator, args, "toml");
+    defer errors.close();
+
     if (std.mem.eql(u8, mode, "read")) {
-        try readMode(allocator);
+        try readMode(allocator, &errors);
         return;
     }
 
     if (std.mem.eql(u8, mode, "write")) {
-        try writeMode(allocator);
+        try writeMode(allocator, &errors);
         return;
     }
 
@@ -44,7 +47,7 @@ pub fn main() !void {
 // Read Mode: TOML -> NDJSON
 // =============================================================================
 
-fn readMode(allocator: std.mem.Allocator) !void {
+fn readMode(allocator: std.mem.Allocator, errors: *jn_core.ErrorSink) !void {
     var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
     var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
     const reader = &stdin_wrapper.interface;
@@ -76,10 +79,13 @@ fn readMode(allocator: std.mem.Allocator) !void {
     defer parser.deinit();
 
     const value = parser.parse() catch {
-        jn_core.exitWithError("toml: parse error at line {d}: {s}", .{
-            parser.line_number,
-            parser.error_message orelse "unknown error",
-        });
+        const message = parser.error_message orelse "unknown error";
+        if (!errors.enabled()) {
+            jn_core.exitWithError("toml: parse error at line {d}: {s}", .{ parser.line_number, message });
+        }
+        errors.report(message, parser.line_number, jn_core.sidecar.lineAt(input.items, parser.line_number));
+        jn_core.flushWriter(writer);
+        return;
     };
     // Note: parser.deinit() handles freeing the root value
 
@@ -113,7 +119,7 @@ const WriteError = error{
     WriteFailed,
 };
 
-fn writeMode(allocator: std.mem.Allocator) !void {
+fn writeMode(allocator: std.mem.Allocator, errors: *jn_core.ErrorSink) !void {
     var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
     var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
     const reader = &stdin_wrapper.interface;
@@ -122,16 +128,22 @@ fn writeMode(allocator: std.mem.Allocator) !void {
     var stdout_wrapper = std.fs.File.stdout().writerStreaming(&stdout_buf);
     const writer = &stdout_wrapper.interface;
 
+    var line_no: u64 = 0;
     while (jn_core.readLine(reader)) |line| {
+        line_no += 1;
         if (line.len == 0) continue;
 
         const parsed = std.json.parseFromSlice(std.json.Value, allocator, line, .{}) catch |err| {
-            jn_core.exitWithError("toml: invalid JSON input: {}", .{err});
+            if (!errors.enabled()) jn_core.exitWithError("toml: invalid JSON input: {}", .{err});
+            errors.report("invalid JSON", line_no, line);
+            continue;
         };
         defer parsed.deinit();
 
         if (parsed.value != .object) {
-            jn_core.exitWithError("toml: root must be an object", .{});
+            if (!errors.enabled()) jn_core.exitWithError("toml: root must be an object", .{});
+            errors.report("root must be an object", line_no, line);
+            continue;
         }
 
         writeTomlObject(writer, parsed.value.object, "") catch |err|
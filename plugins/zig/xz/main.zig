@@ -0,0 +1,225 @@
+const std = @import("std");
+const jn_core = @import("jn-core");
+const jn_cli = @import("jn-cli");
+const jn_plugin = @import("jn-plugin");
+
+const plugin_meta = jn_plugin.PluginMeta{
+    .name = "xz",
+    .version = "0.1.0",
+    .matches = &.{".*\\.xz$"},
+    .role = .compression,
+    .modes = &.{ .raw, .write },
+    .supports_raw = true,
+};
+
+pub fn main() !void {
+    var gpa = std.heap.GeneralPurposeAllocator(.{}){};
+    defer _ = gpa.deinit();
+    const allocator = gpa.allocator();
+
+    const args = jn_cli.parseArgs();
+    const mode = args.get("mode", "raw") orelse "raw";
+
+    if (args.has("jn-meta")) {
+        try jn_plugin.outputManifestToStdout(plugin_meta);
+        return;
+    }
+
+    if (std.mem.eql(u8, mode, "raw")) {
+        try decompressMode(allocator);
+    } else if (std.mem.eql(u8, mode, "write")) {
+        try compressMode(allocator);
+    } else {
+        jn_core.exitWithError("xz: unknown mode '{s}' (supported: raw, write)", .{mode});
+    }
+}
+
+fn decompressMode(allocator: std.mem.Allocator) !void {
+    var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
+    var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
+
+    // The decompressor owns (and may grow) its buffer
+    const buffer = try allocator.alloc(u8, jn_core.STDOUT_BUFFER_SIZE);
+    var decomp = std.compress.xz.Decompress.init(&stdin_wrapper.interface, allocator, buffer) catch |err| {
+        jn_core.exitWithError("xz: decompression error: {}", .{err});
+    };
+    defer decomp.deinit();
+
+    const stdout = std.fs.File.stdout();
+    var out_buf: [jn_core.STDOUT_BUFFER_SIZE]u8 = undefined;
+
+    while (true) {
+        const n = decomp.reader.readSliceShort(&out_buf) catch |err| {
+            if (err == error.EndOfStream) break;
+            jn_core.exitWithError("xz: decompression error: {}", .{err});
+        };
+
+        if (n == 0) break;
+        _ = stdout.write(out_buf[0..n]) catch |err| jn_core.handleWriteError(err);
+    }
+}
+
+// ============================================================================
+// Compression
+//
+// The standard library has no LZMA encoder. When the xz command is on PATH,
+// write mode hands the stream to it; otherwise it stores the data in LZMA2
+// "uncompressed" chunks. The result is a valid .xz file that xz and every
+// other reader accept, but it is not smaller than the input.
+// ============================================================================
+
+const stream_header = [_]u8{ 0xfd, '7', 'z', 'X', 'Z', 0x00 };
+const stream_footer_magic = "YZ";
+/// Stream flags: CRC32 integrity check
+const stream_flags = [_]u8{ 0x00, 0x01 };
+
+/// Largest LZMA2 uncompressed chunk
+const chunk_max = 64 * 1024;
+
+/// Run in place of this process when it is on PATH
+const encoder = [_][]const u8{ "xz", "--compress", "--stdout", "--quiet" };
+
+fn compressMode(allocator: std.mem.Allocator) !void {
+    // execv only returns when xz can't be run, with stdin still unread
+    _ = std.process.execv(allocator, &encoder);
+
+    var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
+    var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
+    const reader = &stdin_wrapper.interface;
+
+    var stdout_buf: [jn_core.STDOUT_BUFFER_SIZE]u8 = undefined;
+    var stdout_wrapper = std.fs.File.stdout().writerStreaming(&stdout_buf);
+    const writer = &stdout_wrapper.interface;
+
+    writeStream(reader, writer) catch |err| switch (err) {
+        error.WriteFailed => jn_core.handleWriteError(stdout_wrapper.err orelse err),
+        else => jn_core.exitWithError("xz: compression error: {}", .{err}),
+    };
+
+    jn_core.flushWriter(writer);
+}
+
+/// Write `reader` as an .xz stream with at most one block of stored chunks.
+fn writeStream(reader: *std.Io.Reader, writer: *std.Io.Writer) !void {
+    try writer.writeAll(&stream_header);
+    try writer.writeAll(&stream_flags);
+    try writer.writeInt(u32, std.hash.Crc32.hash(&stream_flags), .little);
+
+    var chunk: [chunk_max]u8 = undefined;
+    var check = std.hash.Crc32.init();
+    var uncompressed_size: u64 = 0;
+    var compressed_size: u64 = 0;
+
+    while (true) {
+        const n = try reader.readSliceShort(&chunk);
+        if (n == 0) break;
+        if (uncompressed_size == 0) try writer.writeAll(&blockHeader());
+
+        // Control byte: 0x01 resets the dictionary (first chunk), 0x02 doesn't
+        try writer.writeByte(if (uncompressed_size == 0) 0x01 else 0x02);
+        try writer.writeInt(u16, @intCast(n - 1), .big);
+        try writer.writeAll(chunk[0..n]);
+
+        check.update(chunk[0..n]);
+        uncompressed_size += n;
+        compressed_size += 3 + n;
+        if (n < chunk.len) break;
+    }
+
+    var index: [32]u8 = undefined;
+    var index_len: usize = 0;
+    index[0] = 0x00; // index indicator
+    index_len += 1;
+
+    if (uncompressed_size > 0) {
+        // End of LZMA2 data, then pad the block to a multiple of four
+        try writer.writeByte(0x00);
+        compressed_size += 1;
+        try writer.writeAll(zeros[0..padding(compressed_size)]);
+        try writer.writeInt(u32, check.final(), .little);
+
+        const unpadded_size = block_header_len + compressed_size + 4;
+        index_len += putVarint(index[index_len..], 1);
+        index_len += putVarint(index[index_len..], unpadded_size);
+        index_len += putVarint(index[index_len..], uncompressed_size);
+    } else {
+        index_len += putVarint(index[index_len..], 0);
+    }
+    const index_padding = padding(index_len);
+    @memset(index[index_len..][0..index_padding], 0);
+    index_len += index_padding;
+    std.mem.writeInt(u32, index[index_len..][0..4], std.hash.Crc32.hash(index[0..index_len]), .little);
+    index_len += 4;
+    try writer.writeAll(index[0..index_len]);
+
+    // Footer: CRC32 of the backward size and flags, then both, then "YZ"
+    var footer: [12]u8 = undefined;
+    std.mem.writeInt(u32, footer[4..8], @intCast(index_len / 4 - 1), .little);
+    @memcpy(footer[8..10], &stream_flags);
+    std.mem.writeInt(u32, footer[0..4], std.hash.Crc32.hash(footer[4..10]), .little);
+    @memcpy(footer[10..12], stream_footer_magic);
+    try writer.writeAll(&footer);
+}
+
+const block_header_len = 12;
+
+/// Block header: no compressed or uncompressed sizes, one LZMA2 filter with
+/// a 1 MiB dictionary
+fn blockHeader() [block_header_len]u8 {
+    var header = [_]u8{ block_header_len / 4 - 1, 0x00, 0x21, 0x01, 0x10, 0, 0, 0, 0, 0, 0, 0 };
+    std.mem.writeInt(u32, header[8..12], std.hash.Crc32.hash(header[0..8]), .little);
+    return header;
+}
+
+const zeros = [_]u8{0} ** 3;
+
+/// Zero bytes needed to reach a multiple of four
+fn padding(len: u64) usize {
+    return @intCast((4 - len % 4) % 4);
+}
+
+/// xz's variable-length integer: 7 bits per byte, low bits first
+fn putVarint(buf: []u8, value: u64) usize {
+    var rest = value;
+    var i: usize = 0;
+    while (rest >= 0x80) : (i += 1) {
+        buf[i] = @as(u8, @truncate(rest)) | 0x80;
+        rest >>= 7;
+    }
+    buf[i] = @intCast(rest);
+    return i + 1;
+}
+
+// Tests
+test "manifest contains plugin name" {
+    var buf: [256]u8 = undefined;
+    var fbs = std.io.fixedBufferStream(&buf);
+    try jn_plugin.outputManifest(fbs.writer(), plugin_meta);
+    const output = fbs.getWritten();
+    try std.testing.expect(std.mem.indexOf(u8, output, "\"xz\"") != null);
+}
+
+test "manifest contains compression role" {
+    var buf: [256]u8 = undefined;
+    var fbs = std.io.fixedBufferStream(&buf);
+    try jn_plugin.outputManifest(fbs.writer(), plugin_meta);
+    const output = fbs.getWritten();
+    try std.testing.expect(std.mem.indexOf(u8, output, "\"compression\"") != null);
+}
+
+test "stored stream decompresses" {
+    const allocator = std.testing.allocator;
+    for ([_][]const u8{ "", "{\"a\":1}\n{\"a\":2}\n" }) |data| {
+        var input = std.Io.Reader.fixed(data);
+        var compressed: std.Io.Writer.Allocating = .init(allocator);
+        defer compressed.deinit();
+        try writeStream(&input, &compressed.writer);
+
+        var packed_input = std.Io.Reader.fixed(compressed.written());
+        var decomp = try std.compress.xz.Decompress.init(&packed_input, allocator, try allocator.alloc(u8, 4096));
+        defer decomp.deinit();
+        const output = try decomp.reader.allocRemaining(allocator, .unlimited);
+        defer allocator.free(output);
+        try std.testing.expectEqualStrings(data, output);
+    }
+}
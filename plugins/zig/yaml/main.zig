@@ -27,8 +27,11 @@ pub fn main() !void {
         return;
     }
 
+    var errors = jn_core.ErrorSink.fromArgs(allocator, args, "yaml");
+    defer errors.close();
+
     if (std.mem.eql(u8, mode, "read")) {
-        try readMode(allocator);
+        try readMode(allocator, &errors);
         return;
     }
 
@@ -37,7 +40,7 @@ pub fn main() !void {
             std.fmt.parseInt(u8, v, 10) catch 2
         else
             2;
-        try writeMode(allocator, indent);
+        try writeMode(allocator, indent, &errors);
         return;
     }
 
@@ -48,7 +51,7 @@ pub fn main() !void {
 // Read Mode: YAML -> NDJSON
 // =============================================================================
 
-fn readMode(allocator: std.mem.Allocator) !void {
+fn readMode(allocator: std.mem.Allocator, errors: *jn_core.ErrorSink) !void {
     var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
     var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
     const reader = &stdin_wrapper.interface;
@@ -97,10 +100,12 @@ fn readMode(allocator: std.mem.Allocator) !void {
         }
     } else |err| {
         if (err != error.EndOfInput) {
-            jn_core.exitWithError("yaml: parse error at line {d}: {s}", .{
-                parser.line_number,
-                parser.error_message orelse "unknown error",
-            });
+            const message = parser.error_message orelse "unknown error";
+            if (!errors.enabled()) {
+                jn_core.exitWithError("yaml: parse error at line {d}: {s}", .{ parser.line_number, message });
+            }
+            // The documents before the error have been written
+            errors.report(message, parser.line_number, jn_core.sidecar.lineAt(input.items, parser.line_number));
         }
     }
 
@@ -111,7 +116,7 @@ fn readMode(allocator: std.mem.Allocator) !void {
 // Write Mode: NDJSON -> YAML
 // =============================================================================
 
-fn writeMode(allocator: std.mem.Allocator, indent: u8) !void {
+fn writeMode(allocator: std.mem.Allocator, indent: u8, errors: *jn_core.ErrorSink) !void {
     var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
     var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
     const reader = &stdin_wrapper.interface;
@@ -121,11 +126,15 @@ fn writeMode(allocator: std.mem.Allocator, indent: u8) !void {
     const writer = &stdout_wrapper.interface;
 
     var first = true;
+    var line_no: u64 = 0;
     while (jn_core.readLine(reader)) |line| {
+        line_no += 1;
         if (line.len == 0) continue;
 
         const parsed = std.json.parseFromSlice(std.json.Value, allocator, line, .{}) catch |err| {
-            jn_core.exitWithError("yaml: invalid JSON input: {}", .{err});
+            if (!errors.enabled()) jn_core.exitWithError("yaml: invalid JSON input: {}", .{err});
+            errors.report("invalid JSON", line_no, line);
+            continue;
         };
         defer parsed.deinit();
 
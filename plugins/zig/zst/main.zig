@@ -0,0 +1,150 @@
+const std = @import("std");
+const jn_core = @import("jn-core");
+const jn_cli = @import("jn-cli");
+const jn_plugin = @import("jn-plugin");
+
+const zstd = std.compress.zstd;
+
+const plugin_meta = jn_plugin.PluginMeta{
+    .name = "zst",
+    .version = "0.1.0",
+    .matches = &.{".*\\.zst$"},
+    .role = .compression,
+    .modes = &.{ .raw, .write },
+    .supports_raw = true,
+};
+
+pub fn main() !void {
+    var gpa = std.heap.GeneralPurposeAllocator(.{}){};
+    defer _ = gpa.deinit();
+    const allocator = gpa.allocator();
+
+    const args = jn_cli.parseArgs();
+    const mode = args.get("mode", "raw") orelse "raw";
+
+    if (args.has("jn-meta")) {
+        try jn_plugin.outputManifestToStdout(plugin_meta);
+        return;
+    }
+
+    if (std.mem.eql(u8, mode, "raw")) {
+        try decompressMode(allocator);
+    } else if (std.mem.eql(u8, mode, "write")) {
+        try compressMode(allocator);
+    } else {
+        jn_core.exitWithError("zst: unknown mode '{s}' (supported: raw, write)", .{mode});
+    }
+}
+
+fn decompressMode(allocator: std.mem.Allocator) !void {
+    var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
+    var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
+
+    const window_buf = try allocator.alloc(u8, jn_core.ZSTD_WINDOW_SIZE);
+    defer allocator.free(window_buf);
+    var decomp = zstd.Decompress.init(&stdin_wrapper.interface, window_buf, .{});
+
+    const stdout = std.fs.File.stdout();
+    var out_buf: [jn_core.STDOUT_BUFFER_SIZE]u8 = undefined;
+
+    while (true) {
+        const n = decomp.reader.readSliceShort(&out_buf) catch |err| {
+            if (err == error.EndOfStream) break;
+            jn_core.exitWithError("zst: decompression error: {}", .{decomp.err orelse err});
+        };
+
+        if (n == 0) break;
+        _ = stdout.write(out_buf[0..n]) catch |err| jn_core.handleWriteError(err);
+    }
+}
+
+// ============================================================================
+// Compression
+//
+// The standard library has no zstd encoder. When the zstd command is on PATH,
+// write mode hands the stream to it; otherwise it stores the data in raw
+// blocks. The result is a valid .zst file that zstd and every other reader
+// accept, but it is not smaller than the input.
+// ============================================================================
+
+const frame_magic: u32 = 0xfd2fb528;
+/// Window descriptor for a 128 KiB window: one block at a time
+const window_descriptor: u8 = (17 - 10) << 3;
+const raw_block_max = 128 * 1024;
+
+/// Run in place of this process when it is on PATH
+const encoder = [_][]const u8{ "zstd", "--compress", "--stdout", "--quiet" };
+
+fn compressMode(allocator: std.mem.Allocator) !void {
+    // execv only returns when zstd can't be run, with stdin still unread
+    _ = std.process.execv(allocator, &encoder);
+
+    var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
+    var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
+    const reader = &stdin_wrapper.interface;
+
+    var stdout_buf: [jn_core.STDOUT_BUFFER_SIZE]u8 = undefined;
+    var stdout_wrapper = std.fs.File.stdout().writerStreaming(&stdout_buf);
+    const writer = &stdout_wrapper.interface;
+
+    writeFrame(reader, writer) catch |err| switch (err) {
+        error.WriteFailed => jn_core.handleWriteError(stdout_wrapper.err orelse err),
+        else => jn_core.exitWithError("zst: compression error: {}", .{err}),
+    };
+
+    jn_core.flushWriter(writer);
+}
+
+/// Write `reader` as one zstd frame of raw blocks.
+fn writeFrame(reader: *std.Io.Reader, writer: *std.Io.Writer) !void {
+    try writer.writeInt(u32, frame_magic, .little);
+    // Frame header descriptor: no content size, no checksum, not single-segment
+    try writer.writeByte(0x00);
+    try writer.writeByte(window_descriptor);
+
+    var block: [raw_block_max]u8 = undefined;
+    while (true) {
+        const n = try reader.readSliceShort(&block);
+        const last = n < block.len;
+        // Block header: size, type (0 = raw), last-block flag
+        const header: u24 = (@as(u24, @intCast(n)) << 3) | @intFromBool(last);
+        try writer.writeInt(u24, header, .little);
+        try writer.writeAll(block[0..n]);
+        if (last) break;
+    }
+}
+
+// Tests
+test "manifest contains plugin name" {
+    var buf: [256]u8 = undefined;
+    var fbs = std.io.fixedBufferStream(&buf);
+    try jn_plugin.outputManifest(fbs.writer(), plugin_meta);
+    const output = fbs.getWritten();
+    try std.testing.expect(std.mem.indexOf(u8, output, "\"zst\"") != null);
+}
+
+test "manifest contains compression role" {
+    var buf: [256]u8 = undefined;
+    var fbs = std.io.fixedBufferStream(&buf);
+    try jn_plugin.outputManifest(fbs.writer(), plugin_meta);
+    const output = fbs.getWritten();
+    try std.testing.expect(std.mem.indexOf(u8, output, "\"compression\"") != null);
+}
+
+test "stored frame decompresses" {
+    const allocator = std.testing.allocator;
+    for ([_][]const u8{ "", "{\"a\":1}\n{\"a\":2}\n" }) |data| {
+        var input = std.Io.Reader.fixed(data);
+        var compressed: std.Io.Writer.Allocating = .init(allocator);
+        defer compressed.deinit();
+        try writeFrame(&input, &compressed.writer);
+
+        var packed_input = std.Io.Reader.fixed(compressed.written());
+        const window_buf = try allocator.alloc(u8, jn_core.ZSTD_WINDOW_SIZE);
+        defer allocator.free(window_buf);
+        var decomp = zstd.Decompress.init(&packed_input, window_buf, .{});
+        const output = try decomp.reader.allocRemaining(allocator, .unlimited);
+        defer allocator.free(output);
+        try std.testing.expectEqualStrings(data, output);
+    }
+}
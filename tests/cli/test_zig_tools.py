@@ -4,10 +4,18 @@ These tests verify the Zig implementations of JN tools work correctly
 by invoking the binaries directly with subprocess.
 """
 
+import base64
+import bz2
+import gzip
 import json
+import lzma
 import os
 import subprocess
+import threading
+import time
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
 from pathlib import Path
+from urllib.parse import parse_qs, urlsplit
 
 import pytest
 
@@ -72,6 +80,64 @@ def ndjson_data():
     return '{"name":"Alice","age":30}\n{"name":"Bob","age":25}\n{"name":"Carol","age":35}\n'
 
 
+@pytest.fixture
+def api_server():
+    """Local HTTP server answering with ``server.respond(path, query)``.
+
+    ``respond`` returns ``(body, headers)`` or ``(body, headers, status)``;
+    every request path (with query) is recorded in ``server.requests``, and
+    its method, headers and body in ``server.calls``. A ``Content-Encoding:
+    gzip`` header compresses the body.
+    """
+
+    class Handler(BaseHTTPRequestHandler):
+        def do_GET(self):
+            url = urlsplit(self.path)
+            self.server.requests.append(self.path)
+            sent = self.rfile.read(int(self.headers.get("Content-Length") or 0))
+            self.server.calls.append({"method": self.command, "headers": dict(self.headers), "body": sent})
+            body, headers, *status = self.server.respond(url.path, parse_qs(url.query))
+            data = json.dumps(body).encode()
+            if headers.get("Content-Encoding") == "gzip":
+                data = gzip.compress(data)
+            self.send_response(status[0] if status else 200)
+            self.send_header("Content-Type", "application/json")
+            self.send_header("Content-Length", str(len(data)))
+            for key, value in headers.items():
+                self.send_header(key, value)
+            self.end_headers()
+            self.wfile.write(data)
+
+        do_POST = do_PUT = do_DELETE = do_GET
+
+        def log_message(self, *args):
+            pass
+
+    class Server(ThreadingHTTPServer):
+        daemon_threads = True
+
+        def handle_error(self, request, client_address):
+            pass  # clients that time out hang up mid-response
+
+    server = Server(("127.0.0.1", 0), Handler)
+    server.requests = []
+    server.calls = []
+    server.base_url = f"http://127.0.0.1:{server.server_port}"
+    thread = threading.Thread(target=server.serve_forever, daemon=True)
+    thread.start()
+    yield server
+    server.shutdown()
+    server.server_close()
+
+
+def write_http_profile(tmp_path, name: str, profile: dict) -> dict:
+    """Write ``@pages/<name>`` under a fake HOME and return the env to use it."""
+    profile_dir = tmp_path / ".local" / "jn" / "profiles" / "http" / "pages"
+    profile_dir.mkdir(parents=True, exist_ok=True)
+    (profile_dir / f"{name}.json").write_text(json.dumps(profile))
+    return {"HOME": str(tmp_path)}
+
+
 # =============================================================================
 # jn-cat tests
 # =============================================================================
@@ -115,6 +181,501 @@ class TestJnCat:
         assert len(lines) == 1
         assert json.loads(lines[0])["name"] == "Alice"
 
+    def test_cat_errors_sidecar(self, tmp_path):
+        """jn-cat --errors should route unreadable CSV rows to the sidecar."""
+        path = tmp_path / "data.csv"
+        path.write_text('name,age\nAlice,30\nBob,"31\nCarol,32\n')
+        errors = tmp_path / "errors.jsonl"
+
+        code, stdout, stderr = run_tool("jn-cat", [f"--errors={errors}", str(path)])
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        names = [json.loads(l)["name"] for l in stdout.strip().split("\n") if l]
+        assert names == ["Alice", "Carol"]
+
+        records = [json.loads(l) for l in errors.read_text().splitlines()]
+        assert records == [{
+            "_error": "unterminated quoted field",
+            "_line": 3,
+            "_raw": 'Bob,"31',
+            "_source": str(path),
+            "_stage": "csv",
+        }]
+
+        code, stdout, stderr = run_tool("jn-cat", [f"--errors={errors}", "--max-errors=0", str(path)])
+        assert code == 1
+        assert "too many errors" in stderr
+
+    def test_cat_explain(self, tmp_path):
+        """jn-cat --explain=json should describe the pipeline without running it."""
+        path = tmp_path / "data.csv.gz"
+        path.write_bytes(b"not really gzip")
+
+        code, stdout, stderr = run_tool("jn-cat", ["--explain=json", str(path)])
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        report = json.loads(stdout)
+        assert report["tool"] == "jn-cat"
+        assert report["address"]["compression"] == "gzip"
+        assert report["address"]["inferred_format"] == "csv"
+        assert [p["name"] for p in report["plugins"]] == ["gz", "csv"]
+        assert all(p["source"] and p["priority"] >= 1 for p in report["plugins"])
+
+        [stages] = report["pipelines"]
+        assert len(stages) == 2
+        assert stages[0]["stdin"] == f"file:{path}"
+        assert stages[0]["argv"][1:] == ["--mode=raw"]
+        assert stages[1]["argv"][1:] == ["--mode=read"]
+        assert stages[1]["stdin"] == "pipe"
+        assert stages[1]["stdout"] == "inherit"
+
+        code, stdout, stderr = run_tool("jn-cat", [str(path)], env={"JN_EXPLAIN": "1"})
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert "Pipelines:" in stdout
+        assert "--mode=raw" in stdout
+
+    def test_cat_explain_redacts_profile(self, tmp_path):
+        """jn-cat --explain should show the merged profile with secrets redacted."""
+        profile_dir = tmp_path / ".local" / "jn" / "profiles" / "http" / "explaintest"
+        profile_dir.mkdir(parents=True)
+        (profile_dir / "_meta.json").write_text(json.dumps({
+            "base_url": "https://api.example.com",
+            "headers": {"Authorization": "Bearer ${EXPLAIN_TOKEN}", "Accept": "application/json"},
+        }))
+        (profile_dir / "users.json").write_text(json.dumps({"path": "/users"}))
+
+        env = {"HOME": str(tmp_path), "EXPLAIN_TOKEN": "tok-12345"}
+        code, stdout, stderr = run_tool("jn-cat", ["--explain=json", "@explaintest/users"], env=env)
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert "tok-12345" not in stdout
+        report = json.loads(stdout)
+        assert report["profile"]["path"] == str(profile_dir / "users.json")
+        config = report["profile"]["config"]
+        assert config["path"] == "/users"
+        assert config["headers"] == {"Authorization": "[REDACTED]", "Accept": "application/json"}
+        [request] = report["requests"]
+        assert request["method"] == "GET"
+        assert request["url"].startswith("https://api.example.com/users")
+        assert "Authorization: [REDACTED]" in request["headers"]
+
+    def test_cat_reports_failed_stage(self, tmp_path):
+        """jn-cat should exit with the first failing stage's status and name it."""
+        path = tmp_path / "data.csv.gz"
+        path.write_bytes(b"not really gzip")
+
+        code, stdout, stderr = run_tool("jn-cat", [str(path)])
+        assert code == 1
+        assert "stage 1 of 2 (gz) exited with status 1" in stderr
+
+    def test_cat_bzip2_csv(self, tmp_path):
+        """jn-cat should decompress .bz2 files before parsing."""
+        path = tmp_path / "data.csv.bz2"
+        path.write_bytes(bz2.compress(b"name,age\nAlice,30\nBob,25\n"))
+
+        code, stdout, stderr = run_tool("jn-cat", [str(path)])
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        names = [json.loads(l)["name"] for l in stdout.strip().split("\n") if l]
+        assert names == ["Alice", "Bob"]
+
+    def test_cat_http_profile_cursor_pages(self, tmp_path, api_server):
+        """Cursor pagination should stream the records of every page."""
+        def respond(path, query):
+            page = int(query.get("cursor", ["0"])[0])
+            cursor = str(page + 1) if page < 2 else None
+            return {"data": [{"id": page * 2}, {"id": page * 2 + 1}], "meta": {"next": cursor}}, {}
+
+        api_server.respond = respond
+        env = write_http_profile(tmp_path, "events", {
+            "base_url": api_server.base_url,
+            "path": "/events",
+            "items_path": "data",
+            "pagination": {"type": "cursor", "cursor_path": "meta.next"},
+        })
+        code, stdout, stderr = run_tool("jn-cat", ["@pages/events?kind=a"], env=env)
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert [json.loads(l)["id"] for l in stdout.splitlines()] == list(range(6))
+        assert api_server.requests == ["/events?kind=a", "/events?kind=a&cursor=1", "/events?kind=a&cursor=2"]
+
+    def test_cat_http_profile_link_pages(self, tmp_path, api_server):
+        """Link header pagination should follow rel=next until it is absent."""
+        def respond(path, query):
+            page = int(query.get("page", ["1"])[0])
+            headers = {"Link": f'<{api_server.base_url}/users?page={page + 1}>; rel="next"'} if page < 3 else {}
+            return [{"page": page}], headers
+
+        api_server.respond = respond
+        env = write_http_profile(tmp_path, "users", {
+            "base_url": api_server.base_url,
+            "path": "/users",
+            "pagination": {"type": "link"},
+        })
+        code, stdout, stderr = run_tool("jn-cat", ["@pages/users"], env=env)
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert [json.loads(l) for l in stdout.splitlines()] == [{"page": 1}, {"page": 2}, {"page": 3}]
+
+    def test_cat_http_profile_offset_pages(self, tmp_path, api_server):
+        """Offset pagination should stop on a short page."""
+        rows = list(range(5))
+
+        def respond(path, query):
+            offset, limit = int(query["offset"][0]), int(query["limit"][0])
+            return {"results": rows[offset:offset + limit]}, {}
+
+        api_server.respond = respond
+        env = write_http_profile(tmp_path, "rows", {
+            "base_url": api_server.base_url,
+            "path": "/rows",
+            "items_path": "results",
+            "pagination": {"type": "offset", "limit": 2},
+        })
+        code, stdout, stderr = run_tool("jn-cat", ["@pages/rows"], env=env)
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert [json.loads(l) for l in stdout.splitlines()] == rows
+        assert len(api_server.requests) == 3
+
+    def test_cat_http_profile_stops_when_reader_stops(self, tmp_path, api_server):
+        """An endless paginated profile should stop fetching once stdout closes."""
+        def respond(path, query):
+            page = int(query.get("page", ["1"])[0])
+            return [{"page": page, "row": i} for i in range(100)], {}
+
+        api_server.respond = respond
+        env = write_http_profile(tmp_path, "endless", {
+            "base_url": api_server.base_url,
+            "path": "/endless",
+            "pagination": {"type": "page"},
+        })
+        tool_path = get_tool_path("jn-cat")
+        if not tool_path.exists():
+            pytest.skip("Tool jn-cat not built (run 'make zig-tools')")
+        proc = subprocess.Popen(
+            [str(tool_path), "@pages/endless"],
+            stdout=subprocess.PIPE,
+            stderr=subprocess.PIPE,
+            env={**os.environ, **env},
+        )
+        assert json.loads(proc.stdout.readline()) == {"page": 1, "row": 0}
+        proc.stdout.close()
+        assert proc.wait(timeout=30) == 0, proc.stderr.read()
+        assert len(api_server.requests) < 100
+
+    def test_cat_http_profile_explain_pagination(self, tmp_path):
+        """--explain should show the first request and the pagination strategy."""
+        env = write_http_profile(tmp_path, "explained", {
+            "base_url": "https://api.example.com",
+            "path": "/items",
+            "items_path": "items",
+            "pagination": {"type": "page", "size_param": "per_page", "limit": 50},
+        })
+        code, stdout, stderr = run_tool("jn-cat", ["--explain=json", "@pages/explained"], env=env)
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        report = json.loads(stdout)
+        [request] = report["requests"]
+        assert request["url"] == "https://api.example.com/items?page=1&per_page=50"
+        assert report["pipelines"] == []
+        assert any("page pagination" in note for note in report["notes"])
+
+    def test_cat_http_profile_retries_with_retry_after(self, tmp_path, api_server):
+        """Paginated requests should retry a 503, waiting as Retry-After says."""
+        failures = {"/jobs?page=2": 1}
+
+        def respond(path, query):
+            key = f"{path}?page={query['page'][0]}"
+            if failures.get(key):
+                failures[key] -= 1
+                return {"error": "busy"}, {"Retry-After": "1"}, 503
+            page = int(query["page"][0])
+            return ([{"page": page}] if page <= 2 else []), {}
+
+        api_server.respond = respond
+        env = write_http_profile(tmp_path, "jobs", {
+            "base_url": api_server.base_url,
+            "path": "/jobs",
+            "retries": 2,
+            "pagination": {"type": "page"},
+        })
+        start = time.monotonic()
+        code, stdout, stderr = run_tool("jn-cat", ["@pages/jobs"], env=env)
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert [json.loads(l) for l in stdout.splitlines()] == [{"page": 1}, {"page": 2}]
+        assert "HTTP 503 fetching" in stderr and "retry 1 of 2 in 1000ms" in stderr
+        assert time.monotonic() - start >= 1
+        assert api_server.requests == ["/jobs?page=1", "/jobs?page=2", "/jobs?page=2", "/jobs?page=3"]
+
+    def test_cat_http_profile_gives_up_after_retries(self, tmp_path, api_server):
+        """--retries should override the profile and fail once retries run out."""
+        api_server.respond = lambda path, query: ({"error": "down"}, {}, 500)
+        env = write_http_profile(tmp_path, "down", {
+            "base_url": api_server.base_url,
+            "path": "/down",
+            "retries": 5,
+            "pagination": {"type": "link"},
+        })
+        code, stdout, stderr = run_tool("jn-cat", ["--retries=1", "@pages/down"], env=env)
+        assert code == 1
+        assert "HTTP error 500 fetching profile @pages/down" in stderr
+        assert len(api_server.requests) == 2
+
+    def test_cat_http_profile_does_not_retry_client_errors(self, tmp_path, api_server):
+        """A 404 is not transient and should fail without retrying."""
+        api_server.respond = lambda path, query: ({"error": "missing"}, {}, 404)
+        env = write_http_profile(tmp_path, "missing", {
+            "base_url": api_server.base_url,
+            "path": "/missing",
+            "retries": 3,
+            "pagination": {"type": "link"},
+        })
+        code, stdout, stderr = run_tool("jn-cat", ["@pages/missing"], env=env)
+        assert code == 1
+        assert "HTTP error 404" in stderr
+        assert len(api_server.requests) == 1
+
+    def test_cat_http_profile_rate_limit_spans_pages(self, tmp_path, api_server):
+        """A rate limit should pace every page request."""
+        def respond(path, query):
+            page = int(query["page"][0])
+            return ([{"page": page}] if page <= 4 else []), {}
+
+        api_server.respond = respond
+        env = write_http_profile(tmp_path, "paced", {
+            "base_url": api_server.base_url,
+            "path": "/paced",
+            "rate": "5/sec",
+            "pagination": {"type": "page"},
+        })
+        start = time.monotonic()
+        code, stdout, stderr = run_tool("jn-cat", ["@pages/paced"], env=env)
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert len(stdout.splitlines()) == 4
+        # Five requests, the last four each 0.2s after the one before
+        assert time.monotonic() - start >= 0.75
+
+    def test_cat_http_url_timeout(self, api_server):
+        """--timeout should abandon a request that takes too long."""
+        def respond(path, query):
+            time.sleep(3)
+            return [], {}
+
+        api_server.respond = respond
+        start = time.monotonic()
+        code, stdout, stderr = run_tool("jn-cat", ["--timeout=0.5", f"{api_server.base_url}/slow.jsonl"])
+        assert code != 0
+        assert time.monotonic() - start < 2.5
+
+    @pytest.mark.parametrize("timeout", ["0", "-1", "nan", "inf", "1e300", "86401"])
+    def test_cat_rejects_bad_timeout(self, api_server, timeout):
+        """--timeout should be a usage error unless it is positive and at most a day."""
+        code, stdout, stderr = run_tool("jn-cat", [f"--timeout={timeout}", f"{api_server.base_url}/users.jsonl"])
+        assert code == 2
+        assert "invalid timeout" in stderr
+
+    def test_cat_http_url_retries_unavailable(self, api_server):
+        """Streamed URL requests should be retried before the body is read, honouring Retry-After."""
+        failures = [1]
+
+        def respond(path, query):
+            if failures[0]:
+                failures[0] -= 1
+                return {"error": "busy"}, {"Retry-After": "1"}, 503
+            return {"ok": True}, {}
+
+        api_server.respond = respond
+        code, stdout, stderr = run_tool("jn-cat", ["--retries=2", f"{api_server.base_url}/status.json"])
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert json.loads(stdout) == {"ok": True}
+        assert "HTTP 503 fetching" in stderr and "retry 1 of 2 in 1000ms" in stderr
+        assert len(api_server.requests) == 2
+
+    def test_cat_http_url_meta_envelope(self, api_server):
+        """--meta should add the response status, URL and headers as _http."""
+        api_server.respond = lambda path, query: ([{"id": 1}, {"id": 2}], {"X-Request-Id": "abc"})
+        code, stdout, stderr = run_tool("jn-cat", ["--meta", f"{api_server.base_url}/users.json"])
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        records = [json.loads(l) for l in stdout.splitlines()]
+        assert [r["id"] for r in records] == [1, 2]
+        meta = records[0]["_http"]
+        assert meta["status"] == 200
+        assert meta["url"] == f"{api_server.base_url}/users.json"
+        assert meta["headers"]["x-request-id"] == "abc"
+        assert meta["headers"]["content-type"] == "application/json"
+
+    def test_cat_http_url_gzip_encoding(self, api_server):
+        """A gzip Content-Encoding should be decoded before parsing."""
+        api_server.respond = lambda path, query: ({"compressed": True}, {"Content-Encoding": "gzip"})
+        code, stdout, stderr = run_tool("jn-cat", [f"{api_server.base_url}/data.json"])
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert json.loads(stdout) == {"compressed": True}
+        assert "gzip" in api_server.calls[0]["headers"].get("Accept-Encoding", "")
+
+    def test_cat_http_profile_post_body_and_redirect(self, tmp_path, api_server):
+        """A profile's method and JSON body should be sent; a 303 continues as GET."""
+        def respond(path, query):
+            if path == "/search":
+                return {}, {"Location": "/results"}, 303
+            return [{"hit": 1}], {}
+
+        api_server.respond = respond
+        env = write_http_profile(tmp_path, "search", {
+            "base_url": api_server.base_url,
+            "path": "/search",
+            "method": "post",
+            "body": {"q": "jn"},
+        })
+        code, stdout, stderr = run_tool("jn-cat", ["@pages/search"], env=env)
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert json.loads(stdout) == {"hit": 1}
+        post, get = api_server.calls
+        assert post["method"] == "POST" and json.loads(post["body"]) == {"q": "jn"}
+        assert post["headers"]["Content-Type"] == "application/json"
+        assert get["method"] == "GET" and api_server.requests[1] == "/results"
+
+    def test_cat_http_url_proxy_from_env(self, api_server):
+        """http_proxy should route requests through the proxy."""
+        api_server.respond = lambda path, query: ({"proxied": True}, {})
+        code, stdout, stderr = run_tool(
+            "jn-cat", ["http://data.example.invalid/item.json"], env={"http_proxy": api_server.base_url}
+        )
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert json.loads(stdout) == {"proxied": True}
+        assert api_server.requests == ["http://data.example.invalid/item.json"]
+
+    def test_cat_each_merges_responses_in_order(self, tmp_path, api_server):
+        """--each should send one request per record and keep input order."""
+        def respond(path, query):
+            user_id = path.rsplit("/", 1)[1]
+            time.sleep(0.3 if user_id == "1" else 0)
+            return {"name": f"user {user_id}", "id": int(user_id)}, {}
+
+        api_server.respond = respond
+        env = write_http_profile(tmp_path, "user", {"base_url": api_server.base_url, "path": "/users/{id}"})
+        records = "".join(json.dumps({"id": i, "src": "in"}) + "\n" for i in (1, 2, 3))
+        code, stdout, stderr = run_tool("jn-cat", ["--each", "--concurrency=3", "@pages/user"], input_data=records, env=env)
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert [json.loads(l) for l in stdout.splitlines()] == [
+            {"id": i, "src": "in", "name": f"user {i}"} for i in (1, 2, 3)
+        ]
+        assert sorted(api_server.requests) == ["/users/1", "/users/2", "/users/3"]
+
+    def test_cat_each_body_template_into(self, tmp_path, api_server):
+        """--each should render the body per record and nest the response with --into."""
+        api_server.respond = lambda path, query: ({"score": 0.9}, {})
+        env = write_http_profile(tmp_path, "score", {
+            "base_url": api_server.base_url,
+            "path": "/score",
+            "method": "POST",
+            "body": {"text": "{review.text}", "lang": "{lang}"},
+        })
+        code, stdout, stderr = run_tool(
+            "jn-cat", ["--each", "--into=sentiment", "@pages/score"],
+            input_data='{"review": {"text": "great"}, "lang": "en"}\n', env=env,
+        )
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert json.loads(stdout) == {"review": {"text": "great"}, "lang": "en", "sentiment": {"score": 0.9}}
+        [call] = api_server.calls
+        assert call["method"] == "POST"
+        assert json.loads(call["body"]) == {"text": "great", "lang": "en"}
+
+    def test_cat_each_routes_failures_to_errors(self, tmp_path, api_server):
+        """Failed --each records should go to the --errors file."""
+        def respond(path, query):
+            if path == "/users/2":
+                return {"error": "not found"}, {}, 404
+            return {"found": True}, {}
+
+        api_server.respond = respond
+        env = write_http_profile(tmp_path, "user", {"base_url": api_server.base_url, "path": "/users/{id}"})
+        errors = tmp_path / "errors.jsonl"
+        code, stdout, stderr = run_tool(
+            "jn-cat", ["--each", f"--errors={errors}", "@pages/user"],
+            input_data='{"id": 1}\n{"id": 2}\n{"name": "x"}\n', env=env,
+        )
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert [json.loads(l) for l in stdout.splitlines()] == [{"id": 1, "found": True}]
+        failures = [json.loads(l) for l in errors.read_text().splitlines()]
+        assert [f["_line"] for f in failures] == [2, 3]
+        assert "HTTP 404" in failures[0]["_error"]
+        assert "'id'" in failures[1]["_error"]
+
+    def test_cat_profile_placeholders_need_each(self, tmp_path):
+        """A profile with {field} placeholders should ask for --each."""
+        env = write_http_profile(tmp_path, "user", {"base_url": "https://api.example.com", "path": "/users/{id}"})
+        code, stdout, stderr = run_tool("jn-cat", ["@pages/user"], env=env)
+        assert code == 1
+        assert "--each" in stderr
+
+    def test_cat_profile_basic_auth(self, tmp_path, api_server):
+        """An auth block of type basic should send HTTP basic credentials."""
+        api_server.respond = lambda path, query: ({"ok": True}, {})
+        env = write_http_profile(tmp_path, "private", {
+            "base_url": api_server.base_url,
+            "path": "/private",
+            "auth": {"type": "basic", "username": "ada", "password": "${API_PASS}"},
+        })
+        code, stdout, stderr = run_tool("jn-cat", ["@pages/private"], env={**env, "API_PASS": "s3cr3t"})
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert json.loads(stdout) == {"ok": True}
+        [call] = api_server.calls
+        assert call["headers"]["Authorization"] == "Basic " + base64.b64encode(b"ada:s3cr3t").decode()
+
+    def test_cat_profile_oauth2_caches_and_renews(self, tmp_path, api_server):
+        """OAuth2 tokens should be fetched once, cached, and renewed after a 401."""
+        issued = []
+
+        def respond(path, query):
+            if path == "/token":
+                issued.append(parse_qs(api_server.calls[-1]["body"].decode()))
+                return {"access_token": f"t{len(issued)}", "token_type": "Bearer", "expires_in": 3600}, {}
+            if api_server.calls[-1]["headers"].get("Authorization") != f"Bearer t{len(issued)}" or len(issued) < 2:
+                return {"error": "invalid_token"}, {}, 401
+            return {"ok": True}, {}
+
+        api_server.respond = respond
+        env = write_http_profile(tmp_path, "secure", {
+            "base_url": api_server.base_url,
+            "path": "/data",
+            "auth": {
+                "type": "oauth2",
+                "token_url": f"{api_server.base_url}/token",
+                "client_id": "jn",
+                "client_secret": "${CLIENT_SECRET}",
+                "scope": "read",
+            },
+        })
+        env = {**env, "CLIENT_SECRET": "c-secret", "JN_HOME": str(tmp_path / "jn_home")}
+        code, stdout, stderr = run_tool("jn-cat", ["@pages/secure"], env=env)
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert json.loads(stdout) == {"ok": True}
+        assert issued[0] == {"grant_type": ["client_credentials"], "scope": ["read"], "client_id": ["jn"], "client_secret": ["c-secret"]}
+        assert api_server.requests == ["/token", "/data", "/token", "/data"]
+
+        [cached] = (tmp_path / "jn_home" / "cache" / "auth").iterdir()
+        assert json.loads(cached.read_text())["access_token"] == "t2"
+        assert cached.stat().st_mode & 0o777 == 0o600
+
+        # The next run reuses the cached token
+        code, stdout, stderr = run_tool("jn-cat", ["@pages/secure"], env=env)
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert api_server.requests[4:] == ["/data"]
+
+    def test_cat_profile_oauth2_token_failure(self, tmp_path, api_server):
+        """A rejected token request should fail with the server's reason."""
+        api_server.respond = lambda path, query: ({"error": "invalid_client", "error_description": "bad secret"}, {}, 401)
+        env = write_http_profile(tmp_path, "secure", {
+            "base_url": api_server.base_url,
+            "auth": {"type": "oauth2", "token_url": f"{api_server.base_url}/token", "client_id": "jn"},
+        })
+        code, stdout, stderr = run_tool("jn-cat", ["@pages/secure"], env={**env, "JN_HOME": str(tmp_path / "jn_home")})
+        assert code == 1
+        assert "invalid_client: bad secret" in stderr
+        assert api_server.requests == ["/token"]
+
+    def test_cat_path_with_quotes(self, tmp_path):
+        """jn-cat should read files whose names would need shell quoting."""
+        path = tmp_path / "it's $(data).csv"
+        path.write_text("name\nAlice\n")
+
+        code, stdout, stderr = run_tool("jn-cat", [str(path)])
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert json.loads(stdout.strip()) == {"name": "Alice"}
+
     def test_cat_help(self):
         """jn-cat --help should show usage."""
         code, stdout, stderr = run_tool("jn-cat", ["--help"])
@@ -161,6 +722,41 @@ class TestJnPut:
         assert "name" in lines[0]
         assert "Alice" in lines[1]
 
+    def test_put_explain(self, tmp_path, ndjson_data):
+        """jn-put --explain should show the write pipeline and leave the file alone."""
+        out_file = tmp_path / "out.csv"
+
+        code, stdout, stderr = run_tool("jn-put", ["--explain=json", str(out_file)], input_data=ndjson_data)
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert not out_file.exists()
+        report = json.loads(stdout)
+        assert report["tool"] == "jn-put"
+        assert [p["name"] for p in report["plugins"]] == ["csv"]
+        [[stage]] = report["pipelines"]
+        assert stage["argv"][1] == "--mode=write"
+        assert stage["stdout"] == f"file:{out_file}"
+
+    def test_put_compressed_ndjson(self, tmp_path, ndjson_data):
+        """jn-put should compress output whose name ends in .xz."""
+        out_file = tmp_path / "out.ndjson.xz"
+
+        code, stdout, stderr = run_tool("jn-put", [str(out_file)], input_data=ndjson_data)
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert lzma.decompress(out_file.read_bytes()).decode() == ndjson_data
+
+    def test_put_compressed_csv_explain(self, tmp_path):
+        """jn-put should write compressed CSV through the format and compression plugins."""
+        out_file = tmp_path / "out.csv.zst"
+
+        code, stdout, stderr = run_tool("jn-put", ["--explain=json", str(out_file)])
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        report = json.loads(stdout)
+        assert [p["name"] for p in report["plugins"]] == ["csv", "zst"]
+        [[csv, zst]] = report["pipelines"]
+        assert csv["argv"][1] == "--mode=write"
+        assert zst["argv"][1:] == ["--mode=write"]
+        assert zst["stdout"] == f"file:{out_file}"
+
     def test_put_help(self):
         """jn-put --help should show usage."""
         code, stdout, stderr = run_tool("jn-put", ["--help"])
@@ -193,6 +789,25 @@ class TestJnFilter:
         lines = [l for l in stdout.strip().split("\n") if l]
         assert len(lines) == 2  # Alice (30) and Carol (35)
 
+    def test_filter_jobs_keeps_order(self):
+        """jn-filter --jobs should evaluate in parallel and keep record order."""
+        data = "".join(f'{{"id": {i}, "pad": "{"x" * 500}"}}\n' for i in range(5000))
+        code, stdout, stderr = run_tool("jn-filter", ["--jobs=4", ".id"], input_data=data)
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert stdout.split() == [str(i) for i in range(5000)]
+
+    def test_filter_errors_sidecar(self, tmp_path):
+        """jn-filter --errors should route records that fail to the sidecar."""
+        errors = tmp_path / "errors.jsonl"
+        code, stdout, stderr = run_tool(
+            "jn-filter", [f"--errors={errors}", ".a + 1"],
+            input_data='{"a":1}\n{"a":"x"}\n'
+        )
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert stdout.split() == ["2"]
+        record = json.loads(errors.read_text())
+        assert (record["_line"], record["_raw"], record["_stage"]) == (2, '{"a":"x"}', "zq")
+
     def test_filter_help(self):
         """jn-filter --help should show usage."""
         code, stdout, stderr = run_tool("jn-filter", ["--help"])
@@ -328,6 +943,37 @@ class TestJnJoin:
         assert first["name"] == "Alice"
         assert first["product"] == "Book"
 
+    def test_join_errors_sidecar(self, tmp_path):
+        """jn-join --errors should keep malformed records instead of dropping them."""
+        right = tmp_path / "orders.jsonl"
+        right.write_text('{"user_id":1,"product":"Book"}\n{"user_id":2,\n{"product":"Pen"}\n')
+        errors = tmp_path / "errors.jsonl"
+
+        code, stdout, stderr = run_tool(
+            "jn-join",
+            ["--left-key=id", "--right-key=user_id", f"--errors={errors}", str(right)],
+            input_data='{"id":1,"name":"Alice"}\nnot json\n'
+        )
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        lines = [json.loads(l) for l in stdout.strip().split("\n") if l]
+        assert lines == [{"id": 1, "name": "Alice", "product": "Book"}]
+
+        records = [json.loads(l) for l in errors.read_text().splitlines()]
+        assert [(r["_error"], r["_line"], r["_source"]) for r in records] == [
+            ("invalid JSON", 2, str(right)),
+            ("missing join key", 3, str(right)),
+            ("invalid JSON", 2, "<stdin>"),
+        ]
+        assert all(r["_stage"] == "jn-join" for r in records)
+
+        code, stdout, stderr = run_tool(
+            "jn-join",
+            ["--left-key=id", "--right-key=user_id", "--max-errors=1", str(right)],
+            input_data='{"id":1,"name":"Alice"}\n'
+        )
+        assert code == 1
+        assert "too many errors" in stderr
+
     def test_join_help(self):
         """jn-join --help should show usage."""
         code, stdout, stderr = run_tool("jn-join", ["--help"])
@@ -385,6 +1031,50 @@ class TestJnMerge:
         assert "jn-merge" in stdout or "USAGE" in stdout
 
 
+# =============================================================================
+# jn-inspect tests
+# =============================================================================
+
+class TestJnInspect:
+    """Tests for jn-inspect."""
+
+    def test_inspect_profile_redacts_auth(self, tmp_path):
+        """jn-inspect profile should show the merged config without credentials."""
+        profile_dir = tmp_path / ".local" / "jn" / "profiles" / "http" / "pages"
+        profile_dir.mkdir(parents=True)
+        (profile_dir / "_meta.json").write_text(json.dumps({"base_url": "https://api.example.com"}))
+        (profile_dir / "secure.json").write_text(json.dumps({
+            "path": "/data",
+            "auth": {
+                "type": "oauth2",
+                "token_url": "https://auth.example.com/token",
+                "client_id": "jn",
+                "client_secret": "literal-secret",
+                "refresh_token": "${REFRESH_TOKEN}",
+            },
+        }))
+
+        env = {"HOME": str(tmp_path), "REFRESH_TOKEN": "r-12345"}
+        code, stdout, stderr = run_tool("jn-inspect", ["profile", "@pages/secure", "--format=json"], env=env)
+        assert code == 0, f"Exit code {code}, stderr: {stderr}"
+        assert "literal-secret" not in stdout and "r-12345" not in stdout
+        shown = json.loads(stdout)
+        assert shown["type"] == "http"
+        assert shown["path"] == str(profile_dir / "secure.json")
+        assert shown["config"]["base_url"] == "https://api.example.com"
+        assert shown["config"]["auth"] == {
+            "type": "oauth2",
+            "token_url": "https://auth.example.com/token",
+            "client_id": "jn",
+            "client_secret": "[REDACTED]",
+            "refresh_token": "[REDACTED]",
+        }
+
+        code, stdout, stderr = run_tool("jn-inspect", ["profile", "@pages/missing"], env=env)
+        assert code == 1
+        assert "profile not found" in stderr
+
+
 # =============================================================================
 # jn orchestrator tests
 # =============================================================================
@@ -0,0 +1,117 @@
+"""Round-trip tests for the Zig bz2, xz and zst compression plugins."""
+
+import bz2
+import lzma
+import shutil
+import subprocess
+from pathlib import Path
+
+import pytest
+
+
+PLUGINS_ROOT = Path(__file__).parent.parent.parent / "plugins" / "zig"
+
+DATA = b"".join(b'{"id":%d,"name":"user%d"}\n' % (i, i % 7) for i in range(20000))
+
+
+def _plugin(name: str) -> str:
+    """Path to a built compression plugin, or skip."""
+    binary = PLUGINS_ROOT / name / "bin" / name
+    if not binary.exists():
+        pytest.skip(f"Zig {name} plugin not built (run 'make zig-plugins')")
+    return str(binary)
+
+
+def _run(name: str, mode: str, data: bytes, env: dict | None = None) -> subprocess.CompletedProcess:
+    return subprocess.run(
+        [_plugin(name), f"--mode={mode}"],
+        input=data,
+        capture_output=True,
+        timeout=60,
+        env=env,
+    )
+
+
+def test_bz2_reads_bzip2_output():
+    result = _run("bz2", "raw", bz2.compress(DATA))
+    assert result.returncode == 0, result.stderr
+    assert result.stdout == DATA
+
+
+def test_bz2_reads_concatenated_streams():
+    stream = bz2.compress(b'{"a":1}\n') + bz2.compress(b'{"a":2}\n')
+    result = _run("bz2", "raw", stream)
+    assert result.returncode == 0, result.stderr
+    assert result.stdout == b'{"a":1}\n{"a":2}\n'
+
+
+def test_bz2_write_is_readable_by_bzip2():
+    result = _run("bz2", "write", DATA)
+    assert result.returncode == 0, result.stderr
+    assert len(result.stdout) < len(DATA)
+    assert bz2.decompress(result.stdout) == DATA
+
+
+def test_bz2_rejects_damaged_input():
+    damaged = bytearray(bz2.compress(DATA))
+    damaged[len(damaged) // 2] ^= 0xFF
+    result = _run("bz2", "raw", bytes(damaged))
+    assert result.returncode != 0
+    assert b"bz2: decompression error" in result.stderr
+
+
+def test_xz_reads_xz_output():
+    result = _run("xz", "raw", lzma.compress(DATA))
+    assert result.returncode == 0, result.stderr
+    assert result.stdout == DATA
+
+
+@pytest.mark.parametrize("data", [b"", DATA])
+def test_xz_write_is_readable_by_lzma(data):
+    result = _run("xz", "write", data)
+    assert result.returncode == 0, result.stderr
+    assert lzma.decompress(result.stdout, format=lzma.FORMAT_XZ) == data
+    if data and shutil.which("xz"):
+        assert len(result.stdout) < len(data)
+
+
+def test_zst_reads_zstd_output():
+    if not shutil.which("zstd"):
+        pytest.skip("zstd CLI not installed")
+    compressed = subprocess.run(["zstd", "-c"], input=DATA, capture_output=True, check=True).stdout
+    result = _run("zst", "raw", compressed)
+    assert result.returncode == 0, result.stderr
+    assert result.stdout == DATA
+
+
+@pytest.mark.parametrize("data", [b"", DATA])
+def test_zst_write_round_trips(data):
+    result = _run("zst", "write", data)
+    assert result.returncode == 0, result.stderr
+    restored = _run("zst", "raw", result.stdout)
+    assert restored.returncode == 0, restored.stderr
+    assert restored.stdout == data
+    if data and shutil.which("zstd"):
+        assert len(result.stdout) < len(data)
+        decompressed = subprocess.run(["zstd", "-dc"], input=result.stdout, capture_output=True, check=True).stdout
+        assert decompressed == data
+
+
+@pytest.mark.parametrize("name", ["xz", "zst"])
+def test_write_without_encoder_command_stores_data(name, tmp_path):
+    """Without the xz/zstd command the built-in encoder still writes a valid stream."""
+    env = {"PATH": str(tmp_path)}
+    result = _run(name, "write", DATA, env=env)
+    assert result.returncode == 0, result.stderr
+    assert len(result.stdout) >= len(DATA)
+    restored = _run(name, "raw", result.stdout, env=env)
+    assert restored.returncode == 0, restored.stderr
+    assert restored.stdout == DATA
+
+
+@pytest.mark.parametrize("name", ["bz2", "xz", "zst"])
+def test_manifest_declares_compression_role(name):
+    result = subprocess.run([_plugin(name), "--jn-meta"], capture_output=True, text=True)
+    assert result.returncode == 0
+    assert f'"{name}"' in result.stdout
+    assert '"compression"' in result.stdout
@@ -0,0 +1,338 @@
+//! Per-record requests (`jn cat @api/lookup --each`): read NDJSON records
+//! from stdin, send one request per record with its fields filled into the
+//! profile's URL and body, and merge each response back into its record.
+//!
+//! Profile fields:
+//!
+//! ```json
+//! {
+//!   "path": "/users/{id}",
+//!   "method": "POST",
+//!   "body": {"ids": ["{id}"], "note": "lookup for {name}"},
+//!   "each": {"into": "user", "concurrency": 8}
+//! }
+//! ```
+//!
+//! `{field}` placeholders (see jn-profile's template.zig) are filled from
+//! the record: percent-encoded in the path and query, as they are in the
+//! body. A JSON body keeps the type of a string that is one placeholder.
+//!
+//! A response object's fields are added to the record, replacing fields of
+//! the same name; `into` puts the whole response under one field instead,
+//! and any other response goes under `response`. An empty body merges
+//! nothing.
+//!
+//! Up to `concurrency` requests (4 by default) are in flight at once, and
+//! records are written in the order they were read. A record that fails
+//! (no field for a placeholder, an HTTP error, a response that isn't JSON)
+//! goes to the `--errors` file, or ends jn-cat without one.
+
+const std = @import("std");
+const jn_core = @import("jn-core");
+const jn_http = @import("jn-http");
+const jn_profile = @import("jn-profile");
+
+pub const Config = struct {
+    /// The request URL, with placeholders
+    url: []const u8,
+    /// The request body, with placeholders; a string is sent as text and
+    /// any other value as JSON
+    body: ?std.json.Value = null,
+    /// Field to hold the response; null merges an object's fields
+    into: ?[]const u8 = null,
+    /// Requests in flight at once
+    concurrency: usize = 4,
+
+    /// Read `body` and the `each` block (`into`, `concurrency`) of an HTTP
+    /// profile. Strings borrow from `profile`.
+    pub fn fromProfile(profile: std.json.Value, url: []const u8) !Config {
+        var config: Config = .{ .url = url };
+        if (profile != .object) return config;
+        if (profile.object.get("body")) |body| {
+            if (body != .null) config.body = body;
+        }
+
+        const block = profile.object.get("each") orelse return config;
+        if (block != .object) return error.InvalidEach;
+        if (block.object.get("into")) |into| switch (into) {
+            .string => |name| config.into = name,
+            .null => {},
+            else => return error.InvalidEach,
+        };
+        if (block.object.get("concurrency")) |value| {
+            config.concurrency = try parseConcurrency(value);
+        }
+        return config;
+    }
+};
+
+/// A concurrency of at least 1, as a number or (after `${VAR}`
+/// substitution) a string
+pub fn parseConcurrency(value: std.json.Value) !usize {
+    const n = switch (value) {
+        .integer => |count| std.math.cast(usize, count) orelse return error.InvalidEach,
+        .string => |s| std.fmt.parseInt(usize, s, 10) catch return error.InvalidEach,
+        else => return error.InvalidEach,
+    };
+    return if (n == 0) error.InvalidEach else n;
+}
+
+/// One response to a record's request
+pub const Response = struct {
+    status: u16,
+    body: []const u8,
+    /// The `_http` envelope (JSON) to add to the record, when asked for
+    meta: ?[]const u8 = null,
+};
+
+/// Send a request for every NDJSON record in `reader` and write the merged
+/// records to `writer`, in input order, flushing after each.
+///
+/// `fetcher.lookup(allocator, url, body)` returns a `Response` allocated
+/// with `allocator` or an error. It is called from up to
+/// `config.concurrency` threads at once.
+///
+/// Failed records are reported to `errors` when it has a file; otherwise
+/// the failure is printed and `error.LookupFailed` returned.
+pub fn run(allocator: std.mem.Allocator, config: Config, reader: *std.Io.Reader, fetcher: anytype, writer: *std.Io.Writer, errors: *jn_core.ErrorSink) !void {
+    const slots = try allocator.alloc(Slot, @max(config.concurrency, 1));
+    defer allocator.free(slots);
+    for (slots) |*slot| slot.* = .{ .arena_state = .init(allocator) };
+    defer for (slots) |*slot| {
+        if (slot.thread) |thread| thread.join();
+        slot.arena_state.deinit();
+    };
+
+    const work = Worker(@TypeOf(fetcher)).work;
+    var started: usize = 0;
+    var written: usize = 0;
+    var line_no: u64 = 0;
+    while (jn_core.readLine(reader)) |line| {
+        line_no += 1;
+        if (line.len == 0) continue;
+        // Every slot busy: wait for the oldest record and write it
+        if (started - written == slots.len) {
+            try finish(&slots[written % slots.len], writer, errors);
+            written += 1;
+        }
+        const slot = &slots[started % slots.len];
+        slot.line_no = line_no;
+        slot.raw = try slot.arena_state.allocator().dupe(u8, line);
+        slot.thread = try std.Thread.spawn(.{}, work, .{ slot, config, fetcher });
+        started += 1;
+    }
+    while (written < started) : (written += 1) {
+        try finish(&slots[written % slots.len], writer, errors);
+    }
+}
+
+/// One record in flight. The thread owns it until it is joined.
+const Slot = struct {
+    arena_state: std.heap.ArenaAllocator,
+    thread: ?std.Thread = null,
+    line_no: u64 = 0,
+    raw: []const u8 = "",
+    outcome: Outcome = .{ .failed = "" },
+};
+
+const Outcome = union(enum) {
+    /// The merged record, newline-terminated
+    record: []const u8,
+    /// Why the record failed
+    failed: []const u8,
+};
+
+fn Worker(comptime Fetcher: type) type {
+    return struct {
+        fn work(slot: *Slot, config: Config, fetcher: Fetcher) void {
+            slot.outcome = lookup(slot.arena_state.allocator(), config, fetcher, slot.raw) catch |err| .{ .failed = @errorName(err) };
+        }
+    };
+}
+
+/// Wait for the record in `slot`, write it (or report why it failed) and
+/// free the slot for the next record.
+fn finish(slot: *Slot, writer: *std.Io.Writer, errors: *jn_core.ErrorSink) !void {
+    slot.thread.?.join();
+    slot.thread = null;
+    defer _ = slot.arena_state.reset(.retain_capacity);
+    switch (slot.outcome) {
+        .record => |line| {
+            try writer.writeAll(line);
+            try writer.flush();
+        },
+        .failed => |message| {
+            if (!errors.enabled()) {
+                std.debug.print("{s}: line {d}: {s}\n", .{ errors.stage, slot.line_no, message });
+                return error.LookupFailed;
+            }
+            errors.report(message, slot.line_no, slot.raw);
+        },
+    }
+}
+
+/// Send the request for one record and return the merged record
+fn lookup(arena: std.mem.Allocator, config: Config, fetcher: anytype, raw: []const u8) !Outcome {
+    const record = std.json.parseFromSliceLeaky(std.json.Value, arena, raw, .{}) catch {
+        return .{ .failed = "invalid JSON" };
+    };
+    if (record != .object) return .{ .failed = "record is not an object" };
+
+    var missing: []const u8 = "";
+    const url = jn_profile.renderTemplate(arena, config.url, record, .url, &missing) catch |err| switch (err) {
+        error.MissingField => return failed(arena, "record has no '{s}' for the URL", .{missing}),
+        else => |e| return e,
+    };
+    var body: ?[]const u8 = null;
+    if (config.body) |template| {
+        body = renderBody(arena, template, record, &missing) catch |err| switch (err) {
+            error.MissingField => return failed(arena, "record has no '{s}' for the body", .{missing}),
+            else => |e| return e,
+        };
+    }
+
+    const response = fetcher.lookup(arena, url, body) catch |err| {
+        return failed(arena, "request to {s} failed: {s}", .{ url, @errorName(err) });
+    };
+    if (response.status >= 400) return failed(arena, "HTTP {d} fetching {s}", .{ response.status, url });
+
+    const trimmed = std.mem.trim(u8, response.body, " \t\r\n");
+    var merged = record.object;
+    if (trimmed.len > 0) {
+        const value = std.json.parseFromSliceLeaky(std.json.Value, arena, trimmed, .{}) catch {
+            return failed(arena, "response from {s} is not JSON", .{url});
+        };
+        if (config.into) |name| {
+            try merged.put(name, value);
+        } else if (value == .object) {
+            var it = value.object.iterator();
+            while (it.next()) |entry| try merged.put(entry.key_ptr.*, entry.value_ptr.*);
+        } else {
+            try merged.put("response", value);
+        }
+    }
+
+    const json = try std.json.Stringify.valueAlloc(arena, std.json.Value{ .object = merged }, .{});
+    var out: std.Io.Writer.Allocating = .init(arena);
+    if (response.meta) |meta| {
+        try jn_http.envelope.write(&out.writer, meta, json);
+    } else {
+        try out.writer.print("{s}\n", .{json});
+    }
+    return .{ .record = out.written() };
+}
+
+/// A string body as text, any other value as JSON
+fn renderBody(arena: std.mem.Allocator, template: std.json.Value, record: std.json.Value, missing: *[]const u8) ![]const u8 {
+    if (template == .string) return jn_profile.renderTemplate(arena, template.string, record, .none, missing);
+    const value = try jn_profile.renderJsonTemplate(arena, template, record, missing);
+    return std.json.Stringify.valueAlloc(arena, value, .{});
+}
+
+fn failed(arena: std.mem.Allocator, comptime fmt: []const u8, args: anytype) !Outcome {
+    return .{ .failed = try std.fmt.allocPrint(arena, fmt, args) };
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+/// Answers every request with the URL and body it got, failing the ones
+/// listed in `missing`. Safe to call from several threads.
+const EchoServer = struct {
+    missing: []const []const u8 = &.{},
+    mutex: std.Thread.Mutex = .{},
+    requests: usize = 0,
+
+    pub fn lookup(self: *EchoServer, allocator: std.mem.Allocator, url: []const u8, body: ?[]const u8) !Response {
+        self.mutex.lock();
+        self.requests += 1;
+        self.mutex.unlock();
+        for (self.missing) |path| {
+            if (std.mem.endsWith(u8, url, path)) return .{ .status = 404, .body = "{\"error\":\"not found\"}" };
+        }
+        return .{ .status = 200, .body = try std.json.Stringify.valueAlloc(allocator, .{ .url = url, .sent = body }, .{}) };
+    }
+};
+
+fn expectRecords(config: Config, server: *EchoServer, input: []const u8, errors: *jn_core.ErrorSink, expected: []const u8) !void {
+    var reader: std.Io.Reader = .fixed(input);
+    var out: std.Io.Writer.Allocating = .init(std.testing.allocator);
+    defer out.deinit();
+    try run(std.testing.allocator, config, &reader, server, &out.writer, errors);
+    try std.testing.expectEqualStrings(expected, out.written());
+}
+
+test "records are merged in input order" {
+    var server: EchoServer = .{};
+    var errors: jn_core.ErrorSink = .{ .allocator = std.testing.allocator, .stage = "jn-cat" };
+    try expectRecords(.{ .url = "https://api.test/users/{id}", .concurrency = 2 }, &server,
+        \\{"id":1}
+        \\{"id":"a b"}
+        \\
+        \\{"id":3}
+        \\
+    , &errors,
+        \\{"id":1,"url":"https://api.test/users/1","sent":null}
+        \\{"id":"a b","url":"https://api.test/users/a%20b","sent":null}
+        \\{"id":3,"url":"https://api.test/users/3","sent":null}
+        \\
+    );
+    try std.testing.expectEqual(@as(usize, 3), server.requests);
+}
+
+test "body templates and into" {
+    var arena_state = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena_state.deinit();
+    const profile = try std.json.parseFromSliceLeaky(std.json.Value, arena_state.allocator(),
+        \\{"body":{"ids":["{id}"],"q":"name:{name}"},"each":{"into":"lookup","concurrency":"1"}}
+    , .{});
+    const config = try Config.fromProfile(profile, "https://api.test/search");
+    try std.testing.expectEqual(@as(usize, 1), config.concurrency);
+
+    var server: EchoServer = .{};
+    var errors: jn_core.ErrorSink = .{ .allocator = std.testing.allocator, .stage = "jn-cat" };
+    try expectRecords(config, &server, "{\"id\":7,\"name\":\"Ada\"}\n", &errors,
+        \\{"id":7,"name":"Ada","lookup":{"url":"https://api.test/search","sent":"{\"ids\":[7],\"q\":\"name:Ada\"}"}}
+        \\
+    );
+}
+
+test "failed records go to the error file" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var errors: jn_core.ErrorSink = .{
+        .allocator = std.testing.allocator,
+        .stage = "jn-cat",
+        .file = try tmp.dir.createFile("errors.jsonl", .{ .read = true }),
+    };
+    defer errors.close();
+
+    var server: EchoServer = .{ .missing = &.{"/2"} };
+    try expectRecords(.{ .url = "https://api.test/u/{id}" }, &server,
+        \\{"id":1}
+        \\{"id":2}
+        \\{"name":"x"}
+        \\[1]
+        \\
+    , &errors,
+        \\{"id":1,"url":"https://api.test/u/1","sent":null}
+        \\
+    );
+    try std.testing.expectEqual(@as(u64, 3), errors.count);
+
+    const written = try tmp.dir.readFileAlloc(std.testing.allocator, "errors.jsonl", 1 << 16);
+    defer std.testing.allocator.free(written);
+    try std.testing.expect(std.mem.indexOf(u8, written, "HTTP 404 fetching https://api.test/u/2") != null);
+    try std.testing.expect(std.mem.indexOf(u8, written, "record has no 'id' for the URL") != null);
+    try std.testing.expect(std.mem.indexOf(u8, written, "record is not an object") != null);
+}
+
+test "a failed record without an error file stops the run" {
+    var server: EchoServer = .{ .missing = &.{"/1"} };
+    var errors: jn_core.ErrorSink = .{ .allocator = std.testing.allocator, .stage = "jn-cat" };
+    var reader: std.Io.Reader = .fixed("{\"id\":1}\n{\"id\":2}\n");
+    var out: std.Io.Writer.Allocating = .init(std.testing.allocator);
+    defer out.deinit();
+    try std.testing.expectError(error.LookupFailed, run(std.testing.allocator, .{ .url = "https://api.test/u/{id}" }, &reader, &server, &out.writer, &errors));
+}
@@ -7,7 +7,7 @@
 //!
 //! Address formats:
 //!   - data.csv              Local file (format auto-detected)
-//!   - data.csv.gz           Compressed file (decompressed automatically)
+//!   - data.csv.gz           Compressed file (.gz, .bz2, .xz, .zst; decompressed automatically)
 //!   - data.txt~csv          Format override
 //!   - -                     Read from stdin
 //!
@@ -16,6 +16,21 @@
 //!   --version               Show version
 //!   --delimiter=CHAR        CSV delimiter (passed to plugin)
 //!   --no-header             CSV has no header row (passed to plugin)
+//!   --errors=PATH           Append unreadable records to PATH as NDJSON
+//!   --max-errors=N          Fail once more than N records are unreadable
+//!   --timeout=SECS          Give up on an HTTP or cloud request that makes no
+//!                           progress for SECS
+//!   --retries=N             Retry failed requests N times with backoff
+//!   --rate=N/UNIT           Send at most N requests per sec, min or hour
+//!   --meta                  Add path fields (globs) or an _http field with the
+//!                           response status, URL and headers (HTTP)
+//!   --each                  Send an HTTP profile's request once per stdin
+//!                           record, filling {field} placeholders, and merge
+//!                           each response into its record
+//!   --concurrency=N         --each requests in flight at once (default 4)
+//!   --into=FIELD            Put each --each response under FIELD
+//!   --explain[=json]        Print the resolved address, profile, plugins and
+//!                           pipelines instead of running (also JN_EXPLAIN=1|json)
 //!
 //! Examples:
 //!   jn-cat data.csv
@@ -23,26 +38,40 @@
 //!   jn-cat data.txt~csv
 //!   jn-cat - < data.csv
 //!   jn-cat --delimiter=';' data.csv
+//!   jn-cat --errors=bad.jsonl --max-errors=100 data.csv
+//!   jn-cat --explain=json data.csv.gz
+//!   jn-cat --each @github/user < logins.jsonl
 
 const std = @import("std");
 const jn_core = @import("jn-core");
 const jn_cli = @import("jn-cli");
 const jn_address = @import("jn-address");
 const jn_profile = @import("jn-profile");
+const jn_http = @import("jn-http");
+const pagination = @import("pagination.zig");
+const each = @import("each.zig");
 
 const VERSION = "0.1.0";
 
-/// Escape a path for use in single-quoted shell arguments.
-/// Returns an EscapedString with clear ownership semantics.
-///
-/// SECURITY: This prevents command injection via paths containing single quotes.
-///
-/// Usage:
-///   const escaped = try escapeShellPath(allocator, path);
-///   defer escaped.deinit(allocator);
-///   // use escaped.slice in shell commands
-fn escapeShellPath(allocator: std.mem.Allocator, path: []const u8) !jn_core.EscapedString {
-    return jn_core.escapeForShell(allocator, path);
+/// The --explain report, set when jn-cat only describes what it would run
+var explain: ?*jn_core.explain.Report = null;
+
+/// Run `pipeline` and return how its stages ended. Under --explain, report
+/// the pipeline and exit instead of spawning it.
+fn runPipeline(pipeline: *jn_core.Pipeline) jn_core.pipeline.Result {
+    if (explain) |report| {
+        report.addPipeline(pipeline);
+        report.finish();
+    }
+    return pipeline.run() catch |err| {
+        jn_core.exitWithError("jn-cat: cannot start pipeline: {s}", .{@errorName(err)});
+    };
+}
+
+/// Run `pipeline`, exiting with the status of its first failed stage.
+fn runPipelineOrExit(pipeline: *jn_core.Pipeline) void {
+    const result = runPipeline(pipeline);
+    pipeline.exitOnFailure(result, "jn-cat");
 }
 
 /// Check if a string is safe for use as an HTTP header key or value.
@@ -60,12 +89,6 @@ fn isValidHttpHeaderValue(value: []const u8) bool {
     return true;
 }
 
-/// Build a shell command with properly escaped arguments.
-/// All paths should be passed through escapeShellPath before inclusion.
-fn buildShellCommand(allocator: std.mem.Allocator, comptime fmt: []const u8, args: anytype) ![]u8 {
-    return std.fmt.allocPrint(allocator, fmt, args);
-}
-
 pub fn main() !void {
     var gpa = std.heap.GeneralPurposeAllocator(.{}){};
     defer _ = gpa.deinit();
@@ -93,6 +116,15 @@ pub fn main() !void {
     // Parse the address
     const address = jn_address.parse(address_str);
 
+    // --explain: record what gets resolved and print it instead of running
+    var report: jn_core.explain.Report = undefined;
+    if (jn_core.explain.requested(&args, "jn-cat")) |format| {
+        report = .init(allocator, "jn-cat", format);
+        report.setAddress(address);
+        if (args.get("header", null)) |header| report.addHeaderSecret(header);
+        explain = &report;
+    }
+
     // Route based on address type
     switch (address.address_type) {
         .stdin => {
@@ -144,7 +176,13 @@ fn handleStdin(allocator: std.mem.Allocator, address: jn_address.Address, args:
 
     // For JSONL, just pass through
     if (std.mem.eql(u8, format, "jsonl") or std.mem.eql(u8, format, "ndjson")) {
-        try passthroughStdin();
+        if (explain) |report| {
+            report.addNote("stdin is copied to stdout without a plugin");
+            report.finish();
+        }
+        var errors = jn_core.ErrorSink.fromArgs(allocator, args, "jn-cat");
+        defer errors.close();
+        try passthroughStdin(allocator, &errors);
         return;
     }
 
@@ -173,37 +211,26 @@ fn handleFile(allocator: std.mem.Allocator, address: jn_address.Address, args: *
     try spawnFormatPlugin(allocator, format, address.path, address.query_string, args);
 }
 
-/// Build format plugin argument string for shell commands.
-/// Returns allocated string that caller must free.
-/// SECURITY: All values are properly escaped to prevent shell injection.
-fn buildFormatArgs(allocator: std.mem.Allocator, args: *const jn_cli.ArgParser) ![]const u8 {
-    var result: std.ArrayListUnmanaged(u8) = .empty;
-    errdefer result.deinit(allocator);
+/// Append the format plugin options jn-cat passes through. `source` names the
+/// input in error sidecar records (null for stdin).
+fn appendFormatArgs(stage: *jn_core.pipeline.Stage, args: *const jn_cli.ArgParser, source: ?[]const u8) !void {
+    if (args.get("delimiter", null)) |delim| try stage.argPrint("--delimiter={s}", .{delim});
+    if (args.has("no-header")) try stage.arg("--no-header");
 
-    if (args.get("delimiter", null)) |delim| {
-        // SECURITY: Escape delimiter to prevent shell injection via single quotes
-        const escaped = try jn_core.escapeForShellSingleQuote(allocator, delim);
-        defer allocator.free(escaped);
-
-        try result.appendSlice(allocator, " --delimiter='");
-        try result.appendSlice(allocator, escaped);
-        try result.append(allocator, '\'');
-    }
-    if (args.has("no-header")) {
-        try result.appendSlice(allocator, " --no-header");
+    // Error sidecar: the plugin routes what it can't read
+    const errors_path = args.get("errors", null);
+    const max_errors = args.get("max-errors", null);
+    if (errors_path) |path| try stage.argPrint("--errors={s}", .{path});
+    if (max_errors) |max| try stage.argPrint("--max-errors={s}", .{max});
+    if (errors_path != null or max_errors != null) {
+        if (source) |name| try stage.argPrint("--errors-source={s}", .{name});
     }
-
-    return try result.toOwnedSlice(allocator);
 }
 
-/// Build CLI arguments from query string parameters.
+/// Append CLI arguments from query string parameters.
 /// Converts key=value pairs to --key=value format, skipping 'mode' which is handled separately.
-/// SECURITY: All values are properly escaped to prevent shell injection.
-fn buildQueryArgs(allocator: std.mem.Allocator, query_string: ?[]const u8) ![]const u8 {
-    const qs = query_string orelse return try allocator.dupe(u8, "");
-
-    var result: std.ArrayListUnmanaged(u8) = .empty;
-    errdefer result.deinit(allocator);
+fn appendQueryArgs(stage: *jn_core.pipeline.Stage, query_string: ?[]const u8) !void {
+    const qs = query_string orelse return;
 
     // Parse query string: key=value&key2=value2
     var params_iter = std.mem.splitScalar(u8, qs, '&');
@@ -219,26 +246,35 @@ fn buildQueryArgs(allocator: std.mem.Allocator, query_string: ?[]const u8) ![]co
         if (std.mem.eql(u8, key, "mode")) continue;
 
         // Convert underscore to hyphen for CLI args (header_row -> header-row)
-        var key_buf: [64]u8 = undefined;
-        const key_len = @min(key.len, key_buf.len);
-        @memcpy(key_buf[0..key_len], key[0..key_len]);
-        for (key_buf[0..key_len]) |*c| {
-            if (c.* == '_') c.* = '-';
-        }
-        const cli_key = key_buf[0..key_len];
-
-        // SECURITY: Escape value to prevent shell injection
-        const escaped = try jn_core.escapeForShellSingleQuote(allocator, value);
-        defer allocator.free(escaped);
+        const cli_key = try stage.allocator.dupe(u8, key);
+        std.mem.replaceScalar(u8, cli_key, '_', '-');
 
-        try result.appendSlice(allocator, " --");
-        try result.appendSlice(allocator, cli_key);
-        try result.appendSlice(allocator, "='");
-        try result.appendSlice(allocator, escaped);
-        try result.append(allocator, '\'');
+        try stage.argPrint("--{s}={s}", .{ cli_key, value });
     }
+}
+
+/// Append a stage running a format plugin in `mode`. Python plugins run
+/// through `uv run --script`.
+fn addPluginStage(pipeline: *jn_core.Pipeline, info: PluginInfo, mode: []const u8) !*jn_core.pipeline.Stage {
+    const stage = switch (info.plugin_type) {
+        .zig => try pipeline.add(&.{info.path}),
+        .python => blk: {
+            const uv = try pipeline.add(&.{ "uv", "run", "--script", info.path });
+            uv.label = std.fs.path.basename(info.path);
+            break :blk uv;
+        },
+    };
+    try stage.argPrint("--mode={s}", .{mode});
+    return stage;
+}
 
-    return try result.toOwnedSlice(allocator);
+/// Append the stage that decompresses `compression` (gz, bz2, xz or zst).
+fn addDecompressStage(allocator: std.mem.Allocator, pipeline: *jn_core.Pipeline, compression: jn_address.Compression) !void {
+    const name = compression.plugin().?;
+    const path = findPlugin(allocator, name) orelse {
+        jn_core.exitWithError("jn-cat: compression plugin '{s}' not found", .{name});
+    };
+    _ = try pipeline.add(&.{ path, "--mode=raw" });
 }
 
 /// Extract mode from query string, defaulting to "read".
@@ -263,64 +299,19 @@ fn extractModeFromQuery(query_string: ?[]const u8) []const u8 {
 
 /// Handle compressed file (spawn decompression + format pipeline)
 fn handleCompressedFile(allocator: std.mem.Allocator, address: jn_address.Address, format: []const u8, args: *const jn_cli.ArgParser) !void {
-    // Find compression plugin
-    const compression_plugin = switch (address.compression) {
-        .gzip => "gz",
-        .bzip2 => {
-            jn_core.exitWithError("jn-cat: bzip2 decompression not yet supported", .{});
-        },
-        .xz => {
-            jn_core.exitWithError("jn-cat: xz decompression not yet supported", .{});
-        },
-        .zstd => {
-            jn_core.exitWithError("jn-cat: zstd decompression not yet supported", .{});
-        },
-        .none => unreachable,
-    };
-
-    // Find plugins
-    const gz_path = findPlugin(allocator, compression_plugin) orelse {
-        jn_core.exitWithError("jn-cat: compression plugin '{s}' not found", .{compression_plugin});
-    };
+    // Pipeline: gz|bz2|xz|zst --mode=raw < file | format --mode=read [args]
+    var pipeline = jn_core.Pipeline.init(allocator);
+    defer pipeline.deinit();
+    pipeline.stdin = .{ .file = address.path };
+    try addDecompressStage(allocator, &pipeline, address.compression);
 
     const format_path = findPlugin(allocator, format) orelse {
         jn_core.exitWithError("jn-cat: format plugin '{s}' not found", .{format});
     };
+    const format_stage = try pipeline.add(&.{ format_path, "--mode=read" });
+    try appendFormatArgs(format_stage, args, address.path);
 
-    // Build format args (dynamically allocated, properly escaped)
-    const format_args = try buildFormatArgs(allocator, args);
-    defer allocator.free(format_args);
-
-    // SECURITY: Escape the file path to prevent command injection via filenames
-    // containing single quotes (e.g., "file'; rm -rf /; echo '")
-    const escaped_path = try escapeShellPath(allocator, address.path);
-    defer escaped_path.deinit(allocator);
-
-    // Use shell to construct pipeline: cat file | gz --mode=raw | format --mode=read [args]
-    const shell_cmd = try std.fmt.allocPrint(
-        allocator,
-        "cat '{s}' | {s} --mode=raw | {s} --mode=read{s}",
-        .{ escaped_path.slice, gz_path, format_path, format_args },
-    );
-    defer allocator.free(shell_cmd);
-
-    // Run via shell
-    const shell_argv: [3][]const u8 = .{ "/bin/sh", "-c", shell_cmd };
-    var shell_child = std.process.Child.init(&shell_argv, allocator);
-    shell_child.stdin_behavior = .Close;
-    shell_child.stdout_behavior = .Inherit;
-    shell_child.stderr_behavior = .Inherit;
-
-    try shell_child.spawn();
-    const result = shell_child.wait() catch |err| {
-        jn_core.exitWithError("jn-cat: pipeline execution failed: {s}", .{@errorName(err)});
-    };
-
-    switch (result) {
-        .Exited => |code| if (code != 0) std.process.exit(code),
-        .Signal => |sig| std.process.exit(128 +| @as(u8, @intCast(@min(sig, 127)))),
-        .Stopped, .Unknown => std.process.exit(1),
-    }
+    runPipelineOrExit(&pipeline);
 }
 
 /// Profile type enumeration
@@ -439,6 +430,7 @@ fn handleProfile(allocator: std.mem.Allocator, address: jn_address.Address, args
     }
     defer allocator.free(profile_file.?);
     defer allocator.free(profile_dir.?);
+    if (explain) |report| report.setProfile(profile_file.?);
 
     // Route based on profile type
     switch (profile_type) {
@@ -523,6 +515,9 @@ fn handleCodeProfile(allocator: std.mem.Allocator, address: jn_address.Address)
 
     const plugin_path = try std.fmt.allocPrint(allocator, "{s}/jn_home/plugins/protocols/code_.py", .{plugins_root});
     defer allocator.free(plugin_path);
+    if (explain) |report| {
+        report.addPlugin(.{ .name = "code_", .kind = "python", .path = plugin_path, .source = "plugins root", .priority = 1 });
+    }
 
     // Build the full address with query string
     const full_address = if (address.query_string) |qs|
@@ -538,34 +533,14 @@ fn handleCodeProfile(allocator: std.mem.Allocator, address: jn_address.Address)
         });
     defer allocator.free(full_address);
 
-    // Build shell command: uv run --script code_.py --mode=read <address>
-    const shell_cmd = try std.fmt.allocPrint(
-        allocator,
-        "uv run --script '{s}' --mode=read '{s}'",
-        .{ plugin_path, full_address },
-    );
-    defer allocator.free(shell_cmd);
-
-    // Run via shell
-    const shell_argv: [3][]const u8 = .{ "/bin/sh", "-c", shell_cmd };
-    var shell_child = std.process.Child.init(&shell_argv, allocator);
-    shell_child.stdin_behavior = .Close;
-    shell_child.stdout_behavior = .Inherit;
-    shell_child.stderr_behavior = .Inherit;
-
-    try shell_child.spawn();
-    const result = try shell_child.wait();
-
-    switch (result) {
-        .Exited => |code| {
-            if (code != 0) {
-                std.process.exit(code);
-            }
-        },
-        else => {
-            std.process.exit(1);
-        },
-    }
+    // uv run --script code_.py --mode=read <address>
+    var pipeline = jn_core.Pipeline.init(allocator);
+    defer pipeline.deinit();
+    pipeline.stdin = .null_device;
+    const stage = try addPluginStage(&pipeline, .{ .path = plugin_path, .plugin_type = .python }, "read");
+    try stage.arg(full_address);
+
+    runPipelineOrExit(&pipeline);
 }
 
 /// Handle DuckDB profiles by invoking duckdb_.py plugin
@@ -576,6 +551,9 @@ fn handleDuckdbProfile(allocator: std.mem.Allocator, address: jn_address.Address
 
     const plugin_path = try std.fmt.allocPrint(allocator, "{s}/jn_home/plugins/databases/duckdb_.py", .{plugins_root});
     defer allocator.free(plugin_path);
+    if (explain) |report| {
+        report.addPlugin(.{ .name = "duckdb_", .kind = "python", .path = plugin_path, .source = "plugins root", .priority = 1 });
+    }
 
     // Build the full address with query string
     const full_address = if (address.query_string) |qs|
@@ -599,37 +577,19 @@ fn handleDuckdbProfile(allocator: std.mem.Allocator, address: jn_address.Address
     const profiles_dir = std.fs.path.dirname(duckdb_dir) orelse duckdb_dir; // .../profiles
     const project_root = std.fs.path.dirname(profiles_dir) orelse profiles_dir; // ...
 
-    // Build shell command: JN_PROJECT_DIR=<project_root> uv run --script duckdb_.py --mode=read --path <address>
-    const shell_cmd = try std.fmt.allocPrint(
-        allocator,
-        "JN_PROJECT_DIR='{s}' uv run --script '{s}' --mode=read --path '{s}'",
-        .{ project_root, plugin_path, full_address },
-    );
-    defer allocator.free(shell_cmd);
-
-    // Run via shell
-    const shell_argv: [3][]const u8 = .{ "/bin/sh", "-c", shell_cmd };
-    var shell_child = std.process.Child.init(&shell_argv, allocator);
-    shell_child.stdin_behavior = .Close;
-    shell_child.stdout_behavior = .Inherit;
-    shell_child.stderr_behavior = .Inherit;
-
-    try shell_child.spawn();
-    const result = try shell_child.wait();
-
-    switch (result) {
-        .Exited => |code| {
-            if (code != 0) {
-                std.process.exit(code);
-            }
-        },
-        else => {
-            std.process.exit(1);
-        },
-    }
+    // JN_PROJECT_DIR=<project_root> uv run --script duckdb_.py --mode=read --path <address>
+    var pipeline = jn_core.Pipeline.init(allocator);
+    defer pipeline.deinit();
+    pipeline.stdin = .null_device;
+    const stage = try addPluginStage(&pipeline, .{ .path = plugin_path, .plugin_type = .python }, "read");
+    try stage.arg("--path");
+    try stage.arg(full_address);
+    try stage.setEnv("JN_PROJECT_DIR", project_root);
+
+    runPipelineOrExit(&pipeline);
 }
 
-/// Handle HTTP profiles using curl
+/// Handle HTTP profiles with jn-http
 fn handleHttpProfile(allocator: std.mem.Allocator, address: jn_address.Address, args: *const jn_cli.ArgParser, namespace: []const u8, name: []const u8, profile_dir: []const u8) !void {
     // Load profile with hierarchical merge
     const profile_path = try std.fmt.allocPrint(allocator, "{s}.json", .{name});
@@ -639,6 +599,7 @@ fn handleHttpProfile(allocator: std.mem.Allocator, address: jn_address.Address,
         jn_core.exitWithError("jn-cat: failed to load profile @{s}/{s}: {s}", .{ namespace, name, @errorName(err) });
     };
     defer jn_profile.freeValue(allocator, config);
+    if (explain) |report| report.setConfig(config);
 
     // Extract profile fields
     const base_url = if (config.object.get("base_url")) |v| v.string else null;
@@ -662,102 +623,477 @@ fn handleHttpProfile(allocator: std.mem.Allocator, address: jn_address.Address,
         try std.fmt.allocPrint(allocator, "{s}", .{full_url});
     defer allocator.free(url_with_params);
 
-    // Build headers from profile with proper shell escaping
-    var header_list: std.ArrayListUnmanaged(u8) = .empty;
-    defer header_list.deinit(allocator);
-
-    if (config.object.get("headers")) |headers_val| {
-        if (headers_val == .object) {
-            var iter = headers_val.object.iterator();
-            while (iter.next()) |entry| {
-                if (entry.value_ptr.* == .string) {
-                    // SECURITY: Validate header key and value to prevent HTTP header injection
-                    // (CR/LF characters could allow injecting additional headers or response splitting)
-                    if (!isValidHttpHeaderValue(entry.key_ptr.*) or !isValidHttpHeaderValue(entry.value_ptr.string)) {
-                        std.debug.print("jn-cat: warning: skipping header with invalid characters (CR/LF not allowed)\n", .{});
-                        continue;
-                    }
+    // Get format (default to json for HTTP profiles)
+    const format = address.effectiveFormat() orelse "json";
 
-                    // SECURITY: Escape header key and value to prevent command injection
-                    const escaped_key = escapeShellPath(allocator, entry.key_ptr.*) catch continue;
-                    defer escaped_key.deinit(allocator);
-                    const escaped_value = escapeShellPath(allocator, entry.value_ptr.string) catch continue;
-                    defer escaped_value.deinit(allocator);
-
-                    header_list.appendSlice(allocator, " -H '") catch continue;
-                    header_list.appendSlice(allocator, escaped_key.slice) catch continue;
-                    header_list.appendSlice(allocator, ": ") catch continue;
-                    header_list.appendSlice(allocator, escaped_value.slice) catch continue;
-                    header_list.append(allocator, '\'') catch continue;
-                }
+    const what = try std.fmt.allocPrint(allocator, "profile @{s}/{s}", .{ namespace, name });
+    defer allocator.free(what);
+    var source = HttpSource.init(allocator, HttpControls.init(args, config), what);
+    defer source.deinit();
+    try source.applyProfile(config);
+    source.envelope = source.envelope or args.has("meta") or args.has("inject-meta");
+
+    if (args.has("each")) return fetchEach(allocator, &source, config, url_with_params, args);
+    if (jn_profile.template.hasPlaceholders(url_with_params)) {
+        jn_core.exitWithError("jn-cat: profile @{s}/{s} fills {{field}} placeholders from records; read them with --each", .{ namespace, name });
+    }
+
+    const paging = pagination.Config.fromProfile(config) catch |err| {
+        jn_core.exitWithError("jn-cat: profile @{s}/{s} has an invalid pagination block: {s}", .{ namespace, name, @errorName(err) });
+    };
+    if (paging.enabled()) {
+        if (!std.mem.eql(u8, format, "json")) {
+            jn_core.exitWithError("jn-cat: profile @{s}/{s}: pagination and items_path need JSON responses (format is '{s}')", .{ namespace, name, format });
+        }
+        return fetchPages(allocator, &source, paging, url_with_params);
+    }
+
+    // <response body> | format --mode=read
+    var pipeline = jn_core.Pipeline.init(allocator);
+    defer pipeline.deinit();
+    if (findPlugin(allocator, format)) |fmt_path| {
+        const format_stage = try pipeline.add(&.{ fmt_path, "--mode=read" });
+        try appendFormatArgs(format_stage, args, address.raw);
+    } else if (!std.mem.eql(u8, format, "jsonl") and !std.mem.eql(u8, format, "ndjson") and !std.mem.eql(u8, format, "json")) {
+        jn_core.exitWithError("jn-cat: format plugin '{s}' not found", .{format});
+    }
+
+    try streamHttp(allocator, &source, url_with_params, &pipeline);
+}
+
+/// Timeout, retry and rate settings for HTTP requests. `--timeout=SECS`,
+/// `--retries=N` and `--rate=N/UNIT` override the profile's `timeout`,
+/// `retries` and `rate`.
+const HttpControls = struct {
+    /// Seconds a request may go without progress
+    timeout: ?f64 = null,
+    retry: jn_core.RetryPolicy = .{},
+    /// Shared by every request, so it paces all pages of a paginated profile
+    limiter: ?jn_core.RateLimiter = null,
+
+    fn init(args: *const jn_cli.ArgParser, profile: ?std.json.Value) HttpControls {
+        var controls: HttpControls = .{};
+        var buf: [32]u8 = undefined;
+        if (httpSetting(args, profile, "timeout", &buf)) |text| {
+            const seconds = std.fmt.parseFloat(f64, text) catch 0;
+            // Also false for nan; inf is over the maximum
+            if (!(seconds > 0 and seconds <= jn_http.max_timeout)) {
+                std.debug.print("jn-cat: invalid timeout '{s}' (seconds, at most {d})\n", .{ text, jn_http.max_timeout });
+                jn_core.ExitCode.usage_error.exit();
             }
+            controls.timeout = seconds;
+        }
+        if (httpSetting(args, profile, "retries", &buf)) |text| {
+            controls.retry.retries = std.fmt.parseInt(u32, text, 10) catch {
+                jn_core.exitWithError("jn-cat: invalid retries '{s}'", .{text});
+            };
+        }
+        if (httpSetting(args, profile, "rate", &buf)) |text| {
+            controls.limiter = jn_core.RateLimiter.parse(text) catch {
+                jn_core.exitWithError("jn-cat: invalid rate '{s}' (e.g. 10/sec, 100/min)", .{text});
+            };
         }
+        return controls;
     }
-    const header_args = header_list.items;
+};
 
-    // Get format (default to json for HTTP profiles)
-    const format = address.effectiveFormat() orelse "json";
+/// `--name=value` from the command line, else the profile's "name" (a
+/// string or a number, printed into `buf`)
+fn httpSetting(args: *const jn_cli.ArgParser, profile: ?std.json.Value, name: []const u8, buf: []u8) ?[]const u8 {
+    if (args.get(name, null)) |value| return value;
+    const config = profile orelse return null;
+    const value = config.object.get(name) orelse return null;
+    return switch (value) {
+        .string, .number_string => |text| text,
+        .integer => |n| std.fmt.bufPrint(buf, "{d}", .{n}) catch null,
+        .float => |f| std.fmt.bufPrint(buf, "{d}", .{f}) catch null,
+        else => null,
+    };
+}
 
-    // Find format plugin
-    const plugin_path = findPlugin(allocator, format);
+/// The requests of one HTTP profile or URL. One jn-http client sends them
+/// all, so the pages of a paginated profile reuse their connection.
+const HttpSource = struct {
+    client: jn_http.Client,
+    /// Holds the headers and body built from a profile
+    arena_state: std.heap.ArenaAllocator,
+    controls: HttpControls,
+    /// Serializes the rate limiter across --each threads
+    mutex: std.Thread.Mutex = .{},
+    method: jn_http.Method = .GET,
+    headers: []const jn_http.Header = &.{},
+    body: ?[]const u8 = null,
+    follow_redirects: bool = true,
+    /// Add the `_http` envelope to every record
+    envelope: bool = false,
+    /// Credentials from the profile's `auth` block
+    auth: ?jn_http.Authenticator = null,
+    /// Names the source in errors: "profile @ns/name" or "URL"
+    what: []const u8,
+
+    fn init(allocator: std.mem.Allocator, controls: HttpControls, what: []const u8) HttpSource {
+        return .{
+            .client = jn_http.Client.init(allocator),
+            .arena_state = .init(allocator),
+            .controls = controls,
+            .what = what,
+        };
+    }
 
-    // Build format args (dynamically allocated, properly escaped)
-    const format_args = try buildFormatArgs(allocator, args);
-    defer allocator.free(format_args);
+    fn deinit(self: *HttpSource) void {
+        if (self.auth) |*auth| auth.deinit();
+        self.client.deinit();
+        self.arena_state.deinit();
+    }
+
+    /// Read `method`, `headers`, `body`, `auth`, `follow_redirects` and
+    /// `inject_meta` from an HTTP profile. A `body` that isn't a string is
+    /// sent as JSON.
+    fn applyProfile(self: *HttpSource, config: std.json.Value) !void {
+        const arena = self.arena_state.allocator();
+        var headers: std.ArrayListUnmanaged(jn_http.Header) = .empty;
+        var has_content_type = false;
+        if (config.object.get("headers")) |headers_val| {
+            if (headers_val == .object) {
+                var iter = headers_val.object.iterator();
+                while (iter.next()) |entry| {
+                    if (entry.value_ptr.* == .string) {
+                        // SECURITY: Validate header key and value to prevent HTTP header injection
+                        // (CR/LF characters could allow injecting additional headers or response splitting)
+                        if (!isValidHttpHeaderValue(entry.key_ptr.*) or !isValidHttpHeaderValue(entry.value_ptr.string)) {
+                            std.debug.print("jn-cat: warning: skipping header with invalid characters (CR/LF not allowed)\n", .{});
+                            continue;
+                        }
+                        if (std.ascii.eqlIgnoreCase(entry.key_ptr.*, "content-type")) has_content_type = true;
+                        try headers.append(arena, .{ .name = entry.key_ptr.*, .value = entry.value_ptr.string });
+                    }
+                }
+            }
+        }
 
-    // SECURITY: Escape the URL to prevent command injection
-    const escaped_url = try escapeShellPath(allocator, url_with_params);
-    defer escaped_url.deinit(allocator);
+        if (config.object.get("method")) |value| {
+            var buf: [16]u8 = undefined;
+            if (value != .string or value.string.len > buf.len) {
+                jn_core.exitWithError("jn-cat: {s} has an invalid method", .{self.what});
+            }
+            self.method = std.meta.stringToEnum(jn_http.Method, std.ascii.upperString(&buf, value.string)) orelse {
+                jn_core.exitWithError("jn-cat: {s} has an unknown method '{s}'", .{ self.what, value.string });
+            };
+        }
 
-    // Build curl command with headers
-    var shell_cmd: []const u8 = undefined;
+        if (config.object.get("body")) |value| switch (value) {
+            .string => |text| self.body = text,
+            .null => {},
+            else => {
+                self.body = try std.json.Stringify.valueAlloc(arena, value, .{});
+                if (!has_content_type) try headers.append(arena, .{ .name = "Content-Type", .value = "application/json" });
+            },
+        };
 
-    if (plugin_path) |fmt_path| {
-        shell_cmd = std.fmt.allocPrint(
-            allocator,
-            "curl -sS -L -f{s} '{s}' | {s} --mode=read{s}",
-            .{ header_args, escaped_url.slice, fmt_path, format_args },
-        ) catch {
-            jn_core.exitWithError("jn-cat: out of memory", .{});
+        if (config.object.get("auth")) |value| {
+            const auth_config = jn_http.AuthConfig.fromJson(value) catch |err| {
+                jn_core.exitWithError("jn-cat: {s} has an invalid auth block: {s}", .{ self.what, @errorName(err) });
+            };
+            self.auth = jn_http.Authenticator.init(self.arena_state.child_allocator, auth_config);
+            self.auth.?.cache_dir = authCacheDir(arena);
+            self.auth.?.tool = "jn-cat";
+        }
+
+        if (config.object.get("follow_redirects")) |value| {
+            if (value == .bool) self.follow_redirects = value.bool;
+        }
+        if (config.object.get("inject_meta")) |value| {
+            if (value == .bool) self.envelope = value.bool;
+        }
+        self.headers = headers.items;
+    }
+
+    fn request(self: *HttpSource, url: []const u8) jn_http.Request {
+        return .{
+            .method = self.method,
+            .url = url,
+            .headers = self.headers,
+            .body = self.body,
+            .follow_redirects = self.follow_redirects,
+            .timeout = self.controls.timeout,
+            .auth = if (self.auth) |*auth| auth else null,
         };
-    } else if (std.mem.eql(u8, format, "jsonl") or std.mem.eql(u8, format, "ndjson") or std.mem.eql(u8, format, "json")) {
-        shell_cmd = std.fmt.allocPrint(
-            allocator,
-            "curl -sS -L -f{s} '{s}'",
-            .{ header_args, escaped_url.slice },
-        ) catch {
-            jn_core.exitWithError("jn-cat: out of memory", .{});
+    }
+
+    /// Send a request to `url` and return the response. Exits once it has
+    /// failed for good.
+    fn send(self: *HttpSource, url: []const u8) *jn_http.Response {
+        const response = self.sendRetrying(self.request(url)) catch |err| exitRequestFailed(err, url, self.controls.timeout);
+        if (response.status >= 400) {
+            jn_core.exitWithError("jn-cat: HTTP error {d} fetching {s}: {s}", .{ response.status, self.what, url });
+        }
+        return response;
+    }
+
+    /// Send `req`, retrying dropped connections, timeouts, 408, 429 and 5xx
+    /// as the controls allow, and return the last response (which may be
+    /// an HTTP error). The body hasn't been read, so retries never repeat
+    /// output.
+    fn sendRetrying(self: *HttpSource, req: jn_http.Request) !*jn_http.Response {
+        const policy = self.controls.retry;
+        var attempt: u32 = 0;
+        while (true) : (attempt += 1) {
+            self.pace();
+            const response = self.client.send(req) catch |err| {
+                if (!jn_http.isTransientError(err) or attempt == policy.retries) return err;
+                self.backoff(attempt, null, "{s} fetching {s}", .{ @errorName(err), req.url });
+                continue;
+            };
+            if (response.status < 400 or !jn_core.retry.isRetryableStatus(response.status) or attempt == policy.retries) {
+                return response;
+            }
+            defer response.deinit();
+            const retry_after = if (response.header("retry-after")) |value| jn_core.retry.parseRetryAfter(value, std.time.timestamp()) else null;
+            self.backoff(attempt, retry_after, "HTTP {d} fetching {s}", .{ response.status, req.url });
+        }
+    }
+
+    /// Wait for the rate limit, if there is one
+    fn pace(self: *HttpSource) void {
+        if (self.controls.limiter) |*limiter| {
+            self.mutex.lock();
+            defer self.mutex.unlock();
+            limiter.acquire();
+        }
+    }
+
+    /// Say why retry `attempt + 1` is needed and wait for it
+    fn backoff(self: *const HttpSource, attempt: u32, retry_after: ?u64, comptime reason: []const u8, args: anytype) void {
+        const policy = self.controls.retry;
+        const delay_ms = policy.delay(attempt + 1, retry_after, std.crypto.random);
+        std.debug.print("jn-cat: " ++ reason ++ "; retry {d} of {d} in {d}ms\n", args ++ .{ attempt + 1, policy.retries, delay_ms });
+        std.Thread.sleep(delay_ms * std.time.ns_per_ms);
+    }
+
+    /// Fetch one page of a paginated profile (the `pagination.run` fetcher)
+    pub fn fetch(self: *HttpSource, allocator: std.mem.Allocator, url: []const u8) !pagination.Response {
+        const response = self.send(url);
+        defer response.deinit();
+        const body = response.readAll(allocator) catch |err| exitReadFailed(err, url);
+        const headers = try allocator.alloc(jn_http.Header, response.headers.len);
+        for (response.headers, 0..) |header, i| {
+            headers[i] = .{ .name = try allocator.dupe(u8, header.name), .value = try allocator.dupe(u8, header.value) };
+        }
+        return .{
+            .headers = headers,
+            .body = body,
+            .meta = if (self.envelope) try response.meta(allocator) else null,
         };
+    }
+
+    /// Send the request for one --each record (the `each.run` fetcher).
+    /// Called from several threads at once; failures are returned so the
+    /// record can go to the error file.
+    pub fn lookup(self: *HttpSource, allocator: std.mem.Allocator, url: []const u8, body: ?[]const u8) !each.Response {
+        var req = self.request(url);
+        req.body = body;
+        const response = try self.sendRetrying(req);
+        defer response.deinit();
+        return .{
+            .status = response.status,
+            .body = try response.readAll(allocator),
+            .meta = if (self.envelope) try response.meta(allocator) else null,
+        };
+    }
+
+    /// Describe the request to `url` and how it is sent under --explain
+    fn explainRequest(self: *const HttpSource, report: *jn_core.explain.Report, url: []const u8) void {
+        report.addRequest(@tagName(self.method), url, self.headers, if (self.method.requestHasBody()) self.body else null);
+        if (self.controls.timeout) |seconds| {
+            report.addNoteFmt("requests give up after {d}s without a response", .{seconds});
+        }
+        if (self.controls.retry.retries > 0) {
+            report.addNoteFmt("failed requests are retried up to {d} times with jittered backoff, honouring Retry-After", .{self.controls.retry.retries});
+        }
+        if (self.controls.limiter) |limiter| {
+            report.addNoteFmt("requests are limited to {d} per second", .{limiter.per_second});
+        }
+        if (self.envelope) report.addNote("each record gets an _http field with the response status, URL and headers");
+        if (self.auth) |auth| {
+            switch (auth.config) {
+                .basic => report.addNote("requests carry HTTP basic credentials"),
+                .bearer => report.addNote("requests carry a bearer token"),
+                .oauth2 => |oauth| report.addNoteFmt("requests carry an OAuth2 token ({s} grant), cached and renewed when it expires or gets a 401", .{@tagName(oauth.grant)}),
+                .aws_sigv4 => |aws| report.addNoteFmt("requests are signed with AWS SigV4 for {s} in {s}", .{ aws.service, aws.region }),
+            }
+        }
+    }
+};
+
+/// Where OAuth2 tokens are cached: `$JN_HOME/cache/auth`, else
+/// `~/.local/jn/cache/auth`
+fn authCacheDir(arena: std.mem.Allocator) ?[]const u8 {
+    if (std.posix.getenv("JN_HOME")) |home| {
+        return std.fmt.allocPrint(arena, "{s}/cache/auth", .{home}) catch null;
+    }
+    const home = std.posix.getenv("HOME") orelse return null;
+    return std.fmt.allocPrint(arena, "{s}/.local/jn/cache/auth", .{home}) catch null;
+}
+
+fn exitRequestFailed(err: anyerror, url: []const u8, timeout: ?f64) noreturn {
+    switch (err) {
+        error.UnknownHostName => jn_core.exitWithError("jn-cat: could not resolve host for URL: {s}", .{url}),
+        error.Timeout => jn_core.exitWithError("jn-cat: no response within {d}s from {s}", .{ timeout orelse 0, url }),
+        // The token endpoint's reply has been printed
+        error.TokenRequestFailed, error.DeviceCodeExpired => jn_core.exitWithError("jn-cat: cannot get an access token for {s}", .{url}),
+        else => jn_core.exitWithError("jn-cat: request to {s} failed: {s}", .{ url, @errorName(err) }),
+    }
+}
+
+fn exitReadFailed(err: anyerror, url: []const u8) noreturn {
+    jn_core.exitWithError("jn-cat: reading the response from {s} failed: {s}", .{ url, @errorName(err) });
+}
+
+/// Send the request to `url` and stream its body through `pipeline`
+/// (decompression and format stages, or none for NDJSON) to stdout.
+fn streamHttp(allocator: std.mem.Allocator, source: *HttpSource, url: []const u8, pipeline: *jn_core.Pipeline) !void {
+    const staged = pipeline.stages.items.len > 0;
+    if (staged) {
+        pipeline.stdin = .pipe;
+        // Records come back through jn-cat to get the envelope
+        if (source.envelope) pipeline.stdout = .pipe;
+    }
+    if (explain) |report| {
+        source.explainRequest(report, url);
+        if (staged) report.addPipeline(pipeline);
+        report.finish();
+    }
+
+    const response = source.send(url);
+    defer response.deinit();
+    const body = response.reader() catch |err| exitReadFailed(err, url);
+    const meta = if (source.envelope) try response.meta(allocator) else null;
+    defer if (meta) |json| allocator.free(json);
+
+    if (!staged) return copyRecords(body, meta, url);
+
+    var running = pipeline.spawn() catch |err| {
+        jn_core.exitWithError("jn-cat: cannot start pipeline: {s}", .{@errorName(err)});
+    };
+    // feedBody closes the first stage's stdin when the body ends
+    const stdin = running.stdin.?;
+    running.stdin = null;
+    if (meta) |json| {
+        // Feed the body on another thread while the records come back here
+        const feeder = try std.Thread.spawn(.{}, feedBody, .{ body, stdin, url });
+        var stdout_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
+        var records = running.stdout.?.readerStreaming(&stdout_buf);
+        copyRecords(&records.interface, json, url);
+        feeder.join();
     } else {
-        jn_core.exitWithError("jn-cat: format plugin '{s}' not found", .{format});
+        feedBody(body, stdin, url);
     }
-    defer allocator.free(shell_cmd);
 
-    // Run via shell
-    const shell_argv: [3][]const u8 = .{ "/bin/sh", "-c", shell_cmd };
-    var shell_child = std.process.Child.init(&shell_argv, allocator);
-    shell_child.stdin_behavior = .Close;
-    shell_child.stdout_behavior = .Inherit;
-    shell_child.stderr_behavior = .Inherit;
+    const result = running.wait() catch |err| {
+        jn_core.exitWithError("jn-cat: cannot wait for pipeline: {s}", .{@errorName(err)});
+    };
+    pipeline.exitOnFailure(result, "jn-cat");
+}
 
-    try shell_child.spawn();
-    const result = shell_child.wait() catch |err| {
-        jn_core.exitWithError("jn-cat: profile fetch failed: {s}", .{@errorName(err)});
+/// Write a response body into the first stage of a pipeline, then close it.
+fn feedBody(body: *std.Io.Reader, stdin: std.fs.File, url: []const u8) void {
+    defer stdin.close();
+    var buf: [jn_core.STDOUT_BUFFER_SIZE]u8 = undefined;
+    var stdin_wrapper = stdin.writerStreaming(&buf);
+    const writer = &stdin_wrapper.interface;
+    _ = body.streamRemaining(writer) catch |err| switch (err) {
+        // The stage stopped reading, e.g. `jn head` exited downstream
+        error.WriteFailed => return,
+        error.ReadFailed => exitReadFailed(err, url),
     };
+    writer.flush() catch {};
+}
 
-    switch (result) {
-        .Exited => |code| {
-            if (code != 0) {
-                if (code == 22) {
-                    jn_core.exitWithError("jn-cat: HTTP error fetching profile @{s}/{s}: {s}", .{ namespace, name, url_with_params });
-                }
-                std.process.exit(code);
-            }
-        },
-        else => {
-            std.process.exit(1);
-        },
+/// Copy NDJSON records to stdout, adding the `_http` envelope when `meta`
+/// is set.
+fn copyRecords(reader: *std.Io.Reader, meta: ?[]const u8, url: []const u8) void {
+    var stdout_buf: [jn_core.STDOUT_BUFFER_SIZE]u8 = undefined;
+    var stdout_wrapper = std.fs.File.stdout().writerStreaming(&stdout_buf);
+    const writer = &stdout_wrapper.interface;
+
+    const json = meta orelse {
+        _ = reader.streamRemaining(writer) catch |err| switch (err) {
+            error.WriteFailed => jn_core.handleWriteError(stdout_wrapper.err orelse err),
+            error.ReadFailed => exitReadFailed(err, url),
+        };
+        return jn_core.flushWriter(writer);
+    };
+    while (jn_core.readLine(reader)) |line| {
+        if (line.len == 0) continue;
+        jn_http.envelope.write(writer, json, line) catch |err| jn_core.handleWriteError(stdout_wrapper.err orelse err);
+    }
+    jn_core.flushWriter(writer);
+}
+
+/// Stream the records of every page of an HTTP profile as NDJSON
+fn fetchPages(allocator: std.mem.Allocator, source: *HttpSource, paging: pagination.Config, url: []const u8) !void {
+    if (explain) |report| {
+        const first_url = try pagination.firstUrl(allocator, paging, url);
+        defer allocator.free(first_url);
+        const note = if (paging.strategy) |strategy|
+            try std.fmt.allocPrint(allocator, "pages are requested one at a time ({s} pagination) and their records written as NDJSON", .{@tagName(strategy)})
+        else
+            try std.fmt.allocPrint(allocator, "records at '{s}' are written as NDJSON", .{paging.items_path});
+        defer allocator.free(note);
+        report.addNote(note);
+        source.explainRequest(report, first_url);
+        report.finish();
+    }
+
+    var stdout_buf: [jn_core.STDOUT_BUFFER_SIZE]u8 = undefined;
+    var stdout_wrapper = std.fs.File.stdout().writerStreaming(&stdout_buf);
+    const writer = &stdout_wrapper.interface;
+
+    pagination.run(allocator, paging, url, source, writer) catch |err| switch (err) {
+        error.WriteFailed => jn_core.handleWriteError(stdout_wrapper.err orelse err),
+        error.InvalidJson => jn_core.exitWithError("jn-cat: {s} returned a page that is not JSON", .{source.what}),
+        else => return err,
+    };
+}
+
+/// Send an HTTP profile's request once per NDJSON record on stdin (--each)
+/// and write each record with its response merged in
+fn fetchEach(allocator: std.mem.Allocator, source: *HttpSource, config: std.json.Value, url: []const u8, args: *const jn_cli.ArgParser) !void {
+    var settings = each.Config.fromProfile(config, url) catch {
+        jn_core.exitWithError("jn-cat: {s} has an invalid each block", .{source.what});
+    };
+    if (args.get("into", null)) |field| settings.into = field;
+    if (args.get("concurrency", null)) |text| {
+        settings.concurrency = each.parseConcurrency(.{ .string = text }) catch {
+            jn_core.exitWithError("jn-cat: invalid concurrency '{s}' (at least 1)", .{text});
+        };
+    }
+
+    if (explain) |report| {
+        report.addNoteFmt("each stdin record sends its own request ({d} at a time), filling {{field}} placeholders", .{settings.concurrency});
+        if (settings.into) |field| {
+            report.addNoteFmt("responses are added to their records as '{s}'", .{field});
+        } else {
+            report.addNote("response fields are merged into their records, in input order");
+        }
+        source.explainRequest(report, url);
+        report.finish();
     }
+
+    var errors = jn_core.ErrorSink.fromArgs(allocator, args, "jn-cat");
+    defer errors.close();
+
+    var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
+    var stdin_wrapper = std.fs.File.stdin().readerStreaming(&stdin_buf);
+    var stdout_buf: [jn_core.STDOUT_BUFFER_SIZE]u8 = undefined;
+    var stdout_wrapper = std.fs.File.stdout().writerStreaming(&stdout_buf);
+    const writer = &stdout_wrapper.interface;
+
+    each.run(allocator, settings, &stdin_wrapper.interface, source, writer, &errors) catch |err| switch (err) {
+        error.WriteFailed => jn_core.handleWriteError(stdout_wrapper.err orelse err),
+        error.LookupFailed => std.process.exit(1),
+        else => return err,
+    };
 }
 
 /// Handle file/folder profiles - expand glob pattern and process files
@@ -781,6 +1117,7 @@ fn handleFileProfile(allocator: std.mem.Allocator, address: jn_address.Address,
         jn_core.exitWithError("jn-cat: failed to load file profile @{s}/{s}: {s}", .{ namespace, name, @errorName(err) });
     };
     defer jn_profile.freeValue(allocator, config);
+    if (explain) |report| report.setConfig(config);
 
     // Extract profile fields
     const pattern = if (config.object.get("pattern")) |v| v.string else null;
@@ -828,7 +1165,7 @@ fn handleFileProfile(allocator: std.mem.Allocator, address: jn_address.Address,
         // Process glob directly with modified args
         if (effective_inject_meta and !args.has("meta") and !args.has("inject-meta")) {
             // Need to force inject_meta - call handleGlob with meta flag
-            // Create a modified args parser isn't straightforward, so use shell pipeline
+            // Creating a modified args parser isn't straightforward, so expand the pattern here
             try handleFileProfileDirect(allocator, full_pattern, args, effective_inject_meta);
         } else {
             try handleGlob(allocator, glob_address, args);
@@ -840,105 +1177,32 @@ fn handleFileProfile(allocator: std.mem.Allocator, address: jn_address.Address,
 fn handleFileProfileDirect(allocator: std.mem.Allocator, pattern: []const u8, args: *const jn_cli.ArgParser, inject_meta: bool) !void {
     _ = args;
 
-    // Build shell command for glob expansion
-    var expand_cmd: []const u8 = undefined;
-
-    if (std.mem.indexOf(u8, pattern, "**") != null) {
-        // Use find for recursive patterns
-        const last_slash = std.mem.lastIndexOf(u8, pattern, "/") orelse 0;
-        const base_dir = if (last_slash > 0) pattern[0..last_slash] else ".";
-        var file_pattern = pattern[last_slash + 1 ..];
-
-        // Strip **/ prefix from file pattern
-        while (std.mem.startsWith(u8, file_pattern, "**/")) {
-            file_pattern = file_pattern[3..];
-        }
-        if (std.mem.startsWith(u8, file_pattern, "**")) {
-            file_pattern = file_pattern[2..];
-        }
-
-        // Extract the base directory (before any **)
-        var dir_to_search = base_dir;
-        if (std.mem.indexOf(u8, base_dir, "**")) |double_star| {
-            dir_to_search = if (double_star > 0) base_dir[0 .. double_star - 1] else ".";
-        }
+    const files = jn_core.glob.expand(allocator, pattern) catch |err| {
+        jn_core.exitWithError("jn-cat: cannot expand pattern '{s}': {s}", .{ pattern, @errorName(err) });
+    };
+    defer jn_core.glob.freeMatches(allocator, files);
 
-        // SECURITY: Escape directory and file pattern
-        const escaped_dir = try escapeShellPath(allocator, dir_to_search);
-        defer escaped_dir.deinit(allocator);
-        const escaped_file_pattern = try escapeShellPath(allocator, file_pattern);
-        defer escaped_file_pattern.deinit(allocator);
-
-        expand_cmd = std.fmt.allocPrint(
-            allocator,
-            "find '{s}' -type f -name '{s}' 2>/dev/null | sort",
-            .{ escaped_dir.slice, escaped_file_pattern.slice },
-        ) catch {
-            jn_core.exitWithError("jn-cat: out of memory", .{});
-        };
-    } else {
-        // Simple glob - use bash with globstar
-        if (!jn_core.isGlobPatternSafe(pattern)) {
-            jn_core.exitWithError("jn-cat: glob pattern contains unsafe characters: {s}", .{pattern});
+    if (explain) |report| {
+        for (files) |file_path| {
+            const note = try std.fmt.allocPrint(allocator, "{s} is copied to stdout{s}", .{ file_path, if (inject_meta) " with path metadata" else "" });
+            defer allocator.free(note);
+            report.addNote(note);
         }
-
-        expand_cmd = std.fmt.allocPrint(
-            allocator,
-            "shopt -s nullglob; for f in {s}; do [ -f \"$f\" ] && echo \"$f\"; done",
-            .{pattern},
-        ) catch {
-            jn_core.exitWithError("jn-cat: out of memory", .{});
-        };
+        if (files.len == 0) report.addNote("no files match the pattern");
+        report.finish();
     }
-    defer allocator.free(expand_cmd);
-
-    // Use bash for better glob support
-    const expand_argv: [3][]const u8 = .{ "/bin/bash", "-c", expand_cmd };
-    var expand_child = std.process.Child.init(&expand_argv, allocator);
-    expand_child.stdin_behavior = .Close;
-    expand_child.stdout_behavior = .Pipe;
-    expand_child.stderr_behavior = .Inherit;
-
-    try expand_child.spawn();
-
-    // Read file list from stdout and process
-    const stdout_pipe = expand_child.stdout.?;
-    var file_count: usize = 0;
 
-    var reader_buf: [4096]u8 = undefined;
-    var reader_wrapper = stdout_pipe.reader(&reader_buf);
-    const reader = &reader_wrapper.interface;
-    while (jn_core.readLine(reader)) |line| {
-        const file_path = std.mem.trim(u8, line, " \t\r\n");
-        if (file_path.len == 0) continue;
+    if (files.len == 0) {
+        jn_core.exitWithError("jn-cat: no files match pattern: {s}", .{pattern});
+    }
 
+    for (files, 0..) |file_path, file_index| {
         // Process this file with metadata injection
         if (inject_meta) {
-            try outputWithMeta(allocator, file_path, file_count);
+            try outputWithMeta(allocator, file_path, file_index);
         } else {
-            // Just cat the file
-            const escaped_path = try escapeShellPath(allocator, file_path);
-            defer escaped_path.deinit(allocator);
-
-            const shell_cmd = std.fmt.allocPrint(allocator, "cat '{s}'", .{escaped_path.slice}) catch continue;
-            defer allocator.free(shell_cmd);
-
-            const shell_argv: [3][]const u8 = .{ "/bin/sh", "-c", shell_cmd };
-            var child = std.process.Child.init(&shell_argv, allocator);
-            child.stdin_behavior = .Close;
-            child.stdout_behavior = .Inherit;
-            child.stderr_behavior = .Inherit;
-
-            try child.spawn();
-            _ = child.wait() catch {};
+            copyToStdout(file_path);
         }
-        file_count += 1;
-    }
-
-    _ = expand_child.wait() catch {};
-
-    if (file_count == 0) {
-        jn_core.exitWithError("jn-cat: no files match pattern: {s}", .{pattern});
     }
 }
 
@@ -962,51 +1226,10 @@ fn handleFileProfileWithFilter(allocator: std.mem.Allocator, glob_address: jn_ad
         }
     } else |_| {}
 
-    // Build glob expansion command
-    var expand_part: []const u8 = undefined;
-
-    if (std.mem.indexOf(u8, pattern, "**") != null) {
-        const last_slash = std.mem.lastIndexOf(u8, pattern, "/") orelse 0;
-        const base_dir = if (last_slash > 0) pattern[0..last_slash] else ".";
-        var file_pattern = pattern[last_slash + 1 ..];
-
-        while (std.mem.startsWith(u8, file_pattern, "**/")) {
-            file_pattern = file_pattern[3..];
-        }
-        if (std.mem.startsWith(u8, file_pattern, "**")) {
-            file_pattern = file_pattern[2..];
-        }
-
-        var dir_to_search = base_dir;
-        if (std.mem.indexOf(u8, base_dir, "**")) |double_star| {
-            dir_to_search = if (double_star > 0) base_dir[0 .. double_star - 1] else ".";
-        }
-
-        const escaped_dir = try escapeShellPath(allocator, dir_to_search);
-        defer escaped_dir.deinit(allocator);
-        const escaped_file_pattern = try escapeShellPath(allocator, file_pattern);
-        defer escaped_file_pattern.deinit(allocator);
-
-        expand_part = std.fmt.allocPrint(allocator, "find '{s}' -type f -name '{s}' 2>/dev/null | sort", .{ escaped_dir.slice, escaped_file_pattern.slice }) catch {
-            jn_core.exitWithError("jn-cat: out of memory", .{});
-        };
-    } else {
-        if (!jn_core.isGlobPatternSafe(pattern)) {
-            jn_core.exitWithError("jn-cat: glob pattern contains unsafe characters: {s}", .{pattern});
-        }
-        expand_part = std.fmt.allocPrint(allocator, "shopt -s nullglob; for f in {s}; do [ -f \"$f\" ] && echo \"$f\"; done", .{pattern}) catch {
-            jn_core.exitWithError("jn-cat: out of memory", .{});
-        };
+    if (explain) |report| {
+        const on_path = std.mem.eql(u8, filter_path, "jn-filter");
+        report.addPlugin(.{ .name = "jn-filter", .kind = "zig", .path = filter_path, .source = if (on_path) "PATH" else "next to jn-cat", .priority = if (on_path) 2 else 1 });
     }
-    defer allocator.free(expand_part);
-
-    // Escape filter for shell
-    const escaped_filter = try escapeShellPath(allocator, filter);
-    defer escaped_filter.deinit(allocator);
-
-    // Build full pipeline: expand files -> read each with metadata -> filter
-    // We need to process files in a subshell and pipe through filter
-    const meta_flag: []const u8 = if (inject_meta) " --meta" else "";
 
     // Find jn-cat executable path
     var cat_path: []const u8 = "jn-cat";
@@ -1014,43 +1237,15 @@ fn handleFileProfileWithFilter(allocator: std.mem.Allocator, glob_address: jn_ad
         cat_path = exe_path;
     } else |_| {}
 
-    const escaped_cat_path = try escapeShellPath(allocator, cat_path);
-    defer escaped_cat_path.deinit(allocator);
-    const escaped_pattern = try escapeShellPath(allocator, pattern);
-    defer escaped_pattern.deinit(allocator);
-
-    // Build the shell command: jn-cat 'pattern' [--meta] | jn-filter 'filter'
-    const shell_cmd = std.fmt.allocPrint(
-        allocator,
-        "'{s}' '{s}'{s} | '{s}' '{s}'",
-        .{ escaped_cat_path.slice, escaped_pattern.slice, meta_flag, filter_path, escaped_filter.slice },
-    ) catch {
-        jn_core.exitWithError("jn-cat: out of memory", .{});
-    };
-    defer allocator.free(shell_cmd);
-
-    // Run via bash (need shopt for globs)
-    const shell_argv: [3][]const u8 = .{ "/bin/bash", "-c", shell_cmd };
-    var shell_child = std.process.Child.init(&shell_argv, allocator);
-    shell_child.stdin_behavior = .Close;
-    shell_child.stdout_behavior = .Inherit;
-    shell_child.stderr_behavior = .Inherit;
-
-    try shell_child.spawn();
-    const result = shell_child.wait() catch |err| {
-        jn_core.exitWithError("jn-cat: file profile execution failed: {s}", .{@errorName(err)});
-    };
+    // Pipeline: jn-cat <pattern> [--meta] | jn-filter <filter>
+    var pipeline = jn_core.Pipeline.init(allocator);
+    defer pipeline.deinit();
+    pipeline.stdin = .null_device;
+    const cat_stage = try pipeline.add(&.{ cat_path, pattern });
+    if (inject_meta) try cat_stage.arg("--meta");
+    _ = try pipeline.add(&.{ filter_path, filter });
 
-    switch (result) {
-        .Exited => |code| {
-            if (code != 0) {
-                std.process.exit(code);
-            }
-        },
-        else => {
-            std.process.exit(1);
-        },
-    }
+    runPipelineOrExit(&pipeline);
 }
 
 /// Handle URL address (http://, https://, s3://, etc.)
@@ -1059,7 +1254,7 @@ fn handleUrl(allocator: std.mem.Allocator, address: jn_address.Address, args: *c
         jn_core.exitWithError("jn-cat: URL has no protocol: {s}", .{address.raw});
     };
 
-    // For HTTP/HTTPS, use curl (OpenDAL HTTP service doesn't work well with REST APIs)
+    // For HTTP/HTTPS, use jn-http (OpenDAL HTTP service doesn't work well with REST APIs)
     if (std.mem.eql(u8, protocol, "http") or std.mem.eql(u8, protocol, "https")) {
         try handleHttpUrl(allocator, address, args);
         return;
@@ -1078,7 +1273,7 @@ fn handleUrl(allocator: std.mem.Allocator, address: jn_address.Address, args: *c
     jn_core.exitWithError("jn-cat: protocol '{s}' not supported\nAddress: {s}", .{ protocol, address.raw });
 }
 
-/// Handle HTTP/HTTPS URL using curl
+/// Handle HTTP/HTTPS URL with jn-http
 fn handleHttpUrl(allocator: std.mem.Allocator, address: jn_address.Address, args: *const jn_cli.ArgParser) !void {
     const format = address.effectiveFormat() orelse "json"; // Default to JSON for HTTP
 
@@ -1093,100 +1288,35 @@ fn handleHttpUrl(allocator: std.mem.Allocator, address: jn_address.Address, args
     // Find format plugin
     const plugin_path = findPlugin(allocator, format);
 
-    // Build format args (dynamically allocated, properly escaped)
-    const format_args = try buildFormatArgs(allocator, args);
-    defer allocator.free(format_args);
-
-    // Build header arg if provided
-    // Note: Headers from --header arg could also contain quotes; escape them
-    var header_arg_buf: [512]u8 = undefined;
-    var header_arg: []const u8 = "";
-    var escaped_header_storage: ?jn_core.EscapedString = null;
-    defer if (escaped_header_storage) |eh| eh.deinit(allocator);
-    if (args.get("header", null)) |header| {
-        // SECURITY: Escape the header to prevent command injection
-        if (escapeShellPath(allocator, header)) |escaped_header| {
-            escaped_header_storage = escaped_header;
-            header_arg = std.fmt.bufPrint(&header_arg_buf, " -H '{s}'", .{escaped_header.slice}) catch "";
-        } else |_| {}
+    var source = HttpSource.init(allocator, HttpControls.init(args, null), "URL");
+    defer source.deinit();
+    source.envelope = args.has("meta") or args.has("inject-meta");
+    var header: [1]jn_http.Header = undefined;
+    if (args.get("header", null)) |text| {
+        const colon = std.mem.indexOfScalar(u8, text, ':') orelse 0;
+        if (colon == 0 or !isValidHttpHeaderValue(text)) {
+            jn_core.exitWithError("jn-cat: invalid header '{s}' (expected 'Name: value')", .{text});
+        }
+        header[0] = .{ .name = std.mem.trim(u8, text[0..colon], " "), .value = std.mem.trim(u8, text[colon + 1 ..], " ") };
+        source.headers = &header;
     }
 
-    // SECURITY: Escape the URL to prevent command injection
-    const escaped_url = try escapeShellPath(allocator, url);
-    defer escaped_url.deinit(allocator);
-
-    // Build the shell command
-    var shell_cmd: []const u8 = undefined;
-
+    // <response body> [| gz|bz2|xz|zst --mode=raw] [| format --mode=read]
+    var pipeline = jn_core.Pipeline.init(allocator);
+    defer pipeline.deinit();
     if (address.compression != .none) {
-        // URL with compression: curl | gunzip | format_plugin
-        const gz_path = findPlugin(allocator, "gz") orelse {
-            jn_core.exitWithError("jn-cat: compression plugin 'gz' not found", .{});
-        };
+        try addDecompressStage(allocator, &pipeline, address.compression);
+    }
 
-        if (plugin_path) |fmt_path| {
-            // curl URL | gz --mode=raw | format --mode=read
-            shell_cmd = try std.fmt.allocPrint(
-                allocator,
-                "curl -sS -L -f{s} '{s}' | {s} --mode=raw | {s} --mode=read{s}",
-                .{ header_arg, escaped_url.slice, gz_path, fmt_path, format_args },
-            );
-        } else {
-            // No format plugin (assume JSONL) - just decompress
-            shell_cmd = try std.fmt.allocPrint(
-                allocator,
-                "curl -sS -L -f{s} '{s}' | {s} --mode=raw",
-                .{ header_arg, escaped_url.slice, gz_path },
-            );
-        }
-    } else if (plugin_path) |fmt_path| {
-        // curl URL | format_plugin --mode=read
-        shell_cmd = try std.fmt.allocPrint(
-            allocator,
-            "curl -sS -L -f{s} '{s}' | {s} --mode=read{s}",
-            .{ header_arg, escaped_url.slice, fmt_path, format_args },
-        );
-    } else if (std.mem.eql(u8, format, "jsonl") or std.mem.eql(u8, format, "ndjson")) {
-        // JSONL/NDJSON - just curl directly
-        shell_cmd = try std.fmt.allocPrint(
-            allocator,
-            "curl -sS -L -f{s} '{s}'",
-            .{ header_arg, escaped_url.slice },
-        );
-    } else {
+    if (plugin_path) |fmt_path| {
+        const format_stage = try pipeline.add(&.{ fmt_path, "--mode=read" });
+        try appendFormatArgs(format_stage, args, address.raw);
+    } else if (address.compression == .none and !std.mem.eql(u8, format, "jsonl") and !std.mem.eql(u8, format, "ndjson")) {
+        // Without a plugin, only JSONL/NDJSON (or decompressed data) passes through
         jn_core.exitWithError("jn-cat: format plugin '{s}' not found", .{format});
     }
-    defer allocator.free(shell_cmd);
-
-    // Run via shell
-    const shell_argv: [3][]const u8 = .{ "/bin/sh", "-c", shell_cmd };
-    var shell_child = std.process.Child.init(&shell_argv, allocator);
-    shell_child.stdin_behavior = .Close;
-    shell_child.stdout_behavior = .Inherit;
-    shell_child.stderr_behavior = .Inherit;
-
-    try shell_child.spawn();
-    const result = shell_child.wait() catch |err| {
-        jn_core.exitWithError("jn-cat: URL fetch failed: {s}", .{@errorName(err)});
-    };
 
-    switch (result) {
-        .Exited => |code| {
-            if (code != 0) {
-                // curl exit codes: 22 = HTTP error (4xx/5xx), 6 = couldn't resolve host
-                if (code == 22) {
-                    jn_core.exitWithError("jn-cat: HTTP error fetching URL: {s}", .{url});
-                } else if (code == 6) {
-                    jn_core.exitWithError("jn-cat: could not resolve host for URL: {s}", .{url});
-                } else {
-                    std.process.exit(code);
-                }
-            }
-        },
-        else => {
-            std.process.exit(1);
-        },
-    }
+    try streamHttp(allocator, &source, url, &pipeline);
 }
 
 /// Handle glob pattern - expand and process each file
@@ -1195,94 +1325,25 @@ fn handleGlob(allocator: std.mem.Allocator, address: jn_address.Address, args: *
     const format = address.effectiveFormat();
     const inject_meta = args.has("meta") or args.has("inject-meta");
 
-    // Build shell command for glob expansion
-    // For ** patterns, use find; for simple globs, use bash globstar
-    var expand_cmd: []const u8 = undefined;
-
-    if (std.mem.indexOf(u8, pattern, "**") != null) {
-        // Use find for recursive patterns - convert ** to -name pattern
-        // Split pattern into base dir and file pattern
-        // e.g., "test_data/**/*.jsonl" -> find test_data -name "*.jsonl"
-        const last_slash = std.mem.lastIndexOf(u8, pattern, "/") orelse 0;
-        const base_dir = if (last_slash > 0) pattern[0..last_slash] else ".";
-        var file_pattern = pattern[last_slash + 1 ..];
-
-        // Strip **/ prefix from file pattern
-        while (std.mem.startsWith(u8, file_pattern, "**/")) {
-            file_pattern = file_pattern[3..];
-        }
-        if (std.mem.startsWith(u8, file_pattern, "**")) {
-            file_pattern = file_pattern[2..];
-        }
+    const files = jn_core.glob.expand(allocator, pattern) catch |err| {
+        jn_core.exitWithError("jn-cat: cannot expand pattern '{s}': {s}", .{ pattern, @errorName(err) });
+    };
+    defer jn_core.glob.freeMatches(allocator, files);
 
-        // Extract the base directory (before any **)
-        var dir_to_search = base_dir;
-        if (std.mem.indexOf(u8, base_dir, "**")) |double_star| {
-            dir_to_search = if (double_star > 0) base_dir[0 .. double_star - 1] else ".";
+    if (files.len == 0) {
+        if (explain) |report| {
+            report.addNote("no files match the pattern");
+            report.finish();
         }
-
-        // SECURITY: Escape directory and file pattern to prevent shell injection
-        const escaped_dir = try escapeShellPath(allocator, dir_to_search);
-        defer escaped_dir.deinit(allocator);
-        const escaped_file_pattern = try escapeShellPath(allocator, file_pattern);
-        defer escaped_file_pattern.deinit(allocator);
-
-        // Use find with -name pattern
-        expand_cmd = std.fmt.allocPrint(
-            allocator,
-            "find '{s}' -type f -name '{s}' 2>/dev/null | sort",
-            .{ escaped_dir.slice, escaped_file_pattern.slice },
-        ) catch {
-            jn_core.exitWithError("jn-cat: out of memory", .{});
-        };
-    } else {
-        // Simple glob - use bash with globstar
-        // SECURITY: Validate pattern contains only safe characters for unquoted glob expansion
-        // We cannot quote the pattern (glob chars wouldn't expand), so we must reject unsafe patterns
-        if (!jn_core.isGlobPatternSafe(pattern)) {
-            jn_core.exitWithError("jn-cat: glob pattern contains unsafe characters: {s}", .{pattern});
-        }
-
-        expand_cmd = std.fmt.allocPrint(
-            allocator,
-            "shopt -s nullglob; for f in {s}; do [ -f \"$f\" ] && echo \"$f\"; done",
-            .{pattern},
-        ) catch {
-            jn_core.exitWithError("jn-cat: out of memory", .{});
-        };
+        jn_core.exitWithError("jn-cat: no files match pattern: {s}", .{pattern});
     }
-    defer allocator.free(expand_cmd);
-
-    // Use bash for better glob support
-    const expand_argv: [3][]const u8 = .{ "/bin/bash", "-c", expand_cmd };
-    var expand_child = std.process.Child.init(&expand_argv, allocator);
-    expand_child.stdin_behavior = .Close;
-    expand_child.stdout_behavior = .Pipe;
-    expand_child.stderr_behavior = .Inherit;
-
-    try expand_child.spawn();
-
-    // Read file list from stdout
-    const stdout_pipe = expand_child.stdout.?;
-    var file_count: usize = 0;
-
-    var reader_buf: [4096]u8 = undefined;
-    var reader_wrapper = stdout_pipe.reader(&reader_buf);
-    const reader = &reader_wrapper.interface;
-    while (jn_core.readLine(reader)) |line| {
-        const file_path = std.mem.trim(u8, line, " \t\r\n");
-        if (file_path.len == 0) continue;
 
-        // Process this file
-        try processGlobFile(allocator, file_path, format, args, file_count, inject_meta);
-        file_count += 1;
+    for (files, 0..) |file_path, file_index| {
+        try processGlobFile(allocator, file_path, format, args, file_index, inject_meta);
     }
 
-    _ = expand_child.wait() catch {};
-
-    if (file_count == 0) {
-        jn_core.exitWithError("jn-cat: no files match pattern: {s}", .{pattern});
-    }
+    // Under --explain each file added its pipeline
+    if (explain) |report| report.finish();
 }
 
 /// Process a single file from glob expansion
@@ -1315,6 +1376,12 @@ fn processGlobFile(allocator: std.mem.Allocator, file_path: []const u8, format:
     // For JSONL with metadata injection, handle specially (before plugin lookup)
     const is_jsonl = std.mem.eql(u8, effective_format, "jsonl") or std.mem.eql(u8, effective_format, "ndjson");
     if (is_jsonl and inject_meta and compression == .none) {
+        if (explain) |report| {
+            const note = try std.fmt.allocPrint(allocator, "{s} is copied to stdout with path metadata", .{file_path});
+            defer allocator.free(note);
+            report.addNote(note);
+            return;
+        }
         try outputWithMeta(allocator, file_path, file_index);
         return;
     }
@@ -1322,87 +1389,66 @@ fn processGlobFile(allocator: std.mem.Allocator, file_path: []const u8, format:
     // Find format plugin (including Python plugins)
     const plugin_info = findPluginInfo(allocator, effective_format);
 
-    // Build format args (dynamically allocated, properly escaped)
-    const format_args = try buildFormatArgs(allocator, args);
-    defer allocator.free(format_args);
-
-    // SECURITY: Escape the file path to prevent command injection
-    const escaped_file_path = try escapeShellPath(allocator, file_path);
-    defer escaped_file_path.deinit(allocator);
-
-    // Build the pipeline command
-    var shell_cmd: []const u8 = undefined;
+    // Pipeline: [gz|bz2|xz|zst --mode=raw] < file | format --mode=read [args]
+    var pipeline = jn_core.Pipeline.init(allocator);
+    defer pipeline.deinit();
+    pipeline.stdin = .{ .file = file_path };
 
     if (compression != .none) {
-        const gz_path = findPlugin(allocator, "gz") orelse {
-            jn_core.exitWithError("jn-cat: compression plugin 'gz' not found", .{});
-        };
+        try addDecompressStage(allocator, &pipeline, compression);
+    }
 
-        if (plugin_info) |info| {
-            if (info.plugin_type == .python) {
-                // Python plugin with compression
-                shell_cmd = try std.fmt.allocPrint(
-                    allocator,
-                    "cat '{s}' | {s} --mode=raw | uv run --script {s} --mode=read{s}",
-                    .{ escaped_file_path.slice, gz_path, info.path, format_args },
-                );
-            } else {
-                shell_cmd = try std.fmt.allocPrint(
-                    allocator,
-                    "cat '{s}' | {s} --mode=raw | {s} --mode=read{s}",
-                    .{ escaped_file_path.slice, gz_path, info.path, format_args },
-                );
-            }
-        } else {
-            shell_cmd = try std.fmt.allocPrint(
-                allocator,
-                "cat '{s}' | {s} --mode=raw",
-                .{ escaped_file_path.slice, gz_path },
-            );
+    if (plugin_info) |info| {
+        const stage = try addPluginStage(&pipeline, info, "read");
+        try appendFormatArgs(stage, args, file_path);
+    } else if (compression == .none) {
+        if (!is_jsonl) {
+            jn_core.exitWithError("jn-cat: format plugin '{s}' not found", .{effective_format});
         }
-    } else if (plugin_info) |info| {
-        if (info.plugin_type == .python) {
-            // Python plugin
-            shell_cmd = try std.fmt.allocPrint(
-                allocator,
-                "cat '{s}' | uv run --script {s} --mode=read{s}",
-                .{ escaped_file_path.slice, info.path, format_args },
-            );
-        } else {
-            shell_cmd = try std.fmt.allocPrint(
-                allocator,
-                "cat '{s}' | {s} --mode=read{s}",
-                .{ escaped_file_path.slice, info.path, format_args },
-            );
-        }
-    } else if (std.mem.eql(u8, effective_format, "jsonl") or std.mem.eql(u8, effective_format, "ndjson")) {
         // JSONL without metadata injection (inject_meta case handled earlier)
-        shell_cmd = try std.fmt.allocPrint(allocator, "cat '{s}'", .{escaped_file_path.slice});
-    } else {
-        jn_core.exitWithError("jn-cat: format plugin '{s}' not found", .{effective_format});
+        if (explain) |report| {
+            const note = try std.fmt.allocPrint(allocator, "{s} is copied to stdout", .{file_path});
+            defer allocator.free(note);
+            report.addNote(note);
+            return;
+        }
+        copyToStdout(file_path);
+        return;
     }
-    defer allocator.free(shell_cmd);
 
-    // Run the pipeline
-    const shell_argv: [3][]const u8 = .{ "/bin/sh", "-c", shell_cmd };
-    var child = std.process.Child.init(&shell_argv, allocator);
-    child.stdin_behavior = .Close;
-    child.stdout_behavior = .Inherit;
-    child.stderr_behavior = .Inherit;
+    if (explain) |report| {
+        report.addPipeline(&pipeline);
+        return;
+    }
+
+    // A file that fails to read doesn't stop the others; its plugins have
+    // already said why on stderr
+    if (pipeline.run()) |_| {} else |err| {
+        std.debug.print("jn-cat: failed to process file '{s}': {s}\n", .{ file_path, @errorName(err) });
+    }
+}
 
-    try child.spawn();
-    const result = child.wait() catch |err| {
-        jn_core.exitWithError("jn-cat: failed to process file '{s}': {s}", .{ file_path, @errorName(err) });
+/// Copy a file to stdout unchanged
+fn copyToStdout(file_path: []const u8) void {
+    const file = std.fs.cwd().openFile(file_path, .{}) catch |err| {
+        std.debug.print("jn-cat: cannot open '{s}': {s}\n", .{ file_path, @errorName(err) });
+        return;
     };
+    defer file.close();
 
-    switch (result) {
-        .Exited => |code| {
-            if (code != 0) {
-                // Continue with other files, don't fail
-            }
-        },
-        else => {},
+    var reader_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
+    var reader_wrapper = file.reader(&reader_buf);
+    const reader = &reader_wrapper.interface;
+
+    var stdout_buf: [jn_core.STDOUT_BUFFER_SIZE]u8 = undefined;
+    var stdout_wrapper = std.fs.File.stdout().writerStreaming(&stdout_buf);
+    const writer = &stdout_wrapper.interface;
+
+    while (jn_core.readLine(reader)) |line| {
+        writer.writeAll(line) catch |err| jn_core.handleWriteError(err);
+        writer.writeByte('\n') catch |err| jn_core.handleWriteError(err);
     }
+    jn_core.flushWriter(writer);
 }
 
 /// Output JSONL file with path metadata injected
@@ -1474,241 +1520,58 @@ fn handleOpenDalUrl(allocator: std.mem.Allocator, address: jn_address.Address, a
     // Find format plugin if needed
     const format_path = findPlugin(allocator, format);
 
-    // Build format args (dynamically allocated, properly escaped)
-    const format_args = try buildFormatArgs(allocator, args);
-    defer allocator.free(format_args);
-
-    // Build the shell command
-    // Set LD_LIBRARY_PATH for OpenDAL shared library
-    var shell_cmd: []const u8 = undefined;
-
     // Get JN_HOME for library path
     const jn_home = std.posix.getenv("JN_HOME") orelse ".";
     const lib_path = try std.fmt.allocPrint(allocator, "{s}/vendor/opendal/bindings/c/target/release", .{jn_home});
     defer allocator.free(lib_path);
 
-    // SECURITY: Escape address.raw to prevent shell injection via malicious URLs
-    const escaped_address = try escapeShellPath(allocator, address.raw);
-    defer escaped_address.deinit(allocator);
+    // LD_LIBRARY_PATH=<lib> opendal <address> [--timeout=SECS] [--retries=N] [| gz|bz2|xz|zst --mode=raw] [| format --mode=read]
+    var pipeline = jn_core.Pipeline.init(allocator);
+    defer pipeline.deinit();
+    pipeline.stdin = .null_device;
+    const opendal = try pipeline.add(&.{ opendal_path, address.raw });
+    // Set LD_LIBRARY_PATH for OpenDAL shared library
+    try opendal.setEnv("LD_LIBRARY_PATH", lib_path);
+    const controls = HttpControls.init(args, null);
+    if (controls.timeout) |seconds| try opendal.argPrint("--timeout={d}", .{seconds});
+    if (controls.retry.retries > 0) try opendal.argPrint("--retries={d}", .{controls.retry.retries});
 
     if (address.compression != .none) {
-        const gz_path = findPlugin(allocator, "gz") orelse {
-            jn_core.exitWithError("jn-cat: compression plugin 'gz' not found", .{});
-        };
+        try addDecompressStage(allocator, &pipeline, address.compression);
+    }
 
-        if (format_path) |fmt_path| {
-            shell_cmd = try std.fmt.allocPrint(
-                allocator,
-                "LD_LIBRARY_PATH='{s}' {s} '{s}' | {s} --mode=raw | {s} --mode=read{s}",
-                .{ lib_path, opendal_path, escaped_address.slice, gz_path, fmt_path, format_args },
-            );
-        } else {
-            shell_cmd = try std.fmt.allocPrint(
-                allocator,
-                "LD_LIBRARY_PATH='{s}' {s} '{s}' | {s} --mode=raw",
-                .{ lib_path, opendal_path, escaped_address.slice, gz_path },
-            );
-        }
-    } else if (format_path) |fmt_path| {
-        shell_cmd = try std.fmt.allocPrint(
-            allocator,
-            "LD_LIBRARY_PATH='{s}' {s} '{s}' | {s} --mode=read{s}",
-            .{ lib_path, opendal_path, escaped_address.slice, fmt_path, format_args },
-        );
-    } else if (std.mem.eql(u8, format, "jsonl") or std.mem.eql(u8, format, "ndjson")) {
-        shell_cmd = try std.fmt.allocPrint(
-            allocator,
-            "LD_LIBRARY_PATH='{s}' {s} '{s}'",
-            .{ lib_path, opendal_path, escaped_address.slice },
-        );
-    } else {
+    if (format_path) |fmt_path| {
+        const format_stage = try pipeline.add(&.{ fmt_path, "--mode=read" });
+        try appendFormatArgs(format_stage, args, address.raw);
+    } else if (address.compression == .none and !std.mem.eql(u8, format, "jsonl") and !std.mem.eql(u8, format, "ndjson")) {
         jn_core.exitWithError("jn-cat: format plugin '{s}' not found", .{format});
     }
-    defer allocator.free(shell_cmd);
-
-    // Run via shell
-    const shell_argv: [3][]const u8 = .{ "/bin/sh", "-c", shell_cmd };
-    var shell_child = std.process.Child.init(&shell_argv, allocator);
-    shell_child.stdin_behavior = .Close;
-    shell_child.stdout_behavior = .Inherit;
-    shell_child.stderr_behavior = .Inherit;
 
-    try shell_child.spawn();
-    const result = shell_child.wait() catch |err| {
-        jn_core.exitWithError("jn-cat: OpenDAL execution failed: {s}", .{@errorName(err)});
-    };
-
-    switch (result) {
-        .Exited => |code| {
-            if (code != 0) {
-                std.process.exit(code);
-            }
-        },
-        else => {
-            std.process.exit(1);
-        },
-    }
+    runPipelineOrExit(&pipeline);
 }
 
-/// Spawn a format plugin to read a file
+/// Spawn a format plugin to read a file, or stdin when `file_path` is null
 fn spawnFormatPlugin(allocator: std.mem.Allocator, format: []const u8, file_path: ?[]const u8, query_string: ?[]const u8, args: *const jn_cli.ArgParser) !void {
     const plugin_info = findPluginInfo(allocator, format) orelse {
         jn_core.exitWithError("jn-cat: format plugin '{s}' not found", .{format});
     };
 
-    // Build format args from CLI (dynamically allocated, properly escaped)
-    const format_args = try buildFormatArgs(allocator, args);
-    defer allocator.free(format_args);
-
-    // Build query args from query string
-    const query_args = try buildQueryArgs(allocator, query_string);
-    defer allocator.free(query_args);
-
-    // Combine format_args and query_args
-    const all_args = try std.fmt.allocPrint(allocator, "{s}{s}", .{ format_args, query_args });
-    defer allocator.free(all_args);
-
-    // Extract mode from query string (defaults to "read")
-    const mode = extractModeFromQuery(query_string);
-
-    // Handle Python plugins via uv run --script
-    if (plugin_info.plugin_type == .python) {
-        try spawnPythonPlugin(allocator, plugin_info.path, mode, file_path, all_args);
-        return;
-    }
-
-    // Zig plugin handling
-    const plugin_path = plugin_info.path;
-
-    // Build argument list (fixed array with max args)
-    var argv_buf: [5][]const u8 = undefined;
-    var argc: usize = 0;
-
-    // Build mode arg
-    var mode_arg_buf: [32]u8 = undefined;
-    const mode_arg = std.fmt.bufPrint(&mode_arg_buf, "--mode={s}", .{mode}) catch {
-        jn_core.exitWithError("jn-cat: mode name too long", .{});
-    };
-
-    argv_buf[argc] = plugin_path;
-    argc += 1;
-    argv_buf[argc] = mode_arg;
-    argc += 1;
-
-    // Allocate delimiter arg if needed
-    var delim_arg_buf: [32]u8 = undefined;
-    if (args.get("delimiter", null)) |delim| {
-        const written = std.fmt.bufPrint(&delim_arg_buf, "--delimiter={s}", .{delim}) catch {
-            jn_core.exitWithError("jn-cat: delimiter too long", .{});
-        };
-        argv_buf[argc] = written;
-        argc += 1;
-    }
-    if (args.has("no-header")) {
-        argv_buf[argc] = "--no-header";
-        argc += 1;
-    }
-
-    const argv = argv_buf[0..argc];
-
-    // Spawn the plugin
-    var child = std.process.Child.init(argv, allocator);
-
+    var pipeline = jn_core.Pipeline.init(allocator);
+    defer pipeline.deinit();
     if (file_path) |path| {
         // Verify file exists
         std.fs.cwd().access(path, .{}) catch |err| {
             jn_core.exitWithError("jn-cat: cannot open file '{s}': {s}", .{ path, @errorName(err) });
         };
-
-        // SECURITY: Escape the file path to prevent command injection
-        const escaped_path = try escapeShellPath(allocator, path);
-        defer escaped_path.deinit(allocator);
-
-        // Use shell to pipe file into plugin with all args
-        const shell_cmd = try std.fmt.allocPrint(allocator, "cat '{s}' | {s} --mode={s}{s}", .{ escaped_path.slice, plugin_path, mode, all_args });
-        defer allocator.free(shell_cmd);
-
-        const shell_argv: [3][]const u8 = .{ "/bin/sh", "-c", shell_cmd };
-        var shell_child = std.process.Child.init(&shell_argv, allocator);
-        shell_child.stdin_behavior = .Close;
-        shell_child.stdout_behavior = .Inherit;
-        shell_child.stderr_behavior = .Inherit;
-
-        try shell_child.spawn();
-        const result = shell_child.wait() catch |err| {
-            jn_core.exitWithError("jn-cat: plugin execution failed: {s}", .{@errorName(err)});
-        };
-
-        switch (result) {
-            .Exited => |code| if (code != 0) std.process.exit(code),
-            .Signal => |sig| std.process.exit(128 +| @as(u8, @intCast(@min(sig, 127)))),
-            .Stopped, .Unknown => std.process.exit(1),
-        }
-    } else {
-        // Use our stdin directly
-        child.stdin_behavior = .Inherit;
-        child.stdout_behavior = .Inherit;
-        child.stderr_behavior = .Inherit;
-
-        try child.spawn();
-        const result = child.wait() catch |err| {
-            jn_core.exitWithError("jn-cat: plugin execution failed: {s}", .{@errorName(err)});
-        };
-
-        switch (result) {
-            .Exited => |code| if (code != 0) std.process.exit(code),
-            .Signal => |sig| std.process.exit(128 +| @as(u8, @intCast(@min(sig, 127)))),
-            .Stopped, .Unknown => std.process.exit(1),
-        }
-    }
-}
-
-/// Spawn a Python plugin via uv run --script
-fn spawnPythonPlugin(allocator: std.mem.Allocator, plugin_path: []const u8, mode: []const u8, file_path: ?[]const u8, extra_args: []const u8) !void {
-    // SECURITY: Escape paths to prevent command injection
-    const escaped_plugin_path = try escapeShellPath(allocator, plugin_path);
-    defer escaped_plugin_path.deinit(allocator);
-
-    var shell_cmd: []u8 = undefined;
-
-    if (file_path) |path| {
-        const escaped_file_path = try escapeShellPath(allocator, path);
-        defer escaped_file_path.deinit(allocator);
-
-        // Pipe file into plugin: cat <file> | uv run --script <plugin> --mode=<mode> [args]
-        shell_cmd = std.fmt.allocPrint(allocator, "cat '{s}' | uv run --script '{s}' --mode={s}{s}", .{ escaped_file_path.slice, escaped_plugin_path.slice, mode, extra_args }) catch {
-            jn_core.exitWithError("jn-cat: out of memory", .{});
-        };
-    } else {
-        // Use stdin: uv run --script <plugin> --mode=<mode> [args]
-        shell_cmd = std.fmt.allocPrint(allocator, "uv run --script '{s}' --mode={s}{s}", .{ escaped_plugin_path.slice, mode, extra_args }) catch {
-            jn_core.exitWithError("jn-cat: out of memory", .{});
-        };
+        pipeline.stdin = .{ .file = path };
     }
-    defer allocator.free(shell_cmd);
-
-    // Run via shell
-    const shell_argv: [3][]const u8 = .{ "/bin/sh", "-c", shell_cmd };
-    var shell_child = std.process.Child.init(&shell_argv, allocator);
-    shell_child.stdin_behavior = if (file_path != null) .Close else .Inherit;
-    shell_child.stdout_behavior = .Inherit;
-    shell_child.stderr_behavior = .Inherit;
 
-    try shell_child.spawn();
-    const result = shell_child.wait() catch |err| {
-        jn_core.exitWithError("jn-cat: Python plugin execution failed: {s}", .{@errorName(err)});
-    };
+    // Mode from the query string (defaults to "read"), then the CLI and query args
+    const stage = try addPluginStage(&pipeline, plugin_info, extractModeFromQuery(query_string));
+    try appendFormatArgs(stage, args, file_path);
+    try appendQueryArgs(stage, query_string);
 
-    switch (result) {
-        .Exited => |code| {
-            if (code != 0) {
-                std.process.exit(code);
-            }
-        },
-        else => {
-            std.process.exit(1);
-        },
-    }
+    runPipelineOrExit(&pipeline);
 }
 
 /// Plugin type (Zig binary or Python script)
@@ -1732,6 +1595,15 @@ const FORMAT_TO_PYTHON = [_]struct { format: []const u8, plugin: []const u8 }{
     .{ .format = "table", .plugin = "table_.py" },
 };
 
+/// A plugin findPluginInfo settled on. `source` and `priority` name the
+/// discovery location that matched, for --explain.
+fn foundPlugin(name: []const u8, path: []const u8, plugin_type: PluginType, source: []const u8, priority: u32) PluginInfo {
+    if (explain) |report| {
+        report.addPlugin(.{ .name = name, .kind = @tagName(plugin_type), .path = path, .source = source, .priority = priority });
+    }
+    return .{ .path = path, .plugin_type = plugin_type };
+}
+
 /// Find a plugin by name (returns Zig plugin path or null)
 fn findPlugin(allocator: std.mem.Allocator, name: []const u8) ?[]const u8 {
     const info = findPluginInfo(allocator, name);
@@ -1752,7 +1624,7 @@ fn findPluginInfo(allocator: std.mem.Allocator, name: []const u8) ?PluginInfo {
         // Try installed layout: $JN_HOME/bin/{name}
         const installed_path = std.fmt.allocPrint(allocator, "{s}/bin/{s}", .{ jn_home, name }) catch return null;
         if (std.fs.cwd().access(installed_path, .{})) |_| {
-            return .{ .path = installed_path, .plugin_type = .zig };
+            return foundPlugin(name, installed_path, .zig, "$JN_HOME/bin", 1);
         } else |_| {
             allocator.free(installed_path);
         }
@@ -1760,7 +1632,7 @@ fn findPluginInfo(allocator: std.mem.Allocator, name: []const u8) ?PluginInfo {
         // Try development layout: $JN_HOME/plugins/zig/{name}/bin/{name}
         const path = std.fmt.allocPrint(allocator, "{s}/plugins/zig/{s}/bin/{s}", .{ jn_home, name, name }) catch return null;
         if (std.fs.cwd().access(path, .{})) |_| {
-            return .{ .path = path, .plugin_type = .zig };
+            return foundPlugin(name, path, .zig, "$JN_HOME/plugins/zig", 2);
         } else |_| {
             allocator.free(path);
         }
@@ -1772,7 +1644,7 @@ fn findPluginInfo(allocator: std.mem.Allocator, name: []const u8) ?PluginInfo {
         if (std.fs.path.dirname(exe_path)) |exe_dir| {
             const sibling_path = std.fmt.allocPrint(allocator, "{s}/{s}", .{ exe_dir, name }) catch return null;
             if (std.fs.cwd().access(sibling_path, .{})) |_| {
-                return .{ .path = sibling_path, .plugin_type = .zig };
+                return foundPlugin(name, sibling_path, .zig, "next to jn-cat", 3);
             } else |_| {
                 allocator.free(sibling_path);
             }
@@ -1782,7 +1654,7 @@ fn findPluginInfo(allocator: std.mem.Allocator, name: []const u8) ?PluginInfo {
     // Try relative to current directory (development mode)
     const dev_path = std.fmt.allocPrint(allocator, "plugins/zig/{s}/bin/{s}", .{ name, name }) catch return null;
     if (std.fs.cwd().access(dev_path, .{})) |_| {
-        return .{ .path = dev_path, .plugin_type = .zig };
+        return foundPlugin(name, dev_path, .zig, "./plugins/zig", 4);
     } else |_| {
         allocator.free(dev_path);
     }
@@ -1801,7 +1673,7 @@ fn findPluginInfo(allocator: std.mem.Allocator, name: []const u8) ?PluginInfo {
         if (dir) |root| {
             const exe_rel_path = std.fmt.allocPrint(allocator, "{s}/plugins/zig/{s}/bin/{s}", .{ root, name, name }) catch return null;
             if (std.fs.cwd().access(exe_rel_path, .{})) |_| {
-                return .{ .path = exe_rel_path, .plugin_type = .zig };
+                return foundPlugin(name, exe_rel_path, .zig, "plugins/zig of jn-cat's checkout", 5);
             } else |_| {
                 allocator.free(exe_rel_path);
             }
@@ -1812,7 +1684,7 @@ fn findPluginInfo(allocator: std.mem.Allocator, name: []const u8) ?PluginInfo {
     if (std.posix.getenv("HOME")) |home| {
         const user_path = std.fmt.allocPrint(allocator, "{s}/.local/jn/plugins/zig/{s}/bin/{s}", .{ home, name, name }) catch return null;
         if (std.fs.cwd().access(user_path, .{})) |_| {
-            return .{ .path = user_path, .plugin_type = .zig };
+            return foundPlugin(name, user_path, .zig, "~/.local/jn/plugins/zig", 6);
         } else |_| {
             allocator.free(user_path);
         }
@@ -1820,7 +1692,7 @@ fn findPluginInfo(allocator: std.mem.Allocator, name: []const u8) ?PluginInfo {
 
     // Try Python plugins (lower priority)
     if (findPythonPlugin(allocator, name)) |py_path| {
-        return .{ .path = py_path, .plugin_type = .python };
+        return foundPlugin(name, py_path, .python, "jn_home/plugins (Python)", 7);
     }
 
     return null;
@@ -1919,8 +1791,9 @@ fn findPythonPlugin(allocator: std.mem.Allocator, format: []const u8) ?[]const u
     return null;
 }
 
-/// Pass stdin through to stdout (for JSONL)
-fn passthroughStdin() !void {
+/// Pass stdin through to stdout (for JSONL). With an error sidecar, lines
+/// that aren't JSON are routed there instead.
+fn passthroughStdin(allocator: std.mem.Allocator, errors: *jn_core.ErrorSink) !void {
     var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
     var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
     const reader = &stdin_wrapper.interface;
@@ -1929,8 +1802,14 @@ fn passthroughStdin() !void {
     var stdout_wrapper = std.fs.File.stdout().writerStreaming(&stdout_buf);
     const writer = &stdout_wrapper.interface;
 
+    var line_no: u64 = 0;
     while (jn_core.readLine(reader)) |line| {
+        line_no += 1;
         if (line.len == 0) continue;
+        if (errors.enabled() and !jn_core.sidecar.isJson(allocator, line)) {
+            errors.report("invalid JSON", line_no, line);
+            continue;
+        }
         writer.writeAll(line) catch |err| jn_core.handleWriteError(err);
         writer.writeByte('\n') catch |err| jn_core.handleWriteError(err);
     }
@@ -1956,7 +1835,7 @@ fn printUsage() void {
         \\
         \\Address formats:
         \\  data.csv              Local file (format auto-detected)
-        \\  data.csv.gz           Compressed file (decompressed automatically)
+        \\  data.csv.gz           Compressed file (.gz, .bz2, .xz, .zst; decompressed automatically)
         \\  data.txt~csv          Format override
         \\  -                     Read from stdin
         \\
@@ -1965,6 +1844,21 @@ fn printUsage() void {
         \\  --version             Show version
         \\  --delimiter=CHAR      CSV delimiter (passed to plugin)
         \\  --no-header           CSV has no header row (passed to plugin)
+        \\  --errors=PATH         Append unreadable records to PATH as NDJSON
+        \\  --max-errors=N        Fail once more than N records are unreadable
+        \\  --timeout=SECS        Give up on an HTTP or cloud request that makes no
+        \\                        progress for SECS
+        \\  --retries=N           Retry failed requests N times with backoff
+        \\  --rate=N/UNIT         Send at most N requests per sec, min or hour
+        \\  --meta                Add path fields (globs) or an _http field with the
+        \\                        response status, URL and headers (HTTP)
+        \\  --each                Send an HTTP profile's request once per stdin
+        \\                        record, filling {field} placeholders, and merge
+        \\                        each response into its record
+        \\  --concurrency=N       --each requests in flight at once (default 4)
+        \\  --into=FIELD          Put each --each response under FIELD
+        \\  --explain[=json]      Show what would run instead of running it
+        \\                        (also JN_EXPLAIN=1 or JN_EXPLAIN=json)
         \\
         \\Examples:
         \\  jn-cat data.csv
@@ -1972,9 +1866,13 @@ fn printUsage() void {
         \\  jn-cat data.txt~csv
         \\  jn-cat - < data.csv
         \\  jn-cat --delimiter=';' data.csv
+        \\  jn-cat --errors=bad.jsonl data.csv
+        \\  jn-cat --explain @myapi/users
+        \\  jn-cat --retries=3 --rate=10/sec @myapi/users
+        \\  jn-cat --each --concurrency=8 @myapi/user < ids.jsonl
         \\
     ;
-    var buf: [2048]u8 = undefined;
+    var buf: [4096]u8 = undefined;
     var stdout_wrapper = std.fs.File.stdout().writerStreaming(&buf);
     const stdout = &stdout_wrapper.interface;
     stdout.writeAll(usage) catch {};
@@ -1982,6 +1880,11 @@ fn printUsage() void {
 }
 
 // Tests
+test {
+    _ = pagination;
+    _ = each;
+}
+
 test "parse simple file address" {
     const addr = jn_address.parse("data.csv");
     try std.testing.expectEqual(jn_address.AddressType.file, addr.address_type);
@@ -0,0 +1,466 @@
+//! Paginated HTTP profiles: request page after page and stream the records
+//! of every page as one NDJSON stream.
+//!
+//! Profile fields:
+//!
+//! ```json
+//! {
+//!   "path": "/users",
+//!   "items_path": "data",
+//!   "pagination": {
+//!     "type": "cursor",
+//!     "cursor_path": "meta.next_cursor",
+//!     "cursor_param": "cursor",
+//!     "max_pages": 50
+//!   }
+//! }
+//! ```
+//!
+//! `items_path` is a dotted path to the records in each response body
+//! (`data`, `response.results`, `0.items`); without it an array body yields
+//! its elements and any other body is one record.
+//!
+//! Pagination types:
+//!
+//! | type       | next request                                         |
+//! |------------|------------------------------------------------------|
+//! | `link`     | the `rel="next"` URL of the `Link` header (RFC 5988) |
+//! | `next_url` | the URL at `next_path` in the body                   |
+//! | `cursor`   | `cursor_param` set to the value at `cursor_path`     |
+//! | `offset`   | `offset_param` advanced by the records received      |
+//! | `page`     | `page_param` advanced by one                         |
+//!
+//! `offset` and `page` send `limit` as `limit_param` / `size_param` when it
+//! is set, and start at `start` (offset 0, page 1 by default).
+//!
+//! Paging stops at the first of: no next link, URL or cursor; a page with no
+//! records; a page shorter than `limit`; `has_more_path` false; `total_path`
+//! records received; `max_pages` pages; `max_records` records (the last page
+//! is cut short).
+//!
+//! Pages are requested one at a time, only after the previous page has been
+//! written, so a reader that stops early (`jn head`) stops the requests too.
+
+const std = @import("std");
+const jn_http = @import("jn-http");
+
+pub const Type = enum { link, next_url, cursor, offset, page };
+
+pub const Config = struct {
+    /// Null when the profile isn't paginated (one request)
+    strategy: ?Type = null,
+    /// Dotted path to the records in a response body; "" for the body itself
+    items_path: []const u8 = "",
+
+    next_path: []const u8 = "next",
+    cursor_path: []const u8 = "next_cursor",
+    cursor_param: []const u8 = "cursor",
+    offset_param: []const u8 = "offset",
+    limit_param: []const u8 = "limit",
+    page_param: []const u8 = "page",
+    size_param: ?[]const u8 = null,
+    limit: ?u64 = null,
+    start: ?u64 = null,
+
+    has_more_path: ?[]const u8 = null,
+    total_path: ?[]const u8 = null,
+    max_pages: ?u64 = null,
+    max_records: ?u64 = null,
+
+    /// Read `items_path` and the `pagination` block of an HTTP profile.
+    /// Strings borrow from `profile`.
+    pub fn fromProfile(profile: std.json.Value) !Config {
+        var config: Config = .{};
+        if (profile != .object) return config;
+        if (try getString(profile.object, "items_path")) |path| config.items_path = path;
+
+        const block = profile.object.get("pagination") orelse return config;
+        if (block != .object) return error.InvalidPagination;
+        const pagination = block.object;
+
+        const type_name = try getString(pagination, "type") orelse return error.InvalidPagination;
+        config.strategy = std.meta.stringToEnum(Type, type_name) orelse return error.UnknownPaginationType;
+
+        inline for (.{ "next_path", "cursor_path", "cursor_param", "offset_param", "limit_param", "page_param" }) |field| {
+            if (try getString(pagination, field)) |value| @field(config, field) = value;
+        }
+        inline for (.{ "size_param", "has_more_path", "total_path" }) |field| {
+            @field(config, field) = try getString(pagination, field);
+        }
+        inline for (.{ "limit", "start", "max_pages", "max_records" }) |field| {
+            @field(config, field) = try getCount(pagination, field);
+        }
+        return config;
+    }
+
+    /// Whether responses are parsed here rather than by a format plugin
+    pub fn enabled(self: Config) bool {
+        return self.strategy != null or self.items_path.len > 0;
+    }
+};
+
+fn getString(object: std.json.ObjectMap, key: []const u8) !?[]const u8 {
+    const value = object.get(key) orelse return null;
+    return switch (value) {
+        .string => |s| s,
+        .null => null,
+        else => error.InvalidPagination,
+    };
+}
+
+/// A non-negative count, as a number or (after `${VAR}` substitution) a string
+fn getCount(object: std.json.ObjectMap, key: []const u8) !?u64 {
+    const value = object.get(key) orelse return null;
+    return switch (value) {
+        .integer => |n| std.math.cast(u64, n) orelse error.InvalidPagination,
+        .string => |s| std.fmt.parseInt(u64, s, 10) catch error.InvalidPagination,
+        .null => null,
+        else => error.InvalidPagination,
+    };
+}
+
+/// One HTTP response: the headers of the final response (after any
+/// redirects) and the body.
+pub const Response = struct {
+    headers: []const jn_http.Header = &.{},
+    body: []const u8,
+    /// The `_http` envelope (JSON) to add to every record, when asked for
+    meta: ?[]const u8 = null,
+};
+
+/// Request every page starting at `first_url` and write the records to
+/// `writer` as NDJSON, flushing after each page.
+///
+/// `fetcher.fetch(allocator, url)` returns a `Response` allocated with
+/// `allocator` (freed after the page is written) or an error.
+pub fn run(allocator: std.mem.Allocator, config: Config, first_url: []const u8, fetcher: anytype, writer: *std.Io.Writer) !void {
+    var page_arena = std.heap.ArenaAllocator.init(allocator);
+    defer page_arena.deinit();
+
+    // offset/page position of the next request
+    var position = startPosition(config);
+    var url = try firstUrl(allocator, config, first_url);
+    defer allocator.free(url);
+
+    var pages: u64 = 0;
+    var records: u64 = 0;
+    while (true) {
+        _ = page_arena.reset(.retain_capacity);
+        const arena = page_arena.allocator();
+
+        const response = try fetcher.fetch(arena, url);
+        pages += 1;
+        const body = std.json.parseFromSliceLeaky(std.json.Value, arena, response.body, .{}) catch {
+            return error.InvalidJson;
+        };
+
+        const items = try pageItems(arena, body, config.items_path);
+        var received: u64 = 0;
+        for (items) |item| {
+            if (config.max_records) |max| if (records >= max) break;
+            if (response.meta) |meta| {
+                try jn_http.envelope.write(writer, meta, try std.json.Stringify.valueAlloc(arena, item, .{}));
+            } else {
+                try std.json.Stringify.value(item, .{}, writer);
+                try writer.writeByte('\n');
+            }
+            records += 1;
+            received += 1;
+        }
+        try writer.flush();
+
+        // Stop conditions
+        if (received == 0) return;
+        if (config.max_records) |max| if (records >= max) return;
+        if (config.max_pages) |max| if (pages >= max) return;
+        if (config.limit) |limit| if ((config.strategy == .offset or config.strategy == .page) and received < limit) return;
+        if (config.has_more_path) |path| {
+            if (lookup(body, path)) |more| if (more == .bool and !more.bool) return;
+        }
+        if (config.total_path) |path| {
+            if (lookup(body, path)) |total| {
+                if (total == .integer and records >= (std.math.cast(u64, total.integer) orelse 0)) return;
+            }
+        }
+
+        position += if (config.strategy == .page) 1 else received;
+        const next = try nextUrl(arena, config, url, response, body, position) orelse return;
+        // A next link back to the same page would never end
+        if (std.mem.eql(u8, next, url)) return;
+        allocator.free(url);
+        url = try allocator.dupe(u8, next);
+    }
+}
+
+/// The URL of the first page: `url` with the starting offset or page set
+pub fn firstUrl(allocator: std.mem.Allocator, config: Config, url: []const u8) ![]u8 {
+    const position = startPosition(config);
+    const strategy = config.strategy orelse return allocator.dupe(u8, url);
+    return switch (strategy) {
+        .offset, .page => positionUrl(allocator, config, url, position),
+        else => allocator.dupe(u8, url),
+    };
+}
+
+fn startPosition(config: Config) u64 {
+    return config.start orelse if (config.strategy == .page) 1 else 0;
+}
+
+/// `url` with the offset or page parameter (and page size) set
+fn positionUrl(allocator: std.mem.Allocator, config: Config, url: []const u8, position: u64) ![]u8 {
+    var buf: [20]u8 = undefined;
+    const param = if (config.strategy == .page) config.page_param else config.offset_param;
+    const with_position = try setQueryParam(allocator, url, param, std.fmt.bufPrint(&buf, "{d}", .{position}) catch unreachable);
+
+    const limit = config.limit orelse return with_position;
+    const size_param = if (config.strategy == .page) config.size_param orelse return with_position else config.limit_param;
+    defer allocator.free(with_position);
+    return setQueryParam(allocator, with_position, size_param, std.fmt.bufPrint(&buf, "{d}", .{limit}) catch unreachable);
+}
+
+/// The URL of the page after `url`, or null when there is none
+fn nextUrl(arena: std.mem.Allocator, config: Config, url: []const u8, response: Response, body: std.json.Value, position: u64) !?[]const u8 {
+    const strategy = config.strategy orelse return null;
+    switch (strategy) {
+        .link => {
+            const target = linkNext(response.headers) orelse return null;
+            return try jn_http.resolveUrl(arena, url, target);
+        },
+        .next_url => {
+            const value = lookup(body, config.next_path) orelse return null;
+            if (value != .string or value.string.len == 0) return null;
+            return try jn_http.resolveUrl(arena, url, value.string);
+        },
+        .cursor => {
+            const value = lookup(body, config.cursor_path) orelse return null;
+            const cursor = switch (value) {
+                .string => |s| s,
+                .integer => |n| try std.fmt.allocPrint(arena, "{d}", .{n}),
+                else => return null,
+            };
+            if (cursor.len == 0) return null;
+            return try setQueryParam(arena, url, config.cursor_param, cursor);
+        },
+        .offset, .page => return try positionUrl(arena, config, url, position),
+    }
+}
+
+/// The records in a response body
+fn pageItems(arena: std.mem.Allocator, body: std.json.Value, items_path: []const u8) ![]const std.json.Value {
+    const value = lookup(body, items_path) orelse return &.{};
+    return switch (value) {
+        .array => |array| array.items,
+        .null => &.{},
+        else => try arena.dupe(std.json.Value, &.{value}),
+    };
+}
+
+/// The value at a dotted `path` ("data.items", "results.0"); a leading `$`
+/// or `.` is ignored and "" is the value itself.
+pub fn lookup(value: std.json.Value, path: []const u8) ?std.json.Value {
+    var current = value;
+    var keys = std.mem.tokenizeScalar(u8, std.mem.trimLeft(u8, path, "$"), '.');
+    while (keys.next()) |key| {
+        current = switch (current) {
+            .object => |object| object.get(key) orelse return null,
+            .array => |array| blk: {
+                const index = std.fmt.parseInt(usize, key, 10) catch return null;
+                if (index >= array.items.len) return null;
+                break :blk array.items[index];
+            },
+            else => return null,
+        };
+    }
+    return current;
+}
+
+/// The `rel="next"` target of the `Link` headers in `headers`.
+pub fn linkNext(headers: []const jn_http.Header) ?[]const u8 {
+    for (headers) |header| {
+        if (!std.ascii.eqlIgnoreCase(header.name, "link")) continue;
+
+        // <url>; rel="next", <url>; rel="last"
+        var rest = header.value;
+        while (std.mem.indexOfScalar(u8, rest, '<')) |open| {
+            const close = std.mem.indexOfScalarPos(u8, rest, open, '>') orelse break;
+            const target = rest[open + 1 .. close];
+            rest = rest[close + 1 ..];
+            const params = rest[0 .. std.mem.indexOfScalar(u8, rest, '<') orelse rest.len];
+            if (relIsNext(params)) return target;
+        }
+    }
+    return null;
+}
+
+fn relIsNext(params: []const u8) bool {
+    var it = std.mem.splitScalar(u8, params, ';');
+    while (it.next()) |param| {
+        const eq = std.mem.indexOfScalar(u8, param, '=') orelse continue;
+        if (!std.ascii.eqlIgnoreCase(std.mem.trim(u8, param[0..eq], " \t"), "rel")) continue;
+        // rel may list several relations: rel="next last"
+        var rels = std.mem.tokenizeScalar(u8, std.mem.trim(u8, param[eq + 1 ..], " \t\","), ' ');
+        while (rels.next()) |rel| {
+            if (std.ascii.eqlIgnoreCase(rel, "next")) return true;
+        }
+    }
+    return false;
+}
+
+/// `url` with query parameter `name` set to `value` (percent-encoded),
+/// replacing any existing value.
+pub fn setQueryParam(allocator: std.mem.Allocator, url: []const u8, name: []const u8, value: []const u8) ![]u8 {
+    var out: std.Io.Writer.Allocating = .init(allocator);
+    errdefer out.deinit();
+    const w = &out.writer;
+
+    const base = url[0 .. std.mem.indexOfScalar(u8, url, '#') orelse url.len];
+    const query_start = std.mem.indexOfScalar(u8, base, '?');
+    try w.writeAll(base[0 .. query_start orelse base.len]);
+    try w.writeByte('?');
+
+    if (query_start) |start| {
+        var params = std.mem.splitScalar(u8, base[start + 1 ..], '&');
+        while (params.next()) |param| {
+            if (param.len == 0) continue;
+            const key = param[0 .. std.mem.indexOfScalar(u8, param, '=') orelse param.len];
+            if (std.mem.eql(u8, key, name)) continue;
+            try w.writeAll(param);
+            try w.writeByte('&');
+        }
+    }
+    try w.writeAll(name);
+    try w.writeByte('=');
+    for (value) |c| {
+        if (std.ascii.isAlphanumeric(c) or std.mem.indexOfScalar(u8, "-._~", c) != null) {
+            try w.writeByte(c);
+        } else {
+            try w.print("%{X:0>2}", .{c});
+        }
+    }
+    return out.toOwnedSlice();
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+/// Serves canned pages by URL, recording the requests
+const FakeServer = struct {
+    pages: []const struct { url: []const u8, headers: []const jn_http.Header = &.{}, body: []const u8 },
+    requests: usize = 0,
+
+    pub fn fetch(self: *FakeServer, allocator: std.mem.Allocator, url: []const u8) !Response {
+        _ = allocator;
+        self.requests += 1;
+        for (self.pages) |page| {
+            if (std.mem.eql(u8, page.url, url)) return .{ .headers = page.headers, .body = page.body };
+        }
+        std.debug.print("unexpected request: {s}\n", .{url});
+        return error.NotFound;
+    }
+};
+
+fn expectPages(profile_json: []const u8, server: *FakeServer, expected: []const u8) !void {
+    const allocator = std.testing.allocator;
+    const profile = try std.json.parseFromSlice(std.json.Value, allocator, profile_json, .{});
+    defer profile.deinit();
+    const config = try Config.fromProfile(profile.value);
+
+    var out: std.Io.Writer.Allocating = .init(allocator);
+    defer out.deinit();
+    try run(allocator, config, server.pages[0].url, server, &out.writer);
+    try std.testing.expectEqualStrings(expected, out.written());
+}
+
+test "link header pagination" {
+    var server: FakeServer = .{ .pages = &.{
+        .{ .url = "https://api.test/users", .headers = &.{.{ .name = "Link", .value = "<https://api.test/users?page=2>; rel=\"next\", <https://api.test/users?page=2>; rel=\"last\"" }}, .body = "[{\"id\":1},{\"id\":2}]" },
+        .{ .url = "https://api.test/users?page=2", .headers = &.{.{ .name = "link", .value = "<https://api.test/users>; rel=\"first\"" }}, .body = "[{\"id\":3}]" },
+    } };
+    try expectPages("{\"pagination\":{\"type\":\"link\"}}", &server, "{\"id\":1}\n{\"id\":2}\n{\"id\":3}\n");
+    try std.testing.expectEqual(@as(usize, 2), server.requests);
+}
+
+test "cursor pagination with items_path" {
+    var server: FakeServer = .{ .pages = &.{
+        .{ .url = "https://api.test/events?type=a", .body = "{\"data\":[{\"n\":1}],\"meta\":{\"next\":\"c/2\"}}" },
+        .{ .url = "https://api.test/events?type=a&cursor=c%2F2", .body = "{\"data\":[{\"n\":2}],\"meta\":{\"next\":null}}" },
+    } };
+    try expectPages(
+        \\{"items_path":"data","pagination":{"type":"cursor","cursor_path":"meta.next"}}
+    , &server, "{\"n\":1}\n{\"n\":2}\n");
+}
+
+test "offset pagination stops on a short page" {
+    var server: FakeServer = .{ .pages = &.{
+        .{ .url = "https://api.test/items?offset=0&limit=2", .body = "[1,2]" },
+        .{ .url = "https://api.test/items?offset=2&limit=2", .body = "[3]" },
+    } };
+    try expectPages("{\"pagination\":{\"type\":\"offset\",\"limit\":\"2\"}}", &server, "1\n2\n3\n");
+    try std.testing.expectEqual(@as(usize, 2), server.requests);
+}
+
+test "page pagination stops at max_records and has_more" {
+    var server: FakeServer = .{ .pages = &.{
+        .{ .url = "https://api.test/p?page=1", .body = "{\"results\":[1,2],\"has_more\":true}" },
+        .{ .url = "https://api.test/p?page=2", .body = "{\"results\":[3,4],\"has_more\":false}" },
+    } };
+    try expectPages(
+        \\{"items_path":"results","pagination":{"type":"page","has_more_path":"has_more"}}
+    , &server, "1\n2\n3\n4\n");
+
+    server.requests = 0;
+    try expectPages(
+        \\{"items_path":"results","pagination":{"type":"page","max_records":3}}
+    , &server, "1\n2\n3\n");
+    try std.testing.expectEqual(@as(usize, 2), server.requests);
+}
+
+test "next_url pagination resolves relative links" {
+    var server: FakeServer = .{ .pages = &.{
+        .{ .url = "https://api.test/v1/list", .body = "{\"items\":[\"a\"],\"next\":\"/v1/list?after=a\"}" },
+        .{ .url = "https://api.test/v1/list?after=a", .body = "{\"items\":[\"b\"],\"next\":\"\"}" },
+    } };
+    try expectPages(
+        \\{"items_path":"items","pagination":{"type":"next_url"}}
+    , &server, "\"a\"\n\"b\"\n");
+}
+
+test "unknown pagination type is rejected" {
+    const profile = try std.json.parseFromSlice(std.json.Value, std.testing.allocator, "{\"pagination\":{\"type\":\"scroll\"}}", .{});
+    defer profile.deinit();
+    try std.testing.expectError(error.UnknownPaginationType, Config.fromProfile(profile.value));
+}
+
+test "link header with several relations" {
+    const headers = [_]jn_http.Header{
+        .{ .name = "Content-Type", .value = "application/json" },
+        .{ .name = "Link", .value = "<https://api.test/p?page=1>; rel=\"prev first\", <https://api.test/p?page=3>; rel=\"next last\"" },
+    };
+    try std.testing.expectEqualStrings("https://api.test/p?page=3", linkNext(&headers).?);
+    try std.testing.expectEqual(@as(?[]const u8, null), linkNext(headers[0..1]));
+}
+
+test "envelope adds _http to every record" {
+    var server: FakeServer = .{ .pages = &.{
+        .{ .url = "https://api.test/u", .body = "{\"data\":[{\"id\":1},{\"id\":2}]}" },
+    } };
+    const Enveloped = struct {
+        server: *FakeServer,
+        pub fn fetch(self: *@This(), allocator: std.mem.Allocator, url: []const u8) !Response {
+            var response = try self.server.fetch(allocator, url);
+            response.meta = "{\"status\":200}";
+            return response;
+        }
+    };
+    var enveloped: Enveloped = .{ .server = &server };
+
+    var out: std.Io.Writer.Allocating = .init(std.testing.allocator);
+    defer out.deinit();
+    try run(std.testing.allocator, .{ .items_path = "data" }, "https://api.test/u", &enveloped, &out.writer);
+    try std.testing.expectEqualStrings(
+        \\{"_http":{"status":200},"id":1}
+        \\{"_http":{"status":200},"id":2}
+        \\
+    , out.written());
+}
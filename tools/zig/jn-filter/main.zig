@@ -8,9 +8,28 @@
 //! Options:
 //!   --help, -h              Show this help
 //!   --version               Show version
-//!   -c, --compact           Compact output (default)
+//!   -c, --compact           Compact output (default unless stdout is a terminal)
+//!   -C, --color-output      Color output even when not on a terminal
+//!   -M, --monochrome-output Never color output
 //!   -r, --raw-strings       Output raw strings (unquoted)
 //!   -s, --slurp             Read all input into array first
+//!   -n, --null-input        Run once with null input (use input/inputs)
+//!   -R, --raw-input         Each input line is a string
+//!   -S, --sort-keys         Sort object keys
+//!   -j, --join-output       Raw output without newlines
+//!   -e, --exit-status       Exit status from the last output
+//!   --tab, --indent=N       Pretty-print
+//!   --seq                   RFC 7464 record separators
+//!   --jobs=N                Evaluate on N threads, keeping record order (0: one per CPU)
+//!   --errors=PATH           Append malformed or failing records to PATH as NDJSON
+//!   --max-errors=N          Fail once more than N records have gone to --errors
+//!   --arg=NAME=VALUE        Bind $NAME (also --argjson, --slurpfile, --rawfile)
+//!   --args, --jsonargs      Extra positional arguments go to $ARGS.positional
+//!   -L=DIR, --library-path=DIR
+//!                           Directory for ZQ `include "name";` modules
+//!
+//! Output is compact NDJSON when stdout is a pipe or file. On a terminal, zq
+//! pretty-prints in color like jq; NO_COLOR turns the colors off.
 //!
 //! Examples:
 //!   jn-filter '.name'
@@ -43,10 +62,13 @@ pub fn main() !void {
         return;
     }
 
-    // Get expression from positional argument
-    const expression = getPositionalArg() orelse {
+    // Get expression (and --args values) from positional arguments
+    var positionals: std.ArrayListUnmanaged([]const u8) = .empty;
+    defer positionals.deinit(allocator);
+    try collectPositionalArgs(allocator, &positionals);
+    if (positionals.items.len == 0) {
         jn_core.exitWithError("jn-filter: missing expression argument\nUsage: jn-filter [OPTIONS] <EXPRESSION>", .{});
-    };
+    }
 
     // Find ZQ binary
     const zq_path = findZq(allocator) orelse {
@@ -54,34 +76,55 @@ pub fn main() !void {
     };
 
     // Build ZQ arguments
-    var argv_buf: [10][]const u8 = undefined;
-    var argc: usize = 0;
-
-    argv_buf[argc] = zq_path;
-    argc += 1;
+    var argv: std.ArrayListUnmanaged([]const u8) = .empty;
+    defer argv.deinit(allocator);
+    try argv.append(allocator, zq_path);
 
-    // Pass through ZQ options
-    if (args.has("c") or args.has("compact")) {
-        argv_buf[argc] = "-c";
-        argc += 1;
+    // Pass through ZQ flags, short or long
+    for (passthrough_flags) |flag| {
+        for (flag.names) |name| {
+            if (!args.has(name)) continue;
+            try argv.append(allocator, flag.zq);
+            break;
+        }
+    }
+    if (args.get("L", null) orelse args.get("library-path", null)) |lib_dir| {
+        try argv.appendSlice(allocator, &.{ "-L", lib_dir });
+    }
+    if (args.get("indent", null)) |width| {
+        try argv.appendSlice(allocator, &.{ "--indent", width });
     }
-    if (args.has("r") or args.has("raw-strings")) {
-        argv_buf[argc] = "-r";
-        argc += 1;
+    // zq itself falls back to one record at a time when the expression needs it
+    if (args.get("jobs", null)) |jobs| {
+        try argv.appendSlice(allocator, &.{ "--jobs", jobs });
     }
-    if (args.has("s") or args.has("slurp")) {
-        argv_buf[argc] = "-s";
-        argc += 1;
+    if (args.get("errors", null)) |path| {
+        try argv.appendSlice(allocator, &.{ "--errors", path });
+    }
+    if (args.get("max-errors", null)) |max| {
+        try argv.appendSlice(allocator, &.{ "--max-errors", max });
     }
 
-    // Add expression
-    argv_buf[argc] = expression;
-    argc += 1;
+    // --arg=NAME=VALUE and friends become zq's two-argument form; repeatable
+    for (args.keys[0..args.count], args.values[0..args.count]) |key, value| {
+        for (named_options) |option| {
+            if (!std.mem.eql(u8, key, option[2..])) continue;
+            const eq = std.mem.indexOfScalar(u8, value, '=') orelse {
+                jn_core.exitWithError("jn-filter: {s} expects NAME=VALUE, got '{s}'", .{ option, value });
+            };
+            try argv.appendSlice(allocator, &.{ option, value[0..eq], value[eq + 1 ..] });
+        }
+    }
 
-    const argv = argv_buf[0..argc];
+    // Add expression, then any --args/--jsonargs values
+    try argv.append(allocator, positionals.items[0]);
+    if (args.has("args") or args.has("jsonargs")) {
+        try argv.append(allocator, if (args.has("jsonargs")) "--jsonargs" else "--args");
+        try argv.appendSlice(allocator, positionals.items[1..]);
+    }
 
     // Execute ZQ
-    var child = std.process.Child.init(argv, allocator);
+    var child = std.process.Child.init(argv.items, allocator);
     child.stdin_behavior = .Inherit;
     child.stdout_behavior = .Inherit;
     child.stderr_behavior = .Inherit;
@@ -112,8 +155,27 @@ pub fn main() !void {
     }
 }
 
-/// Get the first positional argument (not starting with -)
-fn getPositionalArg() ?[]const u8 {
+/// Boolean zq options, by their jn-filter names, forwarded as the zq flag
+const passthrough_flags = [_]struct { names: []const []const u8, zq: []const u8 }{
+    .{ .names = &.{ "c", "compact" }, .zq = "-c" },
+    .{ .names = &.{ "C", "color-output" }, .zq = "-C" },
+    .{ .names = &.{ "M", "monochrome-output" }, .zq = "-M" },
+    .{ .names = &.{ "r", "raw-strings" }, .zq = "-r" },
+    .{ .names = &.{ "s", "slurp" }, .zq = "-s" },
+    .{ .names = &.{ "n", "null-input" }, .zq = "-n" },
+    .{ .names = &.{ "R", "raw-input" }, .zq = "-R" },
+    .{ .names = &.{ "S", "sort-keys" }, .zq = "-S" },
+    .{ .names = &.{ "j", "join-output" }, .zq = "-j" },
+    .{ .names = &.{ "e", "exit-status" }, .zq = "-e" },
+    .{ .names = &.{"tab"}, .zq = "--tab" },
+    .{ .names = &.{"seq"}, .zq = "--seq" },
+};
+
+/// Options that bind a $NAME in the expression
+const named_options = [_][]const u8{ "--arg", "--argjson", "--slurpfile", "--rawfile" };
+
+/// Collect the positional arguments (not starting with -): the expression first
+fn collectPositionalArgs(allocator: std.mem.Allocator, out: *std.ArrayListUnmanaged([]const u8)) !void {
     var args_iter = std.process.args();
     _ = args_iter.skip(); // Skip program name
 
@@ -122,9 +184,8 @@ fn getPositionalArg() ?[]const u8 {
         if (std.mem.startsWith(u8, arg, "-")) {
             continue;
         }
-        return arg;
+        try out.append(allocator, arg);
     }
-    return null;
 }
 
 /// Find ZQ binary
@@ -223,19 +284,45 @@ fn printUsage() void {
         \\Usage: jn-filter [OPTIONS] <EXPRESSION>
         \\
         \\Filters and transforms NDJSON using ZQ (jq-compatible) expressions.
+        \\Output is compact NDJSON on a pipe; on a terminal it is pretty-printed
+        \\in color (NO_COLOR turns the colors off).
         \\
         \\Options:
         \\  --help, -h            Show this help
         \\  --version             Show version
-        \\  -c, --compact         Compact output (default)
+        \\  -c, --compact         Compact output (default unless stdout is a terminal)
+        \\  -C, --color-output    Color output even when not on a terminal
+        \\  -M, --monochrome-output
+        \\                        Never color output
         \\  -r, --raw-strings     Output raw strings (unquoted)
         \\  -s, --slurp           Read all input into array first
+        \\  -n, --null-input      Run once with null input (use input/inputs)
+        \\  -R, --raw-input       Each input line is a string
+        \\  -S, --sort-keys       Sort object keys
+        \\  -j, --join-output     Raw output without newlines
+        \\  -e, --exit-status     Exit 1 if the last output is false/null, 4 if none
+        \\  --tab, --indent=N     Pretty-print with a tab or N spaces
+        \\  --seq                 RFC 7464 record separators
+        \\  --jobs=N              Evaluate on N threads, keeping record order
+        \\                        (0: one per CPU; sequential for seq, input, -s, -n)
+        \\  --errors=PATH         Append malformed or failing records to PATH
+        \\  --max-errors=N        Exit 1 once more than N records are routed
+        \\  --arg=NAME=VALUE      Bind $NAME to a string
+        \\  --argjson=NAME=JSON   Bind $NAME to a JSON value
+        \\  --slurpfile=NAME=FILE Bind $NAME to the JSON texts in FILE
+        \\  --rawfile=NAME=FILE   Bind $NAME to the contents of FILE
+        \\  --args, --jsonargs    Later arguments go to $ARGS.positional
+        \\  -L=DIR, --library-path=DIR
+        \\                        Directory for include "name"; modules
         \\
         \\Examples:
         \\  cat data.ndjson | jn-filter '.name'
         \\  cat data.ndjson | jn-filter 'select(.age > 21)'
         \\  cat data.ndjson | jn-filter '.x + .y'
         \\  cat data.ndjson | jn-filter -s 'map(.value)'
+        \\  cat data.ndjson | jn-filter -L=lib 'include "team"; clean_record'
+        \\  cat data.ndjson | jn-filter --arg=who=alice 'select(.name == $who)'
+        \\  cat big.ndjson | jn-filter --jobs=0 'select(.score > 0.5) | {id, score}'
         \\
         \\Expression syntax (jq-compatible subset):
         \\  .field              Access field
@@ -255,7 +342,7 @@ fn printUsage() void {
 }
 
 // Tests
-test "getPositionalArg skips flags" {
+test "collectPositionalArgs skips flags" {
     // This test would need mock args, so just verify it compiles
-    _ = getPositionalArg;
+    _ = collectPositionalArgs;
 }
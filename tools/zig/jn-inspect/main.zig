@@ -5,7 +5,7 @@
 //! Usage:
 //!   jn-inspect profiles [--type=TYPE]    List available profiles
 //!   jn-inspect schema [--sample=N]       Infer schema from stdin
-//!   jn-inspect profile @NAME             Show profile details
+//!   jn-inspect profile @NAME             Show a profile's config, secrets redacted
 //!
 //! Options:
 //!   --format={json,text}    Output format (default: text)
@@ -23,6 +23,7 @@
 const std = @import("std");
 const jn_core = @import("jn-core");
 const jn_cli = @import("jn-cli");
+const jn_profile = @import("jn-profile");
 
 const VERSION = "0.1.0";
 const DEFAULT_SAMPLE: usize = 100;
@@ -601,37 +602,82 @@ fn outputSchemaText(writer: anytype, state: *SchemaState) void {
     }
 }
 
-/// Show profile details
+/// Show a profile's merged config (its `_meta.json` defaults included),
+/// with credentials redacted
 fn showProfile(allocator: std.mem.Allocator, profile_ref: []const u8, json_format: bool) void {
-    _ = allocator;
-
-    var stdout_buf: [jn_core.STDOUT_BUFFER_SIZE]u8 = undefined;
-    var stdout_wrapper = std.fs.File.stdout().writerStreaming(&stdout_buf);
-    const writer = &stdout_wrapper.interface;
-
-    // Parse profile reference
     if (!std.mem.startsWith(u8, profile_ref, "@")) {
         jn_core.exitWithError("jn-inspect: profile reference must start with @", .{});
     }
-
     const ref = profile_ref[1..]; // Skip @
+    const slash = std.mem.indexOfScalar(u8, ref, '/') orelse {
+        jn_core.exitWithError("jn-inspect: profile reference requires namespace: @namespace/name", .{});
+    };
+    const namespace = ref[0..slash];
+    const name = ref[slash + 1 ..];
+
+    var arena_state = std.heap.ArenaAllocator.init(allocator);
+    defer arena_state.deinit();
+    const arena = arena_state.allocator();
+
+    const found = findProfile(arena, namespace, name) orelse {
+        jn_core.exitWithError("jn-inspect: profile not found: {s}\nSearched:\n  profiles/http/{s}/{s}.json\n  profiles/file/{s}/{s}.json", .{ profile_ref, namespace, name, namespace, name });
+    };
+    // ${VAR} references are left as they are: showing a profile never reads secrets
+    const config = jn_profile.loadProfile(arena, found.dir, found.file, false) catch |err| {
+        jn_core.exitWithError("jn-inspect: failed to load profile {s}: {s}", .{ profile_ref, @errorName(err) });
+    };
+    const redacted = jn_core.explain.redact(arena, config, false, null) catch {
+        jn_core.exitWithError("jn-inspect: out of memory", .{});
+    };
+    const path = std.fmt.allocPrint(arena, "{s}/{s}", .{ found.dir, found.file }) catch {
+        jn_core.exitWithError("jn-inspect: out of memory", .{});
+    };
+
+    var stdout_buf: [jn_core.STDOUT_BUFFER_SIZE]u8 = undefined;
+    var stdout_wrapper = std.fs.File.stdout().writerStreaming(&stdout_buf);
+    const writer = &stdout_wrapper.interface;
 
     if (json_format) {
         writer.writeAll("{\"ref\":") catch {};
         jn_core.writeJsonString(writer, profile_ref) catch {};
-        writer.writeAll(",\"status\":\"not_implemented\"}\n") catch {};
+        writer.writeAll(",\"type\":") catch {};
+        jn_core.writeJsonString(writer, found.profile_type) catch {};
+        writer.writeAll(",\"path\":") catch {};
+        jn_core.writeJsonString(writer, path) catch {};
+        writer.writeAll(",\"config\":") catch {};
+        std.json.Stringify.value(redacted, .{}, writer) catch {};
+        writer.writeAll("}\n") catch {};
     } else {
-        writer.writeAll("Profile: ") catch {};
-        writer.writeAll(ref) catch {};
-        writer.writeAll("\n\nNote: Profile loading not yet implemented.\n") catch {};
-        writer.writeAll("Use `jn cat @") catch {};
-        writer.writeAll(ref) catch {};
-        writer.writeAll("` to access the profile.\n") catch {};
+        writer.print("Profile: {s}\nType:    {s}\nPath:    {s}\n\n", .{ profile_ref, found.profile_type, path }) catch {};
+        std.json.Stringify.value(redacted, .{ .whitespace = .indent_2 }, writer) catch {};
+        writer.writeByte('\n') catch {};
     }
 
     jn_core.flushWriter(writer);
 }
 
+const FoundProfile = struct {
+    profile_type: []const u8,
+    /// The namespace directory, where `_meta.json` defaults are merged from
+    dir: []const u8,
+    file: []const u8,
+};
+
+/// Find @namespace/name among the JSON profiles (http, then file), in the
+/// same source order as `jn cat`
+fn findProfile(arena: std.mem.Allocator, namespace: []const u8, name: []const u8) ?FoundProfile {
+    const profile_dirs = jn_profile.getProfileDirs(arena, .{ .project_root = "." }) catch return null;
+    const file = std.fmt.allocPrint(arena, "{s}.json", .{name}) catch return null;
+    for ([_][]const u8{ "http", "file" }) |profile_type| {
+        for (profile_dirs) |base| {
+            const dir = std.fmt.allocPrint(arena, "{s}/{s}/{s}", .{ base, profile_type, namespace }) catch return null;
+            const path = std.fmt.allocPrint(arena, "{s}/{s}", .{ dir, file }) catch return null;
+            if (jn_profile.pathExists(path)) return .{ .profile_type = profile_type, .dir = dir, .file = file };
+        }
+    }
+    return null;
+}
+
 /// Print version
 fn printVersion() void {
     var buf: [256]u8 = undefined;
@@ -649,7 +695,7 @@ fn printUsage() void {
         \\Usage:
         \\  jn-inspect profiles [OPTIONS]    List available profiles
         \\  jn-inspect schema [OPTIONS]      Infer schema from stdin
-        \\  jn-inspect profile @NAME         Show profile details
+        \\  jn-inspect profile @NAME         Show a profile's config, secrets redacted
         \\
         \\Options:
         \\  --format={json,text}    Output format (default: text)
@@ -669,6 +715,7 @@ fn printUsage() void {
         \\  jn-inspect profiles --format=json
         \\  cat data.ndjson | jn-inspect schema
         \\  cat data.ndjson | jn-inspect schema --sample=1000
+        \\  jn-inspect profile @github/repos
         \\
     ;
     var buf: [2048]u8 = undefined;
@@ -10,6 +10,8 @@
 //!   --left-key=FIELD        Left side join key (if different from right)
 //!   --right-key=FIELD       Right side join key
 //!   --inner                 Inner join (exclude unmatched left records)
+//!   --errors=PATH           Route malformed records to PATH as NDJSON
+//!   --max-errors=N          Fail once more than N records are malformed
 //!   --help, -h              Show this help
 //!   --version               Show version
 //!
@@ -32,8 +34,9 @@
 //! 3. **Practical data quality**: Real-world data often has occasional corruption.
 //!    Pipelines should be robust to this.
 //!
-//! For strict validation, use a separate validation step before the join.
-//! Future enhancement: Add --strict flag or warning counter for visibility.
+//! To keep them, `--errors=PATH` appends each skipped record to an error
+//! sidecar (see jn-core's sidecar.zig), and `--max-errors=N` fails the join
+//! once too many are skipped. `--strict` fails on the first one.
 //!
 //! See also: spec/08-streaming-backpressure.md
 
@@ -73,6 +76,8 @@ const SkipCounters = struct {
     left_parse_errors: usize = 0,
     left_missing_keys: usize = 0,
     right_memory_used: usize = 0,
+    /// Where skipped records go (--errors, --max-errors)
+    errors: jn_core.ErrorSink,
 
     fn total(self: SkipCounters) usize {
         return self.right_parse_errors + self.right_missing_keys +
@@ -157,9 +162,10 @@ pub fn main() !void {
         right_map.deinit();
     }
 
-    var counters = SkipCounters{};
+    var counters = SkipCounters{ .errors = jn_core.ErrorSink.fromArgs(allocator, args, "jn-join") };
+    defer counters.errors.close();
 
-    try loadRightSource(allocator, right_source, config.getRightKey(), &right_map, &counters, config.strict);
+    try loadRightSource(allocator, right_source, config.getRightKey(), &right_map, &counters, config.strict, &args);
     try processLeftSource(allocator, &config, &right_map, &counters);
 
     // Report skipped records (or exit with error if --strict)
@@ -171,22 +177,22 @@ pub fn main() !void {
     }
 }
 
-fn loadRightSource(allocator: std.mem.Allocator, source: []const u8, key_field: []const u8, map: *RightRecords, counters: *SkipCounters, strict: bool) !void {
+fn loadRightSource(allocator: std.mem.Allocator, source: []const u8, key_field: []const u8, map: *RightRecords, counters: *SkipCounters, strict: bool, args: *const jn_cli.ArgParser) !void {
     const address = jn_address.parse(source);
 
     switch (address.address_type) {
-        .file => try loadFromFile(allocator, source, key_field, map, counters, strict),
+        .file => try loadFromFile(allocator, source, key_field, map, counters, strict, args),
         .stdin => jn_core.exitWithError("jn-join: right source cannot be stdin", .{}),
         else => jn_core.exitWithError("jn-join: right source must be a local file", .{}),
     }
 }
 
-fn loadFromFile(allocator: std.mem.Allocator, path: []const u8, key_field: []const u8, map: *RightRecords, counters: *SkipCounters, strict: bool) !void {
+fn loadFromFile(allocator: std.mem.Allocator, path: []const u8, key_field: []const u8, map: *RightRecords, counters: *SkipCounters, strict: bool, args: *const jn_cli.ArgParser) !void {
     const format = jn_address.parse(path).effectiveFormat() orelse "jsonl";
 
     // For non-JSONL files, use jn-cat
     if (!std.mem.eql(u8, format, "jsonl") and !std.mem.eql(u8, format, "ndjson") and !std.mem.eql(u8, format, "json")) {
-        try loadViaJnCat(allocator, path, key_field, map, counters, strict);
+        try loadViaJnCat(allocator, path, key_field, map, counters, strict, args);
         return;
     }
 
@@ -199,21 +205,45 @@ fn loadFromFile(allocator: std.mem.Allocator, path: []const u8, key_field: []con
     var file_wrapper = file.reader(&file_buf);
     const reader = &file_wrapper.interface;
 
+    var line_no: u64 = 0;
     while (jn_core.readLine(reader)) |line| {
+        line_no += 1;
         if (line.len == 0) continue;
-        try addToMap(allocator, line, key_field, map, counters, strict);
+        try addToMap(allocator, line, path, line_no, key_field, map, counters, strict);
     }
 }
 
-fn loadViaJnCat(allocator: std.mem.Allocator, path: []const u8, key_field: []const u8, map: *RightRecords, counters: *SkipCounters, strict: bool) !void {
+fn loadViaJnCat(allocator: std.mem.Allocator, path: []const u8, key_field: []const u8, map: *RightRecords, counters: *SkipCounters, strict: bool, args: *const jn_cli.ArgParser) !void {
     const jn_cat_path = findTool(allocator, "jn-cat") orelse {
         jn_core.exitWithError("jn-join: jn-cat not found", .{});
     };
 
+    // The format plugin routes the records it can't read to the same sidecar
+    var errors_arg: ?[]u8 = null;
+    defer if (errors_arg) |arg| allocator.free(arg);
+    var max_errors_arg: ?[]u8 = null;
+    defer if (max_errors_arg) |arg| allocator.free(arg);
+
     // Use direct exec instead of shell to avoid command injection vulnerabilities.
     // The path is passed as a direct argument, not through shell interpolation.
-    const argv: [2][]const u8 = .{ jn_cat_path, path };
-    var child = std.process.Child.init(&argv, allocator);
+    var argv_buf: [4][]const u8 = undefined;
+    var argc: usize = 0;
+    argv_buf[argc] = jn_cat_path;
+    argc += 1;
+    if (args.get("errors", null)) |errors_path| {
+        errors_arg = try std.fmt.allocPrint(allocator, "--errors={s}", .{errors_path});
+        argv_buf[argc] = errors_arg.?;
+        argc += 1;
+    }
+    if (args.get("max-errors", null)) |max| {
+        max_errors_arg = try std.fmt.allocPrint(allocator, "--max-errors={s}", .{max});
+        argv_buf[argc] = max_errors_arg.?;
+        argc += 1;
+    }
+    argv_buf[argc] = path;
+    argc += 1;
+
+    var child = std.process.Child.init(argv_buf[0..argc], allocator);
     child.stdin_behavior = .Close;
     child.stdout_behavior = .Pipe;
     child.stderr_behavior = .Inherit;
@@ -224,20 +254,26 @@ fn loadViaJnCat(allocator: std.mem.Allocator, path: []const u8, key_field: []con
     var pipe_wrapper = child.stdout.?.reader(&pipe_buf);
     const reader = &pipe_wrapper.interface;
 
+    // jn-cat output lines aren't file lines, so these records have no _line
     while (jn_core.readLine(reader)) |line| {
         if (line.len == 0) continue;
-        try addToMap(allocator, line, key_field, map, counters, strict);
+        try addToMap(allocator, line, path, null, key_field, map, counters, strict);
     }
 
-    _ = child.wait() catch {};
+    const term = child.wait() catch return;
+    switch (term) {
+        .Exited => |code| if (code != 0) std.process.exit(code),
+        else => {},
+    }
 }
 
-fn addToMap(allocator: std.mem.Allocator, line: []const u8, key_field: []const u8, map: *RightRecords, counters: *SkipCounters, strict: bool) !void {
+fn addToMap(allocator: std.mem.Allocator, line: []const u8, path: []const u8, line_no: ?u64, key_field: []const u8, map: *RightRecords, counters: *SkipCounters, strict: bool) !void {
     const parsed = std.json.parseFromSlice(std.json.Value, allocator, line, .{}) catch {
         counters.right_parse_errors += 1;
         if (strict) {
             jn_core.exitWithError("jn-join: --strict mode: malformed JSON in right source", .{});
         }
+        counters.errors.reportFrom(path, "invalid JSON", line_no, line);
         return;
     };
     defer parsed.deinit();
@@ -247,6 +283,7 @@ fn addToMap(allocator: std.mem.Allocator, line: []const u8, key_field: []const u
         if (strict) {
             jn_core.exitWithError("jn-join: --strict mode: non-object record in right source", .{});
         }
+        counters.errors.reportFrom(path, "record is not an object", line_no, line);
         return;
     }
 
@@ -255,6 +292,7 @@ fn addToMap(allocator: std.mem.Allocator, line: []const u8, key_field: []const u
         if (strict) {
             jn_core.exitWithError("jn-join: --strict mode: missing join key '{s}' in right source", .{key_field});
         }
+        counters.errors.reportFrom(path, "missing join key", line_no, line);
         return;
     };
 
@@ -296,7 +334,9 @@ fn processLeftSource(allocator: std.mem.Allocator, config: *const JoinConfig, ri
     const left_key = config.getLeftKey();
     const right_key = config.getRightKey();
 
+    var line_no: u64 = 0;
     while (jn_core.readLine(reader)) |line| {
+        line_no += 1;
         if (line.len == 0) continue;
 
         const left_parsed = std.json.parseFromSlice(std.json.Value, allocator, line, .{}) catch {
@@ -304,8 +344,10 @@ fn processLeftSource(allocator: std.mem.Allocator, config: *const JoinConfig, ri
             if (config.strict) {
                 jn_core.exitWithError("jn-join: --strict mode: malformed JSON in left source", .{});
             }
-            // In non-strict mode, pass through unparseable lines unchanged
-            if (!config.inner_join) {
+            // In non-strict mode, pass through unparseable lines unchanged,
+            // unless they have an error sidecar to go to
+            counters.errors.report("invalid JSON", line_no, line);
+            if (!config.inner_join and !counters.errors.enabled()) {
                 writer.writeAll(line) catch |err| jn_core.handleWriteError(err);
                 writer.writeByte('\n') catch |err| jn_core.handleWriteError(err);
             }
@@ -318,7 +360,8 @@ fn processLeftSource(allocator: std.mem.Allocator, config: *const JoinConfig, ri
             if (config.strict) {
                 jn_core.exitWithError("jn-join: --strict mode: non-object record in left source", .{});
             }
-            if (!config.inner_join) {
+            counters.errors.report("record is not an object", line_no, line);
+            if (!config.inner_join and !counters.errors.enabled()) {
                 writer.writeAll(line) catch |err| jn_core.handleWriteError(err);
                 writer.writeByte('\n') catch |err| jn_core.handleWriteError(err);
             }
@@ -480,11 +523,14 @@ fn printUsage() void {
         \\  --right-key=FIELD     Right side join key
         \\  --inner               Inner join (exclude unmatched left)
         \\  --strict              Exit with error on malformed records
+        \\  --errors=PATH         Append malformed records to PATH as NDJSON
+        \\  --max-errors=N        Exit with error after N malformed records
         \\  --help, -h            Show this help
         \\  --version             Show version
         \\
         \\By default, malformed records are skipped with a warning to stderr.
-        \\Use --strict to fail fast on any parse errors or missing keys.
+        \\Use --strict to fail fast on any parse errors or missing keys, or
+        \\--errors to keep them ({"_error", "_line", "_raw", "_source", "_stage"}).
         \\
         \\Examples:
         \\  cat orders.ndjson | jn-join customers.ndjson --on=customer_id
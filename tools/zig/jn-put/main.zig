@@ -9,6 +9,7 @@
 //!   - output.csv            Local file (format auto-detected)
 //!   - output.json           JSON array output
 //!   - output.txt~csv        Format override
+//!   - output.csv.gz         Compressed output (.gz, .bz2, .xz, .zst)
 //!   - -                     Write to stdout
 //!
 //! Options:
@@ -16,6 +17,10 @@
 //!   --version               Show version
 //!   --delimiter=CHAR        CSV delimiter (passed to plugin)
 //!   --indent=N              JSON indentation (passed to plugin)
+//!   --errors=PATH           Append records that can't be written to PATH
+//!   --max-errors=N          Fail once more than N records can't be written
+//!   --explain[=json]        Print the resolved address, plugin and pipeline
+//!                           instead of running (also JN_EXPLAIN=1|json)
 //!
 //! Examples:
 //!   jn-put output.csv
@@ -30,6 +35,9 @@ const jn_address = @import("jn-address");
 
 const VERSION = "0.1.0";
 
+/// The --explain report, set when jn-put only describes what it would run
+var explain: ?*jn_core.explain.Report = null;
+
 pub fn main() !void {
     var gpa = std.heap.GeneralPurposeAllocator(.{}){};
     defer _ = gpa.deinit();
@@ -57,6 +65,14 @@ pub fn main() !void {
     // Parse the address
     const address = jn_address.parse(address_str);
 
+    // --explain: record what gets resolved and print it instead of running
+    var report: jn_core.explain.Report = undefined;
+    if (jn_core.explain.requested(&args, "jn-put")) |format| {
+        report = .init(allocator, "jn-put", format);
+        report.setAddress(address);
+        explain = &report;
+    }
+
     // Route based on address type
     switch (address.address_type) {
         .stdin => {
@@ -105,12 +121,18 @@ fn handleStdout(allocator: std.mem.Allocator, address: jn_address.Address, args:
 
     // For JSONL, just pass through
     if (std.mem.eql(u8, format, "jsonl") or std.mem.eql(u8, format, "ndjson")) {
-        try passthroughStdin();
+        if (explain) |report| {
+            report.addNote("stdin is copied to stdout without a plugin");
+            report.finish();
+        }
+        var errors = jn_core.ErrorSink.fromArgs(allocator, args, "jn-put");
+        defer errors.close();
+        try passthroughStdin(allocator, &errors);
         return;
     }
 
     // For other formats, spawn the format plugin in write mode
-    try spawnFormatPlugin(allocator, format, null, args);
+    try spawnFormatPlugin(allocator, format, null, .none, args);
 }
 
 /// Handle local file output
@@ -120,208 +142,64 @@ fn handleFile(allocator: std.mem.Allocator, address: jn_address.Address, args: *
     };
 
     // Spawn format plugin with output redirected to file
-    try spawnFormatPluginToFile(allocator, format, address.path, args);
+    try spawnFormatPlugin(allocator, format, address.path, address.compression, args);
 }
 
-/// Build format args for shell command
-fn buildFormatArgs(args: *const jn_cli.ArgParser, buf: []u8) []const u8 {
-    var pos: usize = 0;
-
-    if (args.get("delimiter", null)) |delim| {
-        const written = std.fmt.bufPrint(buf[pos..], " --delimiter='{s}'", .{delim}) catch return buf[0..pos];
-        pos += written.len;
-    }
-    if (args.get("indent", null)) |indent| {
-        const written = std.fmt.bufPrint(buf[pos..], " --indent={s}", .{indent}) catch return buf[0..pos];
-        pos += written.len;
-    }
-
-    return buf[0..pos];
-}
-
-/// Spawn a format plugin in write mode, output to stdout
-fn spawnFormatPlugin(allocator: std.mem.Allocator, format: []const u8, file_path: ?[]const u8, args: *const jn_cli.ArgParser) !void {
-    _ = file_path; // Not used for stdout
-
-    const plugin_info = findPluginInfo(allocator, format) orelse {
-        jn_core.exitWithError("jn-put: format plugin '{s}' not found", .{format});
-    };
-
-    // Build format args
-    var format_args_buf: [64]u8 = undefined;
-    const format_args = buildFormatArgs(args, &format_args_buf);
-
-    // Handle Python plugins via uv run --script
-    if (plugin_info.plugin_type == .python) {
-        try spawnPythonPlugin(allocator, plugin_info.path, "write", null, format_args);
-        return;
-    }
-
-    // Zig plugin handling
-    const plugin_path = plugin_info.path;
-
-    // Build argument list
-    var argv_buf: [6][]const u8 = undefined;
-    var argc: usize = 0;
-
-    argv_buf[argc] = plugin_path;
-    argc += 1;
-    argv_buf[argc] = "--mode=write";
-    argc += 1;
-
-    // Pass through relevant arguments
-    var delim_arg_buf: [32]u8 = undefined;
-    if (args.get("delimiter", null)) |delim| {
-        const written = std.fmt.bufPrint(&delim_arg_buf, "--delimiter={s}", .{delim}) catch {
-            jn_core.exitWithError("jn-put: delimiter too long", .{});
+/// Spawn a format plugin in write mode, output to `output_file` or stdout.
+/// Compressed output goes through the compression plugin as well.
+fn spawnFormatPlugin(allocator: std.mem.Allocator, format: []const u8, output_file: ?[]const u8, compression: jn_address.Compression, args: *const jn_cli.ArgParser) !void {
+    // plugin --mode=write [args] [| gz|bz2|xz|zst --mode=write] [> file]
+    var pipeline = jn_core.Pipeline.init(allocator);
+    defer pipeline.deinit();
+    if (output_file) |path| pipeline.stdout = .{ .file = path };
+
+    // NDJSON is already what the compressor needs
+    const is_jsonl = std.mem.eql(u8, format, "jsonl") or std.mem.eql(u8, format, "ndjson");
+    if (!is_jsonl or compression == .none) try addFormatStage(allocator, &pipeline, format, args);
+
+    if (compression.plugin()) |name| {
+        const path = findPlugin(allocator, name) orelse {
+            jn_core.exitWithError("jn-put: compression plugin '{s}' not found", .{name});
         };
-        argv_buf[argc] = written;
-        argc += 1;
+        _ = try pipeline.add(&.{ path, "--mode=write" });
     }
 
-    var indent_arg_buf: [32]u8 = undefined;
-    if (args.get("indent", null)) |indent| {
-        const written = std.fmt.bufPrint(&indent_arg_buf, "--indent={s}", .{indent}) catch {
-            jn_core.exitWithError("jn-put: indent too long", .{});
-        };
-        argv_buf[argc] = written;
-        argc += 1;
+    if (explain) |report| {
+        report.addPipeline(&pipeline);
+        report.finish();
     }
 
-    const argv = argv_buf[0..argc];
-
-    // Spawn the plugin
-    var child = std.process.Child.init(argv, allocator);
-    child.stdin_behavior = .Inherit;
-    child.stdout_behavior = .Inherit;
-    child.stderr_behavior = .Inherit;
-
-    try child.spawn();
-    const result = child.wait() catch |err| {
-        jn_core.exitWithError("jn-put: plugin execution failed: {s}", .{@errorName(err)});
+    const result = pipeline.run() catch |err| {
+        jn_core.exitWithError("jn-put: cannot start pipeline: {s}", .{@errorName(err)});
     };
-
-    switch (result) {
-        .Exited => |code| if (code != 0) std.process.exit(code),
-        .Signal => |sig| std.process.exit(128 + @as(u8, @truncate(sig))),
-        .Stopped, .Unknown => std.process.exit(1),
-    }
+    pipeline.exitOnFailure(result, "jn-put");
 }
 
-/// Spawn a format plugin in write mode, output to file
-fn spawnFormatPluginToFile(allocator: std.mem.Allocator, format: []const u8, file_path: []const u8, args: *const jn_cli.ArgParser) !void {
+/// Append the stage running the `format` plugin in write mode
+fn addFormatStage(allocator: std.mem.Allocator, pipeline: *jn_core.Pipeline, format: []const u8, args: *const jn_cli.ArgParser) !void {
     const plugin_info = findPluginInfo(allocator, format) orelse {
         jn_core.exitWithError("jn-put: format plugin '{s}' not found", .{format});
     };
 
-    // Build format args
-    var format_args_buf: [64]u8 = undefined;
-    const format_args = buildFormatArgs(args, &format_args_buf);
-
-    // Handle Python plugins via uv run --script
-    if (plugin_info.plugin_type == .python) {
-        try spawnPythonPlugin(allocator, plugin_info.path, "write", file_path, format_args);
-        return;
-    }
-
-    // Zig plugin handling
-    const plugin_path = plugin_info.path;
-
-    // Build shell command to redirect output to file
-    // Format: plugin --mode=write [args] > file
-    var cmd_buf: [1024]u8 = undefined;
-    var cmd_len: usize = 0;
-
-    // Add plugin path
-    const plugin_slice = std.fmt.bufPrint(cmd_buf[cmd_len..], "{s} --mode=write", .{plugin_path}) catch {
-        jn_core.exitWithError("jn-put: command too long", .{});
-    };
-    cmd_len += plugin_slice.len;
-
-    // Add optional args
-    if (args.get("delimiter", null)) |delim| {
-        const arg_slice = std.fmt.bufPrint(cmd_buf[cmd_len..], " --delimiter={s}", .{delim}) catch {
-            jn_core.exitWithError("jn-put: command too long", .{});
-        };
-        cmd_len += arg_slice.len;
-    }
-
-    if (args.get("indent", null)) |indent| {
-        const arg_slice = std.fmt.bufPrint(cmd_buf[cmd_len..], " --indent={s}", .{indent}) catch {
-            jn_core.exitWithError("jn-put: command too long", .{});
-        };
-        cmd_len += arg_slice.len;
-    }
-
-    // Add output redirection
-    const redir_slice = std.fmt.bufPrint(cmd_buf[cmd_len..], " > '{s}'", .{file_path}) catch {
-        jn_core.exitWithError("jn-put: command too long", .{});
-    };
-    cmd_len += redir_slice.len;
-
-    const shell_cmd = cmd_buf[0..cmd_len];
-
-    // Run via shell
-    const shell_argv: [3][]const u8 = .{ "/bin/sh", "-c", shell_cmd };
-    var shell_child = std.process.Child.init(&shell_argv, allocator);
-    shell_child.stdin_behavior = .Inherit;
-    shell_child.stdout_behavior = .Inherit;
-    shell_child.stderr_behavior = .Inherit;
-
-    try shell_child.spawn();
-    const result = shell_child.wait() catch |err| {
-        jn_core.exitWithError("jn-put: plugin execution failed: {s}", .{@errorName(err)});
-    };
-
-    switch (result) {
-        .Exited => |code| if (code != 0) std.process.exit(code),
-        .Signal => |sig| std.process.exit(128 + @as(u8, @truncate(sig))),
-        .Stopped, .Unknown => std.process.exit(1),
-    }
-}
-
-/// Spawn a Python plugin via uv run --script
-fn spawnPythonPlugin(allocator: std.mem.Allocator, plugin_path: []const u8, mode: []const u8, output_file: ?[]const u8, extra_args: []const u8) !void {
-    var cmd_buf: [2048]u8 = undefined;
-    var cmd_len: usize = 0;
-
-    // Build command: uv run --script <plugin> --mode=<mode> [args]
-    const base = std.fmt.bufPrint(cmd_buf[cmd_len..], "uv run --script '{s}' --mode={s}{s}", .{ plugin_path, mode, extra_args }) catch {
-        jn_core.exitWithError("jn-put: command too long", .{});
+    // Python plugins run via uv run --script
+    const stage = switch (plugin_info.plugin_type) {
+        .zig => try pipeline.add(&.{plugin_info.path}),
+        .python => blk: {
+            const uv = try pipeline.add(&.{ "uv", "run", "--script", plugin_info.path });
+            uv.label = std.fs.path.basename(plugin_info.path);
+            break :blk uv;
+        },
     };
-    cmd_len += base.len;
-
-    // Add output redirection if writing to file
-    if (output_file) |path| {
-        const redir = std.fmt.bufPrint(cmd_buf[cmd_len..], " > '{s}'", .{path}) catch {
-            jn_core.exitWithError("jn-put: command too long", .{});
-        };
-        cmd_len += redir.len;
-    }
+    try stage.arg("--mode=write");
 
-    const shell_cmd = cmd_buf[0..cmd_len];
-
-    // Run via shell
-    const shell_argv: [3][]const u8 = .{ "/bin/sh", "-c", shell_cmd };
-    var shell_child = std.process.Child.init(&shell_argv, allocator);
-    shell_child.stdin_behavior = .Inherit;
-    shell_child.stdout_behavior = .Inherit;
-    shell_child.stderr_behavior = .Inherit;
-
-    try shell_child.spawn();
-    const result = shell_child.wait() catch |err| {
-        jn_core.exitWithError("jn-put: Python plugin execution failed: {s}", .{@errorName(err)});
-    };
+    // Pass through relevant arguments
+    if (args.get("delimiter", null)) |delim| try stage.argPrint("--delimiter={s}", .{delim});
+    if (args.get("indent", null)) |indent| try stage.argPrint("--indent={s}", .{indent});
 
-    switch (result) {
-        .Exited => |code| {
-            if (code != 0) {
-                std.process.exit(code);
-            }
-        },
-        else => {
-            std.process.exit(1);
-        },
+    // Error sidecar: the plugin routes the records it can't write
+    if (plugin_info.plugin_type == .zig) {
+        if (args.get("errors", null)) |path| try stage.argPrint("--errors={s}", .{path});
+        if (args.get("max-errors", null)) |max| try stage.argPrint("--max-errors={s}", .{max});
     }
 }
 
@@ -346,6 +224,15 @@ const FORMAT_TO_PYTHON = [_]struct { format: []const u8, plugin: []const u8 }{
     .{ .format = "table", .plugin = "table_.py" }, // Table output format
 };
 
+/// A plugin findPluginInfo settled on. `source` and `priority` name the
+/// discovery location that matched, for --explain.
+fn foundPlugin(name: []const u8, path: []const u8, plugin_type: PluginType, source: []const u8, priority: u32) PluginInfo {
+    if (explain) |report| {
+        report.addPlugin(.{ .name = name, .kind = @tagName(plugin_type), .path = path, .source = source, .priority = priority });
+    }
+    return .{ .path = path, .plugin_type = plugin_type };
+}
+
 /// Find a plugin by name (returns Zig plugin path or null)
 fn findPlugin(allocator: std.mem.Allocator, name: []const u8) ?[]const u8 {
     const info = findPluginInfo(allocator, name);
@@ -366,7 +253,7 @@ fn findPluginInfo(allocator: std.mem.Allocator, name: []const u8) ?PluginInfo {
         // Try installed layout: $JN_HOME/bin/{name}
         const installed_path = std.fmt.allocPrint(allocator, "{s}/bin/{s}", .{ jn_home, name }) catch return null;
         if (std.fs.cwd().access(installed_path, .{})) |_| {
-            return .{ .path = installed_path, .plugin_type = .zig };
+            return foundPlugin(name, installed_path, .zig, "$JN_HOME/bin", 1);
         } else |_| {
             allocator.free(installed_path);
         }
@@ -374,7 +261,7 @@ fn findPluginInfo(allocator: std.mem.Allocator, name: []const u8) ?PluginInfo {
         // Try development layout: $JN_HOME/plugins/zig/{name}/bin/{name}
         const path = std.fmt.allocPrint(allocator, "{s}/plugins/zig/{s}/bin/{s}", .{ jn_home, name, name }) catch return null;
         if (std.fs.cwd().access(path, .{})) |_| {
-            return .{ .path = path, .plugin_type = .zig };
+            return foundPlugin(name, path, .zig, "$JN_HOME/plugins/zig", 2);
         } else |_| {
             allocator.free(path);
         }
@@ -386,7 +273,7 @@ fn findPluginInfo(allocator: std.mem.Allocator, name: []const u8) ?PluginInfo {
         if (std.fs.path.dirname(exe_path)) |exe_dir| {
             const sibling_path = std.fmt.allocPrint(allocator, "{s}/{s}", .{ exe_dir, name }) catch return null;
             if (std.fs.cwd().access(sibling_path, .{})) |_| {
-                return .{ .path = sibling_path, .plugin_type = .zig };
+                return foundPlugin(name, sibling_path, .zig, "next to jn-put", 3);
             } else |_| {
                 allocator.free(sibling_path);
             }
@@ -396,7 +283,7 @@ fn findPluginInfo(allocator: std.mem.Allocator, name: []const u8) ?PluginInfo {
     // Try relative to current directory (development mode)
     const dev_path = std.fmt.allocPrint(allocator, "plugins/zig/{s}/bin/{s}", .{ name, name }) catch return null;
     if (std.fs.cwd().access(dev_path, .{})) |_| {
-        return .{ .path = dev_path, .plugin_type = .zig };
+        return foundPlugin(name, dev_path, .zig, "./plugins/zig", 4);
     } else |_| {
         allocator.free(dev_path);
     }
@@ -415,7 +302,7 @@ fn findPluginInfo(allocator: std.mem.Allocator, name: []const u8) ?PluginInfo {
         if (dir) |root| {
             const exe_rel_path = std.fmt.allocPrint(allocator, "{s}/plugins/zig/{s}/bin/{s}", .{ root, name, name }) catch return null;
             if (std.fs.cwd().access(exe_rel_path, .{})) |_| {
-                return .{ .path = exe_rel_path, .plugin_type = .zig };
+                return foundPlugin(name, exe_rel_path, .zig, "plugins/zig of jn-put's checkout", 5);
             } else |_| {
                 allocator.free(exe_rel_path);
             }
@@ -426,7 +313,7 @@ fn findPluginInfo(allocator: std.mem.Allocator, name: []const u8) ?PluginInfo {
     if (std.posix.getenv("HOME")) |home| {
         const user_path = std.fmt.allocPrint(allocator, "{s}/.local/jn/plugins/zig/{s}/bin/{s}", .{ home, name, name }) catch return null;
         if (std.fs.cwd().access(user_path, .{})) |_| {
-            return .{ .path = user_path, .plugin_type = .zig };
+            return foundPlugin(name, user_path, .zig, "~/.local/jn/plugins/zig", 6);
         } else |_| {
             allocator.free(user_path);
         }
@@ -434,7 +321,7 @@ fn findPluginInfo(allocator: std.mem.Allocator, name: []const u8) ?PluginInfo {
 
     // Try Python plugins (lower priority)
     if (findPythonPlugin(allocator, name)) |py_path| {
-        return .{ .path = py_path, .plugin_type = .python };
+        return foundPlugin(name, py_path, .python, "$JN_HOME/jn_home/plugins (Python)", 7);
     }
 
     return null;
@@ -468,8 +355,9 @@ fn findPythonPlugin(allocator: std.mem.Allocator, format: []const u8) ?[]const u
     return null;
 }
 
-/// Pass stdin through to stdout (for JSONL)
-fn passthroughStdin() !void {
+/// Pass stdin through to stdout (for JSONL). With an error sidecar, lines
+/// that aren't JSON are routed there instead.
+fn passthroughStdin(allocator: std.mem.Allocator, errors: *jn_core.ErrorSink) !void {
     var stdin_buf: [jn_core.STDIN_BUFFER_SIZE]u8 = undefined;
     var stdin_wrapper = std.fs.File.stdin().reader(&stdin_buf);
     const reader = &stdin_wrapper.interface;
@@ -478,8 +366,14 @@ fn passthroughStdin() !void {
     var stdout_wrapper = std.fs.File.stdout().writerStreaming(&stdout_buf);
     const writer = &stdout_wrapper.interface;
 
+    var line_no: u64 = 0;
     while (jn_core.readLine(reader)) |line| {
+        line_no += 1;
         if (line.len == 0) continue;
+        if (errors.enabled() and !jn_core.sidecar.isJson(allocator, line)) {
+            errors.report("invalid JSON", line_no, line);
+            continue;
+        }
         writer.writeAll(line) catch |err| jn_core.handleWriteError(err);
         writer.writeByte('\n') catch |err| jn_core.handleWriteError(err);
     }
@@ -507,6 +401,7 @@ fn printUsage() void {
         \\  output.csv            Local file (format auto-detected)
         \\  output.json           JSON array output
         \\  output.txt~csv        Format override
+        \\  output.csv.gz         Compressed output (.gz, .bz2, .xz, .zst)
         \\  -                     Write to stdout
         \\
         \\Options:
@@ -514,11 +409,16 @@ fn printUsage() void {
         \\  --version             Show version
         \\  --delimiter=CHAR      CSV delimiter (passed to plugin)
         \\  --indent=N            JSON indentation (passed to plugin)
+        \\  --errors=PATH         Append records that can't be written to PATH
+        \\  --max-errors=N        Fail once more than N records can't be written
+        \\  --explain[=json]      Show what would run instead of running it
+        \\                        (also JN_EXPLAIN=1 or JN_EXPLAIN=json)
         \\
         \\Examples:
         \\  cat data.ndjson | jn-put output.csv
         \\  cat data.ndjson | jn-put output.json
         \\  cat data.ndjson | jn-put output.txt~csv
+        \\  cat data.ndjson | jn-put output.ndjson.xz
         \\  cat data.ndjson | jn-put -~json
         \\
     ;
@@ -4,6 +4,13 @@ pub fn build(b: *std.Build) void {
     const target = b.standardTargetOptions(.{});
     const optimize = b.standardOptimizeOption(.{});
 
+    // --errors records are written by jn-core's ErrorSink
+    const jn_core = b.createModule(.{
+        .root_source_file = b.path("../libs/zig/jn-core/src/root.zig"),
+        .target = target,
+        .optimize = optimize,
+    });
+
     // Main ZQ executable
     // Use LLVM backend for stable builds (x86 backend has some TODO panics in 0.15.x)
     const exe = b.addExecutable(.{
@@ -13,6 +20,7 @@ pub fn build(b: *std.Build) void {
         .optimize = optimize,
         .use_llvm = true,
     });
+    exe.root_module.addImport("jn-core", jn_core);
     b.installArtifact(exe);
 
     // Run command
@@ -31,6 +39,7 @@ pub fn build(b: *std.Build) void {
         .optimize = optimize,
         .use_llvm = true,
     });
+    unit_tests.root_module.addImport("jn-core", jn_core);
     const run_unit_tests = b.addRunArtifact(unit_tests);
     const test_step = b.step("test", "Run unit tests");
     test_step.dependOn(&run_unit_tests.step);
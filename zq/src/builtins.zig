@@ -0,0 +1,440 @@
+const std = @import("std");
+
+// ============================================================================
+// Builtin Registry
+// ============================================================================
+//
+// Every builtin zq knows by name and arity: jq 1.7's library, zq's own
+// additions, and the jq builtins it rejects. `builtins` lists the ones that
+// run, the parser explains the unsupported ones, and `zq --list-builtins`
+// prints the table as the compatibility matrix in
+// spec/zq-functions-inventory.txt.
+
+pub const Status = enum {
+    /// Behaves as in jq 1.7
+    jq,
+    /// jq has it, but zq's behaves differently (the summary says how)
+    differs,
+    /// zq only
+    zq,
+    /// jq has it, zq rejects it at parse time (the summary says why)
+    unsupported,
+};
+
+pub const Builtin = struct {
+    name: []const u8,
+    arity: u8,
+    status: Status,
+    summary: []const u8,
+};
+
+pub const Category = struct {
+    title: []const u8,
+    builtins: []const Builtin,
+};
+
+fn b(name: []const u8, arity: u8, status: Status, summary: []const u8) Builtin {
+    return .{ .name = name, .arity = arity, .status = status, .summary = summary };
+}
+
+pub const categories = [_]Category{
+    .{ .title = "CORE", .builtins = &.{
+        b("empty", 0, .jq, "No output"),
+        b("error", 0, .jq, "Raise the input as an error"),
+        b("error", 1, .jq, "Raise msg as an error"),
+        b("not", 0, .jq, "Negated truthiness"),
+        b("select", 1, .jq, "The input if cond holds"),
+        b("map", 1, .jq, "Apply f to each array element"),
+        b("map_values", 1, .jq, "Apply f to each value, keeping the first output"),
+        b("recurse", 0, .jq, "The input and everything below it"),
+        b("recurse", 1, .jq, "Apply f repeatedly, emitting every step"),
+        b("recurse", 2, .jq, "recurse(f) while cond holds"),
+        b("walk", 1, .jq, "Apply f bottom-up to every value"),
+        b("halt", 0, .jq, "Stop with exit status 0"),
+        b("halt_error", 0, .jq, "Print the input to stderr, stop with status 5"),
+        b("halt_error", 1, .jq, "Print the input to stderr, stop with status code"),
+        b("$__loc__", 0, .jq, "{file, line} of the expression"),
+    } },
+    .{ .title = "TYPES", .builtins = &.{
+        b("type", 0, .jq, "null, boolean, number, string, array or object"),
+        b("arrays", 0, .jq, "The input if it is an array"),
+        b("objects", 0, .jq, "The input if it is an object"),
+        b("iterables", 0, .jq, "The input if it is an array or object"),
+        b("booleans", 0, .jq, "The input if it is a boolean"),
+        b("numbers", 0, .jq, "The input if it is a number"),
+        b("strings", 0, .jq, "The input if it is a string"),
+        b("nulls", 0, .jq, "The input if it is null"),
+        b("values", 0, .differs, "Array of an object's values (jq: non-null input)"),
+        b("scalars", 0, .jq, "The input if it is not an array or object"),
+        b("tostring", 0, .jq, "Strings as-is, anything else as JSON text"),
+        b("tonumber", 0, .jq, "Parse a string as a number"),
+        b("tojson", 0, .jq, "Value as JSON text"),
+        b("fromjson", 0, .jq, "JSON text as a value"),
+        b("toarray", 0, .jq, "Arrays as-is, anything else wrapped in one"),
+        b("isarray", 0, .zq, "True if the input is an array"),
+        b("isboolean", 0, .zq, "True if the input is a boolean"),
+        b("isnull", 0, .zq, "True if the input is null"),
+        b("isnumber", 0, .zq, "True if the input is a number"),
+        b("isobject", 0, .zq, "True if the input is an object"),
+        b("isstring", 0, .zq, "True if the input is a string"),
+        b("bool", 0, .zq, "Coerce to boolean"),
+        b("float", 0, .zq, "Coerce to float"),
+        b("int", 0, .zq, "Coerce to integer"),
+        b("todecimal", 0, .zq, "Exact decimal for + - * without rounding"),
+    } },
+    .{ .title = "ARRAYS AND OBJECTS", .builtins = &.{
+        b("length", 0, .jq, "Codepoints, elements, keys, |number| or 0 for null"),
+        b("utf8bytelength", 0, .jq, "String length in bytes"),
+        b("keys", 0, .jq, "Sorted object keys, or array indexes"),
+        b("keys_unsorted", 0, .jq, "Object keys in input order"),
+        b("has", 1, .jq, "True if the object has the key or array the index"),
+        b("in", 1, .jq, "True if the input is a key of obj"),
+        b("contains", 1, .jq, "Recursive containment; substrings for strings"),
+        b("inside", 1, .jq, "True if b contains the input"),
+        b("add", 0, .jq, "Sum, concatenation or merge of the elements"),
+        b("any", 0, .jq, "True if some element is truthy"),
+        b("any", 1, .jq, "True if cond holds for some element"),
+        b("any", 2, .jq, "True if cond holds for some output of gen"),
+        b("all", 0, .jq, "True if every element is truthy"),
+        b("all", 1, .jq, "True if cond holds for every element"),
+        b("all", 2, .jq, "True if cond holds for every output of gen"),
+        b("flatten", 0, .jq, "Flatten nested arrays completely"),
+        b("flatten", 1, .jq, "Flatten nested arrays depth levels deep"),
+        b("first", 0, .jq, "First element"),
+        b("last", 0, .jq, "Last element"),
+        b("nth", 1, .jq, "Element n"),
+        b("reverse", 0, .jq, "Reverse an array or string"),
+        b("sort", 0, .jq, "Sort in jq order"),
+        b("sort_by", 1, .jq, "Sort by [f], stable"),
+        b("group_by", 1, .jq, "Arrays of elements with equal [f], sorted by it"),
+        b("unique", 0, .jq, "Sorted distinct elements"),
+        b("unique_by", 1, .jq, "First element for each distinct [f], sorted by it"),
+        b("min", 0, .jq, "Smallest element, or null"),
+        b("max", 0, .jq, "Largest element, or null"),
+        b("min_by", 1, .jq, "Element with the smallest [f]"),
+        b("max_by", 1, .jq, "Element with the largest [f]"),
+        b("indices", 1, .jq, "Offsets of s in a string, or of s in an array"),
+        b("index", 1, .jq, "First offset of s, or null"),
+        b("rindex", 1, .jq, "Last offset of s, or null"),
+        b("transpose", 0, .jq, "Rows to columns, padded with null"),
+        b("combinations", 0, .jq, "Every pick of one element from each array"),
+        b("combinations", 1, .jq, "combinations of n copies of the input"),
+        b("to_entries", 0, .jq, "Object to [{key, value}]"),
+        b("from_entries", 0, .jq, "[{key, value}] to object"),
+        b("with_entries", 1, .jq, "to_entries | map(f) | from_entries"),
+    } },
+    .{ .title = "SQL-STYLE", .builtins = &.{
+        b("IN", 1, .jq, "True if the input equals some output of s"),
+        b("IN", 2, .jq, "True if some output of source is an output of s"),
+        b("INDEX", 1, .jq, "Object of the input's elements keyed by f"),
+        b("INDEX", 2, .jq, "Object of stream's outputs keyed by f"),
+        b("JOIN", 2, .jq, "[row, $idx[key]] for each element"),
+        b("JOIN", 3, .jq, "[row, $idx[key]] for each output of stream"),
+        b("JOIN", 4, .jq, "JOIN/3 with each pair passed through join_expr"),
+    } },
+    .{ .title = "PATHS", .builtins = &.{
+        b("path", 1, .jq, "Paths f selects, as arrays"),
+        b("paths", 0, .jq, "Every path below the input"),
+        b("paths", 1, .jq, "Paths whose value passes cond"),
+        b("leaf_paths", 0, .jq, "Paths to scalars"),
+        b("getpath", 1, .jq, "Value at a path, or null"),
+        b("setpath", 2, .jq, "Set the value at a path"),
+        b("delpaths", 1, .jq, "Delete several paths"),
+        b("del", 1, .jq, "Delete the paths f selects"),
+        b("pick", 1, .jq, "Only the paths f selects, nested as in the input"),
+        b("tostream", 0, .jq, "[path, leaf] events for the input"),
+        b("fromstream", 1, .jq, "Values rebuilt from f's events"),
+        b("truncate_stream", 1, .jq, "Drop the first . path elements of f's events"),
+    } },
+    .{ .title = "GENERATORS", .builtins = &.{
+        b("range", 1, .jq, "0 up to n"),
+        b("range", 2, .jq, "from up to upto"),
+        b("range", 3, .jq, "from up to upto in steps of by"),
+        b("limit", 2, .jq, "First n outputs of f"),
+        b("first", 1, .jq, "First output of f"),
+        b("last", 1, .jq, "Last output of f"),
+        b("nth", 2, .jq, "Output n of f"),
+        b("until", 2, .jq, "Apply update until cond holds"),
+        b("while", 2, .jq, "Emit states while cond holds"),
+        b("repeat", 1, .jq, "The input, then f applied over and over"),
+        b("isempty", 1, .jq, "True if g has no outputs"),
+        b("isvalid", 1, .jq, "True if f runs without an error"),
+        b("input", 0, .jq, "The next input record"),
+        b("inputs", 0, .jq, "Every remaining input record"),
+    } },
+    .{ .title = "STRINGS", .builtins = &.{
+        b("startswith", 1, .jq, "True if the string starts with s"),
+        b("endswith", 1, .jq, "True if the string ends with s"),
+        b("ltrimstr", 1, .jq, "Remove a prefix if present"),
+        b("rtrimstr", 1, .jq, "Remove a suffix if present"),
+        b("trim", 0, .jq, "Remove leading and trailing whitespace"),
+        b("ltrim", 0, .jq, "Remove leading whitespace"),
+        b("rtrim", 0, .jq, "Remove trailing whitespace"),
+        b("split", 1, .jq, "Split on a separator string"),
+        b("join", 1, .jq, "Join elements with a separator; null is empty"),
+        b("ascii_downcase", 0, .jq, "Lowercase ASCII letters"),
+        b("ascii_upcase", 0, .jq, "Uppercase ASCII letters"),
+        b("ascii", 0, .jq, "Codepoint 0-127 as a one-character string"),
+        b("implode", 0, .jq, "Array of codepoints to string"),
+        b("explode", 0, .jq, "String to array of codepoints"),
+        b("chars", 0, .zq, "Split into characters"),
+        b("lines", 0, .zq, "Split into lines"),
+        b("words", 0, .zq, "Split on whitespace"),
+        b("slugify", 0, .zq, "URL-safe slug (lowercase, dashes)"),
+        b("capitalize", 0, .zq, "Uppercase the first letter"),
+        b("titlecase", 0, .zq, "Uppercase the first letter of each word"),
+        b("camelcase", 0, .zq, "Convert to camelCase"),
+        b("pascalcase", 0, .zq, "Convert to PascalCase"),
+        b("snakecase", 0, .zq, "Convert to snake_case"),
+        b("kebabcase", 0, .zq, "Convert to kebab-case"),
+        b("screamcase", 0, .zq, "Convert to SCREAMING_SNAKE_CASE"),
+    } },
+    .{ .title = "REGEX", .builtins = &.{
+        b("test", 1, .differs, "True if re matches (re must be a string literal)"),
+        b("test", 2, .differs, "test with flags (literal re and flags)"),
+        b("match", 1, .differs, "Match objects (re must be a string literal)"),
+        b("match", 2, .differs, "match with flags (literal re and flags)"),
+        b("capture", 1, .differs, "Object of named captures (literal re)"),
+        b("capture", 2, .differs, "capture with flags (literal re and flags)"),
+        b("scan", 1, .differs, "Every match (re must be a string literal)"),
+        b("scan", 2, .differs, "scan with flags (literal re and flags)"),
+        b("split", 2, .differs, "Split on a regex (literal re and flags)"),
+        b("splits", 1, .differs, "Stream of pieces between matches (literal re)"),
+        b("splits", 2, .differs, "splits with flags (literal re and flags)"),
+        b("sub", 2, .differs, "Replace the first match (re must be a literal)"),
+        b("sub", 3, .differs, "sub with flags (literal re and flags)"),
+        b("gsub", 2, .differs, "Replace every match (re must be a literal)"),
+        b("gsub", 3, .differs, "gsub with flags (literal re and flags)"),
+    } },
+    .{ .title = "FORMATS", .builtins = &.{
+        b("@text", 0, .jq, "tostring"),
+        b("@json", 0, .jq, "tojson"),
+        b("@csv", 0, .jq, "Array as a CSV row"),
+        b("@tsv", 0, .jq, "Array as a TSV row"),
+        b("@html", 0, .jq, "Escape < > & ' \" as entities"),
+        b("@uri", 0, .jq, "Percent-encode reserved characters"),
+        b("@sh", 0, .jq, "Quote for POSIX shells"),
+        b("@base64", 0, .jq, "Base64 encode"),
+        b("@base64d", 0, .jq, "Base64 decode"),
+        b("@base32", 0, .jq, "Base32 encode"),
+        b("@base32d", 0, .jq, "Base32 decode"),
+    } },
+    .{ .title = "MATH", .builtins = &.{
+        b("floor", 0, .jq, "Round down"),
+        b("ceil", 0, .jq, "Round up"),
+        b("round", 0, .jq, "Round half away from zero"),
+        b("rint", 0, .jq, "Round half to even"),
+        b("nearbyint", 0, .jq, "Round half to even"),
+        b("trunc", 0, .jq, "Round toward zero"),
+        b("fabs", 0, .jq, "Absolute value"),
+        b("abs", 0, .jq, "Absolute value, integers stay integers"),
+        b("sqrt", 0, .jq, "Square root"),
+        b("cbrt", 0, .jq, "Cube root"),
+        b("pow", 2, .jq, "a to the power b"),
+        b("exp", 0, .jq, "e^x"),
+        b("exp2", 0, .jq, "2^x"),
+        b("exp10", 0, .jq, "10^x"),
+        b("pow10", 0, .jq, "10^x"),
+        b("expm1", 0, .jq, "e^x - 1, precise near 0"),
+        b("log", 0, .jq, "Natural logarithm"),
+        b("log2", 0, .jq, "Base-2 logarithm"),
+        b("log10", 0, .jq, "Base-10 logarithm"),
+        b("log1p", 0, .jq, "log(1 + x), precise near 0"),
+        b("logb", 0, .jq, "Binary exponent"),
+        b("significand", 0, .jq, "Mantissa scaled to [1, 2)"),
+        b("frexp", 0, .jq, "[mantissa in [0.5, 1), exponent]"),
+        b("modf", 0, .jq, "[fractional part, integer part]"),
+        b("ldexp", 2, .jq, "x * 2^e"),
+        b("scalb", 2, .jq, "x * 2^e"),
+        b("scalbln", 2, .jq, "x * 2^e"),
+        b("gamma", 0, .jq, "log|Γ(x)|"),
+        b("lgamma", 0, .jq, "log|Γ(x)|"),
+        b("tgamma", 0, .jq, "Γ(x)"),
+        b("lgamma_r", 0, .jq, "[log|Γ(x)|, sign of Γ(x)]"),
+        b("sin", 0, .jq, "Sine (radians)"),
+        b("cos", 0, .jq, "Cosine (radians)"),
+        b("tan", 0, .jq, "Tangent (radians)"),
+        b("asin", 0, .jq, "Arc sine"),
+        b("acos", 0, .jq, "Arc cosine"),
+        b("atan", 0, .jq, "Arc tangent"),
+        b("atan2", 2, .jq, "Arc tangent of y/x in the right quadrant"),
+        b("sinh", 0, .jq, "Hyperbolic sine"),
+        b("cosh", 0, .jq, "Hyperbolic cosine"),
+        b("tanh", 0, .jq, "Hyperbolic tangent"),
+        b("asinh", 0, .jq, "Inverse hyperbolic sine"),
+        b("acosh", 0, .jq, "Inverse hyperbolic cosine"),
+        b("atanh", 0, .jq, "Inverse hyperbolic tangent"),
+        b("fmin", 2, .jq, "Smaller of two numbers"),
+        b("fmax", 2, .jq, "Larger of two numbers"),
+        b("fmod", 2, .jq, "Remainder with the sign of a"),
+        b("drem", 2, .jq, "IEEE remainder"),
+        b("fdim", 2, .jq, "a - b if positive, else 0"),
+        b("copysign", 2, .jq, "Magnitude of a with the sign of b"),
+        b("nextafter", 2, .jq, "Next float after a toward b"),
+        b("nexttoward", 2, .jq, "Next float after a toward b"),
+        b("fma", 3, .jq, "x * y + z with one rounding"),
+        b("infinite", 0, .jq, "Positive infinity"),
+        b("nan", 0, .jq, "Not a number"),
+        b("isinfinite", 0, .jq, "True for infinities"),
+        b("isnan", 0, .jq, "True for NaN"),
+        b("isnormal", 0, .jq, "True for finite non-zero, non-subnormal numbers"),
+        b("finites", 0, .jq, "The input if it is a finite number"),
+        b("normals", 0, .jq, "The input if it is a normal number"),
+        b("ln", 0, .zq, "Natural logarithm, null for x <= 0"),
+        b("incr", 0, .zq, "Add 1"),
+        b("decr", 0, .zq, "Subtract 1"),
+        b("negate", 0, .zq, "Flip the sign"),
+        b("toggle", 0, .zq, "Flip a boolean"),
+    } },
+    .{ .title = "DATES", .builtins = &.{
+        b("now", 0, .differs, "ISO 8601 timestamp (jq: epoch seconds)"),
+        b("mktime", 0, .jq, "Broken-down time to epoch seconds"),
+        b("gmtime", 0, .jq, "Epoch seconds to broken-down time"),
+        b("localtime", 0, .differs, "Same as gmtime: zq has no time zones"),
+        b("strptime", 1, .jq, "Parse a string to broken-down time"),
+        b("strftime", 1, .jq, "Format a time in UTC"),
+        b("strftime", 2, .zq, "Format a time at a UTC offset"),
+        b("strflocaltime", 1, .differs, "Same as strftime: zq has no time zones"),
+        b("todate", 0, .jq, "Epoch seconds to ISO 8601"),
+        b("todateiso8601", 0, .jq, "Epoch seconds to ISO 8601"),
+        b("date", 0, .jq, "Epoch seconds to ISO 8601"),
+        b("fromdate", 0, .jq, "ISO 8601 to epoch seconds"),
+        b("fromdateiso8601", 0, .jq, "ISO 8601 to epoch seconds"),
+        b("dateadd", 2, .differs, "Add n units, calendar-aware (jq: adds n)"),
+        b("datesub", 2, .differs, "Subtract n units, calendar-aware (jq: subtracts n)"),
+        b("date_trunc", 1, .zq, "Round down to the start of a unit"),
+        b("today", 0, .zq, "Current date (YYYY-MM-DD)"),
+        b("time", 0, .zq, "Current time (HH:MM:SS)"),
+        b("epoch", 0, .zq, "Current Unix time in seconds"),
+        b("epoch_ms", 0, .zq, "Current Unix time in milliseconds"),
+        b("year", 0, .zq, "Current year"),
+        b("month", 0, .zq, "Current month (1-12)"),
+        b("day", 0, .zq, "Current day of month (1-31)"),
+        b("hour", 0, .zq, "Current hour (0-23)"),
+        b("minute", 0, .zq, "Current minute (0-59)"),
+        b("second", 0, .zq, "Current second (0-59)"),
+        b("week", 0, .zq, "Current ISO week (1-53)"),
+        b("weekday", 0, .zq, "Current day name"),
+        b("weekday_num", 0, .zq, "Current day number (0=Sunday)"),
+        b("delta", 0, .zq, "Seconds since a timestamp"),
+        b("ago", 0, .zq, "Relative time, e.g. \"3 days ago\""),
+    } },
+    .{ .title = "IDS AND RANDOM", .builtins = &.{
+        b("uuid", 0, .zq, "UUID v4"),
+        b("uuid7", 0, .zq, "UUID v7, time-sortable"),
+        b("ulid", 0, .zq, "ULID, time-sortable"),
+        b("xid", 0, .zq, "XID, compact and sortable"),
+        b("xid_time", 0, .zq, "Epoch seconds of an XID"),
+        b("nanoid", 0, .zq, "NanoID, 21 URL-safe chars"),
+        b("shortid", 0, .zq, "Base62 ID, 8 chars"),
+        b("sid", 0, .zq, "Base62 ID, 6 chars"),
+        b("random", 0, .zq, "Random float in [0, 1)"),
+        b("seq", 0, .zq, "Incrementing counter"),
+    } },
+    .{ .title = "ENVIRONMENT AND I/O", .builtins = &.{
+        b("env", 0, .jq, "Environment variables as an object"),
+        b("$ENV", 0, .jq, "Environment variables as an object"),
+        b("builtins", 0, .jq, "\"name/arity\" for every builtin"),
+        b("input_filename", 0, .jq, "null: zq reads stdin"),
+        b("have_literal_numbers", 0, .jq, "true: number literals keep their text"),
+        b("have_decnum", 0, .jq, "false: exact numbers are text, not decNumber"),
+        b("debug", 0, .jq, "Print [\"DEBUG:\", .] to stderr, pass the input on"),
+        b("debug", 1, .jq, "Print [\"DEBUG:\", msg] to stderr, pass the input on"),
+        b("stderr", 0, .jq, "Print the input to stderr, pass it on"),
+        b("input_line_number", 0, .unsupported, "zq reads records, not a JSON text; use $__loc__ or jn's record numbers"),
+        b("get_search_list", 0, .unsupported, "Module search paths are set with -L"),
+        b("j0", 0, .unsupported, "Bessel functions aren't available"),
+        b("j1", 0, .unsupported, "Bessel functions aren't available"),
+        b("y0", 0, .unsupported, "Bessel functions aren't available"),
+        b("y1", 0, .unsupported, "Bessel functions aren't available"),
+    } },
+};
+
+/// The unsupported jq builtin called `name`, if there is one.
+pub fn unsupported(name: []const u8) ?Builtin {
+    for (categories) |category| {
+        for (category.builtins) |builtin| {
+            if (builtin.status == .unsupported and std.mem.eql(u8, builtin.name, name)) return builtin;
+        }
+    }
+    return null;
+}
+
+/// True for function builtins, as opposed to @formats and $variables.
+pub fn isFunction(builtin: Builtin) bool {
+    return std.ascii.isAlphabetic(builtin.name[0]);
+}
+
+/// "name/arity", or just the name for @formats and $variables.
+pub fn signature(buf: []u8, builtin: Builtin) []const u8 {
+    if (!isFunction(builtin)) return builtin.name;
+    return std.fmt.bufPrint(buf, "{s}/{d}", .{ builtin.name, builtin.arity }) catch builtin.name;
+}
+
+/// Write the compatibility matrix (spec/zq-functions-inventory.txt).
+pub fn writeMatrix(writer: *std.Io.Writer) std.Io.Writer.Error!void {
+    try writer.writeAll(
+        \\# ZQ Builtins: jq 1.7 Compatibility Matrix
+        \\# Generated by `zq --list-builtins`; edit src/builtins.zig, not this file.
+        \\#
+        \\# Status: jq          behaves as in jq 1.7
+        \\#         differs     jq has it, zq's behaves differently (see description)
+        \\#         zq          zq only
+        \\#         unsupported jq has it, zq rejects it (see description)
+        \\# Format: name/arity  status  description
+        \\
+    );
+    for (categories) |category| {
+        try writer.print("\n# === {s} ===\n", .{category.title});
+        for (category.builtins) |builtin| {
+            var buf: [64]u8 = undefined;
+            try writer.print("{s: <24}{s: <13}{s}\n", .{ signature(&buf, builtin), @tagName(builtin.status), builtin.summary });
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+const parser = @import("parser.zig");
+const types = @import("types.zig");
+
+test "every registered builtin parses" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const allocator = arena.allocator();
+
+    for (categories) |category| {
+        for (category.builtins) |builtin| {
+            // "g" works for every kind of argument: a filter, a condition, a regex or its flags
+            var text: std.ArrayListUnmanaged(u8) = .empty;
+            try text.appendSlice(allocator, builtin.name);
+            for (0..builtin.arity) |i| {
+                try text.appendSlice(allocator, if (i == 0) "(\"g\"" else "; \"g\"");
+            }
+            if (builtin.arity > 0) try text.append(allocator, ')');
+
+            var err_ctx: types.ErrorContext = .{};
+            const parsed = parser.parseExprWithContext(allocator, text.items, &err_ctx);
+            if (builtin.status == .unsupported) {
+                try std.testing.expectError(error.UnsupportedFeature, parsed);
+            } else {
+                _ = parsed catch |err| {
+                    std.debug.print("{s} failed to parse: {s}\n", .{ text.items, @errorName(err) });
+                    return err;
+                };
+            }
+        }
+    }
+}
+
+test "matrix lines are name, status and summary" {
+    var aw: std.Io.Writer.Allocating = .init(std.testing.allocator);
+    defer aw.deinit();
+    try writeMatrix(&aw.writer);
+    const matrix = aw.written();
+    try std.testing.expect(std.mem.indexOf(u8, matrix, "\n# === SQL-STYLE ===\n") != null);
+    try std.testing.expect(std.mem.indexOf(u8, matrix, "\nINDEX/2                 jq           Object of stream's outputs keyed by f\n") != null);
+    try std.testing.expect(std.mem.indexOf(u8, matrix, "\n@base32d                jq           Base32 decode\n") != null);
+}
@@ -1,5 +1,10 @@
 const std = @import("std");
 const types = @import("types.zig");
+const regex = @import("regex.zig");
+const output = @import("output.zig");
+const stream = @import("stream.zig");
+const number = @import("number.zig");
+const builtins = @import("builtins.zig");
 
 // Import types for internal use
 const CompareValue = types.CompareValue;
@@ -12,12 +17,24 @@ const SliceExpr = types.SliceExpr;
 const Expr = types.Expr;
 const ObjectExpr = types.ObjectExpr;
 const BuiltinKind = types.BuiltinKind;
+const ArithOp = types.ArithOp;
 const ArithmeticExpr = types.ArithmeticExpr;
 const StrFuncExpr = types.StrFuncExpr;
 const MapExpr = types.MapExpr;
 const ByFuncExpr = types.ByFuncExpr;
 const ArrayExpr = types.ArrayExpr;
 const DelExpr = types.DelExpr;
+const AssignExpr = types.AssignExpr;
+const PathFuncExpr = types.PathFuncExpr;
+const GeneratorExpr = types.GeneratorExpr;
+const DateFuncExpr = types.DateFuncExpr;
+const LibFuncExpr = types.LibFuncExpr;
+const RegexExpr = types.RegexExpr;
+const FormatKind = types.FormatKind;
+const InterpExpr = types.InterpExpr;
+const Pattern = types.Pattern;
+const FuncParam = types.FuncParam;
+const CallExpr = types.CallExpr;
 const EvalError = types.EvalError;
 const EvalResult = types.EvalResult;
 
@@ -25,6 +42,54 @@ const EvalResult = types.EvalResult;
 // Evaluator
 // ============================================================================
 
+/// Lexical scope for `as` bindings and `def`s: an immutable chain of frames
+/// allocated from the evaluation arena. Inner frames shadow outer ones.
+/// Variables and functions share the chain but not the namespace.
+pub const Env = struct {
+    parent: ?*const Env,
+    name: []const u8,
+    value: std.json.Value = .null,
+    /// Set for function frames
+    closure: ?Closure = null,
+
+    pub fn bind(allocator: std.mem.Allocator, parent: ?*const Env, name: []const u8, value: std.json.Value) EvalError!*const Env {
+        const frame = try allocator.create(Env);
+        frame.* = .{ .parent = parent, .name = name, .value = value };
+        return frame;
+    }
+
+    pub fn bindClosure(allocator: std.mem.Allocator, parent: ?*const Env, name: []const u8, closure: Closure) EvalError!*const Env {
+        const frame = try allocator.create(Env);
+        frame.* = .{ .parent = parent, .name = name, .closure = closure };
+        return frame;
+    }
+
+    pub fn lookup(env: ?*const Env, name: []const u8) ?std.json.Value {
+        var current = env;
+        while (current) |frame| : (current = frame.parent) {
+            if (frame.closure == null and std.mem.eql(u8, frame.name, name)) return frame.value;
+        }
+        return null;
+    }
+
+    pub fn lookupClosure(env: ?*const Env, name: []const u8, arity: usize) ?Closure {
+        var current = env;
+        while (current) |frame| : (current = frame.parent) {
+            const closure = frame.closure orelse continue;
+            if (closure.params.len == arity and std.mem.eql(u8, frame.name, name)) return closure;
+        }
+        return null;
+    }
+};
+
+/// A function body together with the scope it was defined in. Filter
+/// arguments are closures with no parameters over the caller's scope.
+pub const Closure = struct {
+    params: []const FuncParam,
+    body: *const Expr,
+    env: ?*const Env,
+};
+
 fn getPath(value: std.json.Value, path: [][]const u8) ?std.json.Value {
     var current = value;
     for (path) |key| {
@@ -123,31 +188,42 @@ fn getSlice(allocator: std.mem.Allocator, value: std.json.Value, slice: SliceExp
     }
 }
 
-pub fn evalCondition(allocator: std.mem.Allocator, cond: *const Condition, value: std.json.Value) bool {
+pub fn evalCondition(allocator: std.mem.Allocator, cond: *const Condition, value: std.json.Value) EvalError!bool {
+    return evalConditionWithEnv(allocator, cond, value, globals);
+}
+
+pub fn evalConditionWithEnv(allocator: std.mem.Allocator, cond: *const Condition, value: std.json.Value, env: ?*const Env) EvalError!bool {
     switch (cond.*) {
-        .simple => |simple| return evalSimpleCondition(allocator, &simple, value),
+        .simple => |simple| return evalSimpleCondition(allocator, &simple, value, env),
         .compound => |compound| {
-            const left_result = evalCondition(allocator, compound.left, value);
+            const left_result = try evalConditionWithEnv(allocator, compound.left, value, env);
             return switch (compound.op) {
-                .and_op => left_result and evalCondition(allocator, compound.right, value),
-                .or_op => left_result or evalCondition(allocator, compound.right, value),
+                .and_op => left_result and try evalConditionWithEnv(allocator, compound.right, value, env),
+                .or_op => left_result or try evalConditionWithEnv(allocator, compound.right, value, env),
             };
         },
-        .negated => |inner| return !evalCondition(allocator, inner, value),
+        .negated => |inner| return !try evalConditionWithEnv(allocator, inner, value, env),
     }
 }
 
-fn evalSimpleCondition(allocator: std.mem.Allocator, cond: *const SimpleCondition, value: std.json.Value) bool {
+fn evalSimpleCondition(allocator: std.mem.Allocator, cond: *const SimpleCondition, value: std.json.Value, env: ?*const Env) EvalError!bool {
+    const cmp_value = resolveCompareValue(cond.value, env);
+
     // Get the left side value(s) - either from expression or path
     if (cond.left_expr) |expr| {
-        // Evaluate the expression - may produce multiple values
-        const results = evalExpr(allocator, expr, value) catch return false;
+        // Evaluate the expression - may produce multiple values. Its outputs
+        // are operands, never outputs of the select, even when it fails
+        const mark = partial.items.len;
+        const results = evalExprWithEnv(allocator, expr, value, env) catch |err| {
+            discardPartial(mark);
+            return err;
+        };
         if (results.values.len == 0) return false;
 
         // For select conditions, return true if ANY result satisfies the condition
         // This matches jq semantics: select(.items[] > 5) is true if any item > 5
         for (results.values) |field_val| {
-            if (evalConditionForValue(field_val, cond.op, cond.value)) {
+            if (evalConditionForValue(field_val, cond.op, cmp_value)) {
                 return true;
             }
         }
@@ -167,12 +243,30 @@ fn evalSimpleCondition(allocator: std.mem.Allocator, cond: *const SimpleConditio
             }
         }
 
-        return evalConditionForValue(field_val, cond.op, cond.value);
+        return evalConditionForValue(field_val, cond.op, cmp_value);
     }
 }
 
+/// Replace a `$name` comparison operand with the value bound in `env`.
+fn resolveCompareValue(cmp_value: CompareValue, env: ?*const Env) CompareValue {
+    const name = switch (cmp_value) {
+        .variable => |n| n,
+        else => return cmp_value,
+    };
+    const bound = Env.lookup(env, name) orelse return .null_val;
+    return switch (bound) {
+        .integer => |i| .{ .int = i },
+        .float => |f| .{ .float = f },
+        .string => |s| .{ .string = s },
+        .bool => |b| .{ .boolean = b },
+        .null => .null_val,
+        .number_string => |s| .{ .number = s },
+        .array, .object => .none,
+    };
+}
+
 /// Evaluate a condition for a single value
-fn evalConditionForValue(field_val: std.json.Value, op: CompareOp, cmp_value: CompareValue) bool {
+pub fn evalConditionForValue(field_val: std.json.Value, op: CompareOp, cmp_value: CompareValue) bool {
     switch (op) {
         .exists => {
             return switch (field_val) {
@@ -192,20 +286,7 @@ fn evalConditionForValue(field_val: std.json.Value, op: CompareOp, cmp_value: Co
 
 fn compareGt(field_val: std.json.Value, cmp_val: CompareValue) bool {
     switch (field_val) {
-        .integer => |i| {
-            switch (cmp_val) {
-                .int => |ci| return i > ci,
-                .float => |cf| return @as(f64, @floatFromInt(i)) > cf,
-                else => return false,
-            }
-        },
-        .float => |f| {
-            switch (cmp_val) {
-                .int => |ci| return f > @as(f64, @floatFromInt(ci)),
-                .float => |cf| return f > cf,
-                else => return false,
-            }
-        },
+        .integer, .float, .number_string => return (numberOrder(field_val, cmp_val) orelse return false) == .gt,
         .string => |s| {
             switch (cmp_val) {
                 .string => |cs| return std.mem.order(u8, s, cs) == .gt,
@@ -218,20 +299,7 @@ fn compareGt(field_val: std.json.Value, cmp_val: CompareValue) bool {
 
 fn compareLt(field_val: std.json.Value, cmp_val: CompareValue) bool {
     switch (field_val) {
-        .integer => |i| {
-            switch (cmp_val) {
-                .int => |ci| return i < ci,
-                .float => |cf| return @as(f64, @floatFromInt(i)) < cf,
-                else => return false,
-            }
-        },
-        .float => |f| {
-            switch (cmp_val) {
-                .int => |ci| return f < @as(f64, @floatFromInt(ci)),
-                .float => |cf| return f < cf,
-                else => return false,
-            }
-        },
+        .integer, .float, .number_string => return (numberOrder(field_val, cmp_val) orelse return false) == .lt,
         .string => |s| {
             switch (cmp_val) {
                 .string => |cs| return std.mem.order(u8, s, cs) == .lt,
@@ -244,20 +312,7 @@ fn compareLt(field_val: std.json.Value, cmp_val: CompareValue) bool {
 
 fn compareEq(field_val: std.json.Value, cmp_val: CompareValue) bool {
     switch (field_val) {
-        .integer => |i| {
-            switch (cmp_val) {
-                .int => |ci| return i == ci,
-                .float => |cf| return @as(f64, @floatFromInt(i)) == cf,
-                else => return false,
-            }
-        },
-        .float => |f| {
-            switch (cmp_val) {
-                .int => |ci| return f == @as(f64, @floatFromInt(ci)),
-                .float => |cf| return f == cf,
-                else => return false,
-            }
-        },
+        .integer, .float, .number_string => return (numberOrder(field_val, cmp_val) orelse return false) == .eq,
         .bool => |b| {
             switch (cmp_val) {
                 .boolean => |cb| return b == cb,
@@ -277,11 +332,56 @@ fn compareEq(field_val: std.json.Value, cmp_val: CompareValue) bool {
     }
 }
 
+/// Order a number and a numeric constant; null if the constant isn't a number.
+fn numberOrder(field_val: std.json.Value, cmp_val: CompareValue) ?std.math.Order {
+    const constant: std.json.Value = switch (cmp_val) {
+        .int => |i| .{ .integer = i },
+        .float => |f| .{ .float = f },
+        .number => |s| .{ .number_string = s },
+        else => return null,
+    };
+    return number.order(field_val, constant);
+}
+
 // ============================================================================
 // Expression Evaluation
 // ============================================================================
 
+/// Evaluate `expr` against one input. On error.Raised, errorValue() and
+/// partialOutputs() describe what happened until the next call.
 pub fn evalExpr(allocator: std.mem.Allocator, expr: *const Expr, value: std.json.Value) EvalError!EvalResult {
+    partial = .empty;
+    return evalExprWithEnv(allocator, expr, value, globals);
+}
+
+/// Variables bound outside the expression (zq --arg, --argjson, $ARGS, ...).
+/// evalExpr starts every evaluation in this scope.
+threadlocal var globals: ?*const Env = null;
+
+pub fn setGlobals(env: ?*const Env) void {
+    globals = env;
+}
+
+pub fn evalExprWithEnv(allocator: std.mem.Allocator, expr: *const Expr, value: std.json.Value, env: ?*const Env) EvalError!EvalResult {
+    const mark = partial.items.len;
+    return evalNode(allocator, expr, value, env) catch |err| {
+        // Outputs stranded inside a value being built (an array, an object,
+        // an argument) are not outputs of this expression
+        if (!passesOutputs(expr)) discardPartial(mark);
+        return err;
+    };
+}
+
+/// Whether the outputs of `expr` are outputs of its sub-expressions, so that
+/// ones produced before an error or break still count.
+fn passesOutputs(expr: *const Expr) bool {
+    return switch (expr.*) {
+        .pipe, .bind, .call, .define, .foreach, .conditional, .alternative, .try_catch, .label => true,
+        else => false,
+    };
+}
+
+fn evalNode(allocator: std.mem.Allocator, expr: *const Expr, value: std.json.Value, env: ?*const Env) EvalError!EvalResult {
     switch (expr.*) {
         .identity => return try EvalResult.single(allocator, value),
 
@@ -338,7 +438,7 @@ pub fn evalExpr(allocator: std.mem.Allocator, expr: *const Expr, value: std.json
         },
 
         .select => |cond| {
-            if (evalCondition(allocator, cond, value)) {
+            if (try evalConditionWithEnv(allocator, cond, value, env)) {
                 return try EvalResult.single(allocator, value);
             }
             return EvalResult.empty(allocator);
@@ -363,11 +463,12 @@ pub fn evalExpr(allocator: std.mem.Allocator, expr: *const Expr, value: std.json
 
         .pipe => |pipe| {
             // Evaluate left side, then for each result evaluate right side
-            const left_results = try evalExpr(allocator, pipe.left, value);
+            const left_results = try evalExprWithEnv(allocator, pipe.left, value, env);
             var all_results: std.ArrayListUnmanaged(std.json.Value) = .empty;
 
+            const mark = partial.items.len;
             for (left_results.values) |left_val| {
-                const right_results = try evalExpr(allocator, pipe.right, left_val);
+                const right_results = evalExprWithEnv(allocator, pipe.right, left_val, env) catch |err| return unwind(allocator, mark, all_results.items, err);
                 try all_results.appendSlice(allocator, right_results.values);
             }
 
@@ -379,7 +480,15 @@ pub fn evalExpr(allocator: std.mem.Allocator, expr: *const Expr, value: std.json
         },
 
         .alternative => |alt| {
-            const primary_result = try evalExpr(allocator, alt.primary, value);
+            // Errors on the left count as no output, like null and false
+            const mark = partial.items.len;
+            const primary_result = evalExprWithEnv(allocator, alt.primary, value, env) catch |err| switch (err) {
+                error.Raised, error.InvalidPath => blk: {
+                    discardPartial(mark);
+                    break :blk EvalResult.empty(allocator);
+                },
+                else => return err,
+            };
             // If primary produces non-null, non-false results, use them
             for (primary_result.values) |v| {
                 switch (v) {
@@ -389,23 +498,23 @@ pub fn evalExpr(allocator: std.mem.Allocator, expr: *const Expr, value: std.json
                 }
             }
             // Fall back to secondary
-            return evalExpr(allocator, alt.fallback, value);
+            return evalExprWithEnv(allocator, alt.fallback, value, env);
         },
 
         .conditional => |cond| {
-            if (evalCondition(allocator, cond.condition, value)) {
-                return evalExpr(allocator, cond.then_branch, value);
+            if (try evalConditionWithEnv(allocator, cond.condition, value, env)) {
+                return evalExprWithEnv(allocator, cond.then_branch, value, env);
             } else {
-                return evalExpr(allocator, cond.else_branch, value);
+                return evalExprWithEnv(allocator, cond.else_branch, value, env);
             }
         },
 
         .object => |obj| {
-            return try evalObject(allocator, obj, value);
+            return try evalObject(allocator, obj, value, env);
         },
 
         .arithmetic => |arith| {
-            return try evalArithmetic(allocator, arith, value);
+            return try evalArithmetic(allocator, arith, value, env);
         },
 
         .literal => |lit| {
@@ -413,6 +522,7 @@ pub fn evalExpr(allocator: std.mem.Allocator, expr: *const Expr, value: std.json
                 .string => |s| .{ .string = s },
                 .integer => |i| .{ .integer = i },
                 .float => |f| .{ .float = f },
+                .number => |s| .{ .number_string = s },
                 .boolean => |b| .{ .bool = b },
                 .null_val => .null,
             };
@@ -426,2857 +536,6400 @@ pub fn evalExpr(allocator: std.mem.Allocator, expr: *const Expr, value: std.json
 
         // Sprint 03: map(expr)
         .map => |m| {
-            return evalMap(allocator, m, value);
+            return evalMap(allocator, m, value, env);
         },
 
         // Sprint 03: group_by, sort_by, etc.
         .by_func => |bf| {
-            return evalByFunc(allocator, bf, value);
+            return evalByFunc(allocator, bf, value, env);
         },
 
         // Sprint 03: Array literal [.x, .y]
         .array => |arr_expr| {
-            return evalArrayLiteral(allocator, arr_expr, value);
+            return evalArrayLiteral(allocator, arr_expr, value, env);
         },
 
         .del => |del_expr| {
             return evalDel(allocator, del_expr, value);
         },
-    }
-}
 
-fn evalDel(allocator: std.mem.Allocator, del_expr: DelExpr, value: std.json.Value) EvalError!EvalResult {
-    switch (value) {
-        .object => |obj| {
-            if (del_expr.paths.len == 1) {
-                const key = del_expr.paths[0];
-                // Check if we need to delete an array element: del(.arr[0])
-                if (del_expr.index) |idx| {
-                    var new_obj = std.json.ObjectMap.init(allocator);
-                    var it = obj.iterator();
-                    while (it.next()) |entry| {
-                        if (std.mem.eql(u8, entry.key_ptr.*, key)) {
-                            // Delete element from array
-                            switch (entry.value_ptr.*) {
-                                .array => |arr| {
-                                    var new_arr = std.json.Array.init(allocator);
-                                    const len = arr.items.len;
-                                    // Handle MIN_I64 specially to avoid overflow when negating
-                                    const actual_idx: ?usize = if (idx < 0) blk: {
-                                        const neg_idx = std.math.negate(idx) catch break :blk null;
-                                        if (@as(usize, @intCast(neg_idx)) <= len)
-                                            break :blk len - @as(usize, @intCast(neg_idx))
-                                        else
-                                            break :blk null;
-                                    } else if (@as(usize, @intCast(idx)) < len)
-                                        @as(usize, @intCast(idx))
-                                    else
-                                        null;
-                                    for (arr.items, 0..) |item, i| {
-                                        if (actual_idx == null or i != actual_idx.?) {
-                                            try new_arr.append(item);
-                                        }
-                                    }
-                                    try new_obj.put(entry.key_ptr.*, .{ .array = new_arr });
-                                },
-                                else => try new_obj.put(entry.key_ptr.*, entry.value_ptr.*),
-                            }
-                        } else {
-                            try new_obj.put(entry.key_ptr.*, entry.value_ptr.*);
-                        }
-                    }
-                    return try EvalResult.single(allocator, .{ .object = new_obj });
-                }
-                // Simple delete: del(.key)
-                var new_obj = std.json.ObjectMap.init(allocator);
-                var it = obj.iterator();
-                while (it.next()) |entry| {
-                    if (!std.mem.eql(u8, entry.key_ptr.*, key)) {
-                        try new_obj.put(entry.key_ptr.*, entry.value_ptr.*);
-                    }
-                }
-                return try EvalResult.single(allocator, .{ .object = new_obj });
-            } else if (del_expr.paths.len > 1) {
-                // Nested delete: del(.a.b) or del(.a.arr[0])
-                var new_obj = std.json.ObjectMap.init(allocator);
-                var it = obj.iterator();
-                while (it.next()) |entry| {
-                    if (std.mem.eql(u8, entry.key_ptr.*, del_expr.paths[0])) {
-                        // Recurse with remaining path
-                        const nested_del = DelExpr{ .paths = del_expr.paths[1..], .index = del_expr.index };
-                        const inner_result = try evalDel(allocator, nested_del, entry.value_ptr.*);
-                        if (inner_result.values.len > 0) {
-                            try new_obj.put(entry.key_ptr.*, inner_result.values[0]);
-                        }
-                    } else {
-                        try new_obj.put(entry.key_ptr.*, entry.value_ptr.*);
-                    }
-                }
-                return try EvalResult.single(allocator, .{ .object = new_obj });
+        .variable => |name| {
+            const bound = Env.lookup(env, name) orelse return EvalResult.empty(allocator);
+            return try EvalResult.single(allocator, bound);
+        },
+
+        .bind => |binding| {
+            // Each source output runs the body once against the original input
+            const sources = try evalExprWithEnv(allocator, binding.source, value, env);
+            var all_results: std.ArrayListUnmanaged(std.json.Value) = .empty;
+
+            const mark = partial.items.len;
+            for (sources.values) |source_val| {
+                const scope = (try destructure(allocator, binding.pattern, source_val, env)) orelse continue;
+                const body_results = evalExprWithEnv(allocator, binding.body, value, scope) catch |err| return unwind(allocator, mark, all_results.items, err);
+                try all_results.appendSlice(allocator, body_results.values);
             }
-            return try EvalResult.single(allocator, value);
+
+            return EvalResult.multi(allocator, try all_results.toOwnedSlice(allocator));
         },
-        .array => |arr| {
-            // Direct array deletion: del(.[0]) on an array value
-            if (del_expr.paths.len == 0 and del_expr.index != null) {
-                const idx = del_expr.index.?;
-                var new_arr = std.json.Array.init(allocator);
-                const len = arr.items.len;
-                const actual_idx: ?usize = if (idx < 0)
-                    if (@as(usize, @intCast(-idx)) <= len)
-                        len - @as(usize, @intCast(-idx))
-                    else
-                        null
-                else if (@as(usize, @intCast(idx)) < len)
-                    @as(usize, @intCast(idx))
-                else
-                    null;
-                for (arr.items, 0..) |item, i| {
-                    if (actual_idx == null or i != actual_idx.?) {
-                        try new_arr.append(item);
-                    }
+
+        .loc => |loc| {
+            var obj = std.json.ObjectMap.init(allocator);
+            try obj.put("file", .{ .string = "<stdin>" });
+            try obj.put("line", .{ .integer = loc.line });
+            return try EvalResult.single(allocator, .{ .object = obj });
+        },
+
+        .reduce => |fold| {
+            const inits = try evalExprWithEnv(allocator, fold.init, value, env);
+            const sources = try evalExprWithEnv(allocator, fold.source, value, env);
+            var all_results: std.ArrayListUnmanaged(std.json.Value) = .empty;
+
+            for (inits.values) |init_val| {
+                var state = init_val;
+                for (sources.values) |source_val| {
+                    const scope = (try destructure(allocator, fold.pattern, source_val, env)) orelse continue;
+                    // The last output of UPDATE becomes the new state; no output resets it to null
+                    const updated = try evalExprWithEnv(allocator, fold.update, state, scope);
+                    state = if (updated.values.len > 0) updated.values[updated.values.len - 1] else .null;
                 }
-                return try EvalResult.single(allocator, .{ .array = new_arr });
+                try all_results.append(allocator, state);
             }
-            return try EvalResult.single(allocator, value);
+
+            return EvalResult.multi(allocator, try all_results.toOwnedSlice(allocator));
         },
-        else => return try EvalResult.single(allocator, value),
-    }
-}
 
-fn evalBuiltin(allocator: std.mem.Allocator, kind: BuiltinKind, value: std.json.Value) EvalError!EvalResult {
-    switch (kind) {
-        .tonumber => {
-            switch (value) {
-                .integer => return try EvalResult.single(allocator, value),
-                .float => return try EvalResult.single(allocator, value),
-                .string => |s| {
-                    // Try to parse as number
-                    if (std.fmt.parseInt(i64, s, 10)) |i| {
-                        return try EvalResult.single(allocator, .{ .integer = i });
-                    } else |_| {
-                        if (std.fmt.parseFloat(f64, s)) |f| {
-                            return try EvalResult.single(allocator, .{ .float = f });
-                        } else |_| {
-                            return EvalResult.empty(allocator);
+        .foreach => |fold| {
+            const inits = try evalExprWithEnv(allocator, fold.init, value, env);
+            const sources = try evalExprWithEnv(allocator, fold.source, value, env);
+            var all_results: std.ArrayListUnmanaged(std.json.Value) = .empty;
+
+            const mark = partial.items.len;
+            for (inits.values) |init_val| {
+                var state = init_val;
+                for (sources.values) |source_val| {
+                    const scope = (try destructure(allocator, fold.pattern, source_val, env)) orelse continue;
+                    // Every output of UPDATE is emitted (through EXTRACT) and becomes the state
+                    const updated = evalExprWithEnv(allocator, fold.update, state, scope) catch |err| return unwind(allocator, mark, all_results.items, err);
+                    for (updated.values) |next| {
+                        state = next;
+                        if (fold.extract) |extract| {
+                            const extracted = evalExprWithEnv(allocator, extract, state, scope) catch |err| return unwind(allocator, mark, all_results.items, err);
+                            try all_results.appendSlice(allocator, extracted.values);
+                        } else {
+                            try all_results.append(allocator, state);
                         }
                     }
-                },
-                else => return EvalResult.empty(allocator),
+                }
             }
+
+            return EvalResult.multi(allocator, try all_results.toOwnedSlice(allocator));
         },
-        .tostring => {
-            switch (value) {
-                .string => return try EvalResult.single(allocator, value),
-                .integer => |i| {
-                    const str = try std.fmt.allocPrint(allocator, "{d}", .{i});
-                    return try EvalResult.single(allocator, .{ .string = str });
-                },
-                .float => |f| {
-                    const str = try std.fmt.allocPrint(allocator, "{d}", .{f});
-                    return try EvalResult.single(allocator, .{ .string = str });
-                },
-                .bool => |b| {
-                    return try EvalResult.single(allocator, .{ .string = if (b) "true" else "false" });
-                },
-                .null => {
-                    return try EvalResult.single(allocator, .{ .string = "null" });
-                },
-                else => return EvalResult.empty(allocator),
+
+        .define => |d| {
+            // The frame closes over itself so the body can recurse
+            const frame = try allocator.create(Env);
+            frame.* = .{ .parent = env, .name = d.def.name, .closure = .{ .params = d.def.params, .body = d.def.body, .env = frame } };
+            return evalExprWithEnv(allocator, d.rest, value, frame);
+        },
+
+        .call => |call| {
+            const closure = Env.lookupClosure(env, call.name, call.args.len) orelse return EvalResult.empty(allocator);
+            var scopes: std.ArrayListUnmanaged(?*const Env) = .empty;
+            try bindArgs(allocator, closure, call, 0, closure.env, value, env, &scopes);
+            var all_results: std.ArrayListUnmanaged(std.json.Value) = .empty;
+            const mark = partial.items.len;
+            for (scopes.items) |scope| {
+                const body_results = evalExprWithEnv(allocator, closure.body, value, scope) catch |err| return unwind(allocator, mark, all_results.items, err);
+                try all_results.appendSlice(allocator, body_results.values);
             }
+            return EvalResult.multi(allocator, try all_results.toOwnedSlice(allocator));
         },
-        .type => {
-            const type_str: []const u8 = switch (value) {
-                .null => "null",
-                .bool => "boolean",
-                .integer, .float => "number",
-                .string => "string",
-                .array => "array",
-                .object => "object",
-                else => "unknown",
+
+        .regex => |rx| {
+            return evalRegex(allocator, rx, value, env);
+        },
+
+        .format => |kind| {
+            const str = (try applyFormat(allocator, kind, value)) orelse return EvalResult.empty(allocator);
+            return try EvalResult.single(allocator, .{ .string = str });
+        },
+
+        .interp => |interp| {
+            return evalInterp(allocator, interp, value, env);
+        },
+
+        .assign => |assign| {
+            return evalAssign(allocator, assign, value, env);
+        },
+
+        .path_func => |pf| {
+            return evalPathFunc(allocator, pf, value, env);
+        },
+
+        .try_catch => |t| {
+            const mark = partial.items.len;
+            return evalExprWithEnv(allocator, t.body, value, env) catch |err| {
+                const caught: std.json.Value = switch (err) {
+                    error.Raised => raised_value,
+                    error.InvalidPath => .{ .string = "Invalid path expression" },
+                    else => return err,
+                };
+                // Outputs before the error are kept, then the handler's
+                const kept = try allocator.dupe(std.json.Value, partial.items[mark..]);
+                discardPartial(mark);
+                const handler = t.handler orelse return EvalResult.multi(allocator, kept);
+                const handled = evalExprWithEnv(allocator, handler, caught, env) catch |handler_err| return unwind(allocator, mark, kept, handler_err);
+                return EvalResult.multi(allocator, try std.mem.concat(allocator, std.json.Value, &.{ kept, handled.values }));
             };
-            return try EvalResult.single(allocator, .{ .string = type_str });
         },
-        .length => {
-            switch (value) {
-                .string => |s| {
-                    return try EvalResult.single(allocator, .{ .integer = @as(i64, @intCast(s.len)) });
-                },
-                .array => |arr| {
-                    return try EvalResult.single(allocator, .{ .integer = @as(i64, @intCast(arr.items.len)) });
-                },
-                .object => |obj| {
-                    return try EvalResult.single(allocator, .{ .integer = @as(i64, @intCast(obj.count())) });
-                },
-                .null => {
-                    return try EvalResult.single(allocator, .{ .integer = 0 });
-                },
-                else => return EvalResult.empty(allocator),
-            }
+
+        .raise => |msg| {
+            const msg_expr = msg orelse return raise(value);
+            const msgs = try evalExprWithEnv(allocator, msg_expr, value, env);
+            if (msgs.values.len == 0) return EvalResult.empty(allocator);
+            return raise(msgs.values[0]);
         },
-        .keys => {
-            switch (value) {
-                .object => |obj| {
-                    var keys = try allocator.alloc(std.json.Value, obj.count());
-                    var i: usize = 0;
-                    var iter = obj.iterator();
-                    while (iter.next()) |entry| {
-                        keys[i] = .{ .string = entry.key_ptr.* };
-                        i += 1;
-                    }
-                    return try EvalResult.single(allocator, .{ .array = .{ .items = keys, .capacity = keys.len, .allocator = allocator } });
-                },
-                else => return EvalResult.empty(allocator),
-            }
-        },
-        .values => {
-            switch (value) {
-                .object => |obj| {
-                    var vals = try allocator.alloc(std.json.Value, obj.count());
-                    var i: usize = 0;
-                    var iter = obj.iterator();
-                    while (iter.next()) |entry| {
-                        vals[i] = entry.value_ptr.*;
-                        i += 1;
-                    }
-                    return try EvalResult.single(allocator, .{ .array = .{ .items = vals, .capacity = vals.len, .allocator = allocator } });
-                },
-                else => return EvalResult.empty(allocator),
-            }
-        },
-        .isnumber => {
-            const result = switch (value) {
-                .integer, .float => true,
-                else => false,
+
+        .label => |l| {
+            label_counter += 1;
+            const id = label_counter;
+            const scope = try Env.bind(allocator, env, l.name, .{ .integer = id });
+            const mark = partial.items.len;
+            return evalExprWithEnv(allocator, l.body, value, scope) catch |err| {
+                if (err != error.Break or break_target != id) return err;
+                const kept = try allocator.dupe(std.json.Value, partial.items[mark..]);
+                discardPartial(mark);
+                return EvalResult.multi(allocator, kept);
             };
-            return try EvalResult.single(allocator, .{ .bool = result });
         },
-        .isstring => {
-            const result = value == .string;
-            return try EvalResult.single(allocator, .{ .bool = result });
+
+        .generator => |gen| {
+            return evalGenerator(allocator, gen, value, env, std.math.maxInt(usize));
         },
-        .isboolean => {
-            const result = value == .bool;
-            return try EvalResult.single(allocator, .{ .bool = result });
+
+        .date_func => |df| {
+            return evalDateFunc(allocator, df, value, env);
         },
-        .isnull => {
-            const result = value == .null;
-            return try EvalResult.single(allocator, .{ .bool = result });
+
+        .lib_func => |lf| {
+            return evalLibFunc(allocator, lf, value, env);
         },
-        .isarray => {
-            const result = value == .array;
-            return try EvalResult.single(allocator, .{ .bool = result });
+
+        .break_label => |name| {
+            const target = Env.lookup(env, name) orelse return EvalResult.empty(allocator);
+            break_target = target.integer;
+            return error.Break;
         },
-        .isobject => {
-            const result = value == .object;
-            return try EvalResult.single(allocator, .{ .bool = result });
+    }
+}
+
+/// Bind the arguments of `call` from index `idx` onward, collecting one scope
+/// per body run: `$x` parameters run the body once per value of their argument.
+fn bindArgs(
+    allocator: std.mem.Allocator,
+    closure: Closure,
+    call: CallExpr,
+    idx: usize,
+    scope: ?*const Env,
+    value: std.json.Value,
+    caller_env: ?*const Env,
+    scopes: *std.ArrayListUnmanaged(?*const Env),
+) EvalError!void {
+    if (idx == call.args.len) {
+        try scopes.append(allocator, scope);
+        return;
+    }
+
+    const param = closure.params[idx];
+    const arg = call.args[idx];
+    if (!param.is_value) {
+        const next = try Env.bindClosure(allocator, scope, param.name, .{ .params = &.{}, .body = arg, .env = caller_env });
+        return bindArgs(allocator, closure, call, idx + 1, next, value, caller_env, scopes);
+    }
+
+    const arg_values = try evalExprWithEnv(allocator, arg, value, caller_env);
+    for (arg_values.values) |arg_val| {
+        // `$x` is also callable as the filter `x`
+        const var_frame = try Env.bind(allocator, scope, param.name, arg_val);
+        const getter = try allocator.create(Expr);
+        getter.* = .{ .variable = param.name };
+        const next = try Env.bindClosure(allocator, var_frame, param.name, .{ .params = &.{}, .body = getter, .env = var_frame });
+        try bindArgs(allocator, closure, call, idx + 1, next, value, caller_env, scopes);
+    }
+}
+
+/// Bind the variables of `pattern` against `value`, returning the extended scope.
+/// Returns null when the value's shape cannot be destructured (e.g. an object
+/// pattern applied to a number); missing keys and indexes bind to null.
+fn destructure(allocator: std.mem.Allocator, pattern: Pattern, value: std.json.Value, env: ?*const Env) EvalError!?*const Env {
+    switch (pattern) {
+        .variable => |name| return try Env.bind(allocator, env, name, value),
+        .array => |elements| {
+            if (value != .array and value != .null) return null;
+            var scope = env;
+            for (elements, 0..) |element, i| {
+                const item: std.json.Value = switch (value) {
+                    .array => |arr| if (i < arr.items.len) arr.items[i] else .null,
+                    else => .null,
+                };
+                scope = (try destructure(allocator, element, item, scope)) orelse return null;
+            }
+            return scope;
+        },
+        .object => |fields| {
+            if (value != .object and value != .null) return null;
+            var scope = env;
+            for (fields) |field| {
+                const item: std.json.Value = switch (value) {
+                    .object => |obj| obj.get(field.key) orelse .null,
+                    else => .null,
+                };
+                scope = (try destructure(allocator, field.value, item, scope)) orelse return null;
+            }
+            return scope;
         },
-        // Sprint 03: Array functions
-        .first => {
-            switch (value) {
-                .array => |arr| {
-                    if (arr.items.len > 0) {
-                        return try EvalResult.single(allocator, arr.items[0]);
-                    }
-                    return EvalResult.empty(allocator);
-                },
-                else => return EvalResult.empty(allocator),
+    }
+}
+
+// ============================================================================
+// Errors and Labels
+// ============================================================================
+
+/// Value carried by the last error.Raised: the argument of error(), or the
+/// message of a type error.
+threadlocal var raised_value: std.json.Value = .null;
+/// Label id targeted by the last error.Break
+threadlocal var break_target: i64 = 0;
+threadlocal var label_counter: i64 = 0;
+/// Outputs produced before an error or break unwound the evaluation, in
+/// order. Each streaming expression splices in what it had finished on the
+/// way out, so try, label and the caller can still emit them.
+threadlocal var partial: std.ArrayListUnmanaged(std.json.Value) = .empty;
+
+/// The value of the error.Raised returned by the last evalExpr.
+pub fn errorValue() std.json.Value {
+    return raised_value;
+}
+
+/// Outputs the last evalExpr produced before it failed.
+pub fn partialOutputs() []const std.json.Value {
+    return partial.items;
+}
+
+fn raise(value: std.json.Value) EvalError {
+    raised_value = value;
+    return error.Raised;
+}
+
+fn raiseMessage(allocator: std.mem.Allocator, comptime fmt: []const u8, args: anytype) EvalError {
+    const msg = std.fmt.allocPrint(allocator, fmt, args) catch return error.OutOfMemory;
+    return raise(.{ .string = msg });
+}
+
+/// "number (42)" style description for type errors; long values are cut short.
+fn describeValue(allocator: std.mem.Allocator, value: std.json.Value) EvalError![]const u8 {
+    const text = try toJsonText(allocator, value);
+    const max_len = 11;
+    if (text.len <= max_len) return std.fmt.allocPrint(allocator, "{s} ({s})", .{ typeName(value), text });
+    return std.fmt.allocPrint(allocator, "{s} ({s}...)", .{ typeName(value), text[0 .. max_len - 1] });
+}
+
+fn typeName(value: std.json.Value) []const u8 {
+    return switch (value) {
+        .null => "null",
+        .bool => "boolean",
+        .integer, .float, .number_string => "number",
+        .string => "string",
+        .array => "array",
+        .object => "object",
+    };
+}
+
+/// Put `done` (outputs finished before `err`) in front of the partial outputs
+/// gathered above `mark`, then pass `err` on.
+fn unwind(allocator: std.mem.Allocator, mark: usize, done: []const std.json.Value, err: EvalError) EvalError {
+    if (err == error.OutOfMemory or done.len == 0) return err;
+    partial.insertSlice(allocator, mark, done) catch return error.OutOfMemory;
+    return err;
+}
+
+fn discardPartial(mark: usize) void {
+    if (partial.items.len > mark) partial.shrinkRetainingCapacity(mark);
+}
+
+// ============================================================================
+// Generators
+// ============================================================================
+
+/// Where input/inputs read further records from; main installs one that
+/// pulls lines from stdin. Null means there is no more input.
+pub const InputSource = struct {
+    context: *anyopaque,
+    nextFn: *const fn (context: *anyopaque, allocator: std.mem.Allocator) ?std.json.Value,
+};
+
+threadlocal var input_source: ?InputSource = null;
+
+pub fn setInputSource(source: ?InputSource) void {
+    input_source = source;
+}
+
+/// Evaluate a generator. Generators that can run forever (repeat, while,
+/// inputs, ...) stop after `cap` outputs, which is how limit(n; f) and
+/// first(f) end them when f is the generator itself.
+fn evalGenerator(allocator: std.mem.Allocator, gen: GeneratorExpr, value: std.json.Value, env: ?*const Env, cap: usize) EvalError!EvalResult {
+    var results: std.ArrayListUnmanaged(std.json.Value) = .empty;
+    switch (gen.kind) {
+        .range => {
+            // Arguments combine like nested loops: range(0, 1; 3) is 0,1,2 then 1,2
+            const zero = [_]std.json.Value{.{ .integer = 0 }};
+            const one = [_]std.json.Value{.{ .integer = 1 }};
+            const froms: []const std.json.Value = if (gen.args.len > 1) (try evalExprWithEnv(allocator, gen.args[0], value, env)).values else &zero;
+            const uptos = (try evalExprWithEnv(allocator, gen.args[if (gen.args.len > 1) 1 else 0], value, env)).values;
+            const bys: []const std.json.Value = if (gen.args.len > 2) (try evalExprWithEnv(allocator, gen.args[2], value, env)).values else &one;
+            for (froms) |from| {
+                for (uptos) |upto| {
+                    for (bys) |by| try rangeValues(allocator, from, upto, by, cap, &results);
+                }
+            }
+        },
+        .limit => {
+            const counts = try evalExprWithEnv(allocator, gen.args[0], value, env);
+            for (counts.values) |count| {
+                const n = try outputCount(allocator, count);
+                try results.appendSlice(allocator, try firstOutputs(allocator, gen.args[1], value, env, @min(n, cap)));
             }
         },
+        .first => try results.appendSlice(allocator, try firstOutputs(allocator, gen.args[0], value, env, 1)),
         .last => {
-            switch (value) {
-                .array => |arr| {
-                    if (arr.items.len > 0) {
-                        return try EvalResult.single(allocator, arr.items[arr.items.len - 1]);
-                    }
-                    return EvalResult.empty(allocator);
-                },
-                else => return EvalResult.empty(allocator),
+            const outputs = try evalExprWithEnv(allocator, gen.args[0], value, env);
+            if (outputs.values.len > 0) try results.append(allocator, outputs.values[outputs.values.len - 1]);
+        },
+        .nth => {
+            const indexes = try evalExprWithEnv(allocator, gen.args[0], value, env);
+            for (indexes.values) |index| {
+                if (gen.args.len == 1) {
+                    // nth(n) is .[n]
+                    if (index != .integer) return raiseMessage(allocator, "Cannot index {s} with {s}", .{ typeName(value), typeName(index) });
+                    if (getIndex(value, index.integer)) |item| try results.append(allocator, item);
+                    continue;
+                }
+                if ((getNumeric(index) orelse 0) < 0) return raiseMessage(allocator, "Out of bounds negative array index", .{});
+                const n = try outputCount(allocator, index);
+                const outputs = try firstOutputs(allocator, gen.args[1], value, env, n +| 1);
+                if (outputs.len > n) try results.append(allocator, outputs[n]);
             }
         },
-        .reverse => {
-            switch (value) {
-                .array => |arr| {
-                    var reversed = try allocator.alloc(std.json.Value, arr.items.len);
-                    for (arr.items, 0..) |item, i| {
-                        reversed[arr.items.len - 1 - i] = item;
-                    }
-                    return try EvalResult.single(allocator, .{ .array = .{ .items = reversed, .capacity = reversed.len, .allocator = allocator } });
-                },
-                .string => |s| {
-                    var reversed = try allocator.alloc(u8, s.len);
-                    for (s, 0..) |c, i| {
-                        reversed[s.len - 1 - i] = c;
-                    }
-                    return try EvalResult.single(allocator, .{ .string = reversed });
-                },
-                else => return EvalResult.empty(allocator),
+        .until => try expandStates(allocator, value, env, gen.args[1], gen.args[0], true, cap, &results),
+        .@"while" => {
+            if (try condHolds(allocator, gen.args[0], value, env)) {
+                try expandStates(allocator, value, env, gen.args[1], gen.args[0], false, cap, &results);
             }
         },
-        .sort => {
-            switch (value) {
-                .array => |arr| {
-                    const sorted = try allocator.alloc(std.json.Value, arr.items.len);
-                    @memcpy(sorted, arr.items);
-                    std.mem.sort(std.json.Value, sorted, {}, jsonLessThan);
-                    return try EvalResult.single(allocator, .{ .array = .{ .items = sorted, .capacity = sorted.len, .allocator = allocator } });
-                },
-                else => return EvalResult.empty(allocator),
+        .repeat => try expandStates(allocator, value, env, gen.args[0], null, false, cap, &results),
+        .recurse => {
+            const next: ?*const Expr = if (gen.args.len > 0) gen.args[0] else null;
+            const cond: ?*const Expr = if (gen.args.len > 1) gen.args[1] else null;
+            try expandStates(allocator, value, env, next, cond, false, cap, &results);
+        },
+        .input => {
+            const next = try nextInput(allocator);
+            try results.append(allocator, next orelse return raiseMessage(allocator, "No more inputs", .{}));
+        },
+        .inputs => {
+            while (results.items.len < cap) {
+                try results.append(allocator, (try nextInput(allocator)) orelse break);
             }
         },
-        .unique => {
-            switch (value) {
-                .array => |arr| {
-                    if (arr.items.len == 0) {
-                        return try EvalResult.single(allocator, .{ .array = .{ .items = &.{}, .capacity = 0, .allocator = allocator } });
-                    }
+        .env => {
+            const env_map = std.process.getEnvMap(allocator) catch return error.OutOfMemory;
+            var obj = std.json.ObjectMap.init(allocator);
+            var it = env_map.iterator();
+            while (it.next()) |entry| {
+                try obj.put(entry.key_ptr.*, .{ .string = entry.value_ptr.* });
+            }
+            try results.append(allocator, .{ .object = obj });
+        },
+        .tostream => try stream.appendEvents(allocator, value, &results),
+        .fromstream => {
+            var assembler: stream.Assembler = .{};
+            const events = try evalExprWithEnv(allocator, gen.args[0], value, env);
+            for (events.values) |event| {
+                if (try pushEvent(allocator, &assembler, event)) |done| try results.append(allocator, done);
+            }
+        },
+        .truncate_stream => {
+            const depth = try streamDepth(allocator, value);
+            // As in jq, the events are generated from null
+            const events = try evalExprWithEnv(allocator, gen.args[0], .null, env);
+            for (events.values) |event| {
+                if (try truncateEvent(allocator, event, depth)) |shorter| try results.append(allocator, shorter);
+            }
+        },
+    }
+    return EvalResult.multi(allocator, try results.toOwnedSlice(allocator));
+}
 
-                    // O(n log n) implementation: sort then remove consecutive duplicates
-                    // This matches jq's behavior where unique returns sorted output
-                    var sorted = try allocator.dupe(std.json.Value, arr.items);
-                    std.mem.sort(std.json.Value, sorted, {}, jsonLessThan);
+fn pushEvent(allocator: std.mem.Allocator, assembler: *stream.Assembler, event: std.json.Value) EvalError!?std.json.Value {
+    return assembler.push(allocator, event) catch |err| switch (err) {
+        error.OutOfMemory => error.OutOfMemory,
+        error.InvalidEvent => raiseMessage(allocator, "Invalid stream event: {s}", .{try describeValue(allocator, event)}),
+    };
+}
 
-                    // Linear pass to remove consecutive duplicates
-                    var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
-                    try result_list.append(allocator, sorted[0]);
-                    for (sorted[1..]) |item| {
-                        if (!jsonEqual(item, result_list.items[result_list.items.len - 1])) {
-                            try result_list.append(allocator, item);
-                        }
-                    }
+fn streamDepth(allocator: std.mem.Allocator, value: std.json.Value) EvalError!usize {
+    if (value != .integer or value.integer < 0) {
+        return raiseMessage(allocator, "truncate_stream depth must be a non-negative integer, not {s}", .{try describeValue(allocator, value)});
+    }
+    return @intCast(value.integer);
+}
 
-                    // Free the temporary sorted array
-                    allocator.free(sorted);
+/// The event with its path shortened by `depth`; events at or above that
+/// depth are dropped.
+fn truncateEvent(allocator: std.mem.Allocator, event: std.json.Value, depth: usize) EvalError!?std.json.Value {
+    if (event != .array or event.array.items.len == 0 or event.array.items[0] != .array) {
+        return raiseMessage(allocator, "Invalid stream event: {s}", .{try describeValue(allocator, event)});
+    }
+    const path = event.array.items[0].array.items;
+    if (path.len <= depth) return null;
+    const items = try allocator.dupe(std.json.Value, event.array.items);
+    const rest = try allocator.dupe(std.json.Value, path[depth..]);
+    items[0] = .{ .array = .{ .items = rest, .capacity = rest.len, .allocator = allocator } };
+    return .{ .array = .{ .items = items, .capacity = items.len, .allocator = allocator } };
+}
 
-                    const result_slice = try result_list.toOwnedSlice(allocator);
-                    return try EvalResult.single(allocator, .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } });
-                },
-                else => return EvalResult.empty(allocator),
+/// Runs `fromstream(f)` or `fromstream(f) | rest` one input at a time, so
+/// `zq -n --stream 'fromstream(1|truncate_stream(inputs))'` needs memory for
+/// one value, not the whole input. This works when f handles each input on
+/// its own: its only input read is an `inputs` that feeds the events, possibly
+/// through pipes and truncate_stream.
+pub const StreamDriver = struct {
+    events: *const Expr,
+    rest: ?*const Expr,
+    assembler: stream.Assembler = .{},
+    /// Holds the value being assembled; reset once it has been emitted
+    arena: std.heap.ArenaAllocator,
+
+    /// Recognize the shape; `input_calls` is the parser's count for the whole expression.
+    pub fn match(gpa: std.mem.Allocator, expr: *const Expr, input_calls: u32) ?StreamDriver {
+        if (input_calls != 1) return null;
+        var head = expr;
+        var rest: ?*const Expr = null;
+        if (expr.* == .pipe) {
+            head = expr.pipe.left;
+            rest = expr.pipe.right;
+        }
+        if (head.* != .generator or head.generator.kind != .fromstream) return null;
+        if (!readsInputsPerEvent(head.generator.args[0])) return null;
+        return .{ .events = head.generator.args[0], .rest = rest, .arena = .init(gpa) };
+    }
+
+    pub fn deinit(self: *StreamDriver) void {
+        self.arena.deinit();
+    }
+
+    /// Run f with `input` as the only thing inputs can read, and return the
+    /// values that completes (piped through rest).
+    pub fn feed(self: *StreamDriver, allocator: std.mem.Allocator, input: std.json.Value) EvalError!EvalResult {
+        if (self.assembler.done) _ = self.arena.reset(.retain_capacity);
+
+        var one: OneInput = .{ .value = input };
+        const saved = input_source;
+        input_source = .{ .context = &one, .nextFn = OneInput.next };
+        defer input_source = saved;
+
+        partial = .empty;
+        const events = try evalExprWithEnv(allocator, self.events, .null, globals);
+        var done: std.ArrayListUnmanaged(std.json.Value) = .empty;
+        for (events.values) |event| {
+            const value = (try pushEvent(self.arena.allocator(), &self.assembler, event)) orelse continue;
+            const rest = self.rest orelse {
+                try done.append(allocator, value);
+                continue;
+            };
+            try done.appendSlice(allocator, (try evalExprWithEnv(allocator, rest, value, globals)).values);
+        }
+        return EvalResult.multi(allocator, try done.toOwnedSlice(allocator));
+    }
+
+    const OneInput = struct {
+        value: ?std.json.Value,
+
+        fn next(context: *anyopaque, _: std.mem.Allocator) ?std.json.Value {
+            const self: *OneInput = @ptrCast(@alignCast(context));
+            defer self.value = null;
+            return self.value;
+        }
+    };
+};
+
+/// True if f's outputs for a run of inputs are the outputs for each input in
+/// turn: `inputs`, `inputs | g`, `N | truncate_stream(f)` and so on.
+fn readsInputsPerEvent(f: *const Expr) bool {
+    return switch (f.*) {
+        .generator => |g| switch (g.kind) {
+            .inputs => true,
+            .truncate_stream => readsInputsPerEvent(g.args[0]),
+            else => false,
+        },
+        // Only one side can read input; a literal on the left runs once
+        .pipe => |p| readsInputsPerEvent(p.left) or (p.left.* == .literal and readsInputsPerEvent(p.right)),
+        else => false,
+    };
+}
+
+fn nextInput(allocator: std.mem.Allocator) EvalError!?std.json.Value {
+    const source = input_source orelse return null;
+    return source.nextFn(source.context, allocator);
+}
+
+/// A limit or nth count as a usize; negative counts are 0.
+fn outputCount(allocator: std.mem.Allocator, count: std.json.Value) EvalError!usize {
+    const n = getNumeric(count) orelse return raiseMessage(allocator, "Invalid limit: {s} is not a number", .{try describeValue(allocator, count)});
+    if (n <= 0) return 0;
+    if (n >= @as(f64, @floatFromInt(std.math.maxInt(u32)))) return std.math.maxInt(u32);
+    return @intFromFloat(n);
+}
+
+/// The first `n` outputs of f. A generator f is stopped after n outputs, and
+/// an error or break after the n-th output doesn't count.
+fn firstOutputs(allocator: std.mem.Allocator, f: *const Expr, value: std.json.Value, env: ?*const Env, n: usize) EvalError![]const std.json.Value {
+    if (n == 0) return &.{};
+    const mark = partial.items.len;
+    const attempt = if (f.* == .generator) evalGenerator(allocator, f.generator, value, env, n) else evalExprWithEnv(allocator, f, value, env);
+    const produced = attempt catch |err| {
+        if (err == error.OutOfMemory or partial.items.len - mark < n) return err;
+        const kept = try allocator.dupe(std.json.Value, partial.items[mark .. mark + n]);
+        discardPartial(mark);
+        return kept;
+    };
+    return produced.values[0..@min(n, produced.values.len)];
+}
+
+/// Append from, from + by, ... while below upto (above it when by is negative).
+fn rangeValues(allocator: std.mem.Allocator, from: std.json.Value, upto: std.json.Value, by: std.json.Value, cap: usize, out: *std.ArrayListUnmanaged(std.json.Value)) EvalError!void {
+    if (from == .integer and upto == .integer and by == .integer) {
+        const step = by.integer;
+        if (step == 0) return;
+        var i = from.integer;
+        while ((if (step > 0) i < upto.integer else i > upto.integer) and out.items.len < cap) {
+            try out.append(allocator, .{ .integer = i });
+            i = std.math.add(i64, i, step) catch return;
+        }
+        return;
+    }
+
+    const start = getNumeric(from) orelse return raiseMessage(allocator, "Range bounds must be numeric", .{});
+    const end = getNumeric(upto) orelse return raiseMessage(allocator, "Range bounds must be numeric", .{});
+    const step = getNumeric(by) orelse return raiseMessage(allocator, "Range bounds must be numeric", .{});
+    if (step == 0) return;
+    var x = start;
+    while ((if (step > 0) x < end else x > end) and out.items.len < cap) : (x += step) {
+        try out.append(allocator, .{ .float = x });
+    }
+}
+
+/// Depth-first expansion behind recurse, repeat, while and until
+/// (`def r: ., (next | select(cond) | r)`). `next` null steps into array
+/// elements and object values, like `.[]?`. With `until`, only states where
+/// cond holds are emitted, and they aren't expanded further.
+fn expandStates(
+    allocator: std.mem.Allocator,
+    value: std.json.Value,
+    env: ?*const Env,
+    next: ?*const Expr,
+    cond: ?*const Expr,
+    until: bool,
+    cap: usize,
+    out: *std.ArrayListUnmanaged(std.json.Value),
+) EvalError!void {
+    var stack: std.ArrayListUnmanaged(std.json.Value) = .empty;
+    try stack.append(allocator, value);
+    while (stack.pop()) |state| {
+        if (out.items.len >= cap) return;
+        if (until) {
+            if (try condHolds(allocator, cond.?, state, env)) {
+                try out.append(allocator, state);
+                continue;
+            }
+        } else {
+            try out.append(allocator, state);
+        }
+
+        const children: []const std.json.Value = if (next) |f|
+            (try evalExprWithEnv(allocator, f, state, env)).values
+        else switch (state) {
+            .array => |arr| arr.items,
+            .object => |obj| obj.values(),
+            else => &.{},
+        };
+        // Pushed in reverse so the first child is expanded first
+        var i = children.len;
+        while (i > 0) {
+            i -= 1;
+            if (!until) {
+                if (cond) |c| {
+                    if (!try condHolds(allocator, c, children[i], env)) continue;
+                }
+            }
+            try stack.append(allocator, children[i]);
+        }
+    }
+}
+
+/// Conditions are parsed as select(...), so they hold when select keeps the value.
+fn condHolds(allocator: std.mem.Allocator, cond: *const Expr, value: std.json.Value, env: ?*const Env) EvalError!bool {
+    return (try evalExprWithEnv(allocator, cond, value, env)).values.len > 0;
+}
+
+// ============================================================================
+// Dates
+// ============================================================================
+
+/// Whole epoch seconds plus a fraction in [0, 1).
+const Instant = struct {
+    secs: i64,
+    frac: f64 = 0,
+
+    fn toValue(self: Instant) std.json.Value {
+        if (self.frac == 0) return .{ .integer = self.secs };
+        return .{ .float = @as(f64, @floatFromInt(self.secs)) + self.frac };
+    }
+};
+
+/// Calendar fields of an instant. Month is 1-12, wday 0 is Sunday, yday starts at 0.
+const Civil = struct {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    frac: f64,
+    wday: u32,
+    yday: u32,
+};
+
+const CivilDate = struct { year: i64, month: u32, day: u32 };
+
+const DateUnit = enum { second, minute, hour, day, week, month, year };
+
+// About three million years either side of 1970; keeps the calendar math in range
+const max_epoch_seconds: i64 = 100_000_000_000_000;
+
+const iso8601_format = "%Y-%m-%dT%H:%M:%SZ";
+
+const month_names = [_][]const u8{ "January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December" };
+const weekday_names = [_][]const u8{ "Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday" };
+
+fn evalDateFunc(allocator: std.mem.Allocator, df: DateFuncExpr, value: std.json.Value, env: ?*const Env) EvalError!EvalResult {
+    var results: std.ArrayListUnmanaged(std.json.Value) = .empty;
+    switch (df.kind) {
+        .gmtime => {
+            const t = try timeInput(allocator, value, "gmtime");
+            try results.append(allocator, try brokenDownValue(allocator, civilFromInstant(t)));
+        },
+        .mktime => {
+            if (value != .array) return raiseMessage(allocator, "mktime requires array of 6 numbers", .{});
+            const t = try instantFromBrokenDown(allocator, value.array.items);
+            try results.append(allocator, .{ .integer = t.secs });
+        },
+        .strptime => {
+            const fmts = try evalExprWithEnv(allocator, df.args[0], value, env);
+            for (fmts.values) |fmt| {
+                if (value != .string or fmt != .string) return raiseMessage(allocator, "strptime/1 requires string inputs and arguments", .{});
+                const t = parseTime(value.string, fmt.string) orelse
+                    return raiseMessage(allocator, "date \"{s}\" does not match format \"{s}\"", .{ value.string, fmt.string });
+                try results.append(allocator, try brokenDownValue(allocator, civilFromInstant(t)));
             }
         },
-        .flatten => {
-            switch (value) {
-                .array => |arr| {
-                    var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
-                    for (arr.items) |item| {
-                        switch (item) {
-                            .array => |inner| {
-                                try result_list.appendSlice(allocator, inner.items);
-                            },
-                            else => try result_list.append(allocator, item),
-                        }
-                    }
-                    const result_slice = try result_list.toOwnedSlice(allocator);
-                    return try EvalResult.single(allocator, .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } });
-                },
-                else => return EvalResult.empty(allocator),
+        .strftime => {
+            const t = try timeInput(allocator, value, "strftime/1");
+            const fmts = try evalExprWithEnv(allocator, df.args[0], value, env);
+            const utc = [_]std.json.Value{.{ .integer = 0 }};
+            const offsets: []const std.json.Value = if (df.args.len > 1) (try evalExprWithEnv(allocator, df.args[1], value, env)).values else &utc;
+            for (fmts.values) |fmt| {
+                if (fmt != .string) return raiseMessage(allocator, "strftime/1 requires a string format", .{});
+                for (offsets) |offset| {
+                    try results.append(allocator, .{ .string = try formatTime(allocator, fmt.string, t, try utcOffset(allocator, offset)) });
+                }
             }
         },
-        // Sprint 03: Aggregation functions
-        .add => {
-            switch (value) {
-                .array => |arr| {
-                    // Sum numbers or concatenate strings
-                    if (arr.items.len == 0) return try EvalResult.single(allocator, .null);
+        .todate => {
+            const t = try timeInput(allocator, value, "todate");
+            try results.append(allocator, .{ .string = try formatTime(allocator, iso8601_format, t, 0) });
+        },
+        .fromdate => {
+            if (value != .string) return raiseMessage(allocator, "fromdate requires string inputs, not {s}", .{typeName(value)});
+            const t = parseIso8601(value.string) orelse
+                return raiseMessage(allocator, "date \"{s}\" does not match format \"" ++ iso8601_format ++ "\"", .{value.string});
+            try results.append(allocator, t.toValue());
+        },
+        .date_trunc => {
+            const t = try timeInput(allocator, value, "date_trunc/1");
+            const units = try evalExprWithEnv(allocator, df.args[0], value, env);
+            for (units.values) |unit| {
+                const truncated = truncateTime(t, try dateUnit(allocator, unit));
+                try results.append(allocator, try timeOutput(allocator, value, truncated));
+            }
+        },
+        .dateadd, .datesub => {
+            const t = try timeInput(allocator, value, if (df.kind == .dateadd) "dateadd/2" else "datesub/2");
+            const units = try evalExprWithEnv(allocator, df.args[0], value, env);
+            const amounts = try evalExprWithEnv(allocator, df.args[1], value, env);
+            for (units.values) |unit| {
+                const u = try dateUnit(allocator, unit);
+                for (amounts.values) |amount| {
+                    const moved = try addTime(allocator, t, u, amount, df.kind == .datesub);
+                    try results.append(allocator, try timeOutput(allocator, value, moved));
+                }
+            }
+        },
+    }
+    return EvalResult.multi(allocator, try results.toOwnedSlice(allocator));
+}
 
-                    // Check first element type
-                    switch (arr.items[0]) {
-                        .integer, .float => {
-                            var sum: f64 = 0;
-                            var all_int = true;
-                            for (arr.items) |item| {
-                                switch (item) {
-                                    .integer => |i| sum += @as(f64, @floatFromInt(i)),
-                                    .float => |f| {
-                                        sum += f;
-                                        all_int = false;
-                                    },
-                                    else => {},
-                                }
-                            }
-                            if (all_int) {
-                                return try EvalResult.single(allocator, .{ .integer = @as(i64, @intFromFloat(sum)) });
-                            }
-                            return try EvalResult.single(allocator, .{ .float = sum });
-                        },
-                        .string => {
-                            var total_len: usize = 0;
-                            for (arr.items) |item| {
-                                switch (item) {
-                                    .string => |s| total_len += s.len,
-                                    else => {},
-                                }
-                            }
-                            var result_str = try allocator.alloc(u8, total_len);
-                            var pos: usize = 0;
-                            for (arr.items) |item| {
-                                switch (item) {
-                                    .string => |s| {
-                                        @memcpy(result_str[pos..][0..s.len], s);
-                                        pos += s.len;
-                                    },
-                                    else => {},
-                                }
-                            }
-                            return try EvalResult.single(allocator, .{ .string = result_str });
-                        },
-                        .array => {
-                            // Flatten arrays
-                            var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
-                            for (arr.items) |item| {
-                                switch (item) {
-                                    .array => |inner| try result_list.appendSlice(allocator, inner.items),
-                                    else => {},
-                                }
-                            }
-                            const result_slice = try result_list.toOwnedSlice(allocator);
-                            return try EvalResult.single(allocator, .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } });
-                        },
-                        else => return EvalResult.empty(allocator),
-                    }
-                },
-                else => return EvalResult.empty(allocator),
+/// Epoch seconds, an ISO 8601 string or a broken-down time, as an instant.
+fn timeInput(allocator: std.mem.Allocator, value: std.json.Value, name: []const u8) EvalError!Instant {
+    switch (value) {
+        .integer => |i| {
+            if (i > max_epoch_seconds or i < -max_epoch_seconds) return dateOutOfRange(allocator, value);
+            return .{ .secs = i };
+        },
+        .float => |f| return instantFromFloat(allocator, f),
+        .number_string => |s| return instantFromFloat(allocator, std.fmt.parseFloat(f64, s) catch return dateOutOfRange(allocator, value)),
+        .string => |s| return parseIso8601(s) orelse
+            return raiseMessage(allocator, "date \"{s}\" does not match format \"" ++ iso8601_format ++ "\"", .{s}),
+        .array => |arr| return instantFromBrokenDown(allocator, arr.items),
+        else => return raiseMessage(allocator, "{s} requires parsed datetime inputs", .{name}),
+    }
+}
+
+/// A computed time in the shape its input came in: ISO strings stay strings,
+/// broken-down times stay arrays and numbers stay numbers.
+fn timeOutput(allocator: std.mem.Allocator, input: std.json.Value, t: Instant) EvalError!std.json.Value {
+    return switch (input) {
+        .string => .{ .string = try formatTime(allocator, iso8601_format, t, 0) },
+        .array => brokenDownValue(allocator, civilFromInstant(t)),
+        else => t.toValue(),
+    };
+}
+
+fn dateOutOfRange(allocator: std.mem.Allocator, value: std.json.Value) EvalError {
+    return raiseMessage(allocator, "{s} is out of range for a date", .{try describeValue(allocator, value)});
+}
+
+fn instantFromFloat(allocator: std.mem.Allocator, f: f64) EvalError!Instant {
+    if (!std.math.isFinite(f) or @abs(f) > @as(f64, @floatFromInt(max_epoch_seconds))) return dateOutOfRange(allocator, .{ .float = f });
+    const whole = @floor(f);
+    return .{ .secs = @intFromFloat(whole), .frac = f - whole };
+}
+
+/// mktime: fields past their range carry over like timegm's, so month 12 is
+/// January of the next year. wday and yday are ignored.
+fn instantFromBrokenDown(allocator: std.mem.Allocator, items: []const std.json.Value) EvalError!Instant {
+    if (items.len < 6) return raiseMessage(allocator, "mktime requires array of 6 numbers", .{});
+    var fields: [6]f64 = undefined;
+    for (items[0..6], &fields) |item, *field| {
+        const n = getNumeric(item) orelse return raiseMessage(allocator, "mktime requires parsed datetime inputs", .{});
+        if (!std.math.isFinite(n) or @abs(n) > @as(f64, @floatFromInt(max_epoch_seconds))) return dateOutOfRange(allocator, item);
+        field.* = n;
+    }
+    const month: i64 = @intFromFloat(@floor(fields[4]));
+    const year = @as(i64, @intFromFloat(@floor(fields[5]))) + @divFloor(month, 12);
+    const days = daysFromCivil(year, @intCast(@mod(month, 12) + 1), 1);
+    const total = @as(f64, @floatFromInt(days)) * 86400 + (@floor(fields[3]) - 1) * 86400 +
+        @floor(fields[2]) * 3600 + @floor(fields[1]) * 60 + fields[0];
+    return instantFromFloat(allocator, total);
+}
+
+fn brokenDownValue(allocator: std.mem.Allocator, c: Civil) EvalError!std.json.Value {
+    var arr = std.json.Array.init(allocator);
+    const second: std.json.Value = if (c.frac == 0) .{ .integer = c.second } else .{ .float = @as(f64, @floatFromInt(c.second)) + c.frac };
+    const fields = [_]std.json.Value{
+        second,
+        .{ .integer = c.minute },
+        .{ .integer = c.hour },
+        .{ .integer = c.day },
+        .{ .integer = c.month - 1 },
+        .{ .integer = c.year },
+        .{ .integer = c.wday },
+        .{ .integer = c.yday },
+    };
+    try arr.appendSlice(&fields);
+    return .{ .array = arr };
+}
+
+fn civilFromInstant(t: Instant) Civil {
+    const days = @divFloor(t.secs, 86400);
+    const rem: u32 = @intCast(@mod(t.secs, 86400));
+    const date = civilFromDays(days);
+    return .{
+        .year = date.year,
+        .month = date.month,
+        .day = date.day,
+        .hour = rem / 3600,
+        .minute = rem / 60 % 60,
+        .second = rem % 60,
+        .frac = t.frac,
+        .wday = @intCast(@mod(days + 4, 7)),
+        .yday = @intCast(days - daysFromCivil(date.year, 1, 1)),
+    };
+}
+
+// Days since 1970-01-01 and back, for any year
+// (http://howardhinnant.github.io/date_algorithms.html)
+fn daysFromCivil(year: i64, month: u32, day: u32) i64 {
+    const y = if (month <= 2) year - 1 else year;
+    const era = @divFloor(y, 400);
+    const yoe = y - era * 400;
+    const m: i64 = month;
+    const doy = @divFloor(153 * (if (m > 2) m - 3 else m + 9) + 2, 5) + @as(i64, day) - 1;
+    const doe = yoe * 365 + @divFloor(yoe, 4) - @divFloor(yoe, 100) + doy;
+    return era * 146097 + doe - 719468;
+}
+
+fn civilFromDays(days: i64) CivilDate {
+    const z = days + 719468;
+    const era = @divFloor(z, 146097);
+    const doe = z - era * 146097;
+    const yoe = @divFloor(doe - @divFloor(doe, 1460) + @divFloor(doe, 36524) - @divFloor(doe, 146096), 365);
+    const doy = doe - (365 * yoe + @divFloor(yoe, 4) - @divFloor(yoe, 100));
+    const mp = @divFloor(5 * doy + 2, 153);
+    const d = doy - @divFloor(153 * mp + 2, 5) + 1;
+    const m = if (mp < 10) mp + 3 else mp - 9;
+    return .{ .year = yoe + era * 400 + @intFromBool(m <= 2), .month = @intCast(m), .day = @intCast(d) };
+}
+
+fn daysInYear(year: i64) i64 {
+    const leap = @mod(year, 4) == 0 and (@mod(year, 100) != 0 or @mod(year, 400) == 0);
+    return if (leap) 366 else 365;
+}
+
+fn daysInMonth(year: i64, month: u32) u32 {
+    const lengths = [_]u32{ 31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31 };
+    if (month == 2 and daysInYear(year) == 366) return 29;
+    return lengths[month - 1];
+}
+
+/// Parse "2015-03-05T23:51:47Z". The time, seconds, fraction and zone are
+/// optional; a space may replace the T, and +hh:mm, +hhmm or +hh offsets are
+/// subtracted so the result is UTC. A missing zone means UTC.
+fn parseIso8601(s: []const u8) ?Instant {
+    var pos: usize = 0;
+    const year = scanNumber(s, &pos, 4, 4) orelse return null;
+    if (!scanLiteral(s, &pos, '-')) return null;
+    const month = scanNumber(s, &pos, 2, 2) orelse return null;
+    if (!scanLiteral(s, &pos, '-')) return null;
+    const day = scanNumber(s, &pos, 2, 2) orelse return null;
+    if (month < 1 or month > 12 or day < 1 or day > daysInMonth(year, @intCast(month))) return null;
+
+    var secs = daysFromCivil(year, @intCast(month), @intCast(day)) * 86400;
+    var frac: f64 = 0;
+    if (pos < s.len and (s[pos] == 'T' or s[pos] == 't' or s[pos] == ' ')) {
+        pos += 1;
+        const hour = scanNumber(s, &pos, 2, 2) orelse return null;
+        if (!scanLiteral(s, &pos, ':')) return null;
+        const minute = scanNumber(s, &pos, 2, 2) orelse return null;
+        var second: i64 = 0;
+        if (scanLiteral(s, &pos, ':')) {
+            second = scanNumber(s, &pos, 2, 2) orelse return null;
+            if (scanLiteral(s, &pos, '.') or scanLiteral(s, &pos, ',')) {
+                var scale: f64 = 0.1;
+                const start = pos;
+                while (pos < s.len and std.ascii.isDigit(s[pos])) : (pos += 1) {
+                    frac += @as(f64, @floatFromInt(s[pos] - '0')) * scale;
+                    scale /= 10;
+                }
+                if (pos == start) return null;
+            }
+        }
+        if (hour > 23 or minute > 59 or second > 60) return null;
+        secs += hour * 3600 + minute * 60 + second;
+    }
+    if (pos < s.len) {
+        const offset = scanOffset(s[pos..]) orelse return null;
+        if (pos + offset.len != s.len) return null;
+        secs -= offset.secs;
+    }
+    return .{ .secs = secs, .frac = frac };
+}
+
+const Offset = struct { secs: i64, len: usize };
+
+/// A zone at the start of `s`: Z, or +hh:mm, +hhmm or +hh (- for west of UTC).
+fn scanOffset(s: []const u8) ?Offset {
+    if (s.len == 0) return null;
+    if (s[0] == 'Z' or s[0] == 'z') return .{ .secs = 0, .len = 1 };
+    if (s[0] != '+' and s[0] != '-') return null;
+    var pos: usize = 1;
+    const hours = scanNumber(s, &pos, 2, 2) orelse return null;
+    _ = scanLiteral(s, &pos, ':');
+    const minutes = scanNumber(s, &pos, 2, 2) orelse 0;
+    if (hours > 23 or minutes > 59) return null;
+    const secs = hours * 3600 + minutes * 60;
+    return .{ .secs = if (s[0] == '-') -secs else secs, .len = pos };
+}
+
+/// The offset for strftime's second argument: seconds east of UTC, or a zone
+/// string like "+05:30", "-0800", "Z" or "UTC".
+fn utcOffset(allocator: std.mem.Allocator, value: std.json.Value) EvalError!i64 {
+    switch (value) {
+        .integer => |i| if (i > -86400 and i < 86400) return i,
+        .string => |s| {
+            if (std.mem.eql(u8, s, "UTC") or std.mem.eql(u8, s, "GMT")) return 0;
+            if (scanOffset(s)) |offset| {
+                if (offset.len == s.len) return offset.secs;
             }
         },
-        .min => {
-            switch (value) {
-                .array => |arr| {
-                    if (arr.items.len == 0) return try EvalResult.single(allocator, .null);
-                    var min_val = arr.items[0];
-                    for (arr.items[1..]) |item| {
-                        if (jsonLessThan({}, item, min_val)) {
-                            min_val = item;
-                        }
-                    }
-                    return try EvalResult.single(allocator, min_val);
-                },
-                else => return EvalResult.empty(allocator),
+        else => {},
+    }
+    return raiseMessage(allocator, "Invalid UTC offset {s}; use seconds or a zone like \"+05:30\"", .{try describeValue(allocator, value)});
+}
+
+/// Read between min_len and max_len digits at `pos`.
+fn scanNumber(s: []const u8, pos: *usize, min_len: usize, max_len: usize) ?i64 {
+    var n: i64 = 0;
+    var len: usize = 0;
+    while (len < max_len and pos.* + len < s.len and std.ascii.isDigit(s[pos.* + len])) : (len += 1) {
+        n = n * 10 + (s[pos.* + len] - '0');
+    }
+    if (len < min_len) return null;
+    pos.* += len;
+    return n;
+}
+
+fn scanLiteral(s: []const u8, pos: *usize, c: u8) bool {
+    if (pos.* >= s.len or s[pos.*] != c) return false;
+    pos.* += 1;
+    return true;
+}
+
+/// Match a full or three-letter name, ignoring case; returns its index.
+fn scanName(s: []const u8, pos: *usize, names: []const []const u8) ?usize {
+    const rest = s[pos.*..];
+    for (names, 0..) |name, i| {
+        if (std.ascii.startsWithIgnoreCase(rest, name)) {
+            pos.* += name.len;
+            return i;
+        }
+    }
+    for (names, 0..) |name, i| {
+        if (std.ascii.startsWithIgnoreCase(rest, name[0..3])) {
+            pos.* += 3;
+            return i;
+        }
+    }
+    return null;
+}
+
+/// Fields gathered by strptime; unset ones default to 1900-01-01T00:00:00.
+const ParsedTime = struct {
+    year: i64 = 1900,
+    month: i64 = 1,
+    day: i64 = 1,
+    yday: ?i64 = null, // %j, used when no month or day was given
+    has_month_day: bool = false,
+    hour: i64 = 0,
+    minute: i64 = 0,
+    second: i64 = 0,
+    pm: ?bool = null,
+    offset: i64 = 0,
+    epoch: ?i64 = null, // %s overrides everything else
+};
+
+/// strptime: match `input` against `fmt`. A %z offset is folded in, so the
+/// result is UTC; %Z only accepts the zone name.
+fn parseTime(input: []const u8, fmt: []const u8) ?Instant {
+    var fields: ParsedTime = .{};
+    var pos: usize = 0;
+    if (!scanTime(&fields, input, &pos, fmt)) return null;
+    while (pos < input.len and std.ascii.isWhitespace(input[pos])) pos += 1;
+    if (pos != input.len) return null;
+
+    if (fields.epoch) |secs| return .{ .secs = secs };
+    if (fields.pm) |pm| fields.hour = @mod(fields.hour, 12) + @as(i64, if (pm) 12 else 0);
+    const days = if (fields.yday != null and !fields.has_month_day)
+        daysFromCivil(fields.year, 1, 1) + fields.yday.? - 1
+    else
+        daysFromCivil(fields.year, @intCast(fields.month), @intCast(fields.day));
+    return .{ .secs = days * 86400 + fields.hour * 3600 + fields.minute * 60 + fields.second - fields.offset };
+}
+
+fn scanTime(fields: *ParsedTime, input: []const u8, pos: *usize, fmt: []const u8) bool {
+    var i: usize = 0;
+    while (i < fmt.len) : (i += 1) {
+        const c = fmt[i];
+        // Whitespace in the format matches any run of whitespace, including none
+        if (std.ascii.isWhitespace(c)) {
+            while (pos.* < input.len and std.ascii.isWhitespace(input[pos.*])) pos.* += 1;
+            continue;
+        }
+        if (c != '%' or i + 1 == fmt.len) {
+            if (!scanLiteral(input, pos, c)) return false;
+            continue;
+        }
+        i += 1;
+        const spec = fmt[i];
+        // Numeric fields may be space-padded
+        if (std.mem.indexOfScalar(u8, "YmdeHIMSjy", spec) != null) {
+            while (pos.* < input.len and input[pos.*] == ' ') pos.* += 1;
+        }
+        switch (spec) {
+            'Y' => fields.year = scanNumber(input, pos, 1, 4) orelse return false,
+            'y' => {
+                const yy = scanNumber(input, pos, 1, 2) orelse return false;
+                fields.year = if (yy < 69) 2000 + yy else 1900 + yy;
+            },
+            'm' => {
+                fields.month = scanNumber(input, pos, 1, 2) orelse return false;
+                if (fields.month < 1 or fields.month > 12) return false;
+                fields.has_month_day = true;
+            },
+            'b', 'B', 'h' => {
+                fields.month = @as(i64, @intCast(scanName(input, pos, &month_names) orelse return false)) + 1;
+                fields.has_month_day = true;
+            },
+            'd', 'e' => {
+                fields.day = scanNumber(input, pos, 1, 2) orelse return false;
+                if (fields.day < 1 or fields.day > 31) return false;
+                fields.has_month_day = true;
+            },
+            'j' => {
+                const yday = scanNumber(input, pos, 1, 3) orelse return false;
+                if (yday < 1 or yday > 366) return false;
+                fields.yday = yday;
+            },
+            'H' => {
+                fields.hour = scanNumber(input, pos, 1, 2) orelse return false;
+                if (fields.hour > 23) return false;
+            },
+            'I' => {
+                fields.hour = scanNumber(input, pos, 1, 2) orelse return false;
+                if (fields.hour < 1 or fields.hour > 12) return false;
+            },
+            'M' => {
+                fields.minute = scanNumber(input, pos, 1, 2) orelse return false;
+                if (fields.minute > 59) return false;
+            },
+            'S' => {
+                fields.second = scanNumber(input, pos, 1, 2) orelse return false;
+                if (fields.second > 60) return false;
+            },
+            'p' => {
+                const rest = input[pos.*..];
+                if (std.ascii.startsWithIgnoreCase(rest, "am")) {
+                    fields.pm = false;
+                } else if (std.ascii.startsWithIgnoreCase(rest, "pm")) {
+                    fields.pm = true;
+                } else return false;
+                pos.* += 2;
+            },
+            'a', 'A' => _ = scanName(input, pos, &weekday_names) orelse return false,
+            'z' => {
+                const offset = scanOffset(input[pos.*..]) orelse return false;
+                fields.offset = offset.secs;
+                pos.* += offset.len;
+            },
+            'Z' => {
+                const start = pos.*;
+                while (pos.* < input.len and std.ascii.isAlphabetic(input[pos.*])) pos.* += 1;
+                if (pos.* == start) return false;
+            },
+            's' => {
+                const negative = scanLiteral(input, pos, '-');
+                const secs = scanNumber(input, pos, 1, 15) orelse return false;
+                fields.epoch = if (negative) -secs else secs;
+            },
+            'F' => if (!scanTime(fields, input, pos, "%Y-%m-%d")) return false,
+            'T' => if (!scanTime(fields, input, pos, "%H:%M:%S")) return false,
+            'R' => if (!scanTime(fields, input, pos, "%H:%M")) return false,
+            'D' => if (!scanTime(fields, input, pos, "%m/%d/%y")) return false,
+            'n', 't' => {
+                while (pos.* < input.len and std.ascii.isWhitespace(input[pos.*])) pos.* += 1;
+            },
+            '%' => if (!scanLiteral(input, pos, '%')) return false,
+            else => return false,
+        }
+    }
+    return true;
+}
+
+/// strftime: render `t` shifted by `offset` seconds east of UTC.
+fn formatTime(allocator: std.mem.Allocator, fmt: []const u8, t: Instant, offset: i64) EvalError![]const u8 {
+    var out: std.ArrayListUnmanaged(u8) = .empty;
+    try appendTime(allocator, &out, fmt, civilFromInstant(.{ .secs = t.secs + offset, .frac = t.frac }), t.secs, offset);
+    return out.toOwnedSlice(allocator);
+}
+
+fn appendTime(allocator: std.mem.Allocator, out: *std.ArrayListUnmanaged(u8), fmt: []const u8, c: Civil, epoch_secs: i64, offset: i64) EvalError!void {
+    var i: usize = 0;
+    while (i < fmt.len) : (i += 1) {
+        if (fmt[i] != '%' or i + 1 == fmt.len) {
+            try out.append(allocator, fmt[i]);
+            continue;
+        }
+        i += 1;
+        switch (fmt[i]) {
+            'Y' => if (c.year >= 0 and c.year <= 9999) try out.print(allocator, "{d:0>4}", .{@as(u64, @intCast(c.year))}) else try out.print(allocator, "{d}", .{c.year}),
+            'C' => try out.print(allocator, "{d:0>2}", .{@as(u64, @intCast(@max(@divFloor(c.year, 100), 0)))}),
+            'y' => try out.print(allocator, "{d:0>2}", .{@as(u64, @intCast(@mod(c.year, 100)))}),
+            'm' => try out.print(allocator, "{d:0>2}", .{c.month}),
+            'd' => try out.print(allocator, "{d:0>2}", .{c.day}),
+            'e' => try out.print(allocator, "{d: >2}", .{c.day}),
+            'j' => try out.print(allocator, "{d:0>3}", .{c.yday + 1}),
+            'H' => try out.print(allocator, "{d:0>2}", .{c.hour}),
+            'I' => try out.print(allocator, "{d:0>2}", .{(c.hour + 11) % 12 + 1}),
+            'M' => try out.print(allocator, "{d:0>2}", .{c.minute}),
+            'S' => try out.print(allocator, "{d:0>2}", .{c.second}),
+            'p' => try out.appendSlice(allocator, if (c.hour < 12) "AM" else "PM"),
+            'a' => try out.appendSlice(allocator, weekday_names[c.wday][0..3]),
+            'A' => try out.appendSlice(allocator, weekday_names[c.wday]),
+            'b', 'h' => try out.appendSlice(allocator, month_names[c.month - 1][0..3]),
+            'B' => try out.appendSlice(allocator, month_names[c.month - 1]),
+            'u' => try out.print(allocator, "{d}", .{if (c.wday == 0) 7 else c.wday}),
+            'w' => try out.print(allocator, "{d}", .{c.wday}),
+            'U' => try out.print(allocator, "{d:0>2}", .{(c.yday + 7 - c.wday) / 7}),
+            'W' => try out.print(allocator, "{d:0>2}", .{(c.yday + 7 - (c.wday + 6) % 7) / 7}),
+            'G' => try out.print(allocator, "{d}", .{isoWeek(c).year}),
+            'V' => try out.print(allocator, "{d:0>2}", .{isoWeek(c).week}),
+            's' => try out.print(allocator, "{d}", .{epoch_secs}),
+            'z' => {
+                const east: u64 = @abs(offset);
+                try out.print(allocator, "{c}{d:0>2}{d:0>2}", .{ @as(u8, if (offset < 0) '-' else '+'), east / 3600, east / 60 % 60 });
+            },
+            'Z' => if (offset == 0) try out.appendSlice(allocator, "UTC") else try appendTime(allocator, out, "%z", c, epoch_secs, offset),
+            'F' => try appendTime(allocator, out, "%Y-%m-%d", c, epoch_secs, offset),
+            'T', 'X' => try appendTime(allocator, out, "%H:%M:%S", c, epoch_secs, offset),
+            'R' => try appendTime(allocator, out, "%H:%M", c, epoch_secs, offset),
+            'D', 'x' => try appendTime(allocator, out, "%m/%d/%y", c, epoch_secs, offset),
+            'c' => try appendTime(allocator, out, "%a %b %e %H:%M:%S %Y", c, epoch_secs, offset),
+            'n' => try out.append(allocator, '\n'),
+            't' => try out.append(allocator, '\t'),
+            '%' => try out.append(allocator, '%'),
+            else => try out.appendSlice(allocator, fmt[i - 1 .. i + 1]),
+        }
+    }
+}
+
+/// ISO 8601 week-numbering year and week (weeks start on Monday; week 1 has
+/// the year's first Thursday).
+fn isoWeek(c: Civil) struct { year: i64, week: u32 } {
+    const thursday = @as(i64, c.yday) - @as(i64, (c.wday + 6) % 7) + 3;
+    if (thursday < 0) return .{ .year = c.year - 1, .week = @intCast(@divFloor(thursday + daysInYear(c.year - 1), 7) + 1) };
+    if (thursday >= daysInYear(c.year)) return .{ .year = c.year + 1, .week = 1 };
+    return .{ .year = c.year, .week = @intCast(@divFloor(thursday, 7) + 1) };
+}
+
+fn dateUnit(allocator: std.mem.Allocator, unit: std.json.Value) EvalError!DateUnit {
+    if (unit == .string) {
+        const name = unit.string;
+        // "hours" and "hour" both work
+        const singular = if (name.len > 1 and name[name.len - 1] == 's') name[0 .. name.len - 1] else name;
+        if (std.meta.stringToEnum(DateUnit, singular)) |u| return u;
+    }
+    return raiseMessage(allocator, "Unknown date unit {s}; use second, minute, hour, day, week, month or year", .{try describeValue(allocator, unit)});
+}
+
+fn unitSeconds(unit: DateUnit) i64 {
+    return switch (unit) {
+        .second => 1,
+        .minute => 60,
+        .hour => 3600,
+        .day => 86400,
+        .week => 7 * 86400,
+        .month, .year => unreachable,
+    };
+}
+
+/// Round down to the start of the unit; weeks start on Monday.
+fn truncateTime(t: Instant, unit: DateUnit) Instant {
+    switch (unit) {
+        .second => return .{ .secs = t.secs },
+        .minute, .hour, .day => return .{ .secs = t.secs - @mod(t.secs, unitSeconds(unit)) },
+        .week => {
+            const days = @divFloor(t.secs, 86400);
+            // 1970-01-01 was a Thursday
+            return .{ .secs = (days - @mod(days + 3, 7)) * 86400 };
+        },
+        .month, .year => {
+            const date = civilFromDays(@divFloor(t.secs, 86400));
+            const month: u32 = if (unit == .month) date.month else 1;
+            return .{ .secs = daysFromCivil(date.year, month, 1) * 86400 };
+        },
+    }
+}
+
+/// Move `t` by `amount` units. Months and years need whole amounts and keep
+/// the time of day and the day of month, clamped to the target month's length.
+fn addTime(allocator: std.mem.Allocator, t: Instant, unit: DateUnit, amount: std.json.Value, subtract: bool) EvalError!Instant {
+    const n = getNumeric(amount) orelse return raiseMessage(allocator, "Date amount must be a number, not {s}", .{try describeValue(allocator, amount)});
+    const signed = if (subtract) -n else n;
+    const limit: f64 = @floatFromInt(max_epoch_seconds);
+
+    if (unit == .month or unit == .year) {
+        if (signed != @floor(signed) or @abs(signed) > limit / (365 * 86400)) {
+            return raiseMessage(allocator, "Months and years must be added in whole numbers, not {s}", .{try describeValue(allocator, amount)});
+        }
+        const months = @as(i64, @intFromFloat(signed)) * @as(i64, if (unit == .year) 12 else 1);
+        const c = civilFromInstant(t);
+        const index = c.year * 12 + (c.month - 1) + months;
+        const year = @divFloor(index, 12);
+        const month: u32 = @intCast(@mod(index, 12) + 1);
+        const day = @min(c.day, daysInMonth(year, month));
+        const secs = daysFromCivil(year, month, day) * 86400 + @as(i64, c.hour) * 3600 + @as(i64, c.minute) * 60 + c.second;
+        if (secs > max_epoch_seconds or secs < -max_epoch_seconds) return dateOutOfRange(allocator, amount);
+        return .{ .secs = secs, .frac = t.frac };
+    }
+
+    const step: f64 = @floatFromInt(unitSeconds(unit));
+    if (amount == .integer and @abs(signed) * step <= limit) {
+        const secs = t.secs + @as(i64, @intFromFloat(signed)) * unitSeconds(unit);
+        if (secs > max_epoch_seconds or secs < -max_epoch_seconds) return dateOutOfRange(allocator, amount);
+        return .{ .secs = secs, .frac = t.frac };
+    }
+    return instantFromFloat(allocator, @as(f64, @floatFromInt(t.secs)) + t.frac + signed * step);
+}
+
+// ============================================================================
+// Paths and Assignment
+// ============================================================================
+
+/// A location in the input: the path from the root and the value found there.
+const PathValue = struct {
+    path: []const std.json.Value,
+    value: std.json.Value,
+};
+
+/// Evaluate `expr` as a path expression (an assignment target, the argument
+/// of path() or del()), appending every location it selects below `at`.
+/// Expressions that compute new values rather than select existing ones,
+/// like literals or arithmetic, fail with error.InvalidPath.
+fn evalPaths(allocator: std.mem.Allocator, expr: *const Expr, at: PathValue, env: ?*const Env, out: *std.ArrayListUnmanaged(PathValue)) EvalError!void {
+    switch (expr.*) {
+        .identity => try out.append(allocator, at),
+        .field => |f| {
+            const next = (try stepPath(allocator, at, .{ .string = f.name })) orelse return;
+            if (f.index) |idx| return indexPaths(allocator, next, idx, out);
+            try out.append(allocator, next);
+        },
+        .path => |p| {
+            var cur = at;
+            for (p.parts) |part| {
+                cur = (try stepPath(allocator, cur, .{ .string = part })) orelse return;
             }
+            if (p.index) |idx| return indexPaths(allocator, cur, idx, out);
+            try out.append(allocator, cur);
         },
-        .max => {
-            switch (value) {
-                .array => |arr| {
-                    if (arr.items.len == 0) return try EvalResult.single(allocator, .null);
-                    var max_val = arr.items[0];
-                    for (arr.items[1..]) |item| {
-                        if (jsonLessThan({}, max_val, item)) {
-                            max_val = item;
-                        }
-                    }
-                    return try EvalResult.single(allocator, max_val);
-                },
-                else => return EvalResult.empty(allocator),
+        .iterate => |it| {
+            var cur = at;
+            for (it.path) |part| {
+                cur = (try stepPath(allocator, cur, .{ .string = part })) orelse return;
             }
+            try indexPaths(allocator, cur, .iterate, out);
         },
-        // Sprint 03: String functions (no args)
-        .ascii_downcase => {
-            switch (value) {
-                .string => |s| {
-                    var lower = try allocator.alloc(u8, s.len);
-                    for (s, 0..) |c, i| {
-                        lower[i] = std.ascii.toLower(c);
-                    }
-                    return try EvalResult.single(allocator, .{ .string = lower });
-                },
-                else => return EvalResult.empty(allocator),
+        .pipe => |p| {
+            var left: std.ArrayListUnmanaged(PathValue) = .empty;
+            try evalPaths(allocator, p.left, at, env, &left);
+            for (left.items) |loc| {
+                try evalPaths(allocator, p.right, loc, env, out);
             }
         },
-        .ascii_upcase => {
-            switch (value) {
-                .string => |s| {
-                    var upper = try allocator.alloc(u8, s.len);
-                    for (s, 0..) |c, i| {
-                        upper[i] = std.ascii.toUpper(c);
-                    }
-                    return try EvalResult.single(allocator, .{ .string = upper });
-                },
-                else => return EvalResult.empty(allocator),
+        .select => |cond| {
+            if (try evalConditionWithEnv(allocator, cond, at.value, env)) try out.append(allocator, at);
+        },
+        .conditional => |c| {
+            const branch = if (try evalConditionWithEnv(allocator, c.condition, at.value, env)) c.then_branch else c.else_branch;
+            try evalPaths(allocator, branch, at, env, out);
+        },
+        .alternative => |alt| {
+            // Paths of the primary whose values are truthy, else the fallback's
+            var primary: std.ArrayListUnmanaged(PathValue) = .empty;
+            try evalPaths(allocator, alt.primary, at, env, &primary);
+            const before = out.items.len;
+            for (primary.items) |loc| {
+                if (isTruthy(loc.value)) try out.append(allocator, loc);
             }
+            if (out.items.len == before) try evalPaths(allocator, alt.fallback, at, env, out);
         },
-
-        .to_entries => {
-            switch (value) {
-                .object => |obj| {
-                    var entries = std.json.Array.init(allocator);
-                    var it = obj.iterator();
-                    while (it.next()) |entry| {
-                        var entry_obj = std.json.ObjectMap.init(allocator);
-                        try entry_obj.put("key", .{ .string = entry.key_ptr.* });
-                        try entry_obj.put("value", entry.value_ptr.*);
-                        try entries.append(.{ .object = entry_obj });
-                    }
-                    return try EvalResult.single(allocator, .{ .array = entries });
-                },
-                else => return EvalResult.empty(allocator),
+        .bind => |b| {
+            const sources = try evalExprWithEnv(allocator, b.source, at.value, env);
+            for (sources.values) |source_val| {
+                const scope = (try destructure(allocator, b.pattern, source_val, env)) orelse continue;
+                try evalPaths(allocator, b.body, at, scope, out);
             }
         },
-
-        .from_entries => {
-            switch (value) {
-                .array => |arr| {
-                    var result_obj = std.json.ObjectMap.init(allocator);
-                    for (arr.items) |item| {
-                        switch (item) {
-                            .object => |entry_obj| {
-                                // Support {key, value}, {k, v}, and {name, value} forms
-                                const key_val = entry_obj.get("key") orelse
-                                    entry_obj.get("k") orelse
-                                    entry_obj.get("name") orelse continue;
-                                const val = entry_obj.get("value") orelse
-                                    entry_obj.get("v") orelse continue;
-                                switch (key_val) {
-                                    .string => |key| {
-                                        try result_obj.put(key, val);
-                                    },
-                                    else => {},
-                                }
-                            },
-                            else => {},
-                        }
-                    }
-                    return try EvalResult.single(allocator, .{ .object = result_obj });
-                },
-                else => return EvalResult.empty(allocator),
+        .define => |d| {
+            const frame = try allocator.create(Env);
+            frame.* = .{ .parent = env, .name = d.def.name, .closure = .{ .params = d.def.params, .body = d.def.body, .env = frame } };
+            try evalPaths(allocator, d.rest, at, frame, out);
+        },
+        .call => |call| {
+            const closure = Env.lookupClosure(env, call.name, call.args.len) orelse return;
+            var scopes: std.ArrayListUnmanaged(?*const Env) = .empty;
+            try bindArgs(allocator, closure, call, 0, closure.env, at.value, env, &scopes);
+            for (scopes.items) |scope| {
+                try evalPaths(allocator, closure.body, at, scope, out);
             }
         },
-        // Sprint 05: Math functions
-        .floor => {
-            switch (value) {
-                .integer => return try EvalResult.single(allocator, value),
-                .float => |f| {
-                    const floored = @floor(f);
-                    // Return as integer if it fits
-                    if (floored >= @as(f64, @floatFromInt(std.math.minInt(i64))) and
-                        floored <= @as(f64, @floatFromInt(std.math.maxInt(i64))))
-                    {
-                        return try EvalResult.single(allocator, .{ .integer = @as(i64, @intFromFloat(floored)) });
-                    }
-                    return try EvalResult.single(allocator, .{ .float = floored });
-                },
-                else => return EvalResult.empty(allocator),
+        .builtin => |b| switch (b.kind) {
+            .first => try indexPaths(allocator, at, .{ .single = 0 }, out),
+            .last => try indexPaths(allocator, at, .{ .single = -1 }, out),
+            .empty => {},
+            else => return error.InvalidPath,
+        },
+        .generator => |gen| switch (gen.kind) {
+            .recurse => try recursePaths(allocator, gen.args, at, env, out),
+            else => return error.InvalidPath,
+        },
+        .path_func => |pf| {
+            if (pf.kind != .getpath) return error.InvalidPath;
+            const paths = try evalExprWithEnv(allocator, pf.args[0], at.value, env);
+            for (paths.values) |p| {
+                if (p != .array) return error.InvalidPath;
+                const full = try std.mem.concat(allocator, std.json.Value, &.{ at.path, p.array.items });
+                try out.append(allocator, .{ .path = full, .value = getPathValue(at.value, p.array.items) });
             }
         },
-        .ceil => {
-            switch (value) {
-                .integer => return try EvalResult.single(allocator, value),
-                .float => |f| {
-                    const ceiled = @ceil(f);
-                    if (ceiled >= @as(f64, @floatFromInt(std.math.minInt(i64))) and
-                        ceiled <= @as(f64, @floatFromInt(std.math.maxInt(i64))))
-                    {
-                        return try EvalResult.single(allocator, .{ .integer = @as(i64, @intFromFloat(ceiled)) });
-                    }
-                    return try EvalResult.single(allocator, .{ .float = ceiled });
-                },
-                else => return EvalResult.empty(allocator),
-            }
+        else => return error.InvalidPath,
+    }
+}
+
+/// Step from `at` into an object key or array index. Missing members and
+/// null parents read as null (setpath creates them); other shapes select nothing.
+fn stepPath(allocator: std.mem.Allocator, at: PathValue, key: std.json.Value) EvalError!?PathValue {
+    const child: std.json.Value = switch (key) {
+        .string => |name| switch (at.value) {
+            .object => |obj| obj.get(name) orelse .null,
+            .null => .null,
+            else => return null,
         },
-        .round => {
-            switch (value) {
-                .integer => return try EvalResult.single(allocator, value),
-                .float => |f| {
-                    const rounded = @round(f);
-                    if (rounded >= @as(f64, @floatFromInt(std.math.minInt(i64))) and
-                        rounded <= @as(f64, @floatFromInt(std.math.maxInt(i64))))
-                    {
-                        return try EvalResult.single(allocator, .{ .integer = @as(i64, @intFromFloat(rounded)) });
-                    }
-                    return try EvalResult.single(allocator, .{ .float = rounded });
-                },
-                else => return EvalResult.empty(allocator),
-            }
+        .integer => |i| switch (at.value) {
+            .array => getIndex(at.value, i) orelse .null,
+            .null => .null,
+            else => return null,
         },
-        .fabs, .abs => {
-            switch (value) {
-                .integer => |i| {
-                    // Handle minInt(i64) specially to avoid overflow
-                    // since -minInt(i64) cannot be represented as i64
-                    if (i == std.math.minInt(i64)) {
-                        // Return as float since the absolute value exceeds i64 max
-                        return try EvalResult.single(allocator, .{ .float = @as(f64, @floatFromInt(std.math.maxInt(i64))) + 1.0 });
-                    }
-                    const abs_val = if (i < 0) -i else i;
-                    return try EvalResult.single(allocator, .{ .integer = abs_val });
-                },
-                .float => |f| {
-                    return try EvalResult.single(allocator, .{ .float = @abs(f) });
-                },
-                else => return EvalResult.empty(allocator),
-            }
+        else => return null,
+    };
+    const path = try allocator.alloc(std.json.Value, at.path.len + 1);
+    @memcpy(path[0..at.path.len], at.path);
+    path[at.path.len] = key;
+    return .{ .path = path, .value = child };
+}
+
+fn indexPaths(allocator: std.mem.Allocator, at: PathValue, index: IndexExpr, out: *std.ArrayListUnmanaged(PathValue)) EvalError!void {
+    switch (index) {
+        .single => |i| {
+            const next = (try stepPath(allocator, at, .{ .integer = i })) orelse return;
+            try out.append(allocator, next);
         },
-        // Sprint 07: More math functions
-        .exp => {
-            const f = switch (value) {
-                .integer => |i| @as(f64, @floatFromInt(i)),
-                .float => |f| f,
-                else => return EvalResult.empty(allocator),
-            };
-            return try EvalResult.single(allocator, .{ .float = @exp(f) });
+        .iterate => switch (at.value) {
+            .array => |arr| {
+                for (0..arr.items.len) |i| {
+                    try out.append(allocator, (try stepPath(allocator, at, .{ .integer = @intCast(i) })).?);
+                }
+            },
+            .object => |obj| {
+                for (obj.keys()) |key| {
+                    try out.append(allocator, (try stepPath(allocator, at, .{ .string = key })).?);
+                }
+            },
+            else => {},
         },
-        .ln => {
-            const f = switch (value) {
-                .integer => |i| @as(f64, @floatFromInt(i)),
-                .float => |f| f,
-                else => return EvalResult.empty(allocator),
+        // Slices would need {start, end} path elements
+        .slice => return error.InvalidPath,
+    }
+}
+
+/// Resolve a possibly negative index against a length; null if out of range.
+fn resolveIndex(index: i64, len: usize) ?usize {
+    if (index >= 0) {
+        const i: usize = @intCast(index);
+        return if (i < len) i else null;
+    }
+    const back = @abs(index);
+    return if (back <= len) len - @as(usize, @intCast(back)) else null;
+}
+
+/// getpath: the value at `path`, or null where it doesn't exist.
+fn getPathValue(value: std.json.Value, path: []const std.json.Value) std.json.Value {
+    var cur = value;
+    for (path) |key| {
+        cur = switch (key) {
+            .string => |name| switch (cur) {
+                .object => |obj| obj.get(name) orelse return .null,
+                else => return .null,
+            },
+            .integer => |i| switch (cur) {
+                .array => |arr| arr.items[resolveIndex(i, arr.items.len) orelse return .null],
+                else => return .null,
+            },
+            else => return .null,
+        };
+    }
+    return cur;
+}
+
+/// setpath: a copy of `value` with `new` at `path`, creating objects and
+/// null-padded arrays along the way. The input is never modified.
+fn setPath(allocator: std.mem.Allocator, value: std.json.Value, path: []const std.json.Value, new: std.json.Value) EvalError!std.json.Value {
+    if (path.len == 0) return new;
+    switch (path[0]) {
+        .string => |key| {
+            var obj = switch (value) {
+                .object => |o| try o.cloneWithAllocator(allocator),
+                .null => std.json.ObjectMap.init(allocator),
+                else => return error.InvalidPath,
             };
-            if (f <= 0) return try EvalResult.single(allocator, .null);
-            return try EvalResult.single(allocator, .{ .float = @log(f) });
+            const child = obj.get(key) orelse .null;
+            try obj.put(key, try setPath(allocator, child, path[1..], new));
+            return .{ .object = obj };
         },
-        .log10 => {
-            const f = switch (value) {
-                .integer => |i| @as(f64, @floatFromInt(i)),
-                .float => |f| f,
-                else => return EvalResult.empty(allocator),
-            };
-            if (f <= 0) return try EvalResult.single(allocator, .null);
-            return try EvalResult.single(allocator, .{ .float = std.math.log10(f) });
+        .integer => |i| {
+            var items: std.ArrayListUnmanaged(std.json.Value) = .empty;
+            switch (value) {
+                .array => |arr| try items.appendSlice(allocator, arr.items),
+                .null => {},
+                else => return error.InvalidPath,
+            }
+            const idx: usize = if (i >= 0) @intCast(i) else resolveIndex(i, items.items.len) orelse return error.InvalidPath;
+            while (items.items.len <= idx) try items.append(allocator, .null);
+            items.items[idx] = try setPath(allocator, items.items[idx], path[1..], new);
+            const slice = try items.toOwnedSlice(allocator);
+            return .{ .array = .{ .items = slice, .capacity = slice.len, .allocator = allocator } };
         },
-        .log2 => {
-            const f = switch (value) {
-                .integer => |i| @as(f64, @floatFromInt(i)),
-                .float => |f| f,
-                else => return EvalResult.empty(allocator),
-            };
-            if (f <= 0) return try EvalResult.single(allocator, .null);
-            return try EvalResult.single(allocator, .{ .float = std.math.log2(f) });
+        else => return error.InvalidPath,
+    }
+}
+
+/// delpaths: delete every path, deepest and last first so earlier deletions
+/// don't shift the array indexes of later ones.
+fn deletePaths(allocator: std.mem.Allocator, value: std.json.Value, paths: [][]const std.json.Value) EvalError!std.json.Value {
+    std.mem.sort([]const std.json.Value, paths, {}, pathLessThan);
+    var result = value;
+    var i = paths.len;
+    while (i > 0) {
+        i -= 1;
+        if (i + 1 < paths.len and pathsEqual(paths[i], paths[i + 1])) continue;
+        result = try deletePath(allocator, result, paths[i]);
+    }
+    return result;
+}
+
+fn deletePath(allocator: std.mem.Allocator, value: std.json.Value, path: []const std.json.Value) EvalError!std.json.Value {
+    if (path.len == 0) return .null;
+    switch (value) {
+        .null => return .null,
+        .object => |obj| {
+            if (path[0] != .string) return error.InvalidPath;
+            const key = path[0].string;
+            const child = obj.get(key) orelse return value;
+            var copy = try obj.cloneWithAllocator(allocator);
+            if (path.len == 1) {
+                _ = copy.orderedRemove(key);
+            } else {
+                try copy.put(key, try deletePath(allocator, child, path[1..]));
+            }
+            return .{ .object = copy };
         },
-        .sqrt => {
-            const f = switch (value) {
-                .integer => |i| @as(f64, @floatFromInt(i)),
-                .float => |f| f,
-                else => return EvalResult.empty(allocator),
-            };
-            if (f < 0) return try EvalResult.single(allocator, .null);
-            return try EvalResult.single(allocator, .{ .float = @sqrt(f) });
+        .array => |arr| {
+            if (path[0] != .integer) return error.InvalidPath;
+            const idx = resolveIndex(path[0].integer, arr.items.len) orelse return value;
+            var items: std.ArrayListUnmanaged(std.json.Value) = .empty;
+            try items.appendSlice(allocator, arr.items);
+            if (path.len == 1) {
+                _ = items.orderedRemove(idx);
+            } else {
+                items.items[idx] = try deletePath(allocator, items.items[idx], path[1..]);
+            }
+            const slice = try items.toOwnedSlice(allocator);
+            return .{ .array = .{ .items = slice, .capacity = slice.len, .allocator = allocator } };
         },
-        // Sprint 07: Trigonometry functions
-        .sin => {
-            const f = switch (value) {
-                .integer => |i| @as(f64, @floatFromInt(i)),
-                .float => |f| f,
-                else => return EvalResult.empty(allocator),
+        else => return error.InvalidPath,
+    }
+}
+
+fn pathLessThan(_: void, a: []const std.json.Value, b: []const std.json.Value) bool {
+    for (a[0..@min(a.len, b.len)], b[0..@min(a.len, b.len)]) |ak, bk| {
+        if (jsonLessThan({}, ak, bk)) return true;
+        if (jsonLessThan({}, bk, ak)) return false;
+    }
+    return a.len < b.len;
+}
+
+fn pathsEqual(a: []const std.json.Value, b: []const std.json.Value) bool {
+    if (a.len != b.len) return false;
+    for (a, b) |ak, bk| {
+        if (!jsonEqual(ak, bk)) return false;
+    }
+    return true;
+}
+
+/// jq truthiness: everything except false and null.
+fn isTruthy(value: std.json.Value) bool {
+    return switch (value) {
+        .null => false,
+        .bool => |b| b,
+        else => true,
+    };
+}
+
+fn pathArray(allocator: std.mem.Allocator, path: []const std.json.Value) EvalError!std.json.Value {
+    const items = try allocator.dupe(std.json.Value, path);
+    return .{ .array = .{ .items = items, .capacity = items.len, .allocator = allocator } };
+}
+
+fn evalAssign(allocator: std.mem.Allocator, assign: AssignExpr, value: std.json.Value, env: ?*const Env) EvalError!EvalResult {
+    var locations: std.ArrayListUnmanaged(PathValue) = .empty;
+    try evalPaths(allocator, assign.target, .{ .path = &.{}, .value = value }, env, &locations);
+
+    if (assign.op == .update) {
+        // |= runs on each selected value; the first output replaces it and
+        // no output deletes it (jq 1.7.1)
+        var result = value;
+        var doomed: std.ArrayListUnmanaged([]const std.json.Value) = .empty;
+        for (locations.items) |loc| {
+            const updated = try evalExprWithEnv(allocator, assign.rhs, getPathValue(result, loc.path), env);
+            if (updated.values.len == 0) {
+                try doomed.append(allocator, loc.path);
+                continue;
+            }
+            result = try setPath(allocator, result, loc.path, updated.values[0]);
+        }
+        if (doomed.items.len > 0) result = try deletePaths(allocator, result, doomed.items);
+        return try EvalResult.single(allocator, result);
+    }
+
+    // The right side sees the original input; each of its outputs yields a result
+    const rhs = try evalExprWithEnv(allocator, assign.rhs, value, env);
+    const results = try allocator.alloc(std.json.Value, rhs.values.len);
+    for (rhs.values, results) |rhs_val, *result| {
+        result.* = value;
+        for (locations.items) |loc| {
+            const current = getPathValue(result.*, loc.path);
+            const new: std.json.Value = switch (assign.op) {
+                .set => rhs_val,
+                .alt => if (isTruthy(current)) current else rhs_val,
+                .add => try arithValues(allocator, .add, current, rhs_val),
+                .sub => try arithValues(allocator, .sub, current, rhs_val),
+                .mul => try arithValues(allocator, .mul, current, rhs_val),
+                .div => try arithValues(allocator, .div, current, rhs_val),
+                .mod => try arithValues(allocator, .mod, current, rhs_val),
+                .update => unreachable,
             };
-            return try EvalResult.single(allocator, .{ .float = @sin(f) });
+            result.* = try setPath(allocator, result.*, loc.path, new);
+        }
+    }
+    return EvalResult.multi(allocator, results);
+}
+
+fn evalPathFunc(allocator: std.mem.Allocator, pf: PathFuncExpr, value: std.json.Value, env: ?*const Env) EvalError!EvalResult {
+    var results: std.ArrayListUnmanaged(std.json.Value) = .empty;
+    switch (pf.kind) {
+        .path => {
+            var locations: std.ArrayListUnmanaged(PathValue) = .empty;
+            try evalPaths(allocator, pf.args[0], .{ .path = &.{}, .value = value }, env, &locations);
+            for (locations.items) |loc| {
+                try results.append(allocator, try pathArray(allocator, loc.path));
+            }
         },
-        .cos => {
-            const f = switch (value) {
-                .integer => |i| @as(f64, @floatFromInt(i)),
-                .float => |f| f,
-                else => return EvalResult.empty(allocator),
-            };
-            return try EvalResult.single(allocator, .{ .float = @cos(f) });
+        .paths, .leaf_paths => {
+            var locations: std.ArrayListUnmanaged(PathValue) = .empty;
+            try collectPaths(allocator, .{ .path = &.{}, .value = value }, &locations);
+            for (locations.items) |loc| {
+                if (pf.kind == .leaf_paths and (loc.value == .array or loc.value == .object)) continue;
+                if (pf.args.len == 1) {
+                    const keep = try evalExprWithEnv(allocator, pf.args[0], loc.value, env);
+                    if (keep.values.len == 0) continue;
+                }
+                try results.append(allocator, try pathArray(allocator, loc.path));
+            }
         },
-        .tan => {
-            const f = switch (value) {
-                .integer => |i| @as(f64, @floatFromInt(i)),
-                .float => |f| f,
-                else => return EvalResult.empty(allocator),
-            };
-            return try EvalResult.single(allocator, .{ .float = @tan(f) });
+        .getpath => {
+            const paths = try evalExprWithEnv(allocator, pf.args[0], value, env);
+            for (paths.values) |p| {
+                if (p != .array) continue;
+                try results.append(allocator, getPathValue(value, p.array.items));
+            }
         },
-        .asin => {
-            const f = switch (value) {
-                .integer => |i| @as(f64, @floatFromInt(i)),
-                .float => |f| f,
-                else => return EvalResult.empty(allocator),
-            };
-            // asin is only valid for [-1, 1]
-            if (f < -1 or f > 1) return try EvalResult.single(allocator, .null);
-            return try EvalResult.single(allocator, .{ .float = std.math.asin(f) });
+        .setpath => {
+            const paths = try evalExprWithEnv(allocator, pf.args[0], value, env);
+            const news = try evalExprWithEnv(allocator, pf.args[1], value, env);
+            for (paths.values) |p| {
+                if (p != .array) continue;
+                for (news.values) |new| {
+                    try results.append(allocator, try setPath(allocator, value, p.array.items, new));
+                }
+            }
         },
-        .acos => {
-            const f = switch (value) {
-                .integer => |i| @as(f64, @floatFromInt(i)),
-                .float => |f| f,
-                else => return EvalResult.empty(allocator),
-            };
-            // acos is only valid for [-1, 1]
-            if (f < -1 or f > 1) return try EvalResult.single(allocator, .null);
-            return try EvalResult.single(allocator, .{ .float = std.math.acos(f) });
+        .delpaths => {
+            const lists = try evalExprWithEnv(allocator, pf.args[0], value, env);
+            for (lists.values) |list| {
+                if (list != .array) continue;
+                const paths = try allocator.alloc([]const std.json.Value, list.array.items.len);
+                for (list.array.items, paths) |p, *path| {
+                    if (p != .array) return error.InvalidPath;
+                    path.* = p.array.items;
+                }
+                try results.append(allocator, try deletePaths(allocator, value, paths));
+            }
         },
-        .atan => {
-            const f = switch (value) {
-                .integer => |i| @as(f64, @floatFromInt(i)),
-                .float => |f| f,
-                else => return EvalResult.empty(allocator),
-            };
-            return try EvalResult.single(allocator, .{ .float = std.math.atan(f) });
+        .del => {
+            var locations: std.ArrayListUnmanaged(PathValue) = .empty;
+            try evalPaths(allocator, pf.args[0], .{ .path = &.{}, .value = value }, env, &locations);
+            const paths = try allocator.alloc([]const std.json.Value, locations.items.len);
+            for (locations.items, paths) |loc, *path| {
+                path.* = loc.path;
+            }
+            try results.append(allocator, try deletePaths(allocator, value, paths));
         },
-        // Sprint 06: Generator functions - Date/Time
-        .now => {
-            // ISO 8601 timestamp in UTC: "2024-12-15T17:30:00Z"
-            const timestamp = std.time.timestamp();
-            const epoch_seconds = safeTimestampToU64(timestamp) orelse return try EvalResult.single(allocator, .{ .null = {} });
-            const epoch_day = epoch_seconds / 86400;
-            const day_seconds = epoch_seconds % 86400;
-            const hours = day_seconds / 3600;
-            const minutes = (day_seconds % 3600) / 60;
-            const seconds = day_seconds % 60;
+        .walk => try walkValue(allocator, pf.args[0], value, env, &results),
+    }
+    return EvalResult.multi(allocator, try results.toOwnedSlice(allocator));
+}
 
-            // Calculate year, month, day from epoch day
-            const ymd = epochDayToYmd(epoch_day);
+/// Every location below `at`, parents before children (the order of `paths`).
+fn collectPaths(allocator: std.mem.Allocator, at: PathValue, out: *std.ArrayListUnmanaged(PathValue)) EvalError!void {
+    var children: std.ArrayListUnmanaged(PathValue) = .empty;
+    try indexPaths(allocator, at, .iterate, &children);
+    for (children.items) |child| {
+        try out.append(allocator, child);
+        try collectPaths(allocator, child, out);
+    }
+}
 
-            const str = try std.fmt.allocPrint(allocator, "{d:0>4}-{d:0>2}-{d:0>2}T{d:0>2}:{d:0>2}:{d:0>2}Z", .{
-                ymd.year, ymd.month, ymd.day, hours, minutes, seconds,
-            });
-            return try EvalResult.single(allocator, .{ .string = str });
+/// walk(f): rebuild arrays and objects from their walked children, then apply
+/// f. Object values keep f's first output (or are dropped); array elements
+/// keep all of them.
+fn walkValue(allocator: std.mem.Allocator, f: *const Expr, value: std.json.Value, env: ?*const Env, out: *std.ArrayListUnmanaged(std.json.Value)) EvalError!void {
+    var rebuilt = value;
+    switch (value) {
+        .array => |arr| {
+            var items: std.ArrayListUnmanaged(std.json.Value) = .empty;
+            for (arr.items) |item| {
+                try walkValue(allocator, f, item, env, &items);
+            }
+            const slice = try items.toOwnedSlice(allocator);
+            rebuilt = .{ .array = .{ .items = slice, .capacity = slice.len, .allocator = allocator } };
         },
-        .today => {
-            // Date only: "2024-12-15"
-            const timestamp = std.time.timestamp();
-            const epoch_seconds = safeTimestampToU64(timestamp) orelse return try EvalResult.single(allocator, .{ .null = {} });
-            const epoch_day = epoch_seconds / 86400;
-            const ymd = epochDayToYmd(epoch_day);
-
-            const str = try std.fmt.allocPrint(allocator, "{d:0>4}-{d:0>2}-{d:0>2}", .{
-                ymd.year, ymd.month, ymd.day,
-            });
-            return try EvalResult.single(allocator, .{ .string = str });
+        .object => |obj| {
+            var walked = std.json.ObjectMap.init(allocator);
+            var it = obj.iterator();
+            while (it.next()) |entry| {
+                var child: std.ArrayListUnmanaged(std.json.Value) = .empty;
+                try walkValue(allocator, f, entry.value_ptr.*, env, &child);
+                if (child.items.len > 0) try walked.put(entry.key_ptr.*, child.items[0]);
+            }
+            rebuilt = .{ .object = walked };
         },
-        .epoch => {
-            // Unix timestamp in seconds
-            const timestamp = std.time.timestamp();
-            return try EvalResult.single(allocator, .{ .integer = timestamp });
+        else => {},
+    }
+    const applied = try evalExprWithEnv(allocator, f, rebuilt, env);
+    try out.appendSlice(allocator, applied.values);
+}
+
+/// Paths visited by recurse(f; cond): the input, then each path f selects, depth first.
+fn recursePaths(allocator: std.mem.Allocator, args: []const *Expr, at: PathValue, env: ?*const Env, out: *std.ArrayListUnmanaged(PathValue)) EvalError!void {
+    try out.append(allocator, at);
+    var children: std.ArrayListUnmanaged(PathValue) = .empty;
+    if (args.len == 0) {
+        try indexPaths(allocator, at, .iterate, &children);
+    } else {
+        try evalPaths(allocator, args[0], at, env, &children);
+    }
+    for (children.items) |child| {
+        if (args.len == 2 and !try condHolds(allocator, args[1], child.value, env)) continue;
+        try recursePaths(allocator, args, child, env, out);
+    }
+}
+
+fn evalDel(allocator: std.mem.Allocator, del_expr: DelExpr, value: std.json.Value) EvalError!EvalResult {
+    switch (value) {
+        .object => |obj| {
+            if (del_expr.paths.len == 1) {
+                const key = del_expr.paths[0];
+                // Check if we need to delete an array element: del(.arr[0])
+                if (del_expr.index) |idx| {
+                    var new_obj = std.json.ObjectMap.init(allocator);
+                    var it = obj.iterator();
+                    while (it.next()) |entry| {
+                        if (std.mem.eql(u8, entry.key_ptr.*, key)) {
+                            // Delete element from array
+                            switch (entry.value_ptr.*) {
+                                .array => |arr| {
+                                    var new_arr = std.json.Array.init(allocator);
+                                    const len = arr.items.len;
+                                    // Handle MIN_I64 specially to avoid overflow when negating
+                                    const actual_idx: ?usize = if (idx < 0) blk: {
+                                        const neg_idx = std.math.negate(idx) catch break :blk null;
+                                        if (@as(usize, @intCast(neg_idx)) <= len)
+                                            break :blk len - @as(usize, @intCast(neg_idx))
+                                        else
+                                            break :blk null;
+                                    } else if (@as(usize, @intCast(idx)) < len)
+                                        @as(usize, @intCast(idx))
+                                    else
+                                        null;
+                                    for (arr.items, 0..) |item, i| {
+                                        if (actual_idx == null or i != actual_idx.?) {
+                                            try new_arr.append(item);
+                                        }
+                                    }
+                                    try new_obj.put(entry.key_ptr.*, .{ .array = new_arr });
+                                },
+                                else => try new_obj.put(entry.key_ptr.*, entry.value_ptr.*),
+                            }
+                        } else {
+                            try new_obj.put(entry.key_ptr.*, entry.value_ptr.*);
+                        }
+                    }
+                    return try EvalResult.single(allocator, .{ .object = new_obj });
+                }
+                // Simple delete: del(.key)
+                var new_obj = std.json.ObjectMap.init(allocator);
+                var it = obj.iterator();
+                while (it.next()) |entry| {
+                    if (!std.mem.eql(u8, entry.key_ptr.*, key)) {
+                        try new_obj.put(entry.key_ptr.*, entry.value_ptr.*);
+                    }
+                }
+                return try EvalResult.single(allocator, .{ .object = new_obj });
+            } else if (del_expr.paths.len > 1) {
+                // Nested delete: del(.a.b) or del(.a.arr[0])
+                var new_obj = std.json.ObjectMap.init(allocator);
+                var it = obj.iterator();
+                while (it.next()) |entry| {
+                    if (std.mem.eql(u8, entry.key_ptr.*, del_expr.paths[0])) {
+                        // Recurse with remaining path
+                        const nested_del = DelExpr{ .paths = del_expr.paths[1..], .index = del_expr.index };
+                        const inner_result = try evalDel(allocator, nested_del, entry.value_ptr.*);
+                        if (inner_result.values.len > 0) {
+                            try new_obj.put(entry.key_ptr.*, inner_result.values[0]);
+                        }
+                    } else {
+                        try new_obj.put(entry.key_ptr.*, entry.value_ptr.*);
+                    }
+                }
+                return try EvalResult.single(allocator, .{ .object = new_obj });
+            }
+            return try EvalResult.single(allocator, value);
         },
-        .epoch_ms => {
-            // Unix timestamp in milliseconds
-            const timestamp_ns = std.time.nanoTimestamp();
-            const timestamp_ms: i64 = @intCast(@divFloor(timestamp_ns, std.time.ns_per_ms));
-            return try EvalResult.single(allocator, .{ .integer = timestamp_ms });
+        .array => |arr| {
+            // Direct array deletion: del(.[0]) on an array value
+            if (del_expr.paths.len == 0 and del_expr.index != null) {
+                const idx = del_expr.index.?;
+                var new_arr = std.json.Array.init(allocator);
+                const len = arr.items.len;
+                const actual_idx: ?usize = if (idx < 0)
+                    if (@as(usize, @intCast(-idx)) <= len)
+                        len - @as(usize, @intCast(-idx))
+                    else
+                        null
+                else if (@as(usize, @intCast(idx)) < len)
+                    @as(usize, @intCast(idx))
+                else
+                    null;
+                for (arr.items, 0..) |item, i| {
+                    if (actual_idx == null or i != actual_idx.?) {
+                        try new_arr.append(item);
+                    }
+                }
+                return try EvalResult.single(allocator, .{ .array = new_arr });
+            }
+            return try EvalResult.single(allocator, value);
         },
-        // Sprint 07: Date/Time component generators
-        .year => {
-            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
-            const epoch_day = secs / 86400;
-            const ymd = epochDayToYmd(epoch_day);
-            return try EvalResult.single(allocator, .{ .integer = @intCast(ymd.year) });
+        else => return try EvalResult.single(allocator, value),
+    }
+}
+
+fn evalBuiltin(allocator: std.mem.Allocator, kind: BuiltinKind, input: std.json.Value) EvalError!EvalResult {
+    // Exact decimals keep their text through the builtins that convert,
+    // negate or pass numbers on; the rest see the nearest f64
+    const value = switch (kind) {
+        .tonumber, .tostring, .todecimal, .negate, .tojson, .toarray, .not, .debug, .stderr => input,
+        .numbers, .scalars, .finites, .normals => input,
+        else => if (input == .number_string) std.json.Value{ .float = getNumeric(input) orelse 0 } else input,
+    };
+    switch (kind) {
+        .tonumber => {
+            switch (value) {
+                .integer, .float, .number_string => return try EvalResult.single(allocator, value),
+                .string => |s| {
+                    // JSON number text round-trips exactly, like input does
+                    if (number.literal(s)) |n| return try EvalResult.single(allocator, n);
+                    if (std.fmt.parseInt(i64, s, 10)) |i| {
+                        return try EvalResult.single(allocator, .{ .integer = i });
+                    } else |_| {
+                        if (std.fmt.parseFloat(f64, s)) |f| {
+                            return try EvalResult.single(allocator, .{ .float = f });
+                        } else |_| {
+                            return raiseMessage(allocator, "Cannot parse '{s}' as JSON", .{s});
+                        }
+                    }
+                },
+                else => return raiseMessage(allocator, "{s} cannot be parsed as a number", .{try describeValue(allocator, value)}),
+            }
         },
-        .month => {
-            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
-            const epoch_day = secs / 86400;
-            const ymd = epochDayToYmd(epoch_day);
-            return try EvalResult.single(allocator, .{ .integer = @intCast(ymd.month) });
+        .tostring => {
+            switch (value) {
+                .string => return try EvalResult.single(allocator, value),
+                .integer => |i| {
+                    const str = try std.fmt.allocPrint(allocator, "{d}", .{i});
+                    return try EvalResult.single(allocator, .{ .string = str });
+                },
+                .float => |f| {
+                    const str = try std.fmt.allocPrint(allocator, "{d}", .{f});
+                    return try EvalResult.single(allocator, .{ .string = str });
+                },
+                .number_string => |s| return try EvalResult.single(allocator, .{ .string = s }),
+                .bool => |b| {
+                    return try EvalResult.single(allocator, .{ .string = if (b) "true" else "false" });
+                },
+                .null => {
+                    return try EvalResult.single(allocator, .{ .string = "null" });
+                },
+                .array, .object => return try EvalResult.single(allocator, .{ .string = try toJsonText(allocator, value) }),
+            }
         },
-        .day => {
-            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
-            const epoch_day = secs / 86400;
-            const ymd = epochDayToYmd(epoch_day);
-            return try EvalResult.single(allocator, .{ .integer = @intCast(ymd.day) });
+        .type => {
+            const type_str: []const u8 = switch (value) {
+                .null => "null",
+                .bool => "boolean",
+                .integer, .float => "number",
+                .string => "string",
+                .array => "array",
+                .object => "object",
+                else => "unknown",
+            };
+            return try EvalResult.single(allocator, .{ .string = type_str });
         },
-        .hour => {
-            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
-            const day_seconds = @mod(secs, 86400);
-            const hour_val = day_seconds / 3600;
-            return try EvalResult.single(allocator, .{ .integer = @intCast(hour_val) });
+        .length => {
+            switch (value) {
+                .string => |s| {
+                    return try EvalResult.single(allocator, .{ .integer = codepointCount(s) });
+                },
+                .array => |arr| {
+                    return try EvalResult.single(allocator, .{ .integer = @as(i64, @intCast(arr.items.len)) });
+                },
+                .object => |obj| {
+                    return try EvalResult.single(allocator, .{ .integer = @as(i64, @intCast(obj.count())) });
+                },
+                .null => {
+                    return try EvalResult.single(allocator, .{ .integer = 0 });
+                },
+                // The absolute value, like jq
+                .integer, .float, .number_string => return evalBuiltin(allocator, .abs, value),
+                .bool => return raiseMessage(allocator, "boolean ({s}) has no length", .{if (value.bool) "true" else "false"}),
+            }
         },
-        .minute => {
-            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
-            const day_seconds = @mod(secs, 86400);
-            const minute_val = @mod(day_seconds / 60, 60);
-            return try EvalResult.single(allocator, .{ .integer = @intCast(minute_val) });
+        .keys, .keys_unsorted => {
+            switch (value) {
+                .object => |obj| {
+                    var keys = try allocator.alloc(std.json.Value, obj.count());
+                    var i: usize = 0;
+                    var iter = obj.iterator();
+                    while (iter.next()) |entry| {
+                        keys[i] = .{ .string = entry.key_ptr.* };
+                        i += 1;
+                    }
+                    if (kind == .keys) std.mem.sort(std.json.Value, keys, {}, jsonLessThan);
+                    return try EvalResult.single(allocator, .{ .array = .{ .items = keys, .capacity = keys.len, .allocator = allocator } });
+                },
+                .array => |arr| {
+                    const indexes = try allocator.alloc(std.json.Value, arr.items.len);
+                    for (indexes, 0..) |*index, i| index.* = .{ .integer = @intCast(i) };
+                    return try EvalResult.single(allocator, .{ .array = .{ .items = indexes, .capacity = indexes.len, .allocator = allocator } });
+                },
+                else => return EvalResult.empty(allocator),
+            }
         },
-        .second => {
-            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
-            const second_val = @mod(secs, 60);
-            return try EvalResult.single(allocator, .{ .integer = @intCast(second_val) });
+        .values => {
+            switch (value) {
+                .object => |obj| {
+                    var vals = try allocator.alloc(std.json.Value, obj.count());
+                    var i: usize = 0;
+                    var iter = obj.iterator();
+                    while (iter.next()) |entry| {
+                        vals[i] = entry.value_ptr.*;
+                        i += 1;
+                    }
+                    return try EvalResult.single(allocator, .{ .array = .{ .items = vals, .capacity = vals.len, .allocator = allocator } });
+                },
+                else => return EvalResult.empty(allocator),
+            }
         },
-        .time => {
-            // HH:MM:SS format
-            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
-            const day_seconds = @mod(secs, 86400);
-            const hour_val = day_seconds / 3600;
-            const minute_val = @mod(day_seconds / 60, 60);
-            const second_val = @mod(day_seconds, 60);
-            const str = try std.fmt.allocPrint(allocator, "{d:0>2}:{d:0>2}:{d:0>2}", .{ hour_val, minute_val, second_val });
-            return try EvalResult.single(allocator, .{ .string = str });
+        .isnumber => {
+            const result = switch (value) {
+                .integer, .float => true,
+                else => false,
+            };
+            return try EvalResult.single(allocator, .{ .bool = result });
         },
-        .week => {
-            // ISO week number (1-53)
-            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
-            const epoch_day = secs / 86400;
-            const week_num = epochDayToIsoWeek(epoch_day);
-            return try EvalResult.single(allocator, .{ .integer = @intCast(week_num) });
+        .isstring => {
+            const result = value == .string;
+            return try EvalResult.single(allocator, .{ .bool = result });
         },
-        .weekday => {
-            // Day of week name (Sunday, Monday, etc.)
-            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
-            const epoch_day = secs / 86400;
-            // Jan 1, 1970 was Thursday (4)
-            const day_of_week: usize = @intCast(@mod(epoch_day + 4, 7));
-            const day_names = [_][]const u8{ "Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday" };
-            return try EvalResult.single(allocator, .{ .string = day_names[day_of_week] });
-        },
-        .weekday_num => {
-            // Day of week number (0=Sunday, 6=Saturday)
-            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
-            const epoch_day = secs / 86400;
-            // Jan 1, 1970 was Thursday (4)
-            const day_of_week = @mod(epoch_day + 4, 7);
-            return try EvalResult.single(allocator, .{ .integer = @intCast(day_of_week) });
+        .isboolean => {
+            const result = value == .bool;
+            return try EvalResult.single(allocator, .{ .bool = result });
         },
-        // Sprint 06: Generator functions - IDs
-        .uuid => {
-            // UUID v4 (random): "550e8400-e29b-41d4-a716-446655440000"
-            var bytes: [16]u8 = undefined;
-            std.crypto.random.bytes(&bytes);
-            // Set version 4 and variant bits
-            bytes[6] = (bytes[6] & 0x0f) | 0x40; // Version 4
-            bytes[8] = (bytes[8] & 0x3f) | 0x80; // Variant 1
-
-            const str = try std.fmt.allocPrint(allocator, "{x:0>2}{x:0>2}{x:0>2}{x:0>2}-{x:0>2}{x:0>2}-{x:0>2}{x:0>2}-{x:0>2}{x:0>2}-{x:0>2}{x:0>2}{x:0>2}{x:0>2}{x:0>2}{x:0>2}", .{
-                bytes[0],  bytes[1],  bytes[2],  bytes[3],
-                bytes[4],  bytes[5],  bytes[6],  bytes[7],
-                bytes[8],  bytes[9],  bytes[10], bytes[11],
-                bytes[12], bytes[13], bytes[14], bytes[15],
-            });
-            return try EvalResult.single(allocator, .{ .string = str });
+        .isnull => {
+            const result = value == .null;
+            return try EvalResult.single(allocator, .{ .bool = result });
         },
-        .shortid => {
-            // Base62 8-char ID
-            const str = try generateBase62(allocator, 8);
-            return try EvalResult.single(allocator, .{ .string = str });
+        .isarray => {
+            const result = value == .array;
+            return try EvalResult.single(allocator, .{ .bool = result });
         },
-        .sid => {
-            // Base62 6-char ID
-            const str = try generateBase62(allocator, 6);
-            return try EvalResult.single(allocator, .{ .string = str });
+        .isobject => {
+            const result = value == .object;
+            return try EvalResult.single(allocator, .{ .bool = result });
         },
-        // Sprint 07: More ID generators
-        .nanoid => {
-            // NanoID: 21 chars, URL-safe alphabet
-            // Alphabet: A-Za-z0-9_-
-            const str = try generateNanoId(allocator, 21);
-            return try EvalResult.single(allocator, .{ .string = str });
+        // Sprint 03: Array functions
+        .first => {
+            switch (value) {
+                .array => |arr| {
+                    if (arr.items.len > 0) {
+                        return try EvalResult.single(allocator, arr.items[0]);
+                    }
+                    return EvalResult.empty(allocator);
+                },
+                else => return EvalResult.empty(allocator),
+            }
         },
-        .ulid => {
-            // ULID: 26 chars, Crockford Base32, time-sortable
-            // Format: TTTTTTTTTTRRRRRRRRRRRRRRR (10 time + 16 random chars)
-            const str = try generateUlid(allocator);
-            return try EvalResult.single(allocator, .{ .string = str });
+        .last => {
+            switch (value) {
+                .array => |arr| {
+                    if (arr.items.len > 0) {
+                        return try EvalResult.single(allocator, arr.items[arr.items.len - 1]);
+                    }
+                    return EvalResult.empty(allocator);
+                },
+                else => return EvalResult.empty(allocator),
+            }
         },
-        .uuid7 => {
-            // UUID v7: 36 chars with dashes, time-sortable
-            // Based on Unix timestamp milliseconds
-            const str = try generateUuid7(allocator);
-            return try EvalResult.single(allocator, .{ .string = str });
+        .reverse => {
+            switch (value) {
+                .array => |arr| {
+                    var reversed = try allocator.alloc(std.json.Value, arr.items.len);
+                    for (arr.items, 0..) |item, i| {
+                        reversed[arr.items.len - 1 - i] = item;
+                    }
+                    return try EvalResult.single(allocator, .{ .array = .{ .items = reversed, .capacity = reversed.len, .allocator = allocator } });
+                },
+                .string => |s| {
+                    var reversed = try allocator.alloc(u8, s.len);
+                    for (s, 0..) |c, i| {
+                        reversed[s.len - 1 - i] = c;
+                    }
+                    return try EvalResult.single(allocator, .{ .string = reversed });
+                },
+                else => return EvalResult.empty(allocator),
+            }
         },
-        .xid => {
-            // XID: 20 chars, URL-safe Base32, sortable
-            // 4 bytes time + 3 bytes machine + 2 bytes PID + 3 bytes counter
-            const str = try generateXid(allocator);
-            return try EvalResult.single(allocator, .{ .string = str });
+        .sort => {
+            switch (value) {
+                .array => |arr| {
+                    const sorted = try allocator.alloc(std.json.Value, arr.items.len);
+                    @memcpy(sorted, arr.items);
+                    std.mem.sort(std.json.Value, sorted, {}, jsonLessThan);
+                    return try EvalResult.single(allocator, .{ .array = .{ .items = sorted, .capacity = sorted.len, .allocator = allocator } });
+                },
+                else => return EvalResult.empty(allocator),
+            }
         },
-        // Sprint 06: Generator functions - Random/Sequence
-        .random => {
-            // Random float between 0.0 and 1.0
-            const rand_int = std.crypto.random.int(u64);
-            const rand_float = @as(f64, @floatFromInt(rand_int)) / @as(f64, @floatFromInt(std.math.maxInt(u64)));
-            return try EvalResult.single(allocator, .{ .float = rand_float });
+        .unique => {
+            switch (value) {
+                .array => |arr| {
+                    if (arr.items.len == 0) {
+                        return try EvalResult.single(allocator, .{ .array = .{ .items = &.{}, .capacity = 0, .allocator = allocator } });
+                    }
+
+                    // O(n log n) implementation: sort then remove consecutive duplicates
+                    // This matches jq's behavior where unique returns sorted output
+                    var sorted = try allocator.dupe(std.json.Value, arr.items);
+                    std.mem.sort(std.json.Value, sorted, {}, jsonLessThan);
+
+                    // Linear pass to remove consecutive duplicates
+                    var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
+                    try result_list.append(allocator, sorted[0]);
+                    for (sorted[1..]) |item| {
+                        if (!jsonEqual(item, result_list.items[result_list.items.len - 1])) {
+                            try result_list.append(allocator, item);
+                        }
+                    }
+
+                    // Free the temporary sorted array
+                    allocator.free(sorted);
+
+                    const result_slice = try result_list.toOwnedSlice(allocator);
+                    return try EvalResult.single(allocator, .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } });
+                },
+                else => return EvalResult.empty(allocator),
+            }
         },
-        .seq => {
-            // Incrementing counter (thread-local, resets each run)
-            const current = seq_counter;
-            seq_counter += 1;
-            return try EvalResult.single(allocator, .{ .integer = current });
+        .flatten => {
+            switch (value) {
+                .array => |arr| {
+                    var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
+                    try flattenInto(allocator, arr.items, std.math.maxInt(usize), &result_list);
+                    const result_slice = try result_list.toOwnedSlice(allocator);
+                    return try EvalResult.single(allocator, .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } });
+                },
+                else => return EvalResult.empty(allocator),
+            }
         },
-        // Sprint 06: Transform functions - Numeric
-        .incr => {
+        // Sprint 03: Aggregation functions
+        .add => {
             switch (value) {
-                .integer => |i| {
-                    // Handle maxInt(i64) specially to avoid overflow
-                    if (i == std.math.maxInt(i64)) {
-                        return try EvalResult.single(allocator, .{ .float = @as(f64, @floatFromInt(i)) + 1.0 });
+                .array => |arr| {
+                    // Sum numbers or concatenate strings
+                    if (arr.items.len == 0) return try EvalResult.single(allocator, .null);
+
+                    // Check first element type
+                    switch (arr.items[0]) {
+                        .integer, .float, .number_string => {
+                            // Same rules as +: integers and decimals stay exact
+                            var sum: std.json.Value = .{ .integer = 0 };
+                            for (arr.items) |item| {
+                                switch (item) {
+                                    .integer, .float, .number_string => sum = try arithValues(allocator, .add, sum, item),
+                                    else => {},
+                                }
+                            }
+                            return try EvalResult.single(allocator, sum);
+                        },
+                        .string => {
+                            var total_len: usize = 0;
+                            for (arr.items) |item| {
+                                switch (item) {
+                                    .string => |s| total_len += s.len,
+                                    else => {},
+                                }
+                            }
+                            var result_str = try allocator.alloc(u8, total_len);
+                            var pos: usize = 0;
+                            for (arr.items) |item| {
+                                switch (item) {
+                                    .string => |s| {
+                                        @memcpy(result_str[pos..][0..s.len], s);
+                                        pos += s.len;
+                                    },
+                                    else => {},
+                                }
+                            }
+                            return try EvalResult.single(allocator, .{ .string = result_str });
+                        },
+                        .array => {
+                            // Flatten arrays
+                            var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
+                            for (arr.items) |item| {
+                                switch (item) {
+                                    .array => |inner| try result_list.appendSlice(allocator, inner.items),
+                                    else => {},
+                                }
+                            }
+                            const result_slice = try result_list.toOwnedSlice(allocator);
+                            return try EvalResult.single(allocator, .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } });
+                        },
+                        else => return EvalResult.empty(allocator),
                     }
-                    return try EvalResult.single(allocator, .{ .integer = i + 1 });
                 },
-                .float => |f| return try EvalResult.single(allocator, .{ .float = f + 1.0 }),
                 else => return EvalResult.empty(allocator),
             }
         },
-        .decr => {
+        .min => {
             switch (value) {
-                .integer => |i| {
-                    // Handle minInt(i64) specially to avoid overflow
-                    if (i == std.math.minInt(i64)) {
-                        return try EvalResult.single(allocator, .{ .float = @as(f64, @floatFromInt(i)) - 1.0 });
+                .array => |arr| {
+                    if (arr.items.len == 0) return try EvalResult.single(allocator, .null);
+                    var min_val = arr.items[0];
+                    for (arr.items[1..]) |item| {
+                        if (jsonLessThan({}, item, min_val)) {
+                            min_val = item;
+                        }
                     }
-                    return try EvalResult.single(allocator, .{ .integer = i - 1 });
+                    return try EvalResult.single(allocator, min_val);
                 },
-                .float => |f| return try EvalResult.single(allocator, .{ .float = f - 1.0 }),
                 else => return EvalResult.empty(allocator),
             }
         },
-        .negate => {
+        .max => {
             switch (value) {
-                .integer => |i| {
-                    // Handle minInt(i64) specially to avoid overflow
-                    // since -minInt(i64) cannot be represented as i64
-                    if (i == std.math.minInt(i64)) {
-                        return try EvalResult.single(allocator, .{ .float = @as(f64, @floatFromInt(std.math.maxInt(i64))) + 1.0 });
+                .array => |arr| {
+                    if (arr.items.len == 0) return try EvalResult.single(allocator, .null);
+                    var max_val = arr.items[0];
+                    for (arr.items[1..]) |item| {
+                        if (jsonLessThan({}, max_val, item)) {
+                            max_val = item;
+                        }
                     }
-                    return try EvalResult.single(allocator, .{ .integer = -i });
+                    return try EvalResult.single(allocator, max_val);
                 },
-                .float => |f| return try EvalResult.single(allocator, .{ .float = -f }),
                 else => return EvalResult.empty(allocator),
             }
-        },
-        .toggle => {
-            switch (value) {
-                .bool => |b| return try EvalResult.single(allocator, .{ .bool = !b }),
-                else => return EvalResult.empty(allocator),
+        },
+        // Sprint 03: String functions (no args)
+        .ascii_downcase => {
+            switch (value) {
+                .string => |s| {
+                    var lower = try allocator.alloc(u8, s.len);
+                    for (s, 0..) |c, i| {
+                        lower[i] = std.ascii.toLower(c);
+                    }
+                    return try EvalResult.single(allocator, .{ .string = lower });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .ascii_upcase => {
+            switch (value) {
+                .string => |s| {
+                    var upper = try allocator.alloc(u8, s.len);
+                    for (s, 0..) |c, i| {
+                        upper[i] = std.ascii.toUpper(c);
+                    }
+                    return try EvalResult.single(allocator, .{ .string = upper });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+
+        .to_entries => {
+            switch (value) {
+                .object => |obj| {
+                    var entries = std.json.Array.init(allocator);
+                    var it = obj.iterator();
+                    while (it.next()) |entry| {
+                        var entry_obj = std.json.ObjectMap.init(allocator);
+                        try entry_obj.put("key", .{ .string = entry.key_ptr.* });
+                        try entry_obj.put("value", entry.value_ptr.*);
+                        try entries.append(.{ .object = entry_obj });
+                    }
+                    return try EvalResult.single(allocator, .{ .array = entries });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+
+        .from_entries => {
+            switch (value) {
+                .array => |arr| {
+                    var result_obj = std.json.ObjectMap.init(allocator);
+                    for (arr.items) |item| {
+                        switch (item) {
+                            .object => |entry_obj| {
+                                // Support {key, value}, {k, v}, {name, value} and capitalized forms (jq 1.7)
+                                const key_val = entry_obj.get("key") orelse
+                                    entry_obj.get("k") orelse
+                                    entry_obj.get("name") orelse
+                                    entry_obj.get("Name") orelse
+                                    entry_obj.get("K") orelse
+                                    entry_obj.get("Key") orelse continue;
+                                const val = entry_obj.get("value") orelse
+                                    entry_obj.get("v") orelse
+                                    entry_obj.get("Value") orelse .null;
+                                // Non-string keys (e.g. array indexes) become their JSON text
+                                const key = switch (key_val) {
+                                    .string => |str| str,
+                                    else => try toJsonText(allocator, key_val),
+                                };
+                                try result_obj.put(key, val);
+                            },
+                            else => {},
+                        }
+                    }
+                    return try EvalResult.single(allocator, .{ .object = result_obj });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        // Sprint 05: Math functions
+        .floor => {
+            switch (value) {
+                .integer => return try EvalResult.single(allocator, value),
+                .float => |f| {
+                    const floored = @floor(f);
+                    // Return as integer if it fits
+                    if (floored >= @as(f64, @floatFromInt(std.math.minInt(i64))) and
+                        floored <= @as(f64, @floatFromInt(std.math.maxInt(i64))))
+                    {
+                        return try EvalResult.single(allocator, .{ .integer = @as(i64, @intFromFloat(floored)) });
+                    }
+                    return try EvalResult.single(allocator, .{ .float = floored });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .ceil => {
+            switch (value) {
+                .integer => return try EvalResult.single(allocator, value),
+                .float => |f| {
+                    const ceiled = @ceil(f);
+                    if (ceiled >= @as(f64, @floatFromInt(std.math.minInt(i64))) and
+                        ceiled <= @as(f64, @floatFromInt(std.math.maxInt(i64))))
+                    {
+                        return try EvalResult.single(allocator, .{ .integer = @as(i64, @intFromFloat(ceiled)) });
+                    }
+                    return try EvalResult.single(allocator, .{ .float = ceiled });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .round => {
+            switch (value) {
+                .integer => return try EvalResult.single(allocator, value),
+                .float => |f| {
+                    const rounded = @round(f);
+                    if (rounded >= @as(f64, @floatFromInt(std.math.minInt(i64))) and
+                        rounded <= @as(f64, @floatFromInt(std.math.maxInt(i64))))
+                    {
+                        return try EvalResult.single(allocator, .{ .integer = @as(i64, @intFromFloat(rounded)) });
+                    }
+                    return try EvalResult.single(allocator, .{ .float = rounded });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .fabs, .abs => {
+            switch (value) {
+                .integer => |i| {
+                    // Handle minInt(i64) specially to avoid overflow
+                    // since -minInt(i64) cannot be represented as i64
+                    if (i == std.math.minInt(i64)) {
+                        // Return as float since the absolute value exceeds i64 max
+                        return try EvalResult.single(allocator, .{ .float = @as(f64, @floatFromInt(std.math.maxInt(i64))) + 1.0 });
+                    }
+                    const abs_val = if (i < 0) -i else i;
+                    return try EvalResult.single(allocator, .{ .integer = abs_val });
+                },
+                .float => |f| {
+                    return try EvalResult.single(allocator, .{ .float = @abs(f) });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        // Sprint 07: More math functions
+        .exp => {
+            const f = switch (value) {
+                .integer => |i| @as(f64, @floatFromInt(i)),
+                .float => |f| f,
+                else => return EvalResult.empty(allocator),
+            };
+            return try EvalResult.single(allocator, .{ .float = @exp(f) });
+        },
+        .ln => {
+            const f = switch (value) {
+                .integer => |i| @as(f64, @floatFromInt(i)),
+                .float => |f| f,
+                else => return EvalResult.empty(allocator),
+            };
+            if (f <= 0) return try EvalResult.single(allocator, .null);
+            return try EvalResult.single(allocator, .{ .float = @log(f) });
+        },
+        .log10 => {
+            const f = switch (value) {
+                .integer => |i| @as(f64, @floatFromInt(i)),
+                .float => |f| f,
+                else => return EvalResult.empty(allocator),
+            };
+            if (f <= 0) return try EvalResult.single(allocator, .null);
+            return try EvalResult.single(allocator, .{ .float = std.math.log10(f) });
+        },
+        .log2 => {
+            const f = switch (value) {
+                .integer => |i| @as(f64, @floatFromInt(i)),
+                .float => |f| f,
+                else => return EvalResult.empty(allocator),
+            };
+            if (f <= 0) return try EvalResult.single(allocator, .null);
+            return try EvalResult.single(allocator, .{ .float = std.math.log2(f) });
+        },
+        .sqrt => {
+            const f = switch (value) {
+                .integer => |i| @as(f64, @floatFromInt(i)),
+                .float => |f| f,
+                else => return EvalResult.empty(allocator),
+            };
+            if (f < 0) return try EvalResult.single(allocator, .null);
+            return try EvalResult.single(allocator, .{ .float = @sqrt(f) });
+        },
+        // Sprint 07: Trigonometry functions
+        .sin => {
+            const f = switch (value) {
+                .integer => |i| @as(f64, @floatFromInt(i)),
+                .float => |f| f,
+                else => return EvalResult.empty(allocator),
+            };
+            return try EvalResult.single(allocator, .{ .float = @sin(f) });
+        },
+        .cos => {
+            const f = switch (value) {
+                .integer => |i| @as(f64, @floatFromInt(i)),
+                .float => |f| f,
+                else => return EvalResult.empty(allocator),
+            };
+            return try EvalResult.single(allocator, .{ .float = @cos(f) });
+        },
+        .tan => {
+            const f = switch (value) {
+                .integer => |i| @as(f64, @floatFromInt(i)),
+                .float => |f| f,
+                else => return EvalResult.empty(allocator),
+            };
+            return try EvalResult.single(allocator, .{ .float = @tan(f) });
+        },
+        .asin => {
+            const f = switch (value) {
+                .integer => |i| @as(f64, @floatFromInt(i)),
+                .float => |f| f,
+                else => return EvalResult.empty(allocator),
+            };
+            // asin is only valid for [-1, 1]
+            if (f < -1 or f > 1) return try EvalResult.single(allocator, .null);
+            return try EvalResult.single(allocator, .{ .float = std.math.asin(f) });
+        },
+        .acos => {
+            const f = switch (value) {
+                .integer => |i| @as(f64, @floatFromInt(i)),
+                .float => |f| f,
+                else => return EvalResult.empty(allocator),
+            };
+            // acos is only valid for [-1, 1]
+            if (f < -1 or f > 1) return try EvalResult.single(allocator, .null);
+            return try EvalResult.single(allocator, .{ .float = std.math.acos(f) });
+        },
+        .atan => {
+            const f = switch (value) {
+                .integer => |i| @as(f64, @floatFromInt(i)),
+                .float => |f| f,
+                else => return EvalResult.empty(allocator),
+            };
+            return try EvalResult.single(allocator, .{ .float = std.math.atan(f) });
+        },
+        // Sprint 06: Generator functions - Date/Time
+        .now => {
+            // ISO 8601 timestamp in UTC: "2024-12-15T17:30:00Z"
+            const timestamp = std.time.timestamp();
+            const epoch_seconds = safeTimestampToU64(timestamp) orelse return try EvalResult.single(allocator, .{ .null = {} });
+            const epoch_day = epoch_seconds / 86400;
+            const day_seconds = epoch_seconds % 86400;
+            const hours = day_seconds / 3600;
+            const minutes = (day_seconds % 3600) / 60;
+            const seconds = day_seconds % 60;
+
+            // Calculate year, month, day from epoch day
+            const ymd = epochDayToYmd(epoch_day);
+
+            const str = try std.fmt.allocPrint(allocator, "{d:0>4}-{d:0>2}-{d:0>2}T{d:0>2}:{d:0>2}:{d:0>2}Z", .{
+                ymd.year, ymd.month, ymd.day, hours, minutes, seconds,
+            });
+            return try EvalResult.single(allocator, .{ .string = str });
+        },
+        .today => {
+            // Date only: "2024-12-15"
+            const timestamp = std.time.timestamp();
+            const epoch_seconds = safeTimestampToU64(timestamp) orelse return try EvalResult.single(allocator, .{ .null = {} });
+            const epoch_day = epoch_seconds / 86400;
+            const ymd = epochDayToYmd(epoch_day);
+
+            const str = try std.fmt.allocPrint(allocator, "{d:0>4}-{d:0>2}-{d:0>2}", .{
+                ymd.year, ymd.month, ymd.day,
+            });
+            return try EvalResult.single(allocator, .{ .string = str });
+        },
+        .epoch => {
+            // Unix timestamp in seconds
+            const timestamp = std.time.timestamp();
+            return try EvalResult.single(allocator, .{ .integer = timestamp });
+        },
+        .epoch_ms => {
+            // Unix timestamp in milliseconds
+            const timestamp_ns = std.time.nanoTimestamp();
+            const timestamp_ms: i64 = @intCast(@divFloor(timestamp_ns, std.time.ns_per_ms));
+            return try EvalResult.single(allocator, .{ .integer = timestamp_ms });
+        },
+        // Sprint 07: Date/Time component generators
+        .year => {
+            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
+            const epoch_day = secs / 86400;
+            const ymd = epochDayToYmd(epoch_day);
+            return try EvalResult.single(allocator, .{ .integer = @intCast(ymd.year) });
+        },
+        .month => {
+            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
+            const epoch_day = secs / 86400;
+            const ymd = epochDayToYmd(epoch_day);
+            return try EvalResult.single(allocator, .{ .integer = @intCast(ymd.month) });
+        },
+        .day => {
+            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
+            const epoch_day = secs / 86400;
+            const ymd = epochDayToYmd(epoch_day);
+            return try EvalResult.single(allocator, .{ .integer = @intCast(ymd.day) });
+        },
+        .hour => {
+            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
+            const day_seconds = @mod(secs, 86400);
+            const hour_val = day_seconds / 3600;
+            return try EvalResult.single(allocator, .{ .integer = @intCast(hour_val) });
+        },
+        .minute => {
+            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
+            const day_seconds = @mod(secs, 86400);
+            const minute_val = @mod(day_seconds / 60, 60);
+            return try EvalResult.single(allocator, .{ .integer = @intCast(minute_val) });
+        },
+        .second => {
+            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
+            const second_val = @mod(secs, 60);
+            return try EvalResult.single(allocator, .{ .integer = @intCast(second_val) });
+        },
+        .time => {
+            // HH:MM:SS format
+            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
+            const day_seconds = @mod(secs, 86400);
+            const hour_val = day_seconds / 3600;
+            const minute_val = @mod(day_seconds / 60, 60);
+            const second_val = @mod(day_seconds, 60);
+            const str = try std.fmt.allocPrint(allocator, "{d:0>2}:{d:0>2}:{d:0>2}", .{ hour_val, minute_val, second_val });
+            return try EvalResult.single(allocator, .{ .string = str });
+        },
+        .week => {
+            // ISO week number (1-53)
+            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
+            const epoch_day = secs / 86400;
+            const week_num = epochDayToIsoWeek(epoch_day);
+            return try EvalResult.single(allocator, .{ .integer = @intCast(week_num) });
+        },
+        .weekday => {
+            // Day of week name (Sunday, Monday, etc.)
+            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
+            const epoch_day = secs / 86400;
+            // Jan 1, 1970 was Thursday (4)
+            const day_of_week: usize = @intCast(@mod(epoch_day + 4, 7));
+            const day_names = [_][]const u8{ "Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday" };
+            return try EvalResult.single(allocator, .{ .string = day_names[day_of_week] });
+        },
+        .weekday_num => {
+            // Day of week number (0=Sunday, 6=Saturday)
+            const secs = safeTimestampToU64(std.time.timestamp()) orelse return try EvalResult.single(allocator, .{ .null = {} });
+            const epoch_day = secs / 86400;
+            // Jan 1, 1970 was Thursday (4)
+            const day_of_week = @mod(epoch_day + 4, 7);
+            return try EvalResult.single(allocator, .{ .integer = @intCast(day_of_week) });
+        },
+        // Sprint 06: Generator functions - IDs
+        .uuid => {
+            // UUID v4 (random): "550e8400-e29b-41d4-a716-446655440000"
+            var bytes: [16]u8 = undefined;
+            std.crypto.random.bytes(&bytes);
+            // Set version 4 and variant bits
+            bytes[6] = (bytes[6] & 0x0f) | 0x40; // Version 4
+            bytes[8] = (bytes[8] & 0x3f) | 0x80; // Variant 1
+
+            const str = try std.fmt.allocPrint(allocator, "{x:0>2}{x:0>2}{x:0>2}{x:0>2}-{x:0>2}{x:0>2}-{x:0>2}{x:0>2}-{x:0>2}{x:0>2}-{x:0>2}{x:0>2}{x:0>2}{x:0>2}{x:0>2}{x:0>2}", .{
+                bytes[0],  bytes[1],  bytes[2],  bytes[3],
+                bytes[4],  bytes[5],  bytes[6],  bytes[7],
+                bytes[8],  bytes[9],  bytes[10], bytes[11],
+                bytes[12], bytes[13], bytes[14], bytes[15],
+            });
+            return try EvalResult.single(allocator, .{ .string = str });
+        },
+        .shortid => {
+            // Base62 8-char ID
+            const str = try generateBase62(allocator, 8);
+            return try EvalResult.single(allocator, .{ .string = str });
+        },
+        .sid => {
+            // Base62 6-char ID
+            const str = try generateBase62(allocator, 6);
+            return try EvalResult.single(allocator, .{ .string = str });
+        },
+        // Sprint 07: More ID generators
+        .nanoid => {
+            // NanoID: 21 chars, URL-safe alphabet
+            // Alphabet: A-Za-z0-9_-
+            const str = try generateNanoId(allocator, 21);
+            return try EvalResult.single(allocator, .{ .string = str });
+        },
+        .ulid => {
+            // ULID: 26 chars, Crockford Base32, time-sortable
+            // Format: TTTTTTTTTTRRRRRRRRRRRRRRR (10 time + 16 random chars)
+            const str = try generateUlid(allocator);
+            return try EvalResult.single(allocator, .{ .string = str });
+        },
+        .uuid7 => {
+            // UUID v7: 36 chars with dashes, time-sortable
+            // Based on Unix timestamp milliseconds
+            const str = try generateUuid7(allocator);
+            return try EvalResult.single(allocator, .{ .string = str });
+        },
+        .xid => {
+            // XID: 20 chars, URL-safe Base32, sortable
+            // 4 bytes time + 3 bytes machine + 2 bytes PID + 3 bytes counter
+            const str = try generateXid(allocator);
+            return try EvalResult.single(allocator, .{ .string = str });
+        },
+        // Sprint 06: Generator functions - Random/Sequence
+        .random => {
+            // Random float between 0.0 and 1.0
+            const rand_int = std.crypto.random.int(u64);
+            const rand_float = @as(f64, @floatFromInt(rand_int)) / @as(f64, @floatFromInt(std.math.maxInt(u64)));
+            return try EvalResult.single(allocator, .{ .float = rand_float });
+        },
+        .seq => {
+            // Incrementing counter (thread-local, resets each run)
+            const current = seq_counter;
+            seq_counter += 1;
+            return try EvalResult.single(allocator, .{ .integer = current });
+        },
+        // Sprint 06: Transform functions - Numeric
+        .incr => {
+            switch (value) {
+                .integer => |i| {
+                    // Handle maxInt(i64) specially to avoid overflow
+                    if (i == std.math.maxInt(i64)) {
+                        return try EvalResult.single(allocator, .{ .float = @as(f64, @floatFromInt(i)) + 1.0 });
+                    }
+                    return try EvalResult.single(allocator, .{ .integer = i + 1 });
+                },
+                .float => |f| return try EvalResult.single(allocator, .{ .float = f + 1.0 }),
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .decr => {
+            switch (value) {
+                .integer => |i| {
+                    // Handle minInt(i64) specially to avoid overflow
+                    if (i == std.math.minInt(i64)) {
+                        return try EvalResult.single(allocator, .{ .float = @as(f64, @floatFromInt(i)) - 1.0 });
+                    }
+                    return try EvalResult.single(allocator, .{ .integer = i - 1 });
+                },
+                .float => |f| return try EvalResult.single(allocator, .{ .float = f - 1.0 }),
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .negate => {
+            switch (value) {
+                .integer => |i| {
+                    // Handle minInt(i64) specially to avoid overflow
+                    // since -minInt(i64) cannot be represented as i64
+                    if (i == std.math.minInt(i64)) {
+                        return try EvalResult.single(allocator, .{ .float = @as(f64, @floatFromInt(std.math.maxInt(i64))) + 1.0 });
+                    }
+                    return try EvalResult.single(allocator, .{ .integer = -i });
+                },
+                .float => |f| return try EvalResult.single(allocator, .{ .float = -f }),
+                .number_string => return try EvalResult.single(allocator, try arithValues(allocator, .sub, .{ .integer = 0 }, value)),
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .toggle => {
+            switch (value) {
+                .bool => |b| return try EvalResult.single(allocator, .{ .bool = !b }),
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        // Sprint 06: Transform functions - String
+        .trim => {
+            switch (value) {
+                .string => |s| {
+                    const trimmed = std.mem.trim(u8, s, " \t\n\r");
+                    return try EvalResult.single(allocator, .{ .string = trimmed });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .ltrim => {
+            switch (value) {
+                .string => |s| {
+                    const trimmed = std.mem.trimLeft(u8, s, " \t\n\r");
+                    return try EvalResult.single(allocator, .{ .string = trimmed });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .rtrim => {
+            switch (value) {
+                .string => |s| {
+                    const trimmed = std.mem.trimRight(u8, s, " \t\n\r");
+                    return try EvalResult.single(allocator, .{ .string = trimmed });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        // Sprint 06: Type coercion
+        .@"int" => {
+            switch (value) {
+                .integer => return try EvalResult.single(allocator, value),
+                .float => |f| {
+                    if (f >= @as(f64, @floatFromInt(std.math.minInt(i64))) and
+                        f <= @as(f64, @floatFromInt(std.math.maxInt(i64))))
+                    {
+                        return try EvalResult.single(allocator, .{ .integer = @as(i64, @intFromFloat(f)) });
+                    }
+                    return try EvalResult.single(allocator, .{ .null = {} });
+                },
+                .string => |s| {
+                    if (std.fmt.parseInt(i64, s, 10)) |i| {
+                        return try EvalResult.single(allocator, .{ .integer = i });
+                    } else |_| {
+                        // Try parsing as float then truncating
+                        if (std.fmt.parseFloat(f64, s)) |f| {
+                            return try EvalResult.single(allocator, .{ .integer = @as(i64, @intFromFloat(f)) });
+                        } else |_| {
+                            return try EvalResult.single(allocator, .{ .null = {} });
+                        }
+                    }
+                },
+                .bool => |b| return try EvalResult.single(allocator, .{ .integer = if (b) 1 else 0 }),
+                .null => return try EvalResult.single(allocator, .{ .null = {} }),
+                else => return try EvalResult.single(allocator, .{ .null = {} }),
+            }
+        },
+        .@"float" => {
+            switch (value) {
+                .float => return try EvalResult.single(allocator, value),
+                .integer => |i| return try EvalResult.single(allocator, .{ .float = @as(f64, @floatFromInt(i)) }),
+                .string => |s| {
+                    if (std.fmt.parseFloat(f64, s)) |f| {
+                        return try EvalResult.single(allocator, .{ .float = f });
+                    } else |_| {
+                        return try EvalResult.single(allocator, .{ .null = {} });
+                    }
+                },
+                .bool => |b| return try EvalResult.single(allocator, .{ .float = if (b) 1.0 else 0.0 }),
+                .null => return try EvalResult.single(allocator, .{ .null = {} }),
+                else => return try EvalResult.single(allocator, .{ .null = {} }),
+            }
+        },
+        .todecimal => {
+            const text = switch (value) {
+                .integer, .number_string => return try EvalResult.single(allocator, value),
+                .string => |s| s,
+                else => try toJsonText(allocator, value),
+            };
+            const decimal = try number.Decimal.fromValue(allocator, .{ .number_string = text }) orelse
+                return raiseMessage(allocator, "{s} cannot be converted to a decimal", .{try describeValue(allocator, value)});
+            return try EvalResult.single(allocator, try decimal.toValue(allocator));
+        },
+        .@"bool" => {
+            // Truthy: true, non-zero numbers, non-empty strings, non-empty arrays/objects
+            // Falsy: false, 0, "", null, [], {}
+            const result = switch (value) {
+                .bool => |b| b,
+                .integer => |i| i != 0,
+                .float => |f| f != 0.0,
+                .string => |s| s.len > 0,
+                .array => |arr| arr.items.len > 0,
+                .object => |obj| obj.count() > 0,
+                .null => false,
+                else => false,
+            };
+            return try EvalResult.single(allocator, .{ .bool = result });
+        },
+        // Sprint 06: Case functions
+        .capitalize => {
+            switch (value) {
+                .string => |s| {
+                    if (s.len == 0) return try EvalResult.single(allocator, value);
+                    var result = try allocator.alloc(u8, s.len);
+                    @memcpy(result, s);
+                    if (result[0] >= 'a' and result[0] <= 'z') {
+                        result[0] = result[0] - 32;
+                    }
+                    return try EvalResult.single(allocator, .{ .string = result });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .titlecase => {
+            switch (value) {
+                .string => |s| {
+                    if (s.len == 0) return try EvalResult.single(allocator, value);
+                    var result = try allocator.alloc(u8, s.len);
+                    var capitalize_next = true;
+                    for (s, 0..) |c, i| {
+                        if (c == ' ' or c == '\t' or c == '\n' or c == '-' or c == '_') {
+                            result[i] = c;
+                            capitalize_next = true;
+                        } else if (capitalize_next and c >= 'a' and c <= 'z') {
+                            result[i] = c - 32;
+                            capitalize_next = false;
+                        } else if (!capitalize_next and c >= 'A' and c <= 'Z') {
+                            result[i] = c + 32;
+                            capitalize_next = false;
+                        } else {
+                            result[i] = c;
+                            capitalize_next = false;
+                        }
+                    }
+                    return try EvalResult.single(allocator, .{ .string = result });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .snakecase => {
+            switch (value) {
+                .string => |s| {
+                    const result = try toSnakeCase(allocator, s);
+                    return try EvalResult.single(allocator, .{ .string = result });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .camelcase => {
+            switch (value) {
+                .string => |s| {
+                    const result = try toCamelCase(allocator, s, false);
+                    return try EvalResult.single(allocator, .{ .string = result });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .kebabcase => {
+            switch (value) {
+                .string => |s| {
+                    const result = try toKebabCase(allocator, s);
+                    return try EvalResult.single(allocator, .{ .string = result });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        // Sprint 07: More case functions
+        .pascalcase => {
+            switch (value) {
+                .string => |s| {
+                    // PascalCase is just camelCase with first letter uppercase
+                    const result = try toCamelCase(allocator, s, true);
+                    return try EvalResult.single(allocator, .{ .string = result });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .screamcase => {
+            switch (value) {
+                .string => |s| {
+                    // SCREAMING_SNAKE_CASE: snake_case but uppercase
+                    const snake = try toSnakeCase(allocator, s);
+                    // Convert to uppercase
+                    for (snake) |*c| {
+                        if (c.* >= 'a' and c.* <= 'z') {
+                            c.* = c.* - 32;
+                        }
+                    }
+                    return try EvalResult.single(allocator, .{ .string = snake });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        // Core jq filters
+        .empty => return EvalResult.empty(allocator),
+        .not => return try EvalResult.single(allocator, .{ .bool = !isTruthy(value) }),
+        .arrays, .objects, .iterables, .booleans, .numbers, .strings, .nulls, .scalars => {
+            const keep = switch (kind) {
+                .arrays => value == .array,
+                .objects => value == .object,
+                .iterables => value == .array or value == .object,
+                .booleans => value == .bool,
+                .numbers => value == .integer or value == .float or value == .number_string,
+                .strings => value == .string,
+                .nulls => value == .null,
+                else => value != .array and value != .object,
+            };
+            if (!keep) return EvalResult.empty(allocator);
+            return try EvalResult.single(allocator, value);
+        },
+        .finites, .normals => {
+            const f = getNumeric(value) orelse return EvalResult.empty(allocator);
+            const keep = if (kind == .finites) std.math.isFinite(f) else std.math.isNormal(f);
+            if (!keep) return EvalResult.empty(allocator);
+            return try EvalResult.single(allocator, value);
+        },
+        // Sprint 06: String splitting
+        .words => {
+            switch (value) {
+                .string => |s| {
+                    var words_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
+                    var iter = std.mem.tokenizeAny(u8, s, " \t\n\r");
+                    while (iter.next()) |word| {
+                        try words_list.append(allocator, .{ .string = word });
+                    }
+                    const result_slice = try words_list.toOwnedSlice(allocator);
+                    return try EvalResult.single(allocator, .{ .array = .{
+                        .items = result_slice,
+                        .capacity = result_slice.len,
+                        .allocator = allocator,
+                    } });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .lines => {
+            switch (value) {
+                .string => |s| {
+                    var lines_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
+                    var iter = std.mem.splitSequence(u8, s, "\n");
+                    while (iter.next()) |line| {
+                        // Trim \r from line endings
+                        const trimmed_line = std.mem.trimRight(u8, line, "\r");
+                        try lines_list.append(allocator, .{ .string = trimmed_line });
+                    }
+                    const result_slice = try lines_list.toOwnedSlice(allocator);
+                    return try EvalResult.single(allocator, .{ .array = .{
+                        .items = result_slice,
+                        .capacity = result_slice.len,
+                        .allocator = allocator,
+                    } });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .chars => {
+            switch (value) {
+                .string => |s| {
+                    var chars_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
+                    for (s) |c| {
+                        const char_str = try allocator.alloc(u8, 1);
+                        char_str[0] = c;
+                        try chars_list.append(allocator, .{ .string = char_str });
+                    }
+                    const result_slice = try chars_list.toOwnedSlice(allocator);
+                    return try EvalResult.single(allocator, .{ .array = .{
+                        .items = result_slice,
+                        .capacity = result_slice.len,
+                        .allocator = allocator,
+                    } });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        // Sprint 06: Slug
+        .slugify => {
+            switch (value) {
+                .string => |s| {
+                    const result = try toSlug(allocator, s);
+                    return try EvalResult.single(allocator, .{ .string = result });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        // Sprint 08: Time functions
+        .xid_time => {
+            // Extract epoch seconds from XID string (20 chars total, first 4 bytes = timestamp)
+            switch (value) {
+                .string => |s| {
+                    if (s.len < 20) return try EvalResult.single(allocator, .null);
+                    const timestamp = decodeXidTimestamp(s) orelse return try EvalResult.single(allocator, .null);
+                    return try EvalResult.single(allocator, .{ .integer = @intCast(timestamp) });
+                },
+                else => return EvalResult.empty(allocator),
+            }
+        },
+        .delta => {
+            // Seconds since a timestamp (accepts epoch int or ISO string)
+            const now_secs: i64 = std.time.timestamp();
+            const then_secs: i64 = switch (value) {
+                .integer => |i| i,
+                .string => |s| (parseIso8601(s) orelse return try EvalResult.single(allocator, .null)).secs,
+                else => return EvalResult.empty(allocator),
+            };
+            return try EvalResult.single(allocator, .{ .integer = now_secs - then_secs });
+        },
+        .ago => {
+            // Human-friendly relative time string
+            const now_secs: i64 = std.time.timestamp();
+            const then_secs: i64 = switch (value) {
+                .integer => |i| i,
+                .string => |s| (parseIso8601(s) orelse return try EvalResult.single(allocator, .null)).secs,
+                else => return EvalResult.empty(allocator),
+            };
+            const diff = now_secs - then_secs;
+            const ago_str = try formatAgo(allocator, diff);
+            return try EvalResult.single(allocator, .{ .string = ago_str });
+        },
+        // jq builtin library
+        .utf8bytelength => {
+            if (value != .string) return raiseMessage(allocator, "{s} only strings have UTF-8 byte length", .{try describeValue(allocator, value)});
+            return try EvalResult.single(allocator, .{ .integer = @intCast(value.string.len) });
+        },
+        .tojson => return try EvalResult.single(allocator, .{ .string = try toJsonText(allocator, value) }),
+        .fromjson => {
+            if (value != .string) return raiseMessage(allocator, "{s} cannot be parsed as JSON", .{try describeValue(allocator, value)});
+            const parsed = number.parseJson(allocator, value.string, .{}) catch |err| switch (err) {
+                error.OutOfMemory => return error.OutOfMemory,
+                else => return raiseMessage(allocator, "{s} (while parsing '{s}')", .{ @errorName(err), value.string }),
+            };
+            return try EvalResult.single(allocator, parsed);
+        },
+        .ascii => {
+            const code = switch (value) {
+                .integer => |i| i,
+                else => -1,
+            };
+            if (code < 0 or code > 127) return raiseMessage(allocator, "ascii only takes numbers 0-127, not {s}", .{try describeValue(allocator, value)});
+            const str = try allocator.alloc(u8, 1);
+            str[0] = @intCast(code);
+            return try EvalResult.single(allocator, .{ .string = str });
+        },
+        .implode => {
+            if (value != .array) return raiseMessage(allocator, "Cannot implode {s}", .{try describeValue(allocator, value)});
+            var out: std.ArrayListUnmanaged(u8) = .empty;
+            for (value.array.items) |item| {
+                const code = getNumeric(item) orelse return raiseMessage(allocator, "Unicode codepoint must be numeric, not {s}", .{try describeValue(allocator, item)});
+                if (!(code >= 0 and code <= 0x10FFFF)) return raiseMessage(allocator, "Invalid codepoint literal {s}", .{try describeValue(allocator, item)});
+                var buf: [4]u8 = undefined;
+                const len = std.unicode.utf8Encode(@intFromFloat(code), &buf) catch return raiseMessage(allocator, "Invalid codepoint literal {s}", .{try describeValue(allocator, item)});
+                try out.appendSlice(allocator, buf[0..len]);
+            }
+            return try EvalResult.single(allocator, .{ .string = try out.toOwnedSlice(allocator) });
+        },
+        .explode => {
+            if (value != .string) return raiseMessage(allocator, "{s} cannot be exploded", .{try describeValue(allocator, value)});
+            var codes: std.ArrayListUnmanaged(std.json.Value) = .empty;
+            var it = (std.unicode.Utf8View.init(value.string) catch return raiseMessage(allocator, "Invalid UTF-8 in {s}", .{try describeValue(allocator, value)})).iterator();
+            while (it.nextCodepoint()) |cp| try codes.append(allocator, .{ .integer = cp });
+            const slice = try codes.toOwnedSlice(allocator);
+            return try EvalResult.single(allocator, .{ .array = .{ .items = slice, .capacity = slice.len, .allocator = allocator } });
+        },
+        .transpose => {
+            if (value != .array) return EvalResult.empty(allocator);
+            var width: usize = 0;
+            for (value.array.items) |row| {
+                if (row == .array) width = @max(width, row.array.items.len);
+            }
+            const columns = try allocator.alloc(std.json.Value, width);
+            for (columns, 0..) |*column, col| {
+                const cells = try allocator.alloc(std.json.Value, value.array.items.len);
+                for (cells, value.array.items) |*cell, row| {
+                    cell.* = if (row == .array and col < row.array.items.len) row.array.items[col] else .null;
+                }
+                column.* = .{ .array = .{ .items = cells, .capacity = cells.len, .allocator = allocator } };
+            }
+            return try EvalResult.single(allocator, .{ .array = .{ .items = columns, .capacity = columns.len, .allocator = allocator } });
+        },
+        .combinations => {
+            if (value != .array) return EvalResult.empty(allocator);
+            var out: std.ArrayListUnmanaged(std.json.Value) = .empty;
+            try combinationsInto(allocator, value.array.items, &.{}, &out);
+            return EvalResult.multi(allocator, try out.toOwnedSlice(allocator));
+        },
+        .toarray => {
+            if (value == .array) return try EvalResult.single(allocator, value);
+            const items = try allocator.dupe(std.json.Value, &.{value});
+            return try EvalResult.single(allocator, .{ .array = .{ .items = items, .capacity = items.len, .allocator = allocator } });
+        },
+        .any, .all => {
+            if (value != .array) return EvalResult.empty(allocator);
+            // any stops at the first truthy element, all at the first falsy one
+            for (value.array.items) |item| {
+                if (isTruthy(item) == (kind == .any)) return try EvalResult.single(allocator, .{ .bool = kind == .any });
+            }
+            return try EvalResult.single(allocator, .{ .bool = kind == .all });
+        },
+        .infinite => return try EvalResult.single(allocator, .{ .float = std.math.inf(f64) }),
+        .nan => return try EvalResult.single(allocator, .{ .float = std.math.nan(f64) }),
+        .isinfinite, .isnan, .isnormal => {
+            const f = getNumeric(value) orelse return raiseMessage(allocator, "{s} number required", .{try describeValue(allocator, value)});
+            const result = switch (kind) {
+                .isinfinite => std.math.isInf(f),
+                .isnan => std.math.isNan(f),
+                else => std.math.isNormal(f),
+            };
+            return try EvalResult.single(allocator, .{ .bool = result });
+        },
+        .trunc, .rint, .nearbyint, .cbrt, .exp2, .exp10, .log, .log1p, .expm1, .logb, .significand, .gamma, .lgamma, .tgamma, .sinh, .cosh, .tanh, .asinh, .acosh, .atanh => {
+            const f = getNumeric(value) orelse return raiseMessage(allocator, "{s} number required", .{try describeValue(allocator, value)});
+            return try EvalResult.single(allocator, .{ .float = libm(kind, f) });
+        },
+        .frexp, .modf, .lgamma_r => {
+            const f = getNumeric(value) orelse return raiseMessage(allocator, "{s} number required", .{try describeValue(allocator, value)});
+            const pair = try allocator.alloc(std.json.Value, 2);
+            switch (kind) {
+                .frexp => {
+                    const parts = std.math.frexp(f);
+                    pair[0] = .{ .float = parts.significand };
+                    pair[1] = .{ .integer = parts.exponent };
+                },
+                .modf => {
+                    const parts = std.math.modf(f);
+                    pair[0] = .{ .float = parts.fpart };
+                    pair[1] = .{ .float = parts.ipart };
+                },
+                else => {
+                    pair[0] = .{ .float = std.math.lgamma(f64, f) };
+                    pair[1] = .{ .integer = if (std.math.signbit(std.math.gamma(f64, f))) -1 else 1 };
+                },
+            }
+            return try EvalResult.single(allocator, .{ .array = .{ .items = pair, .capacity = pair.len, .allocator = allocator } });
+        },
+        .builtins => {
+            var names: std.ArrayListUnmanaged(std.json.Value) = .empty;
+            for (builtins.categories) |category| {
+                for (category.builtins) |builtin| {
+                    if (builtin.status == .unsupported or !builtins.isFunction(builtin)) continue;
+                    const name = try std.fmt.allocPrint(allocator, "{s}/{d}", .{ builtin.name, builtin.arity });
+                    try names.append(allocator, .{ .string = name });
+                }
+            }
+            const slice = try names.toOwnedSlice(allocator);
+            return try EvalResult.single(allocator, .{ .array = .{ .items = slice, .capacity = slice.len, .allocator = allocator } });
+        },
+        .input_filename => return try EvalResult.single(allocator, .null),
+        .have_literal_numbers => return try EvalResult.single(allocator, .{ .bool = true }),
+        .have_decnum => return try EvalResult.single(allocator, .{ .bool = false }),
+        .debug => {
+            std.debug.print("[\"DEBUG:\",{s}]\n", .{try toJsonText(allocator, value)});
+            return try EvalResult.single(allocator, value);
+        },
+        .stderr => {
+            std.debug.print("{s}", .{try toJsonText(allocator, value)});
+            return try EvalResult.single(allocator, value);
+        },
+    }
+}
+
+/// Thread-local sequence counter for the `seq` generator function.
+///
+/// IMPORTANT: Thread-local state behavior:
+/// - Counter starts at 1 for each thread
+/// - Persists across all records processed within the same thread/process
+/// - Resets when the process exits (not between pipeline invocations within the same process)
+/// - For single-threaded CLI use (jn filter), this means the counter increments
+///   monotonically for the entire pipeline run
+/// - NOT suitable for generating unique IDs across separate process invocations
+threadlocal var seq_counter: i64 = 1;
+
+// Helper: Safely convert timestamp to u64, returning null for negative values.
+// Negative timestamps can occur with misconfigured system clocks (before 1970).
+// Callers should return a JSON null value to preserve record flow in pipelines.
+fn safeTimestampToU64(timestamp: i64) ?u64 {
+    if (timestamp < 0) return null;
+    return @intCast(timestamp);
+}
+
+// Helper: Convert epoch day (days since 1970-01-01) to year/month/day
+const YearMonthDay = struct { year: u32, month: u32, day: u32 };
+
+fn epochDayToYmd(epoch_day: u64) YearMonthDay {
+    // Algorithm from http://howardhinnant.github.io/date_algorithms.html
+    const z = epoch_day + 719468;
+    const era = z / 146097;
+    const doe = z - era * 146097;
+    const yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    const y = yoe + era * 400;
+    const doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    const mp = (5 * doy + 2) / 153;
+    const d = doy - (153 * mp + 2) / 5 + 1;
+    const m = if (mp < 10) mp + 3 else mp - 9;
+    const year = if (m <= 2) y + 1 else y;
+
+    return .{
+        .year = @intCast(year),
+        .month = @intCast(m),
+        .day = @intCast(d),
+    };
+}
+
+// Helper: Calculate ISO week number (1-53)
+fn epochDayToIsoWeek(epoch_day: u64) u32 {
+    // ISO 8601 week number formula
+    // Week 1 is the week containing the first Thursday of the year
+
+    const ymd = epochDayToYmd(epoch_day);
+
+    // Calculate day of year (1-indexed)
+    const days_in_months = [_]u32{ 0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334 };
+    var day_of_year: u32 = days_in_months[ymd.month - 1] + ymd.day;
+
+    // Add leap day if after Feb in a leap year
+    if (ymd.month > 2 and isLeapYear(ymd.year)) {
+        day_of_year += 1;
+    }
+
+    // Day of week: 1=Monday, 7=Sunday (ISO)
+    // Jan 1, 1970 was Thursday
+    const dow: u32 = @intCast(@mod(epoch_day + 3, 7) + 1);
+
+    // ISO week number formula:
+    // week = (day_of_year - dow + 10) / 7
+    // This formula accounts for the Thursday rule
+    const week_calc: i32 = @divFloor(@as(i32, @intCast(day_of_year)) - @as(i32, @intCast(dow)) + 10, 7);
+
+    if (week_calc < 1) {
+        // Week belongs to previous year - calculate weeks in previous year
+        return weeksInYear(ymd.year - 1);
+    } else if (week_calc > weeksInYear(ymd.year)) {
+        // Week belongs to next year
+        return 1;
+    }
+
+    return @intCast(week_calc);
+}
+
+fn isLeapYear(year: u32) bool {
+    return (year % 4 == 0 and year % 100 != 0) or (year % 400 == 0);
+}
+
+fn weeksInYear(year: u32) u32 {
+    // A year has 53 weeks if Jan 1 is Thursday, or
+    // Jan 1 is Wednesday and it's a leap year
+    const jan1_dow = getJan1Weekday(year);
+    if (jan1_dow == 4) return 53; // Thursday
+    if (jan1_dow == 3 and isLeapYear(year)) return 53; // Wednesday + leap
+    return 52;
+}
+
+fn getJan1Weekday(year: u32) u32 {
+    // Day of week for Jan 1 of given year (1=Monday, 7=Sunday)
+    // Using a standard formula
+    const y = year - 1;
+    const day = (1 + 5 * (y % 4) + 4 * (y % 100) + 6 * (y % 400)) % 7;
+    // Result: 0=Sunday, 1=Monday, ..., 6=Saturday
+    // Convert to ISO: 1=Monday, 7=Sunday
+    return if (day == 0) 7 else day;
+}
+
+// Base62 alphabet for shortid/sid
+const base62_alphabet = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+fn generateBase62(allocator: std.mem.Allocator, len: usize) ![]u8 {
+    var result = try allocator.alloc(u8, len);
+    var rand_bytes: [16]u8 = undefined;
+    std.crypto.random.bytes(&rand_bytes);
+
+    for (0..len) |i| {
+        const rand_idx = rand_bytes[i % 16] % 62;
+        result[i] = base62_alphabet[rand_idx];
+    }
+    return result;
+}
+
+// Sprint 07: NanoID - URL-safe alphabet
+const nanoid_alphabet = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_-";
+
+fn generateNanoId(allocator: std.mem.Allocator, len: usize) ![]u8 {
+    var result = try allocator.alloc(u8, len);
+    var rand_bytes: [32]u8 = undefined;
+    std.crypto.random.bytes(&rand_bytes);
+
+    for (0..len) |i| {
+        const rand_idx = rand_bytes[i % 32] % 64;
+        result[i] = nanoid_alphabet[rand_idx];
+    }
+    return result;
+}
+
+// Sprint 07: ULID - Crockford Base32, time-sortable
+const crockford_alphabet = "0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+fn generateUlid(allocator: std.mem.Allocator) ![]u8 {
+    var result = try allocator.alloc(u8, 26);
+
+    // Get timestamp in milliseconds
+    const timestamp_ns = std.time.nanoTimestamp();
+    var timestamp_ms: u64 = @intCast(@divFloor(timestamp_ns, std.time.ns_per_ms));
+
+    // Encode 48-bit timestamp as first 10 characters (Crockford Base32)
+    var i: usize = 9;
+    while (i < 10) : (i -%= 1) {
+        result[i] = crockford_alphabet[@intCast(timestamp_ms & 0x1f)];
+        timestamp_ms >>= 5;
+        if (i == 0) break;
+    }
+
+    // Generate 80 bits of randomness for remaining 16 characters
+    var rand_bytes: [10]u8 = undefined;
+    std.crypto.random.bytes(&rand_bytes);
+
+    // Encode random bytes as Base32
+    // Each char is 5 bits, so 16 chars = 80 bits = 10 bytes
+    var rand_bits: u80 = 0;
+    for (rand_bytes) |b| {
+        rand_bits = (rand_bits << 8) | b;
+    }
+
+    var j: usize = 25;
+    while (j >= 10) : (j -%= 1) {
+        result[j] = crockford_alphabet[@intCast(rand_bits & 0x1f)];
+        rand_bits >>= 5;
+        if (j == 10) break;
+    }
+
+    return result;
+}
+
+// Sprint 07: UUID v7 - time-sortable UUID
+fn generateUuid7(allocator: std.mem.Allocator) ![]u8 {
+    // UUID v7: xxxxxxxx-xxxx-7xxx-yxxx-xxxxxxxxxxxx
+    // First 48 bits: Unix timestamp in milliseconds
+    // Next 4 bits: version (7)
+    // Next 12 bits: random
+    // Next 2 bits: variant (10)
+    // Remaining 62 bits: random
+
+    const timestamp_ns = std.time.nanoTimestamp();
+    const timestamp_ms: u64 = @intCast(@divFloor(timestamp_ns, std.time.ns_per_ms));
+
+    var bytes: [16]u8 = undefined;
+
+    // Timestamp (48 bits, big-endian)
+    bytes[0] = @intCast((timestamp_ms >> 40) & 0xff);
+    bytes[1] = @intCast((timestamp_ms >> 32) & 0xff);
+    bytes[2] = @intCast((timestamp_ms >> 24) & 0xff);
+    bytes[3] = @intCast((timestamp_ms >> 16) & 0xff);
+    bytes[4] = @intCast((timestamp_ms >> 8) & 0xff);
+    bytes[5] = @intCast(timestamp_ms & 0xff);
+
+    // Random bytes for the rest
+    std.crypto.random.bytes(bytes[6..]);
+
+    // Set version 7
+    bytes[6] = (bytes[6] & 0x0f) | 0x70;
+    // Set variant (10xx)
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    return try std.fmt.allocPrint(allocator, "{x:0>2}{x:0>2}{x:0>2}{x:0>2}-{x:0>2}{x:0>2}-{x:0>2}{x:0>2}-{x:0>2}{x:0>2}-{x:0>2}{x:0>2}{x:0>2}{x:0>2}{x:0>2}{x:0>2}", .{
+        bytes[0],  bytes[1],  bytes[2],  bytes[3],
+        bytes[4],  bytes[5],  bytes[6],  bytes[7],
+        bytes[8],  bytes[9],  bytes[10], bytes[11],
+        bytes[12], bytes[13], bytes[14], bytes[15],
+    });
+}
+
+/// Sprint 07: XID - compact, sortable ID
+/// XID alphabet is lowercase hex-extended with more URL-safe chars
+const xid_alphabet = "0123456789abcdefghijklmnopqrstuv";
+
+/// Thread-local XID counter (24-bit, wraps at 16 million).
+///
+/// IMPORTANT: Thread-local state behavior:
+/// - Counter wraps around after 2^24 (16,777,216) XIDs within the same thread
+/// - Combined with machine_id and timestamp, provides uniqueness within a process
+/// - Resets to 0 when the process exits
+/// - For single-threaded CLI use, XIDs are unique within a single pipeline run
+threadlocal var xid_counter: u24 = 0;
+
+/// Thread-local machine ID for XID generation (3 random bytes, cached).
+///
+/// Initialized once per thread on first XID generation. Provides uniqueness
+/// across different processes generating XIDs at the same timestamp.
+threadlocal var xid_machine_id: ?[3]u8 = null;
+
+fn generateXid(allocator: std.mem.Allocator) ![]u8 {
+    var result = try allocator.alloc(u8, 20);
+
+    // 4 bytes: unix timestamp (seconds)
+    const timestamp: u32 = @intCast(std.time.timestamp());
+
+    // 3 bytes: machine ID (random, cached)
+    if (xid_machine_id == null) {
+        var machine: [3]u8 = undefined;
+        std.crypto.random.bytes(&machine);
+        xid_machine_id = machine;
+    }
+    const machine_id = xid_machine_id.?;
+
+    // 2 bytes: PID (use random since we don't have easy PID access)
+    var pid_bytes: [2]u8 = undefined;
+    std.crypto.random.bytes(&pid_bytes);
+
+    // 3 bytes: counter
+    xid_counter +%= 1;
+    const counter = xid_counter;
+
+    // Pack into 12 bytes
+    var bytes: [12]u8 = undefined;
+    bytes[0] = @intCast((timestamp >> 24) & 0xff);
+    bytes[1] = @intCast((timestamp >> 16) & 0xff);
+    bytes[2] = @intCast((timestamp >> 8) & 0xff);
+    bytes[3] = @intCast(timestamp & 0xff);
+    bytes[4] = machine_id[0];
+    bytes[5] = machine_id[1];
+    bytes[6] = machine_id[2];
+    bytes[7] = pid_bytes[0];
+    bytes[8] = pid_bytes[1];
+    bytes[9] = @intCast((counter >> 16) & 0xff);
+    bytes[10] = @intCast((counter >> 8) & 0xff);
+    bytes[11] = @intCast(counter & 0xff);
+
+    // Encode as base32 (20 chars for 12 bytes * 8 bits / 5 bits per char = 19.2, round to 20)
+    // Actually XID uses a specific encoding: 12 bytes -> 20 chars
+    // Each 5 bits becomes one character
+    var bits: u96 = 0;
+    for (bytes) |b| {
+        bits = (bits << 8) | b;
+    }
+
+    var i: usize = 19;
+    while (i < 20) : (i -%= 1) {
+        result[i] = xid_alphabet[@intCast(bits & 0x1f)];
+        bits >>= 5;
+        if (i == 0) break;
+    }
+
+    return result;
+}
+
+// Sprint 06: Case conversion helpers
+
+/// Convert string to snake_case
+/// "HelloWorld" -> "hello_world"
+/// "hello-world" -> "hello_world"
+/// "Hello World" -> "hello_world"
+fn toSnakeCase(allocator: std.mem.Allocator, s: []const u8) ![]u8 {
+    if (s.len == 0) return try allocator.alloc(u8, 0);
+
+    // First pass: count output length
+    var output_len: usize = 0;
+    var prev_lower = false;
+    for (s) |c| {
+        if (c == ' ' or c == '-' or c == '_') {
+            if (output_len > 0) output_len += 1; // add underscore
+            prev_lower = false;
+        } else if (c >= 'A' and c <= 'Z') {
+            if (prev_lower and output_len > 0) output_len += 1; // add underscore before uppercase
+            output_len += 1;
+            prev_lower = false;
+        } else {
+            output_len += 1;
+            prev_lower = (c >= 'a' and c <= 'z');
+        }
+    }
+
+    // Second pass: build output
+    var result = try allocator.alloc(u8, output_len);
+    var pos: usize = 0;
+    prev_lower = false;
+
+    for (s) |c| {
+        if (c == ' ' or c == '-' or c == '_') {
+            if (pos > 0) {
+                result[pos] = '_';
+                pos += 1;
+            }
+            prev_lower = false;
+        } else if (c >= 'A' and c <= 'Z') {
+            if (prev_lower and pos > 0) {
+                result[pos] = '_';
+                pos += 1;
+            }
+            result[pos] = c + 32; // lowercase
+            pos += 1;
+            prev_lower = false;
+        } else {
+            result[pos] = c;
+            pos += 1;
+            prev_lower = (c >= 'a' and c <= 'z');
+        }
+    }
+
+    return result[0..pos];
+}
+
+/// Convert string to camelCase or PascalCase
+/// "hello_world" -> "helloWorld" (pascal=false)
+/// "hello_world" -> "HelloWorld" (pascal=true)
+fn toCamelCase(allocator: std.mem.Allocator, s: []const u8, pascal: bool) ![]u8 {
+    if (s.len == 0) return try allocator.alloc(u8, 0);
+
+    // First pass: count output length (skip separators)
+    var output_len: usize = 0;
+    for (s) |c| {
+        if (c != ' ' and c != '-' and c != '_') {
+            output_len += 1;
+        }
+    }
+
+    var result = try allocator.alloc(u8, output_len);
+    var pos: usize = 0;
+    var capitalize_next = pascal;
+
+    for (s) |c| {
+        if (c == ' ' or c == '-' or c == '_') {
+            capitalize_next = true;
+        } else if (capitalize_next) {
+            if (c >= 'a' and c <= 'z') {
+                result[pos] = c - 32;
+            } else {
+                result[pos] = c;
+            }
+            pos += 1;
+            capitalize_next = false;
+        } else {
+            if (c >= 'A' and c <= 'Z') {
+                result[pos] = c + 32;
+            } else {
+                result[pos] = c;
+            }
+            pos += 1;
+        }
+    }
+
+    return result[0..pos];
+}
+
+/// Convert string to kebab-case
+/// "HelloWorld" -> "hello-world"
+/// "hello_world" -> "hello-world"
+fn toKebabCase(allocator: std.mem.Allocator, s: []const u8) ![]u8 {
+    if (s.len == 0) return try allocator.alloc(u8, 0);
+
+    // First pass: count output length
+    var output_len: usize = 0;
+    var prev_lower = false;
+    for (s) |c| {
+        if (c == ' ' or c == '-' or c == '_') {
+            if (output_len > 0) output_len += 1;
+            prev_lower = false;
+        } else if (c >= 'A' and c <= 'Z') {
+            if (prev_lower and output_len > 0) output_len += 1;
+            output_len += 1;
+            prev_lower = false;
+        } else {
+            output_len += 1;
+            prev_lower = (c >= 'a' and c <= 'z');
+        }
+    }
+
+    // Second pass: build output
+    var result = try allocator.alloc(u8, output_len);
+    var pos: usize = 0;
+    prev_lower = false;
+
+    for (s) |c| {
+        if (c == ' ' or c == '-' or c == '_') {
+            if (pos > 0) {
+                result[pos] = '-';
+                pos += 1;
+            }
+            prev_lower = false;
+        } else if (c >= 'A' and c <= 'Z') {
+            if (prev_lower and pos > 0) {
+                result[pos] = '-';
+                pos += 1;
+            }
+            result[pos] = c + 32;
+            pos += 1;
+            prev_lower = false;
+        } else {
+            result[pos] = c;
+            pos += 1;
+            prev_lower = (c >= 'a' and c <= 'z');
+        }
+    }
+
+    return result[0..pos];
+}
+
+/// Convert string to URL-safe slug
+/// "Hello World!" -> "hello-world"
+/// "This & That" -> "this-that"
+fn toSlug(allocator: std.mem.Allocator, s: []const u8) ![]u8 {
+    if (s.len == 0) return try allocator.alloc(u8, 0);
+
+    // First pass: count output length
+    var output_len: usize = 0;
+    var prev_dash = true; // Start true to avoid leading dash
+
+    for (s) |c| {
+        if ((c >= 'a' and c <= 'z') or (c >= '0' and c <= '9')) {
+            output_len += 1;
+            prev_dash = false;
+        } else if (c >= 'A' and c <= 'Z') {
+            output_len += 1;
+            prev_dash = false;
+        } else if (!prev_dash) {
+            // Any non-alphanumeric becomes a dash
+            output_len += 1;
+            prev_dash = true;
+        }
+    }
+
+    // Remove trailing dash if present
+    if (output_len > 0 and prev_dash) output_len -= 1;
+
+    var result = try allocator.alloc(u8, output_len);
+    var pos: usize = 0;
+    prev_dash = true;
+
+    for (s) |c| {
+        if ((c >= 'a' and c <= 'z') or (c >= '0' and c <= '9')) {
+            result[pos] = c;
+            pos += 1;
+            prev_dash = false;
+        } else if (c >= 'A' and c <= 'Z') {
+            result[pos] = c + 32; // lowercase
+            pos += 1;
+            prev_dash = false;
+        } else if (!prev_dash and pos < output_len) {
+            result[pos] = '-';
+            pos += 1;
+            prev_dash = true;
+        }
+    }
+
+    return result[0..pos];
+}
+
+// Sprint 08: XID timestamp decoding
+fn decodeXidTimestamp(s: []const u8) ?u32 {
+    // XID is 20 chars base32 encoding 12 bytes (96 bits)
+    // First 4 bytes (32 bits) are the timestamp
+    // We need to decode all 20 chars and extract the top 32 bits
+    if (s.len < 20) return null;
+
+    // Decode all 20 characters to 96 bits
+    var bits: u128 = 0; // Use u128 to hold 96 bits safely
+    for (s[0..20]) |c| {
+        const val: u128 = if (c >= '0' and c <= '9')
+            c - '0'
+        else if (c >= 'a' and c <= 'v')
+            c - 'a' + 10
+        else
+            return null; // Invalid character
+        bits = (bits << 5) | val;
+    }
+
+    // 20 chars * 5 bits = 100 bits, but we only have 96 bits of data
+    // The encoding uses the full 100 bits, so top 4 bits are padding
+    // Extract timestamp from bits 64-95 (after removing 4 padding bits from top)
+    const timestamp: u32 = @truncate(bits >> 64);
+    return timestamp;
+}
+
+// Sprint 08: Format seconds as human-friendly "ago" string
+fn formatAgo(allocator: std.mem.Allocator, diff: i64) ![]u8 {
+    const abs_diff: u64 = if (diff < 0) @intCast(-diff) else @intCast(diff);
+    const suffix = if (diff < 0) " from now" else " ago";
+
+    if (abs_diff < 60) {
+        return try std.fmt.allocPrint(allocator, "{d} second{s}{s}", .{
+            abs_diff,
+            if (abs_diff == 1) "" else "s",
+            suffix,
+        });
+    }
+
+    const minutes = abs_diff / 60;
+    if (minutes < 60) {
+        return try std.fmt.allocPrint(allocator, "{d} minute{s}{s}", .{
+            minutes,
+            if (minutes == 1) "" else "s",
+            suffix,
+        });
+    }
+
+    const hours = abs_diff / 3600;
+    const remaining_mins = (abs_diff % 3600) / 60;
+    if (hours < 24) {
+        if (remaining_mins > 0) {
+            return try std.fmt.allocPrint(allocator, "{d} hour{s}, {d} minute{s}{s}", .{
+                hours,
+                if (hours == 1) "" else "s",
+                remaining_mins,
+                if (remaining_mins == 1) "" else "s",
+                suffix,
+            });
+        } else {
+            return try std.fmt.allocPrint(allocator, "{d} hour{s}{s}", .{
+                hours,
+                if (hours == 1) "" else "s",
+                suffix,
+            });
+        }
+    }
+
+    const days = abs_diff / 86400;
+    const remaining_hours = (abs_diff % 86400) / 3600;
+    if (days < 30) {
+        if (remaining_hours > 0) {
+            return try std.fmt.allocPrint(allocator, "{d} day{s}, {d} hour{s}{s}", .{
+                days,
+                if (days == 1) "" else "s",
+                remaining_hours,
+                if (remaining_hours == 1) "" else "s",
+                suffix,
+            });
+        } else {
+            return try std.fmt.allocPrint(allocator, "{d} day{s}{s}", .{
+                days,
+                if (days == 1) "" else "s",
+                suffix,
+            });
+        }
+    }
+
+    const months = days / 30;
+    const remaining_days = days % 30;
+    if (months < 12) {
+        if (remaining_days > 0) {
+            return try std.fmt.allocPrint(allocator, "{d} month{s}, {d} day{s}{s}", .{
+                months,
+                if (months == 1) "" else "s",
+                remaining_days,
+                if (remaining_days == 1) "" else "s",
+                suffix,
+            });
+        } else {
+            return try std.fmt.allocPrint(allocator, "{d} month{s}{s}", .{
+                months,
+                if (months == 1) "" else "s",
+                suffix,
+            });
+        }
+    }
+
+    const years = days / 365;
+    const remaining_months = (days % 365) / 30;
+    if (remaining_months > 0) {
+        return try std.fmt.allocPrint(allocator, "{d} year{s}, {d} month{s}{s}", .{
+            years,
+            if (years == 1) "" else "s",
+            remaining_months,
+            if (remaining_months == 1) "" else "s",
+            suffix,
+        });
+    } else {
+        return try std.fmt.allocPrint(allocator, "{d} year{s}{s}", .{
+            years,
+            if (years == 1) "" else "s",
+            suffix,
+        });
+    }
+}
+
+// Sprint 03: Helper functions for sorting/comparing JSON values
+fn jsonLessThan(_: void, a: std.json.Value, b: std.json.Value) bool {
+    return jsonOrder(a, b) == .lt;
+}
+
+/// jq's ordering: null < false < true < numbers < strings < arrays < objects.
+/// Arrays compare element by element; objects by their sorted keys, then by
+/// their values in key order.
+fn jsonOrder(a: std.json.Value, b: std.json.Value) std.math.Order {
+    // Compare by type first, then value
+    const type_order = struct {
+        fn order(v: std.json.Value) u8 {
+            return switch (v) {
+                .null => 0,
+                .bool => 1,
+                .integer, .float, .number_string => 2,
+                .string => 3,
+                .array => 4,
+                .object => 5,
+                else => 6,
+            };
+        }
+    };
+
+    const a_type = type_order.order(a);
+    const b_type = type_order.order(b);
+
+    if (a_type != b_type) return std.math.order(a_type, b_type);
+
+    return switch (a) {
+        .null => .eq,
+        .bool => |ab| std.math.order(@intFromBool(ab), @intFromBool(b.bool)),
+        .integer, .float, .number_string => number.order(a, b) orelse .eq,
+        .string => |as| std.mem.order(u8, as, b.string),
+        .array => |aa| {
+            const common = @min(aa.items.len, b.array.items.len);
+            for (aa.items[0..common], b.array.items[0..common]) |av, bv| {
+                const item_order = jsonOrder(av, bv);
+                if (item_order != .eq) return item_order;
+            }
+            return std.math.order(aa.items.len, b.array.items.len);
+        },
+        .object => |ao| {
+            // Walk both key sets in sorted order without allocating
+            var a_key = nextKey(ao, null);
+            var b_key = nextKey(b.object, null);
+            while (a_key != null and b_key != null) {
+                const key_order = std.mem.order(u8, a_key.?, b_key.?);
+                if (key_order != .eq) return key_order;
+                a_key = nextKey(ao, a_key);
+                b_key = nextKey(b.object, b_key);
             }
-        },
-        // Sprint 06: Transform functions - String
-        .trim => {
-            switch (value) {
-                .string => |s| {
-                    const trimmed = std.mem.trim(u8, s, " \t\n\r");
-                    return try EvalResult.single(allocator, .{ .string = trimmed });
-                },
-                else => return EvalResult.empty(allocator),
+            if (a_key != null or b_key != null) return if (a_key == null) .lt else .gt;
+            var key = nextKey(ao, null);
+            while (key) |k| {
+                const value_order = jsonOrder(ao.get(k).?, b.object.get(k).?);
+                if (value_order != .eq) return value_order;
+                key = nextKey(ao, k);
             }
+            return .eq;
         },
-        .ltrim => {
-            switch (value) {
-                .string => |s| {
-                    const trimmed = std.mem.trimLeft(u8, s, " \t\n\r");
-                    return try EvalResult.single(allocator, .{ .string = trimmed });
-                },
-                else => return EvalResult.empty(allocator),
+    };
+}
+
+/// The smallest key of `obj` after `after`, in byte order.
+fn nextKey(obj: std.json.ObjectMap, after: ?[]const u8) ?[]const u8 {
+    var best: ?[]const u8 = null;
+    for (obj.keys()) |key| {
+        if (after) |prev| {
+            if (!std.mem.lessThan(u8, prev, key)) continue;
+        }
+        if (best == null or std.mem.lessThan(u8, key, best.?)) best = key;
+    }
+    return best;
+}
+
+fn jsonEqual(a: std.json.Value, b: std.json.Value) bool {
+    // Decimals equal numbers of the same value, however they are written
+    if (a == .number_string or b == .number_string) {
+        return (number.order(a, b) orelse return false) == .eq;
+    }
+    if (@intFromEnum(a) != @intFromEnum(b)) return false;
+    return switch (a) {
+        .null => true,
+        .bool => |ab| ab == b.bool,
+        .integer => |ai| ai == b.integer,
+        .float => |af| af == b.float,
+        .string => |as| std.mem.eql(u8, as, b.string),
+        .array => |aa| blk: {
+            const ba = b.array;
+            if (aa.items.len != ba.items.len) break :blk false;
+            for (aa.items, ba.items) |av, bv| {
+                if (!jsonEqual(av, bv)) break :blk false;
             }
+            break :blk true;
         },
-        .rtrim => {
-            switch (value) {
-                .string => |s| {
-                    const trimmed = std.mem.trimRight(u8, s, " \t\n\r");
-                    return try EvalResult.single(allocator, .{ .string = trimmed });
-                },
-                else => return EvalResult.empty(allocator),
+        .object => |ao| blk: {
+            const bo = b.object;
+            if (ao.count() != bo.count()) break :blk false;
+            var it = ao.iterator();
+            while (it.next()) |entry| {
+                const other = bo.get(entry.key_ptr.*) orelse break :blk false;
+                if (!jsonEqual(entry.value_ptr.*, other)) break :blk false;
             }
+            break :blk true;
         },
-        // Sprint 06: Type coercion
-        .@"int" => {
-            switch (value) {
-                .integer => return try EvalResult.single(allocator, value),
-                .float => |f| {
-                    if (f >= @as(f64, @floatFromInt(std.math.minInt(i64))) and
-                        f <= @as(f64, @floatFromInt(std.math.maxInt(i64))))
-                    {
-                        return try EvalResult.single(allocator, .{ .integer = @as(i64, @intFromFloat(f)) });
-                    }
-                    return try EvalResult.single(allocator, .{ .null = {} });
-                },
-                .string => |s| {
-                    if (std.fmt.parseInt(i64, s, 10)) |i| {
-                        return try EvalResult.single(allocator, .{ .integer = i });
-                    } else |_| {
-                        // Try parsing as float then truncating
-                        if (std.fmt.parseFloat(f64, s)) |f| {
-                            return try EvalResult.single(allocator, .{ .integer = @as(i64, @intFromFloat(f)) });
-                        } else |_| {
-                            return try EvalResult.single(allocator, .{ .null = {} });
-                        }
-                    }
-                },
-                .bool => |b| return try EvalResult.single(allocator, .{ .integer = if (b) 1 else 0 }),
-                .null => return try EvalResult.single(allocator, .{ .null = {} }),
-                else => return try EvalResult.single(allocator, .{ .null = {} }),
+        .number_string => unreachable,
+    };
+}
+
+fn evalObject(allocator: std.mem.Allocator, obj: ObjectExpr, value: std.json.Value, env: ?*const Env) EvalError!EvalResult {
+    var map = std.json.ObjectMap.init(allocator);
+
+    for (obj.fields) |field| {
+        // Evaluate key
+        const key: []const u8 = switch (field.key) {
+            .literal => |lit| lit,
+            .dynamic => |key_expr| blk: {
+                const key_result = try evalExprWithEnv(allocator, key_expr, value, env);
+                if (key_result.values.len == 0) continue;
+                switch (key_result.values[0]) {
+                    .string => |s| break :blk s,
+                    else => continue,
+                }
+            },
+        };
+
+        // Evaluate value
+        const val_result = try evalExprWithEnv(allocator, field.value, value, env);
+        if (val_result.values.len > 0) {
+            try map.put(key, val_result.values[0]);
+        }
+    }
+
+    return try EvalResult.single(allocator, .{ .object = map });
+}
+
+fn evalArithmetic(allocator: std.mem.Allocator, arith: ArithmeticExpr, value: std.json.Value, env: ?*const Env) EvalError!EvalResult {
+    const left_result = try evalExprWithEnv(allocator, arith.left, value, env);
+    const right_result = try evalExprWithEnv(allocator, arith.right, value, env);
+
+    if (left_result.values.len == 0 or right_result.values.len == 0) {
+        return EvalResult.empty(allocator);
+    }
+
+    const result = try arithValues(allocator, arith.op, left_result.values[0], right_result.values[0]);
+    return try EvalResult.single(allocator, result);
+}
+
+/// Apply an arithmetic operator to two values, raising a type error if the
+/// types don't support it.
+pub fn arithValues(allocator: std.mem.Allocator, op: ArithOp, left_val: std.json.Value, right_val: std.json.Value) EvalError!std.json.Value {
+    // String concatenation with +
+    if (op == .add) {
+        // null is the identity for + (jq semantics), so `.count += 1` starts from 0
+        if (left_val == .null) return right_val;
+        if (right_val == .null) return left_val;
+        if (left_val == .string and right_val == .string) {
+            const result = try std.fmt.allocPrint(allocator, "{s}{s}", .{ left_val.string, right_val.string });
+            return .{ .string = result };
+        }
+        // Object merge with + (jq semantics: right overrides left)
+        if (left_val == .object and right_val == .object) {
+            var merged = std.json.ObjectMap.init(allocator);
+            // Copy all from left
+            var left_iter = left_val.object.iterator();
+            while (left_iter.next()) |entry| {
+                try merged.put(entry.key_ptr.*, entry.value_ptr.*);
             }
-        },
-        .@"float" => {
-            switch (value) {
-                .float => return try EvalResult.single(allocator, value),
-                .integer => |i| return try EvalResult.single(allocator, .{ .float = @as(f64, @floatFromInt(i)) }),
-                .string => |s| {
-                    if (std.fmt.parseFloat(f64, s)) |f| {
-                        return try EvalResult.single(allocator, .{ .float = f });
-                    } else |_| {
-                        return try EvalResult.single(allocator, .{ .null = {} });
-                    }
-                },
-                .bool => |b| return try EvalResult.single(allocator, .{ .float = if (b) 1.0 else 0.0 }),
-                .null => return try EvalResult.single(allocator, .{ .null = {} }),
-                else => return try EvalResult.single(allocator, .{ .null = {} }),
+            // Copy all from right (overrides left)
+            var right_iter = right_val.object.iterator();
+            while (right_iter.next()) |entry| {
+                try merged.put(entry.key_ptr.*, entry.value_ptr.*);
             }
-        },
-        .@"bool" => {
-            // Truthy: true, non-zero numbers, non-empty strings, non-empty arrays/objects
-            // Falsy: false, 0, "", null, [], {}
-            const result = switch (value) {
-                .bool => |b| b,
-                .integer => |i| i != 0,
-                .float => |f| f != 0.0,
-                .string => |s| s.len > 0,
-                .array => |arr| arr.items.len > 0,
-                .object => |obj| obj.count() > 0,
-                .null => false,
-                else => false,
+            return .{ .object = merged };
+        }
+        // Array concatenation with +
+        if (left_val == .array and right_val == .array) {
+            var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
+            try result_list.appendSlice(allocator, left_val.array.items);
+            try result_list.appendSlice(allocator, right_val.array.items);
+            const result_slice = try result_list.toOwnedSlice(allocator);
+            return .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } };
+        }
+    }
+
+    // Numeric operations
+    const verb = switch (op) {
+        .add => "added",
+        .sub => "subtracted",
+        .mul => "multiplied",
+        .div => "divided",
+        .mod => "divided",
+    };
+    const left_num = getNumeric(left_val) orelse return arithTypeError(allocator, left_val, right_val, verb, "");
+    const right_num = getNumeric(right_val) orelse return arithTypeError(allocator, left_val, right_val, verb, "");
+
+    // + - * are exact on integers (past i64 too) and on decimals
+    if (op == .add or op == .sub or op == .mul) {
+        if (left_val == .integer and right_val == .integer) {
+            const a = left_val.integer;
+            const b = right_val.integer;
+            const checked = switch (op) {
+                .add => @addWithOverflow(a, b),
+                .sub => @subWithOverflow(a, b),
+                else => @mulWithOverflow(a, b),
             };
-            return try EvalResult.single(allocator, .{ .bool = result });
-        },
-        // Sprint 06: Case functions
-        .capitalize => {
-            switch (value) {
-                .string => |s| {
-                    if (s.len == 0) return try EvalResult.single(allocator, value);
-                    var result = try allocator.alloc(u8, s.len);
-                    @memcpy(result, s);
-                    if (result[0] >= 'a' and result[0] <= 'z') {
-                        result[0] = result[0] - 32;
-                    }
-                    return try EvalResult.single(allocator, .{ .string = result });
-                },
-                else => return EvalResult.empty(allocator),
-            }
-        },
-        .titlecase => {
-            switch (value) {
-                .string => |s| {
-                    if (s.len == 0) return try EvalResult.single(allocator, value);
-                    var result = try allocator.alloc(u8, s.len);
-                    var capitalize_next = true;
-                    for (s, 0..) |c, i| {
-                        if (c == ' ' or c == '\t' or c == '\n' or c == '-' or c == '_') {
-                            result[i] = c;
-                            capitalize_next = true;
-                        } else if (capitalize_next and c >= 'a' and c <= 'z') {
-                            result[i] = c - 32;
-                            capitalize_next = false;
-                        } else if (!capitalize_next and c >= 'A' and c <= 'Z') {
-                            result[i] = c + 32;
-                            capitalize_next = false;
-                        } else {
-                            result[i] = c;
-                            capitalize_next = false;
-                        }
-                    }
-                    return try EvalResult.single(allocator, .{ .string = result });
-                },
-                else => return EvalResult.empty(allocator),
-            }
-        },
-        .snakecase => {
-            switch (value) {
-                .string => |s| {
-                    const result = try toSnakeCase(allocator, s);
-                    return try EvalResult.single(allocator, .{ .string = result });
-                },
-                else => return EvalResult.empty(allocator),
+            if (checked[1] == 0) return .{ .integer = checked[0] };
+        }
+        if ((left_val == .integer and right_val == .integer) or left_val == .number_string or right_val == .number_string) {
+            if (try number.Decimal.fromValue(allocator, left_val)) |a| {
+                if (try number.Decimal.fromValue(allocator, right_val)) |b| {
+                    const exact = switch (op) {
+                        .add => try a.add(b, allocator),
+                        .sub => try a.sub(b, allocator),
+                        else => try a.mul(b, allocator),
+                    };
+                    return try exact.toValue(allocator);
+                }
             }
-        },
-        .camelcase => {
+        }
+    }
+
+    const result: f64 = switch (op) {
+        .add => left_num + right_num,
+        .sub => left_num - right_num,
+        .mul => left_num * right_num,
+        .div => if (right_num != 0) left_num / right_num else return arithTypeError(allocator, left_val, right_val, verb, " because the divisor is zero"),
+        .mod => if (right_num != 0) @mod(left_num, right_num) else return arithTypeError(allocator, left_val, right_val, verb, " because the divisor is zero"),
+    };
+
+    // Return integer if both inputs were integers and result is whole
+    if (left_val == .integer and right_val == .integer and @trunc(result) == result) {
+        return .{ .integer = @as(i64, @intFromFloat(result)) };
+    }
+    return .{ .float = result };
+}
+
+fn arithTypeError(allocator: std.mem.Allocator, left_val: std.json.Value, right_val: std.json.Value, verb: []const u8, reason: []const u8) EvalError {
+    const left = describeValue(allocator, left_val) catch |err| return err;
+    const right = describeValue(allocator, right_val) catch |err| return err;
+    return raiseMessage(allocator, "{s} and {s} cannot be {s}{s}", .{ left, right, verb, reason });
+}
+
+fn getNumeric(value: std.json.Value) ?f64 {
+    return number.toFloat(value);
+}
+
+// ============================================================================
+// Sprint 03: Additional Evaluators
+// ============================================================================
+
+fn evalStrFunc(allocator: std.mem.Allocator, sf: StrFuncExpr, value: std.json.Value) EvalError!EvalResult {
+    switch (sf.kind) {
+        .split => {
             switch (value) {
                 .string => |s| {
-                    const result = try toCamelCase(allocator, s, false);
-                    return try EvalResult.single(allocator, .{ .string = result });
+                    var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
+                    var iter = std.mem.splitSequence(u8, s, sf.arg);
+                    while (iter.next()) |part| {
+                        try result_list.append(allocator, .{ .string = part });
+                    }
+                    const result_slice = try result_list.toOwnedSlice(allocator);
+                    return try EvalResult.single(allocator, .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } });
                 },
                 else => return EvalResult.empty(allocator),
             }
         },
-        .kebabcase => {
+        .join => {
             switch (value) {
-                .string => |s| {
-                    const result = try toKebabCase(allocator, s);
-                    return try EvalResult.single(allocator, .{ .string = result });
-                },
+                .array => |arr| return try EvalResult.single(allocator, .{ .string = try joinValues(allocator, arr.items, sf.arg) }),
                 else => return EvalResult.empty(allocator),
             }
         },
-        // Sprint 07: More case functions
-        .pascalcase => {
+        .startswith => {
             switch (value) {
                 .string => |s| {
-                    // PascalCase is just camelCase with first letter uppercase
-                    const result = try toCamelCase(allocator, s, true);
-                    return try EvalResult.single(allocator, .{ .string = result });
+                    return try EvalResult.single(allocator, .{ .bool = std.mem.startsWith(u8, s, sf.arg) });
                 },
                 else => return EvalResult.empty(allocator),
             }
         },
-        .screamcase => {
+        .endswith => {
             switch (value) {
                 .string => |s| {
-                    // SCREAMING_SNAKE_CASE: snake_case but uppercase
-                    const snake = try toSnakeCase(allocator, s);
-                    // Convert to uppercase
-                    for (snake) |*c| {
-                        if (c.* >= 'a' and c.* <= 'z') {
-                            c.* = c.* - 32;
-                        }
-                    }
-                    return try EvalResult.single(allocator, .{ .string = snake });
+                    return try EvalResult.single(allocator, .{ .bool = std.mem.endsWith(u8, s, sf.arg) });
                 },
                 else => return EvalResult.empty(allocator),
             }
         },
-        // Sprint 06: Predicates
-        .empty => {
-            const result = switch (value) {
-                .string => |s| s.len == 0,
-                .array => |arr| arr.items.len == 0,
-                .object => |obj| obj.count() == 0,
-                else => false,
-            };
-            return try EvalResult.single(allocator, .{ .bool = result });
-        },
-        // Sprint 06: String splitting
-        .words => {
+        .contains => {
             switch (value) {
                 .string => |s| {
-                    var words_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
-                    var iter = std.mem.tokenizeAny(u8, s, " \t\n\r");
-                    while (iter.next()) |word| {
-                        try words_list.append(allocator, .{ .string = word });
-                    }
-                    const result_slice = try words_list.toOwnedSlice(allocator);
-                    return try EvalResult.single(allocator, .{ .array = .{
-                        .items = result_slice,
-                        .capacity = result_slice.len,
-                        .allocator = allocator,
-                    } });
+                    return try EvalResult.single(allocator, .{ .bool = std.mem.indexOf(u8, s, sf.arg) != null });
                 },
                 else => return EvalResult.empty(allocator),
             }
         },
-        .lines => {
+        .ltrimstr => {
             switch (value) {
                 .string => |s| {
-                    var lines_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
-                    var iter = std.mem.splitSequence(u8, s, "\n");
-                    while (iter.next()) |line| {
-                        // Trim \r from line endings
-                        const trimmed_line = std.mem.trimRight(u8, line, "\r");
-                        try lines_list.append(allocator, .{ .string = trimmed_line });
+                    if (std.mem.startsWith(u8, s, sf.arg)) {
+                        return try EvalResult.single(allocator, .{ .string = s[sf.arg.len..] });
                     }
-                    const result_slice = try lines_list.toOwnedSlice(allocator);
-                    return try EvalResult.single(allocator, .{ .array = .{
-                        .items = result_slice,
-                        .capacity = result_slice.len,
-                        .allocator = allocator,
-                    } });
+                    return try EvalResult.single(allocator, value);
                 },
                 else => return EvalResult.empty(allocator),
             }
         },
-        .chars => {
+        .rtrimstr => {
             switch (value) {
                 .string => |s| {
-                    var chars_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
-                    for (s) |c| {
-                        const char_str = try allocator.alloc(u8, 1);
-                        char_str[0] = c;
-                        try chars_list.append(allocator, .{ .string = char_str });
+                    if (std.mem.endsWith(u8, s, sf.arg)) {
+                        return try EvalResult.single(allocator, .{ .string = s[0 .. s.len - sf.arg.len] });
                     }
-                    const result_slice = try chars_list.toOwnedSlice(allocator);
-                    return try EvalResult.single(allocator, .{ .array = .{
-                        .items = result_slice,
-                        .capacity = result_slice.len,
-                        .allocator = allocator,
-                    } });
+                    return try EvalResult.single(allocator, value);
                 },
                 else => return EvalResult.empty(allocator),
             }
         },
-        // Sprint 06: Slug
-        .slugify => {
+        .has => {
             switch (value) {
-                .string => |s| {
-                    const result = try toSlug(allocator, s);
-                    return try EvalResult.single(allocator, .{ .string = result });
+                .object => |obj| {
+                    const exists = obj.get(sf.arg) != null;
+                    return try EvalResult.single(allocator, .{ .bool = exists });
                 },
-                else => return EvalResult.empty(allocator),
-            }
-        },
-        // Sprint 08: Time functions
-        .xid_time => {
-            // Extract epoch seconds from XID string (20 chars total, first 4 bytes = timestamp)
-            switch (value) {
-                .string => |s| {
-                    if (s.len < 20) return try EvalResult.single(allocator, .null);
-                    const timestamp = decodeXidTimestamp(s) orelse return try EvalResult.single(allocator, .null);
-                    return try EvalResult.single(allocator, .{ .integer = @intCast(timestamp) });
+                .array => |arr| {
+                    // jq: has("0") on arrays checks for index 0 as string
+                    if (std.fmt.parseInt(usize, sf.arg, 10)) |idx| {
+                        const exists = idx < arr.items.len;
+                        return try EvalResult.single(allocator, .{ .bool = exists });
+                    } else |_| {
+                        return try EvalResult.single(allocator, .{ .bool = false });
+                    }
                 },
-                else => return EvalResult.empty(allocator),
-            }
-        },
-        .delta => {
-            // Seconds since a timestamp (accepts epoch int or ISO string)
-            const now_secs: i64 = std.time.timestamp();
-            const then_secs: i64 = switch (value) {
-                .integer => |i| i,
-                .string => |s| parseIsoTimestamp(s) orelse return try EvalResult.single(allocator, .null),
-                else => return EvalResult.empty(allocator),
-            };
-            return try EvalResult.single(allocator, .{ .integer = now_secs - then_secs });
-        },
-        .ago => {
-            // Human-friendly relative time string
-            const now_secs: i64 = std.time.timestamp();
-            const then_secs: i64 = switch (value) {
-                .integer => |i| i,
-                .string => |s| parseIsoTimestamp(s) orelse return try EvalResult.single(allocator, .null),
-                else => return EvalResult.empty(allocator),
-            };
-            const diff = now_secs - then_secs;
-            const ago_str = try formatAgo(allocator, diff);
-            return try EvalResult.single(allocator, .{ .string = ago_str });
-        },
-    }
-}
-
-/// Thread-local sequence counter for the `seq` generator function.
-///
-/// IMPORTANT: Thread-local state behavior:
-/// - Counter starts at 1 for each thread
-/// - Persists across all records processed within the same thread/process
-/// - Resets when the process exits (not between pipeline invocations within the same process)
-/// - For single-threaded CLI use (jn filter), this means the counter increments
-///   monotonically for the entire pipeline run
-/// - NOT suitable for generating unique IDs across separate process invocations
-threadlocal var seq_counter: i64 = 1;
-
-// Helper: Safely convert timestamp to u64, returning null for negative values.
-// Negative timestamps can occur with misconfigured system clocks (before 1970).
-// Callers should return a JSON null value to preserve record flow in pipelines.
-fn safeTimestampToU64(timestamp: i64) ?u64 {
-    if (timestamp < 0) return null;
-    return @intCast(timestamp);
-}
-
-// Helper: Convert epoch day (days since 1970-01-01) to year/month/day
-const YearMonthDay = struct { year: u32, month: u32, day: u32 };
-
-fn epochDayToYmd(epoch_day: u64) YearMonthDay {
-    // Algorithm from http://howardhinnant.github.io/date_algorithms.html
-    const z = epoch_day + 719468;
-    const era = z / 146097;
-    const doe = z - era * 146097;
-    const yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
-    const y = yoe + era * 400;
-    const doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
-    const mp = (5 * doy + 2) / 153;
-    const d = doy - (153 * mp + 2) / 5 + 1;
-    const m = if (mp < 10) mp + 3 else mp - 9;
-    const year = if (m <= 2) y + 1 else y;
-
-    return .{
-        .year = @intCast(year),
-        .month = @intCast(m),
-        .day = @intCast(d),
-    };
-}
-
-// Helper: Calculate ISO week number (1-53)
-fn epochDayToIsoWeek(epoch_day: u64) u32 {
-    // ISO 8601 week number formula
-    // Week 1 is the week containing the first Thursday of the year
-
-    const ymd = epochDayToYmd(epoch_day);
-
-    // Calculate day of year (1-indexed)
-    const days_in_months = [_]u32{ 0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334 };
-    var day_of_year: u32 = days_in_months[ymd.month - 1] + ymd.day;
-
-    // Add leap day if after Feb in a leap year
-    if (ymd.month > 2 and isLeapYear(ymd.year)) {
-        day_of_year += 1;
-    }
-
-    // Day of week: 1=Monday, 7=Sunday (ISO)
-    // Jan 1, 1970 was Thursday
-    const dow: u32 = @intCast(@mod(epoch_day + 3, 7) + 1);
-
-    // ISO week number formula:
-    // week = (day_of_year - dow + 10) / 7
-    // This formula accounts for the Thursday rule
-    const week_calc: i32 = @divFloor(@as(i32, @intCast(day_of_year)) - @as(i32, @intCast(dow)) + 10, 7);
-
-    if (week_calc < 1) {
-        // Week belongs to previous year - calculate weeks in previous year
-        return weeksInYear(ymd.year - 1);
-    } else if (week_calc > weeksInYear(ymd.year)) {
-        // Week belongs to next year
-        return 1;
-    }
-
-    return @intCast(week_calc);
-}
-
-fn isLeapYear(year: u32) bool {
-    return (year % 4 == 0 and year % 100 != 0) or (year % 400 == 0);
-}
-
-fn weeksInYear(year: u32) u32 {
-    // A year has 53 weeks if Jan 1 is Thursday, or
-    // Jan 1 is Wednesday and it's a leap year
-    const jan1_dow = getJan1Weekday(year);
-    if (jan1_dow == 4) return 53; // Thursday
-    if (jan1_dow == 3 and isLeapYear(year)) return 53; // Wednesday + leap
-    return 52;
-}
-
-fn getJan1Weekday(year: u32) u32 {
-    // Day of week for Jan 1 of given year (1=Monday, 7=Sunday)
-    // Using a standard formula
-    const y = year - 1;
-    const day = (1 + 5 * (y % 4) + 4 * (y % 100) + 6 * (y % 400)) % 7;
-    // Result: 0=Sunday, 1=Monday, ..., 6=Saturday
-    // Convert to ISO: 1=Monday, 7=Sunday
-    return if (day == 0) 7 else day;
-}
-
-// Base62 alphabet for shortid/sid
-const base62_alphabet = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
-
-fn generateBase62(allocator: std.mem.Allocator, len: usize) ![]u8 {
-    var result = try allocator.alloc(u8, len);
-    var rand_bytes: [16]u8 = undefined;
-    std.crypto.random.bytes(&rand_bytes);
-
-    for (0..len) |i| {
-        const rand_idx = rand_bytes[i % 16] % 62;
-        result[i] = base62_alphabet[rand_idx];
+                else => return try EvalResult.single(allocator, .{ .bool = false }),
+            }
+        },
     }
-    return result;
 }
 
-// Sprint 07: NanoID - URL-safe alphabet
-const nanoid_alphabet = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_-";
-
-fn generateNanoId(allocator: std.mem.Allocator, len: usize) ![]u8 {
-    var result = try allocator.alloc(u8, len);
-    var rand_bytes: [32]u8 = undefined;
-    std.crypto.random.bytes(&rand_bytes);
+fn evalRegex(allocator: std.mem.Allocator, rx: RegexExpr, value: std.json.Value, env: ?*const Env) EvalError!EvalResult {
+    const input = switch (value) {
+        .string => |s| s,
+        else => return EvalResult.empty(allocator),
+    };
+    const re = rx.regex;
+    const global = re.flags.global or switch (rx.kind) {
+        .scan, .split, .splits, .gsub => true,
+        .@"test", .match, .capture, .sub => false,
+    };
 
-    for (0..len) |i| {
-        const rand_idx = rand_bytes[i % 32] % 64;
-        result[i] = nanoid_alphabet[rand_idx];
+    // Collect matches left to right; an empty match advances by one code point
+    var matches: std.ArrayListUnmanaged(regex.Match) = .empty;
+    var start: usize = 0;
+    while (start <= input.len) {
+        const m = (try re.exec(allocator, input, start)) orelse break;
+        const whole = m.span(0).?;
+        if (!(re.flags.no_empty and whole.start == whole.end)) {
+            try matches.append(allocator, m);
+            if (!global) break;
+        }
+        if (whole.end > whole.start) {
+            start = whole.end;
+        } else {
+            if (whole.end >= input.len) break;
+            start = whole.end + regex.codepointLen(input, whole.end);
+        }
     }
-    return result;
-}
 
-// Sprint 07: ULID - Crockford Base32, time-sortable
-const crockford_alphabet = "0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+    var results: std.ArrayListUnmanaged(std.json.Value) = .empty;
+    switch (rx.kind) {
+        .@"test" => try results.append(allocator, .{ .bool = matches.items.len > 0 }),
+        .match => for (matches.items) |m| {
+            try results.append(allocator, try matchObject(allocator, re, input, m));
+        },
+        .capture => for (matches.items) |m| {
+            try results.append(allocator, try captureObject(allocator, re, input, m));
+        },
+        .scan => for (matches.items) |m| {
+            if (re.names.len == 1) {
+                const whole = m.span(0).?;
+                try results.append(allocator, .{ .string = input[whole.start..whole.end] });
+                continue;
+            }
+            // With groups, each match becomes the array of its captures
+            var caps: std.ArrayListUnmanaged(std.json.Value) = .empty;
+            for (1..re.names.len) |g| {
+                try caps.append(allocator, if (m.span(g)) |sp| .{ .string = input[sp.start..sp.end] } else .null);
+            }
+            const items = try caps.toOwnedSlice(allocator);
+            try results.append(allocator, .{ .array = .{ .items = items, .capacity = items.len, .allocator = allocator } });
+        },
+        .split, .splits => {
+            var last: usize = 0;
+            for (matches.items) |m| {
+                const whole = m.span(0).?;
+                try results.append(allocator, .{ .string = input[last..whole.start] });
+                last = whole.end;
+            }
+            try results.append(allocator, .{ .string = input[last..] });
+            if (rx.kind == .split) {
+                const items = try results.toOwnedSlice(allocator);
+                return try EvalResult.single(allocator, .{ .array = .{ .items = items, .capacity = items.len, .allocator = allocator } });
+            }
+        },
+        .sub, .gsub => {
+            // Each output of the replacement forks the result (jq 1.7 semantics)
+            var outputs: std.ArrayListUnmanaged([]const u8) = .empty;
+            try outputs.append(allocator, "");
+            var last: usize = 0;
+            for (matches.items) |m| {
+                const whole = m.span(0).?;
+                const caps = try captureObject(allocator, re, input, m);
+                const reps = try evalExprWithEnv(allocator, rx.replacement.?, caps, env);
+                var next: std.ArrayListUnmanaged([]const u8) = .empty;
+                for (outputs.items) |prefix| {
+                    for (reps.values) |rep| {
+                        if (rep != .string) continue;
+                        try next.append(allocator, try std.mem.concat(allocator, u8, &.{ prefix, input[last..whole.start], rep.string }));
+                    }
+                }
+                outputs = next;
+                last = whole.end;
+            }
+            for (outputs.items) |prefix| {
+                try results.append(allocator, .{ .string = try std.mem.concat(allocator, u8, &.{ prefix, input[last..] }) });
+            }
+        },
+    }
 
-fn generateUlid(allocator: std.mem.Allocator) ![]u8 {
-    var result = try allocator.alloc(u8, 26);
+    return EvalResult.multi(allocator, try results.toOwnedSlice(allocator));
+}
 
-    // Get timestamp in milliseconds
-    const timestamp_ns = std.time.nanoTimestamp();
-    var timestamp_ms: u64 = @intCast(@divFloor(timestamp_ns, std.time.ns_per_ms));
+/// Offsets and lengths in match objects count code points, as in jq.
+fn codepointCount(bytes: []const u8) i64 {
+    const count = std.unicode.utf8CountCodepoints(bytes) catch bytes.len;
+    return @intCast(count);
+}
 
-    // Encode 48-bit timestamp as first 10 characters (Crockford Base32)
-    var i: usize = 9;
-    while (i < 10) : (i -%= 1) {
-        result[i] = crockford_alphabet[@intCast(timestamp_ms & 0x1f)];
-        timestamp_ms >>= 5;
-        if (i == 0) break;
+/// jq match object: {offset, length, string, captures: [{offset, length, string, name}]}
+fn matchObject(allocator: std.mem.Allocator, re: *const regex.Regex, input: []const u8, m: regex.Match) EvalError!std.json.Value {
+    const whole = m.span(0).?;
+    var obj = std.json.ObjectMap.init(allocator);
+    try obj.put("offset", .{ .integer = codepointCount(input[0..whole.start]) });
+    try obj.put("length", .{ .integer = codepointCount(input[whole.start..whole.end]) });
+    try obj.put("string", .{ .string = input[whole.start..whole.end] });
+
+    var captures: std.ArrayListUnmanaged(std.json.Value) = .empty;
+    for (1..re.names.len) |g| {
+        var cap = std.json.ObjectMap.init(allocator);
+        if (m.span(g)) |sp| {
+            try cap.put("offset", .{ .integer = codepointCount(input[0..sp.start]) });
+            try cap.put("length", .{ .integer = codepointCount(input[sp.start..sp.end]) });
+            try cap.put("string", .{ .string = input[sp.start..sp.end] });
+        } else {
+            // Groups that did not participate in the match
+            try cap.put("offset", .{ .integer = -1 });
+            try cap.put("length", .{ .integer = 0 });
+            try cap.put("string", .null);
+        }
+        try cap.put("name", if (re.names[g]) |name| .{ .string = name } else .null);
+        try captures.append(allocator, .{ .object = cap });
     }
+    const items = try captures.toOwnedSlice(allocator);
+    try obj.put("captures", .{ .array = .{ .items = items, .capacity = items.len, .allocator = allocator } });
+    return .{ .object = obj };
+}
 
-    // Generate 80 bits of randomness for remaining 16 characters
-    var rand_bytes: [10]u8 = undefined;
-    std.crypto.random.bytes(&rand_bytes);
+/// Object of named captures, e.g. {"year": "2024"}; unmatched groups are null.
+fn captureObject(allocator: std.mem.Allocator, re: *const regex.Regex, input: []const u8, m: regex.Match) EvalError!std.json.Value {
+    var obj = std.json.ObjectMap.init(allocator);
+    for (re.names, 0..) |maybe_name, g| {
+        const name = maybe_name orelse continue;
+        try obj.put(name, if (m.span(g)) |sp| .{ .string = input[sp.start..sp.end] } else .null);
+    }
+    return .{ .object = obj };
+}
 
-    // Encode random bytes as Base32
-    // Each char is 5 bits, so 16 chars = 80 bits = 10 bytes
-    var rand_bits: u80 = 0;
-    for (rand_bytes) |b| {
-        rand_bits = (rand_bits << 8) | b;
+/// String interpolation: one output per combination of interpolated values,
+/// earlier interpolations varying fastest (as in jq).
+fn evalInterp(allocator: std.mem.Allocator, interp: InterpExpr, value: std.json.Value, env: ?*const Env) EvalError!EvalResult {
+    var outputs: std.ArrayListUnmanaged([]const u8) = .empty;
+    try outputs.append(allocator, "");
+
+    for (interp.parts) |part| {
+        switch (part) {
+            .literal => |text| {
+                for (outputs.items) |*prefix| {
+                    prefix.* = try std.mem.concat(allocator, u8, &.{ prefix.*, text });
+                }
+            },
+            .expr => |e| {
+                const result = try evalExprWithEnv(allocator, e, value, env);
+                var next: std.ArrayListUnmanaged([]const u8) = .empty;
+                for (result.values) |v| {
+                    const text = (try applyFormat(allocator, interp.format, v)) orelse continue;
+                    for (outputs.items) |prefix| {
+                        try next.append(allocator, try std.mem.concat(allocator, u8, &.{ prefix, text }));
+                    }
+                }
+                outputs = next;
+            },
+        }
     }
 
-    var j: usize = 25;
-    while (j >= 10) : (j -%= 1) {
-        result[j] = crockford_alphabet[@intCast(rand_bits & 0x1f)];
-        rand_bits >>= 5;
-        if (j == 10) break;
+    const results = try allocator.alloc(std.json.Value, outputs.items.len);
+    for (outputs.items, 0..) |str, idx| {
+        results[idx] = .{ .string = str };
     }
+    return EvalResult.multi(allocator, results);
+}
 
-    return result;
+/// Serialize a value as compact JSON text.
+fn toJsonText(allocator: std.mem.Allocator, value: std.json.Value) EvalError![]const u8 {
+    var aw: std.Io.Writer.Allocating = .init(allocator);
+    output.writeJsonValue(&aw.writer, value) catch return error.OutOfMemory;
+    return try aw.toOwnedSlice();
 }
 
-// Sprint 07: UUID v7 - time-sortable UUID
-fn generateUuid7(allocator: std.mem.Allocator) ![]u8 {
-    // UUID v7: xxxxxxxx-xxxx-7xxx-yxxx-xxxxxxxxxxxx
-    // First 48 bits: Unix timestamp in milliseconds
-    // Next 4 bits: version (7)
-    // Next 12 bits: random
-    // Next 2 bits: variant (10)
-    // Remaining 62 bits: random
+/// Strings as-is, everything else as JSON (jq's tostring).
+fn toText(allocator: std.mem.Allocator, value: std.json.Value) EvalError![]const u8 {
+    return switch (value) {
+        .string => |s| s,
+        else => try toJsonText(allocator, value),
+    };
+}
 
-    const timestamp_ns = std.time.nanoTimestamp();
-    const timestamp_ms: u64 = @intCast(@divFloor(timestamp_ns, std.time.ns_per_ms));
+/// Apply an @format to a value. Returns null for input the format rejects,
+/// e.g. @csv on a non-array or @sh on an object.
+fn applyFormat(allocator: std.mem.Allocator, kind: FormatKind, value: std.json.Value) EvalError!?[]const u8 {
+    var out: std.ArrayListUnmanaged(u8) = .empty;
+    switch (kind) {
+        .text => return try toText(allocator, value),
+        .json => return try toJsonText(allocator, value),
+        .csv, .tsv => {
+            if (value != .array) return null;
+            for (value.array.items, 0..) |item, idx| {
+                if (idx > 0) try out.append(allocator, if (kind == .csv) ',' else '\t');
+                switch (item) {
+                    .null => {},
+                    .bool, .integer, .float, .number_string => try out.appendSlice(allocator, try toJsonText(allocator, item)),
+                    .string => |s| if (kind == .csv) {
+                        try out.append(allocator, '"');
+                        for (s) |c| {
+                            if (c == '"') try out.append(allocator, '"');
+                            try out.append(allocator, c);
+                        }
+                        try out.append(allocator, '"');
+                    } else {
+                        for (s) |c| switch (c) {
+                            '\t' => try out.appendSlice(allocator, "\\t"),
+                            '\n' => try out.appendSlice(allocator, "\\n"),
+                            '\r' => try out.appendSlice(allocator, "\\r"),
+                            '\\' => try out.appendSlice(allocator, "\\\\"),
+                            else => try out.append(allocator, c),
+                        };
+                    },
+                    .array, .object => return null,
+                }
+            }
+        },
+        .html => {
+            for (try toText(allocator, value)) |c| switch (c) {
+                '<' => try out.appendSlice(allocator, "&lt;"),
+                '>' => try out.appendSlice(allocator, "&gt;"),
+                '&' => try out.appendSlice(allocator, "&amp;"),
+                '\'' => try out.appendSlice(allocator, "&#39;"),
+                '"' => try out.appendSlice(allocator, "&quot;"),
+                else => try out.append(allocator, c),
+            };
+        },
+        .uri => {
+            for (try toText(allocator, value)) |c| {
+                if (std.ascii.isAlphanumeric(c) or c == '-' or c == '_' or c == '.' or c == '~') {
+                    try out.append(allocator, c);
+                } else {
+                    try out.print(allocator, "%{X:0>2}", .{c});
+                }
+            }
+        },
+        .sh => {
+            const items: []const std.json.Value = if (value == .array) value.array.items else &.{value};
+            for (items, 0..) |item, idx| {
+                if (idx > 0) try out.append(allocator, ' ');
+                switch (item) {
+                    // 'it'\''s': close the quote, emit an escaped quote, reopen
+                    .string => |s| {
+                        try out.append(allocator, '\'');
+                        for (s) |c| {
+                            if (c == '\'') {
+                                try out.appendSlice(allocator, "'\\''");
+                            } else {
+                                try out.append(allocator, c);
+                            }
+                        }
+                        try out.append(allocator, '\'');
+                    },
+                    .null, .bool, .integer, .float, .number_string => try out.appendSlice(allocator, try toJsonText(allocator, item)),
+                    .array, .object => return null,
+                }
+            }
+        },
+        .base64 => {
+            const text = try toText(allocator, value);
+            const encoded = try allocator.alloc(u8, std.base64.standard.Encoder.calcSize(text.len));
+            return std.base64.standard.Encoder.encode(encoded, text);
+        },
+        .base64d => {
+            // Padding is optional on input
+            const text = std.mem.trimRight(u8, try toText(allocator, value), "=");
+            const decoder = std.base64.standard_no_pad.Decoder;
+            const size = decoder.calcSizeForSlice(text) catch return null;
+            const decoded = try allocator.alloc(u8, size);
+            decoder.decode(decoded, text) catch return null;
+            return decoded;
+        },
+        .base32 => {
+            // RFC 4648: 5 bytes become 8 characters, '=' pads the last group
+            const text = try toText(allocator, value);
+            var i: usize = 0;
+            while (i < text.len) : (i += 5) {
+                var group = [_]u8{0} ** 5;
+                const n = @min(5, text.len - i);
+                @memcpy(group[0..n], text[i..][0..n]);
+                const bits = std.mem.readInt(u40, &group, .big);
+                const chars = (n * 8 + 4) / 5;
+                for (0..8) |c| {
+                    const index: u5 = @truncate(bits >> @intCast(35 - c * 5));
+                    try out.append(allocator, if (c < chars) base32_alphabet[index] else '=');
+                }
+            }
+        },
+        .base32d => {
+            // Padding is optional on input
+            var bits: u16 = 0;
+            var count: u4 = 0;
+            for (std.mem.trimRight(u8, try toText(allocator, value), "=")) |c| {
+                const index = std.mem.indexOfScalar(u8, base32_alphabet, c) orelse return null;
+                bits = (bits << 5) | @as(u16, @intCast(index));
+                count += 5;
+                if (count >= 8) {
+                    count -= 8;
+                    try out.append(allocator, @truncate(bits >> count));
+                }
+            }
+        },
+    }
+    return try out.toOwnedSlice(allocator);
+}
 
-    var bytes: [16]u8 = undefined;
+const base32_alphabet = "ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
 
-    // Timestamp (48 bits, big-endian)
-    bytes[0] = @intCast((timestamp_ms >> 40) & 0xff);
-    bytes[1] = @intCast((timestamp_ms >> 32) & 0xff);
-    bytes[2] = @intCast((timestamp_ms >> 24) & 0xff);
-    bytes[3] = @intCast((timestamp_ms >> 16) & 0xff);
-    bytes[4] = @intCast((timestamp_ms >> 8) & 0xff);
-    bytes[5] = @intCast(timestamp_ms & 0xff);
+fn evalMap(allocator: std.mem.Allocator, m: MapExpr, value: std.json.Value, env: ?*const Env) EvalError!EvalResult {
+    switch (value) {
+        .array => |arr| {
+            var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
+            for (arr.items) |item| {
+                const item_result = try evalExprWithEnv(allocator, m.inner, item, env);
+                try result_list.appendSlice(allocator, item_result.values);
+            }
+            const result_slice = try result_list.toOwnedSlice(allocator);
+            return try EvalResult.single(allocator, .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } });
+        },
+        else => return EvalResult.empty(allocator),
+    }
+}
 
-    // Random bytes for the rest
-    std.crypto.random.bytes(bytes[6..]);
+fn evalByFunc(allocator: std.mem.Allocator, bf: ByFuncExpr, value: std.json.Value, env: ?*const Env) EvalError!EvalResult {
+    const arr = switch (value) {
+        .array => |a| a,
+        else => return EvalResult.empty(allocator),
+    };
 
-    // Set version 7
-    bytes[6] = (bytes[6] & 0x0f) | 0x70;
-    // Set variant (10xx)
-    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    // Each item with its key: [f] for a computed f, else the field (null when missing)
+    const keyed = try allocator.alloc(KeyedValue, arr.items.len);
+    for (keyed, arr.items) |*entry, item| {
+        const key = if (bf.key) |f| (try evalExprWithEnv(allocator, f, item, env)).values[0] else getPath(item, bf.path) orelse .null;
+        entry.* = .{ .key = key, .item = item };
+    }
 
-    return try std.fmt.allocPrint(allocator, "{x:0>2}{x:0>2}{x:0>2}{x:0>2}-{x:0>2}{x:0>2}-{x:0>2}{x:0>2}-{x:0>2}{x:0>2}-{x:0>2}{x:0>2}{x:0>2}{x:0>2}{x:0>2}{x:0>2}", .{
-        bytes[0],  bytes[1],  bytes[2],  bytes[3],
-        bytes[4],  bytes[5],  bytes[6],  bytes[7],
-        bytes[8],  bytes[9],  bytes[10], bytes[11],
-        bytes[12], bytes[13], bytes[14], bytes[15],
-    });
+    switch (bf.kind) {
+        .min_by, .max_by => {
+            if (keyed.len == 0) return try EvalResult.single(allocator, .null);
+            // Like jq, min_by keeps the first of equal keys and max_by the last
+            var best = keyed[0];
+            for (keyed[1..]) |entry| {
+                const better = if (bf.kind == .min_by) jsonLessThan({}, entry.key, best.key) else !jsonLessThan({}, entry.key, best.key);
+                if (better) best = entry;
+            }
+            return try EvalResult.single(allocator, best.item);
+        },
+        .sort_by, .group_by, .unique_by => {
+            // Stable, so equal keys keep their input order
+            std.mem.sort(KeyedValue, keyed, {}, KeyedValue.lessThan);
+            var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
+            var run_start: usize = 0;
+            for (keyed, 0..) |entry, i| {
+                if (bf.kind == .sort_by) {
+                    try result_list.append(allocator, entry.item);
+                    continue;
+                }
+                // group_by and unique_by act on each run of equal keys
+                const run_end = i + 1 == keyed.len or jsonOrder(entry.key, keyed[i + 1].key) != .eq;
+                if (!run_end) continue;
+                const run = keyed[run_start .. i + 1];
+                run_start = i + 1;
+                if (bf.kind == .unique_by) {
+                    try result_list.append(allocator, run[0].item);
+                    continue;
+                }
+                const group = try allocator.alloc(std.json.Value, run.len);
+                for (group, run) |*slot, member| slot.* = member.item;
+                try result_list.append(allocator, .{ .array = .{ .items = group, .capacity = group.len, .allocator = allocator } });
+            }
+            const result_slice = try result_list.toOwnedSlice(allocator);
+            return try EvalResult.single(allocator, .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } });
+        },
+    }
 }
 
-/// Sprint 07: XID - compact, sortable ID
-/// XID alphabet is lowercase hex-extended with more URL-safe chars
-const xid_alphabet = "0123456789abcdefghijklmnopqrstuv";
-
-/// Thread-local XID counter (24-bit, wraps at 16 million).
-///
-/// IMPORTANT: Thread-local state behavior:
-/// - Counter wraps around after 2^24 (16,777,216) XIDs within the same thread
-/// - Combined with machine_id and timestamp, provides uniqueness within a process
-/// - Resets to 0 when the process exits
-/// - For single-threaded CLI use, XIDs are unique within a single pipeline run
-threadlocal var xid_counter: u24 = 0;
-
-/// Thread-local machine ID for XID generation (3 random bytes, cached).
-///
-/// Initialized once per thread on first XID generation. Provides uniqueness
-/// across different processes generating XIDs at the same timestamp.
-threadlocal var xid_machine_id: ?[3]u8 = null;
+const KeyedValue = struct {
+    key: std.json.Value,
+    item: std.json.Value,
 
-fn generateXid(allocator: std.mem.Allocator) ![]u8 {
-    var result = try allocator.alloc(u8, 20);
+    fn lessThan(_: void, a: KeyedValue, b: KeyedValue) bool {
+        return jsonLessThan({}, a.key, b.key);
+    }
+};
 
-    // 4 bytes: unix timestamp (seconds)
-    const timestamp: u32 = @intCast(std.time.timestamp());
+fn evalArrayLiteral(allocator: std.mem.Allocator, arr_expr: ArrayExpr, value: std.json.Value, env: ?*const Env) EvalError!EvalResult {
+    var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
 
-    // 3 bytes: machine ID (random, cached)
-    if (xid_machine_id == null) {
-        var machine: [3]u8 = undefined;
-        std.crypto.random.bytes(&machine);
-        xid_machine_id = machine;
+    for (arr_expr.elements) |elem| {
+        const elem_result = try evalExprWithEnv(allocator, elem, value, env);
+        for (elem_result.values) |v| {
+            try result_list.append(allocator, v);
+        }
     }
-    const machine_id = xid_machine_id.?;
-
-    // 2 bytes: PID (use random since we don't have easy PID access)
-    var pid_bytes: [2]u8 = undefined;
-    std.crypto.random.bytes(&pid_bytes);
 
-    // 3 bytes: counter
-    xid_counter +%= 1;
-    const counter = xid_counter;
+    const result_slice = try result_list.toOwnedSlice(allocator);
+    return try EvalResult.single(allocator, .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } });
+}
 
-    // Pack into 12 bytes
-    var bytes: [12]u8 = undefined;
-    bytes[0] = @intCast((timestamp >> 24) & 0xff);
-    bytes[1] = @intCast((timestamp >> 16) & 0xff);
-    bytes[2] = @intCast((timestamp >> 8) & 0xff);
-    bytes[3] = @intCast(timestamp & 0xff);
-    bytes[4] = machine_id[0];
-    bytes[5] = machine_id[1];
-    bytes[6] = machine_id[2];
-    bytes[7] = pid_bytes[0];
-    bytes[8] = pid_bytes[1];
-    bytes[9] = @intCast((counter >> 16) & 0xff);
-    bytes[10] = @intCast((counter >> 8) & 0xff);
-    bytes[11] = @intCast(counter & 0xff);
+// ============================================================================
+// jq Builtin Library
+// ============================================================================
 
-    // Encode as base32 (20 chars for 12 bytes * 8 bits / 5 bits per char = 19.2, round to 20)
-    // Actually XID uses a specific encoding: 12 bytes -> 20 chars
-    // Each 5 bits becomes one character
-    var bits: u96 = 0;
-    for (bytes) |b| {
-        bits = (bits << 8) | b;
-    }
+/// Exit code and message of the last halt/halt_error; main reports them.
+threadlocal var halt_status: i64 = 0;
+threadlocal var halt_message: ?std.json.Value = null;
 
-    var i: usize = 19;
-    while (i < 20) : (i -%= 1) {
-        result[i] = xid_alphabet[@intCast(bits & 0x1f)];
-        bits >>= 5;
-        if (i == 0) break;
-    }
+/// Exit code for the error.Halt returned by the last evalExpr.
+pub fn haltStatus() i64 {
+    return halt_status;
+}
 
-    return result;
+/// What halt_error prints to stderr, or null for halt.
+pub fn haltMessage() ?std.json.Value {
+    return halt_message;
 }
 
-// Sprint 06: Case conversion helpers
+fn evalLibFunc(allocator: std.mem.Allocator, lf: LibFuncExpr, value: std.json.Value, env: ?*const Env) EvalError!EvalResult {
+    switch (lf.kind) {
+        .any, .all => {
+            // any(cond) and all(cond) test the array elements, any(gen; cond) the outputs of gen
+            const cond = lf.args[lf.args.len - 1];
+            const items: []const std.json.Value = if (lf.args.len == 2)
+                (try evalExprWithEnv(allocator, lf.args[0], value, env)).values
+            else switch (value) {
+                .array => |arr| arr.items,
+                else => return EvalResult.empty(allocator),
+            };
+            for (items) |item| {
+                if (try condHolds(allocator, cond, item, env) == (lf.kind == .any)) return try EvalResult.single(allocator, .{ .bool = lf.kind == .any });
+            }
+            return try EvalResult.single(allocator, .{ .bool = lf.kind == .all });
+        },
+        .isempty => {
+            const first = try firstOutputs(allocator, lf.args[0], value, env, 1);
+            return try EvalResult.single(allocator, .{ .bool = first.len == 0 });
+        },
+        .isvalid => {
+            const mark = partial.items.len;
+            _ = evalExprWithEnv(allocator, lf.args[0], value, env) catch |err| switch (err) {
+                error.Raised, error.InvalidPath => {
+                    discardPartial(mark);
+                    return try EvalResult.single(allocator, .{ .bool = false });
+                },
+                else => return err,
+            };
+            return try EvalResult.single(allocator, .{ .bool = true });
+        },
+        .IN => {
+            // IN(s) is true if the input is an output of s, IN(source; s) if any output of source is
+            const sources: []const std.json.Value = if (lf.args.len == 2) (try evalExprWithEnv(allocator, lf.args[0], value, env)).values else (&value)[0..1];
+            const set = (try evalExprWithEnv(allocator, lf.args[lf.args.len - 1], value, env)).values;
+            for (sources) |source| {
+                for (set) |member| {
+                    if (jsonEqual(source, member)) return try EvalResult.single(allocator, .{ .bool = true });
+                }
+            }
+            return try EvalResult.single(allocator, .{ .bool = false });
+        },
+        .INDEX => {
+            // INDEX(idx_expr) indexes the input's elements, INDEX(stream; idx_expr) the outputs of stream
+            const rows: []const std.json.Value = if (lf.args.len == 2) (try evalExprWithEnv(allocator, lf.args[0], value, env)).values else switch (value) {
+                .array => |arr| arr.items,
+                .object => |obj| obj.values(),
+                else => return raiseMessage(allocator, "Cannot iterate over {s}", .{try describeValue(allocator, value)}),
+            };
+            var index = std.json.ObjectMap.init(allocator);
+            for (rows) |row| {
+                for ((try evalExprWithEnv(allocator, lf.args[lf.args.len - 1], row, env)).values) |key| {
+                    try index.put(try toText(allocator, key), row);
+                }
+            }
+            return try EvalResult.single(allocator, .{ .object = index });
+        },
+        .JOIN => {
+            // JOIN($idx; idx_expr) is one array of [row, $idx[key]] pairs; the
+            // stream forms emit each pair, through join_expr if given
+            var out: std.ArrayListUnmanaged(std.json.Value) = .empty;
+            for ((try evalExprWithEnv(allocator, lf.args[0], value, env)).values) |index| {
+                const rows: []const std.json.Value = if (lf.args.len >= 3) (try evalExprWithEnv(allocator, lf.args[1], value, env)).values else switch (value) {
+                    .array => |arr| arr.items,
+                    else => return raiseMessage(allocator, "Cannot iterate over {s}", .{try describeValue(allocator, value)}),
+                };
+                var pairs: std.ArrayListUnmanaged(std.json.Value) = .empty;
+                for (rows) |row| {
+                    for ((try evalExprWithEnv(allocator, lf.args[if (lf.args.len >= 3) 2 else 1], row, env)).values) |key| {
+                        const match: std.json.Value = switch (index) {
+                            .object => |obj| obj.get(try toText(allocator, key)) orelse .null,
+                            else => .null,
+                        };
+                        const pair = try allocator.dupe(std.json.Value, &.{ row, match });
+                        try pairs.append(allocator, .{ .array = .{ .items = pair, .capacity = pair.len, .allocator = allocator } });
+                    }
+                }
+                if (lf.args.len == 2) {
+                    const slice = try pairs.toOwnedSlice(allocator);
+                    try out.append(allocator, .{ .array = .{ .items = slice, .capacity = slice.len, .allocator = allocator } });
+                } else if (lf.args.len == 4) {
+                    for (pairs.items) |pair| try out.appendSlice(allocator, (try evalExprWithEnv(allocator, lf.args[3], pair, env)).values);
+                } else {
+                    try out.appendSlice(allocator, pairs.items);
+                }
+            }
+            return EvalResult.multi(allocator, try out.toOwnedSlice(allocator));
+        },
+        .pick => {
+            var paths: std.ArrayListUnmanaged(PathValue) = .empty;
+            for (lf.args) |path_expr| try evalPaths(allocator, path_expr, .{ .path = &.{}, .value = value }, env, &paths);
+            var picked: std.json.Value = .null;
+            for (paths.items) |p| picked = try setPath(allocator, picked, p.path, getPathValue(value, p.path));
+            return try EvalResult.single(allocator, picked);
+        },
+        .debug => {
+            for ((try evalExprWithEnv(allocator, lf.args[0], value, env)).values) |msg| {
+                std.debug.print("[\"DEBUG:\",{s}]\n", .{try toJsonText(allocator, msg)});
+            }
+            return try EvalResult.single(allocator, value);
+        },
+        .halt => {
+            halt_status = 0;
+            halt_message = null;
+            return error.Halt;
+        },
+        .halt_error => {
+            halt_status = 5;
+            if (lf.args.len == 1) {
+                const codes = (try evalExprWithEnv(allocator, lf.args[0], value, env)).values;
+                if (codes.len == 0) return EvalResult.empty(allocator);
+                halt_status = switch (codes[0]) {
+                    .integer => |i| i,
+                    else => return raiseMessage(allocator, "halt_error/1: number required, not {s}", .{try describeValue(allocator, codes[0])}),
+                };
+            }
+            halt_message = value;
+            return error.Halt;
+        },
+        else => {},
+    }
+
+    // Everything else is a function of its argument values: one output for
+    // each combination of argument outputs
+    const arg_values = try allocator.alloc([]const std.json.Value, lf.args.len);
+    for (lf.args, arg_values) |arg, *vals| vals.* = (try evalExprWithEnv(allocator, arg, value, env)).values;
 
-/// Convert string to snake_case
-/// "HelloWorld" -> "hello_world"
-/// "hello-world" -> "hello_world"
-/// "Hello World" -> "hello_world"
-fn toSnakeCase(allocator: std.mem.Allocator, s: []const u8) ![]u8 {
-    if (s.len == 0) return try allocator.alloc(u8, 0);
+    var out: std.ArrayListUnmanaged(std.json.Value) = .empty;
+    const current = try allocator.alloc(std.json.Value, lf.args.len);
+    try libCallEach(allocator, lf.kind, value, arg_values, current, 0, &out);
+    return EvalResult.multi(allocator, try out.toOwnedSlice(allocator));
+}
 
-    // First pass: count output length
-    var output_len: usize = 0;
-    var prev_lower = false;
-    for (s) |c| {
-        if (c == ' ' or c == '-' or c == '_') {
-            if (output_len > 0) output_len += 1; // add underscore
-            prev_lower = false;
-        } else if (c >= 'A' and c <= 'Z') {
-            if (prev_lower and output_len > 0) output_len += 1; // add underscore before uppercase
-            output_len += 1;
-            prev_lower = false;
-        } else {
-            output_len += 1;
-            prev_lower = (c >= 'a' and c <= 'z');
+fn libCallEach(
+    allocator: std.mem.Allocator,
+    kind: types.LibFuncKind,
+    input: std.json.Value,
+    arg_values: []const []const std.json.Value,
+    current: []std.json.Value,
+    idx: usize,
+    out: *std.ArrayListUnmanaged(std.json.Value),
+) EvalError!void {
+    if (idx < arg_values.len) {
+        for (arg_values[idx]) |arg| {
+            current[idx] = arg;
+            try libCallEach(allocator, kind, input, arg_values, current, idx + 1, out);
         }
+        return;
     }
+    if (kind == .combinations) {
+        // combinations(n): the combinations of n copies of the input
+        const n = try outputCount(allocator, current[0]);
+        const copies = try allocator.alloc(std.json.Value, n);
+        @memset(copies, input);
+        return combinationsInto(allocator, copies, &.{}, out);
+    }
+    if (try libCall(allocator, kind, input, current)) |result| try out.append(allocator, result);
+}
 
-    // Second pass: build output
-    var result = try allocator.alloc(u8, output_len);
-    var pos: usize = 0;
-    prev_lower = false;
+/// One call of a library function on argument values; null for no output.
+fn libCall(allocator: std.mem.Allocator, kind: types.LibFuncKind, input: std.json.Value, args: []const std.json.Value) EvalError!?std.json.Value {
+    switch (kind) {
+        .has, .in => {
+            const container = if (kind == .has) input else args[0];
+            const key = if (kind == .has) args[0] else input;
+            return .{ .bool = switch (container) {
+                .object => |obj| if (key == .string) obj.get(key.string) != null else return raiseMessage(allocator, "Cannot check whether object has a key of type {s}", .{typeName(key)}),
+                .array => |arr| if (getNumeric(key)) |i| i >= 0 and i < @as(f64, @floatFromInt(arr.items.len)) else return raiseMessage(allocator, "Cannot check whether array has a key of type {s}", .{typeName(key)}),
+                else => return raiseMessage(allocator, "Cannot check whether {s} has a {s} key", .{ typeName(container), typeName(key) }),
+            } };
+        },
+        .contains, .inside => {
+            if (kind == .inside) return .{ .bool = try containsValue(allocator, args[0], input) };
+            return .{ .bool = try containsValue(allocator, input, args[0]) };
+        },
+        .startswith, .endswith => {
+            if (input != .string or args[0] != .string) return raiseMessage(allocator, "{s}() requires string inputs", .{@tagName(kind)});
+            const found = if (kind == .startswith) std.mem.startsWith(u8, input.string, args[0].string) else std.mem.endsWith(u8, input.string, args[0].string);
+            return .{ .bool = found };
+        },
+        .ltrimstr, .rtrimstr => {
+            if (input != .string or args[0] != .string) return input;
+            const s = input.string;
+            const affix = args[0].string;
+            if (kind == .ltrimstr and std.mem.startsWith(u8, s, affix)) return .{ .string = s[affix.len..] };
+            if (kind == .rtrimstr and std.mem.endsWith(u8, s, affix)) return .{ .string = s[0 .. s.len - affix.len] };
+            return input;
+        },
+        .split => {
+            if (input != .string or args[0] != .string) return raiseMessage(allocator, "split input and separator must be strings", .{});
+            return (try evalStrFunc(allocator, .{ .kind = .split, .arg = args[0].string }, input)).values[0];
+        },
+        .join => {
+            if (input != .array) return raiseMessage(allocator, "Cannot iterate over {s}", .{try describeValue(allocator, input)});
+            if (args[0] != .string) return raiseMessage(allocator, "{s} is not a valid separator", .{try describeValue(allocator, args[0])});
+            return .{ .string = try joinValues(allocator, input.array.items, args[0].string) };
+        },
+        .indices, .index, .rindex => {
+            const found = (try indicesOf(allocator, input, args[0])) orelse return @as(std.json.Value, .null);
+            const offsets = found.array.items;
+            if (kind == .indices) return found;
+            if (offsets.len == 0) return @as(std.json.Value, .null);
+            return if (kind == .index) offsets[0] else offsets[offsets.len - 1];
+        },
+        .flatten => {
+            if (input != .array) return null;
+            const depth = getNumeric(args[0]) orelse return raiseMessage(allocator, "flatten depth must be a number", .{});
+            if (depth < 0) return raiseMessage(allocator, "flatten depth must not be negative", .{});
+            var flat: std.ArrayListUnmanaged(std.json.Value) = .empty;
+            try flattenInto(allocator, input.array.items, if (depth >= 1e9) std.math.maxInt(usize) else @intFromFloat(depth), &flat);
+            const slice = try flat.toOwnedSlice(allocator);
+            return .{ .array = .{ .items = slice, .capacity = slice.len, .allocator = allocator } };
+        },
+        else => {
+            // Math on two or three numbers
+            var nums: [3]f64 = undefined;
+            for (args, 0..) |arg, i| {
+                nums[i] = getNumeric(arg) orelse return raiseMessage(allocator, "{s} number required", .{try describeValue(allocator, arg)});
+            }
+            const x = nums[0];
+            const y = nums[1];
+            const result: f64 = switch (kind) {
+                .pow => std.math.pow(f64, x, y),
+                .atan2 => std.math.atan2(x, y),
+                .fmin => @min(x, y),
+                .fmax => @max(x, y),
+                .fmod => if (y == 0) std.math.nan(f64) else @rem(x, y),
+                .fdim => if (x > y) x - y else 0,
+                .copysign => std.math.copysign(x, y),
+                .drem => if (y == 0) std.math.nan(f64) else x - y * roundHalfEven(x / y),
+                .ldexp, .scalb, .scalbln => if (std.math.isNan(y)) y else std.math.ldexp(x, @intFromFloat(std.math.clamp(y, -4096, 4096))),
+                .nextafter, .nexttoward => std.math.nextAfter(f64, x, y),
+                .fma => @mulAdd(f64, x, y, nums[2]),
+                else => unreachable,
+            };
+            return .{ .float = result };
+        },
+    }
+}
 
-    for (s) |c| {
-        if (c == ' ' or c == '-' or c == '_') {
-            if (pos > 0) {
-                result[pos] = '_';
-                pos += 1;
+/// jq's contains: substrings for strings, every key contained for objects,
+/// every element contained by some element for arrays, equality otherwise.
+fn containsValue(allocator: std.mem.Allocator, a: std.json.Value, b: std.json.Value) EvalError!bool {
+    if (!std.mem.eql(u8, typeName(a), typeName(b))) {
+        return raiseMessage(allocator, "{s} and {s} cannot have their containment checked", .{ try describeValue(allocator, a), try describeValue(allocator, b) });
+    }
+    switch (b) {
+        .string => |s| return std.mem.indexOf(u8, a.string, s) != null,
+        .object => |obj| {
+            var it = obj.iterator();
+            while (it.next()) |entry| {
+                const own = a.object.get(entry.key_ptr.*) orelse return false;
+                if (!std.mem.eql(u8, typeName(own), typeName(entry.value_ptr.*))) return false;
+                if (!try containsValue(allocator, own, entry.value_ptr.*)) return false;
             }
-            prev_lower = false;
-        } else if (c >= 'A' and c <= 'Z') {
-            if (prev_lower and pos > 0) {
-                result[pos] = '_';
-                pos += 1;
+            return true;
+        },
+        .array => |arr| {
+            for (arr.items) |wanted| {
+                for (a.array.items) |own| {
+                    if (std.mem.eql(u8, typeName(own), typeName(wanted)) and try containsValue(allocator, own, wanted)) break;
+                } else return false;
             }
-            result[pos] = c + 32; // lowercase
-            pos += 1;
-            prev_lower = false;
-        } else {
-            result[pos] = c;
-            pos += 1;
-            prev_lower = (c >= 'a' and c <= 'z');
-        }
+            return true;
+        },
+        else => return jsonEqual(a, b),
     }
-
-    return result[0..pos];
 }
 
-/// Convert string to camelCase or PascalCase
-/// "hello_world" -> "helloWorld" (pascal=false)
-/// "hello_world" -> "HelloWorld" (pascal=true)
-fn toCamelCase(allocator: std.mem.Allocator, s: []const u8, pascal: bool) ![]u8 {
-    if (s.len == 0) return try allocator.alloc(u8, 0);
+/// Offsets of `needle` in a string (codepoints, overlapping matches) or an
+/// array (where a sub-array or an equal element starts); null for null input.
+fn indicesOf(allocator: std.mem.Allocator, input: std.json.Value, needle: std.json.Value) EvalError!?std.json.Value {
+    var found: std.ArrayListUnmanaged(std.json.Value) = .empty;
+    switch (input) {
+        .null => return null,
+        .string => |s| {
+            if (needle != .string) return raiseMessage(allocator, "Cannot determine indices of {s} in a string", .{try describeValue(allocator, needle)});
+            if (needle.string.len == 0) return null;
+            var pos: usize = 0;
+            while (std.mem.indexOfPos(u8, s, pos, needle.string)) |at| {
+                try found.append(allocator, .{ .integer = codepointCount(s[0..at]) });
+                pos = at + 1;
+            }
+        },
+        .array => |arr| {
+            const seq: []const std.json.Value = if (needle == .array) needle.array.items else &.{needle};
+            if (seq.len == 0) return null;
+            var start: usize = 0;
+            while (start + seq.len <= arr.items.len) : (start += 1) {
+                for (seq, arr.items[start .. start + seq.len]) |want, have| {
+                    if (!jsonEqual(want, have)) break;
+                } else try found.append(allocator, .{ .integer = @intCast(start) });
+            }
+        },
+        else => return raiseMessage(allocator, "Cannot determine indices in {s}", .{try describeValue(allocator, input)}),
+    }
+    const slice = try found.toOwnedSlice(allocator);
+    return .{ .array = .{ .items = slice, .capacity = slice.len, .allocator = allocator } };
+}
 
-    // First pass: count output length (skip separators)
-    var output_len: usize = 0;
-    for (s) |c| {
-        if (c != ' ' and c != '-' and c != '_') {
-            output_len += 1;
+/// jq's join: null is empty, numbers and booleans are their JSON text.
+fn joinValues(allocator: std.mem.Allocator, items: []const std.json.Value, sep: []const u8) EvalError![]const u8 {
+    var out: std.ArrayListUnmanaged(u8) = .empty;
+    for (items, 0..) |item, i| {
+        if (i > 0) try out.appendSlice(allocator, sep);
+        switch (item) {
+            .null => {},
+            .string => |s| try out.appendSlice(allocator, s),
+            .bool, .integer, .float, .number_string => try out.appendSlice(allocator, try toJsonText(allocator, item)),
+            .array, .object => return raiseMessage(allocator, "Cannot join with {s}", .{typeName(item)}),
         }
     }
+    return out.toOwnedSlice(allocator);
+}
 
-    var result = try allocator.alloc(u8, output_len);
-    var pos: usize = 0;
-    var capitalize_next = pascal;
-
-    for (s) |c| {
-        if (c == ' ' or c == '-' or c == '_') {
-            capitalize_next = true;
-        } else if (capitalize_next) {
-            if (c >= 'a' and c <= 'z') {
-                result[pos] = c - 32;
-            } else {
-                result[pos] = c;
-            }
-            pos += 1;
-            capitalize_next = false;
+/// Append the elements of `items`, splicing in nested arrays `depth` levels deep.
+fn flattenInto(allocator: std.mem.Allocator, items: []const std.json.Value, depth: usize, out: *std.ArrayListUnmanaged(std.json.Value)) EvalError!void {
+    for (items) |item| {
+        if (item == .array and depth > 0) {
+            try flattenInto(allocator, item.array.items, depth - 1, out);
         } else {
-            if (c >= 'A' and c <= 'Z') {
-                result[pos] = c + 32;
-            } else {
-                result[pos] = c;
-            }
-            pos += 1;
+            try out.append(allocator, item);
         }
     }
+}
 
-    return result[0..pos];
+/// Append every array that picks one element from each of `lists` after
+/// `prefix`, varying the last list fastest.
+fn combinationsInto(allocator: std.mem.Allocator, lists: []const std.json.Value, prefix: []const std.json.Value, out: *std.ArrayListUnmanaged(std.json.Value)) EvalError!void {
+    if (lists.len == 0) {
+        const picked = try allocator.dupe(std.json.Value, prefix);
+        return out.append(allocator, .{ .array = .{ .items = picked, .capacity = picked.len, .allocator = allocator } });
+    }
+    const choices: []const std.json.Value = switch (lists[0]) {
+        .array => |arr| arr.items,
+        else => return raiseMessage(allocator, "Cannot iterate over {s}", .{try describeValue(allocator, lists[0])}),
+    };
+    for (choices) |choice| {
+        try combinationsInto(allocator, lists[1..], try std.mem.concat(allocator, std.json.Value, &.{ prefix, &.{choice} }), out);
+    }
 }
 
-/// Convert string to kebab-case
-/// "HelloWorld" -> "hello-world"
-/// "hello_world" -> "hello-world"
-fn toKebabCase(allocator: std.mem.Allocator, s: []const u8) ![]u8 {
-    if (s.len == 0) return try allocator.alloc(u8, 0);
+fn roundHalfEven(f: f64) f64 {
+    const rounded = @round(f);
+    if (@abs(f - @trunc(f)) != 0.5) return rounded;
+    return 2 * @round(f / 2);
+}
 
-    // First pass: count output length
-    var output_len: usize = 0;
-    var prev_lower = false;
-    for (s) |c| {
-        if (c == ' ' or c == '-' or c == '_') {
-            if (output_len > 0) output_len += 1;
-            prev_lower = false;
-        } else if (c >= 'A' and c <= 'Z') {
-            if (prev_lower and output_len > 0) output_len += 1;
-            output_len += 1;
-            prev_lower = false;
-        } else {
-            output_len += 1;
-            prev_lower = (c >= 'a' and c <= 'z');
-        }
-    }
+/// One-argument libm functions.
+fn libm(kind: BuiltinKind, f: f64) f64 {
+    return switch (kind) {
+        .trunc => @trunc(f),
+        .rint, .nearbyint => roundHalfEven(f),
+        .cbrt => std.math.cbrt(f),
+        .exp2 => @exp2(f),
+        .exp10 => std.math.pow(f64, 10, f),
+        .log => @log(f),
+        .log1p => std.math.log1p(f),
+        .expm1 => std.math.expm1(f),
+        .logb => if (f == 0) -std.math.inf(f64) else if (!std.math.isFinite(f)) @abs(f) else @floatFromInt(std.math.ilogb(f)),
+        .significand => if (f == 0 or !std.math.isFinite(f)) f else std.math.frexp(f).significand * 2,
+        .gamma, .lgamma => std.math.lgamma(f64, f),
+        .tgamma => std.math.gamma(f64, f),
+        .sinh => std.math.sinh(f),
+        .cosh => std.math.cosh(f),
+        .tanh => std.math.tanh(f),
+        .asinh => std.math.asinh(f),
+        .acosh => std.math.acosh(f),
+        .atanh => std.math.atanh(f),
+        else => unreachable,
+    };
+}
 
-    // Second pass: build output
-    var result = try allocator.alloc(u8, output_len);
-    var pos: usize = 0;
-    prev_lower = false;
+// ============================================================================
+// Evaluator Tests
+// ============================================================================
 
-    for (s) |c| {
-        if (c == ' ' or c == '-' or c == '_') {
-            if (pos > 0) {
-                result[pos] = '-';
-                pos += 1;
-            }
-            prev_lower = false;
-        } else if (c >= 'A' and c <= 'Z') {
-            if (prev_lower and pos > 0) {
-                result[pos] = '-';
-                pos += 1;
-            }
-            result[pos] = c + 32;
-            pos += 1;
-            prev_lower = false;
-        } else {
-            result[pos] = c;
-            pos += 1;
-            prev_lower = (c >= 'a' and c <= 'z');
-        }
-    }
+const parser = @import("parser.zig");
+const parseExprWithContext = parser.parseExprWithContext;
+const parseCondition = parser.parseCondition;
+const ErrorContext = types.ErrorContext;
 
-    return result[0..pos];
+test "eval condition and" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    const json_true = "{\"active\":true,\"verified\":true}";
+    const json_false = "{\"active\":true,\"verified\":false}";
+
+    var err_ctx: ErrorContext = .{};
+    const cond = try parseCondition(arena.allocator(), ".active and .verified", &err_ctx);
+
+    const parsed_true = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json_true, .{});
+    const parsed_false = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json_false, .{});
+
+    try std.testing.expect(try evalCondition(arena.allocator(), cond, parsed_true.value));
+    try std.testing.expect(!try evalCondition(arena.allocator(), cond, parsed_false.value));
 }
 
-/// Convert string to URL-safe slug
-/// "Hello World!" -> "hello-world"
-/// "This & That" -> "this-that"
-fn toSlug(allocator: std.mem.Allocator, s: []const u8) ![]u8 {
-    if (s.len == 0) return try allocator.alloc(u8, 0);
+test "eval condition or" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-    // First pass: count output length
-    var output_len: usize = 0;
-    var prev_dash = true; // Start true to avoid leading dash
+    const json_admin = "{\"admin\":true,\"moderator\":false}";
+    const json_mod = "{\"admin\":false,\"moderator\":true}";
+    const json_neither = "{\"admin\":false,\"moderator\":false}";
+
+    var err_ctx: ErrorContext = .{};
+    const cond = try parseCondition(arena.allocator(), ".admin or .moderator", &err_ctx);
+
+    const parsed_admin = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json_admin, .{});
+    const parsed_mod = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json_mod, .{});
+    const parsed_neither = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json_neither, .{});
+
+    try std.testing.expect(try evalCondition(arena.allocator(), cond, parsed_admin.value));
+    try std.testing.expect(try evalCondition(arena.allocator(), cond, parsed_mod.value));
+    try std.testing.expect(!try evalCondition(arena.allocator(), cond, parsed_neither.value));
+}
+
+test "eval condition not" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    const json_deleted = "{\"deleted\":true}";
+    const json_active = "{\"deleted\":false}";
+
+    var err_ctx: ErrorContext = .{};
+    const cond = try parseCondition(arena.allocator(), "not .deleted", &err_ctx);
+
+    const parsed_deleted = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json_deleted, .{});
+    const parsed_active = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json_active, .{});
+
+    try std.testing.expect(!try evalCondition(arena.allocator(), cond, parsed_deleted.value));
+    try std.testing.expect(try evalCondition(arena.allocator(), cond, parsed_active.value));
+}
+
+test "getIndex positive" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    const json = "[1,2,3,4,5]";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+
+    const first = getIndex(parsed.value, 0);
+    try std.testing.expect(first != null);
+    try std.testing.expectEqual(@as(i64, 1), first.?.integer);
+
+    const third = getIndex(parsed.value, 2);
+    try std.testing.expect(third != null);
+    try std.testing.expectEqual(@as(i64, 3), third.?.integer);
+}
+
+test "getIndex negative" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    const json = "[1,2,3,4,5]";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+
+    const last = getIndex(parsed.value, -1);
+    try std.testing.expect(last != null);
+    try std.testing.expectEqual(@as(i64, 5), last.?.integer);
+
+    const second_last = getIndex(parsed.value, -2);
+    try std.testing.expect(second_last != null);
+    try std.testing.expectEqual(@as(i64, 4), second_last.?.integer);
+}
+
+test "getIndex out of bounds" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-    for (s) |c| {
-        if ((c >= 'a' and c <= 'z') or (c >= '0' and c <= '9')) {
-            output_len += 1;
-            prev_dash = false;
-        } else if (c >= 'A' and c <= 'Z') {
-            output_len += 1;
-            prev_dash = false;
-        } else if (!prev_dash) {
-            // Any non-alphanumeric becomes a dash
-            output_len += 1;
-            prev_dash = true;
-        }
-    }
+    const json = "[1,2,3]";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-    // Remove trailing dash if present
-    if (output_len > 0 and prev_dash) output_len -= 1;
+    try std.testing.expect(getIndex(parsed.value, 10) == null);
+    try std.testing.expect(getIndex(parsed.value, -10) == null);
+}
 
-    var result = try allocator.alloc(u8, output_len);
-    var pos: usize = 0;
-    prev_dash = true;
+test "getIndex MIN_I64 does not overflow" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-    for (s) |c| {
-        if ((c >= 'a' and c <= 'z') or (c >= '0' and c <= '9')) {
-            result[pos] = c;
-            pos += 1;
-            prev_dash = false;
-        } else if (c >= 'A' and c <= 'Z') {
-            result[pos] = c + 32; // lowercase
-            pos += 1;
-            prev_dash = false;
-        } else if (!prev_dash and pos < output_len) {
-            result[pos] = '-';
-            pos += 1;
-            prev_dash = true;
-        }
-    }
+    const json = "[1,2,3]";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-    return result[0..pos];
+    // std.math.minInt(i64) is -9223372036854775808
+    // Negating it would overflow since max i64 is 9223372036854775807
+    // This should return null, not crash
+    try std.testing.expect(getIndex(parsed.value, std.math.minInt(i64)) == null);
 }
 
-// Sprint 08: XID timestamp decoding
-fn decodeXidTimestamp(s: []const u8) ?u32 {
-    // XID is 20 chars base32 encoding 12 bytes (96 bits)
-    // First 4 bytes (32 bits) are the timestamp
-    // We need to decode all 20 chars and extract the top 32 bits
-    if (s.len < 20) return null;
+test "eval pipe" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-    // Decode all 20 characters to 96 bits
-    var bits: u128 = 0; // Use u128 to hold 96 bits safely
-    for (s[0..20]) |c| {
-        const val: u128 = if (c >= '0' and c <= '9')
-            c - '0'
-        else if (c >= 'a' and c <= 'v')
-            c - 'a' + 10
-        else
-            return null; // Invalid character
-        bits = (bits << 5) | val;
-    }
+    const json = "{\"x\":\"42\"}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-    // 20 chars * 5 bits = 100 bits, but we only have 96 bits of data
-    // The encoding uses the full 100 bits, so top 4 bits are padding
-    // Extract timestamp from bits 64-95 (after removing 4 padding bits from top)
-    const timestamp: u32 = @truncate(bits >> 64);
-    return timestamp;
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), ".x | tonumber", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
+
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    try std.testing.expectEqual(@as(i64, 42), result.values[0].integer);
 }
 
-// Sprint 08: Parse ISO timestamp to epoch seconds
-fn parseIsoTimestamp(s: []const u8) ?i64 {
-    // Parse "2024-12-15T17:30:00Z" or "2024-12-15T17:30:00" or "2024-12-15"
-    if (s.len < 10) return null;
+test "eval object construction" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    const json = "{\"x\":1,\"y\":2}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-    // Parse year
-    const year = std.fmt.parseInt(u32, s[0..4], 10) catch return null;
-    if (s[4] != '-') return null;
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "{a: .x, b: .y}", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    // Parse month
-    const month = std.fmt.parseInt(u32, s[5..7], 10) catch return null;
-    if (month < 1 or month > 12) return null;
-    if (s[7] != '-') return null;
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    try std.testing.expect(result.values[0] == .object);
+    try std.testing.expectEqual(@as(i64, 1), result.values[0].object.get("a").?.integer);
+    try std.testing.expectEqual(@as(i64, 2), result.values[0].object.get("b").?.integer);
+}
 
-    // Parse day
-    const day = std.fmt.parseInt(u32, s[8..10], 10) catch return null;
-    if (day < 1 or day > 31) return null;
+test "eval alternative with null" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-    // Calculate days since epoch
-    var days: i64 = 0;
+    const json = "{\"x\":null,\"y\":1}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-    // Years since 1970
-    var y: u32 = 1970;
-    while (y < year) : (y += 1) {
-        days += if (isLeapYear(y)) 366 else 365;
-    }
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), ".x // .y", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    // Months
-    const days_in_months = [_]u32{ 31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31 };
-    var m: u32 = 1;
-    while (m < month) : (m += 1) {
-        days += days_in_months[m - 1];
-        if (m == 2 and isLeapYear(year)) days += 1;
-    }
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    try std.testing.expectEqual(@as(i64, 1), result.values[0].integer);
+}
 
-    // Days
-    days += day - 1;
+test "eval arithmetic" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-    // Convert to seconds
-    var secs = days * 86400;
+    const json = "{\"a\":10,\"b\":3}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-    // Parse time if present
-    if (s.len >= 19 and s[10] == 'T') {
-        const hour = std.fmt.parseInt(u32, s[11..13], 10) catch return null;
-        if (hour > 23) return null;
-        const minute = std.fmt.parseInt(u32, s[14..16], 10) catch return null;
-        if (minute > 59) return null;
-        const second = std.fmt.parseInt(u32, s[17..19], 10) catch return null;
-        if (second > 59) return null;
-        secs += hour * 3600 + minute * 60 + second;
-    }
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), ".a + .b", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    return secs;
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    try std.testing.expectEqual(@as(i64, 13), result.values[0].integer);
 }
 
-// Sprint 08: Format seconds as human-friendly "ago" string
-fn formatAgo(allocator: std.mem.Allocator, diff: i64) ![]u8 {
-    const abs_diff: u64 = if (diff < 0) @intCast(-diff) else @intCast(diff);
-    const suffix = if (diff < 0) " from now" else " ago";
+test "eval string concatenation" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-    if (abs_diff < 60) {
-        return try std.fmt.allocPrint(allocator, "{d} second{s}{s}", .{
-            abs_diff,
-            if (abs_diff == 1) "" else "s",
-            suffix,
-        });
-    }
+    const json = "{\"a\":\"foo\",\"b\":\"bar\"}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-    const minutes = abs_diff / 60;
-    if (minutes < 60) {
-        return try std.fmt.allocPrint(allocator, "{d} minute{s}{s}", .{
-            minutes,
-            if (minutes == 1) "" else "s",
-            suffix,
-        });
-    }
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), ".a + .b", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    const hours = abs_diff / 3600;
-    const remaining_mins = (abs_diff % 3600) / 60;
-    if (hours < 24) {
-        if (remaining_mins > 0) {
-            return try std.fmt.allocPrint(allocator, "{d} hour{s}, {d} minute{s}{s}", .{
-                hours,
-                if (hours == 1) "" else "s",
-                remaining_mins,
-                if (remaining_mins == 1) "" else "s",
-                suffix,
-            });
-        } else {
-            return try std.fmt.allocPrint(allocator, "{d} hour{s}{s}", .{
-                hours,
-                if (hours == 1) "" else "s",
-                suffix,
-            });
-        }
-    }
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    try std.testing.expectEqualStrings("foobar", result.values[0].string);
+}
 
-    const days = abs_diff / 86400;
-    const remaining_hours = (abs_diff % 86400) / 3600;
-    if (days < 30) {
-        if (remaining_hours > 0) {
-            return try std.fmt.allocPrint(allocator, "{d} day{s}, {d} hour{s}{s}", .{
-                days,
-                if (days == 1) "" else "s",
-                remaining_hours,
-                if (remaining_hours == 1) "" else "s",
-                suffix,
-            });
-        } else {
-            return try std.fmt.allocPrint(allocator, "{d} day{s}{s}", .{
-                days,
-                if (days == 1) "" else "s",
-                suffix,
-            });
-        }
-    }
+test "eval conditional true" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-    const months = days / 30;
-    const remaining_days = days % 30;
-    if (months < 12) {
-        if (remaining_days > 0) {
-            return try std.fmt.allocPrint(allocator, "{d} month{s}, {d} day{s}{s}", .{
-                months,
-                if (months == 1) "" else "s",
-                remaining_days,
-                if (remaining_days == 1) "" else "s",
-                suffix,
-            });
-        } else {
-            return try std.fmt.allocPrint(allocator, "{d} month{s}{s}", .{
-                months,
-                if (months == 1) "" else "s",
-                suffix,
-            });
-        }
-    }
+    const json = "{\"x\":10}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-    const years = days / 365;
-    const remaining_months = (days % 365) / 30;
-    if (remaining_months > 0) {
-        return try std.fmt.allocPrint(allocator, "{d} year{s}, {d} month{s}{s}", .{
-            years,
-            if (years == 1) "" else "s",
-            remaining_months,
-            if (remaining_months == 1) "" else "s",
-            suffix,
-        });
-    } else {
-        return try std.fmt.allocPrint(allocator, "{d} year{s}{s}", .{
-            years,
-            if (years == 1) "" else "s",
-            suffix,
-        });
-    }
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "if .x > 5 then .x else .y end", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
+
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    try std.testing.expectEqual(@as(i64, 10), result.values[0].integer);
 }
 
-// Sprint 03: Helper functions for sorting/comparing JSON values
-fn jsonLessThan(_: void, a: std.json.Value, b: std.json.Value) bool {
-    // Compare by type first, then value
-    const type_order = struct {
-        fn order(v: std.json.Value) u8 {
-            return switch (v) {
-                .null => 0,
-                .bool => 1,
-                .integer, .float => 2,
-                .string => 3,
-                .array => 4,
-                .object => 5,
-                else => 6,
-            };
-        }
-    };
+test "eval type function" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-    const a_type = type_order.order(a);
-    const b_type = type_order.order(b);
+    const json = "{\"x\":1}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-    if (a_type != b_type) return a_type < b_type;
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "type", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    return switch (a) {
-        .null => false, // null == null
-        .bool => |ab| !ab and b.bool,
-        .integer => |ai| blk: {
-            const af: f64 = @floatFromInt(ai);
-            const bf: f64 = switch (b) {
-                .integer => |bi| @floatFromInt(bi),
-                .float => |bf| bf,
-                else => unreachable,
-            };
-            break :blk af < bf;
-        },
-        .float => |af| blk: {
-            const bf: f64 = switch (b) {
-                .integer => |bi| @floatFromInt(bi),
-                .float => |bf| bf,
-                else => unreachable,
-            };
-            break :blk af < bf;
-        },
-        .string => |as| std.mem.order(u8, as, b.string) == .lt,
-        else => false,
-    };
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    try std.testing.expectEqualStrings("object", result.values[0].string);
 }
 
-fn jsonEqual(a: std.json.Value, b: std.json.Value) bool {
-    if (@intFromEnum(a) != @intFromEnum(b)) return false;
-    return switch (a) {
-        .null => true,
-        .bool => |ab| ab == b.bool,
-        .integer => |ai| ai == b.integer,
-        .float => |af| af == b.float,
-        .string => |as| std.mem.eql(u8, as, b.string),
-        .array => |aa| blk: {
-            const ba = b.array;
-            if (aa.items.len != ba.items.len) break :blk false;
-            for (aa.items, ba.items) |av, bv| {
-                if (!jsonEqual(av, bv)) break :blk false;
-            }
-            break :blk true;
-        },
-        else => false,
-    };
+test "eval length function" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    const json = "\"hello\"";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "length", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
+
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    try std.testing.expectEqual(@as(i64, 5), result.values[0].integer);
 }
 
-fn evalObject(allocator: std.mem.Allocator, obj: ObjectExpr, value: std.json.Value) EvalError!EvalResult {
-    var map = std.json.ObjectMap.init(allocator);
+test "eval slice" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-    for (obj.fields) |field| {
-        // Evaluate key
-        const key: []const u8 = switch (field.key) {
-            .literal => |lit| lit,
-            .dynamic => |key_expr| blk: {
-                const key_result = try evalExpr(allocator, key_expr, value);
-                if (key_result.values.len == 0) continue;
-                switch (key_result.values[0]) {
-                    .string => |s| break :blk s,
-                    else => continue,
-                }
-            },
-        };
+    const json = "{\"items\":[0,1,2,3,4,5,6]}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-        // Evaluate value
-        const val_result = try evalExpr(allocator, field.value, value);
-        if (val_result.values.len > 0) {
-            try map.put(key, val_result.values[0]);
-        }
-    }
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), ".items[2:5]", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    return try EvalResult.single(allocator, .{ .object = map });
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    const arr = result.values[0].array;
+    try std.testing.expectEqual(@as(usize, 3), arr.items.len);
+    try std.testing.expectEqual(@as(i64, 2), arr.items[0].integer);
+    try std.testing.expectEqual(@as(i64, 3), arr.items[1].integer);
+    try std.testing.expectEqual(@as(i64, 4), arr.items[2].integer);
 }
 
-fn evalArithmetic(allocator: std.mem.Allocator, arith: ArithmeticExpr, value: std.json.Value) EvalError!EvalResult {
-    const left_result = try evalExpr(allocator, arith.left, value);
-    const right_result = try evalExpr(allocator, arith.right, value);
+test "eval slice negative" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-    if (left_result.values.len == 0 or right_result.values.len == 0) {
-        return EvalResult.empty(allocator);
-    }
+    const json = "{\"items\":[0,1,2,3]}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-    const left_val = left_result.values[0];
-    const right_val = right_result.values[0];
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), ".items[-2:]", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    // String concatenation with +
-    if (arith.op == .add) {
-        if (left_val == .string and right_val == .string) {
-            const result = try std.fmt.allocPrint(allocator, "{s}{s}", .{ left_val.string, right_val.string });
-            return try EvalResult.single(allocator, .{ .string = result });
-        }
-        // Object merge with + (jq semantics: right overrides left)
-        if (left_val == .object and right_val == .object) {
-            var merged = std.json.ObjectMap.init(allocator);
-            // Copy all from left
-            var left_iter = left_val.object.iterator();
-            while (left_iter.next()) |entry| {
-                try merged.put(entry.key_ptr.*, entry.value_ptr.*);
-            }
-            // Copy all from right (overrides left)
-            var right_iter = right_val.object.iterator();
-            while (right_iter.next()) |entry| {
-                try merged.put(entry.key_ptr.*, entry.value_ptr.*);
-            }
-            return try EvalResult.single(allocator, .{ .object = merged });
-        }
-        // Array concatenation with +
-        if (left_val == .array and right_val == .array) {
-            var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
-            try result_list.appendSlice(allocator, left_val.array.items);
-            try result_list.appendSlice(allocator, right_val.array.items);
-            const result_slice = try result_list.toOwnedSlice(allocator);
-            return try EvalResult.single(allocator, .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } });
-        }
-    }
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    const arr = result.values[0].array;
+    try std.testing.expectEqual(@as(usize, 2), arr.items.len);
+    try std.testing.expectEqual(@as(i64, 2), arr.items[0].integer);
+    try std.testing.expectEqual(@as(i64, 3), arr.items[1].integer);
+}
 
-    // Numeric operations
-    const left_num = getNumeric(left_val) orelse return EvalResult.empty(allocator);
-    const right_num = getNumeric(right_val) orelse return EvalResult.empty(allocator);
+test "eval has true" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-    const result: f64 = switch (arith.op) {
-        .add => left_num + right_num,
-        .sub => left_num - right_num,
-        .mul => left_num * right_num,
-        .div => if (right_num != 0) left_num / right_num else return EvalResult.empty(allocator),
-        .mod => if (right_num != 0) @mod(left_num, right_num) else return EvalResult.empty(allocator),
-    };
+    const json = "{\"x\":1}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-    // Return integer if both inputs were integers and result is whole
-    if (left_val == .integer and right_val == .integer and @trunc(result) == result) {
-        return try EvalResult.single(allocator, .{ .integer = @as(i64, @intFromFloat(result)) });
-    }
-    return try EvalResult.single(allocator, .{ .float = result });
-}
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "has(\"x\")", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-fn getNumeric(value: std.json.Value) ?f64 {
-    return switch (value) {
-        .integer => |i| @as(f64, @floatFromInt(i)),
-        .float => |f| f,
-        else => null,
-    };
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    try std.testing.expect(result.values[0].bool);
 }
 
-// ============================================================================
-// Sprint 03: Additional Evaluators
-// ============================================================================
-
-fn evalStrFunc(allocator: std.mem.Allocator, sf: StrFuncExpr, value: std.json.Value) EvalError!EvalResult {
-    switch (sf.kind) {
-        .split => {
-            switch (value) {
-                .string => |s| {
-                    var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
-                    var iter = std.mem.splitSequence(u8, s, sf.arg);
-                    while (iter.next()) |part| {
-                        try result_list.append(allocator, .{ .string = part });
-                    }
-                    const result_slice = try result_list.toOwnedSlice(allocator);
-                    return try EvalResult.single(allocator, .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } });
-                },
-                else => return EvalResult.empty(allocator),
-            }
-        },
-        .join => {
-            switch (value) {
-                .array => |arr| {
-                    var total_len: usize = 0;
-                    var str_count: usize = 0;
-                    for (arr.items) |item| {
-                        switch (item) {
-                            .string => |s| {
-                                total_len += s.len;
-                                str_count += 1;
-                            },
-                            else => {},
-                        }
-                    }
-                    if (str_count == 0) return try EvalResult.single(allocator, .{ .string = "" });
+test "eval has false" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-                    const sep_len = sf.arg.len * (str_count - 1);
-                    var result_str = try allocator.alloc(u8, total_len + sep_len);
-                    var pos: usize = 0;
-                    var first = true;
-                    for (arr.items) |item| {
-                        switch (item) {
-                            .string => |s| {
-                                if (!first) {
-                                    @memcpy(result_str[pos..][0..sf.arg.len], sf.arg);
-                                    pos += sf.arg.len;
-                                }
-                                @memcpy(result_str[pos..][0..s.len], s);
-                                pos += s.len;
-                                first = false;
-                            },
-                            else => {},
-                        }
-                    }
-                    return try EvalResult.single(allocator, .{ .string = result_str[0..pos] });
-                },
-                else => return EvalResult.empty(allocator),
-            }
-        },
-        .startswith => {
-            switch (value) {
-                .string => |s| {
-                    return try EvalResult.single(allocator, .{ .bool = std.mem.startsWith(u8, s, sf.arg) });
-                },
-                else => return EvalResult.empty(allocator),
-            }
-        },
-        .endswith => {
-            switch (value) {
-                .string => |s| {
-                    return try EvalResult.single(allocator, .{ .bool = std.mem.endsWith(u8, s, sf.arg) });
-                },
-                else => return EvalResult.empty(allocator),
-            }
-        },
-        .contains => {
-            switch (value) {
-                .string => |s| {
-                    return try EvalResult.single(allocator, .{ .bool = std.mem.indexOf(u8, s, sf.arg) != null });
-                },
-                else => return EvalResult.empty(allocator),
-            }
-        },
-        .ltrimstr => {
-            switch (value) {
-                .string => |s| {
-                    if (std.mem.startsWith(u8, s, sf.arg)) {
-                        return try EvalResult.single(allocator, .{ .string = s[sf.arg.len..] });
-                    }
-                    return try EvalResult.single(allocator, value);
-                },
-                else => return EvalResult.empty(allocator),
-            }
-        },
-        .rtrimstr => {
-            switch (value) {
-                .string => |s| {
-                    if (std.mem.endsWith(u8, s, sf.arg)) {
-                        return try EvalResult.single(allocator, .{ .string = s[0 .. s.len - sf.arg.len] });
-                    }
-                    return try EvalResult.single(allocator, value);
-                },
-                else => return EvalResult.empty(allocator),
-            }
-        },
-        .has => {
-            switch (value) {
-                .object => |obj| {
-                    const exists = obj.get(sf.arg) != null;
-                    return try EvalResult.single(allocator, .{ .bool = exists });
-                },
-                .array => |arr| {
-                    // jq: has("0") on arrays checks for index 0 as string
-                    if (std.fmt.parseInt(usize, sf.arg, 10)) |idx| {
-                        const exists = idx < arr.items.len;
-                        return try EvalResult.single(allocator, .{ .bool = exists });
-                    } else |_| {
-                        return try EvalResult.single(allocator, .{ .bool = false });
-                    }
-                },
-                else => return try EvalResult.single(allocator, .{ .bool = false }),
-            }
-        },
-        .@"test" => {
-            // Simplified regex matching supporting:
-            // - ^pattern  : starts with
-            // - pattern$  : ends with
-            // - ^pattern$ : exact match
-            // - pattern   : contains (default)
-            switch (value) {
-                .string => |s| {
-                    const pattern = sf.arg;
-                    const starts_anchor = pattern.len > 0 and pattern[0] == '^';
-                    const ends_anchor = pattern.len > 0 and pattern[pattern.len - 1] == '$';
-
-                    // Extract the literal part (without anchors)
-                    var literal = pattern;
-                    if (starts_anchor) literal = literal[1..];
-                    if (ends_anchor and literal.len > 0) literal = literal[0 .. literal.len - 1];
-
-                    const matches = if (starts_anchor and ends_anchor) blk: {
-                        // Exact match: ^pattern$
-                        break :blk std.mem.eql(u8, s, literal);
-                    } else if (starts_anchor) blk: {
-                        // Starts with: ^pattern
-                        break :blk std.mem.startsWith(u8, s, literal);
-                    } else if (ends_anchor) blk: {
-                        // Ends with: pattern$
-                        break :blk std.mem.endsWith(u8, s, literal);
-                    } else blk: {
-                        // Contains: pattern (default)
-                        break :blk std.mem.indexOf(u8, s, literal) != null;
-                    };
+    const json = "{\"x\":1}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-                    return try EvalResult.single(allocator, .{ .bool = matches });
-                },
-                else => return EvalResult.empty(allocator),
-            }
-        },
-    }
-}
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "has(\"y\")", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-fn evalMap(allocator: std.mem.Allocator, m: MapExpr, value: std.json.Value) EvalError!EvalResult {
-    switch (value) {
-        .array => |arr| {
-            var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
-            for (arr.items) |item| {
-                const item_result = try evalExpr(allocator, m.inner, item);
-                try result_list.appendSlice(allocator, item_result.values);
-            }
-            const result_slice = try result_list.toOwnedSlice(allocator);
-            return try EvalResult.single(allocator, .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } });
-        },
-        else => return EvalResult.empty(allocator),
-    }
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    try std.testing.expect(!result.values[0].bool);
 }
 
-fn evalByFunc(allocator: std.mem.Allocator, bf: ByFuncExpr, value: std.json.Value) EvalError!EvalResult {
-    switch (value) {
-        .array => |arr| {
-            switch (bf.kind) {
-                .group_by => {
-                    // Group by the path value
-                    // Use type-prefixed keys to prevent collisions between different types
-                    // e.g., string "1" vs integer 1 should group separately
-                    var groups = std.StringHashMap(std.ArrayListUnmanaged(std.json.Value)).init(allocator);
-
-                    for (arr.items) |item| {
-                        const key_val = getPath(item, bf.path) orelse continue;
-                        var key_str: []const u8 = undefined;
-                        switch (key_val) {
-                            // Prefix with type indicator to prevent hash collisions
-                            .string => |s| key_str = try std.fmt.allocPrint(allocator, "s:{s}", .{s}),
-                            .integer => |i| key_str = try std.fmt.allocPrint(allocator, "i:{d}", .{i}),
-                            .float => |f| key_str = try std.fmt.allocPrint(allocator, "f:{d}", .{f}),
-                            .bool => |b| key_str = if (b) "b:true" else "b:false",
-                            .null => key_str = "n:null",
-                            else => continue,
-                        }
+test "eval has null value" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-                        const entry = try groups.getOrPut(key_str);
-                        if (!entry.found_existing) {
-                            entry.value_ptr.* = .empty;
-                        }
-                        try entry.value_ptr.*.append(allocator, item);
-                    }
+    const json = "{\"x\":null}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-                    // Convert to array of arrays
-                    var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
-                    var iter = groups.valueIterator();
-                    while (iter.next()) |group| {
-                        const items = try group.toOwnedSlice(allocator);
-                        try result_list.append(allocator, .{ .array = .{ .items = items, .capacity = items.len, .allocator = allocator } });
-                    }
-                    const result_slice = try result_list.toOwnedSlice(allocator);
-                    return try EvalResult.single(allocator, .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } });
-                },
-                .sort_by => {
-                    const sorted = try allocator.alloc(std.json.Value, arr.items.len);
-                    @memcpy(sorted, arr.items);
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "has(\"x\")", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-                    const SortCtx = struct {
-                        path: [][]const u8,
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    try std.testing.expect(result.values[0].bool); // key exists even if null
+}
 
-                        fn lessThan(ctx: @This(), a: std.json.Value, b: std.json.Value) bool {
-                            const a_key = getPath(a, ctx.path) orelse return false;
-                            const b_key = getPath(b, ctx.path) orelse return true;
-                            return jsonLessThan({}, a_key, b_key);
-                        }
-                    };
+test "eval del" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-                    std.mem.sort(std.json.Value, sorted, SortCtx{ .path = bf.path }, SortCtx.lessThan);
-                    return try EvalResult.single(allocator, .{ .array = .{ .items = sorted, .capacity = sorted.len, .allocator = allocator } });
-                },
-                .unique_by => {
-                    var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
-                    var seen = std.StringHashMap(void).init(allocator);
+    const json = "{\"x\":1,\"y\":2}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-                    for (arr.items) |item| {
-                        const key_val = getPath(item, bf.path) orelse continue;
-                        var key_str: []const u8 = undefined;
-                        switch (key_val) {
-                            // Prefix with type indicator to prevent hash collisions
-                            .string => |s| key_str = try std.fmt.allocPrint(allocator, "s:{s}", .{s}),
-                            .integer => |i| key_str = try std.fmt.allocPrint(allocator, "i:{d}", .{i}),
-                            .float => |f| key_str = try std.fmt.allocPrint(allocator, "f:{d}", .{f}),
-                            .bool => |b| key_str = if (b) "b:true" else "b:false",
-                            .null => key_str = "n:null",
-                            else => continue,
-                        }
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "del(.x)", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-                        const entry = try seen.getOrPut(key_str);
-                        if (!entry.found_existing) {
-                            try result_list.append(allocator, item);
-                        }
-                    }
-                    const result_slice = try result_list.toOwnedSlice(allocator);
-                    return try EvalResult.single(allocator, .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } });
-                },
-                .min_by => {
-                    if (arr.items.len == 0) return try EvalResult.single(allocator, .null);
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    const obj = result.values[0].object;
+    try std.testing.expect(obj.get("x") == null);
+    try std.testing.expect(obj.get("y") != null);
+}
 
-                    var min_item = arr.items[0];
-                    var min_key = getPath(min_item, bf.path) orelse return EvalResult.empty(allocator);
+test "eval del missing key" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-                    for (arr.items[1..]) |item| {
-                        const key = getPath(item, bf.path) orelse continue;
-                        if (jsonLessThan({}, key, min_key)) {
-                            min_key = key;
-                            min_item = item;
-                        }
-                    }
-                    return try EvalResult.single(allocator, min_item);
-                },
-                .max_by => {
-                    if (arr.items.len == 0) return try EvalResult.single(allocator, .null);
+    const json = "{\"x\":1}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-                    var max_item = arr.items[0];
-                    var max_key = getPath(max_item, bf.path) orelse return EvalResult.empty(allocator);
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "del(.z)", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-                    for (arr.items[1..]) |item| {
-                        const key = getPath(item, bf.path) orelse continue;
-                        if (jsonLessThan({}, max_key, key)) {
-                            max_key = key;
-                            max_item = item;
-                        }
-                    }
-                    return try EvalResult.single(allocator, max_item);
-                },
-            }
-        },
-        else => return EvalResult.empty(allocator),
-    }
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    const obj = result.values[0].object;
+    try std.testing.expect(obj.get("x") != null); // x still there
 }
 
-fn evalArrayLiteral(allocator: std.mem.Allocator, arr_expr: ArrayExpr, value: std.json.Value) EvalError!EvalResult {
-    var result_list: std.ArrayListUnmanaged(std.json.Value) = .empty;
+test "eval del array index" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-    for (arr_expr.elements) |elem| {
-        const elem_result = try evalExpr(allocator, elem, value);
-        for (elem_result.values) |v| {
-            try result_list.append(allocator, v);
-        }
-    }
+    const json = "{\"arr\":[1,2,3]}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-    const result_slice = try result_list.toOwnedSlice(allocator);
-    return try EvalResult.single(allocator, .{ .array = .{ .items = result_slice, .capacity = result_slice.len, .allocator = allocator } });
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "del(.arr[0])", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
+
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    const obj = result.values[0].object;
+    const arr = obj.get("arr").?.array;
+    try std.testing.expectEqual(@as(usize, 2), arr.items.len);
+    try std.testing.expectEqual(@as(i64, 2), arr.items[0].integer);
+    try std.testing.expectEqual(@as(i64, 3), arr.items[1].integer);
 }
 
-// ============================================================================
-// Evaluator Tests
-// ============================================================================
+test "eval del array negative index" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
 
-const parser = @import("parser.zig");
-const parseExprWithContext = parser.parseExprWithContext;
-const parseCondition = parser.parseCondition;
-const ErrorContext = types.ErrorContext;
+    const json = "{\"arr\":[1,2,3]}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-test "eval condition and" {
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "del(.arr[-1])", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
+
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    const obj = result.values[0].object;
+    const arr = obj.get("arr").?.array;
+    try std.testing.expectEqual(@as(usize, 2), arr.items.len);
+    try std.testing.expectEqual(@as(i64, 1), arr.items[0].integer);
+    try std.testing.expectEqual(@as(i64, 2), arr.items[1].integer);
+}
+
+test "eval to_entries" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json_true = "{\"active\":true,\"verified\":true}";
-    const json_false = "{\"active\":true,\"verified\":false}";
+    const json = "{\"a\":1,\"b\":2}";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
     var err_ctx: ErrorContext = .{};
-    const cond = try parseCondition(arena.allocator(), ".active and .verified", &err_ctx);
-
-    const parsed_true = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json_true, .{});
-    const parsed_false = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json_false, .{});
+    const expr = try parseExprWithContext(arena.allocator(), "to_entries", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    try std.testing.expect(evalCondition(arena.allocator(), cond, parsed_true.value));
-    try std.testing.expect(!evalCondition(arena.allocator(), cond, parsed_false.value));
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    const arr = result.values[0].array;
+    try std.testing.expectEqual(@as(usize, 2), arr.items.len);
+    try std.testing.expect(arr.items[0].object.get("key") != null);
+    try std.testing.expect(arr.items[0].object.get("value") != null);
 }
 
-test "eval condition or" {
+test "eval from_entries" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json_admin = "{\"admin\":true,\"moderator\":false}";
-    const json_mod = "{\"admin\":false,\"moderator\":true}";
-    const json_neither = "{\"admin\":false,\"moderator\":false}";
+    const json = "[{\"key\":\"x\",\"value\":1},{\"key\":\"y\",\"value\":2}]";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
     var err_ctx: ErrorContext = .{};
-    const cond = try parseCondition(arena.allocator(), ".admin or .moderator", &err_ctx);
-
-    const parsed_admin = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json_admin, .{});
-    const parsed_mod = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json_mod, .{});
-    const parsed_neither = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json_neither, .{});
+    const expr = try parseExprWithContext(arena.allocator(), "from_entries", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    try std.testing.expect(evalCondition(arena.allocator(), cond, parsed_admin.value));
-    try std.testing.expect(evalCondition(arena.allocator(), cond, parsed_mod.value));
-    try std.testing.expect(!evalCondition(arena.allocator(), cond, parsed_neither.value));
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    const obj = result.values[0].object;
+    try std.testing.expectEqual(@as(i64, 1), obj.get("x").?.integer);
+    try std.testing.expectEqual(@as(i64, 2), obj.get("y").?.integer);
 }
 
-test "eval condition not" {
+test "eval from_entries k_v form" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json_deleted = "{\"deleted\":true}";
-    const json_active = "{\"deleted\":false}";
+    const json = "[{\"k\":\"x\",\"v\":1}]";
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
     var err_ctx: ErrorContext = .{};
-    const cond = try parseCondition(arena.allocator(), "not .deleted", &err_ctx);
-
-    const parsed_deleted = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json_deleted, .{});
-    const parsed_active = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json_active, .{});
+    const expr = try parseExprWithContext(arena.allocator(), "from_entries", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    try std.testing.expect(!evalCondition(arena.allocator(), cond, parsed_deleted.value));
-    try std.testing.expect(evalCondition(arena.allocator(), cond, parsed_active.value));
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    const obj = result.values[0].object;
+    try std.testing.expectEqual(@as(i64, 1), obj.get("x").?.integer);
 }
 
-test "getIndex positive" {
+test "eval test contains" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "[1,2,3,4,5]";
+    const json = "\"hello world\"";
     const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-    const first = getIndex(parsed.value, 0);
-    try std.testing.expect(first != null);
-    try std.testing.expectEqual(@as(i64, 1), first.?.integer);
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "test(\"wor\")", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    const third = getIndex(parsed.value, 2);
-    try std.testing.expect(third != null);
-    try std.testing.expectEqual(@as(i64, 3), third.?.integer);
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    try std.testing.expect(result.values[0].bool);
 }
 
-test "getIndex negative" {
+test "eval test starts with" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "[1,2,3,4,5]";
+    const json = "\"hello world\"";
     const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-    const last = getIndex(parsed.value, -1);
-    try std.testing.expect(last != null);
-    try std.testing.expectEqual(@as(i64, 5), last.?.integer);
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "test(\"^hello\")", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    const second_last = getIndex(parsed.value, -2);
-    try std.testing.expect(second_last != null);
-    try std.testing.expectEqual(@as(i64, 4), second_last.?.integer);
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    try std.testing.expect(result.values[0].bool);
 }
 
-test "getIndex out of bounds" {
+test "eval test ends with" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "[1,2,3]";
+    const json = "\"hello world\"";
     const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-    try std.testing.expect(getIndex(parsed.value, 10) == null);
-    try std.testing.expect(getIndex(parsed.value, -10) == null);
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "test(\"world$\")", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
+
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    try std.testing.expect(result.values[0].bool);
 }
 
-test "getIndex MIN_I64 does not overflow" {
+test "eval test exact match" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "[1,2,3]";
+    const json = "\"hello\"";
     const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
-    // std.math.minInt(i64) is -9223372036854775808
-    // Negating it would overflow since max i64 is 9223372036854775807
-    // This should return null, not crash
-    try std.testing.expect(getIndex(parsed.value, std.math.minInt(i64)) == null);
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "test(\"^hello$\")", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
+
+    try std.testing.expectEqual(@as(usize, 1), result.values.len);
+    try std.testing.expect(result.values[0].bool);
 }
 
-test "eval pipe" {
+test "eval test no match" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"x\":\"42\"}";
+    const json = "\"hello world\"";
     const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), ".x | tonumber", &err_ctx);
+    const expr = try parseExprWithContext(arena.allocator(), "test(\"^world\")", &err_ctx);
     const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
     try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    try std.testing.expectEqual(@as(i64, 42), result.values[0].integer);
+    try std.testing.expect(!result.values[0].bool);
 }
 
-test "eval object construction" {
+test "eval variable binding carries outer value into iteration" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"x\":1,\"y\":2}";
+    const json = "{\"price\":2,\"items\":[{\"name\":\"a\",\"qty\":3},{\"name\":\"b\",\"qty\":5}]}";
     const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "{a: .x, b: .y}", &err_ctx);
+    const expr = try parseExprWithContext(arena.allocator(), ".price as $p | .items[] | {name, total: (.qty * $p)}", &err_ctx);
     const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    try std.testing.expect(result.values[0] == .object);
-    try std.testing.expectEqual(@as(i64, 1), result.values[0].object.get("a").?.integer);
-    try std.testing.expectEqual(@as(i64, 2), result.values[0].object.get("b").?.integer);
+    try std.testing.expectEqual(@as(usize, 2), result.values.len);
+    try std.testing.expectEqualStrings("a", result.values[0].object.get("name").?.string);
+    try std.testing.expectEqual(@as(i64, 6), result.values[0].object.get("total").?.integer);
+    try std.testing.expectEqual(@as(i64, 10), result.values[1].object.get("total").?.integer);
 }
 
-test "eval alternative with null" {
+test "eval destructuring binding" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"x\":null,\"y\":1}";
+    const json = "{\"a\":1,\"b\":[2,3]}";
     const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), ".x // .y", &err_ctx);
+    const expr = try parseExprWithContext(arena.allocator(), ". as {a: $x, b: [$y, $z], $missing} | [$x, $y, $z, $missing]", &err_ctx);
     const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
     try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    try std.testing.expectEqual(@as(i64, 1), result.values[0].integer);
+    const items = result.values[0].array.items;
+    try std.testing.expectEqual(@as(usize, 4), items.len);
+    try std.testing.expectEqual(@as(i64, 1), items[0].integer);
+    try std.testing.expectEqual(@as(i64, 2), items[1].integer);
+    try std.testing.expectEqual(@as(i64, 3), items[2].integer);
+    try std.testing.expect(items[3] == .null);
 }
 
-test "eval arithmetic" {
+test "eval variable in select condition" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"a\":10,\"b\":3}";
+    const json = "{\"min\":4,\"items\":[3,4,5]}";
     const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), ".a + .b", &err_ctx);
+    const expr = try parseExprWithContext(arena.allocator(), ".min as $min | .items[] | select(. >= $min)", &err_ctx);
     const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    try std.testing.expectEqual(@as(i64, 13), result.values[0].integer);
+    try std.testing.expectEqual(@as(usize, 2), result.values.len);
+    try std.testing.expectEqual(@as(i64, 4), result.values[0].integer);
+    try std.testing.expectEqual(@as(i64, 5), result.values[1].integer);
 }
 
-test "eval string concatenation" {
+test "eval binding shadows outer variable" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"a\":\"foo\",\"b\":\"bar\"}";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "{\"a\":1,\"b\":2}", .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), ".a + .b", &err_ctx);
+    const expr = try parseExprWithContext(arena.allocator(), ".a as $x | .b as $x | $x", &err_ctx);
     const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
     try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    try std.testing.expectEqualStrings("foobar", result.values[0].string);
+    try std.testing.expectEqual(@as(i64, 2), result.values[0].integer);
 }
 
-test "eval conditional true" {
+test "eval reduce sum" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"x\":10}";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "[1,2,3,4]", .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "if .x > 5 then .x else .y end", &err_ctx);
+    const expr = try parseExprWithContext(arena.allocator(), "reduce .[] as $x (0; . + $x)", &err_ctx);
     const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
     try std.testing.expectEqual(@as(usize, 1), result.values.len);
     try std.testing.expectEqual(@as(i64, 10), result.values[0].integer);
 }
 
-test "eval type function" {
+test "eval reduce builds object from pairs" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"x\":1}";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "[[\"a\",1],[\"b\",2],[\"a\",3]]", .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "type", &err_ctx);
+    const expr = try parseExprWithContext(arena.allocator(), "reduce .[] as [$k, $v] ({}; . + {($k): $v})", &err_ctx);
     const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
     try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    try std.testing.expectEqualStrings("object", result.values[0].string);
+    try std.testing.expectEqual(@as(usize, 2), result.values[0].object.count());
+    try std.testing.expectEqual(@as(i64, 3), result.values[0].object.get("a").?.integer);
+    try std.testing.expectEqual(@as(i64, 2), result.values[0].object.get("b").?.integer);
 }
 
-test "eval length function" {
+test "eval foreach running total" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "\"hello\"";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "[1,2,3]", .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "length", &err_ctx);
+    const expr = try parseExprWithContext(arena.allocator(), "foreach .[] as $x (0; . + $x; [$x, .])", &err_ctx);
     const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    try std.testing.expectEqual(@as(i64, 5), result.values[0].integer);
+    try std.testing.expectEqual(@as(usize, 3), result.values.len);
+    try std.testing.expectEqual(@as(i64, 1), result.values[0].array.items[1].integer);
+    try std.testing.expectEqual(@as(i64, 3), result.values[1].array.items[1].integer);
+    try std.testing.expectEqual(@as(i64, 3), result.values[2].array.items[0].integer);
+    try std.testing.expectEqual(@as(i64, 6), result.values[2].array.items[1].integer);
 }
 
-test "eval slice" {
+test "eval recursive def" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"items\":[0,1,2,3,4,5,6]}";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "5", .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), ".items[2:5]", &err_ctx);
+    const expr = try parseExprWithContext(arena.allocator(), "def fac: if . <= 1 then 1 else ((. - 1 | fac) * .) end; fac", &err_ctx);
     const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
     try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    const arr = result.values[0].array;
-    try std.testing.expectEqual(@as(usize, 3), arr.items.len);
-    try std.testing.expectEqual(@as(i64, 2), arr.items[0].integer);
-    try std.testing.expectEqual(@as(i64, 3), arr.items[1].integer);
-    try std.testing.expectEqual(@as(i64, 4), arr.items[2].integer);
+    try std.testing.expectEqual(@as(i64, 120), result.values[0].integer);
 }
 
-test "eval slice negative" {
+test "eval def filter and value parameters" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"items\":[0,1,2,3]}";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "{\"a\":{\"b\":10,\"c\":[1,2]}}", .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), ".items[-2:]", &err_ctx);
+    // f is re-evaluated inside the body; $by runs the body once per value of .c[]
+    const expr = try parseExprWithContext(arena.allocator(), "def inc(f; $by): f + $by; .a | inc(.b; .c[])", &err_ctx);
     const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    const arr = result.values[0].array;
-    try std.testing.expectEqual(@as(usize, 2), arr.items.len);
-    try std.testing.expectEqual(@as(i64, 2), arr.items[0].integer);
-    try std.testing.expectEqual(@as(i64, 3), arr.items[1].integer);
+    try std.testing.expectEqual(@as(usize, 2), result.values.len);
+    try std.testing.expectEqual(@as(i64, 11), result.values[0].integer);
+    try std.testing.expectEqual(@as(i64, 12), result.values[1].integer);
 }
 
-test "eval has true" {
+test "eval def closes over its definition scope" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"x\":1}";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "{\"k\":3,\"xs\":[1,2]}", .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "has(\"x\")", &err_ctx);
+    const expr = try parseExprWithContext(arena.allocator(), ".k as $k | def addk: . + $k; .xs | map(addk)", &err_ctx);
     const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
     try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    try std.testing.expect(result.values[0].bool);
+    try std.testing.expectEqual(@as(i64, 4), result.values[0].array.items[0].integer);
+    try std.testing.expectEqual(@as(i64, 5), result.values[0].array.items[1].integer);
 }
 
-test "eval has false" {
+test "eval regex test and match" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"x\":1}";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "\"código ERR-1234\"", .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "has(\"y\")", &err_ctx);
-    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
-
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    try std.testing.expect(!result.values[0].bool);
+    const test_expr = try parseExprWithContext(arena.allocator(), "test(\"err-[0-9]{4}\"; \"i\")", &err_ctx);
+    const test_result = try evalExpr(arena.allocator(), &test_expr, parsed.value);
+    try std.testing.expect(test_result.values[0].bool);
+
+    // Offsets count code points, not bytes
+    const match_expr = try parseExprWithContext(arena.allocator(), "match(\"ERR-(?<num>[0-9]+)\")", &err_ctx);
+    const match_result = try evalExpr(arena.allocator(), &match_expr, parsed.value);
+    try std.testing.expectEqual(@as(usize, 1), match_result.values.len);
+    const m = match_result.values[0].object;
+    try std.testing.expectEqual(@as(i64, 7), m.get("offset").?.integer);
+    try std.testing.expectEqual(@as(i64, 8), m.get("length").?.integer);
+    const cap = m.get("captures").?.array.items[0].object;
+    try std.testing.expectEqualStrings("1234", cap.get("string").?.string);
+    try std.testing.expectEqualStrings("num", cap.get("name").?.string);
 }
 
-test "eval has null value" {
+test "eval regex capture and scan" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"x\":null}";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "\"2024-01-15 and 2025-02-20\"", .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "has(\"x\")", &err_ctx);
-    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
-
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    try std.testing.expect(result.values[0].bool); // key exists even if null
+    const capture_expr = try parseExprWithContext(arena.allocator(), "capture(\"(?<year>\\\\d{4})-(?<month>\\\\d{2})\")", &err_ctx);
+    const capture_result = try evalExpr(arena.allocator(), &capture_expr, parsed.value);
+    try std.testing.expectEqual(@as(usize, 1), capture_result.values.len);
+    try std.testing.expectEqualStrings("2024", capture_result.values[0].object.get("year").?.string);
+    try std.testing.expectEqualStrings("01", capture_result.values[0].object.get("month").?.string);
+
+    const scan_expr = try parseExprWithContext(arena.allocator(), "scan(\"\\\\d{4}\")", &err_ctx);
+    const scan_result = try evalExpr(arena.allocator(), &scan_expr, parsed.value);
+    try std.testing.expectEqual(@as(usize, 2), scan_result.values.len);
+    try std.testing.expectEqualStrings("2024", scan_result.values[0].string);
+    try std.testing.expectEqualStrings("2025", scan_result.values[1].string);
 }
 
-test "eval del" {
+test "eval regex split sub and gsub" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"x\":1,\"y\":2}";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
-
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "del(.x)", &err_ctx);
-    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    const obj = result.values[0].object;
-    try std.testing.expect(obj.get("x") == null);
-    try std.testing.expect(obj.get("y") != null);
+    const list = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "\"a, b,c\"", .{});
+    const split_expr = try parseExprWithContext(arena.allocator(), "split(\", *\"; null)", &err_ctx);
+    const split_result = try evalExpr(arena.allocator(), &split_expr, list.value);
+    const pieces = split_result.values[0].array.items;
+    try std.testing.expectEqual(@as(usize, 3), pieces.len);
+    try std.testing.expectEqualStrings("b", pieces[1].string);
+
+    const word = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "\"abc\"", .{});
+    const sub_expr = try parseExprWithContext(arena.allocator(), "sub(\"[a-z]\"; \"X\")", &err_ctx);
+    const sub_result = try evalExpr(arena.allocator(), &sub_expr, word.value);
+    try std.testing.expectEqualStrings("Xbc", sub_result.values[0].string);
+
+    // Empty matches replace at every position, including the end
+    const gsub_expr = try parseExprWithContext(arena.allocator(), "gsub(\"\"; \"-\")", &err_ctx);
+    const gsub_result = try evalExpr(arena.allocator(), &gsub_expr, word.value);
+    try std.testing.expectEqualStrings("-a-b-c-", gsub_result.values[0].string);
+
+    const named_expr = try parseExprWithContext(arena.allocator(), "gsub(\"(?<c>[ac])\"; .c + .c)", &err_ctx);
+    const named_result = try evalExpr(arena.allocator(), &named_expr, word.value);
+    try std.testing.expectEqualStrings("aabcc", named_result.values[0].string);
 }
 
-test "eval del missing key" {
+test "eval string interpolation" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"x\":1}";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "{\"user\":\"ann\",\"n\":2,\"tags\":[\"a\",\"b\"]}", .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "del(.z)", &err_ctx);
+    const expr = try parseExprWithContext(arena.allocator(), "\"\\(.user) has \\(.n) tags: \\(.tags)\"", &err_ctx);
     const result = try evalExpr(arena.allocator(), &expr, parsed.value);
-
     try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    const obj = result.values[0].object;
-    try std.testing.expect(obj.get("x") != null); // x still there
+    try std.testing.expectEqualStrings("ann has 2 tags: [\"a\",\"b\"]", result.values[0].string);
+
+    // One output per interpolated value
+    const multi_expr = try parseExprWithContext(arena.allocator(), "\"tag=\\(.tags[])\"", &err_ctx);
+    const multi_result = try evalExpr(arena.allocator(), &multi_expr, parsed.value);
+    try std.testing.expectEqual(@as(usize, 2), multi_result.values.len);
+    try std.testing.expectEqualStrings("tag=a", multi_result.values[0].string);
+    try std.testing.expectEqualStrings("tag=b", multi_result.values[1].string);
 }
 
-test "eval del array index" {
+test "eval format strings" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"arr\":[1,2,3]}";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "[\"it's\", \"say \\\"hi\\\"\", 1, null, true]", .{});
+
+    const cases = [_]struct { expr: []const u8, expected: []const u8 }{
+        .{ .expr = "@csv", .expected = "\"it's\",\"say \"\"hi\"\"\",1,,true" },
+        .{ .expr = "@tsv", .expected = "it's\tsay \"hi\"\t1\t\ttrue" },
+        .{ .expr = "@sh", .expected = "'it'\\''s' 'say \"hi\"' 1 null true" },
+        .{ .expr = "@json", .expected = "[\"it's\",\"say \\\"hi\\\"\",1,null,true]" },
+        .{ .expr = ".[0] | @html", .expected = "it&#39;s" },
+        .{ .expr = ".[1] | @uri", .expected = "say%20%22hi%22" },
+        .{ .expr = ".[0] | @base64", .expected = "aXQncw==" },
+        .{ .expr = ".[0] | @base64 | @base64d", .expected = "it's" },
+        .{ .expr = "@sh \"echo \\(.[0])\"", .expected = "echo 'it'\\''s'" },
+    };
 
-    var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "del(.arr[0])", &err_ctx);
-    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
+    for (cases) |case| {
+        var err_ctx: ErrorContext = .{};
+        const expr = try parseExprWithContext(arena.allocator(), case.expr, &err_ctx);
+        const result = try evalExpr(arena.allocator(), &expr, parsed.value);
+        try std.testing.expectEqual(@as(usize, 1), result.values.len);
+        try std.testing.expectEqualStrings(case.expected, result.values[0].string);
+    }
+}
 
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    const obj = result.values[0].object;
-    const arr = obj.get("arr").?.array;
-    try std.testing.expectEqual(@as(usize, 2), arr.items.len);
-    try std.testing.expectEqual(@as(i64, 2), arr.items[0].integer);
-    try std.testing.expectEqual(@as(i64, 3), arr.items[1].integer);
+test "eval update and arithmetic assignment" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "{\"items\":[{\"price\":10},{\"price\":20}],\"a\":{}}", .{});
+
+    var err_ctx: ErrorContext = .{};
+    const update = try parseExprWithContext(arena.allocator(), ".items[].price |= . * 2", &err_ctx);
+    const updated = try evalExpr(arena.allocator(), &update, parsed.value);
+    try std.testing.expectEqual(@as(usize, 1), updated.values.len);
+    const items = updated.values[0].object.get("items").?.array.items;
+    try std.testing.expectEqual(@as(i64, 20), items[0].object.get("price").?.integer);
+    try std.testing.expectEqual(@as(i64, 40), items[1].object.get("price").?.integer);
+    // The input is left untouched
+    try std.testing.expectEqual(@as(i64, 10), parsed.value.object.get("items").?.array.items[0].object.get("price").?.integer);
+
+    const set = try parseExprWithContext(arena.allocator(), ".a.b.c = 5 | .count += 1 | .name //= \"anon\"", &err_ctx);
+    const set_result = try evalExpr(arena.allocator(), &set, parsed.value);
+    const obj = set_result.values[0].object;
+    try std.testing.expectEqual(@as(i64, 5), obj.get("a").?.object.get("b").?.object.get("c").?.integer);
+    try std.testing.expectEqual(@as(i64, 1), obj.get("count").?.integer);
+    try std.testing.expectEqualStrings("anon", obj.get("name").?.string);
 }
 
-test "eval del array negative index" {
+test "eval update with empty deletes" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"arr\":[1,2,3]}";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "[1,2,3,4]", .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "del(.arr[-1])", &err_ctx);
+    const expr = try parseExprWithContext(arena.allocator(), ".[] |= select((. % 2) == 0)", &err_ctx);
     const result = try evalExpr(arena.allocator(), &expr, parsed.value);
-
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    const obj = result.values[0].object;
-    const arr = obj.get("arr").?.array;
-    try std.testing.expectEqual(@as(usize, 2), arr.items.len);
-    try std.testing.expectEqual(@as(i64, 1), arr.items[0].integer);
-    try std.testing.expectEqual(@as(i64, 2), arr.items[1].integer);
+    const arr = result.values[0].array.items;
+    try std.testing.expectEqual(@as(usize, 2), arr.len);
+    try std.testing.expectEqual(@as(i64, 2), arr[0].integer);
+    try std.testing.expectEqual(@as(i64, 4), arr[1].integer);
 }
 
-test "eval to_entries" {
+test "eval path functions" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "{\"a\":1,\"b\":2}";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "{\"a\":[1,{\"b\":2}],\"c\":\"x\"}", .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "to_entries", &err_ctx);
-    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
+    const path = try parseExprWithContext(arena.allocator(), "[path(.a[1].b)]", &err_ctx);
+    const path_result = try evalExpr(arena.allocator(), &path, parsed.value);
+    const p = path_result.values[0].array.items[0].array.items;
+    try std.testing.expectEqual(@as(usize, 3), p.len);
+    try std.testing.expectEqualStrings("a", p[0].string);
+    try std.testing.expectEqual(@as(i64, 1), p[1].integer);
+    try std.testing.expectEqualStrings("b", p[2].string);
+
+    // paths(f) filters by the value at each path
+    const numbers = try parseExprWithContext(arena.allocator(), "[paths(type == \"number\")]", &err_ctx);
+    const numbers_result = try evalExpr(arena.allocator(), &numbers, parsed.value);
+    try std.testing.expectEqual(@as(usize, 2), numbers_result.values[0].array.items.len);
+
+    const getpath = try parseExprWithContext(arena.allocator(), "getpath([\"a\", 1, \"b\"])", &err_ctx);
+    try std.testing.expectEqual(@as(i64, 2), (try evalExpr(arena.allocator(), &getpath, parsed.value)).values[0].integer);
+
+    const setpath = try parseExprWithContext(arena.allocator(), "setpath([\"d\", 1]; true) | .d", &err_ctx);
+    const padded = (try evalExpr(arena.allocator(), &setpath, parsed.value)).values[0].array.items;
+    try std.testing.expectEqual(@as(usize, 2), padded.len);
+    try std.testing.expect(padded[0] == .null);
+
+    const delpaths = try parseExprWithContext(arena.allocator(), "delpaths([[\"a\", 0], [\"c\"]])", &err_ctx);
+    const deleted = (try evalExpr(arena.allocator(), &delpaths, parsed.value)).values[0].object;
+    try std.testing.expect(deleted.get("c") == null);
+    try std.testing.expectEqual(@as(usize, 1), deleted.get("a").?.array.items.len);
+
+    // Targets must select existing locations
+    const invalid = try parseExprWithContext(arena.allocator(), "path(1 + 1)", &err_ctx);
+    try std.testing.expectError(error.InvalidPath, evalExpr(arena.allocator(), &invalid, parsed.value));
+}
 
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    const arr = result.values[0].array;
-    try std.testing.expectEqual(@as(usize, 2), arr.items.len);
-    try std.testing.expect(arr.items[0].object.get("key") != null);
-    try std.testing.expect(arr.items[0].object.get("value") != null);
+test "eval del with select and walk" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "[1,[2,3],{\"k\":4}]", .{});
+
+    var err_ctx: ErrorContext = .{};
+    const del = try parseExprWithContext(arena.allocator(), "del(.[] | select(type == \"number\"))", &err_ctx);
+    const del_result = try evalExpr(arena.allocator(), &del, parsed.value);
+    try std.testing.expectEqual(@as(usize, 2), del_result.values[0].array.items.len);
+
+    const walk = try parseExprWithContext(arena.allocator(), "walk(if type == \"number\" then (. * 10) else . end)", &err_ctx);
+    const walked = (try evalExpr(arena.allocator(), &walk, parsed.value)).values[0].array.items;
+    try std.testing.expectEqual(@as(i64, 10), walked[0].integer);
+    try std.testing.expectEqual(@as(i64, 30), walked[1].array.items[1].integer);
+    try std.testing.expectEqual(@as(i64, 40), walked[2].object.get("k").?.integer);
 }
 
-test "eval from_entries" {
+test "eval with_entries round trip" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "[{\"key\":\"x\",\"value\":1},{\"key\":\"y\",\"value\":2}]";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "{\"a\":1,\"b\":2}", .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "from_entries", &err_ctx);
+    const expr = try parseExprWithContext(arena.allocator(), "with_entries(.value += 10)", &err_ctx);
     const result = try evalExpr(arena.allocator(), &expr, parsed.value);
+    try std.testing.expectEqual(@as(i64, 11), result.values[0].object.get("a").?.integer);
+    try std.testing.expectEqual(@as(i64, 12), result.values[0].object.get("b").?.integer);
+}
 
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    const obj = result.values[0].object;
-    try std.testing.expectEqual(@as(i64, 1), obj.get("x").?.integer);
-    try std.testing.expectEqual(@as(i64, 2), obj.get("y").?.integer);
+test "eval try catch and error" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    const bad = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "{\"x\":\"abc\"}", .{});
+    const good = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "{\"x\":\"42\"}", .{});
+
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "try .x | tonumber catch \"bad\"", &err_ctx);
+    try std.testing.expectEqualStrings("bad", (try evalExpr(arena.allocator(), &expr, bad.value)).values[0].string);
+    try std.testing.expectEqual(@as(i64, 42), (try evalExpr(arena.allocator(), &expr, good.value)).values[0].integer);
+
+    // Uncaught type errors carry their message
+    const uncaught = try parseExprWithContext(arena.allocator(), ".x | tonumber", &err_ctx);
+    try std.testing.expectError(error.Raised, evalExpr(arena.allocator(), &uncaught, bad.value));
+    try std.testing.expectEqualStrings("Cannot parse 'abc' as JSON", errorValue().string);
+
+    const add = try parseExprWithContext(arena.allocator(), ".x + 1", &err_ctx);
+    try std.testing.expectError(error.Raised, evalExpr(arena.allocator(), &add, bad.value));
+    try std.testing.expectEqualStrings("string (\"abc\") and number (1) cannot be added", errorValue().string);
+
+    // A condition that fails is an error, not false
+    const cond = try parseExprWithContext(arena.allocator(), "select((.x | tonumber) > 1)", &err_ctx);
+    try std.testing.expectError(error.Raised, evalExpr(arena.allocator(), &cond, bad.value));
+    try std.testing.expectEqualStrings("Cannot parse 'abc' as JSON", errorValue().string);
+    try std.testing.expectEqual(@as(usize, 0), partialOutputs().len);
+    const caught_cond = try parseExprWithContext(arena.allocator(), "try select((.x | tonumber) > 1) catch \"bad\"", &err_ctx);
+    try std.testing.expectEqualStrings("bad", (try evalExpr(arena.allocator(), &caught_cond, bad.value)).values[0].string);
+
+    // The handler sees the error() argument, which can be any value
+    const object = try parseExprWithContext(arena.allocator(), "try error({code: 7}) catch .code", &err_ctx);
+    try std.testing.expectEqual(@as(i64, 7), (try evalExpr(arena.allocator(), &object, bad.value)).values[0].integer);
+
+    // ? drops the error, // treats it as no output
+    const list = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "[\"1\",\"x\",\"3\"]", .{});
+    const optional = try parseExprWithContext(arena.allocator(), "[.[] | tonumber?]", &err_ctx);
+    const numbers = (try evalExpr(arena.allocator(), &optional, list.value)).values[0].array.items;
+    try std.testing.expectEqual(@as(usize, 2), numbers.len);
+    try std.testing.expectEqual(@as(i64, 3), numbers[1].integer);
+
+    const alt = try parseExprWithContext(arena.allocator(), "(.x | tonumber) // 0", &err_ctx);
+    try std.testing.expectEqual(@as(i64, 0), (try evalExpr(arena.allocator(), &alt, bad.value)).values[0].integer);
 }
 
-test "eval from_entries k_v form" {
+test "eval outputs before an error" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "[{\"k\":\"x\",\"v\":1}]";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "[1,2,3]", .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "from_entries", &err_ctx);
-    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
+    const uncaught = try parseExprWithContext(arena.allocator(), ".[] | if . == 2 then error(\"stop\") else . end", &err_ctx);
+    try std.testing.expectError(error.Raised, evalExpr(arena.allocator(), &uncaught, parsed.value));
+    try std.testing.expectEqual(@as(usize, 1), partialOutputs().len);
+    try std.testing.expectEqual(@as(i64, 1), partialOutputs()[0].integer);
+
+    // try keeps them and appends the handler's outputs
+    const caught = try parseExprWithContext(arena.allocator(), "try (.[] | if . == 2 then error(\"stop\") else . end) catch .", &err_ctx);
+    const result = try evalExpr(arena.allocator(), &caught, parsed.value);
+    try std.testing.expectEqual(@as(usize, 2), result.values.len);
+    try std.testing.expectEqual(@as(i64, 1), result.values[0].integer);
+    try std.testing.expectEqualStrings("stop", result.values[1].string);
 
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    const obj = result.values[0].object;
-    try std.testing.expectEqual(@as(i64, 1), obj.get("x").?.integer);
+    // An array that never finished building contributes nothing
+    const array = try parseExprWithContext(arena.allocator(), "try [.[] | if . == 2 then error(\"stop\") else . end] catch .", &err_ctx);
+    const array_result = try evalExpr(arena.allocator(), &array, parsed.value);
+    try std.testing.expectEqual(@as(usize, 1), array_result.values.len);
+    try std.testing.expectEqualStrings("stop", array_result.values[0].string);
 }
 
-test "eval test contains" {
+test "eval label and break" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "\"hello world\"";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "[1,2,3,4]", .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "test(\"wor\")", &err_ctx);
+    const expr = try parseExprWithContext(arena.allocator(), "label $out | .[] | if . > 2 then break $out else . end", &err_ctx);
     const result = try evalExpr(arena.allocator(), &expr, parsed.value);
+    try std.testing.expectEqual(@as(usize, 2), result.values.len);
+    try std.testing.expectEqual(@as(i64, 2), result.values[1].integer);
+
+    // break skips inner labels and is not caught by try
+    const nested = try parseExprWithContext(arena.allocator(), "[label $a | label $b | try (.[] | if . == 2 then break $a else . end) catch \"caught\"]", &err_ctx);
+    const nested_result = (try evalExpr(arena.allocator(), &nested, parsed.value)).values[0].array.items;
+    try std.testing.expectEqual(@as(usize, 1), nested_result.len);
+    try std.testing.expectEqual(@as(i64, 1), nested_result[0].integer);
+}
 
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    try std.testing.expect(result.values[0].bool);
+test "eval range limit first last nth" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "[10,20,30]", .{});
+
+    var err_ctx: ErrorContext = .{};
+    const range = try parseExprWithContext(arena.allocator(), "[range(0; 10; 3)]", &err_ctx);
+    const steps = (try evalExpr(arena.allocator(), &range, parsed.value)).values[0].array.items;
+    try std.testing.expectEqual(@as(usize, 4), steps.len);
+    try std.testing.expectEqual(@as(i64, 9), steps[3].integer);
+
+    const down = try parseExprWithContext(arena.allocator(), "[range(3; 0; -1)]", &err_ctx);
+    try std.testing.expectEqual(@as(i64, 3), (try evalExpr(arena.allocator(), &down, parsed.value)).values[0].array.items[0].integer);
+
+    const limit = try parseExprWithContext(arena.allocator(), "[limit(2; .[])]", &err_ctx);
+    try std.testing.expectEqual(@as(usize, 2), (try evalExpr(arena.allocator(), &limit, parsed.value)).values[0].array.items.len);
+
+    // Infinite generators stop at the limit
+    const repeat = try parseExprWithContext(arena.allocator(), "[1 | limit(5; repeat(. * 2))]", &err_ctx);
+    const powers = (try evalExpr(arena.allocator(), &repeat, parsed.value)).values[0].array.items;
+    try std.testing.expectEqual(@as(usize, 5), powers.len);
+    try std.testing.expectEqual(@as(i64, 16), powers[4].integer);
+
+    // Outputs after the first don't run into the error
+    const first = try parseExprWithContext(arena.allocator(), "first(.[] | if . > 10 then error(\"late\") else . end)", &err_ctx);
+    try std.testing.expectEqual(@as(i64, 10), (try evalExpr(arena.allocator(), &first, parsed.value)).values[0].integer);
+
+    const last = try parseExprWithContext(arena.allocator(), "last(.[])", &err_ctx);
+    try std.testing.expectEqual(@as(i64, 30), (try evalExpr(arena.allocator(), &last, parsed.value)).values[0].integer);
+
+    const nth = try parseExprWithContext(arena.allocator(), "[nth(1), nth(2; .[])]", &err_ctx);
+    const nth_items = (try evalExpr(arena.allocator(), &nth, parsed.value)).values[0].array.items;
+    try std.testing.expectEqual(@as(i64, 20), nth_items[0].integer);
+    try std.testing.expectEqual(@as(i64, 30), nth_items[1].integer);
 }
 
-test "eval test starts with" {
+test "eval until while and recurse" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "\"hello world\"";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const one = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "1", .{});
 
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "test(\"^hello\")", &err_ctx);
-    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
+    const until = try parseExprWithContext(arena.allocator(), "until(. > 100; . * 2)", &err_ctx);
+    try std.testing.expectEqual(@as(i64, 128), (try evalExpr(arena.allocator(), &until, one.value)).values[0].integer);
+
+    const loop = try parseExprWithContext(arena.allocator(), "[while(. < 100; . * 2)]", &err_ctx);
+    const states = (try evalExpr(arena.allocator(), &loop, one.value)).values[0].array.items;
+    try std.testing.expectEqual(@as(usize, 7), states.len);
+    try std.testing.expectEqual(@as(i64, 64), states[6].integer);
+
+    const tree = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "{\"a\":{\"id\":1,\"b\":[{\"id\":2}]},\"id\":3}", .{});
+
+    // .. visits the input first, then depth first in key order
+    const ids = try parseExprWithContext(arena.allocator(), "[.. | .id? | select(. != null)]", &err_ctx);
+    const found = (try evalExpr(arena.allocator(), &ids, tree.value)).values[0].array.items;
+    try std.testing.expectEqual(@as(usize, 3), found.len);
+    try std.testing.expectEqual(@as(i64, 3), found[0].integer);
+    try std.testing.expectEqual(@as(i64, 1), found[1].integer);
+    try std.testing.expectEqual(@as(i64, 2), found[2].integer);
+
+    const count = try parseExprWithContext(arena.allocator(), "[recurse] | length", &err_ctx);
+    try std.testing.expectEqual(@as(i64, 7), (try evalExpr(arena.allocator(), &count, tree.value)).values[0].integer);
+
+    // recurse(f; cond) stops descending where cond fails
+    const halves = try parseExprWithContext(arena.allocator(), "[recurse(. / 2; . >= 1)]", &err_ctx);
+    const eight = try std.json.parseFromSlice(std.json.Value, arena.allocator(), "8", .{});
+    try std.testing.expectEqual(@as(usize, 4), (try evalExpr(arena.allocator(), &halves, eight.value)).values[0].array.items.len);
+
+    // .. is also a path expression
+    const paths = try parseExprWithContext(arena.allocator(), "[path(..)] | length", &err_ctx);
+    try std.testing.expectEqual(@as(i64, 7), (try evalExpr(arena.allocator(), &paths, tree.value)).values[0].integer);
+}
 
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    try std.testing.expect(result.values[0].bool);
+test "eval env and inputs" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    var err_ctx: ErrorContext = .{};
+    const env = try parseExprWithContext(arena.allocator(), "$ENV | type", &err_ctx);
+    try std.testing.expectEqualStrings("object", (try evalExpr(arena.allocator(), &env, .null)).values[0].string);
+
+    // Without an input source there is nothing more to read
+    const inputs = try parseExprWithContext(arena.allocator(), "[inputs]", &err_ctx);
+    try std.testing.expectEqual(@as(usize, 0), (try evalExpr(arena.allocator(), &inputs, .null)).values[0].array.items.len);
+    const input = try parseExprWithContext(arena.allocator(), "input", &err_ctx);
+    try std.testing.expectError(error.Raised, evalExpr(arena.allocator(), &input, .null));
+    try std.testing.expectEqualStrings("No more inputs", errorValue().string);
 }
 
-test "eval test ends with" {
+test "eval strptime strftime mktime gmtime" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
 
-    const json = "\"hello world\"";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const stamp: std.json.Value = .{ .integer = 1425599621 };
+    const todate = try parseExprWithContext(arena.allocator(), "todate", &err_ctx);
+    try std.testing.expectEqualStrings("2015-03-05T23:53:41Z", (try evalExpr(arena.allocator(), &todate, stamp)).values[0].string);
+
+    const gmtime = try parseExprWithContext(arena.allocator(), "gmtime | @json", &err_ctx);
+    try std.testing.expectEqualStrings("[41,53,23,5,2,2015,4,63]", (try evalExpr(arena.allocator(), &gmtime, stamp)).values[0].string);
+
+    const strftime = try parseExprWithContext(arena.allocator(), "strftime(\"%A, %B %d, %Y %G-W%V\")", &err_ctx);
+    try std.testing.expectEqualStrings("Thursday, March 05, 2015 2015-W10", (try evalExpr(arena.allocator(), &strftime, stamp)).values[0].string);
+
+    // A second argument formats at that offset east of UTC
+    const shifted = try parseExprWithContext(arena.allocator(), "strftime(\"%d %H:%M %z\"; \"+05:30\")", &err_ctx);
+    try std.testing.expectEqualStrings("06 05:23 +0530", (try evalExpr(arena.allocator(), &shifted, stamp)).values[0].string);
+
+    const roundtrip = try parseExprWithContext(arena.allocator(), "strptime(\"%Y-%m-%dT%H:%M:%SZ\") | mktime", &err_ctx);
+    try std.testing.expectEqual(@as(i64, 1425599507), (try evalExpr(arena.allocator(), &roundtrip, .{ .string = "2015-03-05T23:51:47Z" })).values[0].integer);
+
+    // %z offsets are folded into UTC
+    const access_log = try parseExprWithContext(arena.allocator(), "strptime(\"%d/%b/%Y:%H:%M:%S %z\") | mktime", &err_ctx);
+    try std.testing.expectEqual(@as(i64, 1425595907), (try evalExpr(arena.allocator(), &access_log, .{ .string = "05/Mar/2015:23:51:47 +0100" })).values[0].integer);
 
+    const fromdate = try parseExprWithContext(arena.allocator(), "fromdate", &err_ctx);
+    try std.testing.expectEqual(@as(i64, 1425595907), (try evalExpr(arena.allocator(), &fromdate, .{ .string = "2015-03-05T23:51:47+01:00" })).values[0].integer);
+    try std.testing.expectEqual(@as(i64, -86400), (try evalExpr(arena.allocator(), &fromdate, .{ .string = "1969-12-31" })).values[0].integer);
+    try std.testing.expectError(error.Raised, evalExpr(arena.allocator(), &fromdate, .{ .string = "2015-02-30T00:00:00Z" }));
+
+    const mismatch = try parseExprWithContext(arena.allocator(), "strptime(\"%Y-%m-%d\")", &err_ctx);
+    try std.testing.expectError(error.Raised, evalExpr(arena.allocator(), &mismatch, .{ .string = "05/03/2015" }));
+    try std.testing.expectEqualStrings("date \"05/03/2015\" does not match format \"%Y-%m-%d\"", errorValue().string);
+}
+
+test "eval date_trunc and dateadd" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "test(\"world$\")", &err_ctx);
-    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    try std.testing.expect(result.values[0].bool);
+    // Numbers stay numbers, ISO strings stay strings
+    const hour = try parseExprWithContext(arena.allocator(), "date_trunc(\"hour\")", &err_ctx);
+    try std.testing.expectEqual(@as(i64, 1425596400), (try evalExpr(arena.allocator(), &hour, .{ .integer = 1425599507 })).values[0].integer);
+    try std.testing.expectEqualStrings("2015-03-05T23:00:00Z", (try evalExpr(arena.allocator(), &hour, .{ .string = "2015-03-05T23:51:47Z" })).values[0].string);
+
+    const week = try parseExprWithContext(arena.allocator(), "date_trunc(\"week\")", &err_ctx);
+    try std.testing.expectEqualStrings("2015-03-02T00:00:00Z", (try evalExpr(arena.allocator(), &week, .{ .string = "2015-03-05T23:51:47Z" })).values[0].string);
+
+    // Adding a month keeps the day, clamped to the month's length
+    const month = try parseExprWithContext(arena.allocator(), "dateadd(\"months\"; 1)", &err_ctx);
+    try std.testing.expectEqualStrings("2024-02-29T10:00:00Z", (try evalExpr(arena.allocator(), &month, .{ .string = "2024-01-31T10:00:00Z" })).values[0].string);
+
+    const day = try parseExprWithContext(arena.allocator(), "datesub(\"day\"; 1)", &err_ctx);
+    try std.testing.expectEqual(@as(i64, 1425513107), (try evalExpr(arena.allocator(), &day, .{ .integer = 1425599507 })).values[0].integer);
+
+    const unknown = try parseExprWithContext(arena.allocator(), "date_trunc(\"fortnight\")", &err_ctx);
+    try std.testing.expectError(error.Raised, evalExpr(arena.allocator(), &unknown, .{ .integer = 0 }));
 }
 
-test "eval test exact match" {
+test "eval globals from command line arguments" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
 
-    const json = "\"hello\"";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    // zq --arg user alice binds $user for the whole expression
+    const names = [_][]const u8{"user"};
+    const scope: types.VarScope = .{ .names = &names };
+    var err_ctx: ErrorContext = .{ .scope = &scope };
+    setGlobals(try Env.bind(arena.allocator(), null, "user", .{ .string = "alice" }));
+    defer setGlobals(null);
+
+    const expr = try parseExprWithContext(arena.allocator(), "select(.name == $user) | .id", &err_ctx);
+    var input: std.json.ObjectMap = .init(arena.allocator());
+    try input.put("name", .{ .string = "alice" });
+    try input.put("id", .{ .integer = 7 });
+    try std.testing.expectEqual(@as(i64, 7), (try evalExpr(arena.allocator(), &expr, .{ .object = input })).values[0].integer);
+
+    try input.put("name", .{ .string = "bob" });
+    try std.testing.expectEqual(@as(usize, 0), (try evalExpr(arena.allocator(), &expr, .{ .object = input })).values.len);
+
+    // Names outside the scope are still compile errors
+    var bare_ctx: ErrorContext = .{};
+    try std.testing.expectError(error.UndefinedVariable, parseExprWithContext(arena.allocator(), "$user", &bare_ctx));
+}
 
+test "eval tostream fromstream truncate_stream" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "test(\"^hello$\")", &err_ctx);
-    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    try std.testing.expect(result.values[0].bool);
+    const input = try std.json.parseFromSliceLeaky(std.json.Value, arena.allocator(), "{\"a\":[1,{\"b\":2}]}", .{});
+
+    const events = try parseExprWithContext(arena.allocator(), "[tostream]", &err_ctx);
+    const events_json = try toJsonText(arena.allocator(), (try evalExpr(arena.allocator(), &events, input)).values[0]);
+    try std.testing.expectEqualStrings("[[[\"a\",0],1],[[\"a\",1,\"b\"],2],[[\"a\",1,\"b\"]],[[\"a\",1]]]", events_json);
+
+    const round_trip = try parseExprWithContext(arena.allocator(), "fromstream(tostream)", &err_ctx);
+    const rebuilt = try toJsonText(arena.allocator(), (try evalExpr(arena.allocator(), &round_trip, input)).values[0]);
+    try std.testing.expectEqualStrings("{\"a\":[1,{\"b\":2}]}", rebuilt);
+
+    // The jq manual's example: the [1,[2]] stream cut by one level
+    const truncated = try parseExprWithContext(arena.allocator(), "[1|truncate_stream([[0],1],[[1,0],2],[[1,0]],[[1]])]", &err_ctx);
+    const truncated_json = try toJsonText(arena.allocator(), (try evalExpr(arena.allocator(), &truncated, .null)).values[0]);
+    try std.testing.expectEqualStrings("[[[0],2],[[0]]]", truncated_json);
+
+    // Elements of .a, rebuilt one at a time; scalars at the cut are dropped, as in jq
+    const elements = try parseExprWithContext(arena.allocator(), ". as $v | [fromstream(1|truncate_stream($v.a | tostream))]", &err_ctx);
+    const elements_json = try toJsonText(arena.allocator(), (try evalExpr(arena.allocator(), &elements, input)).values[0]);
+    try std.testing.expectEqualStrings("[{\"b\":2}]", elements_json);
+
+    const bad = try parseExprWithContext(arena.allocator(), "fromstream(1)", &err_ctx);
+    try std.testing.expectError(error.Raised, evalExpr(arena.allocator(), &bad, .null));
 }
 
-test "eval test no match" {
+test "eval keeps number literals exact" {
     var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
     defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
 
-    const json = "\"hello world\"";
-    const parsed = try std.json.parseFromSlice(std.json.Value, arena.allocator(), json, .{});
+    const input = try number.parseJson(arena.allocator(), "{\"id\":12345678901234567890123,\"items\":[{\"price\":10.10},{\"price\":0.20},{\"price\":19.99}]}", .{});
+
+    const cases = [_]struct { expr: []const u8, expected: []const u8 }{
+        .{ .expr = ".id + 1", .expected = "12345678901234567890124" },
+        .{ .expr = "select(.id > 12345678901234567890122) | .id", .expected = "12345678901234567890123" },
+        .{ .expr = "[.items[].price] | add", .expected = "30.29" },
+        .{ .expr = "(.items[2].price | todecimal) * 3", .expected = "59.97" },
+        .{ .expr = "[.items[].price | tostring]", .expected = "[\"10.10\",\"0.20\",\"19.99\"]" },
+        .{ .expr = "[.items[].price | tostring | tonumber]", .expected = "[10.10,0.20,19.99]" },
+        .{ .expr = "[.items[] | select(.price > 0.2) | .price]", .expected = "[10.10,19.99]" },
+        .{ .expr = ".items | sort_by(.price) | map(.price)", .expected = "[0.20,10.10,19.99]" },
+        // Anything but + - * works on the nearest f64
+        .{ .expr = ".items[0].price / 2", .expected = "5.05" },
+    };
+    for (cases) |case| {
+        const expr = try parseExprWithContext(arena.allocator(), case.expr, &err_ctx);
+        const result = try evalExpr(arena.allocator(), &expr, input);
+        try std.testing.expectEqualStrings(case.expected, try toJsonText(arena.allocator(), result.values[0]));
+    }
+}
+
+/// A jq manual style example for one registered builtin, keyed by its
+/// `builtins.signature` ("name/arity", "@format" or "$name").
+const ManualExample = struct {
+    builtin: []const u8,
+    expr: []const u8,
+    input: []const u8 = "null",
+    expected: []const u8 = "",
+    /// halt and halt_error stop the program instead of producing output
+    halts: bool = false,
+};
+
+// Clocks and random sources are checked for shape, not value
+const manual_examples = [_]ManualExample{
+    // CORE
+    .{ .builtin = "empty/0", .expr = "[1, empty, 2]", .expected = "[1,2]" },
+    .{ .builtin = "error/0", .expr = "try error catch .", .input = "\"oops\"", .expected = "\"oops\"" },
+    .{ .builtin = "error/1", .expr = "try error(\"some exception\") catch .", .expected = "\"some exception\"" },
+    .{ .builtin = "not/0", .expr = "[.[] | not]", .input = "[true,false,null,0]", .expected = "[false,true,true,false]" },
+    .{ .builtin = "select/1", .expr = "map(select(. >= 2))", .input = "[1,5,3,0,7]", .expected = "[5,3,7]" },
+    .{ .builtin = "map/1", .expr = "map(. + 1)", .input = "[1,2,3]", .expected = "[2,3,4]" },
+    .{ .builtin = "map_values/1", .expr = "map_values(. + 1)", .input = "{\"a\":1,\"b\":2}", .expected = "{\"a\":2,\"b\":3}" },
+    .{ .builtin = "recurse/0", .expr = "[recurse]", .input = "[1,[2]]", .expected = "[[1,[2]],1,[2],2]" },
+    .{ .builtin = "recurse/1", .expr = "[recurse(.children[]) | .name]", .input = "{\"name\":\"a\",\"children\":[{\"name\":\"b\",\"children\":[]}]}", .expected = "[\"a\",\"b\"]" },
+    .{ .builtin = "recurse/2", .expr = "[recurse(. * .; . < 20)]", .input = "2", .expected = "[2,4,16]" },
+    .{ .builtin = "walk/1", .expr = "walk(if type == \"array\" then sort else . end)", .input = "[[4,1,7],[8,5,2],[3,6,9]]", .expected = "[[1,4,7],[2,5,8],[3,6,9]]" },
+    .{ .builtin = "halt/0", .expr = "halt", .halts = true },
+    .{ .builtin = "halt_error/0", .expr = "halt_error", .input = "\"bye\\n\"", .halts = true },
+    .{ .builtin = "halt_error/1", .expr = "halt_error(1)", .input = "\"bye\\n\"", .halts = true },
+    .{ .builtin = "$__loc__", .expr = "$__loc__", .expected = "{\"file\":\"<stdin>\",\"line\":1}" },
+    // TYPES
+    .{ .builtin = "type/0", .expr = "map(type)", .input = "[0,false,[],{},null,\"hello\"]", .expected = "[\"number\",\"boolean\",\"array\",\"object\",\"null\",\"string\"]" },
+    .{ .builtin = "arrays/0", .expr = "[.[] | arrays]", .input = "[[1],{\"a\":1},1,\"s\",null,true]", .expected = "[[1]]" },
+    .{ .builtin = "objects/0", .expr = "[.[] | objects]", .input = "[[1],{\"a\":1},1,\"s\",null,true]", .expected = "[{\"a\":1}]" },
+    .{ .builtin = "iterables/0", .expr = "[.[] | iterables]", .input = "[[1],{\"a\":1},1,\"s\",null,true]", .expected = "[[1],{\"a\":1}]" },
+    .{ .builtin = "booleans/0", .expr = "[.[] | booleans]", .input = "[[1],{\"a\":1},1,\"s\",null,true]", .expected = "[true]" },
+    .{ .builtin = "numbers/0", .expr = "[.[] | numbers]", .input = "[1,\"a\",null,2]", .expected = "[1,2]" },
+    .{ .builtin = "strings/0", .expr = "[.[] | strings]", .input = "[[1],{\"a\":1},1,\"s\",null,true]", .expected = "[\"s\"]" },
+    .{ .builtin = "nulls/0", .expr = "[.[] | nulls]", .input = "[[1],{\"a\":1},1,\"s\",null,true]", .expected = "[null]" },
+    .{ .builtin = "values/0", .expr = "values", .input = "{\"a\":1,\"b\":null}", .expected = "[1,null]" },
+    .{ .builtin = "scalars/0", .expr = "[.[] | scalars]", .input = "[[1],{\"a\":1},1,\"s\",null,true]", .expected = "[1,\"s\",null,true]" },
+    .{ .builtin = "tostring/0", .expr = "map(tostring)", .input = "[1,\"1\",[1]]", .expected = "[\"1\",\"1\",\"[1]\"]" },
+    .{ .builtin = "tonumber/0", .expr = "map(tonumber)", .input = "[1,\"1\"]", .expected = "[1,1]" },
+    .{ .builtin = "tojson/0", .expr = "map(tojson)", .input = "[1,\"foo\",[1]]", .expected = "[\"1\",\"\\\"foo\\\"\",\"[1]\"]" },
+    .{ .builtin = "fromjson/0", .expr = "fromjson", .input = "\"{\\\"a\\\":1}\"", .expected = "{\"a\":1}" },
+    .{ .builtin = "toarray/0", .expr = "map(toarray)", .input = "[1,[2]]", .expected = "[[1],[2]]" },
+    .{ .builtin = "isarray/0", .expr = "map(isarray)", .input = "[[],1]", .expected = "[true,false]" },
+    .{ .builtin = "isboolean/0", .expr = "map(isboolean)", .input = "[true,1]", .expected = "[true,false]" },
+    .{ .builtin = "isnull/0", .expr = "map(isnull)", .input = "[null,0]", .expected = "[true,false]" },
+    .{ .builtin = "isnumber/0", .expr = "map(isnumber)", .input = "[1,\"1\"]", .expected = "[true,false]" },
+    .{ .builtin = "isobject/0", .expr = "map(isobject)", .input = "[{},[]]", .expected = "[true,false]" },
+    .{ .builtin = "isstring/0", .expr = "map(isstring)", .input = "[\"a\",1]", .expected = "[true,false]" },
+    .{ .builtin = "bool/0", .expr = "map(bool)", .input = "[0,1,\"\",[]]", .expected = "[false,true,false,false]" },
+    .{ .builtin = "float/0", .expr = "float", .input = "\"2.5\"", .expected = "2.5" },
+    .{ .builtin = "int/0", .expr = "int", .input = "3.7", .expected = "3" },
+    .{ .builtin = "todecimal/0", .expr = "(\"0.1\" | todecimal) + (\"0.2\" | todecimal)", .expected = "0.3" },
+    // ARRAYS AND OBJECTS
+    .{ .builtin = "length/0", .expr = "[.[] | length]", .input = "[-5,\"h\\u00e9llo\",[1,2],{\"a\":1},null]", .expected = "[5,5,2,1,0]" },
+    .{ .builtin = "utf8bytelength/0", .expr = "[.[] | utf8bytelength]", .input = "[\"h\\u00e9llo\"]", .expected = "[6]" },
+    .{ .builtin = "keys/0", .expr = "keys", .input = "{\"abc\":1,\"abcd\":2,\"Foo\":3}", .expected = "[\"Foo\",\"abc\",\"abcd\"]" },
+    .{ .builtin = "keys_unsorted/0", .expr = "keys_unsorted", .input = "{\"abc\":1,\"abcd\":2,\"Foo\":3}", .expected = "[\"abc\",\"abcd\",\"Foo\"]" },
+    .{ .builtin = "has/1", .expr = "[has(0), has(5)]", .input = "[1]", .expected = "[true,false]" },
+    .{ .builtin = "in/1", .expr = "[.[] | in({foo: 42})]", .input = "[\"foo\",\"bar\"]", .expected = "[true,false]" },
+    .{ .builtin = "contains/1", .expr = "contains([\"baz\", \"bar\"])", .input = "[\"foobar\",\"foobaz\",\"blarp\"]", .expected = "true" },
+    .{ .builtin = "contains/1", .expr = "contains({foo: 12, bar: [{barp: 12}]})", .input = "{\"foo\":12,\"bar\":[1,2,{\"barp\":12,\"blip\":13}]}", .expected = "true" },
+    .{ .builtin = "inside/1", .expr = "inside(\"foobar\")", .input = "\"bar\"", .expected = "true" },
+    .{ .builtin = "add/0", .expr = "add", .input = "[1,2,3]", .expected = "6" },
+    .{ .builtin = "any/0", .expr = "any", .input = "[true,false]", .expected = "true" },
+    .{ .builtin = "any/1", .expr = "any(. > 2)", .input = "[1,2,3]", .expected = "true" },
+    .{ .builtin = "any/2", .expr = "any(.[]; . == 2)", .input = "[1,2]", .expected = "true" },
+    .{ .builtin = "all/0", .expr = "all", .input = "[true,false]", .expected = "false" },
+    .{ .builtin = "all/1", .expr = "all(. > 2)", .input = "[1,2,3]", .expected = "false" },
+    .{ .builtin = "all/2", .expr = "all(.[]; . > 0)", .input = "[1,2]", .expected = "true" },
+    .{ .builtin = "flatten/0", .expr = "flatten", .input = "[1,[2],[[3]]]", .expected = "[1,2,3]" },
+    .{ .builtin = "flatten/1", .expr = "flatten(1)", .input = "[1,[2],[[3]]]", .expected = "[1,2,[3]]" },
+    .{ .builtin = "first/0", .expr = "first", .input = "[1,2]", .expected = "1" },
+    .{ .builtin = "last/0", .expr = "last", .input = "[1,2]", .expected = "2" },
+    .{ .builtin = "nth/1", .expr = "nth(1)", .input = "[1,2,3]", .expected = "2" },
+    .{ .builtin = "reverse/0", .expr = "reverse", .input = "[1,2,3]", .expected = "[3,2,1]" },
+    .{ .builtin = "sort/0", .expr = "sort", .input = "[[2],[1,2],{\"b\":1},{\"a\":2},{\"a\":1}]", .expected = "[[1,2],[2],{\"a\":1},{\"a\":2},{\"b\":1}]" },
+    .{ .builtin = "sort_by/1", .expr = "sort_by(.foo)", .input = "[{\"foo\":4,\"bar\":10},{\"foo\":3,\"bar\":10},{\"foo\":2,\"bar\":1}]", .expected = "[{\"foo\":2,\"bar\":1},{\"foo\":3,\"bar\":10},{\"foo\":4,\"bar\":10}]" },
+    .{ .builtin = "group_by/1", .expr = "group_by(.foo)", .input = "[{\"foo\":1,\"bar\":10},{\"foo\":3,\"bar\":100},{\"foo\":1,\"bar\":1}]", .expected = "[[{\"foo\":1,\"bar\":10},{\"foo\":1,\"bar\":1}],[{\"foo\":3,\"bar\":100}]]" },
+    .{ .builtin = "unique/0", .expr = "unique", .input = "[1,2,5,3,5,3,1,3]", .expected = "[1,2,3,5]" },
+    .{ .builtin = "unique_by/1", .expr = "unique_by(length)", .input = "[\"chunky\",\"bacon\",\"kitten\",\"cicada\",\"asparagus\"]", .expected = "[\"bacon\",\"chunky\",\"asparagus\"]" },
+    .{ .builtin = "min/0", .expr = "min", .input = "[5,4,2,7]", .expected = "2" },
+    .{ .builtin = "max/0", .expr = "max", .input = "[5,4,2,7]", .expected = "7" },
+    .{ .builtin = "min_by/1", .expr = "min_by(.foo)", .input = "[{\"foo\":1,\"bar\":14},{\"foo\":2,\"bar\":3}]", .expected = "{\"foo\":1,\"bar\":14}" },
+    .{ .builtin = "max_by/1", .expr = "max_by(.foo)", .input = "[{\"foo\":1,\"bar\":14},{\"foo\":2,\"bar\":3}]", .expected = "{\"foo\":2,\"bar\":3}" },
+    .{ .builtin = "indices/1", .expr = "indices(\", \")", .input = "\"a,b, cd, efg, hijk\"", .expected = "[3,7,12]" },
+    .{ .builtin = "indices/1", .expr = "indices(1)", .input = "[0,1,2,1,3,1,4]", .expected = "[1,3,5]" },
+    .{ .builtin = "indices/1", .expr = "indices([1,2])", .input = "[0,1,2,3,1,4,2,5,1,2,6,7]", .expected = "[1,8]" },
+    .{ .builtin = "index/1", .expr = "index(\", \")", .input = "\"a,b, cd, efg, hijk\"", .expected = "3" },
+    .{ .builtin = "rindex/1", .expr = "rindex(\", \")", .input = "\"a,b, cd, efg, hijk\"", .expected = "12" },
+    .{ .builtin = "transpose/0", .expr = "transpose", .input = "[[1],[2,3]]", .expected = "[[1,2],[null,3]]" },
+    .{ .builtin = "combinations/0", .expr = "[combinations]", .input = "[[1,2],[3,4]]", .expected = "[[1,3],[1,4],[2,3],[2,4]]" },
+    .{ .builtin = "combinations/1", .expr = "[combinations(2)]", .input = "[0,1]", .expected = "[[0,0],[0,1],[1,0],[1,1]]" },
+    .{ .builtin = "to_entries/0", .expr = "to_entries", .input = "{\"a\":1,\"b\":2}", .expected = "[{\"key\":\"a\",\"value\":1},{\"key\":\"b\",\"value\":2}]" },
+    .{ .builtin = "from_entries/0", .expr = "from_entries", .input = "[{\"key\":\"a\",\"value\":1},{\"key\":\"b\",\"value\":2}]", .expected = "{\"a\":1,\"b\":2}" },
+    .{ .builtin = "with_entries/1", .expr = "with_entries(.value += 1)", .input = "{\"a\":1,\"b\":2}", .expected = "{\"a\":2,\"b\":3}" },
+    // SQL-STYLE
+    .{ .builtin = "IN/1", .expr = "[.[] | IN(2, 3)]", .input = "[1,2,3]", .expected = "[false,true,true]" },
+    .{ .builtin = "IN/2", .expr = "IN(.[]; 2, 3)", .input = "[1,2]", .expected = "true" },
+    .{ .builtin = "INDEX/1", .expr = "INDEX(.id)", .input = "[{\"id\":1,\"n\":\"a\"},{\"id\":\"x\",\"n\":\"b\"}]", .expected = "{\"1\":{\"id\":1,\"n\":\"a\"},\"x\":{\"id\":\"x\",\"n\":\"b\"}}" },
+    .{ .builtin = "INDEX/2", .expr = "INDEX(.[]; .id)", .input = "[{\"id\":1},{\"id\":2}]", .expected = "{\"1\":{\"id\":1},\"2\":{\"id\":2}}" },
+    .{ .builtin = "JOIN/2", .expr = "JOIN({a: 1}; .k)", .input = "[{\"k\":\"a\"},{\"k\":\"b\"}]", .expected = "[[{\"k\":\"a\"},1],[{\"k\":\"b\"},null]]" },
+    .{ .builtin = "JOIN/3", .expr = ".idx as $idx | [JOIN($idx; .rows[]; .k)]", .input = "{\"idx\":{\"a\":1},\"rows\":[{\"k\":\"a\"},{\"k\":\"b\"}]}", .expected = "[[{\"k\":\"a\"},1],[{\"k\":\"b\"},null]]" },
+    .{ .builtin = "JOIN/4", .expr = "[JOIN({a: 1}; .[]; .k; .[1])]", .input = "[{\"k\":\"a\"},{\"k\":\"b\"}]", .expected = "[1,null]" },
+    // PATHS
+    .{ .builtin = "path/1", .expr = "path(.a[0].b)", .expected = "[\"a\",0,\"b\"]" },
+    .{ .builtin = "paths/0", .expr = "[paths]", .input = "[1,[[],{\"a\":2}]]", .expected = "[[0],[1],[1,0],[1,1],[1,1,\"a\"]]" },
+    .{ .builtin = "paths/1", .expr = "[paths(type == \"number\")]", .input = "[1,[[],{\"a\":2}]]", .expected = "[[0],[1,1,\"a\"]]" },
+    .{ .builtin = "leaf_paths/0", .expr = "[leaf_paths]", .input = "[1,[[],{\"a\":2}]]", .expected = "[[0],[1,1,\"a\"]]" },
+    .{ .builtin = "getpath/1", .expr = "getpath([\"a\",\"b\"])", .input = "{\"a\":{\"b\":0,\"c\":1}}", .expected = "0" },
+    .{ .builtin = "setpath/2", .expr = "setpath([\"a\",\"b\"]; 1)", .expected = "{\"a\":{\"b\":1}}" },
+    .{ .builtin = "delpaths/1", .expr = "delpaths([[\"a\",\"b\"]])", .input = "{\"a\":{\"b\":1},\"x\":{\"y\":2}}", .expected = "{\"a\":{},\"x\":{\"y\":2}}" },
+    .{ .builtin = "del/1", .expr = "del(.foo)", .input = "{\"foo\":42,\"bar\":9001}", .expected = "{\"bar\":9001}" },
+    .{ .builtin = "pick/1", .expr = "pick(.a, .b.c)", .input = "{\"a\":1,\"b\":{\"c\":2,\"d\":3},\"e\":4}", .expected = "{\"a\":1,\"b\":{\"c\":2}}" },
+    .{ .builtin = "pick/1", .expr = "pick(.[2])", .input = "[1,2,3,4]", .expected = "[null,null,3]" },
+    .{ .builtin = "tostream/0", .expr = "[tostream]", .input = "{\"a\":[1,{\"b\":2}]}", .expected = "[[[\"a\",0],1],[[\"a\",1,\"b\"],2],[[\"a\",1,\"b\"]],[[\"a\",1]]]" },
+    .{ .builtin = "fromstream/1", .expr = "fromstream(tostream)", .input = "{\"a\":[1,{\"b\":2}]}", .expected = "{\"a\":[1,{\"b\":2}]}" },
+    .{ .builtin = "truncate_stream/1", .expr = "[1|truncate_stream([[0],1],[[1,0],2],[[1,0]],[[1]])]", .expected = "[[[0],2],[[0]]]" },
+    // GENERATORS
+    .{ .builtin = "range/1", .expr = "[range(3)]", .expected = "[0,1,2]" },
+    .{ .builtin = "range/2", .expr = "[range(2; 4)]", .expected = "[2,3]" },
+    .{ .builtin = "range/3", .expr = "[range(0; 10; 3)]", .expected = "[0,3,6,9]" },
+    .{ .builtin = "limit/2", .expr = "[limit(3; .[])]", .input = "[0,1,2,3,4]", .expected = "[0,1,2]" },
+    .{ .builtin = "first/1", .expr = "first(range(10; 20))", .expected = "10" },
+    .{ .builtin = "last/1", .expr = "last(range(10; 20))", .expected = "19" },
+    .{ .builtin = "nth/2", .expr = "nth(5; range(10; 20))", .expected = "15" },
+    .{ .builtin = "until/2", .expr = "[., 1] | until(.[0] < 1; [.[0] - 1, .[1] * .[0]]) | .[1]", .input = "4", .expected = "24" },
+    .{ .builtin = "while/2", .expr = "[while(. < 100; . * 2)]", .input = "1", .expected = "[1,2,4,8,16,32,64]" },
+    .{ .builtin = "repeat/1", .expr = "[limit(4; repeat(. * 2))]", .input = "1", .expected = "[1,2,4,8]" },
+    .{ .builtin = "isempty/1", .expr = "[isempty(empty), isempty(.[])]", .input = "[1]", .expected = "[true,false]" },
+    .{ .builtin = "isvalid/1", .expr = "[isvalid(.a), isvalid(error(\"x\"))]", .input = "{}", .expected = "[true,false]" },
+    // Without an input source there is nothing more to read
+    .{ .builtin = "input/0", .expr = "try input catch .", .expected = "\"No more inputs\"" },
+    .{ .builtin = "inputs/0", .expr = "[inputs]", .expected = "[]" },
+    // STRINGS
+    .{ .builtin = "startswith/1", .expr = "[.[] | startswith(\"foo\")]", .input = "[\"fo\",\"foo\",\"barfoo\",\"foobar\"]", .expected = "[false,true,false,true]" },
+    .{ .builtin = "endswith/1", .expr = "[.[] | endswith(\"foo\")]", .input = "[\"foobar\",\"barfoo\"]", .expected = "[false,true]" },
+    .{ .builtin = "ltrimstr/1", .expr = "[.[] | ltrimstr(\"foo\")]", .input = "[\"fo\",\"foo\",\"barfoo\",\"foobar\",\"afoo\"]", .expected = "[\"fo\",\"\",\"barfoo\",\"bar\",\"afoo\"]" },
+    .{ .builtin = "rtrimstr/1", .expr = "[.[] | rtrimstr(\"foo\")]", .input = "[\"fo\",\"foo\",\"barfoo\",\"foobar\",\"foob\"]", .expected = "[\"fo\",\"\",\"bar\",\"foobar\",\"foob\"]" },
+    .{ .builtin = "trim/0", .expr = "trim", .input = "\"  abc  \"", .expected = "\"abc\"" },
+    .{ .builtin = "ltrim/0", .expr = "ltrim", .input = "\"  abc  \"", .expected = "\"abc  \"" },
+    .{ .builtin = "rtrim/0", .expr = "rtrim", .input = "\"  abc  \"", .expected = "\"  abc\"" },
+    .{ .builtin = "split/1", .expr = "split(\", \")", .input = "\"a, b,c,d, e\"", .expected = "[\"a\",\"b,c,d\",\"e\"]" },
+    .{ .builtin = "join/1", .expr = "join(\", \")", .input = "[\"a\",\"b,c,d\",\"e\"]", .expected = "\"a, b,c,d, e\"" },
+    .{ .builtin = "join/1", .expr = "join(\" \")", .input = "[\"a\",1,2.3,true,null,false]", .expected = "\"a 1 2.3 true  false\"" },
+    .{ .builtin = "ascii_downcase/0", .expr = "ascii_downcase", .input = "\"ABC\"", .expected = "\"abc\"" },
+    .{ .builtin = "ascii_upcase/0", .expr = "ascii_upcase", .input = "\"abc\"", .expected = "\"ABC\"" },
+    .{ .builtin = "ascii/0", .expr = "[.[] | ascii]", .input = "[65,97]", .expected = "[\"A\",\"a\"]" },
+    .{ .builtin = "implode/0", .expr = "implode", .input = "[65,66,67]", .expected = "\"ABC\"" },
+    .{ .builtin = "explode/0", .expr = "explode", .input = "\"foobar\"", .expected = "[102,111,111,98,97,114]" },
+    .{ .builtin = "chars/0", .expr = "chars", .input = "\"abc\"", .expected = "[\"a\",\"b\",\"c\"]" },
+    .{ .builtin = "lines/0", .expr = "lines", .input = "\"a\\nb\"", .expected = "[\"a\",\"b\"]" },
+    .{ .builtin = "words/0", .expr = "words", .input = "\" a b \"", .expected = "[\"a\",\"b\"]" },
+    .{ .builtin = "slugify/0", .expr = "slugify", .input = "\"Hello World!\"", .expected = "\"hello-world\"" },
+    .{ .builtin = "capitalize/0", .expr = "capitalize", .input = "\"hello world\"", .expected = "\"Hello world\"" },
+    .{ .builtin = "titlecase/0", .expr = "titlecase", .input = "\"hello wORLD\"", .expected = "\"Hello World\"" },
+    .{ .builtin = "camelcase/0", .expr = "camelcase", .input = "\"hello_world\"", .expected = "\"helloWorld\"" },
+    .{ .builtin = "pascalcase/0", .expr = "pascalcase", .input = "\"hello_world\"", .expected = "\"HelloWorld\"" },
+    .{ .builtin = "snakecase/0", .expr = "snakecase", .input = "\"HelloWorld\"", .expected = "\"hello_world\"" },
+    .{ .builtin = "kebabcase/0", .expr = "kebabcase", .input = "\"HelloWorld\"", .expected = "\"hello-world\"" },
+    .{ .builtin = "screamcase/0", .expr = "screamcase", .input = "\"helloWorld\"", .expected = "\"HELLO_WORLD\"" },
+    // REGEX
+    .{ .builtin = "test/1", .expr = "[.[] | test(\"a.c\")]", .input = "[\"xabcd\",\"ABC\"]", .expected = "[true,false]" },
+    .{ .builtin = "test/2", .expr = "[.[] | test(\"abc\"; \"i\")]", .input = "[\"xabcd\",\"ABC\"]", .expected = "[true,true]" },
+    .{ .builtin = "match/1", .expr = "match(\"foo\")", .input = "\"foo bar foo\"", .expected = "{\"offset\":0,\"length\":3,\"string\":\"foo\",\"captures\":[]}" },
+    .{ .builtin = "match/2", .expr = "[match(\"(abc)+\"; \"g\") | .offset]", .input = "\"abc abc\"", .expected = "[0,4]" },
+    .{ .builtin = "capture/1", .expr = "capture(\"(?<a>[a-z]+)-(?<n>[0-9]+)\")", .input = "\"xyzzy-14\"", .expected = "{\"a\":\"xyzzy\",\"n\":\"14\"}" },
+    .{ .builtin = "capture/2", .expr = "capture(\"(?<word>[a-z]+)\"; \"i\")", .input = "\"ABC def\"", .expected = "{\"word\":\"ABC\"}" },
+    .{ .builtin = "scan/1", .expr = "[scan(\"c\")]", .input = "\"abcdefabc\"", .expected = "[\"c\",\"c\"]" },
+    .{ .builtin = "scan/2", .expr = "[scan(\"C\"; \"i\")]", .input = "\"abcdefabc\"", .expected = "[\"c\",\"c\"]" },
+    .{ .builtin = "split/2", .expr = "split(\", *\"; null)", .input = "\"ab,cd, ef\"", .expected = "[\"ab\",\"cd\",\"ef\"]" },
+    .{ .builtin = "splits/1", .expr = "[splits(\", *\")]", .input = "\"ab,cd, ef\"", .expected = "[\"ab\",\"cd\",\"ef\"]" },
+    .{ .builtin = "splits/2", .expr = "[splits(\"A\"; \"i\")]", .input = "\"bab\"", .expected = "[\"b\",\"b\"]" },
+    .{ .builtin = "sub/2", .expr = "sub(\"a\"; \"b\")", .input = "\"aaa\"", .expected = "\"baa\"" },
+    .{ .builtin = "sub/3", .expr = "sub(\"A\"; \"b\"; \"gi\")", .input = "\"aAa\"", .expected = "\"bbb\"" },
+    .{ .builtin = "gsub/2", .expr = "gsub(\"o\"; \"0\")", .input = "\"foo boo\"", .expected = "\"f00 b00\"" },
+    .{ .builtin = "gsub/3", .expr = "gsub(\"O\"; \"0\"; \"i\")", .input = "\"foo boo\"", .expected = "\"f00 b00\"" },
+    // FORMATS
+    .{ .builtin = "@text", .expr = "@text", .input = "[1,2]", .expected = "\"[1,2]\"" },
+    .{ .builtin = "@json", .expr = "@json", .input = "[1,\"a\"]", .expected = "\"[1,\\\"a\\\"]\"" },
+    .{ .builtin = "@csv", .expr = "@csv", .input = "[1,\"a,b\",null]", .expected = "\"1,\\\"a,b\\\",\"" },
+    .{ .builtin = "@tsv", .expr = "@tsv", .input = "[1,\"a\\tb\"]", .expected = "\"1\\ta\\\\tb\"" },
+    .{ .builtin = "@html", .expr = "@html", .input = "\"<b>&</b>\"", .expected = "\"&lt;b&gt;&amp;&lt;/b&gt;\"" },
+    .{ .builtin = "@uri", .expr = "@uri", .input = "\"a b&c\"", .expected = "\"a%20b%26c\"" },
+    .{ .builtin = "@sh", .expr = "@sh", .input = "[\"it's\",1]", .expected = "\"'it'\\\\''s' 1\"" },
+    .{ .builtin = "@base64", .expr = "@base64", .input = "\"hello\"", .expected = "\"aGVsbG8=\"" },
+    .{ .builtin = "@base64d", .expr = "@base64d", .input = "\"aGVsbG8=\"", .expected = "\"hello\"" },
+    .{ .builtin = "@base32", .expr = "@base32", .input = "\"hello\"", .expected = "\"NBSWY3DP\"" },
+    .{ .builtin = "@base32d", .expr = "@base32d", .input = "\"NBSWY3DP\"", .expected = "\"hello\"" },
+    // MATH: results that aren't exact are scaled and rounded
+    .{ .builtin = "floor/0", .expr = "floor", .input = "3.7", .expected = "3" },
+    .{ .builtin = "ceil/0", .expr = "ceil", .input = "3.2", .expected = "4" },
+    .{ .builtin = "round/0", .expr = "map(round)", .input = "[2.5,-2.5]", .expected = "[3,-3]" },
+    .{ .builtin = "rint/0", .expr = "[.[] | rint]", .input = "[2.5,3.5]", .expected = "[2,4]" },
+    .{ .builtin = "nearbyint/0", .expr = "[.[] | nearbyint]", .input = "[2.5,3.5]", .expected = "[2,4]" },
+    .{ .builtin = "trunc/0", .expr = "trunc", .input = "-3.7", .expected = "-3" },
+    .{ .builtin = "fabs/0", .expr = "fabs", .input = "-5.5", .expected = "5.5" },
+    .{ .builtin = "abs/0", .expr = "abs", .input = "-5", .expected = "5" },
+    .{ .builtin = "sqrt/0", .expr = "sqrt", .input = "16", .expected = "4" },
+    .{ .builtin = "cbrt/0", .expr = "cbrt | round", .input = "27", .expected = "3" },
+    .{ .builtin = "pow/2", .expr = "pow(2; 10)", .expected = "1024" },
+    .{ .builtin = "exp/0", .expr = "exp * 1000 | round", .input = "1", .expected = "2718" },
+    .{ .builtin = "exp2/0", .expr = "exp2 | round", .input = "10", .expected = "1024" },
+    .{ .builtin = "exp10/0", .expr = "exp10 | round", .input = "2", .expected = "100" },
+    .{ .builtin = "pow10/0", .expr = "pow10 | round", .input = "2", .expected = "100" },
+    .{ .builtin = "expm1/0", .expr = "expm1 * 1000 | round", .input = "1", .expected = "1718" },
+    .{ .builtin = "log/0", .expr = "log * 1000 | round", .input = "10", .expected = "2303" },
+    .{ .builtin = "log2/0", .expr = "log2 | round", .input = "8", .expected = "3" },
+    .{ .builtin = "log10/0", .expr = "log10 | round", .input = "1000", .expected = "3" },
+    .{ .builtin = "log1p/0", .expr = "log1p * 1000 | round", .input = "1", .expected = "693" },
+    .{ .builtin = "logb/0", .expr = "logb", .input = "8", .expected = "3" },
+    .{ .builtin = "significand/0", .expr = "significand", .input = "12", .expected = "1.5" },
+    .{ .builtin = "frexp/0", .expr = "frexp", .input = "8.5", .expected = "[0.53125,4]" },
+    .{ .builtin = "modf/0", .expr = "modf", .input = "8.5", .expected = "[0.5,8]" },
+    .{ .builtin = "ldexp/2", .expr = "ldexp(3; 2)", .expected = "12" },
+    .{ .builtin = "scalb/2", .expr = "scalb(3; 2)", .expected = "12" },
+    .{ .builtin = "scalbln/2", .expr = "scalbln(3; 2)", .expected = "12" },
+    .{ .builtin = "gamma/0", .expr = "gamma * 1000 | round", .input = "5", .expected = "3178" },
+    .{ .builtin = "lgamma/0", .expr = "lgamma * 1000 | round", .input = "5", .expected = "3178" },
+    .{ .builtin = "tgamma/0", .expr = "tgamma | round", .input = "5", .expected = "24" },
+    .{ .builtin = "lgamma_r/0", .expr = "lgamma_r | [(.[0] * 1000 | round), .[1]]", .input = "5", .expected = "[3178,1]" },
+    .{ .builtin = "sin/0", .expr = "sin * 1000 | round", .input = "1", .expected = "841" },
+    .{ .builtin = "cos/0", .expr = "cos * 1000 | round", .input = "1", .expected = "540" },
+    .{ .builtin = "tan/0", .expr = "tan * 1000 | round", .input = "1", .expected = "1557" },
+    .{ .builtin = "asin/0", .expr = "asin * 1000 | round", .input = "1", .expected = "1571" },
+    .{ .builtin = "acos/0", .expr = "acos * 1000 | round", .input = "0", .expected = "1571" },
+    .{ .builtin = "atan/0", .expr = "atan * 1000 | round", .input = "1", .expected = "785" },
+    .{ .builtin = "atan2/2", .expr = "atan2(1; 1) * 1000 | round", .expected = "785" },
+    .{ .builtin = "sinh/0", .expr = "sinh * 1000 | round", .input = "1", .expected = "1175" },
+    .{ .builtin = "cosh/0", .expr = "cosh * 1000 | round", .input = "1", .expected = "1543" },
+    .{ .builtin = "tanh/0", .expr = "tanh * 1000 | round", .input = "1", .expected = "762" },
+    .{ .builtin = "asinh/0", .expr = "asinh * 1000 | round", .input = "1", .expected = "881" },
+    .{ .builtin = "acosh/0", .expr = "acosh * 1000 | round", .input = "2", .expected = "1317" },
+    .{ .builtin = "atanh/0", .expr = "atanh * 1000 | round", .input = "0.5", .expected = "549" },
+    .{ .builtin = "fmin/2", .expr = "fmin(1; 2)", .expected = "1" },
+    .{ .builtin = "fmax/2", .expr = "fmax(1; 2)", .expected = "2" },
+    .{ .builtin = "fmod/2", .expr = "[fmod(7; 3), fmod(-7; 3)]", .expected = "[1,-1]" },
+    .{ .builtin = "drem/2", .expr = "drem(7; 2)", .expected = "-1" },
+    .{ .builtin = "fdim/2", .expr = "[fdim(5; 3), fdim(3; 5)]", .expected = "[2,0]" },
+    .{ .builtin = "copysign/2", .expr = "copysign(3; -1)", .expected = "-3" },
+    .{ .builtin = "nextafter/2", .expr = "all(nextafter(1; 2); . > 1)", .expected = "true" },
+    .{ .builtin = "nexttoward/2", .expr = "all(nexttoward(1; 0); . < 1)", .expected = "true" },
+    .{ .builtin = "fma/3", .expr = "fma(2; 3; 4)", .expected = "10" },
+    .{ .builtin = "infinite/0", .expr = "infinite | isinfinite", .expected = "true" },
+    .{ .builtin = "nan/0", .expr = "nan | isnan", .expected = "true" },
+    .{ .builtin = "isinfinite/0", .expr = "[infinite, 1] | map(isinfinite)", .expected = "[true,false]" },
+    .{ .builtin = "isnan/0", .expr = "[nan, 1] | map(isnan)", .expected = "[true,false]" },
+    .{ .builtin = "isnormal/0", .expr = "map(isnormal)", .input = "[1,0]", .expected = "[true,false]" },
+    .{ .builtin = "finites/0", .expr = "[1, infinite, nan] | map(finites)", .expected = "[1]" },
+    .{ .builtin = "normals/0", .expr = "[1, 0, nan] | map(normals)", .expected = "[1]" },
+    .{ .builtin = "ln/0", .expr = "map(ln)", .input = "[1,0]", .expected = "[0,null]" },
+    .{ .builtin = "incr/0", .expr = "incr", .input = "1", .expected = "2" },
+    .{ .builtin = "decr/0", .expr = "decr", .input = "1", .expected = "0" },
+    .{ .builtin = "negate/0", .expr = "negate", .input = "5", .expected = "-5" },
+    .{ .builtin = "toggle/0", .expr = "toggle", .input = "true", .expected = "false" },
+    // DATES
+    .{ .builtin = "now/0", .expr = "now | test(\"^[0-9]{4}-[0-9]{2}-[0-9]{2}T[0-9]{2}:[0-9]{2}:[0-9]{2}Z$\")", .expected = "true" },
+    .{ .builtin = "mktime/0", .expr = "mktime", .input = "[41,53,23,5,2,2015,4,63]", .expected = "1425599621" },
+    .{ .builtin = "gmtime/0", .expr = "gmtime", .input = "1425599621", .expected = "[41,53,23,5,2,2015,4,63]" },
+    .{ .builtin = "localtime/0", .expr = "localtime", .input = "1425599621", .expected = "[41,53,23,5,2,2015,4,63]" },
+    .{ .builtin = "strptime/1", .expr = "strptime(\"%Y-%m-%dT%H:%M:%SZ\")", .input = "\"2015-03-05T23:51:47Z\"", .expected = "[47,51,23,5,2,2015,4,63]" },
+    .{ .builtin = "strftime/1", .expr = "strftime(\"%Y-%m-%dT%H:%M:%SZ\")", .input = "1425599621", .expected = "\"2015-03-05T23:53:41Z\"" },
+    .{ .builtin = "strftime/2", .expr = "strftime(\"%H:%M\"; \"+05:30\")", .input = "1425599621", .expected = "\"05:23\"" },
+    .{ .builtin = "strflocaltime/1", .expr = "strflocaltime(\"%H:%M\")", .input = "1425599621", .expected = "\"23:53\"" },
+    .{ .builtin = "todate/0", .expr = "todate", .input = "1425599621", .expected = "\"2015-03-05T23:53:41Z\"" },
+    .{ .builtin = "todateiso8601/0", .expr = "todateiso8601", .input = "1425599621", .expected = "\"2015-03-05T23:53:41Z\"" },
+    .{ .builtin = "date/0", .expr = "date", .input = "1425599621", .expected = "\"2015-03-05T23:53:41Z\"" },
+    .{ .builtin = "fromdate/0", .expr = "fromdate", .input = "\"2015-03-05T23:51:47Z\"", .expected = "1425599507" },
+    .{ .builtin = "fromdateiso8601/0", .expr = "fromdateiso8601", .input = "\"2015-03-05T23:51:47Z\"", .expected = "1425599507" },
+    .{ .builtin = "dateadd/2", .expr = "dateadd(\"day\"; 1)", .input = "\"2015-03-05T23:51:47Z\"", .expected = "\"2015-03-06T23:51:47Z\"" },
+    .{ .builtin = "datesub/2", .expr = "datesub(\"day\"; 5)", .input = "\"2015-03-05T23:51:47Z\"", .expected = "\"2015-02-28T23:51:47Z\"" },
+    .{ .builtin = "date_trunc/1", .expr = "date_trunc(\"day\")", .input = "\"2015-03-05T23:51:47Z\"", .expected = "\"2015-03-05T00:00:00Z\"" },
+    .{ .builtin = "today/0", .expr = "today | test(\"^[0-9]{4}-[0-9]{2}-[0-9]{2}$\")", .expected = "true" },
+    .{ .builtin = "time/0", .expr = "time | test(\"^[0-9]{2}:[0-9]{2}:[0-9]{2}$\")", .expected = "true" },
+    .{ .builtin = "epoch/0", .expr = "all(epoch; . > 1600000000)", .expected = "true" },
+    .{ .builtin = "epoch_ms/0", .expr = "all(epoch_ms; . > 1600000000000)", .expected = "true" },
+    .{ .builtin = "year/0", .expr = "all(year; . >= 2020)", .expected = "true" },
+    .{ .builtin = "month/0", .expr = "all(month; . >= 1 and . <= 12)", .expected = "true" },
+    .{ .builtin = "day/0", .expr = "all(day; . >= 1 and . <= 31)", .expected = "true" },
+    .{ .builtin = "hour/0", .expr = "all(hour; . >= 0 and . <= 23)", .expected = "true" },
+    .{ .builtin = "minute/0", .expr = "all(minute; . >= 0 and . <= 59)", .expected = "true" },
+    .{ .builtin = "second/0", .expr = "all(second; . >= 0 and . <= 59)", .expected = "true" },
+    .{ .builtin = "week/0", .expr = "all(week; . >= 1 and . <= 53)", .expected = "true" },
+    .{ .builtin = "weekday/0", .expr = "weekday | endswith(\"day\")", .expected = "true" },
+    .{ .builtin = "weekday_num/0", .expr = "all(weekday_num; . >= 0 and . <= 6)", .expected = "true" },
+    .{ .builtin = "delta/0", .expr = "all(delta; . > 1600000000)", .input = "0", .expected = "true" },
+    .{ .builtin = "ago/0", .expr = "epoch - 7200 | ago", .expected = "\"2 hours ago\"" },
+    // IDS AND RANDOM
+    .{ .builtin = "uuid/0", .expr = "uuid | test(\"^[0-9a-f]{8}-[0-9a-f]{4}-4[0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{12}$\")", .expected = "true" },
+    .{ .builtin = "uuid7/0", .expr = "uuid7 | test(\"^[0-9a-f]{8}-[0-9a-f]{4}-7[0-9a-f]{3}-[89ab]\")", .expected = "true" },
+    .{ .builtin = "ulid/0", .expr = "ulid | length", .expected = "26" },
+    .{ .builtin = "xid/0", .expr = "xid | length", .expected = "20" },
+    .{ .builtin = "xid_time/0", .expr = "all(xid | xid_time; . > 1600000000)", .expected = "true" },
+    .{ .builtin = "nanoid/0", .expr = "nanoid | length", .expected = "21" },
+    .{ .builtin = "shortid/0", .expr = "shortid | length", .expected = "8" },
+    .{ .builtin = "sid/0", .expr = "sid | length", .expected = "6" },
+    .{ .builtin = "random/0", .expr = "all(random; . >= 0 and . <= 1)", .expected = "true" },
+    .{ .builtin = "seq/0", .expr = "[seq, seq] | .[1] - .[0]", .expected = "1" },
+    // ENVIRONMENT AND I/O
+    .{ .builtin = "env/0", .expr = "env | type", .expected = "\"object\"" },
+    .{ .builtin = "$ENV", .expr = "$ENV | type", .expected = "\"object\"" },
+    .{ .builtin = "builtins/0", .expr = "builtins | map(select(. == \"IN/2\"))", .expected = "[\"IN/2\"]" },
+    .{ .builtin = "input_filename/0", .expr = "input_filename", .expected = "null" },
+    .{ .builtin = "have_literal_numbers/0", .expr = "have_literal_numbers", .expected = "true" },
+    .{ .builtin = "have_decnum/0", .expr = "have_decnum", .expected = "false" },
+    .{ .builtin = "debug/0", .expr = "debug", .input = "1", .expected = "1" },
+    .{ .builtin = "debug/1", .expr = "debug(\"msg\")", .input = "1", .expected = "1" },
+    .{ .builtin = "stderr/0", .expr = "stderr", .input = "1", .expected = "1" },
+};
+
+test "eval jq builtin library (manual examples)" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    for (manual_examples) |case| {
+        errdefer std.debug.print("manual example for {s} failed: {s}\n", .{ case.builtin, case.expr });
+        // A fresh context each time, since $__loc__ counts lines in the first expression parsed
+        var err_ctx: ErrorContext = .{};
+        const input = try number.parseJson(arena.allocator(), case.input, .{});
+        const expr = try parseExprWithContext(arena.allocator(), case.expr, &err_ctx);
+        if (case.halts) {
+            try std.testing.expectError(error.Halt, evalExpr(arena.allocator(), &expr, input));
+            continue;
+        }
+        const result = try evalExpr(arena.allocator(), &expr, input);
+        try std.testing.expect(result.values.len > 0);
+        try std.testing.expectEqualStrings(case.expected, try toJsonText(arena.allocator(), result.values[0]));
+    }
+}
+
+test "every supported builtin has a manual example" {
+    var missing: usize = 0;
+    for (builtins.categories) |category| {
+        for (category.builtins) |builtin| {
+            if (builtin.status == .unsupported) continue;
+            var buf: [64]u8 = undefined;
+            const name = builtins.signature(&buf, builtin);
+            for (manual_examples) |case| {
+                if (std.mem.eql(u8, case.builtin, name)) break;
+            } else {
+                std.debug.print("no manual example for {s}\n", .{name});
+                missing += 1;
+            }
+        }
+    }
+    try std.testing.expectEqual(@as(usize, 0), missing);
+
+    // And every example is keyed by a builtin that runs
+    for (manual_examples) |case| {
+        const known = known: for (builtins.categories) |category| {
+            for (category.builtins) |builtin| {
+                var buf: [64]u8 = undefined;
+                if (builtin.status != .unsupported and std.mem.eql(u8, builtins.signature(&buf, builtin), case.builtin)) break :known true;
+            }
+        } else false;
+        if (!known) std.debug.print("manual example for unknown builtin {s}\n", .{case.builtin});
+        try std.testing.expect(known);
+    }
+}
 
+test "eval halt_error stops with status 5 and the input as message" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
     var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), "test(\"^world\")", &err_ctx);
-    const result = try evalExpr(arena.allocator(), &expr, parsed.value);
 
-    try std.testing.expectEqual(@as(usize, 1), result.values.len);
-    try std.testing.expect(!result.values[0].bool);
+    const expr = try parseExprWithContext(arena.allocator(), "halt_error", &err_ctx);
+    try std.testing.expectError(error.Halt, evalExpr(arena.allocator(), &expr, .{ .string = "bye\n" }));
+    try std.testing.expectEqual(@as(i64, 5), haltStatus());
+    try std.testing.expectEqualStrings("bye\n", haltMessage().?.string);
 }
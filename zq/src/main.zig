@@ -4,9 +4,15 @@ const types = @import("types.zig");
 const output = @import("output.zig");
 const parser = @import("parser.zig");
 const eval = @import("eval.zig");
+const plan = @import("plan.zig");
+const stream = @import("stream.zig");
+const number = @import("number.zig");
+const builtins = @import("builtins.zig");
+const jn_core = @import("jn-core");
 
 // Re-export types for internal use
 const Config = types.Config;
+const Expr = types.Expr;
 const ParseError = types.ParseError;
 const ErrorContext = types.ErrorContext;
 const EvalError = types.EvalError;
@@ -53,6 +59,404 @@ fn readLine(reader: anytype) ?[]u8 {
     }
 }
 
+/// Feeds input/inputs with the records after the current one.
+const StdinInputs = struct {
+    reader: *std.Io.Reader,
+    line_no: *usize,
+    skip_invalid: bool,
+    /// --errors / --max-errors: where malformed lines go
+    errors: *jn_core.ErrorSink,
+    /// -R: every line (even an empty one) is a string record
+    raw: bool = false,
+    /// --seq: records may start with the RFC 7464 record separator
+    seq: bool = false,
+
+    fn next(context: *anyopaque, allocator: std.mem.Allocator) ?std.json.Value {
+        const self: *StdinInputs = @ptrCast(@alignCast(context));
+        while (readLine(self.reader)) |raw_line| {
+            self.line_no.* += 1;
+            const line = recordText(raw_line, self.seq);
+            if (line.len == 0 and !self.raw) continue;
+
+            // The record outlives the reader's buffer
+            const line_copy = allocator.dupe(u8, line) catch return null;
+            if (self.raw) return .{ .string = line_copy };
+            const parsed = number.parseJson(allocator, line_copy, .{}) catch {
+                if (!self.skip_invalid and !self.errors.enabled()) {
+                    std.debug.print("Error: malformed JSON\n", .{});
+                    std.process.exit(1);
+                }
+                if (self.errors.counting()) self.errors.report("malformed JSON", self.line_no.*, line_copy);
+                continue;
+            };
+            return parsed;
+        }
+        return null;
+    }
+};
+
+/// Feeds input/inputs (and the main loop) with --stream events.
+const EventInputs = struct {
+    events: *stream.EventReader,
+    line_no: *usize,
+    /// Flushed before a malformed input ends the run
+    writer: *std.Io.Writer,
+
+    fn next(context: *anyopaque, allocator: std.mem.Allocator) ?std.json.Value {
+        const self: *EventInputs = @ptrCast(@alignCast(context));
+        const event = self.events.next(allocator) catch |err| {
+            self.writer.flush() catch {};
+            const reason = switch (err) {
+                error.MalformedJson => "malformed JSON",
+                error.ReadFailed => "read failed",
+                error.OutOfMemory => "out of memory",
+            };
+            std.debug.print("zq: error (at <stdin>:{d}): {s}\n", .{ self.events.line, reason });
+            std.process.exit(2);
+        };
+        self.line_no.* = self.events.line;
+        return event;
+    }
+};
+
+/// With --seq, input records may be prefixed by RS (0x1e).
+fn recordText(line: []const u8, seq: bool) []const u8 {
+    return if (seq) std.mem.trimLeft(u8, line, "\x1e") else line;
+}
+
+/// Writes outputs in the configured layout and remembers enough for -e.
+const Printer = struct {
+    writer: *std.Io.Writer,
+    config: Config,
+    count: usize = 0,
+    last_truthy: bool = false,
+
+    fn print(self: *Printer, allocator: std.mem.Allocator, value: std.json.Value) !void {
+        if (self.config.seq) try self.writer.writeByte(0x1e);
+        try writeJson(allocator, self.writer, value, self.config);
+        if (!self.config.join_output) try self.writer.writeByte('\n');
+        if (self.config.unbuffered) try self.writer.flush();
+        self.count += 1;
+        self.last_truthy = switch (value) {
+            .null => false,
+            .bool => |b| b,
+            else => true,
+        };
+    }
+
+    /// jq -e: 0 if the last output was truthy, 1 if it was false or null,
+    /// 4 if there was no output at all.
+    fn exitStatus(self: *const Printer) u8 {
+        if (self.count == 0) return 4;
+        return if (self.last_truthy) 0 else 1;
+    }
+};
+
+/// Evaluate one input and print its outputs. On an uncaught error the
+/// outputs before it are printed, then the message; returns false.
+fn runRecord(allocator: std.mem.Allocator, printer: *Printer, expr: *const Expr, value: std.json.Value, line_no: usize) !bool {
+    const results = evalExpr(allocator, expr, value) catch |err| {
+        // Outputs before the error still count, as in jq
+        for (eval.partialOutputs()) |result| try printer.print(allocator, result);
+        try printer.writer.flush();
+        reportEvalError(allocator, err, line_no);
+        return false;
+    };
+    for (results.values) |result| try printer.print(allocator, result);
+    return true;
+}
+
+/// runRecord for a line of input. With --errors, a record whose evaluation
+/// fails goes to the sidecar instead of stderr; either way it counts
+/// against --max-errors.
+fn runLine(allocator: std.mem.Allocator, printer: *Printer, expr: *const Expr, value: std.json.Value, line_no: usize, raw: []const u8, errors: *jn_core.ErrorSink) !bool {
+    const results = evalExpr(allocator, expr, value) catch |err| {
+        for (eval.partialOutputs()) |result| try printer.print(allocator, result);
+        if (err == error.Halt or !errors.enabled()) {
+            try printer.writer.flush();
+            reportEvalError(allocator, err, line_no);
+        }
+        if (errors.counting()) errors.report(evalErrorText(allocator, err), line_no, raw);
+        return false;
+    };
+    for (results.values) |result| try printer.print(allocator, result);
+    return true;
+}
+
+/// Report an evaluation error for the record ending at `line`, jq style:
+/// zq: error (at <stdin>:3): Cannot parse 'abc' as JSON
+/// halt and halt_error exit here with their own status.
+fn reportEvalError(allocator: std.mem.Allocator, err: EvalError, line: usize) void {
+    var stderr_buffer: [1024]u8 = undefined;
+    var stderr_writer = std.fs.File.stderr().writerStreaming(&stderr_buffer);
+    writeEvalError(&stderr_writer.interface, allocator, err, line) catch {};
+    stderr_writer.interface.flush() catch {};
+    if (err == error.Halt) std.process.exit(haltExitCode());
+}
+
+/// The message reportEvalError prints.
+fn writeEvalError(writer: *std.Io.Writer, allocator: std.mem.Allocator, err: EvalError, line: usize) std.Io.Writer.Error!void {
+    switch (err) {
+        error.Raised => {
+            const value = eval.errorValue();
+            if (value == .string) {
+                return writer.print("zq: error (at <stdin>:{d}): {s}\n", .{ line, value.string });
+            }
+            var aw: std.Io.Writer.Allocating = .init(allocator);
+            writeJsonValue(&aw.writer, value) catch {};
+            try writer.print("zq: error (at <stdin>:{d}) (not a string): {s}\n", .{ line, aw.written() });
+        },
+        error.InvalidPath => try writer.print("zq: error (at <stdin>:{d}): Invalid path expression\n", .{line}),
+        error.Break => try writer.print("zq: error (at <stdin>:{d}): break outside of its label\n", .{line}),
+        error.OutOfMemory => try writer.print("zq: error (at <stdin>:{d}): out of memory\n", .{line}),
+        error.Halt => {
+            // halt_error prints strings as-is and anything else as JSON on a line
+            const message = eval.haltMessage() orelse return;
+            if (message == .string) return writer.writeAll(message.string);
+            try writeJsonValue(writer, message);
+            try writer.writeByte('\n');
+        },
+    }
+}
+
+/// The `_error` of a record routed to --errors: the message without the
+/// location, since the record has its own `_line`.
+fn evalErrorText(allocator: std.mem.Allocator, err: EvalError) []const u8 {
+    return switch (err) {
+        error.Raised => blk: {
+            const value = eval.errorValue();
+            if (value == .string) break :blk value.string;
+            var aw: std.Io.Writer.Allocating = .init(allocator);
+            writeJsonValue(&aw.writer, value) catch {};
+            break :blk aw.written();
+        },
+        error.InvalidPath => "Invalid path expression",
+        error.Break => "break outside of its label",
+        error.OutOfMemory => "out of memory",
+        error.Halt => "halt",
+    };
+}
+
+/// The process exit status for halt and halt_error.
+fn haltExitCode() u8 {
+    return @truncate(@as(u64, @bitCast(eval.haltStatus())));
+}
+
+// ============================================================================
+// Parallel evaluation (--jobs)
+// ============================================================================
+
+/// Input handed to one worker, rounded up to the next line break.
+const chunk_bytes = 1 << 20;
+
+/// What every worker shares; read-only once they start.
+const Job = struct {
+    expr: *const Expr,
+    plan: ?*const plan.Plan,
+    config: Config,
+    globals: ?*const eval.Env,
+    /// Workers only ask whether failed records are routed or counted;
+    /// the main thread writes and counts them
+    errors: *const jn_core.ErrorSink,
+};
+
+/// A run of input lines evaluated on its own thread. Outputs and error
+/// messages are held until every earlier chunk has been written.
+const Chunk = struct {
+    /// Input lines, each ending in '\n'
+    input: std.ArrayListUnmanaged(u8) = .empty,
+    /// Number of input lines before this chunk, for error messages
+    line_base: usize = 0,
+    output: std.ArrayListUnmanaged(u8) = .empty,
+    messages: std.ArrayListUnmanaged(u8) = .empty,
+    /// --errors records, written by the main thread in order
+    routed: std.ArrayListUnmanaged(u8) = .empty,
+    /// Where the messages go between the outputs
+    breaks: std.ArrayListUnmanaged(Break) = .empty,
+    /// What the chunk's Printer saw, for -e
+    count: usize = 0,
+    last_truthy: bool = false,
+    /// Exit status when a record ends the run: --strict, halt, malformed JSON
+    stop: ?u8 = null,
+    failure: ?anyerror = null,
+    thread: std.Thread = undefined,
+
+    /// output[..at] comes before messages[..end]; a failed record that
+    /// counts against --max-errors ends routed[..routed]
+    const Break = struct { at: usize, end: usize, routed: usize = 0, counted: bool = false };
+
+    fn deinit(self: *Chunk, gpa: std.mem.Allocator) void {
+        self.input.deinit(gpa);
+        self.output.deinit(gpa);
+        self.messages.deinit(gpa);
+        self.routed.deinit(gpa);
+        self.breaks.deinit(gpa);
+    }
+};
+
+/// Evaluate stdin in chunks on `config.jobs` threads, writing the outputs
+/// in input order.
+fn runParallel(job: *const Job, reader: *std.Io.Reader, writer: *std.Io.Writer, printer: *Printer, errors: *jn_core.ErrorSink) !void {
+    const gpa = std.heap.smp_allocator;
+    const chunks = try gpa.alloc(Chunk, job.config.jobs);
+    defer gpa.free(chunks);
+
+    var started: usize = 0;
+    var written: usize = 0;
+    var line_no: usize = 0;
+    var eof = false;
+    while (!eof or written < started) {
+        if (!eof and started - written < chunks.len) {
+            const chunk = &chunks[started % chunks.len];
+            chunk.* = .{ .line_base = line_no };
+            while (chunk.input.items.len < chunk_bytes) {
+                const line = readLine(reader) orelse {
+                    eof = true;
+                    break;
+                };
+                line_no += 1;
+                try chunk.input.ensureUnusedCapacity(gpa, line.len + 1);
+                chunk.input.appendSliceAssumeCapacity(line);
+                chunk.input.appendAssumeCapacity('\n');
+            }
+            if (chunk.input.items.len == 0) continue;
+            chunk.thread = try std.Thread.spawn(.{}, runChunk, .{ job, chunk });
+            started += 1;
+        } else {
+            const chunk = &chunks[written % chunks.len];
+            chunk.thread.join();
+            defer chunk.deinit(gpa);
+            try writeChunk(chunk, writer, printer, errors);
+            written += 1;
+        }
+    }
+}
+
+fn runChunk(job: *const Job, chunk: *Chunk) void {
+    evalChunk(job, chunk) catch |err| {
+        chunk.failure = err;
+    };
+}
+
+/// The main loop of a sequential run, over one chunk.
+fn evalChunk(job: *const Job, chunk: *Chunk) !void {
+    const gpa = std.heap.smp_allocator;
+    eval.setGlobals(job.globals);
+
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    var out: std.Io.Writer.Allocating = .fromArrayList(gpa, &chunk.output);
+    defer chunk.output = out.toArrayList();
+    var messages: std.Io.Writer.Allocating = .fromArrayList(gpa, &chunk.messages);
+    defer chunk.messages = messages.toArrayList();
+    var routed: std.Io.Writer.Allocating = .fromArrayList(gpa, &chunk.routed);
+    defer chunk.routed = routed.toArrayList();
+
+    var printer: Printer = .{ .writer = &out.writer, .config = job.config };
+    // The main thread flushes
+    printer.config.unbuffered = false;
+    defer {
+        chunk.count = printer.count;
+        chunk.last_truthy = printer.last_truthy;
+    }
+
+    var line_no = chunk.line_base;
+    var lines = std.mem.splitScalar(u8, chunk.input.items[0 .. chunk.input.items.len - 1], '\n');
+    while (lines.next()) |raw_line| {
+        line_no += 1;
+        const line = recordText(raw_line, job.config.seq);
+        if (line.len == 0 and !job.config.raw_input) continue;
+
+        _ = arena.reset(.retain_capacity);
+
+        var run_expr: *const Expr = job.expr;
+        var value: std.json.Value = .{ .string = line };
+        if (!job.config.raw_input) {
+            if (job.plan) |p| switch (p.filter(line)) {
+                .drop => continue,
+                .pass => run_expr = p.on_pass,
+                .unknown => {},
+            };
+            const parsed = number.parseJson(arena.allocator(), line, .{}) catch {
+                if (!job.config.skip_invalid and !job.errors.enabled()) {
+                    try messages.writer.writeAll("Error: malformed JSON\n");
+                    try chunk.breaks.append(gpa, .{ .at = out.written().len, .end = messages.written().len });
+                    chunk.stop = 1;
+                    return;
+                }
+                if (job.errors.counting()) {
+                    if (job.errors.enabled()) try job.errors.format(&routed.writer, "malformed JSON", line_no, line);
+                    try chunk.breaks.append(gpa, .{
+                        .at = out.written().len,
+                        .end = messages.written().len,
+                        .routed = routed.written().len,
+                        .counted = true,
+                    });
+                }
+                continue;
+            };
+            value = parsed;
+        }
+
+        const results = evalExpr(arena.allocator(), run_expr, value) catch |err| {
+            // Outputs before the error still count, as in jq
+            for (eval.partialOutputs()) |result| try printer.print(arena.allocator(), result);
+            const counted = err != error.Halt and job.errors.counting();
+            if (counted and job.errors.enabled()) {
+                try job.errors.format(&routed.writer, evalErrorText(arena.allocator(), err), line_no, line);
+            } else {
+                try writeEvalError(&messages.writer, arena.allocator(), err, line_no);
+            }
+            try chunk.breaks.append(gpa, .{
+                .at = out.written().len,
+                .end = messages.written().len,
+                .routed = routed.written().len,
+                .counted = counted,
+            });
+            if (err == error.Halt) {
+                chunk.stop = haltExitCode();
+                return;
+            }
+            if (job.config.strict) {
+                chunk.stop = 5;
+                return;
+            }
+            continue;
+        };
+        for (results.values) |result| try printer.print(arena.allocator(), result);
+    }
+}
+
+/// Write a finished chunk, printing each error message (and routing each
+/// failed record) where a sequential run would have.
+fn writeChunk(chunk: *const Chunk, writer: *std.Io.Writer, printer: *Printer, errors: *jn_core.ErrorSink) !void {
+    if (chunk.failure) |err| return err;
+    var at: usize = 0;
+    var end: usize = 0;
+    var routed: usize = 0;
+    for (chunk.breaks.items) |brk| {
+        try writer.writeAll(chunk.output.items[at..brk.at]);
+        try writer.flush();
+        std.debug.print("{s}", .{chunk.messages.items[end..brk.end]});
+        if (brk.counted) {
+            errors.commit(chunk.routed.items[routed..brk.routed]);
+            routed = brk.routed;
+        }
+        at = brk.at;
+        end = brk.end;
+    }
+    try writer.writeAll(chunk.output.items[at..]);
+
+    printer.count += chunk.count;
+    if (chunk.count > 0) printer.last_truthy = chunk.last_truthy;
+    if (chunk.stop) |status| {
+        try writer.flush();
+        std.process.exit(status);
+    }
+    if (printer.config.unbuffered) try writer.flush();
+}
+
 // ============================================================================
 // CLI
 // ============================================================================
@@ -95,13 +499,18 @@ fn printUsage() void {
         \\  {(.key): .value}   Dynamic key from field
         \\
         \\TYPE FUNCTIONS:
-        \\  tonumber           String to number
+        \\  tonumber           String to number (JSON number text round-trips exactly)
         \\  tostring           Any to string
+        \\  todecimal          Exact decimal: + - * without f64 rounding (0.1+0.2 = 0.3)
         \\  type               Returns type name
         \\  length             String/array/object length
         \\  keys               Object keys as array
         \\  values             Object values as array
         \\
+        \\NUMBERS:
+        \\  Integers beyond i64 and decimals an f64 would change (10.50, 0.1000000000000000055)
+        \\  are kept as written. + - * on them are exact; other math uses the nearest f64.
+        \\
         \\TYPE CHECKS:
         \\  isnumber           True if number
         \\  isstring           True if string
@@ -110,10 +519,66 @@ fn printUsage() void {
         \\  isarray            True if array
         \\  isobject           True if object
         \\
+        \\VARIABLES:
+        \\  .x as $v | expr    Bind .x to $v for the rest of the pipe
+        \\  . as [$a, $b] | .. Destructure an array
+        \\  . as {a: $x, $b}   Destructure an object ($b binds .b)
+        \\  $v.field           Access a field of a bound value
+        \\  $__loc__           {file, line} of this reference
+        \\  $name  $ARGS       Values from --arg, --argjson, --args, ...
+        \\
+        \\REDUCTIONS:
+        \\  reduce .[] as $x (0; . + $x)          Fold into a single value
+        \\  foreach .[] as $x (0; . + $x)         Emit every intermediate state
+        \\  foreach .[] as $x (0; . + $x; [$x, .]) Emit EXTRACT for each state
+        \\
+        \\FUNCTIONS:
+        \\  def f: body; expr           Define f for the rest of the expression
+        \\  def f(g; $x): body; expr    g is a filter argument, $x a value
+        \\  include "name"; expr        Load defs from name.jq (see -L)
+        \\
         \\CONTROL FLOW:
-        \\  .x // .y           Alternative (first non-null)
+        \\  .x // .y           Alternative (first non-null, errors count as null)
         \\  if .x then .a else .b end
         \\
+        \\ERRORS AND LABELS:
+        \\  try .x | tonumber catch "bad"   Run the handler with the error message
+        \\  f?                              Drop errors from f (try f)
+        \\  error("msg")  error             Raise an error (with . as the value)
+        \\  label $out | .[] | if . > 2 then break $out else . end
+        \\                                  Stop at break, keeping earlier outputs
+        \\  Uncaught errors go to stderr (or --errors) with the input line; the record is skipped
+        \\
+        \\GENERATORS:
+        \\  range(5)  range(0; 10; 2)       Numbers from 0 (or from) below upto
+        \\  limit(3; f)  first(f)  last(f)  Some of the outputs of f
+        \\  nth(n)  nth(n; f)               .[n] / the n-th output of f
+        \\  until(. > 100; . * 2)           Apply update until cond holds
+        \\  while(. < 100; . * 2)           Emit states while cond holds
+        \\  repeat(f)                       ., f, f|f, ... (end it with limit)
+        \\  ..  recurse  recurse(f; cond)   Every value below the input, depth first
+        \\  input  inputs                   Read the next / every remaining record
+        \\  $ENV  env                       Environment variables as an object
+        \\
+        \\DATES (UTC; epoch seconds, ISO 8601 strings or gmtime arrays):
+        \\  todate  fromdate                Epoch ↔ "2015-03-05T23:51:47Z" (+01:00 ok)
+        \\  gmtime  mktime                  Epoch ↔ [sec,min,hour,mday,mon,year,wday,yday]
+        \\  strptime("%d/%b/%Y %z")         Parse; %z offsets are converted to UTC
+        \\  strftime("%H:%M")               Format in UTC
+        \\  strftime(f; "+05:30")           Format at an offset from UTC
+        \\  date_trunc("hour")              Round down to the start of a unit
+        \\  dateadd("days"; 7)  datesub     Shift; months clamp the day (Jan 31 → Feb 29)
+        \\
+        \\STREAMING (for documents too big to load):
+        \\  zq --stream '.'                  [["a",0],1] ... [["a",0]] events, path then leaf
+        \\  tostream                         The events of . (same order as --stream)
+        \\  fromstream(f)                    Rebuild values from f's events
+        \\  truncate_stream(f)               Drop the first . path elements (1|truncate_stream(...))
+        \\  zq -n --stream 'fromstream(1|truncate_stream(inputs))'
+        \\                                   Elements of a huge top-level array, one at a time
+        \\  zq -n --stream 'fromstream(2|truncate_stream(inputs | select(.[0][0] == "results")))'
+        \\                                   .results[] of a huge object, one at a time
+        \\
         \\ARITHMETIC:
         \\  .x + .y            Addition / string concat
         \\  .x - .y            Subtraction
@@ -149,10 +614,38 @@ fn printUsage() void {
         \\  startswith("s")    Test string prefix
         \\  endswith("s")      Test string suffix
         \\  contains("s")      Test substring
-        \\  test("pattern")    Regex-like match (^start, end$, ^exact$)
         \\  ltrimstr("s")      Remove prefix
         \\  rtrimstr("s")      Remove suffix
         \\
+        \\STRING INTERPOLATION AND FORMATS:
+        \\  "\(.user) did \(.action)"    Interpolate expressions into a string
+        \\  @csv @tsv                    Array to CSV / TSV row (use with -r)
+        \\  @json @text                  Value as JSON / as text
+        \\  @html @uri @sh               Escape for HTML, URLs, POSIX shells
+        \\  @base64 @base64d             Base64 encode / decode
+        \\  @sh "rm \(.file)"            Apply the format to each interpolation
+        \\
+        \\REGEX FUNCTIONS (flags: g global, i ignore case, x extended, n skip empty):
+        \\  test("re"; "i")              True if the string matches
+        \\  match("re")                  {offset,length,string,captures}
+        \\  capture("(?<y>\\d+)")        Object of named captures
+        \\  scan("re")                   Every match (or its capture groups)
+        \\  split("re"; flags)           Split on a regex (array)
+        \\  splits("re")                 Split on a regex (stream)
+        \\  sub("re"; "str")             Replace first match (.name = capture)
+        \\  gsub("re"; "str")            Replace every match
+        \\
+        \\PATHS AND ASSIGNMENT:
+        \\  .items[].price |= . * 2      Update each selected value
+        \\  .a.b = 5                     Set (creates missing objects)
+        \\  .n += 1  (-= *= /= %=)       Arithmetic update
+        \\  .name //= "anon"             Set if null or false
+        \\  path(f)  paths  paths(f)     Paths as arrays, e.g. ["a",0]
+        \\  getpath(p)  setpath(p; v)    Read / write a path
+        \\  delpaths(ps)  del(f)         Delete paths
+        \\  walk(f)                      Apply f bottom-up to every value
+        \\  with_entries(f)              to_entries | map(f) | from_entries
+        \\
         \\OBJECT FUNCTIONS:
         \\  has("key")         Test if object has key
         \\  del(.key)          Delete key from object
@@ -173,13 +666,54 @@ fn printUsage() void {
         \\  seq                Incrementing counter (1, 2, 3...)
         \\
         \\OPTIONS:
-        \\  -c          Compact output (default, NDJSON compatible)
+        \\  -c          Compact output (default when stdout is not a terminal, NDJSON compatible)
         \\  -r          Raw string output (no quotes around strings)
+        \\  -j          Like -r, without a newline after each output
         \\  -s          Slurp mode: read all input into array first
-        \\  -e          Exit with error code if no output produced
+        \\  -n          Run the expression once with null input (use input/inputs)
+        \\  -R          Raw input: each line is a string (with -s, all of stdin)
+        \\  -S          Sort object keys (--sort-keys)
+        \\  -e          Exit status from the last output (see EXIT STATUS)
+        \\  -C          Colorize output (default when stdout is a terminal)
+        \\  -M          Monochrome output
+        \\  --tab       Pretty-print, indenting with tabs
+        \\  --indent N  Pretty-print, indenting N spaces (0-7; 0 is compact)
+        \\  --seq       RFC 7464: write RS before each output, skip it on input
+        \\  --unbuffered  Flush after every output
+        \\  --jobs N    Evaluate records on N threads, output in input order (0: one per CPU);
+        \\              sequential anyway with -s, -n, --stream, input, seq, debug or stderr
+        \\  --stream    Read input as [path, leaf] events (constant memory, see STREAMING)
+        \\  -L DIR      Search DIR for include "name"; modules (repeatable)
+        \\  --strict    Stop at the first uncaught error (exit 5)
+        \\  --errors PATH  Append malformed or failing records to PATH as NDJSON,
+        \\              {"_error", "_line", "_raw", "_source", "_stage"}, instead of stderr
+        \\  --max-errors N  Exit 1 once more than N records were malformed or failed
+        \\  --no-plan   Evaluate every record in full (skip the select fast path)
+        \\  --list-builtins  Print every builtin and how it compares to jq 1.7
         \\  --version   Print version and exit
         \\  --help      Print this help message
         \\
+        \\ARGUMENTS (available as $name and in $ARGS.named):
+        \\  --arg NAME VALUE         $NAME is the string VALUE
+        \\  --argjson NAME JSON      $NAME is the parsed JSON text
+        \\  --slurpfile NAME FILE    $NAME is an array of the JSON texts in FILE
+        \\  --rawfile NAME FILE      $NAME is the contents of FILE as a string
+        \\  --args                   Later arguments are strings in $ARGS.positional
+        \\  --jsonargs               Later arguments are JSON in $ARGS.positional
+        \\
+        \\EXIT STATUS:
+        \\  0  Success (records with uncaught errors are skipped unless --strict)
+        \\  1  With -e: the last output was false or null
+        \\  2  Usage error, or an unreadable --slurpfile/--rawfile
+        \\  3  The expression does not compile
+        \\  4  With -e: there was no output
+        \\  5  Uncaught error with --strict, -s or -n; halt_error (halt_error(N) exits N)
+        \\
+        \\ENVIRONMENT:
+        \\  JQ_COLORS   Colors as null:false:true:numbers:strings:arrays:objects:keys,
+        \\              e.g. JQ_COLORS='0;90:0;39:0;39:0;39:0;32:1;39:1;39:34;1' (the default)
+        \\  NO_COLOR    If set and not empty, no colors on a terminal unless -C
+        \\
         \\EXAMPLES:
         \\  echo '{"name":"Alice","age":30}' | zq '.name'
         \\  cat data.ndjson | zq 'select(.age >= 18)'
@@ -188,6 +722,7 @@ fn printUsage() void {
         \\  echo '{"x":"42"}' | zq '.x | tonumber'
         \\  echo '{"a":1,"b":2}' | zq '{sum: .a + .b}'
         \\  echo '{"x":null,"y":1}' | zq '.x // .y'
+        \\  echo '{"p":2,"items":[{"qty":3}]}' | zq '.p as $p | .items[] | {total: (.qty * $p)}'
         \\  cat data.ndjson | zq -s 'length'           # Count records
         \\  cat data.ndjson | zq -s '.[] | .name'      # Iterate slurped array
         \\
@@ -207,6 +742,12 @@ fn printUsage() void {
         \\  cat data.ndjson | zq '{row: seq, data: .}' # Add row numbers
         \\  echo '{}' | zq 'shortid'                   # Generate 8-char ID
         \\
+        \\  # jq command line options:
+        \\  zq --arg user alice 'select(.name == $user)' < data.ndjson
+        \\  zq -n '[inputs | .size] | add' < data.ndjson
+        \\  zq -R 'split(",")' < data.csv
+        \\  zq --indent 4 -S '.' < data.ndjson
+        \\
     ;
     std.debug.print("{s}", .{usage});
 }
@@ -215,6 +756,88 @@ fn printVersion() void {
     std.debug.print("zq {s}\n", .{version});
 }
 
+/// Report a command line mistake and exit 2, as jq does.
+fn usageError(comptime fmt: []const u8, args: anytype) noreturn {
+    std.debug.print("Error: " ++ fmt ++ "\n", args);
+    std.debug.print("  Run 'zq --help' for usage.\n", .{});
+    std.process.exit(2);
+}
+
+/// The `count` arguments following the option at args[i.*]; advances i.
+fn optionValues(args: []const [:0]u8, i: *usize, comptime count: usize) [count][]const u8 {
+    if (i.* + count >= args.len) {
+        usageError("{s} takes {d} argument(s)", .{ args[i.*], count });
+    }
+    var values: [count][]const u8 = undefined;
+    for (&values) |*v| {
+        i.* += 1;
+        v.* = args[i.*];
+    }
+    return values;
+}
+
+/// Contents of a --slurpfile/--rawfile file; a missing file is a usage error.
+fn readArgFile(allocator: std.mem.Allocator, option: []const u8, path: []const u8) []u8 {
+    const file = std.fs.cwd().openFile(path, .{}) catch |err| {
+        usageError("{s}: cannot open {s}: {s}", .{ option, path, @errorName(err) });
+    };
+    defer file.close();
+    return file.readToEndAlloc(allocator, std.math.maxInt(usize)) catch |err| {
+        usageError("{s}: cannot read {s}: {s}", .{ option, path, @errorName(err) });
+    };
+}
+
+/// The JSON texts of a --slurpfile: a single document, or one per line.
+fn parseJsonTexts(allocator: std.mem.Allocator, text: []const u8) ?std.json.Array {
+    var values = std.json.Array.init(allocator);
+    if (number.parseJson(allocator, text, .{})) |parsed| {
+        values.append(parsed) catch return null;
+        return values;
+    } else |_| {}
+
+    var lines = std.mem.splitScalar(u8, text, '\n');
+    while (lines.next()) |line| {
+        if (std.mem.trim(u8, line, " \t\r").len == 0) continue;
+        const parsed = number.parseJson(allocator, line, .{}) catch return null;
+        values.append(parsed) catch return null;
+    }
+    return values;
+}
+
+/// Apply one single-letter option; false if `c` is not one.
+fn applyShortFlag(config: *Config, c: u8) bool {
+    switch (c) {
+        'c' => config.compact = true,
+        'r' => config.raw_strings = true,
+        'j' => {
+            config.raw_strings = true;
+            config.join_output = true;
+        },
+        'e' => config.exit_status = true,
+        's' => config.slurp = true,
+        'n' => config.null_input = true,
+        'R' => config.raw_input = true,
+        'S' => config.sort_keys = true,
+        'C' => config.color = true,
+        'M' => config.color = false,
+        else => return false,
+    }
+    return true;
+}
+
+const long_flags = [_]struct { name: []const u8, short: u8 }{
+    .{ .name = "--compact-output", .short = 'c' },
+    .{ .name = "--raw-output", .short = 'r' },
+    .{ .name = "--join-output", .short = 'j' },
+    .{ .name = "--exit-status", .short = 'e' },
+    .{ .name = "--slurp", .short = 's' },
+    .{ .name = "--null-input", .short = 'n' },
+    .{ .name = "--raw-input", .short = 'R' },
+    .{ .name = "--sort-keys", .short = 'S' },
+    .{ .name = "--color-output", .short = 'C' },
+    .{ .name = "--monochrome-output", .short = 'M' },
+};
+
 pub fn main() !void {
     const page_alloc = std.heap.page_allocator;
 
@@ -222,11 +845,26 @@ pub fn main() !void {
     defer std.process.argsFree(page_alloc, args);
 
     var config = Config{};
+    // On a terminal, pretty-print in color like jq; pipes keep compact NDJSON
+    if (std.fs.File.stdout().isTty()) {
+        config.compact = false;
+        // https://no-color.org: a non-empty NO_COLOR turns colors off unless -C
+        config.color = if (std.posix.getenv("NO_COLOR")) |no_color| no_color.len == 0 else true;
+    }
     var expr_arg: ?[]const u8 = null;
+    // --errors and --max-errors
+    var errors: jn_core.ErrorSink = .{ .allocator = page_alloc, .stage = "zq" };
+    defer errors.close();
+    var lib_dirs: std.ArrayListUnmanaged([]const u8) = .empty;
+    // $ARGS.named (also bound as $name) and $ARGS.positional
+    var named: std.json.ObjectMap = .init(page_alloc);
+    var positional = std.json.Array.init(page_alloc);
+    // Set by --args / --jsonargs: arguments after the expression are positional
+    var positional_kind: ?enum { string, json } = null;
 
     // Parse arguments
     var i: usize = 1;
-    while (i < args.len) : (i += 1) {
+    next_arg: while (i < args.len) : (i += 1) {
         const arg = args[i];
         if (std.mem.eql(u8, arg, "--help") or std.mem.eql(u8, arg, "-h")) {
             printUsage();
@@ -234,32 +872,139 @@ pub fn main() !void {
         } else if (std.mem.eql(u8, arg, "--version")) {
             printVersion();
             return;
-        } else if (std.mem.eql(u8, arg, "-c")) {
-            config.compact = true;
-        } else if (std.mem.eql(u8, arg, "-r")) {
-            config.raw_strings = true;
-        } else if (std.mem.eql(u8, arg, "-e")) {
-            config.exit_on_empty = true;
-        } else if (std.mem.eql(u8, arg, "-s")) {
-            config.slurp = true;
-        } else if (arg[0] != '-') {
-            expr_arg = arg;
+        } else if (std.mem.eql(u8, arg, "--list-builtins")) {
+            var stdout_buf: [4096]u8 = undefined;
+            var stdout_writer = std.fs.File.stdout().writerStreaming(&stdout_buf);
+            try builtins.writeMatrix(&stdout_writer.interface);
+            try stdout_writer.interface.flush();
+            return;
+        } else if (std.mem.eql(u8, arg, "--strict")) {
+            config.strict = true;
+        } else if (std.mem.eql(u8, arg, "--no-plan")) {
+            config.plan = false;
+        } else if (std.mem.eql(u8, arg, "--tab")) {
+            config.compact = false;
+            config.tab = true;
+        } else if (std.mem.eql(u8, arg, "--indent")) {
+            const text = optionValues(args, &i, 1)[0];
+            const n = std.fmt.parseInt(u8, text, 10) catch usageError("--indent takes a number, got '{s}'", .{text});
+            if (n > 7) usageError("Cannot indent more than 7 characters", .{});
+            config.compact = n == 0;
+            config.tab = false;
+            config.indent = n;
+        } else if (std.mem.eql(u8, arg, "--seq")) {
+            config.seq = true;
+        } else if (std.mem.eql(u8, arg, "--unbuffered")) {
+            config.unbuffered = true;
+        } else if (std.mem.eql(u8, arg, "--jobs")) {
+            const text = optionValues(args, &i, 1)[0];
+            const n = std.fmt.parseInt(usize, text, 10) catch usageError("--jobs takes a number, got '{s}'", .{text});
+            // 0 is one per CPU
+            config.jobs = if (n == 0) std.Thread.getCpuCount() catch 1 else n;
+        } else if (std.mem.eql(u8, arg, "--errors")) {
+            const path = optionValues(args, &i, 1)[0];
+            errors.file = jn_core.sidecar.openAppend(path) catch |err| {
+                usageError("--errors: cannot open '{s}': {s}", .{ path, @errorName(err) });
+            };
+        } else if (std.mem.eql(u8, arg, "--max-errors")) {
+            const text = optionValues(args, &i, 1)[0];
+            errors.max_errors = std.fmt.parseInt(u64, text, 10) catch usageError("--max-errors takes a number, got '{s}'", .{text});
+        } else if (std.mem.eql(u8, arg, "--stream")) {
+            config.stream = true;
+        } else if (std.mem.eql(u8, arg, "--arg")) {
+            const kv = optionValues(args, &i, 2);
+            try named.put(kv[0], .{ .string = kv[1] });
+        } else if (std.mem.eql(u8, arg, "--argjson")) {
+            const kv = optionValues(args, &i, 2);
+            const parsed = number.parseJson(page_alloc, kv[1], .{}) catch {
+                usageError("--argjson {s}: invalid JSON text '{s}'", .{ kv[0], kv[1] });
+            };
+            try named.put(kv[0], parsed);
+        } else if (std.mem.eql(u8, arg, "--slurpfile")) {
+            const kv = optionValues(args, &i, 2);
+            const texts = parseJsonTexts(page_alloc, readArgFile(page_alloc, arg, kv[1])) orelse {
+                usageError("--slurpfile {s}: {s} is not valid JSON", .{ kv[0], kv[1] });
+            };
+            try named.put(kv[0], .{ .array = texts });
+        } else if (std.mem.eql(u8, arg, "--rawfile")) {
+            const kv = optionValues(args, &i, 2);
+            try named.put(kv[0], .{ .string = readArgFile(page_alloc, arg, kv[1]) });
+        } else if (std.mem.eql(u8, arg, "--args")) {
+            positional_kind = .string;
+        } else if (std.mem.eql(u8, arg, "--jsonargs")) {
+            positional_kind = .json;
+        } else if (std.mem.eql(u8, arg, "-L") or std.mem.eql(u8, arg, "--library-path")) {
+            i += 1;
+            if (i >= args.len) usageError("{s} requires a directory", .{arg});
+            try lib_dirs.append(page_alloc, args[i]);
+        } else if (std.mem.startsWith(u8, arg, "-L")) {
+            try lib_dirs.append(page_alloc, arg[2..]);
+        } else if (arg.len == 0 or arg[0] != '-') {
+            if (expr_arg == null) {
+                expr_arg = arg;
+            } else if (positional_kind) |kind| switch (kind) {
+                .string => try positional.append(.{ .string = arg }),
+                .json => {
+                    const parsed = number.parseJson(page_alloc, arg, .{}) catch {
+                        usageError("--jsonargs: invalid JSON text '{s}'", .{arg});
+                    };
+                    try positional.append(parsed);
+                },
+            } else {
+                usageError("unexpected argument '{s}' (zq reads records from stdin)", .{arg});
+            }
+        } else if (std.mem.startsWith(u8, arg, "--")) {
+            for (long_flags) |flag| {
+                if (std.mem.eql(u8, arg, flag.name)) {
+                    _ = applyShortFlag(&config, flag.short);
+                    continue :next_arg;
+                }
+            }
+            usageError("Unknown option: {s}", .{arg});
         } else {
-            std.debug.print("Unknown option: {s}\n", .{arg});
-            std.process.exit(1);
+            // Clusters of single-letter options, e.g. -nr
+            for (arg[1..]) |c| {
+                if (!applyShortFlag(&config, c)) usageError("Unknown option: {s}", .{arg});
+            }
         }
     }
 
     if (expr_arg == null) {
         std.debug.print("Error: expression required\n\n", .{});
         printUsage();
-        std.process.exit(1);
+        std.process.exit(2);
+    }
+    if (config.stream and config.raw_input) usageError("--stream reads JSON; it cannot be combined with -R", .{});
+    if (config.color) {
+        if (std.posix.getenv("JQ_COLORS")) |spec| {
+            // Like jq, a bad $JQ_COLORS is a warning and the defaults stay
+            if (output.parsePalette(spec)) |palette| {
+                config.palette = palette;
+            } else {
+                std.debug.print("Failed to set $JQ_COLORS\n", .{});
+            }
+        }
     }
 
+    // Named arguments are variables of the whole expression, next to $ARGS
+    var args_object: std.json.ObjectMap = .init(page_alloc);
+    try args_object.put("positional", .{ .array = positional });
+    try args_object.put("named", .{ .object = named });
+    var globals = try eval.Env.bind(page_alloc, null, "ARGS", .{ .object = args_object });
+    var global_names: std.ArrayListUnmanaged([]const u8) = .empty;
+    try global_names.append(page_alloc, "ARGS");
+    var named_iter = named.iterator();
+    while (named_iter.next()) |entry| {
+        globals = try eval.Env.bind(page_alloc, globals, entry.key_ptr.*, entry.value_ptr.*);
+        try global_names.append(page_alloc, entry.key_ptr.*);
+    }
+    eval.setGlobals(globals);
+    const global_scope: types.VarScope = .{ .names = global_names.items };
+
     // Local error context - avoids global mutable state for thread safety
-    var err_ctx: ErrorContext = .{};
+    var err_ctx: ErrorContext = .{ .lib_dirs = lib_dirs.items, .scope = &global_scope };
 
-    const expr = parseExprWithContext(page_alloc, expr_arg.?, &err_ctx) catch |err| {
+    var expr = parseExprWithContext(page_alloc, expr_arg.?, &err_ctx) catch |err| {
         switch (err) {
             error.UnsupportedFeature => {
                 std.debug.print("Error: Unsupported jq feature: {s}\n", .{err_ctx.feature});
@@ -277,12 +1022,40 @@ pub fn main() !void {
             error.InvalidValue => {
                 std.debug.print("Error: Invalid value in expression: '{s}'\n", .{expr_arg.?});
             },
+            error.UndefinedVariable => {
+                std.debug.print("Error: {s} is not defined\n", .{err_ctx.feature});
+                if (std.mem.startsWith(u8, err_ctx.feature, "$*label-")) {
+                    std.debug.print("  break needs an enclosing label: label ${s} | ...\n", .{err_ctx.feature["$*label-".len..]});
+                } else {
+                    std.debug.print("  Bind it first: .field as {s} | ... (or pass --arg {s} VALUE)\n", .{ err_ctx.feature, err_ctx.feature[1..] });
+                }
+            },
+            error.InvalidRegex => {
+                std.debug.print("Error: Invalid regular expression: {s}\n", .{err_ctx.feature});
+                std.debug.print("  {s}\n", .{err_ctx.suggestion});
+            },
+            error.ModuleNotFound => {
+                std.debug.print("Error: module not found: {s}.jq\n", .{err_ctx.feature});
+                std.debug.print("  Searched the -L directories (default ~/.jq)\n", .{});
+            },
             error.OutOfMemory => {
                 std.debug.print("Error: Out of memory while parsing expression\n", .{});
             },
         }
-        std.process.exit(1);
+        // jq's exit status for a program that does not compile
+        std.process.exit(3);
     };
+    plan.foldConstants(page_alloc, &expr);
+
+    // select(...) filters can usually reject a record without parsing it;
+    // raw input lines are strings, not JSON, so the plan does not apply
+    const use_plan = config.plan and !config.raw_input and !config.stream and !config.null_input and !config.slurp;
+    const compiled = if (use_plan) try plan.Plan.compile(page_alloc, &expr) else null;
+
+    // --jobs: records are evaluated out of order, so not when the expression
+    // reads other records (input), counts them (seq) or writes to stderr as it goes
+    const parallel = config.jobs > 1 and !config.slurp and !config.null_input and !config.stream and
+        err_ctx.input_calls == 0 and err_ctx.ordered_calls == 0;
 
     // Zig 0.15.2 I/O with buffered reader/writer
     // Buffer for reading JSON lines (64KB max line)
@@ -297,92 +1070,120 @@ pub fn main() !void {
     var arena = std.heap.ArenaAllocator.init(page_alloc);
     defer arena.deinit();
 
-    var output_count: usize = 0;
-
-    if (config.slurp) {
-        // Slurp mode: collect all JSON values into an array, then apply expression
-        var slurp_values: std.ArrayListUnmanaged(std.json.Value) = .empty;
+    var printer: Printer = .{ .writer = writer, .config = config };
+    errors.output = writer;
+    // Input line of the current record, for error messages
+    var line_no: usize = 0;
 
-        // Read lines until EOF (compatible with Zig 0.15.1+)
-        while (true) {
-            const maybe_line = readLine(reader);
-            if (maybe_line) |line| {
-                if (line.len == 0) continue;
+    var stdin_inputs: StdinInputs = .{
+        .reader = reader,
+        .line_no = &line_no,
+        .skip_invalid = config.skip_invalid,
+        .errors = &errors,
+        .raw = config.raw_input,
+        .seq = config.seq,
+    };
+    // --stream: records are events from an incremental tokenizer, not lines
+    var events = stream.EventReader.init(std.heap.smp_allocator, reader);
+    defer events.deinit();
+    events.seq = config.seq;
+    var event_inputs: EventInputs = .{ .events = &events, .line_no = &line_no, .writer = writer };
 
-                // Make a copy of the line since the reader buffer gets reused
-                const line_copy = try arena.allocator().dupe(u8, line);
+    const source: eval.InputSource = if (config.stream)
+        .{ .context = &event_inputs, .nextFn = EventInputs.next }
+    else
+        .{ .context = &stdin_inputs, .nextFn = StdinInputs.next };
+    eval.setInputSource(source);
 
-                const parsed = std.json.parseFromSlice(std.json.Value, arena.allocator(), line_copy, .{}) catch {
-                    if (!config.skip_invalid) {
-                        std.debug.print("Error: malformed JSON\n", .{});
-                        std.process.exit(1);
-                    }
-                    continue;
+    if (config.null_input) {
+        if (eval.StreamDriver.match(page_alloc, &expr, err_ctx.input_calls)) |matched| {
+            // fromstream(... inputs ...): one input at a time, so only the
+            // value being rebuilt is held in memory
+            var driver = matched;
+            defer driver.deinit();
+            while (true) {
+                _ = arena.reset(.retain_capacity);
+                const input = source.nextFn(source.context, arena.allocator()) orelse break;
+                const results = driver.feed(arena.allocator(), input) catch |err| {
+                    try writer.flush();
+                    reportEvalError(arena.allocator(), err, line_no);
+                    std.process.exit(5);
                 };
-                try slurp_values.append(arena.allocator(), parsed.value);
-            } else {
-                // EOF reached
-                break;
+                for (results.values) |result| try printer.print(arena.allocator(), result);
             }
+        } else {
+            // One run against null; input/inputs read stdin
+            if (!try runRecord(arena.allocator(), &printer, &expr, .null, line_no)) std.process.exit(5);
         }
-
-        // Create array from collected values
-        const array_value = std.json.Value{ .array = std.json.Array{
-            .items = slurp_values.items,
-            .capacity = slurp_values.capacity,
-            .allocator = arena.allocator(),
-        } };
-
-        // Apply expression to the array
-        const results = evalExpr(arena.allocator(), &expr, array_value) catch |err| {
-            std.debug.print("Evaluation error: {}\n", .{err});
-            std.process.exit(1);
+    } else if (config.slurp) {
+        // Slurp mode: -R reads stdin as one string, otherwise every record goes into an array
+        const input: std.json.Value = if (config.raw_input) .{
+            .string = try reader.allocRemaining(arena.allocator(), .unlimited),
+        } else blk: {
+            var slurp_values = std.json.Array.init(arena.allocator());
+            while (source.nextFn(source.context, arena.allocator())) |value| {
+                try slurp_values.append(value);
+            }
+            break :blk .{ .array = slurp_values };
         };
 
-        for (results.values) |result| {
-            try writeJson(arena.allocator(), writer, result, config);
-            try writer.writeByte('\n');
-            output_count += 1;
+        if (!try runRecord(arena.allocator(), &printer, &expr, input, line_no)) std.process.exit(5);
+    } else if (config.stream) {
+        // Each event is a record
+        while (true) {
+            _ = arena.reset(.retain_capacity);
+            const event = EventInputs.next(&event_inputs, arena.allocator()) orelse break;
+            const ok = try runRecord(arena.allocator(), &printer, &expr, event, line_no);
+            if (!ok and config.strict) std.process.exit(5);
         }
+    } else if (parallel) {
+        const job: Job = .{
+            .expr = &expr,
+            .plan = if (compiled) |*p| p else null,
+            .config = config,
+            .globals = globals,
+            .errors = &errors,
+        };
+        try runParallel(&job, reader, writer, &printer, &errors);
     } else {
         // Normal streaming mode
-        // Read lines until EOF (compatible with Zig 0.15.1+)
-        while (true) {
-            const maybe_line = readLine(reader);
-            if (maybe_line) |line| {
-                if (line.len == 0) continue;
+        while (readLine(reader)) |raw_line| {
+            line_no += 1;
+            const line = recordText(raw_line, config.seq);
+            if (line.len == 0 and !config.raw_input) continue;
 
-                _ = arena.reset(.retain_capacity);
+            _ = arena.reset(.retain_capacity);
+
+            // input/inputs read further lines, which can move this one in the reader's buffer
+            const record = if (err_ctx.input_calls > 0) try arena.allocator().dupe(u8, line) else line;
 
-                const parsed = std.json.parseFromSlice(std.json.Value, arena.allocator(), line, .{}) catch {
-                    if (!config.skip_invalid) {
+            var run_expr: *const Expr = &expr;
+            var value: std.json.Value = .{ .string = record };
+            if (!config.raw_input) {
+                if (compiled) |p| switch (p.filter(record)) {
+                    .drop => continue,
+                    .pass => run_expr = p.on_pass,
+                    .unknown => {},
+                };
+                const parsed = number.parseJson(arena.allocator(), record, .{}) catch {
+                    if (!config.skip_invalid and !errors.enabled()) {
                         std.debug.print("Error: malformed JSON\n", .{});
                         std.process.exit(1);
                     }
+                    if (errors.counting()) errors.report("malformed JSON", line_no, record);
                     continue;
                 };
-                const value = parsed.value;
-
-                const results = evalExpr(arena.allocator(), &expr, value) catch |err| {
-                    std.debug.print("Evaluation error: {}\n", .{err});
-                    continue;
-                };
-
-                for (results.values) |result| {
-                    try writeJson(arena.allocator(), writer, result, config);
-                    try writer.writeByte('\n');
-                    output_count += 1;
-                }
-            } else {
-                // EOF reached
-                break;
+                value = parsed;
             }
+
+            const ok = try runLine(arena.allocator(), &printer, run_expr, value, line_no, record, &errors);
+            if (!ok and config.strict) std.process.exit(5);
         }
     }
 
     try writer.flush();
 
-    if (config.exit_on_empty and output_count == 0) {
-        std.process.exit(1);
+    if (config.exit_status) {
+        std.process.exit(printer.exitStatus());
     }
 }
@@ -0,0 +1,443 @@
+const std = @import("std");
+
+// ============================================================================
+// Lossless Numbers
+// ============================================================================
+//
+// std.json reads every number as an i64 or an f64, so a 20-digit ID, a
+// high-precision amount or a price like 10.50 comes back out changed. zq
+// keeps the literal text (a .number_string) for any number an f64 can't print
+// back as written, and writes it out untouched.
+//
+// Number strings are exact decimals: +, - and * with integers or other
+// decimals are done in base 10 without rounding (`todecimal` turns any number
+// into one). Everything else (/, %, math builtins) sees the nearest f64, as
+// in jq.
+
+/// Significant digits that always survive a trip through f64
+const f64_digits = 15;
+/// Longest digit string a decimal may expand to before arithmetic falls back to f64
+const max_digits = 1000;
+
+/// The pieces of a JSON number literal.
+const Literal = struct {
+    negative: bool,
+    /// Digits before the point
+    int: []const u8,
+    /// Digits after the point (may be empty)
+    frac: []const u8,
+    /// Power of ten from the exponent, clamped far beyond any real literal
+    exp: i64,
+    has_exp: bool,
+
+    /// Split `text` if it is a JSON number: -?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)?
+    fn split(text: []const u8) ?Literal {
+        var lit: Literal = .{ .negative = false, .int = "", .frac = "", .exp = 0, .has_exp = false };
+        var i: usize = 0;
+        if (i < text.len and text[i] == '-') {
+            lit.negative = true;
+            i += 1;
+        }
+        lit.int = digitRun(text, &i);
+        if (lit.int.len == 0 or (lit.int[0] == '0' and lit.int.len > 1)) return null;
+        if (i < text.len and text[i] == '.') {
+            i += 1;
+            lit.frac = digitRun(text, &i);
+            if (lit.frac.len == 0) return null;
+        }
+        if (i < text.len and (text[i] == 'e' or text[i] == 'E')) {
+            i += 1;
+            lit.has_exp = true;
+            var exp_negative = false;
+            if (i < text.len and (text[i] == '+' or text[i] == '-')) {
+                exp_negative = text[i] == '-';
+                i += 1;
+            }
+            const digits = digitRun(text, &i);
+            if (digits.len == 0) return null;
+            for (digits) |c| lit.exp = @min(lit.exp * 10 + (c - '0'), 1 << 40);
+            if (exp_negative) lit.exp = -lit.exp;
+        }
+        if (i != text.len) return null;
+        return lit;
+    }
+
+    /// Digit `i` of int ++ frac, with zeros past the end.
+    fn digit(self: Literal, i: usize) u8 {
+        if (i < self.int.len) return self.int[i];
+        if (i - self.int.len < self.frac.len) return self.frac[i - self.int.len];
+        return '0';
+    }
+
+    /// Leading zeros of int ++ frac, or null if every digit is zero.
+    fn leadingZeros(self: Literal) ?usize {
+        const len = self.int.len + self.frac.len;
+        for (0..len) |i| {
+            if (self.digit(i) != '0') return i;
+        }
+        return null;
+    }
+};
+
+fn digitRun(text: []const u8, i: *usize) []const u8 {
+    const start = i.*;
+    while (i.* < text.len and std.ascii.isDigit(text[i.*])) i.* += 1;
+    return text[start..i.*];
+}
+
+/// Whether `text` is a JSON number literal.
+pub fn isLiteral(text: []const u8) bool {
+    return Literal.split(text) != null;
+}
+
+/// How zq reads a number literal: an integer when it fits in an i64, a float
+/// when an f64 prints it back unchanged, otherwise the literal text itself.
+/// Null if `text` isn't a JSON number.
+pub fn literal(text: []const u8) ?std.json.Value {
+    const lit = Literal.split(text) orelse return null;
+    if (lit.frac.len == 0 and !lit.has_exp) {
+        // -0 has no i64 form
+        if (!(lit.negative and lit.leadingZeros() == null)) {
+            if (std.fmt.parseInt(i64, text, 10)) |i| return .{ .integer = i } else |_| {}
+        }
+        return .{ .number_string = text };
+    }
+    // Short literals without exponents or trailing zeros are exactly the
+    // shortest text of their f64, which is how floats are written
+    const significant = lit.int.len + lit.frac.len - (lit.leadingZeros() orelse 0);
+    if (lit.has_exp or lit.frac[lit.frac.len - 1] == '0' or significant > f64_digits) {
+        return .{ .number_string = text };
+    }
+    const f = std.fmt.parseFloat(f64, text) catch return .{ .number_string = text };
+    return .{ .float = f };
+}
+
+/// Parse JSON text, typing each number with `literal`.
+pub fn parseJson(allocator: std.mem.Allocator, text: []const u8, options: std.json.ParseOptions) !std.json.Value {
+    var number_options = options;
+    number_options.parse_numbers = false;
+    var value = try std.json.parseFromSliceLeaky(std.json.Value, allocator, text, number_options);
+
+    // Containers still to visit; an explicit stack, since nesting is unbounded
+    var pending: std.ArrayListUnmanaged(*std.json.Value) = .empty;
+    defer pending.deinit(allocator);
+    try retype(allocator, &pending, &value);
+    while (pending.pop()) |container| switch (container.*) {
+        .array => |arr| for (arr.items) |*item| try retype(allocator, &pending, item),
+        .object => |obj| {
+            var it = obj.iterator();
+            while (it.next()) |entry| try retype(allocator, &pending, entry.value_ptr);
+        },
+        else => {},
+    };
+    return value;
+}
+
+fn retype(allocator: std.mem.Allocator, pending: *std.ArrayListUnmanaged(*std.json.Value), value: *std.json.Value) error{OutOfMemory}!void {
+    switch (value.*) {
+        .number_string => |s| value.* = literal(s) orelse value.*,
+        .array, .object => try pending.append(allocator, value),
+        else => {},
+    }
+}
+
+/// Order two numbers of any representation: exactly when neither is an
+/// f64, otherwise as f64s. Null if either isn't a number, or is NaN.
+pub fn order(a: std.json.Value, b: std.json.Value) ?std.math.Order {
+    if (a == .integer and b == .integer) return std.math.order(a.integer, b.integer);
+    if (a == .float or b == .float) {
+        const af = toFloat(a) orelse return null;
+        const bf = toFloat(b) orelse return null;
+        if (std.math.isNan(af) or std.math.isNan(bf)) return null;
+        return std.math.order(af, bf);
+    }
+    var a_buf: [24]u8 = undefined;
+    var b_buf: [24]u8 = undefined;
+    const a_lit = Literal.split(exactText(&a_buf, a) orelse return null) orelse return null;
+    const b_lit = Literal.split(exactText(&b_buf, b) orelse return null) orelse return null;
+    return orderLiterals(a_lit, b_lit);
+}
+
+/// The nearest f64 to a number.
+pub fn toFloat(value: std.json.Value) ?f64 {
+    return switch (value) {
+        .integer => |i| @floatFromInt(i),
+        .float => |f| f,
+        .number_string => |s| std.fmt.parseFloat(f64, s) catch null,
+        else => null,
+    };
+}
+
+/// The literal text of an integer or a number string.
+fn exactText(buf: *[24]u8, value: std.json.Value) ?[]const u8 {
+    return switch (value) {
+        .integer => |i| std.fmt.bufPrint(buf, "{d}", .{i}) catch unreachable,
+        .number_string => |s| s,
+        else => null,
+    };
+}
+
+fn orderLiterals(a: Literal, b: Literal) std.math.Order {
+    const a_lead = a.leadingZeros();
+    const b_lead = b.leadingZeros();
+    // Zero has no sign
+    const a_sign: i2 = if (a_lead == null) 0 else if (a.negative) -1 else 1;
+    const b_sign: i2 = if (b_lead == null) 0 else if (b.negative) -1 else 1;
+    if (a_sign != b_sign) return std.math.order(a_sign, b_sign);
+    if (a_sign == 0) return .eq;
+
+    const magnitude = orderMagnitudes(a, a_lead.?, b, b_lead.?);
+    return if (a_sign < 0) magnitude.invert() else magnitude;
+}
+
+/// Compare |a| and |b|, both non-zero: first by the position of the leading
+/// digit relative to the point, then digit by digit.
+fn orderMagnitudes(a: Literal, a_lead: usize, b: Literal, b_lead: usize) std.math.Order {
+    const a_pos = @as(i64, @intCast(a.int.len)) - @as(i64, @intCast(a_lead)) + a.exp;
+    const b_pos = @as(i64, @intCast(b.int.len)) - @as(i64, @intCast(b_lead)) + b.exp;
+    if (a_pos != b_pos) return std.math.order(a_pos, b_pos);
+
+    const a_len = a.int.len + a.frac.len - a_lead;
+    const b_len = b.int.len + b.frac.len - b_lead;
+    for (0..@max(a_len, b_len)) |i| {
+        const ad = a.digit(a_lead + i);
+        const bd = b.digit(b_lead + i);
+        if (ad != bd) return std.math.order(ad, bd);
+    }
+    return .eq;
+}
+
+/// An exact base-10 number: `digits` (ASCII, most significant first, no
+/// leading zeros) divided by 10^scale.
+pub const Decimal = struct {
+    negative: bool,
+    digits: []const u8,
+    scale: usize,
+
+    /// The exact value of an integer or a number string, or of a float's
+    /// shortest text. Null for other values, NaN and infinities, and numbers
+    /// that would need more than max_digits digits.
+    pub fn fromValue(allocator: std.mem.Allocator, value: std.json.Value) error{OutOfMemory}!?Decimal {
+        return switch (value) {
+            .integer => |i| try fromText(allocator, try std.fmt.allocPrint(allocator, "{d}", .{i})),
+            .number_string => |s| try fromText(allocator, s),
+            .float => |f| if (std.math.isFinite(f)) try fromText(allocator, try std.fmt.allocPrint(allocator, "{d}", .{f})) else null,
+            else => null,
+        };
+    }
+
+    fn fromText(allocator: std.mem.Allocator, text: []const u8) error{OutOfMemory}!?Decimal {
+        const lit = Literal.split(text) orelse return null;
+        // digits / 10^(frac.len - exp)
+        const scale = @as(i64, @intCast(lit.frac.len)) - lit.exp;
+        const zeros: usize = if (scale < 0) @intCast(-scale) else 0;
+        const len = lit.int.len + lit.frac.len + zeros;
+        if (len > max_digits or scale > max_digits) return null;
+
+        const buf = try allocator.alloc(u8, len);
+        @memcpy(buf[0..lit.int.len], lit.int);
+        @memcpy(buf[lit.int.len..][0..lit.frac.len], lit.frac);
+        @memset(buf[lit.int.len + lit.frac.len ..], '0');
+        const digits = trimDigits(buf);
+        return .{
+            .negative = lit.negative and !isZero(digits),
+            .digits = digits,
+            .scale = if (scale > 0) @intCast(scale) else 0,
+        };
+    }
+
+    pub fn add(a: Decimal, b: Decimal, allocator: std.mem.Allocator) error{OutOfMemory}!Decimal {
+        const scale = @max(a.scale, b.scale);
+        const a_digits = try shift(allocator, a.digits, scale - a.scale);
+        const b_digits = try shift(allocator, b.digits, scale - b.scale);
+        if (a.negative == b.negative) {
+            const digits = try addDigits(allocator, a_digits, b_digits);
+            return .{ .negative = a.negative and !isZero(digits), .digits = digits, .scale = scale };
+        }
+        // Opposite signs: the larger magnitude decides the sign
+        return switch (orderDigits(a_digits, b_digits)) {
+            .eq => .{ .negative = false, .digits = "0", .scale = scale },
+            .gt => .{ .negative = a.negative, .digits = try subDigits(allocator, a_digits, b_digits), .scale = scale },
+            .lt => .{ .negative = b.negative, .digits = try subDigits(allocator, b_digits, a_digits), .scale = scale },
+        };
+    }
+
+    pub fn sub(a: Decimal, b: Decimal, allocator: std.mem.Allocator) error{OutOfMemory}!Decimal {
+        var negated = b;
+        negated.negative = !b.negative and !isZero(b.digits);
+        return a.add(negated, allocator);
+    }
+
+    pub fn mul(a: Decimal, b: Decimal, allocator: std.mem.Allocator) error{OutOfMemory}!Decimal {
+        const digits = try mulDigits(allocator, a.digits, b.digits);
+        return .{
+            .negative = a.negative != b.negative and !isZero(digits),
+            .digits = digits,
+            .scale = a.scale + b.scale,
+        };
+    }
+
+    /// An integer when there is no fraction and it fits in an i64, otherwise
+    /// a number string that keeps every digit (and the scale, so 1.10 + 1.20
+    /// is 2.30).
+    pub fn toValue(self: Decimal, allocator: std.mem.Allocator) error{OutOfMemory}!std.json.Value {
+        var text: std.ArrayListUnmanaged(u8) = .empty;
+        if (self.negative) try text.append(allocator, '-');
+        if (self.scale == 0) {
+            try text.appendSlice(allocator, self.digits);
+            if (std.fmt.parseInt(i64, text.items, 10)) |i| return .{ .integer = i } else |_| {}
+            return .{ .number_string = try text.toOwnedSlice(allocator) };
+        }
+        if (self.digits.len > self.scale) {
+            const point = self.digits.len - self.scale;
+            try text.appendSlice(allocator, self.digits[0..point]);
+            try text.append(allocator, '.');
+            try text.appendSlice(allocator, self.digits[point..]);
+        } else {
+            try text.appendSlice(allocator, "0.");
+            try text.appendNTimes(allocator, '0', self.scale - self.digits.len);
+            try text.appendSlice(allocator, self.digits);
+        }
+        return .{ .number_string = try text.toOwnedSlice(allocator) };
+    }
+};
+
+fn isZero(digits: []const u8) bool {
+    return digits.len == 1 and digits[0] == '0';
+}
+
+/// Drop leading zeros, keeping at least one digit.
+fn trimDigits(digits: []u8) []u8 {
+    var start: usize = 0;
+    while (start + 1 < digits.len and digits[start] == '0') start += 1;
+    return digits[start..];
+}
+
+/// `digits` times 10^zeros.
+fn shift(allocator: std.mem.Allocator, digits: []const u8, zeros: usize) error{OutOfMemory}![]const u8 {
+    if (zeros == 0 or isZero(digits)) return digits;
+    const buf = try allocator.alloc(u8, digits.len + zeros);
+    @memcpy(buf[0..digits.len], digits);
+    @memset(buf[digits.len..], '0');
+    return buf;
+}
+
+fn orderDigits(a: []const u8, b: []const u8) std.math.Order {
+    if (a.len != b.len) return std.math.order(a.len, b.len);
+    return std.mem.order(u8, a, b);
+}
+
+fn addDigits(allocator: std.mem.Allocator, a: []const u8, b: []const u8) error{OutOfMemory}![]const u8 {
+    const len = @max(a.len, b.len) + 1;
+    const out = try allocator.alloc(u8, len);
+    var carry: u8 = 0;
+    for (0..len) |i| {
+        const sum = digitFromEnd(a, i) + digitFromEnd(b, i) + carry;
+        out[len - 1 - i] = '0' + sum % 10;
+        carry = sum / 10;
+    }
+    return trimDigits(out);
+}
+
+/// a - b, where a >= b.
+fn subDigits(allocator: std.mem.Allocator, a: []const u8, b: []const u8) error{OutOfMemory}![]const u8 {
+    const out = try allocator.alloc(u8, a.len);
+    var borrow: u8 = 0;
+    for (0..a.len) |i| {
+        const subtrahend = digitFromEnd(b, i) + borrow;
+        var minuend = digitFromEnd(a, i);
+        borrow = 0;
+        if (minuend < subtrahend) {
+            minuend += 10;
+            borrow = 1;
+        }
+        out[a.len - 1 - i] = '0' + (minuend - subtrahend);
+    }
+    return trimDigits(out);
+}
+
+fn mulDigits(allocator: std.mem.Allocator, a: []const u8, b: []const u8) error{OutOfMemory}![]const u8 {
+    const len = a.len + b.len;
+    const columns = try allocator.alloc(u32, len);
+    defer allocator.free(columns);
+    @memset(columns, 0);
+    for (a, 0..) |ad, i| {
+        for (b, 0..) |bd, j| columns[i + j + 1] += @as(u32, ad - '0') * (bd - '0');
+    }
+    const out = try allocator.alloc(u8, len);
+    var carry: u32 = 0;
+    var k = len;
+    while (k > 0) {
+        k -= 1;
+        const total = columns[k] + carry;
+        out[k] = '0' + @as(u8, @intCast(total % 10));
+        carry = total / 10;
+    }
+    return trimDigits(out);
+}
+
+/// Digit `i` counting from the least significant, as a number.
+fn digitFromEnd(digits: []const u8, i: usize) u8 {
+    return if (i < digits.len) digits[digits.len - 1 - i] - '0' else 0;
+}
+
+// ============================================================================
+// Number Tests
+// ============================================================================
+
+test "literals keep the text f64 would change" {
+    try std.testing.expectEqual(@as(i64, 42), literal("42").?.integer);
+    try std.testing.expectEqual(@as(f64, 0.25), literal("0.25").?.float);
+    try std.testing.expectEqualStrings("12345678901234567890", literal("12345678901234567890").?.number_string);
+    try std.testing.expectEqualStrings("10.50", literal("10.50").?.number_string);
+    try std.testing.expectEqualStrings("0.1234567890123456789", literal("0.1234567890123456789").?.number_string);
+    try std.testing.expectEqualStrings("1e3", literal("1e3").?.number_string);
+    try std.testing.expectEqualStrings("-0", literal("-0").?.number_string);
+    try std.testing.expect(literal("01") == null);
+    try std.testing.expect(literal("1.") == null);
+    try std.testing.expect(literal("nan") == null);
+}
+
+test "parseJson types nested numbers" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    const value = try parseJson(arena.allocator(), "{\"id\":1234567890123456789012,\"xs\":[1,2.5,2.50]}", .{});
+    try std.testing.expectEqualStrings("1234567890123456789012", value.object.get("id").?.number_string);
+    const xs = value.object.get("xs").?.array.items;
+    try std.testing.expectEqual(@as(i64, 1), xs[0].integer);
+    try std.testing.expectEqual(@as(f64, 2.5), xs[1].float);
+    try std.testing.expectEqualStrings("2.50", xs[2].number_string);
+}
+
+test "order compares literals exactly" {
+    const big: std.json.Value = .{ .number_string = "12345678901234567891" };
+    const bigger: std.json.Value = .{ .number_string = "12345678901234567892" };
+    try std.testing.expectEqual(std.math.Order.lt, order(big, bigger).?);
+    try std.testing.expectEqual(std.math.Order.eq, order(.{ .number_string = "1.50" }, .{ .number_string = "15e-1" }).?);
+    try std.testing.expectEqual(std.math.Order.gt, order(.{ .number_string = "-0.5" }, .{ .integer = -1 }).?);
+    try std.testing.expectEqual(std.math.Order.eq, order(.{ .number_string = "-0" }, .{ .integer = 0 }).?);
+    try std.testing.expectEqual(std.math.Order.lt, order(.{ .float = 1.5 }, .{ .number_string = "2.00" }).?);
+    try std.testing.expect(order(.{ .integer = 1 }, .{ .string = "1" }) == null);
+}
+
+test "decimal arithmetic is exact" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const allocator = arena.allocator();
+
+    const price = (try Decimal.fromValue(allocator, .{ .number_string = "19.99" })).?;
+    const qty = (try Decimal.fromValue(allocator, .{ .integer = 3 })).?;
+    try std.testing.expectEqualStrings("59.97", (try (try price.mul(qty, allocator)).toValue(allocator)).number_string);
+
+    const a = (try Decimal.fromValue(allocator, .{ .number_string = "1.10" })).?;
+    const b = (try Decimal.fromValue(allocator, .{ .float = 0.2 })).?;
+    try std.testing.expectEqualStrings("1.30", (try (try a.add(b, allocator)).toValue(allocator)).number_string);
+    try std.testing.expectEqualStrings("-0.90", (try (try b.sub(a, allocator)).toValue(allocator)).number_string);
+
+    const id = (try Decimal.fromValue(allocator, .{ .integer = std.math.maxInt(i64) })).?;
+    const one = (try Decimal.fromValue(allocator, .{ .integer = 1 })).?;
+    try std.testing.expectEqualStrings("9223372036854775808", (try (try id.add(one, allocator)).toValue(allocator)).number_string);
+    try std.testing.expectEqual(@as(i64, 0), (try (try id.sub(id, allocator)).toValue(allocator)).integer);
+    try std.testing.expect((try Decimal.fromValue(allocator, .{ .number_string = "1e100000" })) == null);
+}
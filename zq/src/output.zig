@@ -2,13 +2,13 @@ const std = @import("std");
 const types = @import("types.zig");
 
 const Config = types.Config;
+const Palette = types.Palette;
 
 // ============================================================================
 // Output
 // ============================================================================
 
 pub fn writeJson(allocator: std.mem.Allocator, writer: anytype, value: std.json.Value, config: Config) !void {
-    _ = allocator; // Arena allocator available if needed
     if (config.raw_strings) {
         switch (value) {
             .string => |s| {
@@ -18,7 +18,107 @@ pub fn writeJson(allocator: std.mem.Allocator, writer: anytype, value: std.json.
             else => {},
         }
     }
-    try writeJsonValue(writer, value);
+    // The NDJSON fast path: nothing to lay out, sort or color
+    if (config.compact and !config.sort_keys and !config.color) return writeJsonValue(writer, value);
+    try writeJsonStyled(allocator, writer, value, config, 0);
+}
+
+/// Pretty-printed (--indent, --tab), key-sorted (-S) and colored (-C) output, laid
+/// out like jq: one element per line, `"key": value`, empty containers as [] and {}.
+fn writeJsonStyled(allocator: std.mem.Allocator, writer: anytype, value: std.json.Value, config: Config, depth: usize) !void {
+    switch (value) {
+        .array => |arr| {
+            const color = config.palette.array;
+            if (arr.items.len == 0) return writeColored(writer, config, color, "[]");
+            try writeColored(writer, config, color, "[");
+            for (arr.items, 0..) |item, i| {
+                if (i > 0) try writeColored(writer, config, color, ",");
+                try writeLineBreak(writer, config, depth + 1);
+                try writeJsonStyled(allocator, writer, item, config, depth + 1);
+            }
+            try writeLineBreak(writer, config, depth);
+            try writeColored(writer, config, color, "]");
+        },
+        .object => |obj| {
+            const color = config.palette.object;
+            if (obj.count() == 0) return writeColored(writer, config, color, "{}");
+            var keys = obj.keys();
+            if (config.sort_keys) {
+                keys = try allocator.dupe([]const u8, keys);
+                std.mem.sort([]const u8, keys, {}, keyLessThan);
+            }
+            try writeColored(writer, config, color, "{");
+            for (keys, 0..) |key, i| {
+                if (i > 0) try writeColored(writer, config, color, ",");
+                try writeLineBreak(writer, config, depth + 1);
+                try startColor(writer, config, config.palette.object_key);
+                try writeJsonValue(writer, .{ .string = key });
+                try endColor(writer, config);
+                try writeColored(writer, config, color, if (config.compact) ":" else ": ");
+                try writeJsonStyled(allocator, writer, obj.get(key).?, config, depth + 1);
+            }
+            try writeLineBreak(writer, config, depth);
+            try writeColored(writer, config, color, "}");
+        },
+        else => {
+            try startColor(writer, config, scalarColor(config.palette, value));
+            try writeJsonValue(writer, value);
+            try endColor(writer, config);
+        },
+    }
+}
+
+fn scalarColor(palette: Palette, value: std.json.Value) []const u8 {
+    return switch (value) {
+        .null => palette.null_value,
+        .bool => |b| if (b) palette.true_value else palette.false_value,
+        .string => palette.string,
+        else => palette.number,
+    };
+}
+
+fn startColor(writer: anytype, config: Config, color: []const u8) !void {
+    if (config.color) try writer.print("\x1b[{s}m", .{color});
+}
+
+fn endColor(writer: anytype, config: Config) !void {
+    if (config.color) try writer.writeAll("\x1b[0m");
+}
+
+fn writeColored(writer: anytype, config: Config, color: []const u8, text: []const u8) !void {
+    try startColor(writer, config, color);
+    try writer.writeAll(text);
+    try endColor(writer, config);
+}
+
+/// The palette for a $JQ_COLORS value: colon-separated SGR parameters for
+/// null:false:true:numbers:strings:arrays:objects:object keys. Fields left out
+/// keep their defaults. Null if a field is anything but digits and semicolons,
+/// in which case jq ignores the whole variable.
+pub fn parsePalette(spec: []const u8) ?Palette {
+    var palette: Palette = .{};
+    var fields = std.mem.splitScalar(u8, spec, ':');
+    inline for (std.meta.fields(Palette)) |field| {
+        const part = fields.next() orelse return palette;
+        for (part) |c| {
+            if (!std.ascii.isDigit(c) and c != ';') return null;
+        }
+        @field(palette, field.name) = part;
+    }
+    return palette;
+}
+
+fn writeLineBreak(writer: anytype, config: Config, depth: usize) !void {
+    if (config.compact) return;
+    try writer.writeByte('\n');
+    const unit: u8 = if (config.tab) '\t' else ' ';
+    const width = if (config.tab) depth else depth * config.indent;
+    for (0..width) |_| try writer.writeByte(unit);
+}
+
+/// Byte order of UTF-8 keys is code point order, which is how jq sorts.
+fn keyLessThan(_: void, a: []const u8, b: []const u8) bool {
+    return std.mem.lessThan(u8, a, b);
 }
 
 pub fn writeJsonValue(writer: anytype, value: std.json.Value) !void {
@@ -27,9 +127,12 @@ pub fn writeJsonValue(writer: anytype, value: std.json.Value) !void {
         .bool => |b| try writer.writeAll(if (b) "true" else "false"),
         .integer => |i| try writer.print("{d}", .{i}),
         .float => |f| {
-            // Handle special cases for JSON compatibility
-            if (std.math.isNan(f) or std.math.isInf(f)) {
+            // Handle special cases for JSON compatibility: NaN is null and
+            // infinities are the largest doubles, like jq
+            if (std.math.isNan(f)) {
                 try writer.writeAll("null");
+            } else if (std.math.isInf(f)) {
+                try writer.writeAll(if (f > 0) "1.7976931348623157e+308" else "-1.7976931348623157e+308");
             } else {
                 try writer.print("{d}", .{f});
             }
@@ -89,3 +192,32 @@ pub fn writeJsonValue(writer: anytype, value: std.json.Value) !void {
         },
     }
 }
+
+// ============================================================================
+// Output Tests
+// ============================================================================
+
+test "colored output wraps each token in its palette color" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var aw: std.Io.Writer.Allocating = .init(arena.allocator());
+
+    var obj: std.json.ObjectMap = .init(arena.allocator());
+    try obj.put("a", .null);
+    try obj.put("b", .{ .string = "x" });
+    try writeJson(arena.allocator(), &aw.writer, .{ .object = obj }, .{ .color = true });
+    try std.testing.expectEqualStrings(
+        "\x1b[1;39m{\x1b[0m\x1b[34;1m\"a\"\x1b[0m\x1b[1;39m:\x1b[0m\x1b[0;90mnull\x1b[0m" ++
+            "\x1b[1;39m,\x1b[0m\x1b[34;1m\"b\"\x1b[0m\x1b[1;39m:\x1b[0m\x1b[0;32m\"x\"\x1b[0m\x1b[1;39m}\x1b[0m",
+        aw.written(),
+    );
+}
+
+test "JQ_COLORS overrides leading fields and rejects non-SGR text" {
+    const palette = parsePalette("1;31:0;32").?;
+    try std.testing.expectEqualStrings("1;31", palette.null_value);
+    try std.testing.expectEqualStrings("0;32", palette.false_value);
+    try std.testing.expectEqualStrings("0;39", palette.true_value);
+    try std.testing.expectEqualStrings("34;1", palette.object_key);
+    try std.testing.expect(parsePalette("red:green") == null);
+}
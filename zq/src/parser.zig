@@ -1,5 +1,8 @@
 const std = @import("std");
 const types = @import("types.zig");
+const regex = @import("regex.zig");
+const number = @import("number.zig");
+const builtins = @import("builtins.zig");
 
 // Import types
 const CompareValue = types.CompareValue;
@@ -31,7 +34,21 @@ const MapExpr = types.MapExpr;
 const ByFuncKind = types.ByFuncKind;
 const ByFuncExpr = types.ByFuncExpr;
 const ArrayExpr = types.ArrayExpr;
+const RegexKind = types.RegexKind;
+const FormatKind = types.FormatKind;
+const InterpPart = types.InterpPart;
 const DelExpr = types.DelExpr;
+const AssignOp = types.AssignOp;
+const PathFuncKind = types.PathFuncKind;
+const GeneratorKind = types.GeneratorKind;
+const DateFuncKind = types.DateFuncKind;
+const LibFuncKind = types.LibFuncKind;
+const Pattern = types.Pattern;
+const ObjectPatternField = types.ObjectPatternField;
+const VarScope = types.VarScope;
+const FuncScope = types.FuncScope;
+const FuncDef = types.FuncDef;
+const FuncParam = types.FuncParam;
 const ParseError = types.ParseError;
 const ErrorContext = types.ErrorContext;
 const MAX_PARSE_DEPTH = types.MAX_PARSE_DEPTH;
@@ -48,164 +65,12 @@ const whitespace = " \t\n\r";
 pub fn checkUnsupportedFeatures(expr: []const u8, err_ctx: *ErrorContext) ParseError!void {
     const trimmed = std.mem.trim(u8, expr, whitespace);
 
-    // Check for variable declarations ($var)
-    if (std.mem.indexOf(u8, trimmed, " as $")) |_| {
-        err_ctx.* = .{
-            .expression = trimmed,
-            .feature = "variable binding (as $var)",
-            .suggestion = "Use pipes instead: .items[] | select(.x > 0) | .name",
-        };
-        return error.UnsupportedFeature;
-    }
-
-    // Check for variable references ($var) - but not string interpolation
-    var i: usize = 0;
-    while (i < trimmed.len) : (i += 1) {
-        if (trimmed[i] == '$' and i + 1 < trimmed.len) {
-            const next = trimmed[i + 1];
-            // Check if it's a variable (letter or underscore after $)
-            if ((next >= 'a' and next <= 'z') or (next >= 'A' and next <= 'Z') or next == '_') {
-                err_ctx.* = .{
-                    .expression = trimmed,
-                    .feature = "variables ($var)",
-                    .suggestion = "ZQ doesn't support variables. Restructure with pipes.",
-                };
-                return error.UnsupportedFeature;
-            }
-        }
-    }
-
-    // Check for regex functions (test() is supported with basic patterns)
-    const regex_funcs = [_][]const u8{ "match(", "capture(", "scan(", "splits(", "sub(", "gsub(" };
-    for (regex_funcs) |func| {
-        if (std.mem.indexOf(u8, trimmed, func)) |_| {
-            err_ctx.* = .{
-                .expression = trimmed,
-                .feature = func[0 .. func.len - 1],
-                .suggestion = "Use test(), contains(), startswith(), or endswith() instead",
-            };
-            return error.UnsupportedFeature;
-        }
-    }
-
-    // Check for module imports
-    if (std.mem.startsWith(u8, trimmed, "import ") or std.mem.startsWith(u8, trimmed, "include ")) {
+    // Check for namespaced module imports (include is supported)
+    if (std.mem.startsWith(u8, trimmed, "import ")) {
         err_ctx.* = .{
             .expression = trimmed,
             .feature = "module imports",
-            .suggestion = "ZQ doesn't support modules. Use inline expressions.",
-        };
-        return error.UnsupportedFeature;
-    }
-
-    // Check for reduce
-    if (std.mem.startsWith(u8, trimmed, "reduce ")) {
-        err_ctx.* = .{
-            .expression = trimmed,
-            .feature = "reduce",
-            .suggestion = "Use add, min, max, or group_by instead",
-        };
-        return error.UnsupportedFeature;
-    }
-
-    // Check for limit
-    if (std.mem.startsWith(u8, trimmed, "limit(")) {
-        err_ctx.* = .{
-            .expression = trimmed,
-            .feature = "limit()",
-            .suggestion = "Use 'head -n N' after the pipeline: zq '.' | head -n 10",
-        };
-        return error.UnsupportedFeature;
-    }
-
-    // Check for recursive descent (..)
-    if (std.mem.indexOf(u8, trimmed, "..")) |pos| {
-        // Make sure it's not // (alternative operator)
-        if (pos == 0 or trimmed[pos - 1] != '/') {
-            err_ctx.* = .{
-                .expression = trimmed,
-                .feature = "recursive descent (..)",
-                .suggestion = "Use explicit paths instead: .items[].nested",
-            };
-            return error.UnsupportedFeature;
-        }
-    }
-
-    // Check for recurse/walk
-    if (std.mem.indexOf(u8, trimmed, "recurse") != null or std.mem.indexOf(u8, trimmed, "walk(") != null) {
-        err_ctx.* = .{
-            .expression = trimmed,
-            .feature = "recurse/walk",
-            .suggestion = "Use explicit iteration: .items[] | .children[]",
-        };
-        return error.UnsupportedFeature;
-    }
-
-    // Check for path functions
-    const path_funcs = [_][]const u8{ "path(", "getpath(", "setpath(", "delpaths(" };
-    for (path_funcs) |func| {
-        if (std.mem.indexOf(u8, trimmed, func)) |_| {
-            err_ctx.* = .{
-                .expression = trimmed,
-                .feature = func[0 .. func.len - 1],
-                .suggestion = "Use direct field access: .path.to.field",
-            };
-            return error.UnsupportedFeature;
-        }
-    }
-
-    // Check for debug/input functions
-    if (std.mem.indexOf(u8, trimmed, "debug") != null) {
-        err_ctx.* = .{
-            .expression = trimmed,
-            .feature = "debug",
-            .suggestion = "ZQ doesn't have debug output. Use stderr redirection.",
-        };
-        return error.UnsupportedFeature;
-    }
-
-    if (std.mem.eql(u8, trimmed, "input") or std.mem.eql(u8, trimmed, "inputs")) {
-        err_ctx.* = .{
-            .expression = trimmed,
-            .feature = "input/inputs",
-            .suggestion = "ZQ reads from stdin automatically. Just use '.'",
-        };
-        return error.UnsupportedFeature;
-    }
-
-    // Check for @base64 and other format strings
-    if (std.mem.indexOf(u8, trimmed, "@base64") != null or
-        std.mem.indexOf(u8, trimmed, "@uri") != null or
-        std.mem.indexOf(u8, trimmed, "@csv") != null or
-        std.mem.indexOf(u8, trimmed, "@html") != null or
-        std.mem.indexOf(u8, trimmed, "@json") != null or
-        std.mem.indexOf(u8, trimmed, "@text") != null or
-        std.mem.indexOf(u8, trimmed, "@sh") != null)
-    {
-        err_ctx.* = .{
-            .expression = trimmed,
-            .feature = "format strings (@base64, @uri, etc.)",
-            .suggestion = "Use jn put or external tools for format conversion",
-        };
-        return error.UnsupportedFeature;
-    }
-
-    // Check for try-catch
-    if (std.mem.indexOf(u8, trimmed, "try ") != null or std.mem.indexOf(u8, trimmed, " catch ") != null) {
-        err_ctx.* = .{
-            .expression = trimmed,
-            .feature = "try-catch",
-            .suggestion = "Use optional access (.field?) or alternative operator (//)",
-        };
-        return error.UnsupportedFeature;
-    }
-
-    // Check for def (function definitions)
-    if (std.mem.startsWith(u8, trimmed, "def ")) {
-        err_ctx.* = .{
-            .expression = trimmed,
-            .feature = "function definitions (def)",
-            .suggestion = "ZQ doesn't support custom functions. Use pipes.",
+            .suggestion = "Use include \"name\"; to load defs from NAME.jq in a -L directory",
         };
         return error.UnsupportedFeature;
     }
@@ -225,11 +90,30 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
         return error.UnsupportedFeature;
     }
 
+    // Remember the outermost expression so $__loc__ can report line numbers
+    if (err_ctx.depth == 1 and err_ctx.source.len == 0) err_ctx.source = expr;
+
     const trimmed = std.mem.trim(u8, expr, whitespace);
 
     // Check for unsupported jq features first
     try checkUnsupportedFeatures(trimmed, err_ctx);
 
+    // Module includes and function definitions scope over the rest of the expression
+    if (std.mem.startsWith(u8, trimmed, "include ")) {
+        return try parseInclude(allocator, trimmed, err_ctx);
+    }
+    if (std.mem.startsWith(u8, trimmed, "def ")) {
+        return try parseDefinition(allocator, trimmed, err_ctx);
+    }
+
+    // try/catch and label bind looser than pipes, so they're split off first
+    if (std.mem.startsWith(u8, trimmed, "try ")) {
+        return try parseTry(allocator, trimmed, err_ctx);
+    }
+    if (std.mem.startsWith(u8, trimmed, "label ")) {
+        return try parseLabel(allocator, trimmed, err_ctx);
+    }
+
     // Check for parenthesized expression (grouping)
     // Must match balanced parens at start and end
     if (trimmed.len > 2 and trimmed[0] == '(') {
@@ -246,14 +130,17 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
     }
 
     // Check for pipe operator first (lowest precedence)
-    // Need to find " | " not inside parentheses or braces
+    // Need to find " | " not inside parentheses, braces or brackets
     // Using u32 with saturating ops to avoid underflow with malformed input
     var paren_depth: u32 = 0;
     var brace_depth: u32 = 0;
+    var bracket_depth: u32 = 0;
     var i: usize = 0;
     while (i < trimmed.len) : (i += 1) {
         const c = trimmed[i];
-        if (c == '(') {
+        if (c == '"') {
+            i = stringEnd(trimmed, i) orelse trimmed.len;
+        } else if (c == '(') {
             paren_depth += 1;
         } else if (c == ')') {
             paren_depth -|= 1; // Saturating subtraction
@@ -261,15 +148,24 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
             brace_depth += 1;
         } else if (c == '}') {
             brace_depth -|= 1; // Saturating subtraction
-        } else if (c == '|' and paren_depth == 0 and brace_depth == 0) {
-            // Check it's not // (alternative operator)
-            if (i + 1 < trimmed.len and trimmed[i + 1] == '/') continue;
+        } else if (c == '[') {
+            bracket_depth += 1;
+        } else if (c == ']') {
+            bracket_depth -|= 1; // Saturating subtraction
+        } else if (c == '|' and paren_depth == 0 and brace_depth == 0 and bracket_depth == 0) {
+            // Check it's not // (alternative operator) or |= (update-assignment)
+            if (i + 1 < trimmed.len and (trimmed[i + 1] == '/' or trimmed[i + 1] == '=')) continue;
             if (i > 0 and trimmed[i - 1] == '/') continue;
 
             const left_str = std.mem.trim(u8, trimmed[0..i], whitespace);
             const right_str = std.mem.trim(u8, trimmed[i + 1 ..], whitespace);
 
             if (left_str.len > 0 and right_str.len > 0) {
+                // `source as $name | body`: the binding scopes over the rest of the pipe
+                if (findBindingAs(left_str)) |as_pos| {
+                    return try parseBinding(allocator, left_str[0..as_pos], left_str[as_pos + 4 ..], right_str, err_ctx);
+                }
+
                 const left = try allocator.create(Expr);
                 left.* = try parseExprWithContext(allocator, left_str, err_ctx);
                 const right = try allocator.create(Expr);
@@ -279,13 +175,20 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
         }
     }
 
+    // A binding must be followed by a body: `.x as $v | ...`
+    if (findBindingAs(trimmed) != null) {
+        return error.InvalidExpression;
+    }
+
     // Check for alternative operator (//)
     i = 0;
     paren_depth = 0;
     brace_depth = 0;
     while (i + 1 < trimmed.len) : (i += 1) {
         const c = trimmed[i];
-        if (c == '(') {
+        if (c == '"') {
+            i = stringEnd(trimmed, i) orelse trimmed.len;
+        } else if (c == '(') {
             paren_depth += 1;
         } else if (c == ')') {
             paren_depth -|= 1;
@@ -294,6 +197,11 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
         } else if (c == '}') {
             brace_depth -|= 1;
         } else if (c == '/' and trimmed[i + 1] == '/' and paren_depth == 0 and brace_depth == 0) {
+            // //= is an assignment, handled below
+            if (i + 2 < trimmed.len and trimmed[i + 2] == '=') {
+                i += 2;
+                continue;
+            }
             const left_str = std.mem.trim(u8, trimmed[0..i], whitespace);
             const right_str = std.mem.trim(u8, trimmed[i + 2 ..], whitespace);
 
@@ -307,6 +215,18 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
         }
     }
 
+    // Assignment operators (|= = += -= *= /= %= //=) bind tighter than // but looser than arithmetic
+    if (findAssignment(trimmed)) |assign| {
+        const target_str = std.mem.trim(u8, trimmed[0..assign.start], whitespace);
+        const rhs_str = std.mem.trim(u8, trimmed[assign.end..], whitespace);
+        if (target_str.len == 0 or rhs_str.len == 0) return error.InvalidExpression;
+        const target = try allocator.create(Expr);
+        target.* = try parseExprWithContext(allocator, target_str, err_ctx);
+        const rhs = try allocator.create(Expr);
+        rhs.* = try parseExprWithContext(allocator, rhs_str, err_ctx);
+        return .{ .assign = .{ .target = target, .op = assign.op, .rhs = rhs } };
+    }
+
     // Check for arithmetic operators (+ - * / %) - with spaces around them
     // Lower precedence operators first (+ -)
     i = 0;
@@ -314,7 +234,9 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
     brace_depth = 0;
     while (i + 2 < trimmed.len) : (i += 1) {
         const c = trimmed[i];
-        if (c == '(') {
+        if (c == '"') {
+            i = stringEnd(trimmed, i) orelse trimmed.len;
+        } else if (c == '(') {
             paren_depth += 1;
         } else if (c == ')') {
             paren_depth -|= 1;
@@ -346,7 +268,9 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
     brace_depth = 0;
     while (i + 2 < trimmed.len) : (i += 1) {
         const c = trimmed[i];
-        if (c == '(') {
+        if (c == '"') {
+            i = stringEnd(trimmed, i) orelse trimmed.len;
+        } else if (c == '(') {
             paren_depth += 1;
         } else if (c == ')') {
             paren_depth -|= 1;
@@ -379,16 +303,52 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
         }
     }
 
+    // Postfix ? drops errors: tonumber?, (.a | f)?; .foo? paths are handled below
+    if (trimmed.len > 1 and trimmed[trimmed.len - 1] == '?' and trimmed[0] != '.') {
+        const body = try allocator.create(Expr);
+        body.* = try parseExprWithContext(allocator, trimmed[0 .. trimmed.len - 1], err_ctx);
+        return .{ .try_catch = .{ .body = body } };
+    }
+
+    // reduce/foreach folds
+    if (std.mem.startsWith(u8, trimmed, "reduce ") or std.mem.startsWith(u8, trimmed, "foreach ")) {
+        return try parseFold(allocator, trimmed, err_ctx);
+    }
+
+    // User-defined functions shadow builtins of the same name and arity
+    if (err_ctx.funcs != null) {
+        if (try parseCall(allocator, trimmed, err_ctx)) |call| return call;
+    }
+
     // Identity
     if (std.mem.eql(u8, trimmed, ".")) {
         return .identity;
     }
 
+    // Recursive descent: .. is recurse
+    if (std.mem.eql(u8, trimmed, "..")) {
+        return .{ .generator = .{ .kind = .recurse } };
+    }
+
     // Root iteration: .[]
     if (std.mem.eql(u8, trimmed, ".[]")) {
         return .{ .iterate = .{ .path = &[_][]const u8{} } };
     }
 
+    // error, error(msg) and break $label
+    if (std.mem.eql(u8, trimmed, "error")) return .{ .raise = null };
+    if (std.mem.startsWith(u8, trimmed, "error(")) {
+        if (try splitCall(allocator, trimmed)) |call| {
+            if (call.args.len != 1) return error.InvalidExpression;
+            const msg = try allocator.create(Expr);
+            msg.* = try parseExprWithContext(allocator, call.args[0], err_ctx);
+            return .{ .raise = msg };
+        }
+    }
+    if (std.mem.startsWith(u8, trimmed, "break ")) {
+        return try parseBreak(allocator, trimmed, err_ctx);
+    }
+
     // Builtin functions (no arguments)
     if (std.mem.eql(u8, trimmed, "tonumber")) return .{ .builtin = .{ .kind = .tonumber } };
     if (std.mem.eql(u8, trimmed, "tostring")) return .{ .builtin = .{ .kind = .tostring } };
@@ -462,7 +422,10 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
     if (std.mem.eql(u8, trimmed, "xid")) return .{ .builtin = .{ .kind = .xid } };
     // Sprint 06: Generator functions - Random/Sequence
     if (std.mem.eql(u8, trimmed, "random")) return .{ .builtin = .{ .kind = .random } };
-    if (std.mem.eql(u8, trimmed, "seq")) return .{ .builtin = .{ .kind = .seq } };
+    if (std.mem.eql(u8, trimmed, "seq")) {
+        err_ctx.ordered_calls += 1;
+        return .{ .builtin = .{ .kind = .seq } };
+    }
     // Sprint 06: Transform functions - Numeric
     if (std.mem.eql(u8, trimmed, "incr")) return .{ .builtin = .{ .kind = .incr } };
     if (std.mem.eql(u8, trimmed, "decr")) return .{ .builtin = .{ .kind = .decr } };
@@ -475,6 +438,7 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
     // Sprint 06: Type coercion
     if (std.mem.eql(u8, trimmed, "int")) return .{ .builtin = .{ .kind = .@"int" } };
     if (std.mem.eql(u8, trimmed, "float")) return .{ .builtin = .{ .kind = .@"float" } };
+    if (std.mem.eql(u8, trimmed, "todecimal")) return .{ .builtin = .{ .kind = .todecimal } };
     if (std.mem.eql(u8, trimmed, "bool")) return .{ .builtin = .{ .kind = .@"bool" } };
     // Sprint 06: Case functions
     if (std.mem.eql(u8, trimmed, "capitalize")) return .{ .builtin = .{ .kind = .capitalize } };
@@ -485,8 +449,19 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
     // Sprint 07: More case functions
     if (std.mem.eql(u8, trimmed, "pascalcase")) return .{ .builtin = .{ .kind = .pascalcase } };
     if (std.mem.eql(u8, trimmed, "screamcase")) return .{ .builtin = .{ .kind = .screamcase } };
-    // Sprint 06: Predicates
+    // Core jq filters
     if (std.mem.eql(u8, trimmed, "empty")) return .{ .builtin = .{ .kind = .empty } };
+    if (std.mem.eql(u8, trimmed, "not")) return .{ .builtin = .{ .kind = .not } };
+    if (std.mem.eql(u8, trimmed, "arrays")) return .{ .builtin = .{ .kind = .arrays } };
+    if (std.mem.eql(u8, trimmed, "objects")) return .{ .builtin = .{ .kind = .objects } };
+    if (std.mem.eql(u8, trimmed, "iterables")) return .{ .builtin = .{ .kind = .iterables } };
+    if (std.mem.eql(u8, trimmed, "booleans")) return .{ .builtin = .{ .kind = .booleans } };
+    if (std.mem.eql(u8, trimmed, "numbers")) return .{ .builtin = .{ .kind = .numbers } };
+    if (std.mem.eql(u8, trimmed, "strings")) return .{ .builtin = .{ .kind = .strings } };
+    if (std.mem.eql(u8, trimmed, "nulls")) return .{ .builtin = .{ .kind = .nulls } };
+    if (std.mem.eql(u8, trimmed, "scalars")) return .{ .builtin = .{ .kind = .scalars } };
+    if (std.mem.eql(u8, trimmed, "finites")) return .{ .builtin = .{ .kind = .finites } };
+    if (std.mem.eql(u8, trimmed, "normals")) return .{ .builtin = .{ .kind = .normals } };
     // Sprint 06: String splitting
     if (std.mem.eql(u8, trimmed, "words")) return .{ .builtin = .{ .kind = .words } };
     if (std.mem.eql(u8, trimmed, "lines")) return .{ .builtin = .{ .kind = .lines } };
@@ -497,11 +472,65 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
     if (std.mem.eql(u8, trimmed, "xid_time")) return .{ .builtin = .{ .kind = .xid_time } };
     if (std.mem.eql(u8, trimmed, "delta")) return .{ .builtin = .{ .kind = .delta } };
     if (std.mem.eql(u8, trimmed, "ago")) return .{ .builtin = .{ .kind = .ago } };
+    // jq builtin library
+    if (std.mem.eql(u8, trimmed, "keys_unsorted")) return .{ .builtin = .{ .kind = .keys_unsorted } };
+    if (std.mem.eql(u8, trimmed, "utf8bytelength")) return .{ .builtin = .{ .kind = .utf8bytelength } };
+    if (std.mem.eql(u8, trimmed, "tojson")) return .{ .builtin = .{ .kind = .tojson } };
+    if (std.mem.eql(u8, trimmed, "fromjson")) return .{ .builtin = .{ .kind = .fromjson } };
+    if (std.mem.eql(u8, trimmed, "ascii")) return .{ .builtin = .{ .kind = .ascii } };
+    if (std.mem.eql(u8, trimmed, "implode")) return .{ .builtin = .{ .kind = .implode } };
+    if (std.mem.eql(u8, trimmed, "explode")) return .{ .builtin = .{ .kind = .explode } };
+    if (std.mem.eql(u8, trimmed, "transpose")) return .{ .builtin = .{ .kind = .transpose } };
+    if (std.mem.eql(u8, trimmed, "combinations")) return .{ .builtin = .{ .kind = .combinations } };
+    if (std.mem.eql(u8, trimmed, "toarray")) return .{ .builtin = .{ .kind = .toarray } };
+    if (std.mem.eql(u8, trimmed, "any")) return .{ .builtin = .{ .kind = .any } };
+    if (std.mem.eql(u8, trimmed, "all")) return .{ .builtin = .{ .kind = .all } };
+    if (std.mem.eql(u8, trimmed, "infinite")) return .{ .builtin = .{ .kind = .infinite } };
+    if (std.mem.eql(u8, trimmed, "nan")) return .{ .builtin = .{ .kind = .nan } };
+    if (std.mem.eql(u8, trimmed, "isinfinite")) return .{ .builtin = .{ .kind = .isinfinite } };
+    if (std.mem.eql(u8, trimmed, "isnan")) return .{ .builtin = .{ .kind = .isnan } };
+    if (std.mem.eql(u8, trimmed, "isnormal")) return .{ .builtin = .{ .kind = .isnormal } };
+    if (std.mem.eql(u8, trimmed, "trunc")) return .{ .builtin = .{ .kind = .trunc } };
+    if (std.mem.eql(u8, trimmed, "rint")) return .{ .builtin = .{ .kind = .rint } };
+    if (std.mem.eql(u8, trimmed, "nearbyint")) return .{ .builtin = .{ .kind = .nearbyint } };
+    if (std.mem.eql(u8, trimmed, "cbrt")) return .{ .builtin = .{ .kind = .cbrt } };
+    if (std.mem.eql(u8, trimmed, "exp2")) return .{ .builtin = .{ .kind = .exp2 } };
+    if (std.mem.eql(u8, trimmed, "exp10")) return .{ .builtin = .{ .kind = .exp10 } };
+    if (std.mem.eql(u8, trimmed, "pow10")) return .{ .builtin = .{ .kind = .exp10 } };
+    if (std.mem.eql(u8, trimmed, "log")) return .{ .builtin = .{ .kind = .log } };
+    if (std.mem.eql(u8, trimmed, "log1p")) return .{ .builtin = .{ .kind = .log1p } };
+    if (std.mem.eql(u8, trimmed, "expm1")) return .{ .builtin = .{ .kind = .expm1 } };
+    if (std.mem.eql(u8, trimmed, "logb")) return .{ .builtin = .{ .kind = .logb } };
+    if (std.mem.eql(u8, trimmed, "significand")) return .{ .builtin = .{ .kind = .significand } };
+    if (std.mem.eql(u8, trimmed, "gamma")) return .{ .builtin = .{ .kind = .gamma } };
+    if (std.mem.eql(u8, trimmed, "lgamma")) return .{ .builtin = .{ .kind = .lgamma } };
+    if (std.mem.eql(u8, trimmed, "tgamma")) return .{ .builtin = .{ .kind = .tgamma } };
+    if (std.mem.eql(u8, trimmed, "lgamma_r")) return .{ .builtin = .{ .kind = .lgamma_r } };
+    if (std.mem.eql(u8, trimmed, "frexp")) return .{ .builtin = .{ .kind = .frexp } };
+    if (std.mem.eql(u8, trimmed, "modf")) return .{ .builtin = .{ .kind = .modf } };
+    if (std.mem.eql(u8, trimmed, "sinh")) return .{ .builtin = .{ .kind = .sinh } };
+    if (std.mem.eql(u8, trimmed, "cosh")) return .{ .builtin = .{ .kind = .cosh } };
+    if (std.mem.eql(u8, trimmed, "tanh")) return .{ .builtin = .{ .kind = .tanh } };
+    if (std.mem.eql(u8, trimmed, "asinh")) return .{ .builtin = .{ .kind = .asinh } };
+    if (std.mem.eql(u8, trimmed, "acosh")) return .{ .builtin = .{ .kind = .acosh } };
+    if (std.mem.eql(u8, trimmed, "atanh")) return .{ .builtin = .{ .kind = .atanh } };
+    if (std.mem.eql(u8, trimmed, "builtins")) return .{ .builtin = .{ .kind = .builtins } };
+    if (std.mem.eql(u8, trimmed, "input_filename")) return .{ .builtin = .{ .kind = .input_filename } };
+    if (std.mem.eql(u8, trimmed, "have_literal_numbers")) return .{ .builtin = .{ .kind = .have_literal_numbers } };
+    if (std.mem.eql(u8, trimmed, "have_decnum")) return .{ .builtin = .{ .kind = .have_decnum } };
+    if (std.mem.eql(u8, trimmed, "debug")) {
+        err_ctx.ordered_calls += 1;
+        return .{ .builtin = .{ .kind = .debug } };
+    }
+    if (std.mem.eql(u8, trimmed, "stderr")) {
+        err_ctx.ordered_calls += 1;
+        return .{ .builtin = .{ .kind = .stderr } };
+    }
 
     if (std.mem.startsWith(u8, trimmed, "del(") and std.mem.endsWith(u8, trimmed, ")")) {
         const inner_str = trimmed[4 .. trimmed.len - 1];
-        // Parse the path expression inside del()
-        if (std.mem.startsWith(u8, inner_str, ".")) {
+        // Parse the path expression inside del(); anything else goes through path_func
+        if (isSimpleDelPath(inner_str)) {
             // Check for array index at end: del(.arr[0])
             var path_str = inner_str;
             var del_index: ?i64 = null;
@@ -519,6 +548,26 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
         }
     }
 
+    // Path functions - path(f), paths, getpath(p), setpath(p; v), del(f), walk(f), etc.
+    if (try parsePathFunc(allocator, trimmed, err_ctx)) |path_func| {
+        return path_func;
+    }
+
+    // Generators - range(n), limit(n; f), first(f), until, while, repeat, recurse, inputs, env, etc.
+    if (try parseGenerator(allocator, trimmed, err_ctx)) |generator| {
+        return generator;
+    }
+
+    // Dates - strptime(fmt), strftime(fmt), mktime, gmtime, todate, date_trunc(unit), dateadd(unit; n), etc.
+    if (try parseDateFunc(allocator, trimmed, err_ctx)) |date_func| {
+        return date_func;
+    }
+
+    // Regex functions - test("re"), match("re"; "g"), sub("re"; "x"), etc.
+    if (try parseRegexFunc(allocator, trimmed, err_ctx)) |regex_func| {
+        return regex_func;
+    }
+
     // Sprint 03: String functions with argument - split("sep"), join("sep"), etc.
     if (try parseStrFunc(allocator, trimmed)) |str_func| {
         return str_func;
@@ -533,10 +582,15 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
     }
 
     // Sprint 03: group_by(.field), sort_by(.field), etc.
-    if (try parseByFunc(allocator, trimmed)) |by_func| {
+    if (try parseByFunc(allocator, trimmed, err_ctx)) |by_func| {
         return by_func;
     }
 
+    // jq library - IN(s), INDEX(f), any(g; cond), indices(s), pow(a; b), has(.k), ...
+    if (try parseLibFunc(allocator, trimmed, err_ctx)) |lib_func| {
+        return lib_func;
+    }
+
     // Sprint 03: Array literal [.x, .y, .z]
     if (trimmed[0] == '[' and trimmed[trimmed.len - 1] == ']') {
         return try parseArrayLiteral(allocator, trimmed, err_ctx);
@@ -559,8 +613,23 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
         return try parseObject(allocator, trimmed, err_ctx);
     }
 
+    // Variable reference: $name, $name.field, $__loc__
+    if (trimmed[0] == '$') {
+        return try parseVariableRef(allocator, trimmed, err_ctx);
+    }
+
     // Field path with optional iteration: .foo or .foo.bar or .items[]
     if (trimmed[0] == '.') {
+        // Iteration or index mid-path: .items[].price is .items[] | .price
+        if (midPathBracket(trimmed)) |close| {
+            const rest = trimmed[close + 1 ..];
+            const left = try allocator.create(Expr);
+            left.* = try parseExprWithContext(allocator, trimmed[0 .. close + 1], err_ctx);
+            const right = try allocator.create(Expr);
+            right.* = try parseExprWithContext(allocator, if (rest[0] == '[') try std.mem.concat(allocator, u8, &.{ ".", rest }) else rest, err_ctx);
+            return .{ .pipe = .{ .left = left, .right = right } };
+        }
+
         var optional = false;
         var expr_str = trimmed;
         if (std.mem.endsWith(u8, trimmed, "?")) {
@@ -613,9 +682,14 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
         return .{ .path = .{ .parts = path, .index = index, .optional = optional } };
     }
 
-    // String literal: "..."
-    if (trimmed.len >= 2 and trimmed[0] == '"' and trimmed[trimmed.len - 1] == '"') {
-        return .{ .literal = .{ .string = trimmed[1 .. trimmed.len - 1] } };
+    // Format strings: @csv, or @sh "rm \(.file)" to format each interpolation
+    if (trimmed[0] == '@') {
+        return try parseFormat(allocator, trimmed, err_ctx);
+    }
+
+    // String literal: "...", possibly with \(expr) interpolations
+    if (isStringLiteral(trimmed)) {
+        return try parseStringTemplate(allocator, trimmed, null, err_ctx);
     }
 
     // Boolean literals
@@ -631,6 +705,14 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
         return .{ .literal = .null_val };
     }
 
+    // Number literal, kept as text when an i64/f64 would change it
+    if (number.literal(trimmed)) |num| switch (num) {
+        .integer => |int_val| return .{ .literal = .{ .integer = int_val } },
+        .float => |float_val| return .{ .literal = .{ .float = float_val } },
+        .number_string => |text| return .{ .literal = .{ .number = text } },
+        else => unreachable,
+    };
+
     // Number literal (integer)
     if (std.fmt.parseInt(i64, trimmed, 10)) |int_val| {
         return .{ .literal = .{ .integer = int_val } };
@@ -641,6 +723,16 @@ pub fn parseExprWithContext(allocator: std.mem.Allocator, expr: []const u8, err_
         return .{ .literal = .{ .float = float_val } };
     } else |_| {}
 
+    // jq builtins zq knows of but doesn't run
+    if (try splitCall(allocator, trimmed)) |call| {
+        if (builtins.unsupported(call.name)) |builtin| {
+            err_ctx.expression = trimmed;
+            err_ctx.feature = builtin.name;
+            err_ctx.suggestion = builtin.summary;
+            return error.UnsupportedFeature;
+        }
+    }
+
     return error.InvalidExpression;
 }
 
@@ -746,6 +838,10 @@ fn parseObject(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorCo
         const at_end = i == inner.len;
         const c = if (at_end) ',' else inner[i];
 
+        if (c == '"') {
+            i = stringEnd(inner, i) orelse inner.len - 1;
+            continue;
+        }
         if (c == '(') paren_depth += 1;
         if (c == ')') paren_depth -|= 1; // Saturating subtraction for safety
         if (c == '{') brace_depth += 1;
@@ -773,6 +869,7 @@ fn parseObjectField(allocator: std.mem.Allocator, field_str: []const u8, err_ctx
     var paren_depth: u32 = 0;
     var i: usize = 0;
     while (i < trimmed.len) : (i += 1) {
+        if (trimmed[i] == '"') i = stringEnd(trimmed, i) orelse trimmed.len - 1;
         if (trimmed[i] == '(') paren_depth += 1;
         if (trimmed[i] == ')') paren_depth -|= 1; // Saturating subtraction for safety
         if (trimmed[i] == ':' and paren_depth == 0) {
@@ -803,6 +900,11 @@ fn parseObjectField(allocator: std.mem.Allocator, field_str: []const u8, err_ctx
         value.* = try parseExprWithContext(allocator, val_part, err_ctx);
 
         return ObjectField{ .key = key, .value = value };
+    } else if (trimmed.len > 1 and trimmed[0] == '$') {
+        // Shorthand: {$foo} means {foo: $foo}
+        const value = try allocator.create(Expr);
+        value.* = try parseExprWithContext(allocator, trimmed, err_ctx);
+        return ObjectField{ .key = .{ .literal = trimmed[1..] }, .value = value };
     } else {
         // Shorthand: just "foo" means {foo: .foo}
         const value = try allocator.create(Expr);
@@ -888,13 +990,13 @@ fn findOperatorOutsideParens(str: []const u8, op: []const u8) ?usize {
     return null;
 }
 
-/// Check if expression is a simple path (no pipes, no parens)
+/// Check if expression is a simple path (no pipes, no parens, no indexes)
 fn isSimplePath(expr: []const u8) bool {
     const trimmed = std.mem.trim(u8, expr, whitespace);
     if (trimmed.len == 0) return false;
     if (trimmed[0] != '.') return false;
     for (trimmed) |c| {
-        if (c == '|' or c == '(' or c == ')') return false;
+        if (c == '|' or c == '(' or c == ')' or c == '[') return false;
     }
     return true;
 }
@@ -916,13 +1018,20 @@ fn parseSimpleCondition(allocator: std.mem.Allocator, expr: []const u8, err_ctx:
             const left_str = std.mem.trim(u8, expr[0..pos], whitespace);
             const right_str = expr[pos + op_info.len ..];
 
+            const cmp_value = try parseValue(right_str);
+            if (cmp_value == .variable and !VarScope.contains(err_ctx.scope, cmp_value.variable)) {
+                err_ctx.expression = expr;
+                err_ctx.feature = std.mem.trim(u8, right_str, whitespace);
+                return error.UndefinedVariable;
+            }
+
             // Check if left side is a simple path or a complex expression
             if (isSimplePath(left_str)) {
                 // Simple path like .revenue
                 return SimpleCondition{
                     .path = try parsePath(allocator, left_str),
                     .op = op_info.op,
-                    .value = try parseValue(right_str),
+                    .value = cmp_value,
                 };
             } else {
                 // Complex expression like (.revenue | tonumber)
@@ -931,7 +1040,7 @@ fn parseSimpleCondition(allocator: std.mem.Allocator, expr: []const u8, err_ctx:
                 return SimpleCondition{
                     .left_expr = left_expr,
                     .op = op_info.op,
-                    .value = try parseValue(right_str),
+                    .value = cmp_value,
                 };
             }
         }
@@ -989,6 +1098,19 @@ pub fn parseValue(str: []const u8) ParseError!CompareValue {
         return .{ .string = trimmed[1 .. trimmed.len - 1] };
     }
 
+    // Variable ($name)
+    if (trimmed.len >= 2 and trimmed[0] == '$' and isIdentifier(trimmed[1..])) {
+        return .{ .variable = trimmed[1..] };
+    }
+
+    // Number, kept as text when an i64/f64 would change it
+    if (number.literal(trimmed)) |num| switch (num) {
+        .integer => |int| return .{ .int = int },
+        .float => |float| return .{ .float = float },
+        .number_string => |text| return .{ .number = text },
+        else => unreachable,
+    };
+
     // Integer
     if (std.fmt.parseInt(i64, trimmed, 10)) |int| {
         return .{ .int = int };
@@ -1017,16 +1139,16 @@ fn parseStrFunc(allocator: std.mem.Allocator, expr: []const u8) ParseError!?Expr
         .{ .name = "ltrimstr", .kind = .ltrimstr },
         .{ .name = "rtrimstr", .kind = .rtrimstr },
         .{ .name = "has", .kind = .has },
-        .{ .name = "test", .kind = .@"test" },
     };
 
     for (funcs) |func| {
         if (std.mem.startsWith(u8, expr, func.name)) {
             const after_name = expr[func.name.len..];
+            // One string literal; computed arguments go to parseLibFunc
             if (after_name.len >= 4 and after_name[0] == '(' and
                 after_name[1] == '"' and
                 after_name[after_name.len - 1] == ')' and
-                after_name[after_name.len - 2] == '"')
+                (stringEnd(after_name, 1) orelse 0) == after_name.len - 2)
             {
                 const arg = after_name[2 .. after_name.len - 2];
                 return .{ .str_func = .{ .kind = func.kind, .arg = arg } };
@@ -1036,124 +1158,1243 @@ fn parseStrFunc(allocator: std.mem.Allocator, expr: []const u8) ParseError!?Expr
     return null;
 }
 
-fn parseByFunc(allocator: std.mem.Allocator, expr: []const u8) ParseError!?Expr {
-    const funcs = [_]struct { name: []const u8, kind: ByFuncKind }{
-        .{ .name = "group_by", .kind = .group_by },
-        .{ .name = "sort_by", .kind = .sort_by },
-        .{ .name = "unique_by", .kind = .unique_by },
-        .{ .name = "min_by", .kind = .min_by },
-        .{ .name = "max_by", .kind = .max_by },
+fn parseRegexFunc(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!?Expr {
+    const funcs = [_]struct { name: []const u8, kind: RegexKind, min_args: usize, max_args: usize }{
+        .{ .name = "test(", .kind = .@"test", .min_args = 1, .max_args = 2 },
+        .{ .name = "match(", .kind = .match, .min_args = 1, .max_args = 2 },
+        .{ .name = "capture(", .kind = .capture, .min_args = 1, .max_args = 2 },
+        .{ .name = "scan(", .kind = .scan, .min_args = 1, .max_args = 2 },
+        // split/1 is the literal string split
+        .{ .name = "split(", .kind = .split, .min_args = 2, .max_args = 2 },
+        .{ .name = "splits(", .kind = .splits, .min_args = 1, .max_args = 2 },
+        .{ .name = "sub(", .kind = .sub, .min_args = 2, .max_args = 3 },
+        .{ .name = "gsub(", .kind = .gsub, .min_args = 2, .max_args = 3 },
     };
 
     for (funcs) |func| {
-        if (std.mem.startsWith(u8, expr, func.name)) {
-            const after_name = expr[func.name.len..];
-            if (after_name.len >= 3 and after_name[0] == '(' and
-                after_name[1] == '.' and
-                after_name[after_name.len - 1] == ')')
-            {
-                const path_str = after_name[1 .. after_name.len - 1];
-                const path = try parsePath(allocator, path_str);
-                return .{ .by_func = .{ .kind = func.kind, .path = path } };
-            }
+        if (!std.mem.startsWith(u8, expr, func.name) or expr[expr.len - 1] != ')') continue;
+        const args = try splitTopLevel(allocator, expr[func.name.len .. expr.len - 1], ';');
+        if (args.len < func.min_args or args.len > func.max_args) {
+            if (func.kind == .split) return null;
+            return error.InvalidExpression;
+        }
+
+        const has_replacement = func.kind == .sub or func.kind == .gsub;
+        const pattern = try parseStringLiteral(allocator, args[0]) orelse return error.InvalidExpression;
+        const flags_idx: usize = if (has_replacement) 2 else 1;
+        const flags_str = if (args.len <= flags_idx or std.mem.eql(u8, args[flags_idx], "null"))
+            ""
+        else
+            try parseStringLiteral(allocator, args[flags_idx]) orelse return error.InvalidExpression;
+
+        err_ctx.expression = expr;
+        err_ctx.feature = pattern;
+        const flags = regex.Flags.parse(flags_str) orelse {
+            err_ctx.feature = flags_str;
+            err_ctx.suggestion = "Valid flags are g, i, x, n, p, s and l";
+            return error.InvalidRegex;
+        };
+        const compiled = try allocator.create(regex.Regex);
+        compiled.* = regex.Regex.compile(allocator, pattern, flags) catch |err| switch (err) {
+            error.OutOfMemory => return error.OutOfMemory,
+            error.InvalidPattern => {
+                err_ctx.suggestion = "Check for unbalanced (), [] or a quantifier with nothing to repeat";
+                return error.InvalidRegex;
+            },
+        };
+
+        var replacement: ?*Expr = null;
+        if (has_replacement) {
+            const repl = try allocator.create(Expr);
+            repl.* = try parseExprWithContext(allocator, args[1], err_ctx);
+            replacement = repl;
         }
+        return .{ .regex = .{ .kind = func.kind, .regex = compiled, .replacement = replacement } };
     }
     return null;
 }
 
-fn parseArrayLiteral(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!Expr {
-    const inner = std.mem.trim(u8, expr[1 .. expr.len - 1], whitespace);
-
-    if (inner.len == 0) {
-        return .{ .array = .{ .elements = &[_]*Expr{} } };
-    }
+/// Unquote a JSON-style string literal ("a\\nb"), or return null if `expr` is not one.
+fn parseStringLiteral(allocator: std.mem.Allocator, expr: []const u8) ParseError!?[]const u8 {
+    if (!isStringLiteral(expr)) return null;
+    return try unescapeString(allocator, expr[1 .. expr.len - 1]);
+}
 
-    var elements: std.ArrayListUnmanaged(*Expr) = .empty;
+/// Decode the escapes of a string body (without quotes).
+fn unescapeString(allocator: std.mem.Allocator, body: []const u8) ParseError![]const u8 {
+    if (std.mem.indexOfScalar(u8, body, '\\') == null) return body;
 
-    // Split by comma (respecting nesting)
-    var start: usize = 0;
-    var paren_depth: u32 = 0;
-    var brace_depth: u32 = 0;
-    var bracket_depth: u32 = 0;
+    var out: std.ArrayListUnmanaged(u8) = .empty;
     var i: usize = 0;
+    while (i < body.len) : (i += 1) {
+        if (body[i] != '\\' or i + 1 >= body.len) {
+            try out.append(allocator, body[i]);
+            continue;
+        }
+        i += 1;
+        switch (body[i]) {
+            'n' => try out.append(allocator, '\n'),
+            't' => try out.append(allocator, '\t'),
+            'r' => try out.append(allocator, '\r'),
+            'b' => try out.append(allocator, 0x08),
+            'f' => try out.append(allocator, 0x0c),
+            '"', '\\', '/' => try out.append(allocator, body[i]),
+            'u' => {
+                if (i + 4 >= body.len) return error.InvalidValue;
+                const code = std.fmt.parseInt(u21, body[i + 1 .. i + 5], 16) catch return error.InvalidValue;
+                var buf: [4]u8 = undefined;
+                const len = std.unicode.utf8Encode(code, &buf) catch return error.InvalidValue;
+                try out.appendSlice(allocator, buf[0..len]);
+                i += 4;
+            },
+            // Unknown escapes are kept so regex shorthands like "\d" still work
+            else => try out.appendSlice(allocator, body[i - 1 .. i + 1]),
+        }
+    }
+    return try out.toOwnedSlice(allocator);
+}
 
-    while (i <= inner.len) : (i += 1) {
-        const at_end = i == inner.len;
-        const c = if (at_end) ',' else inner[i];
+/// True if `expr` is exactly one string literal, e.g. "a \\(.b) c".
+fn isStringLiteral(expr: []const u8) bool {
+    if (expr.len < 2 or expr[0] != '"') return false;
+    const end = stringEnd(expr, 0) orelse return false;
+    return end == expr.len - 1;
+}
 
-        if (c == '(') paren_depth += 1;
-        if (c == ')') paren_depth -|= 1; // Saturating subtraction for safety
-        if (c == '{') brace_depth += 1;
-        if (c == '}') brace_depth -|= 1; // Saturating subtraction for safety
-        if (c == '[') bracket_depth += 1;
-        if (c == ']') bracket_depth -|= 1; // Saturating subtraction for safety
+/// Index of the quote closing the string that opens at `start`, skipping
+/// escapes and \\(...) interpolations, which may contain strings themselves.
+/// Returns null for an unterminated string.
+fn stringEnd(expr: []const u8, start: usize) ?usize {
+    var i = start + 1;
+    while (i < expr.len) : (i += 1) {
+        switch (expr[i]) {
+            '"' => return i,
+            '\\' => {
+                if (i + 1 < expr.len and expr[i + 1] == '(') {
+                    i = interpolationEnd(expr, i + 1) orelse return null;
+                } else {
+                    i += 1;
+                }
+            },
+            else => {},
+        }
+    }
+    return null;
+}
 
-        if ((c == ',' or at_end) and paren_depth == 0 and brace_depth == 0 and bracket_depth == 0) {
-            const elem_str = std.mem.trim(u8, inner[start..i], whitespace);
-            if (elem_str.len > 0) {
-                const elem = try allocator.create(Expr);
-                elem.* = try parseExprWithContext(allocator, elem_str, err_ctx);
-                try elements.append(allocator, elem);
+/// Index of the `)` closing the interpolation paren at `open`.
+fn interpolationEnd(expr: []const u8, open: usize) ?usize {
+    var depth: u32 = 0;
+    var i = open;
+    while (i < expr.len) : (i += 1) {
+        switch (expr[i]) {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if (depth == 0) return i;
+            },
+            '"' => i = stringEnd(expr, i) orelse return null,
+            else => {},
+        }
+    }
+    return null;
+}
+
+/// Split "text \\(expr) text" into literal runs and parsed expressions.
+/// Without interpolations or a format the result is a plain string literal.
+fn parseStringTemplate(allocator: std.mem.Allocator, literal: []const u8, format: ?FormatKind, err_ctx: *ErrorContext) ParseError!Expr {
+    const body = literal[1 .. literal.len - 1];
+    var parts: std.ArrayListUnmanaged(InterpPart) = .empty;
+    var text_start: usize = 0;
+    var i: usize = 0;
+    while (i < body.len) : (i += 1) {
+        if (body[i] != '\\') continue;
+        if (i + 1 < body.len and body[i + 1] == '(') {
+            const close = interpolationEnd(body, i + 1) orelse return error.InvalidExpression;
+            if (i > text_start) {
+                try parts.append(allocator, .{ .literal = try unescapeString(allocator, body[text_start..i]) });
             }
-            start = i + 1;
+            const inner = try allocator.create(Expr);
+            inner.* = try parseExprWithContext(allocator, body[i + 2 .. close], err_ctx);
+            try parts.append(allocator, .{ .expr = inner });
+            i = close;
+            text_start = close + 1;
+        } else {
+            i += 1; // escaped character
         }
     }
 
-    return .{ .array = .{ .elements = try elements.toOwnedSlice(allocator) } };
+    const tail = try unescapeString(allocator, body[text_start..]);
+    if (parts.items.len == 0 and format == null) {
+        return .{ .literal = .{ .string = tail } };
+    }
+    if (tail.len > 0) try parts.append(allocator, .{ .literal = tail });
+    return .{ .interp = .{ .parts = try parts.toOwnedSlice(allocator), .format = format orelse .text } };
 }
 
-// ============================================================================
-// Parser Tests
-// ============================================================================
+/// @name alone formats the input; @name "..." formats each interpolated value.
+fn parseFormat(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!Expr {
+    const formats = [_]struct { name: []const u8, kind: FormatKind }{
+        .{ .name = "text", .kind = .text },
+        .{ .name = "json", .kind = .json },
+        .{ .name = "csv", .kind = .csv },
+        .{ .name = "tsv", .kind = .tsv },
+        .{ .name = "html", .kind = .html },
+        .{ .name = "uri", .kind = .uri },
+        .{ .name = "sh", .kind = .sh },
+        .{ .name = "base64", .kind = .base64 },
+        .{ .name = "base64d", .kind = .base64d },
+        .{ .name = "base32", .kind = .base32 },
+        .{ .name = "base32d", .kind = .base32d },
+    };
 
-test "parse identity" {
-    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
-    defer arena.deinit();
-    var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), ".", &err_ctx);
-    try std.testing.expect(expr == .identity);
-}
+    var name_end: usize = 1;
+    while (name_end < expr.len and isIdentChar(expr[name_end])) : (name_end += 1) {}
+    const name = expr[1..name_end];
+    const rest = std.mem.trim(u8, expr[name_end..], whitespace);
 
-test "parse field" {
-    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
-    defer arena.deinit();
-    var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), ".name", &err_ctx);
-    try std.testing.expect(expr == .field);
-    try std.testing.expectEqualStrings("name", expr.field.name);
-}
+    for (formats) |f| {
+        if (!std.mem.eql(u8, name, f.name)) continue;
+        if (rest.len == 0) return .{ .format = f.kind };
+        if (isStringLiteral(rest)) return try parseStringTemplate(allocator, rest, f.kind, err_ctx);
+        return error.InvalidExpression;
+    }
 
-test "parse path" {
-    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
-    defer arena.deinit();
-    var err_ctx: ErrorContext = .{};
-    const expr = try parseExprWithContext(arena.allocator(), ".foo.bar.baz", &err_ctx);
-    try std.testing.expect(expr == .path);
-    try std.testing.expectEqual(@as(usize, 3), expr.path.parts.len);
+    err_ctx.expression = expr;
+    err_ctx.feature = expr[0..name_end];
+    err_ctx.suggestion = "Available formats: @text @json @csv @tsv @html @uri @sh @base64 @base64d @base32 @base32d";
+    return error.UnsupportedFeature;
 }
 
-test "parse depth limit prevents stack overflow" {
-    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
-    defer arena.deinit();
+fn parseGenerator(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!?Expr {
+    const funcs = [_]struct { name: []const u8, kind: GeneratorKind, min_args: usize, max_args: usize }{
+        .{ .name = "range", .kind = .range, .min_args = 1, .max_args = 3 },
+        .{ .name = "limit", .kind = .limit, .min_args = 2, .max_args = 2 },
+        .{ .name = "first", .kind = .first, .min_args = 1, .max_args = 1 },
+        .{ .name = "last", .kind = .last, .min_args = 1, .max_args = 1 },
+        .{ .name = "nth", .kind = .nth, .min_args = 1, .max_args = 2 },
+        .{ .name = "until", .kind = .until, .min_args = 2, .max_args = 2 },
+        .{ .name = "while", .kind = .@"while", .min_args = 2, .max_args = 2 },
+        .{ .name = "repeat", .kind = .repeat, .min_args = 1, .max_args = 1 },
+        .{ .name = "recurse", .kind = .recurse, .min_args = 0, .max_args = 2 },
+        .{ .name = "input", .kind = .input, .min_args = 0, .max_args = 0 },
+        .{ .name = "inputs", .kind = .inputs, .min_args = 0, .max_args = 0 },
+        .{ .name = "env", .kind = .env, .min_args = 0, .max_args = 0 },
+        .{ .name = "tostream", .kind = .tostream, .min_args = 0, .max_args = 0 },
+        .{ .name = "fromstream", .kind = .fromstream, .min_args = 1, .max_args = 1 },
+        .{ .name = "truncate_stream", .kind = .truncate_stream, .min_args = 1, .max_args = 1 },
+    };
 
-    // Create a deeply nested expression that exceeds MAX_PARSE_DEPTH
-    const depth = MAX_PARSE_DEPTH + 5;
-    var deeply_nested: [depth * 2 + 1]u8 = undefined;
-    var pos: usize = 0;
-    for (0..depth) |_| {
-        deeply_nested[pos] = '(';
-        pos += 1;
-    }
-    deeply_nested[pos] = '.';
-    pos += 1;
-    for (0..depth) |_| {
-        deeply_nested[pos] = ')';
-        pos += 1;
+    const syntax = (try splitCall(allocator, expr)) orelse return null;
+    for (funcs) |func| {
+        if (!std.mem.eql(u8, syntax.name, func.name)) continue;
+        if (syntax.args.len < func.min_args or syntax.args.len > func.max_args) return null;
+
+        const args = try allocator.alloc(*Expr, syntax.args.len);
+        for (syntax.args, args, 0..) |arg_str, *arg, idx| {
+            arg.* = try allocator.create(Expr);
+            // Conditions are comparisons (. < 100), which only parse as select(...)
+            const is_cond = switch (func.kind) {
+                .until, .@"while" => idx == 0,
+                .recurse => idx == 1,
+                else => false,
+            };
+            if (is_cond) {
+                arg.*.* = .{ .select = try parseCondition(allocator, arg_str, err_ctx) };
+            } else {
+                arg.*.* = try parseExprWithContext(allocator, arg_str, err_ctx);
+            }
+        }
+        if (func.kind == .input or func.kind == .inputs) err_ctx.input_calls += 1;
+        return .{ .generator = .{ .kind = func.kind, .args = args } };
     }
+    return null;
+}
 
-    var err_ctx: ErrorContext = .{};
-    const result = parseExprWithContext(arena.allocator(), deeply_nested[0..pos], &err_ctx);
-    try std.testing.expectError(error.UnsupportedFeature, result);
+fn parseDateFunc(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!?Expr {
+    const funcs = [_]struct { name: []const u8, kind: DateFuncKind, min_args: usize, max_args: usize }{
+        .{ .name = "gmtime", .kind = .gmtime, .min_args = 0, .max_args = 0 },
+        // zq has no time zones; local time is UTC
+        .{ .name = "localtime", .kind = .gmtime, .min_args = 0, .max_args = 0 },
+        .{ .name = "mktime", .kind = .mktime, .min_args = 0, .max_args = 0 },
+        .{ .name = "strptime", .kind = .strptime, .min_args = 1, .max_args = 1 },
+        .{ .name = "strftime", .kind = .strftime, .min_args = 1, .max_args = 2 },
+        .{ .name = "strflocaltime", .kind = .strftime, .min_args = 1, .max_args = 1 },
+        .{ .name = "todate", .kind = .todate, .min_args = 0, .max_args = 0 },
+        .{ .name = "todateiso8601", .kind = .todate, .min_args = 0, .max_args = 0 },
+        .{ .name = "date", .kind = .todate, .min_args = 0, .max_args = 0 },
+        .{ .name = "fromdate", .kind = .fromdate, .min_args = 0, .max_args = 0 },
+        .{ .name = "fromdateiso8601", .kind = .fromdate, .min_args = 0, .max_args = 0 },
+        .{ .name = "date_trunc", .kind = .date_trunc, .min_args = 1, .max_args = 1 },
+        .{ .name = "dateadd", .kind = .dateadd, .min_args = 2, .max_args = 2 },
+        .{ .name = "datesub", .kind = .datesub, .min_args = 2, .max_args = 2 },
+    };
+
+    const syntax = (try splitCall(allocator, expr)) orelse return null;
+    for (funcs) |func| {
+        if (!std.mem.eql(u8, syntax.name, func.name)) continue;
+        if (syntax.args.len < func.min_args or syntax.args.len > func.max_args) return null;
+
+        const args = try allocator.alloc(*Expr, syntax.args.len);
+        for (syntax.args, args) |arg_str, *arg| {
+            arg.* = try allocator.create(Expr);
+            arg.*.* = try parseExprWithContext(allocator, arg_str, err_ctx);
+        }
+        return .{ .date_func = .{ .kind = func.kind, .args = args } };
+    }
+    return null;
+}
+
+const Assignment = struct {
+    op: AssignOp,
+    start: usize, // first byte of the operator
+    end: usize, // one past the operator
+};
+
+/// Find the first top-level assignment operator, skipping ==, !=, <= and >=.
+fn findAssignment(expr: []const u8) ?Assignment {
+    var depth: u32 = 0;
+    var i: usize = 0;
+    while (i < expr.len) : (i += 1) {
+        switch (expr[i]) {
+            '"' => i = stringEnd(expr, i) orelse return null,
+            '(', '[', '{' => depth += 1,
+            ')', ']', '}' => depth -|= 1,
+            '=' => {
+                if (depth > 0) continue;
+                if (i + 1 < expr.len and expr[i + 1] == '=') {
+                    i += 1;
+                    continue;
+                }
+                const prev: u8 = if (i > 0) expr[i - 1] else 0;
+                const op: AssignOp = switch (prev) {
+                    '!', '<', '>' => continue,
+                    '|' => .update,
+                    '+' => .add,
+                    '-' => .sub,
+                    '*' => .mul,
+                    '%' => .mod,
+                    '/' => if (i >= 2 and expr[i - 2] == '/') .alt else .div,
+                    else => .set,
+                };
+                const op_len: usize = switch (op) {
+                    .set => 1,
+                    .alt => 3,
+                    else => 2,
+                };
+                return .{ .op = op, .start = i + 1 - op_len, .end = i + 1 };
+            },
+            else => {},
+        }
+    }
+    return null;
+}
+
+/// The paths DelExpr handles directly: .a, .a.b, .a[0], .[-1]
+fn isSimpleDelPath(path: []const u8) bool {
+    if (path.len == 0 or path[0] != '.') return false;
+    var base = path;
+    if (std.mem.lastIndexOfScalar(u8, path, '[')) |open| {
+        if (path[path.len - 1] != ']') return false;
+        _ = std.fmt.parseInt(i64, path[open + 1 .. path.len - 1], 10) catch return false;
+        base = path[0..open];
+    }
+    for (base) |c| {
+        if (c != '.' and !isIdentChar(c)) return false;
+    }
+    return true;
+}
+
+/// Plain field paths: ., .a, .a.b
+fn isFieldPath(path: []const u8) bool {
+    if (path.len == 0 or path[0] != '.') return false;
+    for (path) |c| {
+        if (c != '.' and !isIdentChar(c)) return false;
+    }
+    return true;
+}
+
+/// Index of a `]` that is followed by more path (`.a[].b`, `.a[0][1]`), if any.
+fn midPathBracket(path: []const u8) ?usize {
+    var i: usize = 0;
+    while (i + 1 < path.len) : (i += 1) {
+        if (path[i] == '"') return null; // quoted keys stay with the path parser
+        if (path[i] == ']' and (path[i + 1] == '.' or path[i + 1] == '[')) return i;
+    }
+    return null;
+}
+
+fn parsePathFunc(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!?Expr {
+    const funcs = [_]struct { name: []const u8, kind: PathFuncKind, min_args: usize, max_args: usize }{
+        .{ .name = "path", .kind = .path, .min_args = 1, .max_args = 1 },
+        .{ .name = "paths", .kind = .paths, .min_args = 0, .max_args = 1 },
+        .{ .name = "leaf_paths", .kind = .leaf_paths, .min_args = 0, .max_args = 0 },
+        .{ .name = "getpath", .kind = .getpath, .min_args = 1, .max_args = 1 },
+        .{ .name = "setpath", .kind = .setpath, .min_args = 2, .max_args = 2 },
+        .{ .name = "delpaths", .kind = .delpaths, .min_args = 1, .max_args = 1 },
+        .{ .name = "del", .kind = .del, .min_args = 1, .max_args = 1 },
+        .{ .name = "walk", .kind = .walk, .min_args = 1, .max_args = 1 },
+    };
+
+    const syntax = (try splitCall(allocator, expr)) orelse return null;
+
+    // with_entries(f) is sugar for to_entries | map(f) | from_entries
+    if (std.mem.eql(u8, syntax.name, "with_entries") and syntax.args.len == 1) {
+        const desugared = try std.fmt.allocPrint(allocator, "to_entries | map({s}) | from_entries", .{syntax.args[0]});
+        return try parseExprWithContext(allocator, desugared, err_ctx);
+    }
+    // map_values(f) is .[] |= f
+    if (std.mem.eql(u8, syntax.name, "map_values") and syntax.args.len == 1) {
+        const desugared = try std.fmt.allocPrint(allocator, ".[] |= ({s})", .{syntax.args[0]});
+        return try parseExprWithContext(allocator, desugared, err_ctx);
+    }
+
+    for (funcs) |func| {
+        if (!std.mem.eql(u8, syntax.name, func.name)) continue;
+        if (syntax.args.len < func.min_args or syntax.args.len > func.max_args) return null;
+
+        const args = try allocator.alloc(*Expr, syntax.args.len);
+        for (syntax.args, args) |arg_str, *arg| {
+            arg.* = try allocator.create(Expr);
+            if (func.kind == .paths) {
+                // paths(f) keeps the paths whose value passes f, like select(f)
+                arg.*.* = .{ .select = try parseCondition(allocator, arg_str, err_ctx) };
+            } else {
+                arg.*.* = try parseExprWithContext(allocator, arg_str, err_ctx);
+            }
+        }
+        return .{ .path_func = .{ .kind = func.kind, .args = args } };
+    }
+    return null;
+}
+
+fn parseByFunc(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!?Expr {
+    const funcs = [_]struct { name: []const u8, kind: ByFuncKind }{
+        .{ .name = "group_by", .kind = .group_by },
+        .{ .name = "sort_by", .kind = .sort_by },
+        .{ .name = "unique_by", .kind = .unique_by },
+        .{ .name = "min_by", .kind = .min_by },
+        .{ .name = "max_by", .kind = .max_by },
+    };
+
+    const syntax = (try splitCall(allocator, expr)) orelse return null;
+    if (syntax.args.len != 1) return null;
+    for (funcs) |func| {
+        if (!std.mem.eql(u8, syntax.name, func.name)) continue;
+        const arg = syntax.args[0];
+        if (isFieldPath(arg)) {
+            return .{ .by_func = .{ .kind = func.kind, .path = try parsePath(allocator, arg) } };
+        }
+        // Any other f is compared as [f], so sort_by(.a, .b) sorts by .a, then .b
+        const key = try allocator.create(Expr);
+        key.* = try parseArrayLiteral(allocator, try std.fmt.allocPrint(allocator, "[{s}]", .{arg}), err_ctx);
+        return .{ .by_func = .{ .kind = func.kind, .path = &.{}, .key = key } };
+    }
+    return null;
+}
+
+fn parseLibFunc(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!?Expr {
+    const funcs = [_]struct { name: []const u8, kind: LibFuncKind, min_args: usize, max_args: usize }{
+        .{ .name = "in", .kind = .in, .min_args = 1, .max_args = 1 },
+        .{ .name = "inside", .kind = .inside, .min_args = 1, .max_args = 1 },
+        .{ .name = "has", .kind = .has, .min_args = 1, .max_args = 1 },
+        .{ .name = "contains", .kind = .contains, .min_args = 1, .max_args = 1 },
+        .{ .name = "startswith", .kind = .startswith, .min_args = 1, .max_args = 1 },
+        .{ .name = "endswith", .kind = .endswith, .min_args = 1, .max_args = 1 },
+        .{ .name = "ltrimstr", .kind = .ltrimstr, .min_args = 1, .max_args = 1 },
+        .{ .name = "rtrimstr", .kind = .rtrimstr, .min_args = 1, .max_args = 1 },
+        .{ .name = "split", .kind = .split, .min_args = 1, .max_args = 1 },
+        .{ .name = "join", .kind = .join, .min_args = 1, .max_args = 1 },
+        .{ .name = "indices", .kind = .indices, .min_args = 1, .max_args = 1 },
+        .{ .name = "index", .kind = .index, .min_args = 1, .max_args = 1 },
+        .{ .name = "rindex", .kind = .rindex, .min_args = 1, .max_args = 1 },
+        .{ .name = "any", .kind = .any, .min_args = 1, .max_args = 2 },
+        .{ .name = "all", .kind = .all, .min_args = 1, .max_args = 2 },
+        .{ .name = "isempty", .kind = .isempty, .min_args = 1, .max_args = 1 },
+        .{ .name = "isvalid", .kind = .isvalid, .min_args = 1, .max_args = 1 },
+        .{ .name = "combinations", .kind = .combinations, .min_args = 1, .max_args = 1 },
+        .{ .name = "flatten", .kind = .flatten, .min_args = 1, .max_args = 1 },
+        .{ .name = "IN", .kind = .IN, .min_args = 1, .max_args = 2 },
+        .{ .name = "INDEX", .kind = .INDEX, .min_args = 1, .max_args = 2 },
+        .{ .name = "JOIN", .kind = .JOIN, .min_args = 2, .max_args = 4 },
+        .{ .name = "pick", .kind = .pick, .min_args = 1, .max_args = 1 },
+        .{ .name = "pow", .kind = .pow, .min_args = 2, .max_args = 2 },
+        .{ .name = "atan2", .kind = .atan2, .min_args = 2, .max_args = 2 },
+        .{ .name = "fmin", .kind = .fmin, .min_args = 2, .max_args = 2 },
+        .{ .name = "fmax", .kind = .fmax, .min_args = 2, .max_args = 2 },
+        .{ .name = "fmod", .kind = .fmod, .min_args = 2, .max_args = 2 },
+        .{ .name = "fdim", .kind = .fdim, .min_args = 2, .max_args = 2 },
+        .{ .name = "copysign", .kind = .copysign, .min_args = 2, .max_args = 2 },
+        .{ .name = "drem", .kind = .drem, .min_args = 2, .max_args = 2 },
+        .{ .name = "ldexp", .kind = .ldexp, .min_args = 2, .max_args = 2 },
+        .{ .name = "scalb", .kind = .scalb, .min_args = 2, .max_args = 2 },
+        .{ .name = "scalbln", .kind = .scalbln, .min_args = 2, .max_args = 2 },
+        .{ .name = "nextafter", .kind = .nextafter, .min_args = 2, .max_args = 2 },
+        .{ .name = "nexttoward", .kind = .nexttoward, .min_args = 2, .max_args = 2 },
+        .{ .name = "fma", .kind = .fma, .min_args = 3, .max_args = 3 },
+        .{ .name = "debug", .kind = .debug, .min_args = 1, .max_args = 1 },
+        .{ .name = "halt", .kind = .halt, .min_args = 0, .max_args = 0 },
+        .{ .name = "halt_error", .kind = .halt_error, .min_args = 0, .max_args = 1 },
+    };
+
+    const syntax = (try splitCall(allocator, expr)) orelse return null;
+    for (funcs) |func| {
+        if (!std.mem.eql(u8, syntax.name, func.name)) continue;
+        if (syntax.args.len < func.min_args or syntax.args.len > func.max_args) return null;
+
+        // pick(.a, .b.c): each path is its own argument, since a comma stream isn't a path
+        const arg_strs = if (func.kind == .pick) try splitTopLevel(allocator, syntax.args[0], ',') else syntax.args;
+        const args = try allocator.alloc(*Expr, arg_strs.len);
+        for (arg_strs, args, 0..) |arg_str, *arg, idx| {
+            arg.* = try allocator.create(Expr);
+            // The last argument of any/all is a condition, which only parses as select(...)
+            const is_cond = (func.kind == .any or func.kind == .all) and idx == arg_strs.len - 1;
+            if (is_cond) {
+                arg.*.* = .{ .select = try parseCondition(allocator, arg_str, err_ctx) };
+            } else {
+                arg.*.* = try parseStreamArg(allocator, arg_str, err_ctx);
+            }
+        }
+        if (func.kind == .debug) err_ctx.ordered_calls += 1;
+        return .{ .lib_func = .{ .kind = func.kind, .args = args } };
+    }
+    return null;
+}
+
+/// Parse a filter argument. A top-level comma makes it a stream, so
+/// IN(2, 3) runs `[2, 3] | .[]`.
+fn parseStreamArg(allocator: std.mem.Allocator, arg: []const u8, err_ctx: *ErrorContext) ParseError!Expr {
+    if ((try splitTopLevel(allocator, arg, ',')).len < 2) return parseExprWithContext(allocator, arg, err_ctx);
+    const items = try allocator.create(Expr);
+    items.* = try parseArrayLiteral(allocator, try std.fmt.allocPrint(allocator, "[{s}]", .{arg}), err_ctx);
+    const each = try allocator.create(Expr);
+    each.* = .{ .iterate = .{ .path = &.{} } };
+    return .{ .pipe = .{ .left = items, .right = each } };
+}
+
+fn parseArrayLiteral(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!Expr {
+    const inner = std.mem.trim(u8, expr[1 .. expr.len - 1], whitespace);
+
+    if (inner.len == 0) {
+        return .{ .array = .{ .elements = &[_]*Expr{} } };
+    }
+
+    var elements: std.ArrayListUnmanaged(*Expr) = .empty;
+
+    // Split by comma (respecting nesting)
+    var start: usize = 0;
+    var paren_depth: u32 = 0;
+    var brace_depth: u32 = 0;
+    var bracket_depth: u32 = 0;
+    var i: usize = 0;
+
+    while (i <= inner.len) : (i += 1) {
+        const at_end = i == inner.len;
+        const c = if (at_end) ',' else inner[i];
+
+        if (c == '"') {
+            i = stringEnd(inner, i) orelse inner.len - 1;
+            continue;
+        }
+        if (c == '(') paren_depth += 1;
+        if (c == ')') paren_depth -|= 1; // Saturating subtraction for safety
+        if (c == '{') brace_depth += 1;
+        if (c == '}') brace_depth -|= 1; // Saturating subtraction for safety
+        if (c == '[') bracket_depth += 1;
+        if (c == ']') bracket_depth -|= 1; // Saturating subtraction for safety
+
+        if ((c == ',' or at_end) and paren_depth == 0 and brace_depth == 0 and bracket_depth == 0) {
+            const elem_str = std.mem.trim(u8, inner[start..i], whitespace);
+            if (elem_str.len > 0) {
+                const elem = try allocator.create(Expr);
+                elem.* = try parseExprWithContext(allocator, elem_str, err_ctx);
+                try elements.append(allocator, elem);
+            }
+            start = i + 1;
+        }
+    }
+
+    return .{ .array = .{ .elements = try elements.toOwnedSlice(allocator) } };
+}
+
+// ============================================================================
+// Variables
+// ============================================================================
+
+fn isIdentChar(c: u8) bool {
+    return std.ascii.isAlphanumeric(c) or c == '_';
+}
+
+fn isIdentifier(s: []const u8) bool {
+    if (s.len == 0 or std.ascii.isDigit(s[0])) return false;
+    for (s) |c| {
+        if (!isIdentChar(c)) return false;
+    }
+    return true;
+}
+
+/// Find a top-level " as " whose tail is a complete destructuring pattern.
+/// Returns the position of the leading space, or null if `expr` is not a binding.
+fn findBindingAs(expr: []const u8) ?usize {
+    var depth: u32 = 0;
+    var in_string = false;
+    var found: ?usize = null;
+    var i: usize = 0;
+    while (i < expr.len) : (i += 1) {
+        const c = expr[i];
+        if (in_string) {
+            if (c == '\\') {
+                i += 1;
+            } else if (c == '"') {
+                in_string = false;
+            }
+            continue;
+        }
+        switch (c) {
+            '"' => in_string = true,
+            '(', '[', '{' => depth += 1,
+            ')', ']', '}' => depth -|= 1,
+            ' ' => if (depth == 0 and std.mem.startsWith(u8, expr[i..], " as ")) {
+                found = i;
+            },
+            else => {},
+        }
+    }
+
+    const pos = found orelse return null;
+    const pattern = std.mem.trim(u8, expr[pos + 4 ..], whitespace);
+    if (pattern.len < 2) return null;
+    return switch (pattern[0]) {
+        '$' => if (isIdentifier(pattern[1..])) pos else null,
+        '[' => if (pattern[pattern.len - 1] == ']') pos else null,
+        '{' => if (pattern[pattern.len - 1] == '}') pos else null,
+        else => null,
+    };
+}
+
+fn parseBinding(allocator: std.mem.Allocator, source_str: []const u8, pattern_str: []const u8, body_str: []const u8, err_ctx: *ErrorContext) ParseError!Expr {
+    // The source is evaluated in the enclosing scope
+    const source = try allocator.create(Expr);
+    source.* = try parseExprWithContext(allocator, source_str, err_ctx);
+
+    var names: std.ArrayListUnmanaged([]const u8) = .empty;
+    const pattern = try parsePattern(allocator, pattern_str, &names);
+
+    // Bound names are visible to every stage of the body
+    const frame = VarScope{ .parent = err_ctx.scope, .names = names.items };
+    err_ctx.scope = &frame;
+    defer err_ctx.scope = frame.parent;
+
+    const body = try allocator.create(Expr);
+    body.* = try parseExprWithContext(allocator, body_str, err_ctx);
+
+    return .{ .bind = .{ .source = source, .pattern = pattern, .body = body } };
+}
+
+/// Parse `$name`, `[$a, $b]` or `{key: $v, $name}` destructuring patterns.
+/// Every bound variable name is appended to `names`.
+pub fn parsePattern(allocator: std.mem.Allocator, expr: []const u8, names: *std.ArrayListUnmanaged([]const u8)) ParseError!Pattern {
+    const trimmed = std.mem.trim(u8, expr, whitespace);
+    if (trimmed.len < 2) return error.InvalidExpression;
+
+    if (trimmed[0] == '$') {
+        const name = trimmed[1..];
+        if (!isIdentifier(name)) return error.InvalidExpression;
+        try names.append(allocator, name);
+        return .{ .variable = name };
+    }
+
+    if (trimmed[0] == '[' and trimmed[trimmed.len - 1] == ']') {
+        const parts = try splitTopLevel(allocator, trimmed[1 .. trimmed.len - 1], ',');
+        var elements = try allocator.alloc(Pattern, parts.len);
+        for (parts, 0..) |part, idx| {
+            elements[idx] = try parsePattern(allocator, part, names);
+        }
+        return .{ .array = elements };
+    }
+
+    if (trimmed[0] == '{' and trimmed[trimmed.len - 1] == '}') {
+        const parts = try splitTopLevel(allocator, trimmed[1 .. trimmed.len - 1], ',');
+        var fields = try allocator.alloc(ObjectPatternField, parts.len);
+        for (parts, 0..) |part, idx| {
+            const colon_parts = try splitTopLevel(allocator, part, ':');
+            if (colon_parts.len == 1) {
+                // Shorthand: {$name} binds .name to $name
+                const field_pattern = try parsePattern(allocator, part, names);
+                if (field_pattern != .variable) return error.InvalidExpression;
+                fields[idx] = .{ .key = field_pattern.variable, .value = field_pattern };
+            } else if (colon_parts.len == 2) {
+                var key = colon_parts[0];
+                if (key.len >= 2 and key[0] == '"' and key[key.len - 1] == '"') {
+                    key = key[1 .. key.len - 1];
+                } else if (!isIdentifier(key)) {
+                    return error.InvalidExpression;
+                }
+                fields[idx] = .{ .key = key, .value = try parsePattern(allocator, colon_parts[1], names) };
+            } else {
+                return error.InvalidExpression;
+            }
+        }
+        return .{ .object = fields };
+    }
+
+    return error.InvalidExpression;
+}
+
+/// Split on `sep` outside of (), [], {} and string literals; parts are trimmed
+/// and empty parts are dropped.
+fn splitTopLevel(allocator: std.mem.Allocator, expr: []const u8, sep: u8) ParseError![][]const u8 {
+    var parts: std.ArrayListUnmanaged([]const u8) = .empty;
+    var depth: u32 = 0;
+    var start: usize = 0;
+    var i: usize = 0;
+    while (i <= expr.len) : (i += 1) {
+        const at_end = i == expr.len;
+        const c = if (at_end) sep else expr[i];
+        switch (c) {
+            '"' => {
+                i = stringEnd(expr, i) orelse expr.len - 1;
+                continue;
+            },
+            '(', '[', '{' => depth += 1,
+            ')', ']', '}' => depth -|= 1,
+            else => {},
+        }
+        if (c == sep and (depth == 0 or at_end)) {
+            const part = std.mem.trim(u8, expr[start..i], whitespace);
+            if (part.len > 0) try parts.append(allocator, part);
+            start = i + 1;
+        }
+    }
+    return parts.toOwnedSlice(allocator);
+}
+
+fn parseVariableRef(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!Expr {
+    var name_end: usize = 1;
+    while (name_end < expr.len and isIdentChar(expr[name_end])) : (name_end += 1) {}
+    const name = expr[1..name_end];
+    if (!isIdentifier(name)) return error.InvalidExpression;
+
+    const base: Expr = if (std.mem.eql(u8, name, "__loc__"))
+        .{ .loc = .{ .line = sourceLine(err_ctx, expr) } }
+    else if (std.mem.eql(u8, name, "ENV"))
+        .{ .generator = .{ .kind = .env } }
+    else blk: {
+        if (!VarScope.contains(err_ctx.scope, name)) {
+            err_ctx.expression = err_ctx.source;
+            err_ctx.feature = expr[0..name_end];
+            return error.UndefinedVariable;
+        }
+        break :blk .{ .variable = name };
+    };
+
+    // $name.field and $name[0] apply a path to the bound value
+    const rest = expr[name_end..];
+    if (rest.len == 0) return base;
+    const path_str = switch (rest[0]) {
+        '.' => rest,
+        '[' => try std.fmt.allocPrint(allocator, ".{s}", .{rest}),
+        else => return error.InvalidExpression,
+    };
+    const left = try allocator.create(Expr);
+    left.* = base;
+    const right = try allocator.create(Expr);
+    right.* = try parseExprWithContext(allocator, path_str, err_ctx);
+    return .{ .pipe = .{ .left = left, .right = right } };
+}
+
+/// Parse `reduce SOURCE as PATTERN (INIT; UPDATE)` and
+/// `foreach SOURCE as PATTERN (INIT; UPDATE[; EXTRACT])`.
+fn parseFold(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!Expr {
+    const is_reduce = std.mem.startsWith(u8, expr, "reduce ");
+    const keyword_len: usize = if (is_reduce) "reduce ".len else "foreach ".len;
+
+    // The argument list is the last top-level (...) group and must end the expression
+    var depth: u32 = 0;
+    var in_string = false;
+    var open_pos: ?usize = null;
+    var i: usize = keyword_len;
+    while (i < expr.len) : (i += 1) {
+        const c = expr[i];
+        if (in_string) {
+            if (c == '\\') {
+                i += 1;
+            } else if (c == '"') {
+                in_string = false;
+            }
+            continue;
+        }
+        switch (c) {
+            '"' => in_string = true,
+            '(' => {
+                if (depth == 0) open_pos = i;
+                depth += 1;
+            },
+            '[', '{' => depth += 1,
+            ')', ']', '}' => depth -|= 1,
+            else => {},
+        }
+    }
+    const open = open_pos orelse return error.InvalidExpression;
+    if (depth != 0 or expr[expr.len - 1] != ')') return error.InvalidExpression;
+
+    const head = std.mem.trimRight(u8, expr[keyword_len..open], whitespace);
+    const as_pos = findBindingAs(head) orelse return error.InvalidExpression;
+    const args = try splitTopLevel(allocator, expr[open + 1 .. expr.len - 1], ';');
+    if (args.len < 2 or args.len > 3 or (is_reduce and args.len != 2)) return error.InvalidExpression;
+
+    // Source and INIT see only the enclosing scope
+    const source = try allocator.create(Expr);
+    source.* = try parseExprWithContext(allocator, head[0..as_pos], err_ctx);
+    const init = try allocator.create(Expr);
+    init.* = try parseExprWithContext(allocator, args[0], err_ctx);
+
+    var names: std.ArrayListUnmanaged([]const u8) = .empty;
+    const pattern = try parsePattern(allocator, head[as_pos + 4 ..], &names);
+
+    const frame = VarScope{ .parent = err_ctx.scope, .names = names.items };
+    err_ctx.scope = &frame;
+    defer err_ctx.scope = frame.parent;
+
+    const update = try allocator.create(Expr);
+    update.* = try parseExprWithContext(allocator, args[1], err_ctx);
+
+    if (is_reduce) {
+        return .{ .reduce = .{ .source = source, .pattern = pattern, .init = init, .update = update } };
+    }
+
+    var extract: ?*Expr = null;
+    if (args.len == 3) {
+        const e = try allocator.create(Expr);
+        e.* = try parseExprWithContext(allocator, args[2], err_ctx);
+        extract = e;
+    }
+    return .{ .foreach = .{ .source = source, .pattern = pattern, .init = init, .update = update, .extract = extract } };
+}
+
+/// 1-based line of `fragment` within the outermost expression.
+fn sourceLine(err_ctx: *const ErrorContext, fragment: []const u8) i64 {
+    const base = @intFromPtr(err_ctx.source.ptr);
+    const pos = @intFromPtr(fragment.ptr);
+    if (err_ctx.source.len == 0 or pos < base or pos > base + err_ctx.source.len) return 1;
+    const before = err_ctx.source[0 .. pos - base];
+    return @as(i64, @intCast(std.mem.count(u8, before, "\n"))) + 1;
+}
+
+// ============================================================================
+// Functions
+// ============================================================================
+
+fn parseDefinition(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!Expr {
+    // Format: def name: body; rest  or  def name(f; $x): body; rest
+    const header_start = "def ".len;
+    var colon_pos: ?usize = null;
+    var paren_depth: u32 = 0;
+    var i: usize = header_start;
+    while (i < expr.len) : (i += 1) {
+        switch (expr[i]) {
+            '(' => paren_depth += 1,
+            ')' => paren_depth -|= 1,
+            ':' => if (paren_depth == 0) {
+                colon_pos = i;
+                break;
+            },
+            else => {},
+        }
+    }
+    const colon = colon_pos orelse return error.InvalidExpression;
+    const body_end = findDefEnd(expr, colon + 1) orelse return error.InvalidExpression;
+    const header = std.mem.trim(u8, expr[header_start..colon], whitespace);
+    const body_str = expr[colon + 1 .. body_end];
+    const rest_str = std.mem.trim(u8, expr[body_end + 1 ..], whitespace);
+    if (rest_str.len == 0) return error.InvalidExpression;
+
+    var name = header;
+    var params: []const FuncParam = &.{};
+    if (std.mem.indexOfScalar(u8, header, '(')) |open| {
+        if (header[header.len - 1] != ')') return error.InvalidExpression;
+        name = std.mem.trimRight(u8, header[0..open], whitespace);
+        const parts = try splitTopLevel(allocator, header[open + 1 .. header.len - 1], ';');
+        const list = try allocator.alloc(FuncParam, parts.len);
+        for (parts, list) |part, *param| {
+            const is_value = part[0] == '$';
+            const param_name = if (is_value) part[1..] else part;
+            if (!isIdentifier(param_name)) return error.InvalidExpression;
+            param.* = .{ .name = param_name, .is_value = is_value };
+        }
+        params = list;
+    }
+    if (!isIdentifier(name)) return error.InvalidExpression;
+
+    const saved_funcs = err_ctx.funcs;
+    const saved_scope = err_ctx.scope;
+    defer {
+        err_ctx.funcs = saved_funcs;
+        err_ctx.scope = saved_scope;
+    }
+
+    // The function is visible in its own body (recursion) and in the rest
+    const self_frame = FuncScope{ .parent = saved_funcs, .name = name, .arity = params.len };
+
+    // Every parameter is callable as a filter; `$x` parameters are also variables
+    const param_frames = try allocator.alloc(FuncScope, params.len);
+    var value_names: std.ArrayListUnmanaged([]const u8) = .empty;
+    var funcs: *const FuncScope = &self_frame;
+    for (params, param_frames) |param, *frame| {
+        frame.* = .{ .parent = funcs, .name = param.name, .arity = 0 };
+        funcs = frame;
+        if (param.is_value) try value_names.append(allocator, param.name);
+    }
+    const var_frame = VarScope{ .parent = saved_scope, .names = value_names.items };
+
+    err_ctx.funcs = funcs;
+    err_ctx.scope = &var_frame;
+    const body = try allocator.create(Expr);
+    body.* = try parseExprWithContext(allocator, body_str, err_ctx);
+
+    err_ctx.funcs = &self_frame;
+    err_ctx.scope = saved_scope;
+    const rest = try allocator.create(Expr);
+    rest.* = try parseExprWithContext(allocator, rest_str, err_ctx);
+
+    const def = try allocator.create(FuncDef);
+    def.* = .{ .name = name, .params = params, .body = body };
+    return .{ .define = .{ .def = def, .rest = rest } };
+}
+
+/// Find the `;` that ends a def body starting at `start`, skipping brackets,
+/// strings and the bodies of nested defs.
+fn findDefEnd(expr: []const u8, start: usize) ?usize {
+    var depth: u32 = 0;
+    var nested_defs: u32 = 0;
+    var in_string = false;
+    var i = start;
+    while (i < expr.len) : (i += 1) {
+        const c = expr[i];
+        if (in_string) {
+            if (c == '\\') {
+                i += 1;
+            } else if (c == '"') {
+                in_string = false;
+            }
+            continue;
+        }
+        switch (c) {
+            '"' => in_string = true,
+            '(', '[', '{' => depth += 1,
+            ')', ']', '}' => depth -|= 1,
+            ';' => if (depth == 0) {
+                if (nested_defs == 0) return i;
+                nested_defs -= 1;
+            },
+            'd' => if (depth == 0 and std.mem.startsWith(u8, expr[i..], "def ") and (i == 0 or !isIdentChar(expr[i - 1]))) {
+                nested_defs += 1;
+            },
+            else => {},
+        }
+    }
+    return null;
+}
+
+/// Parse a call to a user-defined function in scope. Returns null when no
+/// definition matches the name and arity, so builtins are tried next.
+fn parseCall(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!?Expr {
+    const syntax = (try splitCall(allocator, expr)) orelse return null;
+    if (!FuncScope.contains(err_ctx.funcs, syntax.name, syntax.args.len)) return null;
+
+    // Arguments are closures over the caller's scope
+    const args = try allocator.alloc(*Expr, syntax.args.len);
+    for (syntax.args, args) |arg_str, *arg| {
+        arg.* = try allocator.create(Expr);
+        arg.*.* = try parseExprWithContext(allocator, arg_str, err_ctx);
+    }
+    return .{ .call = .{ .name = syntax.name, .args = args } };
+}
+
+const CallSyntax = struct {
+    name: []const u8,
+    args: []const []const u8,
+};
+
+/// Split `name` or `name(a; b)` into the name and argument strings, or return
+/// null if `expr` is not shaped like a call.
+fn splitCall(allocator: std.mem.Allocator, expr: []const u8) ParseError!?CallSyntax {
+    var name_end: usize = 0;
+    while (name_end < expr.len and isIdentChar(expr[name_end])) : (name_end += 1) {}
+    const name = expr[0..name_end];
+    if (!isIdentifier(name)) return null;
+    if (name_end == expr.len) return .{ .name = name, .args = &.{} };
+
+    if (expr[name_end] != '(' or expr[expr.len - 1] != ')') return null;
+    // The argument list must close at the very end: f(.a), not f(.a).b
+    var depth: u32 = 0;
+    var i = name_end;
+    while (i < expr.len) : (i += 1) {
+        switch (expr[i]) {
+            '"' => i = stringEnd(expr, i) orelse return null,
+            '(' => depth += 1,
+            ')' => {
+                depth -|= 1;
+                if (depth == 0 and i != expr.len - 1) return null;
+            },
+            else => {},
+        }
+    }
+    return .{ .name = name, .args = try splitTopLevel(allocator, expr[name_end + 1 .. expr.len - 1], ';') };
+}
+
+/// Splice the defs of `include "name";` in front of the rest of the expression.
+fn parseInclude(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!Expr {
+    const after = std.mem.trimLeft(u8, expr["include ".len..], whitespace);
+    if (after.len < 2 or after[0] != '"') return error.InvalidExpression;
+    const close = std.mem.indexOfScalarPos(u8, after, 1, '"') orelse return error.InvalidExpression;
+    const name = after[1..close];
+    const tail = std.mem.trimLeft(u8, after[close + 1 ..], whitespace);
+    if (tail.len == 0 or tail[0] != ';') return error.InvalidExpression;
+
+    const module = try loadModule(allocator, name, err_ctx);
+    const combined = try std.mem.concat(allocator, u8, &.{ module, " ", tail[1..] });
+    return parseExprWithContext(allocator, combined, err_ctx);
+}
+
+/// Read NAME.jq from the first -L directory that has it (default ~/.jq).
+fn loadModule(allocator: std.mem.Allocator, name: []const u8, err_ctx: *ErrorContext) ParseError![]const u8 {
+    var default_dirs: [1][]const u8 = undefined;
+    var dirs = err_ctx.lib_dirs;
+    if (dirs.len == 0) {
+        const home = std.posix.getenv("HOME") orelse ".";
+        default_dirs[0] = try std.fmt.allocPrint(allocator, "{s}/.jq", .{home});
+        dirs = &default_dirs;
+    }
+
+    for (dirs) |dir| {
+        const path = try std.fmt.allocPrint(allocator, "{s}/{s}.jq", .{ dir, name });
+        const file = std.fs.cwd().openFile(path, .{}) catch continue;
+        defer file.close();
+        const text = file.readToEndAlloc(allocator, 1024 * 1024) catch |err| switch (err) {
+            error.OutOfMemory => return error.OutOfMemory,
+            else => continue,
+        };
+        return try normalizeModuleSource(allocator, text);
+    }
+
+    err_ctx.expression = err_ctx.source;
+    err_ctx.feature = name;
+    return error.ModuleNotFound;
+}
+
+/// Drop `#` comments and fold line breaks to spaces (outside string literals)
+/// so multi-line module files parse like a single-line expression.
+fn normalizeModuleSource(allocator: std.mem.Allocator, text: []const u8) ParseError![]const u8 {
+    var out: std.ArrayListUnmanaged(u8) = .empty;
+    var in_string = false;
+    var in_comment = false;
+    var i: usize = 0;
+    while (i < text.len) : (i += 1) {
+        const c = text[i];
+        if (in_comment) {
+            if (c == '\n') {
+                in_comment = false;
+                try out.append(allocator, ' ');
+            }
+            continue;
+        }
+        if (in_string) {
+            if (c == '\\' and i + 1 < text.len) {
+                try out.appendSlice(allocator, text[i .. i + 2]);
+                i += 1;
+                continue;
+            }
+            if (c == '"') in_string = false;
+            try out.append(allocator, c);
+            continue;
+        }
+        switch (c) {
+            '"' => {
+                in_string = true;
+                try out.append(allocator, c);
+            },
+            '#' => in_comment = true,
+            '\n', '\r', '\t' => try out.append(allocator, ' '),
+            else => try out.append(allocator, c),
+        }
+    }
+    return out.toOwnedSlice(allocator);
+}
+
+// ============================================================================
+// Errors and Labels
+// ============================================================================
+
+/// True if `word` starts at `expr[i]` as a whole keyword (not .word, $word or part of a name).
+fn keywordAt(expr: []const u8, i: usize, word: []const u8) bool {
+    if (!std.mem.startsWith(u8, expr[i..], word)) return false;
+    if (i > 0 and (isIdentChar(expr[i - 1]) or expr[i - 1] == '.' or expr[i - 1] == '$')) return false;
+    const end = i + word.len;
+    return end == expr.len or !isIdentChar(expr[end]);
+}
+
+/// Position of the top-level `catch` that closes this `try`, skipping nested try/catch pairs.
+fn findCatch(expr: []const u8) ?usize {
+    var depth: u32 = 0;
+    var nested: u32 = 0;
+    var i: usize = 0;
+    while (i < expr.len) : (i += 1) {
+        switch (expr[i]) {
+            '"' => i = stringEnd(expr, i) orelse return null,
+            '(', '[', '{' => depth += 1,
+            ')', ']', '}' => depth -|= 1,
+            else => {
+                if (depth > 0) continue;
+                if (keywordAt(expr, i, "try")) {
+                    nested += 1;
+                } else if (keywordAt(expr, i, "catch")) {
+                    if (nested == 0) return i;
+                    nested -= 1;
+                }
+            },
+        }
+    }
+    return null;
+}
+
+/// Position of the first top-level `|` that isn't part of `|=`.
+fn findPipe(expr: []const u8) ?usize {
+    var depth: u32 = 0;
+    var i: usize = 0;
+    while (i < expr.len) : (i += 1) {
+        switch (expr[i]) {
+            '"' => i = stringEnd(expr, i) orelse return null,
+            '(', '[', '{' => depth += 1,
+            ')', ']', '}' => depth -|= 1,
+            '|' => if (depth == 0 and (i + 1 == expr.len or expr[i + 1] != '=')) return i,
+            else => {},
+        }
+    }
+    return null;
+}
+
+/// Parse `try BODY` or `try BODY catch HANDLER`. The body runs up to its
+/// `catch`; the handler ends at the next top-level `|`, which pipes the whole
+/// try expression onward: `try .a catch "x" | length`.
+fn parseTry(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!Expr {
+    const rest = expr["try ".len..];
+    const catch_pos = findCatch(rest);
+
+    const body_str = std.mem.trim(u8, rest[0 .. catch_pos orelse rest.len], whitespace);
+    if (body_str.len == 0) return error.InvalidExpression;
+    const body = try allocator.create(Expr);
+    body.* = try parseExprWithContext(allocator, body_str, err_ctx);
+
+    const pos = catch_pos orelse return .{ .try_catch = .{ .body = body } };
+    const tail = rest[pos + "catch".len ..];
+    const pipe_pos = findPipe(tail);
+    const handler_str = std.mem.trim(u8, tail[0 .. pipe_pos orelse tail.len], whitespace);
+    if (handler_str.len == 0) return error.InvalidExpression;
+    const handler = try allocator.create(Expr);
+    handler.* = try parseExprWithContext(allocator, handler_str, err_ctx);
+
+    const try_expr: Expr = .{ .try_catch = .{ .body = body, .handler = handler } };
+    const p = pipe_pos orelse return try_expr;
+    const left = try allocator.create(Expr);
+    left.* = try_expr;
+    const right = try allocator.create(Expr);
+    right.* = try parseExprWithContext(allocator, tail[p + 1 ..], err_ctx);
+    return .{ .pipe = .{ .left = left, .right = right } };
+}
+
+/// Parse `label $name | BODY`. The label is scoped like a variable so that
+/// `break $name` outside of BODY is rejected at parse time.
+fn parseLabel(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!Expr {
+    const after = std.mem.trimLeft(u8, expr["label ".len..], whitespace);
+    if (after.len < 2 or after[0] != '$') return error.InvalidExpression;
+    var name_end: usize = 1;
+    while (name_end < after.len and isIdentChar(after[name_end])) : (name_end += 1) {}
+    if (!isIdentifier(after[1..name_end])) return error.InvalidExpression;
+
+    const body_str = std.mem.trimLeft(u8, after[name_end..], whitespace);
+    if (body_str.len < 2 or body_str[0] != '|') return error.InvalidExpression;
+
+    const name = try labelName(allocator, after[1..name_end]);
+    const names = [_][]const u8{name};
+    const frame = VarScope{ .parent = err_ctx.scope, .names = &names };
+    err_ctx.scope = &frame;
+    defer err_ctx.scope = frame.parent;
+
+    const body = try allocator.create(Expr);
+    body.* = try parseExprWithContext(allocator, body_str[1..], err_ctx);
+    return .{ .label = .{ .name = name, .body = body } };
+}
+
+fn parseBreak(allocator: std.mem.Allocator, expr: []const u8, err_ctx: *ErrorContext) ParseError!Expr {
+    const var_str = std.mem.trim(u8, expr["break ".len..], whitespace);
+    if (var_str.len < 2 or var_str[0] != '$' or !isIdentifier(var_str[1..])) return error.InvalidExpression;
+    const name = try labelName(allocator, var_str[1..]);
+    if (!VarScope.contains(err_ctx.scope, name)) {
+        err_ctx.expression = err_ctx.source;
+        err_ctx.feature = try std.fmt.allocPrint(allocator, "${s}", .{name});
+        return error.UndefinedVariable;
+    }
+    return .{ .break_label = name };
+}
+
+/// Labels live in the variable scope under a name no `$var` can spell (jq's convention).
+fn labelName(allocator: std.mem.Allocator, name: []const u8) ParseError![]const u8 {
+    return std.fmt.allocPrint(allocator, "*label-{s}", .{name});
+}
+
+// ============================================================================
+// Parser Tests
+// ============================================================================
+
+test "parse identity" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), ".", &err_ctx);
+    try std.testing.expect(expr == .identity);
+}
+
+test "parse field" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), ".name", &err_ctx);
+    try std.testing.expect(expr == .field);
+    try std.testing.expectEqualStrings("name", expr.field.name);
+}
+
+test "parse path" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), ".foo.bar.baz", &err_ctx);
+    try std.testing.expect(expr == .path);
+    try std.testing.expectEqual(@as(usize, 3), expr.path.parts.len);
+}
+
+test "parse depth limit prevents stack overflow" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    // Create a deeply nested expression that exceeds MAX_PARSE_DEPTH
+    const depth = MAX_PARSE_DEPTH + 5;
+    var deeply_nested: [depth * 2 + 1]u8 = undefined;
+    var pos: usize = 0;
+    for (0..depth) |_| {
+        deeply_nested[pos] = '(';
+        pos += 1;
+    }
+    deeply_nested[pos] = '.';
+    pos += 1;
+    for (0..depth) |_| {
+        deeply_nested[pos] = ')';
+        pos += 1;
+    }
+
+    var err_ctx: ErrorContext = .{};
+    const result = parseExprWithContext(arena.allocator(), deeply_nested[0..pos], &err_ctx);
+    try std.testing.expectError(error.UnsupportedFeature, result);
     try std.testing.expectEqualStrings("expression nesting too deep", err_ctx.feature);
 }
 
@@ -1458,7 +2699,376 @@ test "parse test" {
     defer arena.deinit();
     var err_ctx: ErrorContext = .{};
     const expr = try parseExprWithContext(arena.allocator(), "test(\"^hello\")", &err_ctx);
-    try std.testing.expect(expr == .str_func);
-    try std.testing.expect(expr.str_func.kind == .@"test");
-    try std.testing.expectEqualStrings("^hello", expr.str_func.arg);
+    try std.testing.expect(expr == .regex);
+    try std.testing.expect(expr.regex.kind == .@"test");
+    try std.testing.expect(expr.regex.replacement == null);
+}
+
+test "parse regex functions" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+    const with_flags = try parseExprWithContext(arena.allocator(), "test(\"err\"; \"i\")", &err_ctx);
+    try std.testing.expect(with_flags.regex.regex.flags.ignore_case);
+
+    const gsub = try parseExprWithContext(arena.allocator(), "gsub(\"\\\\s+\"; \" \")", &err_ctx);
+    try std.testing.expect(gsub.regex.kind == .gsub);
+    try std.testing.expect(gsub.regex.replacement.?.* == .literal);
+
+    // split/1 stays the literal string split; split/2 takes regex flags
+    const plain = try parseExprWithContext(arena.allocator(), "split(\", \")", &err_ctx);
+    try std.testing.expect(plain == .str_func);
+    const re_split = try parseExprWithContext(arena.allocator(), "split(\", *\"; null)", &err_ctx);
+    try std.testing.expect(re_split == .regex);
+    try std.testing.expect(re_split.regex.kind == .split);
+}
+
+test "parse string interpolation" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+
+    const expr = try parseExprWithContext(arena.allocator(), "\"\\(.user) did \\(.action | ascii_upcase)\\n\"", &err_ctx);
+    try std.testing.expect(expr == .interp);
+    const parts = expr.interp.parts;
+    try std.testing.expectEqual(@as(usize, 4), parts.len);
+    try std.testing.expect(parts[0] == .expr);
+    try std.testing.expectEqualStrings(" did ", parts[1].literal);
+    try std.testing.expect(parts[2].expr.* == .pipe);
+    try std.testing.expectEqualStrings("\n", parts[3].literal);
+
+    // Operators inside strings and nested strings don't split the expression
+    const nested = try parseExprWithContext(arena.allocator(), "\"a | b\" + \"\\(\"x\" + .y) - z\"", &err_ctx);
+    try std.testing.expect(nested == .arithmetic);
+    try std.testing.expectEqualStrings("a | b", nested.arithmetic.left.literal.string);
+    try std.testing.expect(nested.arithmetic.right.* == .interp);
+
+    try std.testing.expectError(error.InvalidExpression, parseExprWithContext(arena.allocator(), "\"\\(.a\"", &err_ctx));
+}
+
+test "parse format strings" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+
+    const csv = try parseExprWithContext(arena.allocator(), "[.a, .b] | @csv", &err_ctx);
+    try std.testing.expect(csv.pipe.right.* == .format);
+    try std.testing.expect(csv.pipe.right.format == .csv);
+
+    const sh = try parseExprWithContext(arena.allocator(), "@sh \"rm \\(.file)\"", &err_ctx);
+    try std.testing.expect(sh == .interp);
+    try std.testing.expect(sh.interp.format == .sh);
+
+    // A prefixed string without interpolations is still a template
+    const plain = try parseExprWithContext(arena.allocator(), "@html \"<b>\"", &err_ctx);
+    try std.testing.expect(plain == .interp);
+
+    try std.testing.expectError(error.UnsupportedFeature, parseExprWithContext(arena.allocator(), "@yaml", &err_ctx));
+    try std.testing.expectEqualStrings("@yaml", err_ctx.feature);
+}
+
+test "parse assignment operators" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+
+    const cases = [_]struct { expr: []const u8, op: AssignOp }{
+        .{ .expr = ".a |= . + 1", .op = .update },
+        .{ .expr = ".a.b = 5", .op = .set },
+        .{ .expr = ".count += 1", .op = .add },
+        .{ .expr = ".n -= 2", .op = .sub },
+        .{ .expr = ".n *= 2", .op = .mul },
+        .{ .expr = ".n /= 2", .op = .div },
+        .{ .expr = ".n %= 2", .op = .mod },
+        .{ .expr = ".name //= \"anon\"", .op = .alt },
+    };
+    for (cases) |case| {
+        const expr = try parseExprWithContext(arena.allocator(), case.expr, &err_ctx);
+        try std.testing.expect(expr == .assign);
+        try std.testing.expectEqual(case.op, expr.assign.op);
+    }
+
+    // | and // bind looser than assignment; comparisons are not assignments
+    const piped = try parseExprWithContext(arena.allocator(), ".a |= . + 1 | .a", &err_ctx);
+    try std.testing.expect(piped == .pipe);
+    try std.testing.expect(piped.pipe.left.* == .assign);
+    const alt = try parseExprWithContext(arena.allocator(), ".a //= 1 // 2", &err_ctx);
+    try std.testing.expect(alt == .alternative);
+    try std.testing.expect(alt.alternative.primary.* == .assign);
+    const cmp = try parseExprWithContext(arena.allocator(), "if .a == 1 then .b else .c end", &err_ctx);
+    try std.testing.expect(cmp == .conditional);
+}
+
+test "parse path functions" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+
+    const path = try parseExprWithContext(arena.allocator(), "path(.items[].price)", &err_ctx);
+    try std.testing.expect(path.path_func.kind == .path);
+    // Mid-path iteration becomes a pipe
+    try std.testing.expect(path.path_func.args[0].* == .pipe);
+
+    const paths = try parseExprWithContext(arena.allocator(), "paths(type == \"number\")", &err_ctx);
+    try std.testing.expect(paths.path_func.kind == .paths);
+    try std.testing.expect(paths.path_func.args[0].* == .select);
+
+    const setpath = try parseExprWithContext(arena.allocator(), "setpath([\"a\", 0]; 1)", &err_ctx);
+    try std.testing.expectEqual(@as(usize, 2), setpath.path_func.args.len);
+
+    // Simple del() keeps DelExpr; anything else is a path expression
+    try std.testing.expect((try parseExprWithContext(arena.allocator(), "del(.a.b)", &err_ctx)) == .del);
+    const del = try parseExprWithContext(arena.allocator(), "del(.[] | select(. == 2))", &err_ctx);
+    try std.testing.expect(del.path_func.kind == .del);
+
+    const with_entries = try parseExprWithContext(arena.allocator(), "with_entries(.value += 1)", &err_ctx);
+    try std.testing.expect(with_entries == .pipe);
+}
+
+test "parse invalid regex" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+    try std.testing.expectError(error.InvalidRegex, parseExprWithContext(arena.allocator(), "test(\"(unclosed\")", &err_ctx));
+    try std.testing.expectEqualStrings("(unclosed", err_ctx.feature);
+    try std.testing.expectError(error.InvalidRegex, parseExprWithContext(arena.allocator(), "test(\"a\"; \"q\")", &err_ctx));
+}
+
+test "parse variable binding" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), ".price as $p | .items[] | .qty * $p", &err_ctx);
+    try std.testing.expect(expr == .bind);
+    try std.testing.expect(expr.bind.pattern == .variable);
+    try std.testing.expectEqualStrings("p", expr.bind.pattern.variable);
+    try std.testing.expect(expr.bind.body.* == .pipe);
+}
+
+test "parse destructuring pattern" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), ". as {a: $x, \"b\": [$y, $z], $c} | [$x, $y, $z, $c]", &err_ctx);
+    try std.testing.expect(expr == .bind);
+    const fields = expr.bind.pattern.object;
+    try std.testing.expectEqual(@as(usize, 3), fields.len);
+    try std.testing.expectEqualStrings("a", fields[0].key);
+    try std.testing.expectEqualStrings("x", fields[0].value.variable);
+    try std.testing.expectEqual(@as(usize, 2), fields[1].value.array.len);
+    try std.testing.expectEqualStrings("c", fields[2].key);
+}
+
+test "parse undefined variable" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+    const result = parseExprWithContext(arena.allocator(), ".x | $missing", &err_ctx);
+    try std.testing.expectError(error.UndefinedVariable, result);
+    try std.testing.expectEqualStrings("$missing", err_ctx.feature);
+}
+
+test "parse variable out of scope in source" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+    const result = parseExprWithContext(arena.allocator(), "$x as $x | .", &err_ctx);
+    try std.testing.expectError(error.UndefinedVariable, result);
+}
+
+test "parse __loc__" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), ".a |\n$__loc__", &err_ctx);
+    try std.testing.expect(expr == .pipe);
+    try std.testing.expect(expr.pipe.right.* == .loc);
+    try std.testing.expectEqual(@as(i64, 2), expr.pipe.right.loc.line);
+}
+
+test "parse reduce" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "reduce .[] as $x (0; . + $x)", &err_ctx);
+    try std.testing.expect(expr == .reduce);
+    try std.testing.expect(expr.reduce.source.* == .iterate);
+    try std.testing.expectEqualStrings("x", expr.reduce.pattern.variable);
+    try std.testing.expect(expr.reduce.update.* == .arithmetic);
+}
+
+test "parse foreach with and without extract" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+    const three = try parseExprWithContext(arena.allocator(), "foreach .[] as [$k, $v] (0; . + $v; [$k, .])", &err_ctx);
+    try std.testing.expect(three == .foreach);
+    try std.testing.expect(three.foreach.pattern == .array);
+    try std.testing.expect(three.foreach.extract != null);
+
+    const two = try parseExprWithContext(arena.allocator(), "foreach .[] as $x (0; . + $x)", &err_ctx);
+    try std.testing.expect(two == .foreach);
+    try std.testing.expect(two.foreach.extract == null);
+}
+
+test "parse reduce errors" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+    try std.testing.expectError(error.InvalidExpression, parseExprWithContext(arena.allocator(), "reduce .[] as $x (0)", &err_ctx));
+    try std.testing.expectError(error.InvalidExpression, parseExprWithContext(arena.allocator(), "reduce .[] (0; . + 1)", &err_ctx));
+    // The loop variable is not visible in INIT
+    err_ctx = .{};
+    try std.testing.expectError(error.UndefinedVariable, parseExprWithContext(arena.allocator(), "reduce .[] as $x ($x; .)", &err_ctx));
+}
+
+test "parse def and call" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+    const expr = try parseExprWithContext(arena.allocator(), "def inc(f; $by): f + $by; .a | inc(.b; 2)", &err_ctx);
+    try std.testing.expect(expr == .define);
+    try std.testing.expectEqualStrings("inc", expr.define.def.name);
+    try std.testing.expectEqual(@as(usize, 2), expr.define.def.params.len);
+    try std.testing.expect(!expr.define.def.params[0].is_value);
+    try std.testing.expect(expr.define.def.params[1].is_value);
+    try std.testing.expect(expr.define.rest.* == .pipe);
+    try std.testing.expect(expr.define.rest.pipe.right.* == .call);
+    // Definitions do not leak out of the expression that declared them
+    try std.testing.expect(err_ctx.funcs == null);
+}
+
+test "parse def scoping" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+    // A call with the wrong arity does not resolve to the def
+    try std.testing.expectError(error.InvalidExpression, parseExprWithContext(arena.allocator(), "def f(g): g; f", &err_ctx));
+    // Parameters are only visible in the body
+    try std.testing.expectError(error.UndefinedVariable, parseExprWithContext(arena.allocator(), "def f($x): $x; $x", &err_ctx));
+    // A def needs a body terminator and an expression to apply
+    try std.testing.expectError(error.InvalidExpression, parseExprWithContext(arena.allocator(), "def f: .", &err_ctx));
+    try std.testing.expectError(error.InvalidExpression, parseExprWithContext(arena.allocator(), "def f: .;", &err_ctx));
+}
+
+test "parse include" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    try tmp.dir.writeFile(.{ .sub_path = "team.jq", .data = "# team helpers\ndef double: . * 2;\ndef quad:\n  double | double;\n" });
+    const dirs = [_][]const u8{try tmp.dir.realpathAlloc(arena.allocator(), ".")};
+
+    var err_ctx: ErrorContext = .{ .lib_dirs = &dirs };
+    const expr = try parseExprWithContext(arena.allocator(), "include \"team\"; quad", &err_ctx);
+    try std.testing.expect(expr == .define);
+    try std.testing.expect(expr.define.rest.* == .define);
+
+    err_ctx = .{ .lib_dirs = &dirs };
+    try std.testing.expectError(error.ModuleNotFound, parseExprWithContext(arena.allocator(), "include \"missing\"; .", &err_ctx));
+    try std.testing.expectEqualStrings("missing", err_ctx.feature);
+}
+
+test "parse try catch and optional" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+
+    // The body extends to catch, so it can contain pipes
+    const expr = try parseExprWithContext(arena.allocator(), "try .x | tonumber catch \"bad\"", &err_ctx);
+    try std.testing.expect(expr == .try_catch);
+    try std.testing.expect(expr.try_catch.body.* == .pipe);
+    try std.testing.expect(expr.try_catch.handler.?.* == .literal);
+
+    // A pipe after the handler applies to the whole try
+    const piped = try parseExprWithContext(arena.allocator(), "try .a catch . | length", &err_ctx);
+    try std.testing.expect(piped == .pipe);
+    try std.testing.expect(piped.pipe.left.* == .try_catch);
+
+    const nested = try parseExprWithContext(arena.allocator(), "try (try error(\"x\") catch .) catch 1", &err_ctx);
+    try std.testing.expect(nested.try_catch.body.* == .try_catch);
+    try std.testing.expect(nested.try_catch.body.try_catch.body.* == .raise);
+
+    const optional = try parseExprWithContext(arena.allocator(), "(.a | tonumber)?", &err_ctx);
+    try std.testing.expect(optional == .try_catch);
+    try std.testing.expect(optional.try_catch.handler == null);
+
+    // A field named catch is not the keyword
+    const field = try parseExprWithContext(arena.allocator(), "try .catch", &err_ctx);
+    try std.testing.expect(field.try_catch.handler == null);
+
+    try std.testing.expect((try parseExprWithContext(arena.allocator(), "error", &err_ctx)).raise == null);
+    try std.testing.expectError(error.InvalidExpression, parseExprWithContext(arena.allocator(), "try .a catch", &err_ctx));
+}
+
+test "parse label and break" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+
+    const expr = try parseExprWithContext(arena.allocator(), "label $out | .[] | if . > 2 then break $out else . end", &err_ctx);
+    try std.testing.expect(expr == .label);
+    try std.testing.expectEqualStrings("*label-out", expr.label.name);
+    try std.testing.expect(expr.label.body.* == .pipe);
+
+    // break needs an enclosing label of the same name
+    try std.testing.expectError(error.UndefinedVariable, parseExprWithContext(arena.allocator(), "break $out", &err_ctx));
+    try std.testing.expectError(error.UndefinedVariable, parseExprWithContext(arena.allocator(), "label $a | break $b", &err_ctx));
+    // Labels are not variables
+    try std.testing.expectError(error.UndefinedVariable, parseExprWithContext(arena.allocator(), "label $out | $out", &err_ctx));
+}
+
+test "parse generators" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+
+    const range = try parseExprWithContext(arena.allocator(), "range(0; 10; 2)", &err_ctx);
+    try std.testing.expectEqual(GeneratorKind.range, range.generator.kind);
+    try std.testing.expectEqual(@as(usize, 3), range.generator.args.len);
+
+    // Conditions parse as select so comparisons work
+    const until = try parseExprWithContext(arena.allocator(), "until(. > 100; . * 2)", &err_ctx);
+    try std.testing.expect(until.generator.args[0].* == .select);
+    try std.testing.expect(until.generator.args[1].* == .arithmetic);
+
+    // first/last without arguments are still the array builtins
+    try std.testing.expect((try parseExprWithContext(arena.allocator(), "first", &err_ctx)) == .builtin);
+    try std.testing.expectEqual(GeneratorKind.first, (try parseExprWithContext(arena.allocator(), "first(.[])", &err_ctx)).generator.kind);
+
+    const descent = try parseExprWithContext(arena.allocator(), "..|.a?", &err_ctx);
+    try std.testing.expectEqual(GeneratorKind.recurse, descent.pipe.left.generator.kind);
+    try std.testing.expectEqual(GeneratorKind.env, (try parseExprWithContext(arena.allocator(), "$ENV.HOME", &err_ctx)).pipe.left.generator.kind);
+
+    try std.testing.expectEqual(@as(u32, 0), err_ctx.input_calls);
+    _ = try parseExprWithContext(arena.allocator(), "[., inputs]", &err_ctx);
+    try std.testing.expectEqual(@as(u32, 1), err_ctx.input_calls);
+
+    // seq, debug and stderr keep --jobs sequential
+    try std.testing.expectEqual(@as(u32, 0), err_ctx.ordered_calls);
+    _ = try parseExprWithContext(arena.allocator(), "seq", &err_ctx);
+    _ = try parseExprWithContext(arena.allocator(), "debug(\"x\")", &err_ctx);
+    try std.testing.expectEqual(@as(u32, 2), err_ctx.ordered_calls);
+
+    try std.testing.expectError(error.InvalidExpression, parseExprWithContext(arena.allocator(), "limit(1)", &err_ctx));
+}
+
+test "parse date functions" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+
+    const strptime = try parseExprWithContext(arena.allocator(), "strptime(\"%Y-%m-%d\")", &err_ctx);
+    try std.testing.expectEqual(DateFuncKind.strptime, strptime.date_func.kind);
+    try std.testing.expectEqual(@as(usize, 1), strptime.date_func.args.len);
+
+    // The iso8601 spellings and `date` are aliases of todate and fromdate
+    try std.testing.expectEqual(DateFuncKind.todate, (try parseExprWithContext(arena.allocator(), "todateiso8601", &err_ctx)).date_func.kind);
+    try std.testing.expectEqual(DateFuncKind.todate, (try parseExprWithContext(arena.allocator(), "date", &err_ctx)).date_func.kind);
+    try std.testing.expectEqual(DateFuncKind.fromdate, (try parseExprWithContext(arena.allocator(), "fromdateiso8601", &err_ctx)).date_func.kind);
+
+    const add = try parseExprWithContext(arena.allocator(), ".ts | dateadd(\"hours\"; 2)", &err_ctx);
+    try std.testing.expectEqual(DateFuncKind.dateadd, add.pipe.right.date_func.kind);
+
+    try std.testing.expectError(error.InvalidExpression, parseExprWithContext(arena.allocator(), "dateadd(\"days\")", &err_ctx));
 }
@@ -0,0 +1,622 @@
+const std = @import("std");
+const types = @import("types.zig");
+const eval = @import("eval.zig");
+const number = @import("number.zig");
+
+const Expr = types.Expr;
+const Condition = types.Condition;
+const CompareOp = types.CompareOp;
+const CompareValue = types.CompareValue;
+const LiteralExpr = types.LiteralExpr;
+
+// ============================================================================
+// Evaluation Plans
+// ============================================================================
+//
+// The tree evaluator parses every record into a std.json.Value and walks the
+// Expr for it. For the common `select(cond)` and `select(cond) | rest` shapes
+// that is mostly wasted work, since most records are dropped. A Plan lowers
+// the select condition into a flat instruction list with short-circuit jumps,
+// and runs it on the raw line: one pass over the bytes validates the JSON and
+// decodes only the fields the condition reads, without allocating. Records
+// that pass are parsed and handed to `rest` as usual.
+//
+// Only those two shapes are planned. Field access, projections and object
+// construction on every record need the values themselves, so they gain
+// little from skipping the parse and stay on the tree evaluator.
+//
+// Anything the scanner can't decide exactly like the tree evaluator would
+// (escaped keys, duplicate keys, deep nesting, malformed input) is reported as
+// `.unknown`, and the caller falls back to the full evaluator.
+
+/// Most fields a compiled condition may read
+pub const max_slots = 16;
+/// Deepest nesting the scanner validates before giving up
+const max_depth = 64;
+
+pub const Instr = union(enum) {
+    /// Compare a field with a constant and set the flag; absent fields are false
+    compare: struct { slot: u8, op: CompareOp, value: CompareValue },
+    /// Short-circuit `and`: skip to the target when the flag is false
+    jump_if_false: u16,
+    /// Short-circuit `or`: skip to the target when the flag is true
+    jump_if_true: u16,
+    /// `not`
+    negate,
+};
+
+pub const Verdict = enum {
+    pass, // the condition holds; run `on_pass` on the parsed record
+    drop, // the condition fails; the record has no output
+    unknown, // the scanner couldn't decide; evaluate the whole expression
+};
+
+pub const Plan = struct {
+    /// Field paths read by the condition, e.g. ["nested", "score"]
+    slots: []const []const []const u8,
+    code: []const Instr,
+    /// What runs on records that pass: the rest of the pipe, or identity
+    on_pass: *const Expr,
+
+    /// Compile `select(cond)` or `select(cond) | rest`. Returns null when the
+    /// expression has another shape or the condition needs the tree evaluator
+    /// (variables, expressions on the left, indexes, too many fields).
+    pub fn compile(allocator: std.mem.Allocator, expr: *const Expr) error{OutOfMemory}!?Plan {
+        var cond: *const Condition = undefined;
+        var rest: ?*const Expr = null;
+        switch (expr.*) {
+            .select => |c| cond = c,
+            .pipe => |p| {
+                if (p.left.* != .select) return null;
+                cond = p.left.select;
+                rest = p.right;
+            },
+            else => return null,
+        }
+
+        var compiler: Compiler = .{ .allocator = allocator };
+        if (!try compiler.emit(cond)) return null;
+
+        const on_pass = rest orelse blk: {
+            const identity = try allocator.create(Expr);
+            identity.* = .identity;
+            break :blk identity;
+        };
+        return .{
+            .slots = try compiler.slots.toOwnedSlice(allocator),
+            .code = try compiler.code.toOwnedSlice(allocator),
+            .on_pass = on_pass,
+        };
+    }
+
+    /// Decide the condition from the raw JSON line.
+    pub fn filter(self: *const Plan, line: []const u8) Verdict {
+        var scanner: Scanner = .{ .text = line, .slots = self.slots };
+        scanner.skipSpace();
+        if (scanner.pos >= line.len or line[scanner.pos] != '{') return .unknown;
+        const all: u32 = @intCast((@as(u64, 1) << @intCast(self.slots.len)) - 1);
+        scanner.object(all, 0) catch return .unknown;
+        scanner.skipSpace();
+        if (scanner.pos != line.len) return .unknown;
+        return if (self.run(&scanner.values)) .pass else .drop;
+    }
+
+    fn run(self: *const Plan, values: []const ?std.json.Value) bool {
+        var flag = false;
+        var pc: usize = 0;
+        while (pc < self.code.len) : (pc += 1) {
+            switch (self.code[pc]) {
+                .compare => |c| {
+                    const field = values[c.slot] orelse {
+                        flag = false;
+                        continue;
+                    };
+                    flag = eval.evalConditionForValue(field, c.op, c.value);
+                },
+                .jump_if_false => |target| if (!flag) {
+                    pc = target - 1;
+                },
+                .jump_if_true => |target| if (flag) {
+                    pc = target - 1;
+                },
+                .negate => flag = !flag,
+            }
+        }
+        return flag;
+    }
+};
+
+const Compiler = struct {
+    allocator: std.mem.Allocator,
+    slots: std.ArrayListUnmanaged([]const []const u8) = .empty,
+    code: std.ArrayListUnmanaged(Instr) = .empty,
+
+    /// Append the instructions for `cond`; false if it can't be compiled.
+    fn emit(self: *Compiler, cond: *const Condition) error{OutOfMemory}!bool {
+        switch (cond.*) {
+            .simple => |simple| {
+                if (simple.left_expr != null or simple.index != null or simple.path.len == 0) return false;
+                if (simple.value == .variable) return false;
+                const slot = (try self.slotFor(simple.path)) orelse return false;
+                try self.code.append(self.allocator, .{ .compare = .{ .slot = slot, .op = simple.op, .value = simple.value } });
+            },
+            .compound => |compound| {
+                if (!try self.emit(compound.left)) return false;
+                const jump = self.code.items.len;
+                try self.code.append(self.allocator, switch (compound.op) {
+                    .and_op => .{ .jump_if_false = 0 },
+                    .or_op => .{ .jump_if_true = 0 },
+                });
+                if (!try self.emit(compound.right)) return false;
+                if (self.code.items.len > std.math.maxInt(u16)) return false;
+                const target: u16 = @intCast(self.code.items.len);
+                switch (self.code.items[jump]) {
+                    .jump_if_false => |*t| t.* = target,
+                    .jump_if_true => |*t| t.* = target,
+                    else => unreachable,
+                }
+            },
+            .negated => |inner| {
+                if (!try self.emit(inner)) return false;
+                try self.code.append(self.allocator, .negate);
+            },
+        }
+        return true;
+    }
+
+    fn slotFor(self: *Compiler, path: []const []const u8) error{OutOfMemory}!?u8 {
+        for (self.slots.items, 0..) |existing, i| {
+            if (existing.len != path.len) continue;
+            for (existing, path) |a, b| {
+                if (!std.mem.eql(u8, a, b)) break;
+            } else return @intCast(i);
+        }
+        if (self.slots.items.len == max_slots) return null;
+        try self.slots.append(self.allocator, path);
+        return @intCast(self.slots.items.len - 1);
+    }
+};
+
+// ============================================================================
+// Raw Line Scanner
+// ============================================================================
+
+const ScanError = error{Unknown};
+
+/// Stands in for arrays and objects the condition reads: comparisons only
+/// look at the type of a compound value, so its contents are never decoded.
+const compound_value: std.json.Value = .{ .array = .{ .items = &.{}, .capacity = 0, .allocator = std.heap.page_allocator } };
+
+const Scanner = struct {
+    text: []const u8,
+    pos: usize = 0,
+    slots: []const []const []const u8,
+    values: [max_slots]?std.json.Value = @splat(null),
+    /// Slots whose key has been seen; a second sighting is a duplicate key
+    seen: u32 = 0,
+    /// Unescaped string values live here
+    buf: [4096]u8 = undefined,
+    buf_len: usize = 0,
+
+    fn skipSpace(self: *Scanner) void {
+        while (self.pos < self.text.len) : (self.pos += 1) {
+            switch (self.text[self.pos]) {
+                ' ', '\t', '\n', '\r' => {},
+                else => return,
+            }
+        }
+    }
+
+    fn expect(self: *Scanner, c: u8) ScanError!void {
+        if (self.pos >= self.text.len or self.text[self.pos] != c) return error.Unknown;
+        self.pos += 1;
+    }
+
+    fn peek(self: *Scanner) ScanError!u8 {
+        if (self.pos >= self.text.len) return error.Unknown;
+        return self.text[self.pos];
+    }
+
+    /// Scan an object at `depth`, recording the slots in `mask` whose path
+    /// continues through it.
+    fn object(self: *Scanner, mask: u32, depth: usize) ScanError!void {
+        try self.expect('{');
+        self.skipSpace();
+        if (try self.peek() == '}') {
+            self.pos += 1;
+            return;
+        }
+        while (true) {
+            const key = try self.string();
+            self.skipSpace();
+            try self.expect(':');
+            self.skipSpace();
+
+            var hits: u32 = 0;
+            if (mask != 0) {
+                // Escaped keys would need decoding to compare
+                if (key.escaped) return error.Unknown;
+                for (self.slots, 0..) |path, i| {
+                    const bit = @as(u32, 1) << @intCast(i);
+                    if (mask & bit != 0 and std.mem.eql(u8, path[depth], key.raw)) hits |= bit;
+                }
+            }
+            if (hits & self.seen != 0) return error.Unknown;
+            self.seen |= hits;
+
+            if (hits == 0) {
+                try self.skipValue(depth + 1);
+            } else {
+                var leaves: u32 = 0;
+                for (self.slots, 0..) |path, i| {
+                    const bit = @as(u32, 1) << @intCast(i);
+                    if (hits & bit != 0 and path.len == depth + 1) leaves |= bit;
+                }
+                const deeper = hits & ~leaves;
+                const field = if (deeper != 0 and try self.peek() == '{') blk: {
+                    try self.object(deeper, depth + 1);
+                    break :blk compound_value;
+                } else try self.value(depth + 1);
+                for (0..self.slots.len) |i| {
+                    if (leaves & (@as(u32, 1) << @intCast(i)) != 0) self.values[i] = field;
+                }
+            }
+
+            self.skipSpace();
+            switch (try self.peek()) {
+                ',' => {
+                    self.pos += 1;
+                    self.skipSpace();
+                },
+                '}' => {
+                    self.pos += 1;
+                    return;
+                },
+                else => return error.Unknown,
+            }
+        }
+    }
+
+    /// Decode a value the condition reads. Strings and scalars are decoded
+    /// the way std.json would; arrays and objects are only validated.
+    fn value(self: *Scanner, depth: usize) ScanError!std.json.Value {
+        switch (try self.peek()) {
+            '"' => {
+                const s = try self.string();
+                if (!s.escaped) return .{ .string = s.raw };
+                return .{ .string = try self.unescape(s.raw) };
+            },
+            '{', '[' => {
+                try self.skipValue(depth);
+                return compound_value;
+            },
+            't' => return self.literal("true", .{ .bool = true }),
+            'f' => return self.literal("false", .{ .bool = false }),
+            'n' => return self.literal("null", .null),
+            else => return self.numberLiteral(),
+        }
+    }
+
+    fn skipValue(self: *Scanner, depth: usize) ScanError!void {
+        if (depth > max_depth) return error.Unknown;
+        switch (try self.peek()) {
+            '"' => _ = try self.string(),
+            '{' => try self.object(0, depth),
+            '[' => {
+                self.pos += 1;
+                self.skipSpace();
+                if (try self.peek() == ']') {
+                    self.pos += 1;
+                    return;
+                }
+                while (true) {
+                    try self.skipValue(depth + 1);
+                    self.skipSpace();
+                    switch (try self.peek()) {
+                        ',' => {
+                            self.pos += 1;
+                            self.skipSpace();
+                        },
+                        ']' => {
+                            self.pos += 1;
+                            return;
+                        },
+                        else => return error.Unknown,
+                    }
+                }
+            },
+            't' => _ = try self.literal("true", .null),
+            'f' => _ = try self.literal("false", .null),
+            'n' => _ = try self.literal("null", .null),
+            else => _ = try self.numberLiteral(),
+        }
+    }
+
+    fn literal(self: *Scanner, word: []const u8, result: std.json.Value) ScanError!std.json.Value {
+        if (!std.mem.startsWith(u8, self.text[self.pos..], word)) return error.Unknown;
+        self.pos += word.len;
+        return result;
+    }
+
+    /// A JSON number, typed like number.parseJson types it for the evaluator.
+    fn numberLiteral(self: *Scanner) ScanError!std.json.Value {
+        const start = self.pos;
+        _ = self.consume('-');
+        const int_start = self.pos;
+        if (!self.digits()) return error.Unknown;
+        // No leading zeros
+        if (self.text[int_start] == '0' and self.pos - int_start > 1) return error.Unknown;
+        if (self.consume('.')) {
+            if (!self.digits()) return error.Unknown;
+        }
+        if (self.consume('e') or self.consume('E')) {
+            _ = self.consume('+') or self.consume('-');
+            if (!self.digits()) return error.Unknown;
+        }
+        return number.literal(self.text[start..self.pos]) orelse error.Unknown;
+    }
+
+    fn consume(self: *Scanner, c: u8) bool {
+        if (self.pos >= self.text.len or self.text[self.pos] != c) return false;
+        self.pos += 1;
+        return true;
+    }
+
+    fn digits(self: *Scanner) bool {
+        const start = self.pos;
+        while (self.pos < self.text.len and std.ascii.isDigit(self.text[self.pos])) self.pos += 1;
+        return self.pos > start;
+    }
+
+    const RawString = struct { raw: []const u8, escaped: bool };
+
+    /// Validate a string and return the text between its quotes.
+    fn string(self: *Scanner) ScanError!RawString {
+        try self.expect('"');
+        const start = self.pos;
+        var escaped = false;
+        while (self.pos < self.text.len) {
+            const c = self.text[self.pos];
+            switch (c) {
+                '"' => {
+                    const raw = self.text[start..self.pos];
+                    self.pos += 1;
+                    if (!std.unicode.utf8ValidateSlice(raw)) return error.Unknown;
+                    return .{ .raw = raw, .escaped = escaped };
+                },
+                '\\' => {
+                    escaped = true;
+                    if (self.pos + 1 >= self.text.len) return error.Unknown;
+                    switch (self.text[self.pos + 1]) {
+                        '"', '\\', '/', 'b', 'f', 'n', 'r', 't' => self.pos += 2,
+                        'u' => {
+                            if (self.pos + 6 > self.text.len) return error.Unknown;
+                            _ = std.fmt.parseInt(u16, self.text[self.pos + 2 .. self.pos + 6], 16) catch return error.Unknown;
+                            self.pos += 6;
+                        },
+                        else => return error.Unknown,
+                    }
+                },
+                0...0x1f => return error.Unknown,
+                else => self.pos += 1,
+            }
+        }
+        return error.Unknown;
+    }
+
+    /// Decode the escapes of an already validated string into `buf`.
+    fn unescape(self: *Scanner, raw: []const u8) ScanError![]const u8 {
+        const start = self.buf_len;
+        var i: usize = 0;
+        while (i < raw.len) {
+            if (raw[i] != '\\') {
+                try self.put(raw[i .. i + 1]);
+                i += 1;
+                continue;
+            }
+            const c = raw[i + 1];
+            i += 2;
+            const simple: ?u8 = switch (c) {
+                'b' => 0x08,
+                'f' => 0x0c,
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                'u' => null,
+                else => c,
+            };
+            if (simple) |byte| {
+                try self.put(&.{byte});
+                continue;
+            }
+            const unit = std.fmt.parseInt(u16, raw[i .. i + 4], 16) catch unreachable;
+            i += 4;
+            var cp: u21 = unit;
+            if (std.unicode.utf16IsHighSurrogate(unit)) {
+                // A high surrogate must be followed by an escaped low one
+                if (i + 6 > raw.len or raw[i] != '\\' or raw[i + 1] != 'u') return error.Unknown;
+                const low = std.fmt.parseInt(u16, raw[i + 2 .. i + 6], 16) catch unreachable;
+                if (!std.unicode.utf16IsLowSurrogate(low)) return error.Unknown;
+                cp = 0x10000 + ((@as(u21, unit) - 0xd800) << 10) + (@as(u21, low) - 0xdc00);
+                i += 6;
+            } else if (std.unicode.utf16IsLowSurrogate(unit)) {
+                return error.Unknown;
+            }
+            var utf8: [4]u8 = undefined;
+            const len = std.unicode.utf8Encode(cp, &utf8) catch return error.Unknown;
+            try self.put(utf8[0..len]);
+        }
+        return self.buf[start..self.buf_len];
+    }
+
+    fn put(self: *Scanner, bytes: []const u8) ScanError!void {
+        if (self.buf_len + bytes.len > self.buf.len) return error.Unknown;
+        @memcpy(self.buf[self.buf_len..][0..bytes.len], bytes);
+        self.buf_len += bytes.len;
+    }
+};
+
+// ============================================================================
+// Constant Folding
+// ============================================================================
+
+/// Replace arithmetic on literals with its result (`60 * 60` becomes 3600,
+/// `"a" + "b"` becomes "ab") and `literal // x` with whichever side the
+/// alternative would pick. Folding stops at anything that would raise, so
+/// runtime errors stay runtime errors.
+pub fn foldConstants(allocator: std.mem.Allocator, expr: *Expr) void {
+    switch (expr.*) {
+        .pipe => |p| {
+            foldConstants(allocator, p.left);
+            foldConstants(allocator, p.right);
+        },
+        .arithmetic => |a| {
+            foldConstants(allocator, a.left);
+            foldConstants(allocator, a.right);
+            if (a.left.* != .literal or a.right.* != .literal) return;
+            const left = literalValue(a.left.literal);
+            const right = literalValue(a.right.literal);
+            // Keep products of large integers away from the float round trip
+            if (left == .integer and @abs(left.integer) > 1 << 31) return;
+            if (right == .integer and @abs(right.integer) > 1 << 31) return;
+            const result = eval.arithValues(allocator, a.op, left, right) catch return;
+            expr.* = .{ .literal = valueLiteral(result) orelse return };
+        },
+        .alternative => |alt| {
+            foldConstants(allocator, alt.primary);
+            foldConstants(allocator, alt.fallback);
+            if (alt.primary.* != .literal) return;
+            const lit = alt.primary.literal;
+            const falsy = lit == .null_val or (lit == .boolean and !lit.boolean);
+            expr.* = if (falsy) alt.fallback.* else alt.primary.*;
+        },
+        .array => |arr| for (arr.elements) |element| foldConstants(allocator, element),
+        .object => |obj| for (obj.fields) |field| {
+            if (field.key == .dynamic) foldConstants(allocator, field.key.dynamic);
+            foldConstants(allocator, field.value);
+        },
+        .conditional => |c| {
+            foldCondition(allocator, c.condition);
+            foldConstants(allocator, c.then_branch);
+            foldConstants(allocator, c.else_branch);
+        },
+        .select => |cond| foldCondition(allocator, cond),
+        .map => |m| foldConstants(allocator, m.inner),
+        .bind => |b| {
+            foldConstants(allocator, b.source);
+            foldConstants(allocator, b.body);
+        },
+        .try_catch => |t| {
+            foldConstants(allocator, t.body);
+            if (t.handler) |handler| foldConstants(allocator, handler);
+        },
+        else => {},
+    }
+}
+
+fn foldCondition(allocator: std.mem.Allocator, cond: *Condition) void {
+    switch (cond.*) {
+        .simple => |simple| if (simple.left_expr) |left| foldConstants(allocator, left),
+        .compound => |compound| {
+            foldCondition(allocator, compound.left);
+            foldCondition(allocator, compound.right);
+        },
+        .negated => |inner| foldCondition(allocator, inner),
+    }
+}
+
+fn literalValue(lit: LiteralExpr) std.json.Value {
+    return switch (lit) {
+        .string => |s| .{ .string = s },
+        .integer => |i| .{ .integer = i },
+        .float => |f| .{ .float = f },
+        .number => |s| .{ .number_string = s },
+        .boolean => |b| .{ .bool = b },
+        .null_val => .null,
+    };
+}
+
+fn valueLiteral(value: std.json.Value) ?LiteralExpr {
+    return switch (value) {
+        .string => |s| .{ .string = s },
+        .integer => |i| .{ .integer = i },
+        .float => |f| .{ .float = f },
+        .number_string => |s| .{ .number = s },
+        .bool => |b| .{ .boolean = b },
+        .null => .null_val,
+        else => null,
+    };
+}
+
+// ============================================================================
+// Plan Tests
+// ============================================================================
+
+const parser = @import("parser.zig");
+const ErrorContext = types.ErrorContext;
+
+fn testPlan(allocator: std.mem.Allocator, text: []const u8) !?Plan {
+    var err_ctx: ErrorContext = .{};
+    const expr = try allocator.create(Expr);
+    expr.* = try parser.parseExprWithContext(allocator, text, &err_ctx);
+    return Plan.compile(allocator, expr);
+}
+
+test "plan filters raw lines like select" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    const plan = (try testPlan(arena.allocator(), "select(.status == \"x\" and .nested.score > 500)")).?;
+    try std.testing.expectEqual(@as(usize, 2), plan.slots.len);
+    try std.testing.expect(plan.on_pass.* == .identity);
+
+    try std.testing.expectEqual(Verdict.pass, plan.filter("{\"id\":1,\"status\":\"x\",\"nested\":{\"score\":900}}"));
+    try std.testing.expectEqual(Verdict.drop, plan.filter("{\"status\":\"y\",\"nested\":{\"score\":900}}"));
+    try std.testing.expectEqual(Verdict.drop, plan.filter("{\"status\": \"x\", \"nested\": {\"score\": 1e2}}"));
+    // Missing fields and non-object parents are absent, like getPath
+    try std.testing.expectEqual(Verdict.drop, plan.filter("{\"status\":\"x\",\"nested\":[1]}"));
+    try std.testing.expectEqual(Verdict.drop, plan.filter("{\"status\":\"x\"}"));
+    // Escaped values are decoded before comparing
+    try std.testing.expectEqual(Verdict.pass, plan.filter("{\"status\":\"\\u0078\",\"nested\":{\"score\":501}}"));
+
+    // Anything the scanner can't settle goes to the tree evaluator
+    try std.testing.expectEqual(Verdict.unknown, plan.filter("{\"status\":\"x\",\"status\":\"y\"}"));
+    try std.testing.expectEqual(Verdict.unknown, plan.filter("{\"st\\u0061tus\":\"x\"}"));
+    try std.testing.expectEqual(Verdict.unknown, plan.filter("{\"status\":\"x\",}"));
+    try std.testing.expectEqual(Verdict.unknown, plan.filter("[1, 2]"));
+}
+
+test "plan short-circuits or and not" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    const plan = (try testPlan(arena.allocator(), "select(.a or not .b) | .c")).?;
+    try std.testing.expect(plan.on_pass.* == .field);
+    try std.testing.expectEqual(Verdict.pass, plan.filter("{\"a\":true,\"b\":true}"));
+    try std.testing.expectEqual(Verdict.pass, plan.filter("{\"a\":false,\"b\":null}"));
+    try std.testing.expectEqual(Verdict.drop, plan.filter("{\"a\":null,\"b\":[]}"));
+
+    // Shapes the plan doesn't cover
+    try std.testing.expect((try testPlan(arena.allocator(), ".a | select(.b)")) == null);
+    try std.testing.expect((try testPlan(arena.allocator(), "select(.a | length > 1)")) == null);
+}
+
+test "fold constants" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    var err_ctx: ErrorContext = .{};
+
+    var product = try parser.parseExprWithContext(arena.allocator(), "60 * 60 * 24", &err_ctx);
+    foldConstants(arena.allocator(), &product);
+    try std.testing.expectEqual(@as(i64, 86400), product.literal.integer);
+
+    var concat = try parser.parseExprWithContext(arena.allocator(), "{a: (\"x\" + \"y\"), b: (null // 3)}", &err_ctx);
+    foldConstants(arena.allocator(), &concat);
+    try std.testing.expectEqualStrings("xy", concat.object.fields[0].value.literal.string);
+    try std.testing.expectEqual(@as(i64, 3), concat.object.fields[1].value.literal.integer);
+
+    // Division by zero is left for the evaluator to raise
+    var div = try parser.parseExprWithContext(arena.allocator(), "1 / 0", &err_ctx);
+    foldConstants(arena.allocator(), &div);
+    try std.testing.expect(div == .arithmetic);
+}
@@ -0,0 +1,838 @@
+const std = @import("std");
+
+// ============================================================================
+// Regular Expressions
+// ============================================================================
+//
+// Patterns are parsed into a small syntax tree, compiled to a flat instruction
+// list and executed by a Pike VM: every thread advances in lockstep over the
+// input, so matching is O(pattern * input) with no backtracking. Matching works
+// on UTF-8 code points; `.` and character classes consume one code point.
+//
+// Supported syntax:
+//   literals, `.`, `[...]`, `[^...]`, `[:alpha:]` inside classes
+//   \d \D \w \W \s \S \b \B \A \z \Z \n \t \r \f \v \xHH and escaped metacharacters
+//   `^` `$` (start/end of input)
+//   `*` `+` `?` `{n}` `{n,}` `{n,m}` and their lazy `?` forms
+//   `(...)`, `(?:...)`, `(?<name>...)`, alternation `|`
+
+pub const Error = error{
+    InvalidPattern,
+    OutOfMemory,
+};
+
+/// jq regex flags ("gixnpsl").
+pub const Flags = struct {
+    global: bool = false, // g: every match, not just the first
+    ignore_case: bool = false, // i: ASCII case-insensitive
+    extended: bool = false, // x: ignore whitespace and # comments in the pattern
+    no_empty: bool = false, // n: skip empty matches
+    dot_all: bool = false, // p: `.` also matches newline
+
+    /// Parse a flags string such as "gi". Returns null on an unknown flag.
+    pub fn parse(str: []const u8) ?Flags {
+        var flags = Flags{};
+        for (str) |c| {
+            switch (c) {
+                'g' => flags.global = true,
+                'i' => flags.ignore_case = true,
+                'x' => flags.extended = true,
+                'n' => flags.no_empty = true,
+                'p' => flags.dot_all = true,
+                // s (single line) and l (longest) already describe the default behaviour
+                's', 'l' => {},
+                else => return null,
+            }
+        }
+        return flags;
+    }
+};
+
+/// Largest counted repetition accepted in `{n,m}`, to bound program size.
+const MAX_REPEAT: u32 = 1000;
+
+/// Marks a capture slot that was never set.
+const unset = std.math.maxInt(usize);
+
+const Assert = enum {
+    text_start, // ^ \A
+    text_end, // $ \z \Z
+    word_boundary, // \b
+    not_word_boundary, // \B
+};
+
+const Range = struct {
+    lo: u21,
+    hi: u21,
+};
+
+const Class = struct {
+    ranges: []const Range,
+    negated: bool,
+
+    fn contains(self: Class, c: u21) bool {
+        for (self.ranges) |r| {
+            if (c >= r.lo and c <= r.hi) return true;
+        }
+        return false;
+    }
+
+    fn matches(self: Class, c: u21, ignore_case: bool) bool {
+        var found = self.contains(c);
+        if (!found and ignore_case and c < 128) {
+            const ascii: u8 = @intCast(c);
+            found = self.contains(std.ascii.toLower(ascii)) or self.contains(std.ascii.toUpper(ascii));
+        }
+        return found != self.negated;
+    }
+};
+
+const Inst = union(enum) {
+    char: u21,
+    any, // any code point except \n
+    any_nl, // any code point
+    class: usize, // index into Regex.classes
+    split: [2]usize, // fork; [0] has priority
+    jmp: usize,
+    save: usize, // record the position in a capture slot
+    assert: Assert,
+    match,
+};
+
+const Node = union(enum) {
+    empty,
+    char: u21,
+    any,
+    class: usize,
+    assert: Assert,
+    group: Group,
+    concat: []const Node,
+    alternate: []const Node,
+    repeat: Repeat,
+};
+
+const Group = struct {
+    node: *const Node,
+    index: ?usize, // null for (?:...)
+};
+
+const Repeat = struct {
+    node: *const Node,
+    min: u32,
+    max: ?u32, // null is unbounded
+    greedy: bool,
+};
+
+/// A span of the input in byte offsets.
+pub const Span = struct {
+    start: usize,
+    end: usize,
+};
+
+/// Capture positions of one match. Group 0 is the whole match.
+pub const Match = struct {
+    slots: []const usize,
+
+    pub fn span(self: Match, group: usize) ?Span {
+        const start = self.slots[group * 2];
+        const end = self.slots[group * 2 + 1];
+        if (start == unset or end == unset) return null;
+        return .{ .start = start, .end = end };
+    }
+};
+
+pub const Regex = struct {
+    prog: []const Inst,
+    classes: []const Class,
+    /// Capture group names; index 0 is the whole match, unnamed groups are null
+    names: []const ?[]const u8,
+    flags: Flags,
+
+    pub fn compile(allocator: std.mem.Allocator, pattern: []const u8, flags: Flags) Error!Regex {
+        var parser = Parser{ .allocator = allocator, .pattern = pattern, .flags = flags };
+        try parser.names.append(allocator, null);
+        const root = try parser.parseAlternation();
+        // Anything left over is an unbalanced ')'
+        if (parser.pos < pattern.len) return error.InvalidPattern;
+
+        var compiler = Compiler{ .allocator = allocator, .flags = flags };
+        _ = try compiler.push(.{ .save = 0 });
+        try compiler.emit(root);
+        _ = try compiler.push(.{ .save = 1 });
+        _ = try compiler.push(.match);
+
+        return .{
+            .prog = try compiler.prog.toOwnedSlice(allocator),
+            .classes = try parser.classes.toOwnedSlice(allocator),
+            .names = try parser.names.toOwnedSlice(allocator),
+            .flags = flags,
+        };
+    }
+
+    /// Find the leftmost match starting at or after byte offset `start`.
+    pub fn exec(self: *const Regex, allocator: std.mem.Allocator, input: []const u8, start: usize) Error!?Match {
+        const nslots = self.names.len * 2;
+        var lists: [2]ThreadList = undefined;
+        for (&lists) |*list| {
+            list.* = .{
+                .pcs = try allocator.alloc(usize, self.prog.len),
+                .slots = try allocator.alloc(usize, self.prog.len * nslots),
+                .seen = try allocator.alloc(usize, self.prog.len),
+            };
+            @memset(list.seen, 0);
+        }
+        var clist = &lists[0];
+        var nlist = &lists[1];
+        const scratch = try allocator.alloc(usize, nslots);
+        var best: ?[]usize = null;
+
+        var pos = start;
+        var gen: usize = 1;
+        while (true) {
+            // Seed a new attempt at this position until something has matched
+            if (best == null) {
+                @memset(scratch, unset);
+                self.addThread(clist, 0, scratch, input, pos, gen);
+            }
+            if (clist.len == 0) {
+                // Nothing alive: either done, or the seed failed an assertion here
+                if (best != null) break;
+                const skip = decodeAt(input, pos) orelse break;
+                pos += skip.len;
+                gen += 1;
+                continue;
+            }
+
+            const next = decodeAt(input, pos);
+            gen += 1;
+            nlist.len = 0;
+            for (0..clist.len) |i| {
+                const pc = clist.pcs[i];
+                const slots = clist.slots[i * nslots ..][0..nslots];
+                if (self.prog[pc] == .match) {
+                    // Lower-priority threads can no longer win
+                    best = try allocator.dupe(usize, slots);
+                    break;
+                }
+                const c = next orelse continue;
+                if (self.accepts(self.prog[pc], c.value)) {
+                    @memcpy(scratch, slots);
+                    self.addThread(nlist, pc + 1, scratch, input, pos + c.len, gen);
+                }
+            }
+
+            const cp = next orelse break;
+            std.mem.swap(*ThreadList, &clist, &nlist);
+            pos += cp.len;
+        }
+
+        return if (best) |slots| Match{ .slots = slots } else null;
+    }
+
+    /// Follow control-flow instructions from `pc` and queue the threads that
+    /// consume input. `slots` is scratch space restored before returning.
+    fn addThread(self: *const Regex, list: *ThreadList, pc: usize, slots: []usize, input: []const u8, pos: usize, gen: usize) void {
+        if (list.seen[pc] == gen) return;
+        list.seen[pc] = gen;
+
+        switch (self.prog[pc]) {
+            .jmp => |target| self.addThread(list, target, slots, input, pos, gen),
+            .split => |targets| {
+                self.addThread(list, targets[0], slots, input, pos, gen);
+                self.addThread(list, targets[1], slots, input, pos, gen);
+            },
+            .save => |slot| {
+                const old = slots[slot];
+                slots[slot] = pos;
+                self.addThread(list, pc + 1, slots, input, pos, gen);
+                slots[slot] = old;
+            },
+            .assert => |kind| {
+                if (checkAssert(kind, input, pos)) self.addThread(list, pc + 1, slots, input, pos, gen);
+            },
+            else => {
+                const idx = list.len;
+                list.pcs[idx] = pc;
+                @memcpy(list.slots[idx * slots.len ..][0..slots.len], slots);
+                list.len += 1;
+            },
+        }
+    }
+
+    fn accepts(self: *const Regex, inst: Inst, c: u21) bool {
+        return switch (inst) {
+            .char => |want| c == want or (self.flags.ignore_case and foldCase(c) == foldCase(want)),
+            .any => c != '\n',
+            .any_nl => true,
+            .class => |idx| self.classes[idx].matches(c, self.flags.ignore_case),
+            else => false,
+        };
+    }
+};
+
+const ThreadList = struct {
+    pcs: []usize,
+    slots: []usize,
+    /// Generation in which each pc was last queued, to drop duplicate threads
+    seen: []usize,
+    len: usize = 0,
+};
+
+const CodePoint = struct {
+    value: u21,
+    len: usize,
+};
+
+/// Decode the code point at `pos`; invalid UTF-8 bytes decode as themselves.
+fn decodeAt(input: []const u8, pos: usize) ?CodePoint {
+    if (pos >= input.len) return null;
+    const len = std.unicode.utf8ByteSequenceLength(input[pos]) catch return .{ .value = input[pos], .len = 1 };
+    if (pos + len > input.len) return .{ .value = input[pos], .len = 1 };
+    const value = std.unicode.utf8Decode(input[pos .. pos + len]) catch return .{ .value = input[pos], .len = 1 };
+    return .{ .value = value, .len = len };
+}
+
+/// Byte length of the code point at `pos` (1 past the end of input).
+pub fn codepointLen(input: []const u8, pos: usize) usize {
+    return if (decodeAt(input, pos)) |cp| cp.len else 1;
+}
+
+fn foldCase(c: u21) u21 {
+    return if (c < 128) std.ascii.toLower(@intCast(c)) else c;
+}
+
+fn isWordByte(c: u8) bool {
+    return std.ascii.isAlphanumeric(c) or c == '_';
+}
+
+fn checkAssert(kind: Assert, input: []const u8, pos: usize) bool {
+    return switch (kind) {
+        .text_start => pos == 0,
+        .text_end => pos == input.len,
+        .word_boundary, .not_word_boundary => blk: {
+            const before = pos > 0 and isWordByte(input[pos - 1]);
+            const after = pos < input.len and isWordByte(input[pos]);
+            break :blk (before != after) == (kind == .word_boundary);
+        },
+    };
+}
+
+// ============================================================================
+// Pattern Parser
+// ============================================================================
+
+const digit_ranges = [_]Range{.{ .lo = '0', .hi = '9' }};
+const word_ranges = [_]Range{
+    .{ .lo = '0', .hi = '9' },
+    .{ .lo = 'A', .hi = 'Z' },
+    .{ .lo = '_', .hi = '_' },
+    .{ .lo = 'a', .hi = 'z' },
+};
+const space_ranges = [_]Range{
+    .{ .lo = '\t', .hi = '\r' },
+    .{ .lo = ' ', .hi = ' ' },
+};
+
+const Parser = struct {
+    allocator: std.mem.Allocator,
+    pattern: []const u8,
+    flags: Flags,
+    pos: usize = 0,
+    classes: std.ArrayListUnmanaged(Class) = .empty,
+    names: std.ArrayListUnmanaged(?[]const u8) = .empty,
+
+    fn peek(self: *Parser) ?u8 {
+        self.skipExtended();
+        return if (self.pos < self.pattern.len) self.pattern[self.pos] else null;
+    }
+
+    /// With the x flag, whitespace and # comments outside classes are ignored.
+    fn skipExtended(self: *Parser) void {
+        if (!self.flags.extended) return;
+        while (self.pos < self.pattern.len) {
+            const c = self.pattern[self.pos];
+            if (std.ascii.isWhitespace(c)) {
+                self.pos += 1;
+            } else if (c == '#') {
+                while (self.pos < self.pattern.len and self.pattern[self.pos] != '\n') self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn parseAlternation(self: *Parser) Error!Node {
+        var branches: std.ArrayListUnmanaged(Node) = .empty;
+        try branches.append(self.allocator, try self.parseConcat());
+        while (self.peek() == '|') {
+            self.pos += 1;
+            try branches.append(self.allocator, try self.parseConcat());
+        }
+        if (branches.items.len == 1) return branches.items[0];
+        return .{ .alternate = try branches.toOwnedSlice(self.allocator) };
+    }
+
+    fn parseConcat(self: *Parser) Error!Node {
+        var items: std.ArrayListUnmanaged(Node) = .empty;
+        while (self.peek()) |c| {
+            if (c == '|' or c == ')') break;
+            const atom = try self.parseAtom();
+            try items.append(self.allocator, try self.parseQuantifiers(atom));
+        }
+        return switch (items.items.len) {
+            0 => .empty,
+            1 => items.items[0],
+            else => .{ .concat = try items.toOwnedSlice(self.allocator) },
+        };
+    }
+
+    fn parseAtom(self: *Parser) Error!Node {
+        const c = self.pattern[self.pos];
+        switch (c) {
+            '(' => return self.parseGroup(),
+            '[' => {
+                self.pos += 1;
+                return .{ .class = try self.parseClass() };
+            },
+            '.' => {
+                self.pos += 1;
+                return .any;
+            },
+            '^' => {
+                self.pos += 1;
+                return .{ .assert = .text_start };
+            },
+            '$' => {
+                self.pos += 1;
+                return .{ .assert = .text_end };
+            },
+            '\\' => return self.parseEscape(),
+            '*', '+', '?' => return error.InvalidPattern, // nothing to repeat
+            else => {
+                const cp = decodeAt(self.pattern, self.pos).?;
+                self.pos += cp.len;
+                return .{ .char = cp.value };
+            },
+        }
+    }
+
+    fn parseGroup(self: *Parser) Error!Node {
+        self.pos += 1; // (
+        var index: ?usize = null;
+        if (std.mem.startsWith(u8, self.pattern[self.pos..], "?:")) {
+            self.pos += 2;
+        } else if (std.mem.startsWith(u8, self.pattern[self.pos..], "?<") or std.mem.startsWith(u8, self.pattern[self.pos..], "?P<")) {
+            self.pos += if (self.pattern[self.pos + 1] == 'P') 3 else 2;
+            const close = std.mem.indexOfScalarPos(u8, self.pattern, self.pos, '>') orelse return error.InvalidPattern;
+            const name = self.pattern[self.pos..close];
+            if (name.len == 0) return error.InvalidPattern;
+            for (name) |ch| {
+                if (!isWordByte(ch)) return error.InvalidPattern;
+            }
+            self.pos = close + 1;
+            index = self.names.items.len;
+            try self.names.append(self.allocator, name);
+        } else if (self.pos < self.pattern.len and self.pattern[self.pos] == '?') {
+            return error.InvalidPattern; // lookaround and inline flags are not supported
+        } else {
+            index = self.names.items.len;
+            try self.names.append(self.allocator, null);
+        }
+
+        const inner = try self.allocator.create(Node);
+        inner.* = try self.parseAlternation();
+        if (self.peek() != ')') return error.InvalidPattern;
+        self.pos += 1;
+        return .{ .group = .{ .node = inner, .index = index } };
+    }
+
+    fn parseEscape(self: *Parser) Error!Node {
+        self.pos += 1; // backslash
+        if (self.pos >= self.pattern.len) return error.InvalidPattern;
+        const c = self.pattern[self.pos];
+        self.pos += 1;
+        return switch (c) {
+            'd', 'D', 'w', 'W', 's', 'S' => .{ .class = try self.addClass(shorthandRanges(c), std.ascii.isUpper(c)) },
+            'b' => .{ .assert = .word_boundary },
+            'B' => .{ .assert = .not_word_boundary },
+            'A' => .{ .assert = .text_start },
+            'z', 'Z' => .{ .assert = .text_end },
+            '1'...'9' => error.InvalidPattern, // backreferences need backtracking
+            else => .{ .char = try self.escapedChar(c) },
+        };
+    }
+
+    /// Character for a single-character escape such as \n or \. (after the backslash).
+    fn escapedChar(self: *Parser, c: u8) Error!u21 {
+        return switch (c) {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'f' => 0x0c,
+            'v' => 0x0b,
+            'e' => 0x1b,
+            '0' => 0,
+            'x' => blk: {
+                if (self.pos + 2 > self.pattern.len) return error.InvalidPattern;
+                const value = std.fmt.parseInt(u8, self.pattern[self.pos .. self.pos + 2], 16) catch return error.InvalidPattern;
+                self.pos += 2;
+                break :blk value;
+            },
+            else => if (std.ascii.isAlphanumeric(c)) error.InvalidPattern else c,
+        };
+    }
+
+    fn parseClass(self: *Parser) Error!usize {
+        var ranges: std.ArrayListUnmanaged(Range) = .empty;
+        var negated = false;
+        if (self.pos < self.pattern.len and self.pattern[self.pos] == '^') {
+            negated = true;
+            self.pos += 1;
+        }
+
+        var first = true;
+        while (true) : (first = false) {
+            if (self.pos >= self.pattern.len) return error.InvalidPattern;
+            const c = self.pattern[self.pos];
+            if (c == ']' and !first) {
+                self.pos += 1;
+                break;
+            }
+
+            // POSIX bracket expressions: [[:alpha:]]
+            if (c == '[' and self.pos + 1 < self.pattern.len and self.pattern[self.pos + 1] == ':') {
+                const end = std.mem.indexOfPos(u8, self.pattern, self.pos, ":]") orelse return error.InvalidPattern;
+                const name = self.pattern[self.pos + 2 .. end];
+                try ranges.appendSlice(self.allocator, posixRanges(name) orelse return error.InvalidPattern);
+                self.pos = end + 2;
+                continue;
+            }
+
+            var lo: u21 = undefined;
+            if (c == '\\') {
+                if (self.pos + 1 >= self.pattern.len) return error.InvalidPattern;
+                const esc = self.pattern[self.pos + 1];
+                self.pos += 2;
+                switch (esc) {
+                    'd', 'w', 's' => {
+                        try ranges.appendSlice(self.allocator, shorthandRanges(esc));
+                        continue;
+                    },
+                    'D', 'W', 'S' => {
+                        try ranges.appendSlice(self.allocator, try complement(self.allocator, shorthandRanges(esc)));
+                        continue;
+                    },
+                    'b' => lo = 0x08,
+                    else => lo = try self.escapedChar(esc),
+                }
+            } else {
+                const cp = decodeAt(self.pattern, self.pos).?;
+                self.pos += cp.len;
+                lo = cp.value;
+            }
+
+            // Range a-z, unless the '-' is the last character of the class
+            if (self.pos + 1 < self.pattern.len and self.pattern[self.pos] == '-' and self.pattern[self.pos + 1] != ']') {
+                self.pos += 1;
+                var hi: u21 = undefined;
+                if (self.pattern[self.pos] == '\\') {
+                    if (self.pos + 1 >= self.pattern.len) return error.InvalidPattern;
+                    const esc = self.pattern[self.pos + 1];
+                    self.pos += 2;
+                    hi = try self.escapedChar(esc);
+                } else {
+                    const cp = decodeAt(self.pattern, self.pos).?;
+                    self.pos += cp.len;
+                    hi = cp.value;
+                }
+                if (hi < lo) return error.InvalidPattern;
+                try ranges.append(self.allocator, .{ .lo = lo, .hi = hi });
+            } else {
+                try ranges.append(self.allocator, .{ .lo = lo, .hi = lo });
+            }
+        }
+
+        const index = self.classes.items.len;
+        try self.classes.append(self.allocator, .{ .ranges = try ranges.toOwnedSlice(self.allocator), .negated = negated });
+        return index;
+    }
+
+    fn addClass(self: *Parser, ranges: []const Range, negated: bool) Error!usize {
+        const index = self.classes.items.len;
+        try self.classes.append(self.allocator, .{ .ranges = ranges, .negated = negated });
+        return index;
+    }
+
+    fn parseQuantifiers(self: *Parser, atom: Node) Error!Node {
+        var node = atom;
+        while (self.peek()) |c| {
+            var min: u32 = undefined;
+            var max: ?u32 = undefined;
+            switch (c) {
+                '*' => {
+                    min = 0;
+                    max = null;
+                    self.pos += 1;
+                },
+                '+' => {
+                    min = 1;
+                    max = null;
+                    self.pos += 1;
+                },
+                '?' => {
+                    min = 0;
+                    max = 1;
+                    self.pos += 1;
+                },
+                '{' => {
+                    // A '{' that does not start a valid {n,m} is a literal
+                    const bounds = parseBounds(self.pattern[self.pos..]) orelse break;
+                    min = bounds.min;
+                    max = bounds.max;
+                    self.pos += bounds.len;
+                },
+                else => break,
+            }
+            if (node == .assert or node == .empty) return error.InvalidPattern;
+
+            var greedy = true;
+            if (self.pos < self.pattern.len and self.pattern[self.pos] == '?') {
+                greedy = false;
+                self.pos += 1;
+            }
+
+            const inner = try self.allocator.create(Node);
+            inner.* = node;
+            node = .{ .repeat = .{ .node = inner, .min = min, .max = max, .greedy = greedy } };
+        }
+        return node;
+    }
+};
+
+const Bounds = struct {
+    min: u32,
+    max: ?u32,
+    len: usize,
+};
+
+/// Parse `{n}`, `{n,}` or `{n,m}` at the start of `str`.
+fn parseBounds(str: []const u8) ?Bounds {
+    const close = std.mem.indexOfScalar(u8, str, '}') orelse return null;
+    const body = str[1..close];
+    const comma = std.mem.indexOfScalar(u8, body, ',');
+    const min_str = if (comma) |i| body[0..i] else body;
+    const min = std.fmt.parseInt(u32, min_str, 10) catch return null;
+    var max: ?u32 = min;
+    if (comma) |i| {
+        const max_str = body[i + 1 ..];
+        max = if (max_str.len == 0) null else std.fmt.parseInt(u32, max_str, 10) catch return null;
+    }
+    if (min > MAX_REPEAT) return null;
+    if (max) |m| {
+        if (m < min or m > MAX_REPEAT) return null;
+    }
+    return .{ .min = min, .max = max, .len = close + 1 };
+}
+
+fn shorthandRanges(c: u8) []const Range {
+    return switch (std.ascii.toLower(c)) {
+        'd' => &digit_ranges,
+        'w' => &word_ranges,
+        else => &space_ranges,
+    };
+}
+
+fn posixRanges(name: []const u8) ?[]const Range {
+    const table = [_]struct { name: []const u8, ranges: []const Range }{
+        .{ .name = "alpha", .ranges = &.{ .{ .lo = 'A', .hi = 'Z' }, .{ .lo = 'a', .hi = 'z' } } },
+        .{ .name = "digit", .ranges = &digit_ranges },
+        .{ .name = "alnum", .ranges = &.{ .{ .lo = '0', .hi = '9' }, .{ .lo = 'A', .hi = 'Z' }, .{ .lo = 'a', .hi = 'z' } } },
+        .{ .name = "upper", .ranges = &.{.{ .lo = 'A', .hi = 'Z' }} },
+        .{ .name = "lower", .ranges = &.{.{ .lo = 'a', .hi = 'z' }} },
+        .{ .name = "space", .ranges = &space_ranges },
+        .{ .name = "word", .ranges = &word_ranges },
+        .{ .name = "xdigit", .ranges = &.{ .{ .lo = '0', .hi = '9' }, .{ .lo = 'A', .hi = 'F' }, .{ .lo = 'a', .hi = 'f' } } },
+        .{ .name = "punct", .ranges = &.{ .{ .lo = '!', .hi = '/' }, .{ .lo = ':', .hi = '@' }, .{ .lo = '[', .hi = '`' }, .{ .lo = '{', .hi = '~' } } },
+    };
+    for (table) |entry| {
+        if (std.mem.eql(u8, entry.name, name)) return entry.ranges;
+    }
+    return null;
+}
+
+/// Complement of sorted, non-overlapping ranges over all code points.
+fn complement(allocator: std.mem.Allocator, ranges: []const Range) Error![]const Range {
+    var result: std.ArrayListUnmanaged(Range) = .empty;
+    var next: u21 = 0;
+    for (ranges) |r| {
+        if (r.lo > next) try result.append(allocator, .{ .lo = next, .hi = r.lo - 1 });
+        next = r.hi + 1;
+    }
+    try result.append(allocator, .{ .lo = next, .hi = 0x10FFFF });
+    return result.toOwnedSlice(allocator);
+}
+
+// ============================================================================
+// Compiler
+// ============================================================================
+
+const Compiler = struct {
+    allocator: std.mem.Allocator,
+    flags: Flags,
+    prog: std.ArrayListUnmanaged(Inst) = .empty,
+
+    fn push(self: *Compiler, inst: Inst) Error!usize {
+        try self.prog.append(self.allocator, inst);
+        return self.prog.items.len - 1;
+    }
+
+    fn here(self: *const Compiler) usize {
+        return self.prog.items.len;
+    }
+
+    fn fork(body: usize, out: usize, greedy: bool) Inst {
+        return .{ .split = if (greedy) .{ body, out } else .{ out, body } };
+    }
+
+    fn emit(self: *Compiler, node: Node) Error!void {
+        switch (node) {
+            .empty => {},
+            .char => |c| _ = try self.push(.{ .char = c }),
+            .any => _ = try self.push(if (self.flags.dot_all) .any_nl else .any),
+            .class => |idx| _ = try self.push(.{ .class = idx }),
+            .assert => |kind| _ = try self.push(.{ .assert = kind }),
+            .group => |group| {
+                if (group.index) |idx| {
+                    _ = try self.push(.{ .save = idx * 2 });
+                    try self.emit(group.node.*);
+                    _ = try self.push(.{ .save = idx * 2 + 1 });
+                } else {
+                    try self.emit(group.node.*);
+                }
+            },
+            .concat => |items| {
+                for (items) |item| try self.emit(item);
+            },
+            .alternate => |branches| {
+                // split L1, next; L1: a; jmp end; next: split L2, ... ; last branch
+                var jumps: std.ArrayListUnmanaged(usize) = .empty;
+                for (branches, 0..) |branch, i| {
+                    if (i + 1 == branches.len) {
+                        try self.emit(branch);
+                        break;
+                    }
+                    const split = try self.push(.{ .split = .{ 0, 0 } });
+                    try self.emit(branch);
+                    try jumps.append(self.allocator, try self.push(.{ .jmp = 0 }));
+                    self.prog.items[split] = .{ .split = .{ split + 1, self.here() } };
+                }
+                for (jumps.items) |j| self.prog.items[j] = .{ .jmp = self.here() };
+            },
+            .repeat => |rep| {
+                var count: u32 = 0;
+                while (count < rep.min) : (count += 1) try self.emit(rep.node.*);
+
+                if (rep.max) |max| {
+                    // Each optional copy may be skipped straight to the end
+                    var splits: std.ArrayListUnmanaged(usize) = .empty;
+                    while (count < max) : (count += 1) {
+                        try splits.append(self.allocator, try self.push(.{ .split = .{ 0, 0 } }));
+                        try self.emit(rep.node.*);
+                    }
+                    for (splits.items) |s| self.prog.items[s] = fork(s + 1, self.here(), rep.greedy);
+                } else {
+                    const loop = try self.push(.{ .split = .{ 0, 0 } });
+                    try self.emit(rep.node.*);
+                    _ = try self.push(.{ .jmp = loop });
+                    self.prog.items[loop] = fork(loop + 1, self.here(), rep.greedy);
+                }
+            },
+        }
+    }
+};
+
+// ============================================================================
+// Regex Tests
+// ============================================================================
+
+fn expectMatch(pattern: []const u8, flags: Flags, input: []const u8, expected: ?[]const u8) !void {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const re = try Regex.compile(arena.allocator(), pattern, flags);
+    const m = try re.exec(arena.allocator(), input, 0);
+    if (expected) |want| {
+        const span = m.?.span(0).?;
+        try std.testing.expectEqualStrings(want, input[span.start..span.end]);
+    } else {
+        try std.testing.expect(m == null);
+    }
+}
+
+test "regex literals and anchors" {
+    try expectMatch("wor", .{}, "hello world", "wor");
+    try expectMatch("^hello", .{}, "hello world", "hello");
+    try expectMatch("^world", .{}, "hello world", null);
+    try expectMatch("world$", .{}, "hello world", "world");
+    try expectMatch("a\\.b", .{}, "axb a.b", "a.b");
+    try expectMatch("\\bcat\\b", .{}, "concat cat", "cat");
+}
+
+test "regex classes and quantifiers" {
+    try expectMatch("ERR-[0-9]{4}", .{}, "code ERR-123 then ERR-4567", "ERR-4567");
+    try expectMatch("\\d+", .{}, "abc 42 7", "42");
+    try expectMatch("[^a-c]+", .{}, "abcdefabc", "def");
+    try expectMatch("\\w+@\\w+\\.com", .{}, "mail bob@example.com now", "bob@example.com");
+    try expectMatch("[[:upper:]][[:lower:]]*", .{}, "hello World", "World");
+    try expectMatch("a{2,3}", .{}, "a aaaa", "aaa");
+    try expectMatch("x{,2}", .{}, "x{,2}", "x{,2}");
+}
+
+test "regex greedy and lazy" {
+    try expectMatch("<.+>", .{}, "<a><b>", "<a><b>");
+    try expectMatch("<.+?>", .{}, "<a><b>", "<a>");
+    try expectMatch("a|ab", .{}, "ab", "a");
+}
+
+test "regex flags" {
+    try expectMatch("HELLO", .{ .ignore_case = true }, "say hello", "hello");
+    try expectMatch("[A-Z]+", .{ .ignore_case = true }, "123 abc", "abc");
+    try expectMatch("a . c  # comment", .{ .extended = true }, "xabc", "abc");
+    try expectMatch("a.c", .{}, "a\nc", null);
+    try expectMatch("a.c", .{ .dot_all = true }, "a\nc", "a\nc");
+    try std.testing.expect(Flags.parse("gq") == null);
+}
+
+test "regex captures" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const re = try Regex.compile(arena.allocator(), "(?<year>\\d{4})-(\\d{2})(x)?", .{});
+    try std.testing.expectEqual(@as(usize, 4), re.names.len);
+    try std.testing.expectEqualStrings("year", re.names[1].?);
+    try std.testing.expect(re.names[2] == null);
+
+    const input = "on 2024-06-01";
+    const m = (try re.exec(arena.allocator(), input, 0)).?;
+    try std.testing.expectEqual(@as(usize, 3), m.span(1).?.start);
+    try std.testing.expectEqualStrings("06", input[m.span(2).?.start..m.span(2).?.end]);
+    try std.testing.expect(m.span(3) == null);
+}
+
+test "regex pathological pattern runs in linear time" {
+    // (a*)*b against a long run of a's is exponential for a backtracking engine
+    const input = "a" ** 5000;
+    try expectMatch("(a*)*b", .{}, input, null);
+}
+
+test "regex unicode code points" {
+    try expectMatch("h.llo", .{}, "hällo", "hällo");
+    try expectMatch("[ä-ö]+", .{}, "xäöx", "äö");
+}
+
+test "regex invalid patterns" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const bad = [_][]const u8{ "(abc", "abc)", "[abc", "*a", "(?=a)", "\\1", "[z-a]", "\\" };
+    for (bad) |pattern| {
+        try std.testing.expectError(error.InvalidPattern, Regex.compile(arena.allocator(), pattern, .{}));
+    }
+}
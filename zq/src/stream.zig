@@ -0,0 +1,404 @@
+const std = @import("std");
+const output = @import("output.zig");
+const number = @import("number.zig");
+
+// ============================================================================
+// Streaming
+// ============================================================================
+//
+// jq's streaming form turns a document into a flat sequence of events, so a
+// value far bigger than memory can be processed one piece at a time:
+//
+//   [path, leaf]   a scalar or an empty container at `path`
+//   [path]         the container holding the last `path` element just closed
+//
+// {"a":[1,2]} streams as [["a",0],1], [["a",1],2], [["a",1]], [["a"]]. A
+// top-level scalar is the single event [[],v].
+//
+// EventReader produces these events straight from an I/O reader (zq
+// --stream): it keeps only the current path, so memory is bounded by the
+// nesting depth and the largest scalar. appendEvents does the same for a
+// value already in memory (tostream), and Assembler puts events back
+// together (fromstream).
+
+pub const Error = error{ OutOfMemory, ReadFailed, MalformedJson };
+
+/// Incremental JSON tokenizer that emits stream events. Accepts any number
+/// of whitespace-separated documents, like NDJSON.
+pub const EventReader = struct {
+    reader: *std.Io.Reader,
+    /// Owns the current path's keys and the container stack
+    gpa: std.mem.Allocator,
+    path: std.ArrayListUnmanaged(std.json.Value) = .empty,
+    /// Whether each open container is an object (true) or an array (false)
+    in_object: std.ArrayListUnmanaged(bool) = .empty,
+    state: enum { document, value, after_value } = .document,
+    /// Scratch space for the raw text of one scalar
+    scalar: std.ArrayListUnmanaged(u8) = .empty,
+    /// Treat RS (0x1e) as whitespace, for --seq input
+    seq: bool = false,
+    /// Current input line, for error messages
+    line: usize = 1,
+
+    pub fn init(gpa: std.mem.Allocator, reader: *std.Io.Reader) EventReader {
+        return .{ .reader = reader, .gpa = gpa };
+    }
+
+    pub fn deinit(self: *EventReader) void {
+        for (self.path.items) |k| {
+            if (k == .string) self.gpa.free(k.string);
+        }
+        self.path.deinit(self.gpa);
+        self.in_object.deinit(self.gpa);
+        self.scalar.deinit(self.gpa);
+    }
+
+    /// The next event, allocated from `allocator`, or null at the end of the input.
+    pub fn next(self: *EventReader, allocator: std.mem.Allocator) Error!?std.json.Value {
+        while (true) {
+            switch (self.state) {
+                .document => {
+                    if (try self.skipSpace() == null) return null;
+                    self.state = .value;
+                },
+                .value => {
+                    const c = (try self.skipSpace()) orelse return error.MalformedJson;
+                    switch (c) {
+                        '{' => {
+                            self.reader.toss(1);
+                            if (try self.at('}')) {
+                                self.reader.toss(1);
+                                return try self.leaf(allocator, .{ .object = .init(allocator) });
+                            }
+                            try self.in_object.append(self.gpa, true);
+                            try self.path.append(self.gpa, .{ .string = try self.key() });
+                        },
+                        '[' => {
+                            self.reader.toss(1);
+                            if (try self.at(']')) {
+                                self.reader.toss(1);
+                                return try self.leaf(allocator, .{ .array = .init(allocator) });
+                            }
+                            try self.in_object.append(self.gpa, false);
+                            try self.path.append(self.gpa, .{ .integer = 0 });
+                        },
+                        else => return try self.leaf(allocator, try self.scalarValue(allocator)),
+                    }
+                },
+                .after_value => {
+                    const c = (try self.skipSpace()) orelse return error.MalformedJson;
+                    self.reader.toss(1);
+                    const is_object = self.in_object.items[self.in_object.items.len - 1];
+                    const last = &self.path.items[self.path.items.len - 1];
+                    switch (c) {
+                        ',' => {
+                            if (is_object) {
+                                self.gpa.free(last.string);
+                                last.* = .{ .string = try self.key() };
+                            } else {
+                                last.integer += 1;
+                            }
+                            self.state = .value;
+                        },
+                        '}', ']' => {
+                            if ((c == '}') != is_object) return error.MalformedJson;
+                            const closing = try self.event(allocator, null);
+                            const closed = self.path.pop().?;
+                            if (closed == .string) self.gpa.free(closed.string);
+                            _ = self.in_object.pop();
+                            self.state = if (self.in_object.items.len == 0) .document else .after_value;
+                            return closing;
+                        },
+                        else => return error.MalformedJson,
+                    }
+                },
+            }
+        }
+    }
+
+    fn leaf(self: *EventReader, allocator: std.mem.Allocator, value: std.json.Value) Error!std.json.Value {
+        self.state = if (self.in_object.items.len == 0) .document else .after_value;
+        return self.event(allocator, value);
+    }
+
+    /// [path, value], or [path] for a closing event
+    fn event(self: *EventReader, allocator: std.mem.Allocator, value: ?std.json.Value) Error!std.json.Value {
+        // Keys are copied: the reader frees its own when containers close
+        const path = try allocator.alloc(std.json.Value, self.path.items.len);
+        for (self.path.items, path) |k, *copy| {
+            copy.* = switch (k) {
+                .string => |s| .{ .string = try allocator.dupe(u8, s) },
+                else => k,
+            };
+        }
+        return eventValue(allocator, path, value);
+    }
+
+    /// Read `"key":` and return the key, owned by gpa.
+    fn key(self: *EventReader) Error![]const u8 {
+        if (!try self.at('"')) return error.MalformedJson;
+        const parsed = try self.scalarValue(self.gpa);
+        if (!try self.at(':')) return error.MalformedJson;
+        self.reader.toss(1);
+        return parsed.string;
+    }
+
+    /// Read one string, number or literal and parse it like a whole record,
+    /// so escapes and number types match the line-at-a-time reader.
+    fn scalarValue(self: *EventReader, allocator: std.mem.Allocator) Error!std.json.Value {
+        self.scalar.clearRetainingCapacity();
+        const first = (try self.peek()) orelse return error.MalformedJson;
+        if (first == '"') {
+            try self.take();
+            var escaped = false;
+            while (true) {
+                const c = (try self.peek()) orelse return error.MalformedJson;
+                try self.take();
+                if (c == '\n') return error.MalformedJson;
+                if (escaped) {
+                    escaped = false;
+                } else if (c == '\\') {
+                    escaped = true;
+                } else if (c == '"') {
+                    break;
+                }
+            }
+        } else {
+            while (try self.peek()) |c| {
+                switch (c) {
+                    ' ', '\t', '\r', '\n', ',', ']', '}', ':', 0x1e => break,
+                    '{', '[', '"' => return error.MalformedJson,
+                    else => try self.take(),
+                }
+            }
+        }
+        // alloc_always: the result must not point into the scratch buffer
+        return number.parseJson(allocator, self.scalar.items, .{ .allocate = .alloc_always }) catch |err| switch (err) {
+            error.OutOfMemory => error.OutOfMemory,
+            else => error.MalformedJson,
+        };
+    }
+
+    /// Move the next byte into the scalar buffer.
+    fn take(self: *EventReader) Error!void {
+        try self.scalar.append(self.gpa, self.reader.takeByte() catch return error.ReadFailed);
+    }
+
+    fn peek(self: *EventReader) Error!?u8 {
+        return self.reader.peekByte() catch |err| switch (err) {
+            error.EndOfStream => null,
+            error.ReadFailed => error.ReadFailed,
+        };
+    }
+
+    /// Skip whitespace and report whether the next byte is `c`.
+    fn at(self: *EventReader, c: u8) Error!bool {
+        const next_byte = (try self.skipSpace()) orelse return false;
+        return next_byte == c;
+    }
+
+    /// Skip whitespace and return the next byte without consuming it.
+    fn skipSpace(self: *EventReader) Error!?u8 {
+        while (try self.peek()) |c| {
+            switch (c) {
+                '\n' => self.line += 1,
+                ' ', '\t', '\r' => {},
+                0x1e => if (!self.seq) return c,
+                else => return c,
+            }
+            self.reader.toss(1);
+        }
+        return null;
+    }
+};
+
+/// tostream: append the events of `value` to `out`, in the order
+/// EventReader would produce them.
+pub fn appendEvents(allocator: std.mem.Allocator, value: std.json.Value, out: *std.ArrayListUnmanaged(std.json.Value)) error{OutOfMemory}!void {
+    var path: std.ArrayListUnmanaged(std.json.Value) = .empty;
+    try appendEventsAt(allocator, value, &path, out);
+}
+
+fn appendEventsAt(allocator: std.mem.Allocator, value: std.json.Value, path: *std.ArrayListUnmanaged(std.json.Value), out: *std.ArrayListUnmanaged(std.json.Value)) error{OutOfMemory}!void {
+    switch (value) {
+        .array => |arr| if (arr.items.len > 0) {
+            for (arr.items, 0..) |item, i| {
+                try path.append(allocator, .{ .integer = @intCast(i) });
+                try appendEventsAt(allocator, item, path, out);
+                if (i + 1 < arr.items.len) _ = path.pop();
+            }
+            try out.append(allocator, try eventValue(allocator, path.items, null));
+            _ = path.pop();
+            return;
+        },
+        .object => |obj| if (obj.count() > 0) {
+            var it = obj.iterator();
+            var i: usize = 0;
+            while (it.next()) |entry| : (i += 1) {
+                try path.append(allocator, .{ .string = entry.key_ptr.* });
+                try appendEventsAt(allocator, entry.value_ptr.*, path, out);
+                if (i + 1 < obj.count()) _ = path.pop();
+            }
+            try out.append(allocator, try eventValue(allocator, path.items, null));
+            _ = path.pop();
+            return;
+        },
+        else => {},
+    }
+    try out.append(allocator, try eventValue(allocator, path.items, value));
+}
+
+fn eventValue(allocator: std.mem.Allocator, path: []const std.json.Value, value: ?std.json.Value) error{OutOfMemory}!std.json.Value {
+    const path_items = try allocator.dupe(std.json.Value, path);
+    const items = try allocator.alloc(std.json.Value, if (value == null) 1 else 2);
+    items[0] = .{ .array = .{ .items = path_items, .capacity = path_items.len, .allocator = allocator } };
+    if (value) |v| items[1] = v;
+    return .{ .array = .{ .items = items, .capacity = items.len, .allocator = allocator } };
+}
+
+/// fromstream: rebuilds values from events. A value is complete at a
+/// top-level [[], v] event or when its top-level container closes.
+pub const Assembler = struct {
+    value: std.json.Value = .null,
+    /// The last push completed `value`; the next one starts a new value
+    done: bool = false,
+
+    /// Apply one event. Returns the finished value, if the event completed one.
+    /// Everything kept is copied into `allocator`, so events can be freed.
+    pub fn push(self: *Assembler, allocator: std.mem.Allocator, event: std.json.Value) error{ OutOfMemory, InvalidEvent }!?std.json.Value {
+        if (self.done) {
+            self.value = .null;
+            self.done = false;
+        }
+        if (event != .array or event.array.items.len == 0 or event.array.items.len > 2) return error.InvalidEvent;
+        const items = event.array.items;
+        if (items[0] != .array) return error.InvalidEvent;
+        const path = items[0].array.items;
+
+        if (items.len == 1) {
+            // Only the close of a top-level container finishes a value
+            if (path.len != 1) return null;
+            self.done = true;
+            return self.value;
+        }
+        if (path.len == 0) {
+            self.done = true;
+            return try copyLeaf(allocator, items[1]);
+        }
+
+        var cur = &self.value;
+        for (path) |key| {
+            switch (key) {
+                .string => |name| {
+                    if (cur.* == .null) cur.* = .{ .object = .init(allocator) };
+                    if (cur.* != .object) return error.InvalidEvent;
+                    const entry = try cur.object.getOrPut(name);
+                    if (!entry.found_existing) {
+                        entry.key_ptr.* = try allocator.dupe(u8, name);
+                        entry.value_ptr.* = .null;
+                    }
+                    cur = entry.value_ptr;
+                },
+                .integer => |i| {
+                    if (cur.* == .null) cur.* = .{ .array = .init(allocator) };
+                    if (cur.* != .array or i < 0) return error.InvalidEvent;
+                    const idx: usize = @intCast(i);
+                    while (cur.array.items.len <= idx) try cur.array.append(.null);
+                    cur = &cur.array.items[idx];
+                },
+                else => return error.InvalidEvent,
+            }
+        }
+        cur.* = try copyLeaf(allocator, items[1]);
+        return null;
+    }
+};
+
+/// Leaves are scalars or empty containers; copy their storage into `allocator`.
+fn copyLeaf(allocator: std.mem.Allocator, leaf: std.json.Value) error{OutOfMemory}!std.json.Value {
+    return switch (leaf) {
+        .string => |s| .{ .string = try allocator.dupe(u8, s) },
+        .number_string => |s| .{ .number_string = try allocator.dupe(u8, s) },
+        .array => |arr| if (arr.items.len == 0) .{ .array = .init(allocator) } else try deepCopy(allocator, leaf),
+        .object => |obj| if (obj.count() == 0) .{ .object = .init(allocator) } else try deepCopy(allocator, leaf),
+        else => leaf,
+    };
+}
+
+fn deepCopy(allocator: std.mem.Allocator, value: std.json.Value) error{OutOfMemory}!std.json.Value {
+    switch (value) {
+        .array => |arr| {
+            var copy = try std.json.Array.initCapacity(allocator, arr.items.len);
+            for (arr.items) |item| copy.appendAssumeCapacity(try deepCopy(allocator, item));
+            return .{ .array = copy };
+        },
+        .object => |obj| {
+            var copy: std.json.ObjectMap = .init(allocator);
+            var it = obj.iterator();
+            while (it.next()) |entry| {
+                try copy.put(try allocator.dupe(u8, entry.key_ptr.*), try deepCopy(allocator, entry.value_ptr.*));
+            }
+            return .{ .object = copy };
+        },
+        else => return copyLeaf(allocator, value),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+fn expectEvents(expected: []const u8, input: []const u8) !void {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    var reader: std.Io.Reader = .fixed(input);
+    var events = EventReader.init(std.testing.allocator, &reader);
+    defer events.deinit();
+
+    var aw: std.Io.Writer.Allocating = .init(arena.allocator());
+    while (try events.next(arena.allocator())) |event| {
+        try output.writeJsonValue(&aw.writer, event);
+        try aw.writer.writeByte('\n');
+    }
+    try std.testing.expectEqualStrings(expected, aw.written());
+}
+
+test "event reader streams nested documents" {
+    try expectEvents(
+        \\[["a",0],1]
+        \\[["a",1],{}]
+        \\[["a",1]]
+        \\[["b"],"x\"y"]
+        \\[["b"]]
+        \\[[],3]
+        \\[[],[]]
+        \\
+    , "{\"a\": [1, {}],\n \"b\": \"x\\\"y\"}\n3 []");
+}
+
+test "event reader rejects malformed input" {
+    try std.testing.expectError(error.MalformedJson, expectEvents("", "{\"a\" 1}"));
+    try std.testing.expectError(error.MalformedJson, expectEvents("", "[1,2}"));
+    try std.testing.expectError(error.MalformedJson, expectEvents("", "[1,"));
+}
+
+test "tostream matches the event reader and fromstream inverts it" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const allocator = arena.allocator();
+
+    const text = "{\"a\":[1,{\"b\":null}],\"c\":{}}";
+    const value = try std.json.parseFromSliceLeaky(std.json.Value, allocator, text, .{});
+    var events: std.ArrayListUnmanaged(std.json.Value) = .empty;
+    try appendEvents(allocator, value, &events);
+    try std.testing.expectEqual(@as(usize, 6), events.items.len);
+
+    var assembler: Assembler = .{};
+    var rebuilt: ?std.json.Value = null;
+    for (events.items) |event| {
+        if (try assembler.push(allocator, event)) |done| rebuilt = done;
+    }
+    var aw: std.Io.Writer.Allocating = .init(allocator);
+    try output.writeJsonValue(&aw.writer, rebuilt.?);
+    try std.testing.expectEqualStrings(text, aw.written());
+}
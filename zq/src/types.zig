@@ -1,4 +1,5 @@
 const std = @import("std");
+const Regex = @import("regex.zig").Regex;
 
 // ============================================================================
 // Types
@@ -7,10 +8,12 @@ const std = @import("std");
 pub const CompareValue = union(enum) {
     int: i64,
     float: f64,
+    number: []const u8, // literal text an i64/f64 would change, compared exactly
     string: []const u8,
     boolean: bool,
     null_val,
     none,
+    variable: []const u8, // $name, resolved against the evaluation scope
 };
 
 pub const CompareOp = enum {
@@ -79,12 +82,40 @@ pub const Expr = union(enum) {
     by_func: ByFuncExpr, // group_by(.field), sort_by(.field), etc.
     array: ArrayExpr, // [.x, .y, .z]
     del: DelExpr, // del(.key)
+    // Variables
+    variable: []const u8, // $name
+    bind: BindExpr, // .x as $v | body
+    loc: LocExpr, // $__loc__
+    reduce: ReduceExpr, // reduce .[] as $x (init; update)
+    foreach: ForeachExpr, // foreach .[] as $x (init; update; extract)
+    // User-defined functions
+    define: DefineExpr, // def f(g; $x): body; rest
+    call: CallExpr, // f or f(.a; 1)
+    regex: RegexExpr, // test("re"), match, capture, scan, split, splits, sub, gsub
+    // Strings
+    format: FormatKind, // @csv, @tsv, @json, ...
+    interp: InterpExpr, // "\(.a) and \(.b)", @sh "echo \(.x)"
+    // Paths and assignment
+    assign: AssignExpr, // .a |= f, .a = 1, .n += 1, .x //= "default"
+    path_func: PathFuncExpr, // path(f), paths, getpath(p), setpath(p; v), delpaths(ps), del(f), walk(f)
+    // Errors and control flow
+    try_catch: TryExpr, // try .x catch "bad", f?
+    raise: ?*Expr, // error or error(msg)
+    label: LabelExpr, // label $out | body
+    break_label: []const u8, // break $out (internal label name)
+    // Generators
+    generator: GeneratorExpr, // range(n), limit(n; f), first(f), recurse, .., inputs, $ENV, ...
+    // Dates
+    date_func: DateFuncExpr, // strptime(fmt), strftime(fmt), mktime, gmtime, todate, date_trunc(u), ...
+    // jq's builtin library
+    lib_func: LibFuncExpr, // IN(s), INDEX(f), any(g; cond), indices(s), pow(a; b), has(.k), ...
 };
 
 pub const LiteralExpr = union(enum) {
     string: []const u8,
     integer: i64,
     float: f64,
+    number: []const u8, // evaluates to an exact number string
     boolean: bool,
     null_val,
 };
@@ -198,6 +229,7 @@ pub const BuiltinKind = enum {
     @"int", // Coerce to integer
     @"float", // Coerce to float (note: using @"" to escape keyword-like name)
     @"bool", // Coerce to boolean
+    todecimal, // Exact decimal: + - * without rounding (see number.zig)
     // Case functions (Sprint 06)
     capitalize, // First letter uppercase
     titlecase, // Each word capitalized
@@ -207,8 +239,20 @@ pub const BuiltinKind = enum {
     // More case functions (Sprint 07)
     pascalcase, // ToPascalCase (like camelCase but first letter uppercase)
     screamcase, // TO_SCREAMING_SNAKE_CASE
-    // Predicate functions (Sprint 06)
-    empty, // True if empty string/array/object
+    // Core jq filters
+    empty, // No output
+    not, // Negated truthiness
+    // Type selectors: the input if it has the type, else nothing
+    arrays,
+    objects,
+    iterables, // arrays and objects
+    booleans,
+    numbers,
+    strings,
+    nulls,
+    scalars, // anything but arrays and objects
+    finites, // numbers that aren't infinite or NaN
+    normals, // finite numbers that aren't zero or subnormal
     // String splitting (Sprint 06)
     words, // Split string into words
     lines, // Split string into lines
@@ -219,6 +263,56 @@ pub const BuiltinKind = enum {
     xid_time, // Extract epoch seconds from XID string
     delta, // Seconds since a timestamp (epoch or ISO)
     ago, // Human-friendly relative time ("7 days, 8 hours ago")
+    // jq builtin library
+    keys_unsorted, // Object keys in input order
+    utf8bytelength, // String length in bytes
+    tojson, // Value as JSON text
+    fromjson, // JSON text as a value
+    ascii, // Codepoint 0-127 as a one-character string
+    implode, // Array of codepoints -> string
+    explode, // String -> array of codepoints
+    transpose, // Array of rows -> array of columns, padded with null
+    combinations, // Every way of picking one element from each array
+    toarray, // Arrays as-is, anything else wrapped in one
+    any, // Some element is truthy
+    all, // Every element is truthy
+    // Floating point classes and constants
+    infinite,
+    nan,
+    isinfinite,
+    isnan,
+    isnormal,
+    // libm functions; one number in, one out
+    trunc,
+    rint, // Round half to even
+    nearbyint, // Same as rint
+    cbrt,
+    exp2,
+    exp10,
+    log, // Natural logarithm, like ln
+    log1p, // log(1 + x), precise near 0
+    expm1, // exp(x) - 1, precise near 0
+    logb, // Binary exponent
+    significand, // Mantissa scaled to [1, 2)
+    gamma, // log|Γ(x)|, like lgamma
+    lgamma,
+    tgamma, // Γ(x)
+    lgamma_r, // [lgamma, sign of Γ(x)]
+    frexp, // [mantissa in [0.5, 1), exponent]
+    modf, // [fractional part, integer part]
+    sinh,
+    cosh,
+    tanh,
+    asinh,
+    acosh,
+    atanh,
+    // Environment
+    builtins, // "name/arity" for every builtin
+    input_filename, // Always null: zq reads stdin
+    have_literal_numbers, // true: number literals keep their text
+    have_decnum, // false: exact numbers are text, not decNumber
+    debug, // Print ["DEBUG:", .] to stderr, pass the input on
+    stderr, // Print the input as JSON to stderr, pass it on
 };
 
 pub const BuiltinExpr = struct {
@@ -276,7 +370,6 @@ pub const StrFuncKind = enum {
     ltrimstr, // ltrimstr("prefix")
     rtrimstr, // rtrimstr("suffix")
     has, // has("key")
-    @"test", // test("pattern") - regex-like matching
 };
 
 pub const StrFuncExpr = struct {
@@ -284,6 +377,49 @@ pub const StrFuncExpr = struct {
     arg: []const u8,
 };
 
+// Regex family; the pattern is compiled once at parse time
+pub const RegexKind = enum {
+    @"test", // test("re"; "flags") - bool
+    match, // match("re") - match objects with offsets and captures
+    capture, // capture("(?<name>re)") - object of named captures
+    scan, // scan("re") - every match (or its captures)
+    split, // split("re"; "flags") - array of pieces
+    splits, // splits("re") - stream of pieces
+    sub, // sub("re"; "replacement") - first match
+    gsub, // gsub("re"; "replacement") - every match
+};
+
+pub const RegexExpr = struct {
+    kind: RegexKind,
+    regex: *const Regex,
+    replacement: ?*Expr = null, // sub/gsub: evaluated with the named captures as input
+};
+
+// @format filters; also applied to each \(...) inside a prefixed string
+pub const FormatKind = enum {
+    text, // tostring
+    json, // tojson
+    csv, // array -> comma-separated, strings double-quoted
+    tsv, // array -> tab-separated, \t \n \r \\ escaped
+    html, // < > & ' " as entities
+    uri, // percent-encode all but unreserved characters
+    sh, // single-quoted for POSIX shells, arrays space-joined
+    base64,
+    base64d,
+    base32,
+    base32d,
+};
+
+pub const InterpPart = union(enum) {
+    literal: []const u8, // unescaped text
+    expr: *Expr, // \(expr), evaluated against the string's input
+};
+
+pub const InterpExpr = struct {
+    parts: []const InterpPart,
+    format: FormatKind = .text,
+};
+
 // Sprint 03: map(expr)
 pub const MapExpr = struct {
     inner: *Expr,
@@ -300,7 +436,8 @@ pub const ByFuncKind = enum {
 
 pub const ByFuncExpr = struct {
     kind: ByFuncKind,
-    path: [][]const u8,
+    path: [][]const u8, // .field keys; a missing field is null
+    key: ?*Expr = null, // any other f, evaluated as [f]
 };
 
 // Sprint 03: Array literal [.x, .y, .z]
@@ -313,12 +450,246 @@ pub const DelExpr = struct {
     index: ?i64 = null, // optional array index (e.g., 0 for del(.arr[0]))
 };
 
+pub const AssignOp = enum {
+    update, // |=  runs the right side on each selected value
+    set, // =
+    add, // +=
+    sub, // -=
+    mul, // *=
+    div, // /=
+    mod, // %=
+    alt, // //=
+};
+
+// Assignment: the target is a path expression evaluated against the input
+pub const AssignExpr = struct {
+    target: *Expr,
+    op: AssignOp,
+    rhs: *Expr,
+};
+
+pub const PathFuncKind = enum {
+    path, // path(f) - the paths f selects, as arrays
+    paths, // paths, paths(f) - every path below the input (whose value passes f)
+    leaf_paths, // paths to scalars
+    getpath, // getpath(["a", 0])
+    setpath, // setpath(p; v)
+    delpaths, // delpaths([p, ...])
+    del, // del(f) for path expressions DelExpr can't represent
+    walk, // walk(f) - apply f bottom-up
+};
+
+pub const PathFuncExpr = struct {
+    kind: PathFuncKind,
+    args: []*Expr,
+};
+
+pub const GeneratorKind = enum {
+    range, // range(upto), range(from; upto), range(from; upto; by)
+    limit, // limit(n; f) - the first n outputs of f
+    first, // first(f)
+    last, // last(f)
+    nth, // nth(n) is .[n]; nth(n; f) is the n-th output of f
+    until, // until(cond; update) - apply update until cond holds
+    @"while", // while(cond; update) - emit states while cond holds
+    repeat, // repeat(f) - ., then f applied over and over
+    recurse, // recurse, recurse(f), recurse(f; cond), ..
+    input, // the next input record
+    inputs, // every remaining input record
+    env, // env, $ENV - the environment as an object
+    tostream, // [path, leaf] and closing [path] events for .
+    fromstream, // fromstream(f) - rebuild the values described by f's events
+    truncate_stream, // truncate_stream(f) - drop the first . path elements of f's events
+};
+
+pub const GeneratorExpr = struct {
+    kind: GeneratorKind,
+    args: []*Expr = &.{},
+};
+
+// Dates are epoch seconds, ISO 8601 strings or jq's broken-down time arrays
+// [sec, min, hour, mday, mon (0-11), year, wday, yday], always in UTC
+pub const DateFuncKind = enum {
+    gmtime, // epoch seconds -> broken-down time
+    mktime, // broken-down time -> epoch seconds
+    strptime, // strptime(fmt) - string -> broken-down time, %z offsets folded into UTC
+    strftime, // strftime(fmt), strftime(fmt; "+05:30") - format in UTC or at an offset
+    todate, // todate, todateiso8601, date - "2015-03-05T23:51:47Z"
+    fromdate, // fromdate, fromdateiso8601 - ISO 8601 with Z or +hh:mm -> epoch seconds
+    date_trunc, // date_trunc("hour") - round down to the start of a unit
+    dateadd, // dateadd("days"; n) - months and years keep the day, clamped to the month
+    datesub, // datesub("days"; n)
+};
+
+pub const DateFuncExpr = struct {
+    kind: DateFuncKind,
+    args: []*Expr = &.{},
+};
+
+// jq library functions that take filter arguments. Predicate arguments
+// (any/all conditions) are parsed as select(...) so comparisons work.
+pub const LibFuncKind = enum {
+    // Membership and search
+    in, // in(obj) - the input is a key of obj
+    inside, // inside(b) - b contains the input
+    has, // has(key) with a computed key, e.g. has(0) on arrays
+    contains, // contains(b) - recursive containment for arrays and objects
+    startswith, // startswith/endswith/ltrimstr/rtrimstr/split/join with computed arguments
+    endswith,
+    ltrimstr,
+    rtrimstr,
+    split,
+    join,
+    indices, // indices(s) - offsets of s in a string or array
+    index, // index(s) - the first offset, or null
+    rindex, // rindex(s) - the last offset, or null
+    // Streams
+    any, // any(cond), any(gen; cond)
+    all, // all(cond), all(gen; cond)
+    isempty, // isempty(g) - g has no outputs
+    isvalid, // isvalid(f) - f runs without an error
+    combinations, // combinations(n) - n copies of the input array
+    flatten, // flatten(depth)
+    // SQL-style
+    IN, // IN(s), IN(source; s) - some output of source equals some output of s
+    INDEX, // INDEX(idx_expr), INDEX(stream; idx_expr) - object keyed by idx_expr
+    JOIN, // JOIN($idx; idx_expr), JOIN($idx; stream; idx_expr), JOIN($idx; stream; idx_expr; join_expr)
+    // Paths
+    pick, // pick(pathexps) - only the given paths, nested as in the input; one arg per comma-separated path
+    // Two- and three-argument math
+    pow,
+    atan2,
+    fmin,
+    fmax,
+    fmod,
+    fdim,
+    copysign,
+    drem, // IEEE remainder
+    ldexp, // x * 2^n
+    scalb,
+    scalbln,
+    nextafter,
+    nexttoward,
+    fma, // x * y + z in one rounding
+    // Control
+    debug, // debug(msg) - print ["DEBUG:", msg] to stderr, pass the input on
+    halt, // exit 0 without further output
+    halt_error, // halt_error, halt_error(code) - print the input to stderr and exit (5)
+};
+
+pub const LibFuncExpr = struct {
+    kind: LibFuncKind,
+    args: []*Expr = &.{},
+};
+
+// `try body catch handler`; postfix `?` is try without a handler
+pub const TryExpr = struct {
+    body: *Expr,
+    handler: ?*Expr = null, // null drops the error
+};
+
+// `label $name | body`; `break $name` inside body stops it
+pub const LabelExpr = struct {
+    name: []const u8, // internal name, "*label-" ++ name, so it can't collide with $vars
+    body: *Expr,
+};
+
+// Variable binding: `source as pattern | body`
+pub const BindExpr = struct {
+    source: *Expr,
+    pattern: Pattern,
+    body: *Expr,
+};
+
+/// Destructuring target on the right of `as`.
+pub const Pattern = union(enum) {
+    variable: []const u8, // $name
+    array: []Pattern, // [$a, $b]
+    object: []ObjectPatternField, // {a: $x, $b}
+};
+
+pub const ObjectPatternField = struct {
+    key: []const u8,
+    value: Pattern,
+};
+
+pub const LocExpr = struct {
+    line: i64,
+};
+
+// Fold: `reduce source as pattern (init; update)`
+pub const ReduceExpr = struct {
+    source: *Expr,
+    pattern: Pattern,
+    init: *Expr,
+    update: *Expr,
+};
+
+// Streaming fold: `foreach source as pattern (init; update; extract)`
+pub const ForeachExpr = struct {
+    source: *Expr,
+    pattern: Pattern,
+    init: *Expr,
+    update: *Expr,
+    extract: ?*Expr, // null emits the state itself
+};
+
+// Function definition: `def name(params): body;`
+pub const FuncDef = struct {
+    name: []const u8,
+    params: []const FuncParam,
+    body: *Expr,
+};
+
+pub const FuncParam = struct {
+    name: []const u8,
+    is_value: bool, // `$x` (evaluated once per value) vs `f` (passed as a filter)
+};
+
+// `def ...; rest` - the definition is visible in its own body and in rest
+pub const DefineExpr = struct {
+    def: *const FuncDef,
+    rest: *Expr,
+};
+
+pub const CallExpr = struct {
+    name: []const u8,
+    args: []*Expr,
+};
+
+/// ANSI SGR parameters for each kind of output, in $JQ_COLORS order.
+/// The defaults are jq 1.7.1's.
+pub const Palette = struct {
+    null_value: []const u8 = "0;90",
+    false_value: []const u8 = "0;39",
+    true_value: []const u8 = "0;39",
+    number: []const u8 = "0;39",
+    string: []const u8 = "0;32",
+    array: []const u8 = "1;39",
+    object: []const u8 = "1;39",
+    object_key: []const u8 = "34;1",
+};
+
 pub const Config = struct {
-    compact: bool = true,
+    compact: bool = true, // pretty-printed by default when stdout is a terminal
     raw_strings: bool = false,
-    exit_on_empty: bool = false,
+    exit_status: bool = false, // -e: exit code reflects the last output
     skip_invalid: bool = true,
     slurp: bool = false,
+    strict: bool = false, // uncaught errors stop zq instead of skipping the record
+    plan: bool = true, // decide select(...) filters from the raw line when possible
+    null_input: bool = false, // -n: run once with null as the input
+    raw_input: bool = false, // -R: each input line is a string, not JSON
+    indent: u8 = 2, // spaces per level when not compact
+    tab: bool = false, // indent with tabs instead of spaces
+    sort_keys: bool = false,
+    join_output: bool = false, // -j: no newline after each output
+    seq: bool = false, // RFC 7464: RS before each output, stripped from input
+    stream: bool = false, // --stream: input is [path, leaf] events, read incrementally
+    color: bool = false, // -C: ANSI colors (the default when stdout is a terminal)
+    palette: Palette = .{}, // $JQ_COLORS
+    unbuffered: bool = false, // flush after every output
+    jobs: usize = 1, // --jobs N: evaluate chunks of records on N threads
 };
 
 // ============================================================================
@@ -331,6 +702,9 @@ pub const ParseError = error{
     InvalidValue,
     OutOfMemory,
     UnsupportedFeature,
+    UndefinedVariable,
+    ModuleNotFound,
+    InvalidRegex,
 };
 
 /// Maximum recursion depth for expression parsing to prevent stack overflow
@@ -345,10 +719,63 @@ pub const ErrorContext = struct {
     suggestion: []const u8 = "",
     /// Current parsing recursion depth
     depth: u32 = 0,
+    /// Full expression text, used to compute line numbers for $__loc__
+    source: []const u8 = "",
+    /// Variables in scope at the current parse position
+    scope: ?*const VarScope = null,
+    /// User-defined functions (and filter parameters) in scope
+    funcs: ?*const FuncScope = null,
+    /// Directories searched by `include "name";` (zq -L)
+    lib_dirs: []const []const u8 = &.{},
+    /// Number of input/inputs calls; any read past the current record
+    input_calls: u32 = 0,
+    /// Number of seq, debug and stderr calls, whose effects depend on record order
+    ordered_calls: u32 = 0,
+};
+
+/// Parse-time scope frame listing the variables bound by one `as` pattern.
+/// Frames live on the parser's stack and are unlinked when the body is done.
+pub const VarScope = struct {
+    parent: ?*const VarScope = null,
+    names: []const []const u8,
+
+    pub fn contains(scope: ?*const VarScope, name: []const u8) bool {
+        var current = scope;
+        while (current) |frame| : (current = frame.parent) {
+            for (frame.names) |n| {
+                if (std.mem.eql(u8, n, name)) return true;
+            }
+        }
+        return false;
+    }
+};
+
+/// Parse-time scope frame for one function name/arity pair.
+pub const FuncScope = struct {
+    parent: ?*const FuncScope = null,
+    name: []const u8,
+    arity: usize,
+
+    pub fn contains(scope: ?*const FuncScope, name: []const u8, arity: usize) bool {
+        var current = scope;
+        while (current) |frame| : (current = frame.parent) {
+            if (frame.arity == arity and std.mem.eql(u8, frame.name, name)) return true;
+        }
+        return false;
+    }
 };
 
 pub const EvalError = error{
     OutOfMemory,
+    /// Assignment target or path() argument is not a path expression,
+    /// or a path doesn't fit the value's shape (e.g. setting .a on a number)
+    InvalidPath,
+    /// error(v) or a type error; the payload is available from eval.errorValue()
+    Raised,
+    /// break $label unwinding to its label
+    Break,
+    /// halt or halt_error: stop zq; eval.haltStatus() has the exit code
+    Halt,
 };
 
 pub const EvalResult = struct {
@@ -4,10 +4,30 @@ const std = @import("std");
 // These test the full pipeline: stdin -> parse -> eval -> stdout
 
 fn runZq(allocator: std.mem.Allocator, expr: []const u8, input: []const u8) ![]u8 {
+    const run = try runZqArgs(allocator, &.{expr}, input);
+    if (run.code != 0) {
+        std.debug.print("ZQ failed with exit code {d}: {s}\n", .{ run.code, run.stderr });
+        return error.ProcessFailed;
+    }
+    return run.stdout;
+}
+
+const ZqRun = struct {
+    stdout: []u8,
+    stderr: []u8,
+    code: u8,
+};
+
+/// Run zq with command line arguments and report its exit code instead of failing on it.
+fn runZqArgs(allocator: std.mem.Allocator, args: []const []const u8, input: []const u8) !ZqRun {
     const exe_path = "zig-out/bin/zq";
 
+    var argv: std.ArrayListUnmanaged([]const u8) = .empty;
+    try argv.append(allocator, exe_path);
+    try argv.appendSlice(allocator, args);
+
     // Zig 0.15.2 API: init takes (argv, allocator) as positional args
-    var child = std.process.Child.init(&[_][]const u8{ exe_path, expr }, allocator);
+    var child = std.process.Child.init(argv.items, allocator);
     child.stdin_behavior = .Pipe;
     child.stdout_behavior = .Pipe;
     child.stderr_behavior = .Pipe;
@@ -25,7 +45,7 @@ fn runZq(allocator: std.mem.Allocator, expr: []const u8, input: []const u8) ![]u
     var stdout_data: std.ArrayListUnmanaged(u8) = .empty;
     errdefer stdout_data.deinit(allocator);
     var stderr_data: std.ArrayListUnmanaged(u8) = .empty;
-    defer stderr_data.deinit(allocator);
+    errdefer stderr_data.deinit(allocator);
 
     // Read in chunks until EOF
     var read_buf: [4096]u8 = undefined;
@@ -46,20 +66,19 @@ fn runZq(allocator: std.mem.Allocator, expr: []const u8, input: []const u8) ![]u
     }
 
     const term = try child.wait();
-    switch (term) {
-        .Exited => |code| {
-            if (code != 0) {
-                std.debug.print("ZQ failed with exit code {d}: {s}\n", .{ code, stderr_data.items });
-                return error.ProcessFailed;
-            }
-        },
+    const code: u8 = switch (term) {
+        .Exited => |status| status,
         else => {
             std.debug.print("ZQ terminated abnormally: {s}\n", .{stderr_data.items});
             return error.ProcessFailed;
         },
-    }
+    };
 
-    return stdout_data.toOwnedSlice(allocator);
+    return .{
+        .stdout = try stdout_data.toOwnedSlice(allocator),
+        .stderr = try stderr_data.toOwnedSlice(allocator),
+        .code = code,
+    };
 }
 
 test "integration: identity" {
@@ -408,6 +427,436 @@ test "integration: array construction" {
     try std.testing.expectEqualStrings("[1,2]\n", output);
 }
 
+test "integration: variable binding" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const output = runZq(arena.allocator(), ".price as $p | .items[] | {name, total: (.qty * $p)}", "{\"price\":2,\"items\":[{\"name\":\"a\",\"qty\":3}]}\n") catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings("{\"name\":\"a\",\"total\":6}\n", output);
+}
+
+test "integration: destructuring binding" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const output = runZq(arena.allocator(), ". as {a: $x, b: $y} | $x + $y", "{\"a\":1,\"b\":2}\n") catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings("3\n", output);
+}
+
+test "integration: reduce" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const output = runZq(arena.allocator(), "reduce .items[] as $i (0; . + $i.qty)", "{\"items\":[{\"qty\":2},{\"qty\":5}]}\n") catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings("7\n", output);
+}
+
+test "integration: foreach running total" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const output = runZq(arena.allocator(), "foreach .[] as $x (0; . + $x)", "[1,2,3]\n") catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings("1\n3\n6\n", output);
+}
+
+test "integration: def with recursion and filter argument" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const expr = "def countdown(f): if . <= 0 then f else (. - 1 | countdown(f)) end; .n | countdown(\"done\")";
+    const output = runZq(arena.allocator(), expr, "{\"n\":3}\n") catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings("\"done\"\n", output);
+}
+
+test "integration: regex select and gsub" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const expr = "select(.msg | test(\"ERR-[0-9]{4}\")) | .msg | gsub(\"[0-9]\"; \"#\")";
+    const output = runZq(arena.allocator(), expr, "{\"msg\":\"ok\"}\n{\"msg\":\"failed ERR-1234\"}\n") catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings("\"failed ERR-####\"\n", output);
+}
+
+test "integration: string interpolation and @csv" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const expr = "\"\\(.user) did \\(.action)\" as $msg | [$msg, .n] | @csv";
+    const output = runZq(arena.allocator(), expr, "{\"user\":\"ann\",\"action\":\"login\",\"n\":2}\n") catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings("\"\\\"ann did login\\\",2\"\n", output);
+}
+
+test "integration: update assignment on nested array" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const expr = ".items[].qty += 1 | .total = (.items | map(.qty) | add)";
+    const output = runZq(arena.allocator(), expr, "{\"items\":[{\"qty\":1},{\"qty\":2}]}\n") catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings("{\"items\":[{\"qty\":2},{\"qty\":3}],\"total\":5}\n", output);
+}
+
+test "integration: try catch and uncaught errors" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    const input =
+        \\{"x":"1"}
+        \\{"x":"oops"}
+        \\{"x":"3"}
+        \\
+    ;
+
+    const caught = runZq(arena.allocator(), "try .x | tonumber catch \"bad\"", input) catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings("1\n\"bad\"\n3\n", caught);
+
+    // Without try the bad record is reported on stderr and skipped
+    const skipped = try runZq(arena.allocator(), ".x | tonumber", input);
+    try std.testing.expectEqualStrings("1\n3\n", skipped);
+}
+
+test "integration: inputs and generators" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    // The first record is ., inputs drains the rest of stdin
+    const output = runZq(arena.allocator(), "[., inputs] | add", "1\n2\n\n3\n") catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings("6\n", output);
+
+    const ids = try runZq(arena.allocator(), "[.. | .id? | select(. != null)]", "{\"id\":1,\"items\":[{\"id\":2},{\"tags\":{\"id\":3}}]}\n");
+    try std.testing.expectEqualStrings("[1,2,3]\n", ids);
+}
+
+test "integration: bucket timestamps by hour" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    const input = "{\"ts\":\"2015-03-05T23:51:47+01:00\"}\n{\"ts\":\"05/Mar/2015:22:10:00 +0000\"}\n";
+    const output = runZq(arena.allocator(), ".ts | (fromdate? // (strptime(\"%d/%b/%Y:%H:%M:%S %z\") | mktime)) | date_trunc(\"hour\") | todate", input) catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings("\"2015-03-05T22:00:00Z\"\n\"2015-03-05T22:00:00Z\"\n", output);
+}
+
+test "integration: select fast path matches the evaluator" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    // Spacing is normalized, escapes are decoded, and a non-object record
+    // falls back to full evaluation
+    const input =
+        \\{"status": "x", "n": 1}
+        \\{"status":"y","n":2}
+        \\{"status":"\u0078","n":3}
+        \\[1,2]
+        \\
+    ;
+    const output = runZq(arena.allocator(), "select(.status == \"x\") | .n", input) catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings("1\n3\n", output);
+
+    const records = try runZq(arena.allocator(), "select(.n > 1 and .n < 4)", input);
+    try std.testing.expectEqualStrings("{\"status\":\"y\",\"n\":2}\n{\"status\":\"x\",\"n\":3}\n", records);
+}
+
+test "integration: jq command line options" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const input =
+        \\{"name":"alice","id":1}
+        \\{"name":"bob","id":2}
+        \\
+    ;
+
+    const named = runZqArgs(alloc, &.{ "--arg", "who", "bob", "--argjson", "n", "{\"x\":1}", "select(.name == $who) | [.id, $n.x]" }, input) catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings("[2,1]\n", named.stdout);
+
+    const positional = try runZqArgs(alloc, &.{ "-n", "-c", "$ARGS", "--args", "a", "b" }, "");
+    try std.testing.expectEqualStrings("{\"positional\":[\"a\",\"b\"],\"named\":{}}\n", positional.stdout);
+
+    // -n runs once; inputs reads the records
+    const total = try runZqArgs(alloc, &.{ "-n", "[inputs | .id] | add" }, input);
+    try std.testing.expectEqualStrings("3\n", total.stdout);
+
+    const raw = try runZqArgs(alloc, &.{ "-R", "length" }, "abc\n\nde\n");
+    try std.testing.expectEqualStrings("3\n0\n2\n", raw.stdout);
+    const raw_slurp = try runZqArgs(alloc, &.{ "-Rs", "." }, "a\nb\n");
+    try std.testing.expectEqualStrings("\"a\\nb\\n\"\n", raw_slurp.stdout);
+
+    const pretty = try runZqArgs(alloc, &.{ "--indent", "1", "-S", "." }, "{\"b\":[1],\"a\":{}}\n");
+    try std.testing.expectEqualStrings("{\n \"a\": {},\n \"b\": [\n  1\n ]\n}\n", pretty.stdout);
+    const tabbed = try runZqArgs(alloc, &.{ "--tab", "." }, "[1]\n");
+    try std.testing.expectEqualStrings("[\n\t1\n]\n", tabbed.stdout);
+
+    const joined = try runZqArgs(alloc, &.{ "-j", ".name" }, input);
+    try std.testing.expectEqualStrings("alicebob", joined.stdout);
+    const seq = try runZqArgs(alloc, &.{ "--seq", ".id" }, "\x1e{\"id\":1}\n");
+    try std.testing.expectEqualStrings("\x1e1\n", seq.stdout);
+}
+
+test "integration: jq exit codes" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const truthy = runZqArgs(alloc, &.{ "-e", ".ok" }, "{\"ok\":true}\n") catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqual(@as(u8, 0), truthy.code);
+    try std.testing.expectEqual(@as(u8, 1), (try runZqArgs(alloc, &.{ "-e", ".ok" }, "{\"ok\":null}\n")).code);
+    try std.testing.expectEqual(@as(u8, 4), (try runZqArgs(alloc, &.{ "-e", "empty" }, "{}\n")).code);
+    try std.testing.expectEqual(@as(u8, 2), (try runZqArgs(alloc, &.{ "--bogus", "." }, "")).code);
+    try std.testing.expectEqual(@as(u8, 2), (try runZqArgs(alloc, &.{ "--slurpfile", "x", "/nonexistent/file", "." }, "")).code);
+    try std.testing.expectEqual(@as(u8, 3), (try runZqArgs(alloc, &.{"$undefined"}, "")).code);
+    try std.testing.expectEqual(@as(u8, 5), (try runZqArgs(alloc, &.{ "-n", "error(\"boom\")" }, "")).code);
+}
+
+test "integration: stream mode" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    // One document spread over several lines, then a second one
+    const input = "{\"results\": [\n  {\"id\": 1},\n  {\"id\": 2}\n], \"n\": 2}\n[]\n";
+
+    const events = runZqArgs(alloc, &.{ "--stream", "-c", "." }, input) catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings(
+        \\[["results",0,"id"],1]
+        \\[["results",0,"id"]]
+        \\[["results",1,"id"],2]
+        \\[["results",1,"id"]]
+        \\[["results",1]]
+        \\[["n"],2]
+        \\[["n"]]
+        \\[[],[]]
+        \\
+    , events.stdout);
+
+    const results = try runZqArgs(alloc, &.{ "-n", "--stream", "fromstream(2|truncate_stream(inputs | select(.[0][0] == \"results\"))) | .id" }, input);
+    try std.testing.expectEqualStrings("1\n2\n", results.stdout);
+
+    // Without -n the same functions work on in-memory values
+    const round_trip = try runZqArgs(alloc, &.{ "-c", "fromstream(tostream)" }, "{\"a\":[1,{\"b\":null}]}\n");
+    try std.testing.expectEqualStrings("{\"a\":[1,{\"b\":null}]}\n", round_trip.stdout);
+
+    const malformed = try runZqArgs(alloc, &.{ "--stream", "." }, "[1,2\n");
+    try std.testing.expectEqual(@as(u8, 2), malformed.code);
+    try std.testing.expectEqualStrings("[[0],1]\n[[1],2]\n", malformed.stdout);
+}
+
+test "integration: lossless numbers" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const input = "{\"id\":1234567890123456789012,\"amount\":1234.50,\"ratio\":0.1000000000000000055511151231257827}\n";
+
+    // Literals an f64 would change pass through byte for byte
+    const identity = runZqArgs(alloc, &.{ "-c", "." }, input) catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings(input, identity.stdout);
+
+    try std.testing.expectEqualStrings("1234567890123456789013\n", try runZq(alloc, ".id + 1", input));
+    try std.testing.expectEqualStrings("1234.50\n", try runZq(alloc, "select(.id == 1234567890123456789012) | .amount", input));
+    try std.testing.expectEqualStrings("1234.75\n", try runZq(alloc, ".amount + 0.25", input));
+
+    // tostring and tonumber round-trip the literal
+    try std.testing.expectEqualStrings("\"1234.50\"\n", try runZq(alloc, ".amount | tostring", input));
+    try std.testing.expectEqualStrings("0.1000000000000000055511151231257827\n", try runZq(alloc, ".ratio | tostring | tonumber", input));
+
+    // Decimals are exact; plain floats keep jq's f64 results
+    const decimal = try runZqArgs(alloc, &.{ "-n", "(0.1 | todecimal) + (0.2 | todecimal)" }, "");
+    try std.testing.expectEqualStrings("0.3\n", decimal.stdout);
+    const float = try runZqArgs(alloc, &.{ "-n", "0.1 + 0.2" }, "");
+    try std.testing.expectEqualStrings("0.30000000000000004\n", float.stdout);
+    const overflow = try runZqArgs(alloc, &.{ "-n", "9223372036854775807 * 10" }, "");
+    try std.testing.expectEqualStrings("92233720368547758070\n", overflow.stdout);
+}
+
+test "integration: jq builtin library" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const in_filter = runZqArgs(alloc, &.{ "-c", ".[] | IN(2, 3)" }, "[1,2,3]") catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings("false\ntrue\ntrue\n", in_filter.stdout);
+    const indices = try runZqArgs(alloc, &.{ "-c", "indices(\", \")" }, "\"a,b, cd, efg, hijk\"");
+    try std.testing.expectEqualStrings("[3,7,12]\n", indices.stdout);
+
+    // halt_error prints a string message as-is and exits 5; halt_error(N) exits N
+    const halted = try runZqArgs(alloc, &.{ "-n", "\"bye\\n\" | halt_error" }, "");
+    try std.testing.expectEqual(@as(u8, 5), halted.code);
+    try std.testing.expectEqualStrings("bye\n", halted.stderr);
+    const halted_n = try runZqArgs(alloc, &.{ "-n", "{a: 1} | halt_error(1)" }, "");
+    try std.testing.expectEqual(@as(u8, 1), halted_n.code);
+    try std.testing.expectEqualStrings("{\"a\":1}\n", halted_n.stderr);
+
+    // debug writes to stderr and passes its input through
+    const debugged = try runZqArgs(alloc, &.{ "-c", "debug" }, "[1]");
+    try std.testing.expectEqualStrings("[1]\n", debugged.stdout);
+    try std.testing.expect(std.mem.indexOf(u8, debugged.stderr, "[\"DEBUG:\",[1]]") != null);
+
+    // The compatibility matrix comes from the same registry as the parser
+    const matrix = try runZqArgs(alloc, &.{"--list-builtins"}, "");
+    try std.testing.expectEqual(@as(u8, 0), matrix.code);
+    try std.testing.expect(std.mem.indexOf(u8, matrix.stdout, "\nINDEX/2 ") != null);
+
+    // Builtins zq can't offer are compile errors that name the builtin
+    const missing = try runZqArgs(alloc, &.{ "-n", "input_line_number" }, "");
+    try std.testing.expectEqual(@as(u8, 3), missing.code);
+    try std.testing.expect(std.mem.indexOf(u8, missing.stderr, "input_line_number") != null);
+}
+
+test "integration: colored output" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    // A pipe gets plain NDJSON unless -C asks for colors
+    const plain = runZqArgs(alloc, &.{"."}, "{\"a\":[1,true]}\n") catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqualStrings("{\"a\":[1,true]}\n", plain.stdout);
+
+    const colored = try runZqArgs(alloc, &.{ "-C", "." }, "{\"a\":[1,true]}\n");
+    try std.testing.expectEqualStrings(
+        "\x1b[1;39m{\x1b[0m\x1b[34;1m\"a\"\x1b[0m\x1b[1;39m:\x1b[0m\x1b[1;39m[\x1b[0m\x1b[0;39m1\x1b[0m" ++
+            "\x1b[1;39m,\x1b[0m\x1b[0;39mtrue\x1b[0m\x1b[1;39m]\x1b[0m\x1b[1;39m}\x1b[0m\n",
+        colored.stdout,
+    );
+    const pretty = try runZqArgs(alloc, &.{ "-C", "--indent", "1", "." }, "[null]\n");
+    try std.testing.expectEqualStrings("\x1b[1;39m[\x1b[0m\n \x1b[0;90mnull\x1b[0m\n\x1b[1;39m]\x1b[0m\n", pretty.stdout);
+
+    // -M wins over an earlier -C; raw strings are never colored
+    const mono = try runZqArgs(alloc, &.{ "-C", "-M", "." }, "[1]\n");
+    try std.testing.expectEqualStrings("[1]\n", mono.stdout);
+    const raw = try runZqArgs(alloc, &.{ "-C", "-r", ".a" }, "{\"a\":\"x\"}\n");
+    try std.testing.expectEqualStrings("x\n", raw.stdout);
+
+    const unbuffered = try runZqArgs(alloc, &.{ "--unbuffered", ".id" }, "{\"id\":1}\n{\"id\":2}\n");
+    try std.testing.expectEqualStrings("1\n2\n", unbuffered.stdout);
+}
+
+test "integration: --jobs keeps record order" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    // A few chunks of input with little output (so the pipes never fill),
+    // and an error near the end
+    const pad = "x" ** 700;
+    var input: std.ArrayListUnmanaged(u8) = .empty;
+    var expected: std.ArrayListUnmanaged(u8) = .empty;
+    const records = 3000;
+    for (0..records) |i| {
+        if (i == records - 10) {
+            try input.appendSlice(alloc, "{\"id\":\"oops\",\"pad\":\"" ++ pad ++ "\"}\n");
+            continue;
+        }
+        try input.print(alloc, "{{\"id\":{d},\"pad\":\"{s}\"}}\n", .{ i, pad });
+        try expected.print(alloc, "{d}\n", .{i + 1});
+    }
+
+    const parallel = runZqArgs(alloc, &.{ "--jobs", "4", ".id + 1" }, input.items) catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqual(@as(u8, 0), parallel.code);
+    try std.testing.expectEqualStrings(expected.items, parallel.stdout);
+    // Line numbers count from the start of the input, not of the chunk
+    try std.testing.expect(std.mem.indexOf(u8, parallel.stderr, "(at <stdin>:2991)") != null);
+
+    const strict = try runZqArgs(alloc, &.{ "--jobs", "4", "--strict", ".id + 1" }, input.items);
+    try std.testing.expectEqual(@as(u8, 5), strict.code);
+    try std.testing.expect(std.mem.endsWith(u8, strict.stdout, "\n2990\n"));
+
+    const last = try runZqArgs(alloc, &.{ "--jobs", "4", "-e", ".id == 0" }, input.items);
+    try std.testing.expectEqual(@as(u8, 1), last.code);
+
+    // seq numbers records in order, so it runs on one thread
+    const numbered = try runZqArgs(alloc, &.{ "--jobs", "4", "-c", "[seq, .id]" }, "{\"id\":\"a\"}\n{\"id\":\"b\"}\n");
+    try std.testing.expectEqualStrings("[1,\"a\"]\n[2,\"b\"]\n", numbered.stdout);
+}
+
+test "integration: --errors sidecar" {
+    var arena = std.heap.ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    const dir = try tmp.dir.realpathAlloc(alloc, ".");
+    const path = try std.fs.path.join(alloc, &.{ dir, "errors.jsonl" });
+
+    const input = "{\"a\":1}\nnot json\n{\"a\":\"x\"}\n3\n";
+    const routed = runZqArgs(alloc, &.{ "--errors", path, ".a + 1" }, input) catch |err| {
+        std.debug.print("Test skipped: zq not built ({any})\n", .{err});
+        return;
+    };
+    try std.testing.expectEqual(@as(u8, 0), routed.code);
+    try std.testing.expectEqualStrings("2\n", routed.stdout);
+    try std.testing.expectEqualStrings("", routed.stderr);
+
+    const records = try tmp.dir.readFileAlloc(alloc, "errors.jsonl", 1 << 20);
+    try std.testing.expectEqual(@as(usize, 3), std.mem.count(u8, records, "\n"));
+    try std.testing.expect(std.mem.startsWith(u8, records, "{\"_error\":\"malformed JSON\",\"_line\":2,\"_raw\":\"not json\",\"_source\":\"<stdin>\",\"_stage\":\"zq\"}\n"));
+    try std.testing.expect(std.mem.indexOf(u8, records, "\"_line\":3,\"_raw\":\"{\\\"a\\\":\\\"x\\\"}\"") != null);
+    try std.testing.expect(std.mem.indexOf(u8, records, "\"_line\":4,\"_raw\":\"3\"") != null);
+
+    // Appends, and threads route the same records in the same order
+    const parallel = try runZqArgs(alloc, &.{ "--jobs", "2", "--errors", path, ".a + 1" }, input);
+    try std.testing.expectEqualStrings("2\n", parallel.stdout);
+    const both = try tmp.dir.readFileAlloc(alloc, "errors.jsonl", 1 << 20);
+    try std.testing.expectEqualStrings(records, both[records.len..]);
+
+    // The budget counts failed records with or without a file
+    const over = try runZqArgs(alloc, &.{ "--max-errors", "1", ".a + 1" }, input);
+    try std.testing.expectEqual(@as(u8, 1), over.code);
+    try std.testing.expectEqualStrings("2\n", over.stdout);
+    try std.testing.expect(std.mem.indexOf(u8, over.stderr, "too many errors (2, --max-errors=1)") != null);
+    const within = try runZqArgs(alloc, &.{ "--max-errors", "3", ".a + 1" }, input);
+    try std.testing.expectEqual(@as(u8, 0), within.code);
+}
+
 // Edge case tests for integer overflow handling
 // These tests verify that overflow cases don't crash and produce reasonable output
 test "integration: incr at maxInt handles overflow" {